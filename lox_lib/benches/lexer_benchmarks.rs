@@ -6,7 +6,7 @@ pub fn criterion_benchmark(c: &mut Criterion) {
     let src_code = generate_src_code();
 
     c.bench_function("lex simple main", |b| {
-        b.iter(|| lexer.lex(black_box(&src_code)))
+        b.iter(|| lexer.lex(black_box(src_code)))
     });
 }
 
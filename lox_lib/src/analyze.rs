@@ -0,0 +1,1094 @@
+//!
+//! This module contains analyses of scripts which report on their source rather than run them, e.g.
+//! to help shrink scripts for the WASM playground or to review them before running them.
+//!
+//! String literals are interned into a [`StringTable`] as they are read, so that each distinct string
+//! is stored once along with how often it appears. The [`StringReport`] of a file is built from that
+//! table.
+//!
+//! The [`AuditReport`] of a script lists the natives with access to the outside world which the
+//! script can reach from its top-level code, following the functions it declares and the modules it
+//! imports. Functions are followed by name whenever they are referenced, not only when they are
+//! called directly, since a function stored in a variable can still be called later.
+//!
+//! The [`CallGraph`] of a script is built from the same references, and exported as DOT or JSON to
+//! get an overview of larger scripts and to find the functions which are never used.
+
+use crate::ast::{
+    ClassDecl, ExprNode, ExprVisitor, FunctionDecl, Literal, Operator, StmtNode, StmtVisitor,
+};
+use crate::emit::json_string;
+use crate::lexer::{Token, TokenType};
+use anyhow::{anyhow, Result};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::rc::Rc;
+use std::str::FromStr;
+
+/// how many of the longest strings a report lists
+const LONGEST_STRINGS: usize = 5;
+/// the number of characters after which strings are cut short when printing a report
+const PREVIEW_CHARS: usize = 40;
+
+/// an interning table which stores each distinct string once and counts how often it was interned.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct StringTable {
+    ids: HashMap<String, usize>,
+    strings: Vec<String>,
+    counts: Vec<usize>,
+}
+
+impl StringTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// interns a string, returning its id. Interning the same string again returns the same id.
+    pub fn intern(&mut self, value: &str) -> usize {
+        let id = match self.ids.get(value) {
+            Some(id) => *id,
+            None => {
+                let id = self.strings.len();
+                self.ids.insert(value.to_string(), id);
+                self.strings.push(value.to_string());
+                self.counts.push(0);
+                id
+            }
+        };
+        self.counts[id] += 1;
+        id
+    }
+
+    /// the string with the given id, if it was interned
+    pub fn get(&self, id: usize) -> Option<&str> {
+        self.strings.get(id).map(String::as_str)
+    }
+
+    /// how often the string with the given id was interned
+    pub fn count(&self, id: usize) -> usize {
+        self.counts.get(id).copied().unwrap_or_default()
+    }
+
+    /// the number of distinct strings
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    /// the distinct strings in the order they were first interned, with how often each was interned
+    pub fn iter(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.strings
+            .iter()
+            .map(String::as_str)
+            .zip(self.counts.iter().copied())
+    }
+}
+
+/// the string literals of a single file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StringReport {
+    pub name: String,
+    pub table: StringTable,
+}
+
+impl StringReport {
+    /// interns the string literals among the tokens of a file
+    pub fn new(name: impl Into<String>, tokens: &[Token]) -> Self {
+        let mut table = StringTable::new();
+        for token in tokens {
+            if let TokenType::String(value) = &token.token_type {
+                table.intern(value);
+            }
+        }
+        Self {
+            name: name.into(),
+            table,
+        }
+    }
+
+    /// the number of string literals, counting duplicates
+    pub fn literals(&self) -> usize {
+        self.table.iter().map(|(_, count)| count).sum()
+    }
+
+    /// the bytes taken up by all of the string literals, counting duplicates
+    pub fn total_bytes(&self) -> usize {
+        self.table
+            .iter()
+            .map(|(value, count)| value.len() * count)
+            .sum()
+    }
+
+    /// the strings which appear more than once with how often they do, the ones repeating the most
+    /// bytes first
+    pub fn duplicates(&self) -> Vec<(&str, usize)> {
+        let mut duplicates: Vec<_> = self.table.iter().filter(|(_, count)| *count > 1).collect();
+        duplicates.sort_by_key(|(value, count)| std::cmp::Reverse(value.len() * (count - 1)));
+        duplicates
+    }
+
+    /// the bytes which are spent on repeating strings that already appeared
+    pub fn duplicate_bytes(&self) -> usize {
+        self.duplicates()
+            .iter()
+            .map(|(value, count)| value.len() * (count - 1))
+            .sum()
+    }
+
+    /// the longest distinct strings, longest first
+    pub fn longest(&self) -> Vec<&str> {
+        let mut strings: Vec<_> = self.table.iter().map(|(value, _)| value).collect();
+        strings.sort_by_key(|value| std::cmp::Reverse(value.len()));
+        strings.truncate(LONGEST_STRINGS);
+        strings
+    }
+}
+
+/// prints a string of the report, cut short when it is long
+fn preview(value: &str) -> String {
+    match value.char_indices().nth(PREVIEW_CHARS) {
+        Some((end, _)) => format!("\"{}...\"", &value[..end]),
+        None => format!("\"{}\"", value),
+    }
+}
+
+impl fmt::Display for StringReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{}: {} string literals, {} unique, {} bytes",
+            self.name,
+            self.literals(),
+            self.table.len(),
+            self.total_bytes()
+        )?;
+        let duplicates = self.duplicates();
+        if !duplicates.is_empty() {
+            writeln!(
+                f,
+                "  duplicates ({} bytes repeated):",
+                self.duplicate_bytes()
+            )?;
+            for (value, count) in duplicates {
+                writeln!(f, "    {}x {}", count, preview(value))?;
+            }
+        }
+        let longest = self.longest();
+        if !longest.is_empty() {
+            writeln!(f, "  longest:")?;
+            for value in longest {
+                writeln!(f, "    {} bytes {}", value.len(), preview(value))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// an access to the outside world which an embedder has to grant before natives using it work.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Capability {
+    FileIo,
+    System,
+    Environment,
+    Network,
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Capability::FileIo => "file I/O",
+            Capability::System => "system",
+            Capability::Environment => "environment",
+            Capability::Network => "network",
+        };
+        f.pad(name)
+    }
+}
+
+/// parses the name of a capability as given to `--allow`: file-io, system, environment or network
+impl FromStr for Capability {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name {
+            "file-io" => Ok(Capability::FileIo),
+            "system" => Ok(Capability::System),
+            "environment" => Ok(Capability::Environment),
+            "network" => Ok(Capability::Network),
+            name => Err(anyhow!(
+                "unknown capability {}, expected file-io, system, environment or network",
+                name
+            )),
+        }
+    }
+}
+
+/// a reference to a native needing a capability, in code reachable from the top level of a script.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub capability: Capability,
+    pub native: String,
+    pub line: u32,
+    /// the resolved path of the imported module the reference is in, or none for the script itself
+    pub file: Option<String>,
+    /// the function the reference is in, or none for top-level code
+    pub function: Option<String>,
+}
+
+/// the capabilities which a script requires.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditReport {
+    pub name: String,
+    pub findings: Vec<Finding>,
+}
+
+impl AuditReport {
+    /// audits a parsed script for the given natives needing a capability, along with the modules it
+    /// imports, which `import` resolves and parses given the path of the importing module, or none
+    /// for the script itself, and the path it imports. Every module is audited once however often it
+    /// is imported, so import cycles end.
+    /// Scripts with syntax errors can not be audited, since the code which did not parse could reach
+    /// any native.
+    pub fn new(
+        name: impl Into<String>,
+        statements: &[StmtNode],
+        natives: &HashMap<String, Capability>,
+        mut import: impl FnMut(Option<&str>, &str) -> Result<(String, Vec<StmtNode>)>,
+    ) -> Result<Self> {
+        let references = References::collect(statements, &mut import)
+            .map_err(|err| anyhow!("can not audit a script whose imports fail: {}", err))?;
+        if let Some(err) = references.errors.first() {
+            return Err(anyhow!(
+                "can not audit a script with syntax errors: {}",
+                err
+            ));
+        }
+
+        let mut findings = Vec::new();
+        references.reach(|caller, reference| {
+            if let Some(capability) = natives.get(&reference.name) {
+                findings.push(Finding {
+                    capability: *capability,
+                    native: reference.name.clone(),
+                    line: reference.line,
+                    file: reference.file.clone(),
+                    function: match caller {
+                        Caller::Function(name) => Some(name.clone()),
+                        Caller::File(_) => None,
+                    },
+                });
+            }
+        });
+        findings
+            .sort_by(|a, b| (a.capability, &a.file, a.line).cmp(&(b.capability, &b.file, b.line)));
+        findings.dedup();
+        Ok(Self {
+            name: name.into(),
+            findings,
+        })
+    }
+
+    /// the distinct capabilities the script requires
+    pub fn capabilities(&self) -> Vec<Capability> {
+        let mut capabilities: Vec<_> = self.findings.iter().map(|f| f.capability).collect();
+        capabilities.dedup();
+        capabilities
+    }
+}
+
+impl fmt::Display for AuditReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let capabilities = self.capabilities();
+        if capabilities.is_empty() {
+            return writeln!(f, "{} requires no capabilities", self.name);
+        }
+        let capabilities: Vec<_> = capabilities.iter().map(|c| c.to_string()).collect();
+        writeln!(f, "{} requires {}", self.name, capabilities.join(", "))?;
+        for finding in &self.findings {
+            write!(
+                f,
+                "  {}: {} in line {}",
+                finding.capability, finding.native, finding.line
+            )?;
+            if let Some(file) = &finding.file {
+                write!(f, " of {}", file)?;
+            }
+            match &finding.function {
+                Some(function) => writeln!(f, " (in {})", function)?,
+                None => writeln!(f)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The formats a [`CallGraph`] can be exported in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GraphFormat {
+    #[default]
+    Dot,
+    Json,
+}
+
+impl FromStr for GraphFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(format: &str) -> Result<Self> {
+        match format {
+            "dot" => Ok(GraphFormat::Dot),
+            "json" => Ok(GraphFormat::Json),
+            _ => Err(anyhow!(
+                "unknown graph format {}, expected dot or json",
+                format
+            )),
+        }
+    }
+}
+
+/// the static call graph of a script and the modules it imports, along with which modules import
+/// which. The top-level code of the script is named after the script and that of a module after its
+/// resolved path, while functions and classes go by their names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallGraph {
+    pub name: String,
+    /// the functions and classes declared anywhere in the script or its modules, sorted
+    pub functions: Vec<String>,
+    /// the functions and classes referenced by each function or top-level code, sorted
+    pub calls: Vec<(String, String)>,
+    /// the modules imported by the script and by each module, in the order they were imported
+    pub imports: Vec<(String, String)>,
+    /// the functions and classes which can not be reached from the top level of the script
+    pub unreachable: Vec<String>,
+}
+
+impl CallGraph {
+    /// builds the call graph of a parsed script, following its imports like [`AuditReport::new`].
+    pub fn new(
+        name: impl Into<String>,
+        statements: &[StmtNode],
+        import: impl FnMut(Option<&str>, &str) -> Result<(String, Vec<StmtNode>)>,
+    ) -> Result<Self> {
+        let references = References::collect(statements, import)
+            .map_err(|err| anyhow!("can not analyze a script whose imports fail: {}", err))?;
+        if let Some(err) = references.errors.first() {
+            return Err(anyhow!(
+                "can not analyze a script with syntax errors: {}",
+                err
+            ));
+        }
+
+        let name = name.into();
+        let node = |caller: &Caller| match caller {
+            Caller::File(None) => name.clone(),
+            Caller::File(Some(path)) => path.clone(),
+            Caller::Function(function) => function.clone(),
+        };
+        let mut calls: Vec<_> = references
+            .graph
+            .iter()
+            .flat_map(|(caller, callees)| {
+                callees
+                    .iter()
+                    .filter(|callee| references.functions.contains(&callee.name))
+                    .map(|callee| (node(caller), callee.name.clone()))
+            })
+            .collect();
+        // a function of a module imported with a name is called through its namespace
+        for (caller, members) in &references.members {
+            for member in members {
+                let module = references
+                    .namespaces
+                    .get(&member.file)
+                    .and_then(|namespaces| namespaces.get(&member.namespace));
+                let declared =
+                    module.and_then(|module| references.declared.get(&Some(module.clone())));
+                if declared.is_some_and(|declared| declared.contains(&member.name)) {
+                    calls.push((node(caller), member.name.clone()));
+                }
+            }
+        }
+        calls.sort();
+        calls.dedup();
+
+        let mut imports = Vec::new();
+        let mut queue = VecDeque::from([None]);
+        let mut visited = HashSet::new();
+        while let Some(file) = queue.pop_front() {
+            for module in references.dependencies.get(&file).into_iter().flatten() {
+                let import = (node(&Caller::File(file.clone())), module.clone());
+                if !imports.contains(&import) {
+                    imports.push(import);
+                }
+                if visited.insert(module.clone()) {
+                    queue.push_back(Some(module.clone()));
+                }
+            }
+        }
+
+        let mut functions: Vec<_> = references.functions.iter().cloned().collect();
+        functions.sort();
+        let reached = references.reach(|_, _| {});
+        let unreachable = functions
+            .iter()
+            .filter(|function| !reached.contains(&Caller::Function(function.to_string())))
+            .cloned()
+            .collect();
+        Ok(Self {
+            name,
+            functions,
+            calls,
+            imports,
+            unreachable,
+        })
+    }
+
+    pub fn render(&self, format: GraphFormat) -> String {
+        match format {
+            GraphFormat::Dot => self.to_dot(),
+            GraphFormat::Json => self.to_json(),
+        }
+    }
+
+    /// the graph in the DOT language of Graphviz. Imports are dashed and unreachable functions are
+    /// greyed out.
+    pub fn to_dot(&self) -> String {
+        let mut dot = format!("digraph {} {{\n", json_string(&self.name));
+        for function in &self.functions {
+            dot.push_str(&format!("  {}", json_string(function)));
+            if self.unreachable.contains(function) {
+                dot.push_str(" [color=gray, fontcolor=gray]");
+            }
+            dot.push_str(";\n");
+        }
+        for (caller, callee) in &self.calls {
+            dot.push_str(&format!(
+                "  {} -> {};\n",
+                json_string(caller),
+                json_string(callee)
+            ));
+        }
+        for (importer, module) in &self.imports {
+            dot.push_str(&format!(
+                "  {} -> {} [style=dashed];\n",
+                json_string(importer),
+                json_string(module)
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    pub fn to_json(&self) -> String {
+        let strings = |values: &[String]| {
+            values
+                .iter()
+                .map(|value| json_string(value))
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        let edges = |edges: &[(String, String)]| {
+            edges
+                .iter()
+                .map(|(from, to)| format!("[{},{}]", json_string(from), json_string(to)))
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        format!(
+            "{{\"name\":{},\"functions\":[{}],\"calls\":[{}],\"imports\":[{}],\"unreachable\":[{}]}}\n",
+            json_string(&self.name),
+            strings(&self.functions),
+            edges(&self.calls),
+            edges(&self.imports),
+            strings(&self.unreachable)
+        )
+    }
+}
+
+/// a node of the call graph: the top-level code of the script or of a module it imports, or the
+/// functions of a name
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Caller {
+    File(Option<String>),
+    Function(String),
+}
+
+/// a name referenced in the file, or none for the script itself, at the line
+struct Reference {
+    name: String,
+    line: u32,
+    file: Option<String>,
+}
+
+/// an import of a module which is yet to be followed
+struct Import {
+    caller: Caller,
+    importer: Option<String>,
+    path: String,
+    /// the namespace the module is imported into, if it is imported with a name
+    name: Option<String>,
+}
+
+/// a member of a namespace referenced in a file, or none for the script itself
+struct Member {
+    file: Option<String>,
+    namespace: String,
+    name: String,
+}
+
+/// collects the names referenced by the top-level code of each file and by each function of a
+/// script, which are the edges of its call graph. Functions sharing a name share their node.
+#[derive(Default)]
+struct References {
+    graph: HashMap<Caller, Vec<Reference>>,
+    functions: HashSet<String>,
+    /// the modules each node imports, along with the functions it reaches through their namespaces
+    imported: HashMap<Caller, Vec<Caller>>,
+    /// the functions and classes declared at the top level of each file
+    declared: HashMap<Option<String>, Vec<String>>,
+    imports: Vec<Import>,
+    /// the modules imported by each file, by their resolved paths
+    dependencies: HashMap<Option<String>, Vec<String>>,
+    /// the modules imported with a name by each file, by their namespace
+    namespaces: HashMap<Option<String>, HashMap<String, String>>,
+    /// the members of variables referenced by each node, which are functions when the variable is
+    /// a namespace
+    members: HashMap<Caller, Vec<Member>>,
+    /// the file being visited, or none for the script itself
+    file: Option<String>,
+    /// the functions whose bodies are being visited, from the outermost to the innermost one
+    current: Vec<String>,
+    errors: Vec<String>,
+}
+
+impl References {
+    /// collects the references of a script and of the modules it imports, see [`AuditReport::new`]
+    /// for how `import` resolves them. Every module is visited once however often it is imported, so
+    /// import cycles end.
+    fn collect(
+        statements: &[StmtNode],
+        mut import: impl FnMut(Option<&str>, &str) -> Result<(String, Vec<StmtNode>)>,
+    ) -> Result<Self> {
+        let mut references = References::default();
+        references.visit_file(None, statements);
+        let mut modules = HashSet::new();
+        while let Some(imported) = references.imports.pop() {
+            let (path, statements) = import(imported.importer.as_deref(), &imported.path)?;
+            if modules.insert(path.clone()) {
+                references.visit_file(Some(&path), &statements);
+            }
+            let dependencies = references
+                .dependencies
+                .entry(imported.importer.clone())
+                .or_default();
+            if !dependencies.contains(&path) {
+                dependencies.push(path.clone());
+            }
+            // a module imported with a name reaches its functions through the namespace
+            let mut callees = vec![Caller::File(Some(path.clone()))];
+            if let Some(name) = imported.name {
+                let declared = references.declared.get(&Some(path.clone()));
+                callees.extend(
+                    declared
+                        .into_iter()
+                        .flatten()
+                        .cloned()
+                        .map(Caller::Function),
+                );
+                references
+                    .namespaces
+                    .entry(imported.importer)
+                    .or_default()
+                    .insert(name, path);
+            }
+            references
+                .imported
+                .entry(imported.caller)
+                .or_default()
+                .extend(callees);
+        }
+        Ok(references)
+    }
+
+    /// walks the call graph from the top level of the script, visiting the references which are
+    /// not to functions of every node it reaches. Returns the nodes which were reached.
+    fn reach(&self, mut visit: impl FnMut(&Caller, &Reference)) -> HashSet<Caller> {
+        let mut reached = HashSet::from([Caller::File(None)]);
+        let mut queue = VecDeque::from([Caller::File(None)]);
+        while let Some(caller) = queue.pop_front() {
+            let mut callees = self.imported.get(&caller).cloned().unwrap_or_default();
+            for reference in self.graph.get(&caller).into_iter().flatten() {
+                if self.functions.contains(&reference.name) {
+                    callees.push(Caller::Function(reference.name.clone()));
+                } else {
+                    visit(&caller, reference);
+                }
+            }
+            for callee in callees {
+                if reached.insert(callee.clone()) {
+                    queue.push_back(callee);
+                }
+            }
+        }
+        reached
+    }
+
+    fn visit_file(&mut self, file: Option<&str>, statements: &[StmtNode]) {
+        self.file = file.map(str::to_string);
+        for statement in statements {
+            self.visit_stmt(statement);
+        }
+    }
+
+    fn caller(&self) -> Caller {
+        match self.current.last() {
+            Some(function) => Caller::Function(function.clone()),
+            None => Caller::File(self.file.clone()),
+        }
+    }
+
+    fn reference(&mut self, name: &str, line: u32) {
+        let reference = Reference {
+            name: name.to_string(),
+            line,
+            file: self.file.clone(),
+        };
+        self.graph.entry(self.caller()).or_default().push(reference);
+    }
+
+    /// starts visiting the body of a function or class
+    fn declare(&mut self, name: &str) {
+        if self.current.is_empty() {
+            let file = self.file.clone();
+            self.declared
+                .entry(file)
+                .or_default()
+                .push(name.to_string());
+        }
+        self.functions.insert(name.to_string());
+        self.current.push(name.to_string());
+    }
+}
+
+impl StmtVisitor for References {
+    type Output = ();
+
+    fn visit_print_stmt(&mut self, node: &ExprNode) {
+        self.visit_expr_node(node)
+    }
+
+    fn visit_expr_stmt(&mut self, node: &ExprNode) {
+        self.visit_expr_node(node)
+    }
+
+    fn visit_err_stmt(&mut self, err: String) {
+        self.errors.push(err)
+    }
+
+    fn visit_var_stmt(&mut self, _name: &str, initializer: Option<&ExprNode>) {
+        if let Some(initializer) = initializer {
+            self.visit_expr_node(initializer)
+        }
+    }
+
+    fn visit_import_stmt(&mut self, path: &str, name: Option<&str>, _names: &[String], _line: u32) {
+        self.imports.push(Import {
+            caller: self.caller(),
+            importer: self.file.clone(),
+            path: path.to_string(),
+            name: name.map(str::to_string),
+        })
+    }
+
+    fn visit_export_stmt(&mut self, statement: &StmtNode, _line: u32) {
+        self.visit_stmt(statement)
+    }
+
+    fn visit_const_stmt(&mut self, _name: &str, initializer: &ExprNode) {
+        self.visit_expr_node(initializer)
+    }
+
+    fn visit_block_stmt(&mut self, statements: &[StmtNode]) {
+        for statement in statements {
+            self.visit_stmt(statement)
+        }
+    }
+
+    fn visit_if_stmt(
+        &mut self,
+        condition: &ExprNode,
+        then_branch: &StmtNode,
+        else_branch: Option<&StmtNode>,
+    ) {
+        self.visit_expr_node(condition);
+        self.visit_stmt(then_branch);
+        if let Some(else_branch) = else_branch {
+            self.visit_stmt(else_branch)
+        }
+    }
+
+    fn visit_while_stmt(
+        &mut self,
+        condition: &ExprNode,
+        body: &StmtNode,
+        increment: Option<&ExprNode>,
+    ) {
+        self.visit_expr_node(condition);
+        self.visit_stmt(body);
+        if let Some(increment) = increment {
+            self.visit_expr_node(increment)
+        }
+    }
+
+    fn visit_do_while_stmt(&mut self, body: &StmtNode, condition: &ExprNode) {
+        self.visit_stmt(body);
+        self.visit_expr_node(condition);
+    }
+
+    fn visit_break_stmt(&mut self, _line: u32) {}
+
+    fn visit_continue_stmt(&mut self, _line: u32) {}
+
+    fn visit_function_stmt(&mut self, declaration: &Rc<FunctionDecl>) {
+        self.declare(&declaration.name);
+        self.visit_block_stmt(&declaration.body);
+        self.current.pop();
+    }
+
+    /// the methods of a class are reachable once the class is, since they can be called on any of
+    /// its instances
+    fn visit_class_stmt(&mut self, declaration: &Rc<ClassDecl>) {
+        self.declare(&declaration.name);
+        // a class reaches the methods it inherits through its superclass
+        if let Some(superclass) = &declaration.superclass {
+            self.visit_expr_node(superclass);
+        }
+        for method in declaration.methods.iter().chain(&declaration.class_methods) {
+            self.visit_block_stmt(&method.body);
+        }
+        self.current.pop();
+    }
+
+    fn visit_return_stmt(&mut self, value: Option<&ExprNode>, _line: u32) {
+        if let Some(value) = value {
+            self.visit_expr_node(value)
+        }
+    }
+
+    fn visit_throw_stmt(&mut self, value: &ExprNode, _line: u32) {
+        self.visit_expr_node(value)
+    }
+
+    fn visit_try_stmt(&mut self, body: &[StmtNode], _name: &str, handler: &[StmtNode]) {
+        self.visit_block_stmt(body);
+        self.visit_block_stmt(handler);
+    }
+}
+
+impl ExprVisitor for References {
+    type Output = ();
+
+    fn visit_literal(&mut self, _literal: &Literal) {}
+
+    fn visit_grouping(&mut self, grouping: &ExprNode) {
+        self.visit_expr_node(grouping)
+    }
+
+    fn visit_binary_expr(&mut self, left: &ExprNode, _operator: &Operator, right: &ExprNode) {
+        self.visit_expr_node(left);
+        self.visit_expr_node(right);
+    }
+
+    fn visit_unary_expr(&mut self, _operator: &Operator, child: &ExprNode) {
+        self.visit_expr_node(child)
+    }
+
+    fn visit_logical_expr(&mut self, left: &ExprNode, _operator: &Operator, right: &ExprNode) {
+        self.visit_expr_node(left);
+        self.visit_expr_node(right);
+    }
+
+    fn visit_variable(&mut self, name: &str, line: u32) {
+        self.reference(name, line)
+    }
+
+    fn visit_assign(&mut self, _name: &str, value: &ExprNode, _line: u32) {
+        self.visit_expr_node(value)
+    }
+
+    fn visit_call(&mut self, callee: &ExprNode, arguments: &[ExprNode], _line: u32) {
+        self.visit_expr_node(callee);
+        for argument in arguments {
+            self.visit_expr_node(argument);
+        }
+    }
+
+    fn visit_get(&mut self, object: &ExprNode, name: &str, _line: u32) {
+        if let ExprNode::Variable {
+            name: namespace, ..
+        } = object
+        {
+            let member = Member {
+                file: self.file.clone(),
+                namespace: namespace.clone(),
+                name: name.to_string(),
+            };
+            self.members.entry(self.caller()).or_default().push(member);
+        }
+        self.visit_expr_node(object)
+    }
+
+    fn visit_set(&mut self, object: &ExprNode, _name: &str, value: &ExprNode, _line: u32) {
+        self.visit_expr_node(object);
+        self.visit_expr_node(value);
+    }
+
+    fn visit_list(&mut self, elements: &[ExprNode], _line: u32) {
+        for element in elements {
+            self.visit_expr_node(element);
+        }
+    }
+
+    fn visit_map(&mut self, entries: &[(ExprNode, ExprNode)], _line: u32) {
+        for (key, value) in entries {
+            self.visit_expr_node(key);
+            self.visit_expr_node(value);
+        }
+    }
+
+    fn visit_index(&mut self, object: &ExprNode, index: &ExprNode, _line: u32) {
+        self.visit_expr_node(object);
+        self.visit_expr_node(index);
+    }
+
+    fn visit_slice(
+        &mut self,
+        object: &ExprNode,
+        start: Option<&ExprNode>,
+        end: Option<&ExprNode>,
+        _line: u32,
+    ) {
+        self.visit_expr_node(object);
+        for bound in start.into_iter().chain(end) {
+            self.visit_expr_node(bound);
+        }
+    }
+
+    fn visit_set_index(
+        &mut self,
+        object: &ExprNode,
+        index: &ExprNode,
+        value: &ExprNode,
+        _line: u32,
+    ) {
+        self.visit_expr_node(object);
+        self.visit_expr_node(index);
+        self.visit_expr_node(value);
+    }
+
+    fn visit_this(&mut self, _line: u32) {}
+
+    fn visit_super(&mut self, _method: &str, _line: u32) {}
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    #[test]
+    /// tests that interning a string again returns the same id and counts it
+    fn string_table_test() {
+        let mut table = StringTable::new();
+        let hello = table.intern("hello");
+        let world = table.intern("world");
+        assert_eq!(table.intern("hello"), hello);
+        assert_ne!(hello, world);
+        assert_eq!(table.get(world), Some("world"));
+        assert_eq!(table.count(hello), 2);
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.get(2), None);
+    }
+
+    #[test]
+    /// tests the counts of a report and how it is printed
+    fn string_report_test() {
+        let tokens = Lexer::new()
+            .lex("print \"hi\";\nprint \"hello\";\nprint \"hi\" + \"hello\" + \"hello\";\nprint \"\";")
+            .unwrap();
+        let report = StringReport::new("script.lox", &tokens);
+        assert_eq!(report.literals(), 6);
+        assert_eq!(report.total_bytes(), 19);
+        assert_eq!(report.duplicates(), vec![("hello", 3), ("hi", 2)]);
+        assert_eq!(report.duplicate_bytes(), 12);
+        assert_eq!(
+            report.to_string(),
+            "script.lox: 6 string literals, 3 unique, 19 bytes\n  \
+            duplicates (12 bytes repeated):\n    3x \"hello\"\n    2x \"hi\"\n  \
+            longest:\n    5 bytes \"hello\"\n    2 bytes \"hi\"\n    0 bytes \"\"\n"
+        );
+        assert_eq!(
+            preview(&"a".repeat(41)),
+            format!("\"{}...\"", "a".repeat(40))
+        );
+    }
+
+    #[test]
+    /// tests that only the natives reachable from the top level are reported
+    fn audit_test() {
+        let source = "fun load(path) { return dbOpen(path); }\n\
+            fun unused() { httpPost(\"url\", \"\"); }\n\
+            fun outer() { fun inner() { return dbQuery(db, \"\", nil); } return inner; }\n\
+            var f = outer;\nprint f()();\nprint load(\"a\") + load(\"b\");\nhttpGet(\"url\");";
+        let tokens = Lexer::new().lex(source).unwrap();
+        let statements = Parser::new().parse(tokens);
+        let report = AuditReport::new("script.lox", &statements, &natives(), no_imports).unwrap();
+        assert_eq!(
+            report.capabilities(),
+            vec![Capability::FileIo, Capability::Network]
+        );
+        assert_eq!(
+            report.to_string(),
+            "script.lox requires file I/O, network\n  \
+            file I/O: dbOpen in line 1 (in load)\n  \
+            file I/O: dbQuery in line 3 (in inner)\n  \
+            network: httpGet in line 7\n"
+        );
+
+        // a function declared by the script is not the native of the same name
+        let tokens = Lexer::new()
+            .lex("fun httpGet(url) { print url; }\nhttpGet(\"url\");")
+            .unwrap();
+        let statements = Parser::new().parse(tokens);
+        let report = AuditReport::new("safe.lox", &statements, &natives(), no_imports).unwrap();
+        assert_eq!(report.to_string(), "safe.lox requires no capabilities\n");
+
+        // only the natives given are audited
+        let tokens = Lexer::new().lex("httpGet(\"url\");").unwrap();
+        let statements = Parser::new().parse(tokens);
+        let report = AuditReport::new("net.lox", &statements, &HashMap::new(), no_imports).unwrap();
+        assert_eq!(report.to_string(), "net.lox requires no capabilities\n");
+
+        let tokens = Lexer::new().lex("print );").unwrap();
+        let statements = Parser::new().parse(tokens);
+        assert!(AuditReport::new("broken.lox", &statements, &natives(), no_imports).is_err());
+    }
+
+    /// the natives of the HTTP and SQLite plugins
+    fn natives() -> HashMap<String, Capability> {
+        [
+            ("dbOpen", Capability::FileIo),
+            ("dbExec", Capability::FileIo),
+            ("dbQuery", Capability::FileIo),
+            ("httpGet", Capability::Network),
+            ("httpPost", Capability::Network),
+        ]
+        .into_iter()
+        .map(|(name, capability)| (name.to_string(), capability))
+        .collect()
+    }
+
+    fn no_imports(_: Option<&str>, path: &str) -> Result<(String, Vec<StmtNode>)> {
+        Err(anyhow!("no module {}", path))
+    }
+
+    #[test]
+    /// tests that the natives reachable through imported modules are reported, however the modules
+    /// are imported and even when they import each other
+    fn audit_imports_test() {
+        let modules = HashMap::from([
+            ("net.lox", "import \"db.lox\";\nfun fetch(url) { return httpGet(url); }\nfun post(url) { return httpPost(url, \"\"); }"),
+            ("db.lox", "import \"net.lox\";\nvar db = dbOpen(\"app.db\");"),
+            ("util.lox", "fun unused() { return httpPost(\"url\", \"\"); }"),
+        ]);
+        let import = |_: Option<&str>, path: &str| {
+            let source = modules
+                .get(path)
+                .ok_or_else(|| anyhow!("no module {}", path))?;
+            Ok((
+                path.to_string(),
+                Parser::new().parse(Lexer::new().lex(source)?),
+            ))
+        };
+        let tokens = Lexer::new()
+            .lex("import \"net.lox\" as net;\nimport \"util.lox\";\nprint net.fetch(\"url\");")
+            .unwrap();
+        let statements = Parser::new().parse(tokens);
+        let report = AuditReport::new("script.lox", &statements, &natives(), import).unwrap();
+        assert_eq!(
+            report.to_string(),
+            "script.lox requires file I/O, network\n  \
+            file I/O: dbOpen in line 2 of db.lox\n  \
+            network: httpGet in line 2 of net.lox (in fetch)\n  \
+            network: httpPost in line 3 of net.lox (in post)\n"
+        );
+
+        let tokens = Lexer::new().lex("import \"missing.lox\";").unwrap();
+        let statements = Parser::new().parse(tokens);
+        assert!(AuditReport::new("script.lox", &statements, &natives(), import).is_err());
+    }
+
+    #[test]
+    /// tests the calls, imports and unreachable functions of a call graph and how it is exported
+    fn call_graph_test() {
+        let modules = HashMap::from([
+            ("util.lox", "import \"script.lox\";\nfun twice(x) { return x * 2; }\nfun half(x) { return x / 2; }"),
+            ("script.lox", "import \"util.lox\" as util;\nfun main() { helper(); return util.twice(2); }\nfun helper() { print 1; }\nfun unused() { helper(); }\nprint main();"),
+        ]);
+        let import = |_: Option<&str>, path: &str| {
+            let source = modules
+                .get(path)
+                .ok_or_else(|| anyhow!("no module {}", path))?;
+            Ok((
+                path.to_string(),
+                Parser::new().parse(Lexer::new().lex(source)?),
+            ))
+        };
+        let tokens = Lexer::new().lex(modules["script.lox"]).unwrap();
+        let statements = Parser::new().parse(tokens);
+        let graph = CallGraph::new("script.lox", &statements, import).unwrap();
+        assert_eq!(
+            graph.functions,
+            ["half", "helper", "main", "twice", "unused"]
+        );
+        assert_eq!(
+            graph.calls,
+            [
+                ("main", "helper"),
+                ("main", "twice"),
+                ("script.lox", "main"),
+                ("unused", "helper")
+            ]
+            .map(|(from, to)| (from.to_string(), to.to_string()))
+        );
+        assert_eq!(
+            graph.imports,
+            [("script.lox", "util.lox"), ("util.lox", "script.lox")]
+                .map(|(from, to)| (from.to_string(), to.to_string()))
+        );
+        // the functions of a namespace are reachable through it even when they are not called
+        assert_eq!(graph.unreachable, ["unused"]);
+        assert_eq!(
+            graph.render(GraphFormat::Dot),
+            "digraph \"script.lox\" {\n  \"half\";\n  \"helper\";\n  \"main\";\n  \"twice\";\n  \
+            \"unused\" [color=gray, fontcolor=gray];\n  \"main\" -> \"helper\";\n  \
+            \"main\" -> \"twice\";\n  \"script.lox\" -> \"main\";\n  \"unused\" -> \"helper\";\n  \
+            \"script.lox\" -> \"util.lox\" [style=dashed];\n  \
+            \"util.lox\" -> \"script.lox\" [style=dashed];\n}\n"
+        );
+        assert_eq!(
+            graph.render(GraphFormat::Json),
+            "{\"name\":\"script.lox\",\"functions\":[\"half\",\"helper\",\"main\",\"twice\",\"unused\"],\
+            \"calls\":[[\"main\",\"helper\"],[\"main\",\"twice\"],[\"script.lox\",\"main\"],[\"unused\",\"helper\"]],\
+            \"imports\":[[\"script.lox\",\"util.lox\"],[\"util.lox\",\"script.lox\"]],\"unreachable\":[\"unused\"]}\n"
+        );
+        assert!("svg".parse::<GraphFormat>().is_err());
+
+        let tokens = Lexer::new().lex("print );").unwrap();
+        let statements = Parser::new().parse(tokens);
+        assert!(CallGraph::new("broken.lox", &statements, no_imports).is_err());
+    }
+}
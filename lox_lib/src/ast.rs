@@ -5,23 +5,189 @@
 /// The current Lox grammar is as follows:
 /// program         -> declarations* EOF ;
 ///
-/// declarations    -> varDecl | statement ;
+/// declarations    -> classDecl | funDecl | varDecl | constDecl | defineDecl | importDecl
+///                    | statement ;
 ///
-/// varDecl         -> "var" IDENTIFIER ("=" expression)? ;
+/// classDecl       -> "class" IDENTIFIER ( "<" IDENTIFIER )? "{" ( "class"? function )* "}" ;
 ///
-/// statement       -> expressionStmt | printStmt ;  
+/// funDecl         -> "fun" function ;
 ///
-/// expressionStmt 	-> expression ";" ;
+/// function        -> IDENTIFIER "(" parameters? ")" block ;
 ///
-/// printStmt  		-> "print" expression ";" ;
-use crate::lexer::{Token, TokenType};
+/// parameters      -> ( IDENTIFIER "," )* ( IDENTIFIER | "..." IDENTIFIER ) ;
+///
+/// varDecl         -> "var" IDENTIFIER ("=" expression)? ";" ;
+///
+/// constDecl       -> "const" IDENTIFIER "=" expression ";" ;
+///
+/// defineDecl      -> "define" IDENTIFIER constant ";" ;
+///
+/// importDecl      -> "import" ( STRING ( "as" IDENTIFIER )? | IDENTIFIER ) ";" ;
+///
+/// statement       -> expressionStmt | printStmt | ifStmt | whileStmt | doWhileStmt | forStmt
+///                    | breakStmt | continueStmt | returnStmt | throwStmt | tryStmt | block ;
+///
+/// expressionStmt  -> expression ";" ;
+///
+/// printStmt       -> "print" expression ";" ;
+///
+/// ifStmt          -> "if" "(" expression ")" statement ( "else" statement )? ;
+///
+/// whileStmt       -> "while" "(" expression ")" statement ;
+///
+/// doWhileStmt     -> "do" statement "while" "(" expression ")" ";" ;
+///
+/// forStmt         -> "for" "(" ( varDecl | expressionStmt | ";" ) expression? ";" expression? ")"
+///                    statement ;
+///
+/// breakStmt       -> "break" ";" ;
+///
+/// continueStmt    -> "continue" ";" ;
+///
+/// returnStmt      -> "return" expression? ";" ;
+///
+/// throwStmt       -> "throw" expression ";" ;
+///
+/// tryStmt         -> "try" block "catch" "(" IDENTIFIER ")" block ;
+///
+/// block           -> "{" declarations* "}" ;
+///
+/// Note that `print` is a statement and not a function. `print("a");` is a print statement of the
+/// grouping `("a")` and behaves the same as `print "a";`.
+///
+/// There is no node for a for statement, the parser desugars it into a while loop inside of a block.
+/// The increment of the for loop becomes the increment of the while loop, so that it still runs after
+/// a `continue`.
+use crate::datetime::DateTime;
+use crate::interpreter::{LoxCallable, LoxInstance};
+use crate::lexer::{Comment, Token, TokenType};
+use crate::map::LoxMap;
+use crate::natives::Handle;
+use crate::number::Decimal;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
 
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub enum StmtNode {
     PrintStmt(ExprNode),
     ExprStmt(ExprNode),
     ErrStmt(String),
-    VarStmt(String),
+    /// declares a variable, which is nil unless it has an initializer
+    VarStmt {
+        name: String,
+        initializer: Option<ExprNode>,
+    },
+    /// declares a constant, which can not be assigned to after it is declared
+    ConstStmt {
+        name: String,
+        initializer: ExprNode,
+    },
+    /// runs the script at the path unless it was imported before, and then declares its globals in
+    /// the current scope, or a namespace holding them if it has a name. `import foo;` imports
+    /// `foo.lox` as `foo`. A module which exports any of its globals only gives the exported ones.
+    ImportStmt {
+        path: String,
+        name: Option<String>,
+        /// the globals to declare, which are all of them when it is empty
+        names: Vec<String>,
+        line: u32,
+    },
+    /// runs a declaration at the top level of a module and exports the names it declares, so that
+    /// they are the ones the module gives to the scripts importing it
+    ExportStmt {
+        statement: Box<StmtNode>,
+        line: u32,
+    },
+    /// runs its statements in a new scope
+    BlockStmt(Vec<StmtNode>),
+    /// runs the then branch when the condition is truthy and otherwise the else branch, if any
+    IfStmt {
+        condition: ExprNode,
+        then_branch: Box<StmtNode>,
+        else_branch: Option<Box<StmtNode>>,
+    },
+    /// runs the body for as long as the condition is truthy, checking it before every iteration.
+    /// The increment is evaluated after every iteration, including ones cut short by a `continue`.
+    WhileStmt {
+        condition: ExprNode,
+        body: Box<StmtNode>,
+        increment: Option<ExprNode>,
+    },
+    /// runs the body and then again for as long as the condition is truthy, so the body always runs
+    /// at least once. A `continue` skips to checking the condition.
+    DoWhileStmt {
+        body: Box<StmtNode>,
+        condition: ExprNode,
+    },
+    /// stops the innermost loop it is in, which the parser makes sure exists
+    BreakStmt {
+        line: u32,
+    },
+    /// skips the rest of the body of the innermost loop it is in and moves on to its next iteration
+    ContinueStmt {
+        line: u32,
+    },
+    /// declares a function, binding it to its name
+    FunctionStmt(Rc<FunctionDecl>),
+    /// declares a class, binding it to its name
+    ClassStmt(Rc<ClassDecl>),
+    /// returns from the innermost function it is in, with nil if it has no value
+    ReturnStmt {
+        value: Option<ExprNode>,
+        line: u32,
+    },
+    /// throws the value, which unwinds to the innermost `try` around it
+    ThrowStmt {
+        value: ExprNode,
+        line: u32,
+    },
+    /// runs the body, and if it throws runs the handler with the thrown value bound to the name
+    TryStmt {
+        body: Vec<StmtNode>,
+        name: String,
+        handler: Vec<StmtNode>,
+    },
+    /// a statement with the comments written before it and on its last line, which the parser only
+    /// attaches when it is given the comments of the source. It runs exactly like the statement.
+    Commented {
+        leading: Vec<Comment>,
+        statement: Box<StmtNode>,
+        trailing: Vec<Comment>,
+    },
+}
+
+/// The declaration of a function. It is shared between the syntax tree and every function value
+/// created from it, so calling a function does not copy its body.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct FunctionDecl {
+    pub name: String,
+    pub params: Vec<String>,
+    /// the parameter after `...` which collects the arguments after the other parameters into a
+    /// list, if the function takes any number of them
+    pub rest: Option<String>,
+    /// the statements of the body, which run in the same scope as the parameters
+    pub body: Vec<StmtNode>,
+    pub line: u32,
+    /// the comments documenting the function, which the parser only attaches when it is given the
+    /// comments of the source
+    pub doc: Option<String>,
+}
+
+/// The declaration of a class. Calling the class creates an instance of it, which runs the `init`
+/// method with the arguments of the call if the class has one.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct ClassDecl {
+    pub name: String,
+    /// the variable holding the class this one inherits from, if any
+    pub superclass: Option<ExprNode>,
+    pub methods: Vec<Rc<FunctionDecl>>,
+    /// the methods marked with `class`, which are called on the class itself rather than on its
+    /// instances. They are an extension to canonical Lox.
+    pub class_methods: Vec<Rc<FunctionDecl>>,
+    pub line: u32,
+    /// the comments documenting the class, like [`FunctionDecl::doc`]
+    pub doc: Option<String>,
 }
 
 /// The operators supported by the Lox language.
@@ -32,7 +198,9 @@ pub enum Operator {
     Multiply { line: u32 },
     Divide { line: u32 },
     GreaterThan { line: u32 },
+    GreaterEqual { line: u32 },
     LessThan { line: u32 },
+    LessEqual { line: u32 },
     Equal { line: u32 },
     EqualEqual { line: u32 },
     NotEqual { line: u32 },
@@ -41,6 +209,48 @@ pub enum Operator {
     Bang { line: u32 },
 }
 
+impl Operator {
+    /// the line of the source code the operator was found on.
+    pub fn line(&self) -> u32 {
+        match self {
+            Operator::Add { line }
+            | Operator::Subtract { line }
+            | Operator::Multiply { line }
+            | Operator::Divide { line }
+            | Operator::GreaterThan { line }
+            | Operator::GreaterEqual { line }
+            | Operator::LessThan { line }
+            | Operator::LessEqual { line }
+            | Operator::Equal { line }
+            | Operator::EqualEqual { line }
+            | Operator::NotEqual { line }
+            | Operator::And { line }
+            | Operator::Or { line }
+            | Operator::Bang { line } => *line,
+        }
+    }
+
+    /// the operator as it is written in Lox source code.
+    pub fn lexeme(&self) -> &'static str {
+        match self {
+            Operator::Add { .. } => "+",
+            Operator::Subtract { .. } => "-",
+            Operator::Multiply { .. } => "*",
+            Operator::Divide { .. } => "/",
+            Operator::GreaterThan { .. } => ">",
+            Operator::GreaterEqual { .. } => ">=",
+            Operator::LessThan { .. } => "<",
+            Operator::LessEqual { .. } => "<=",
+            Operator::Equal { .. } => "=",
+            Operator::EqualEqual { .. } => "==",
+            Operator::NotEqual { .. } => "!=",
+            Operator::And { .. } => "and",
+            Operator::Or { .. } => "or",
+            Operator::Bang { .. } => "!",
+        }
+    }
+}
+
 impl TryFrom<&Token> for Operator {
     type Error = String;
 
@@ -59,43 +269,175 @@ impl TryFrom<&Token> for Operator {
             TokenType::Or => Ok(Operator::Or { line }),
             TokenType::Bang => Ok(Operator::Bang { line }),
             TokenType::EqualEqual => Ok(Operator::EqualEqual { line }),
-            TokenType::GreaterEqual => Ok(Operator::GreaterThan { line }),
-            TokenType::LessEqual => Ok(Operator::LessThan { line }),
+            TokenType::GreaterEqual => Ok(Operator::GreaterEqual { line }),
+            TokenType::LessEqual => Ok(Operator::LessEqual { line }),
             _ => Err(format!("{:?} is not an operator", token.token_type)),
         }
     }
 }
 
-///
+/// A literal value in a Lox program, which is also the result of evaluating an expression.
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub enum Literal {
-    Number(f32),
+    Number(f64),
     String(String),
     Boolean(bool),
     Nil,
+    /// a function declared in the program or a native one, which only exist at runtime
+    Callable(Rc<dyn LoxCallable>),
+    /// an instance of a class, which only exists at runtime
+    Instance(Rc<LoxInstance>),
+    /// an object owned by natives, which only exists at runtime
+    Handle(Handle),
+    /// a list, which is shared between every value holding it so that changes to its elements are
+    /// seen through all of them. It only exists at runtime, a list literal is an [`ExprNode::List`].
+    List(Rc<RefCell<Vec<Literal>>>),
+    /// a map, which is shared like a list. It only exists at runtime, a map literal is an
+    /// [`ExprNode::Map`].
+    Map(LoxMap),
+    /// binary data written like `b"GIF\x00"`, which can not be changed, see [`crate::bytes`]
+    Bytes(Rc<[u8]>),
+    /// an instant in UTC returned by `now()`, see [`crate::datetime`]
+    DateTime(DateTime),
+    /// a number which is the result of an operator in decimal mode, see [`crate::number`]
+    Decimal(Decimal),
+    /// a whole number of any size, written like `123n`, see [`crate::bigint`]
+    #[cfg(feature = "bigint")]
+    BigInt(num_bigint::BigInt),
 }
 
 impl Literal {
-    pub fn is_falsy(&self) -> bool {
+    /// whether the value counts as true in a condition. Like in Ruby, only `false` and `nil` are
+    /// falsy and every other value is truthy.
+    pub fn is_truthy(&self) -> bool {
         match &self {
             Literal::Number(_) => true,
             Literal::String(_) => true,
             Literal::Boolean(val) => *val,
             Literal::Nil => false,
+            Literal::Callable(_) => true,
+            Literal::Instance(_) => true,
+            Literal::Handle(_) => true,
+            Literal::List(_) => true,
+            Literal::Map(_) => true,
+            Literal::Bytes(_) => true,
+            Literal::DateTime(_) => true,
+            Literal::Decimal(_) => true,
+            #[cfg(feature = "bigint")]
+            Literal::BigInt(_) => true,
         }
     }
+
+    /// the number the value is, with a decimal as the closest double, or None if it is not a number.
+    /// Natives taking numbers read their arguments with it.
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Literal::Number(number) => Some(*number),
+            Literal::Decimal(decimal) => Some(decimal.to_f64()),
+            _ => None,
+        }
+    }
+
     pub fn is_equal(&self, other: &Literal) -> bool {
         match (self, other) {
             (Literal::Number(a), Literal::Number(b)) => *a == *b,
             (Literal::String(a), Literal::String(b)) => a == b,
             (Literal::Boolean(a), Literal::Boolean(b)) => a == b,
             (Literal::Nil, Literal::Nil) => true,
+            // callables are only equal to themselves
+            (Literal::Callable(a), Literal::Callable(b)) => a == b,
+            (Literal::Instance(a), Literal::Instance(b)) => Rc::ptr_eq(a, b),
+            (Literal::Handle(a), Literal::Handle(b)) => a == b,
+            // like instances, lists are only equal to themselves even if they hold the same elements
+            (Literal::List(a), Literal::List(b)) => Rc::ptr_eq(a, b),
+            // unlike lists, maps are equal when they hold equal values for the same keys
+            (Literal::Map(a), Literal::Map(b)) => a == b,
+            (Literal::Bytes(a), Literal::Bytes(b)) => a == b,
+            (Literal::DateTime(a), Literal::DateTime(b)) => a == b,
+            // a decimal equals the number which prints as the same decimal, so `0.1 + 0.2 == 0.3`
+            (Literal::Decimal(a), Literal::Decimal(b)) => a == b,
+            (Literal::Decimal(a), Literal::Number(b))
+            | (Literal::Number(b), Literal::Decimal(a)) => Decimal::from_f64(*b) == Some(*a),
+            // a big integer equals the number which is the same whole number, so `1n == 1`
+            #[cfg(feature = "bigint")]
+            (Literal::BigInt(a), Literal::BigInt(b)) => a == b,
+            #[cfg(feature = "bigint")]
+            (Literal::BigInt(a), Literal::Number(b)) | (Literal::Number(b), Literal::BigInt(a)) => {
+                crate::bigint::equals_number(a, *b)
+            }
             (Literal::Nil, _) => false,
             (_, Literal::Nil) => false,
             _ => false,
         }
     }
 }
+/// The canonical way values are printed, which matches the output of the reference Lox implementations.
+impl std::fmt::Display for Literal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Literal::Number(number) => write!(f, "{}", number),
+            Literal::String(string) => write!(f, "{}", string),
+            Literal::Boolean(boolean) => write!(f, "{}", boolean),
+            Literal::Nil => write!(f, "nil"),
+            Literal::Callable(callable) => write!(f, "{}", callable),
+            Literal::Instance(instance) => write!(f, "{} instance", instance.class.name),
+            Literal::Handle(handle) => write!(f, "{}", handle),
+            Literal::List(_) | Literal::Map(_) => write_nested(f, self, &mut Vec::new()),
+            Literal::Bytes(bytes) => crate::bytes::write_bytes(f, bytes),
+            Literal::DateTime(date) => write!(f, "{}", date),
+            Literal::Decimal(decimal) => write!(f, "{}", decimal),
+            #[cfg(feature = "bigint")]
+            Literal::BigInt(bigint) => write!(f, "{}", bigint),
+        }
+    }
+}
+
+/// writes a value nested in a list or map, where lists are written as `[1, "a", [true]]` and maps
+/// as `{"a": 1}` with their keys in order, quoting the strings in them. A list or map which holds
+/// itself is written as `[...]` or `{...}` where it is nested in itself.
+fn write_nested(
+    f: &mut std::fmt::Formatter<'_>,
+    value: &Literal,
+    outer: &mut Vec<*const ()>,
+) -> std::fmt::Result {
+    match value {
+        Literal::String(string) => write!(f, "\"{}\"", string),
+        Literal::List(list) => {
+            let ptr = Rc::as_ptr(list).cast();
+            if outer.contains(&ptr) {
+                return write!(f, "[...]");
+            }
+            outer.push(ptr);
+            write!(f, "[")?;
+            for (i, element) in list.borrow().iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write_nested(f, element, outer)?;
+            }
+            outer.pop();
+            write!(f, "]")
+        }
+        Literal::Map(map) => {
+            if outer.contains(&map.as_ptr()) {
+                return write!(f, "{{...}}");
+            }
+            outer.push(map.as_ptr());
+            write!(f, "{{")?;
+            for (i, (key, value)) in map.entries().iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}: ", key)?;
+                write_nested(f, value, outer)?;
+            }
+            outer.pop();
+            write!(f, "}}")
+        }
+        value => write!(f, "{}", value),
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
 struct BinaryExpr {
     left: Box<ExprNode>,
@@ -123,23 +465,195 @@ pub enum ExprNode {
         operator: Operator,
         right: Box<ExprNode>,
     },
+    /// an `and` or `or`, which only evaluates its right operand when the left one does not already
+    /// decide the result
+    Logical {
+        left: Box<ExprNode>,
+        operator: Operator,
+        right: Box<ExprNode>,
+    },
+    /// reads the value bound to a variable
+    Variable {
+        name: String,
+        line: u32,
+    },
+    /// assigns a new value to an existing variable, evaluating to that value
+    Assign {
+        name: String,
+        value: Box<ExprNode>,
+        line: u32,
+    },
+    /// calls a function with the values of its arguments, evaluated from left to right
+    Call {
+        callee: Box<ExprNode>,
+        arguments: Vec<ExprNode>,
+        /// the line of the closing parenthesis, which is where errors of the call are reported
+        line: u32,
+    },
+    /// reads a property of an instance, which is either one of its fields or one of its methods
+    Get {
+        object: Box<ExprNode>,
+        name: String,
+        line: u32,
+    },
+    /// sets a field of an instance, evaluating to the value
+    Set {
+        object: Box<ExprNode>,
+        name: String,
+        value: Box<ExprNode>,
+        line: u32,
+    },
+    /// creates a new list holding the values of its elements, evaluated from left to right
+    List {
+        elements: Vec<ExprNode>,
+        /// the line of the opening bracket
+        line: u32,
+    },
+    /// creates a new map holding the values of its entries, evaluating the key and then the value of
+    /// each entry from left to right
+    Map {
+        entries: Vec<(ExprNode, ExprNode)>,
+        /// the line of the opening brace
+        line: u32,
+    },
+    /// reads the element of a list at an index, or the value of a map at a key
+    Index {
+        object: Box<ExprNode>,
+        index: Box<ExprNode>,
+        /// the line of the opening bracket, which is where errors of the access are reported
+        line: u32,
+    },
+    /// a new list or string of the elements or characters of one from the start up to but not
+    /// including the end, which default to the start and the end of the whole
+    Slice {
+        object: Box<ExprNode>,
+        start: Option<Box<ExprNode>>,
+        end: Option<Box<ExprNode>>,
+        /// the line of the opening bracket
+        line: u32,
+    },
+    /// sets the element of a list at an index or the value of a map at a key, evaluating to the value
+    SetIndex {
+        object: Box<ExprNode>,
+        index: Box<ExprNode>,
+        value: Box<ExprNode>,
+        line: u32,
+    },
+    /// the instance a method is called on, which the parser only allows inside of a class
+    This {
+        line: u32,
+    },
+    /// a method of the superclass bound to the instance, which the parser only allows inside of a
+    /// class with a superclass
+    Super {
+        method: String,
+        line: u32,
+    },
 }
 
+/// The visitor is a trait for executing or otherwise walking the statements of a Lox AST
 pub trait StmtVisitor {
-    fn visit_stmt(&mut self, node: &StmtNode) {
+    type Output;
+
+    fn visit_stmt(&mut self, node: &StmtNode) -> Self::Output {
         match node {
             StmtNode::PrintStmt(print_stmt) => self.visit_print_stmt(print_stmt),
             StmtNode::ExprStmt(expr) => self.visit_expr_stmt(expr),
             StmtNode::ErrStmt(err) => self.visit_err_stmt(err.clone()),
-            StmtNode::VarStmt(_) => todo!("Executing Variable Statements not yet implemented!"),
+            StmtNode::VarStmt { name, initializer } => {
+                self.visit_var_stmt(name, initializer.as_ref())
+            }
+            StmtNode::ConstStmt { name, initializer } => self.visit_const_stmt(name, initializer),
+            StmtNode::ImportStmt {
+                path,
+                name,
+                names,
+                line,
+            } => self.visit_import_stmt(path, name.as_deref(), names, *line),
+            StmtNode::ExportStmt { statement, line } => self.visit_export_stmt(statement, *line),
+            StmtNode::BlockStmt(statements) => self.visit_block_stmt(statements),
+            StmtNode::IfStmt {
+                condition,
+                then_branch,
+                else_branch,
+            } => self.visit_if_stmt(condition, then_branch, else_branch.as_deref()),
+            StmtNode::WhileStmt {
+                condition,
+                body,
+                increment,
+            } => self.visit_while_stmt(condition, body, increment.as_ref()),
+            StmtNode::DoWhileStmt { body, condition } => self.visit_do_while_stmt(body, condition),
+            StmtNode::BreakStmt { line } => self.visit_break_stmt(*line),
+            StmtNode::ContinueStmt { line } => self.visit_continue_stmt(*line),
+            StmtNode::FunctionStmt(declaration) => self.visit_function_stmt(declaration),
+            StmtNode::ClassStmt(declaration) => self.visit_class_stmt(declaration),
+            StmtNode::ReturnStmt { value, line } => self.visit_return_stmt(value.as_ref(), *line),
+            StmtNode::ThrowStmt { value, line } => self.visit_throw_stmt(value, *line),
+            StmtNode::TryStmt {
+                body,
+                name,
+                handler,
+            } => self.visit_try_stmt(body, name, handler),
+            StmtNode::Commented { statement, .. } => self.visit_stmt(statement),
         }
     }
 
-    fn visit_print_stmt(&mut self, node: &ExprNode);
+    fn visit_print_stmt(&mut self, node: &ExprNode) -> Self::Output;
+
+    fn visit_expr_stmt(&mut self, node: &ExprNode) -> Self::Output;
 
-    fn visit_expr_stmt(&mut self, node: &ExprNode);
+    fn visit_err_stmt(&mut self, err: String) -> Self::Output;
 
-    fn visit_err_stmt(&mut self, err: String);
+    fn visit_var_stmt(&mut self, name: &str, initializer: Option<&ExprNode>) -> Self::Output;
+
+    fn visit_const_stmt(&mut self, name: &str, initializer: &ExprNode) -> Self::Output;
+
+    fn visit_import_stmt(
+        &mut self,
+        path: &str,
+        name: Option<&str>,
+        names: &[String],
+        line: u32,
+    ) -> Self::Output;
+
+    fn visit_export_stmt(&mut self, statement: &StmtNode, line: u32) -> Self::Output;
+
+    fn visit_block_stmt(&mut self, statements: &[StmtNode]) -> Self::Output;
+
+    fn visit_if_stmt(
+        &mut self,
+        condition: &ExprNode,
+        then_branch: &StmtNode,
+        else_branch: Option<&StmtNode>,
+    ) -> Self::Output;
+
+    fn visit_while_stmt(
+        &mut self,
+        condition: &ExprNode,
+        body: &StmtNode,
+        increment: Option<&ExprNode>,
+    ) -> Self::Output;
+
+    fn visit_do_while_stmt(&mut self, body: &StmtNode, condition: &ExprNode) -> Self::Output;
+
+    fn visit_break_stmt(&mut self, line: u32) -> Self::Output;
+
+    fn visit_continue_stmt(&mut self, line: u32) -> Self::Output;
+
+    fn visit_function_stmt(&mut self, declaration: &Rc<FunctionDecl>) -> Self::Output;
+
+    fn visit_class_stmt(&mut self, declaration: &Rc<ClassDecl>) -> Self::Output;
+
+    fn visit_return_stmt(&mut self, value: Option<&ExprNode>, line: u32) -> Self::Output;
+
+    fn visit_throw_stmt(&mut self, value: &ExprNode, line: u32) -> Self::Output;
+
+    fn visit_try_stmt(
+        &mut self,
+        body: &[StmtNode],
+        name: &str,
+        handler: &[StmtNode],
+    ) -> Self::Output;
 }
 
 /// The visitor is a trait for parsing and evaluating expressions in an Lox AST made up
@@ -160,6 +674,46 @@ pub trait ExprVisitor {
                 operator,
                 right,
             } => self.visit_binary_expr(left, operator, right),
+            ExprNode::Logical {
+                left,
+                operator,
+                right,
+            } => self.visit_logical_expr(left, operator, right),
+            ExprNode::Variable { name, line } => self.visit_variable(name, *line),
+            ExprNode::Assign { name, value, line } => self.visit_assign(name, value, *line),
+            ExprNode::Call {
+                callee,
+                arguments,
+                line,
+            } => self.visit_call(callee, arguments, *line),
+            ExprNode::Get { object, name, line } => self.visit_get(object, name, *line),
+            ExprNode::Set {
+                object,
+                name,
+                value,
+                line,
+            } => self.visit_set(object, name, value, *line),
+            ExprNode::List { elements, line } => self.visit_list(elements, *line),
+            ExprNode::Map { entries, line } => self.visit_map(entries, *line),
+            ExprNode::Slice {
+                object,
+                start,
+                end,
+                line,
+            } => self.visit_slice(object, start.as_deref(), end.as_deref(), *line),
+            ExprNode::Index {
+                object,
+                index,
+                line,
+            } => self.visit_index(object, index, *line),
+            ExprNode::SetIndex {
+                object,
+                index,
+                value,
+                line,
+            } => self.visit_set_index(object, index, value, *line),
+            ExprNode::This { line } => self.visit_this(*line),
+            ExprNode::Super { method, line } => self.visit_super(method, *line),
         }
     }
 
@@ -175,4 +729,785 @@ pub trait ExprVisitor {
     ) -> Self::Output;
 
     fn visit_unary_expr(&mut self, operator: &Operator, child: &ExprNode) -> Self::Output;
+
+    fn visit_logical_expr(
+        &mut self,
+        left: &ExprNode,
+        operator: &Operator,
+        right: &ExprNode,
+    ) -> Self::Output;
+
+    fn visit_variable(&mut self, name: &str, line: u32) -> Self::Output;
+
+    fn visit_assign(&mut self, name: &str, value: &ExprNode, line: u32) -> Self::Output;
+
+    fn visit_call(&mut self, callee: &ExprNode, arguments: &[ExprNode], line: u32) -> Self::Output;
+
+    fn visit_get(&mut self, object: &ExprNode, name: &str, line: u32) -> Self::Output;
+
+    fn visit_set(
+        &mut self,
+        object: &ExprNode,
+        name: &str,
+        value: &ExprNode,
+        line: u32,
+    ) -> Self::Output;
+
+    fn visit_list(&mut self, elements: &[ExprNode], line: u32) -> Self::Output;
+
+    fn visit_map(&mut self, entries: &[(ExprNode, ExprNode)], line: u32) -> Self::Output;
+
+    fn visit_index(&mut self, object: &ExprNode, index: &ExprNode, line: u32) -> Self::Output;
+
+    fn visit_slice(
+        &mut self,
+        object: &ExprNode,
+        start: Option<&ExprNode>,
+        end: Option<&ExprNode>,
+        line: u32,
+    ) -> Self::Output;
+
+    fn visit_set_index(
+        &mut self,
+        object: &ExprNode,
+        index: &ExprNode,
+        value: &ExprNode,
+        line: u32,
+    ) -> Self::Output;
+
+    fn visit_this(&mut self, line: u32) -> Self::Output;
+
+    fn visit_super(&mut self, method: &str, line: u32) -> Self::Output;
+}
+
+/// the names a module exports with `export` at its top level, or none when it exports nothing and so
+/// gives all of its globals to the scripts importing it.
+pub fn exports(statements: &[StmtNode]) -> Option<HashSet<String>> {
+    let mut exports = HashSet::new();
+    let mut exporting = false;
+    for statement in statements {
+        let mut statement = statement;
+        while let StmtNode::Commented {
+            statement: inner, ..
+        } = statement
+        {
+            statement = inner;
+        }
+        let StmtNode::ExportStmt { statement, .. } = statement else {
+            continue;
+        };
+        exporting = true;
+        match statement.as_ref() {
+            StmtNode::VarStmt { name, .. } | StmtNode::ConstStmt { name, .. } => {
+                exports.insert(name.clone());
+            }
+            StmtNode::FunctionStmt(declaration) => {
+                exports.insert(declaration.name.clone());
+            }
+            StmtNode::ClassStmt(declaration) => {
+                exports.insert(declaration.name.clone());
+            }
+            StmtNode::ImportStmt { name, names, .. } => {
+                exports.extend(name.iter().chain(names).cloned());
+            }
+            _ => {}
+        }
+    }
+    exporting.then_some(exports)
+}
+
+/// A single change between two versions of a program, as produced by [`diff`].
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub enum Change {
+    /// a statement only found in the new program, at `index` of the new program
+    Inserted { index: usize, statement: StmtNode },
+    /// a statement only found in the old program, at `index` of the old program
+    Removed { index: usize, statement: StmtNode },
+    /// a statement of the old program that was replaced by a different statement in the new program
+    Modified {
+        old_index: usize,
+        new_index: usize,
+        old: StmtNode,
+        new: StmtNode,
+    },
+}
+
+/// Produces a minimal structural diff between the statements of two parsed programs.
+///
+/// Statements are compared structurally without their lines and comments, so changes to whitespace
+/// or comments don't show up. The longest common subsequence of statements is kept unchanged and
+/// every other statement is reported as inserted or removed, where a removal directly followed by an
+/// insertion is reported as modified.
+pub fn diff(old: &[StmtNode], new: &[StmtNode]) -> Vec<Change> {
+    let stripped_old: Vec<StmtNode> = old.iter().map(strip_stmt).collect();
+    let stripped_new: Vec<StmtNode> = new.iter().map(strip_stmt).collect();
+    let same = |i: usize, j: usize| stripped_old[i] == stripped_new[j];
+
+    // lcs[i][j] is the length of the longest common subsequence of old[i..] and new[j..]
+    let mut lcs = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if same(i, j) {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && same(i, j) {
+            i += 1;
+            j += 1;
+        } else if i < old.len() && j < new.len() && lcs[i + 1][j + 1] == lcs[i][j] {
+            // neither statement is part of the common subsequence so one replaced the other
+            changes.push(Change::Modified {
+                old_index: i,
+                new_index: j,
+                old: old[i].clone(),
+                new: new[j].clone(),
+            });
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            changes.push(Change::Removed {
+                index: i,
+                statement: old[i].clone(),
+            });
+            i += 1;
+        } else {
+            changes.push(Change::Inserted {
+                index: j,
+                statement: new[j].clone(),
+            });
+            j += 1;
+        }
+    }
+    changes
+}
+
+/// a copy of the statement with every line set to 0 and without its comments and docs, so that two
+/// statements which only differ in where they are written compare equal
+fn strip_stmt(stmt: &StmtNode) -> StmtNode {
+    let block = |statements: &Vec<StmtNode>| statements.iter().map(strip_stmt).collect();
+    let boxed = |statement: &StmtNode| Box::new(strip_stmt(statement));
+    match stmt {
+        StmtNode::PrintStmt(expr) => StmtNode::PrintStmt(strip_expr(expr)),
+        StmtNode::ExprStmt(expr) => StmtNode::ExprStmt(strip_expr(expr)),
+        StmtNode::ErrStmt(err) => StmtNode::ErrStmt(err.clone()),
+        StmtNode::VarStmt { name, initializer } => StmtNode::VarStmt {
+            name: name.clone(),
+            initializer: initializer.as_ref().map(strip_expr),
+        },
+        StmtNode::ConstStmt { name, initializer } => StmtNode::ConstStmt {
+            name: name.clone(),
+            initializer: strip_expr(initializer),
+        },
+        StmtNode::ImportStmt {
+            path, name, names, ..
+        } => StmtNode::ImportStmt {
+            path: path.clone(),
+            name: name.clone(),
+            names: names.clone(),
+            line: 0,
+        },
+        StmtNode::ExportStmt { statement, .. } => StmtNode::ExportStmt {
+            statement: boxed(statement),
+            line: 0,
+        },
+        StmtNode::BlockStmt(statements) => StmtNode::BlockStmt(block(statements)),
+        StmtNode::IfStmt {
+            condition,
+            then_branch,
+            else_branch,
+        } => StmtNode::IfStmt {
+            condition: strip_expr(condition),
+            then_branch: boxed(then_branch),
+            else_branch: else_branch.as_deref().map(boxed),
+        },
+        StmtNode::WhileStmt {
+            condition,
+            body,
+            increment,
+        } => StmtNode::WhileStmt {
+            condition: strip_expr(condition),
+            body: boxed(body),
+            increment: increment.as_ref().map(strip_expr),
+        },
+        StmtNode::DoWhileStmt { body, condition } => StmtNode::DoWhileStmt {
+            body: boxed(body),
+            condition: strip_expr(condition),
+        },
+        StmtNode::BreakStmt { .. } => StmtNode::BreakStmt { line: 0 },
+        StmtNode::ContinueStmt { .. } => StmtNode::ContinueStmt { line: 0 },
+        StmtNode::FunctionStmt(function) => StmtNode::FunctionStmt(strip_function(function)),
+        StmtNode::ClassStmt(class) => StmtNode::ClassStmt(Rc::new(ClassDecl {
+            name: class.name.clone(),
+            superclass: class.superclass.as_ref().map(strip_expr),
+            methods: class.methods.iter().map(strip_function).collect(),
+            class_methods: class.class_methods.iter().map(strip_function).collect(),
+            line: 0,
+            doc: None,
+        })),
+        StmtNode::ReturnStmt { value, .. } => StmtNode::ReturnStmt {
+            value: value.as_ref().map(strip_expr),
+            line: 0,
+        },
+        StmtNode::ThrowStmt { value, .. } => StmtNode::ThrowStmt {
+            value: strip_expr(value),
+            line: 0,
+        },
+        StmtNode::TryStmt {
+            body,
+            name,
+            handler,
+        } => StmtNode::TryStmt {
+            body: block(body),
+            name: name.clone(),
+            handler: block(handler),
+        },
+        StmtNode::Commented { statement, .. } => strip_stmt(statement),
+    }
+}
+
+/// a copy of the function like [`strip_stmt`]
+fn strip_function(function: &Rc<FunctionDecl>) -> Rc<FunctionDecl> {
+    Rc::new(FunctionDecl {
+        name: function.name.clone(),
+        params: function.params.clone(),
+        rest: function.rest.clone(),
+        body: function.body.iter().map(strip_stmt).collect(),
+        line: 0,
+        doc: None,
+    })
+}
+
+/// a copy of the expression like [`strip_stmt`]
+fn strip_expr(expr: &ExprNode) -> ExprNode {
+    let boxed = |expr: &ExprNode| Box::new(strip_expr(expr));
+    match expr {
+        ExprNode::Literal(literal) => ExprNode::Literal(literal.clone()),
+        ExprNode::Grouping(expr) => ExprNode::Grouping(boxed(expr)),
+        ExprNode::UnaryExpr { operator, right } => ExprNode::UnaryExpr {
+            operator: strip_operator(operator),
+            right: boxed(right),
+        },
+        ExprNode::BinaryExpr {
+            left,
+            operator,
+            right,
+        } => ExprNode::BinaryExpr {
+            left: boxed(left),
+            operator: strip_operator(operator),
+            right: boxed(right),
+        },
+        ExprNode::Logical {
+            left,
+            operator,
+            right,
+        } => ExprNode::Logical {
+            left: boxed(left),
+            operator: strip_operator(operator),
+            right: boxed(right),
+        },
+        ExprNode::Variable { name, .. } => ExprNode::Variable {
+            name: name.clone(),
+            line: 0,
+        },
+        ExprNode::Assign { name, value, .. } => ExprNode::Assign {
+            name: name.clone(),
+            value: boxed(value),
+            line: 0,
+        },
+        ExprNode::Call {
+            callee, arguments, ..
+        } => ExprNode::Call {
+            callee: boxed(callee),
+            arguments: arguments.iter().map(strip_expr).collect(),
+            line: 0,
+        },
+        ExprNode::Get { object, name, .. } => ExprNode::Get {
+            object: boxed(object),
+            name: name.clone(),
+            line: 0,
+        },
+        ExprNode::Set {
+            object,
+            name,
+            value,
+            ..
+        } => ExprNode::Set {
+            object: boxed(object),
+            name: name.clone(),
+            value: boxed(value),
+            line: 0,
+        },
+        ExprNode::List { elements, .. } => ExprNode::List {
+            elements: elements.iter().map(strip_expr).collect(),
+            line: 0,
+        },
+        ExprNode::Map { entries, .. } => ExprNode::Map {
+            entries: entries
+                .iter()
+                .map(|(key, value)| (strip_expr(key), strip_expr(value)))
+                .collect(),
+            line: 0,
+        },
+        ExprNode::Index { object, index, .. } => ExprNode::Index {
+            object: boxed(object),
+            index: boxed(index),
+            line: 0,
+        },
+        ExprNode::Slice {
+            object, start, end, ..
+        } => ExprNode::Slice {
+            object: boxed(object),
+            start: start.as_deref().map(boxed),
+            end: end.as_deref().map(boxed),
+            line: 0,
+        },
+        ExprNode::SetIndex {
+            object,
+            index,
+            value,
+            ..
+        } => ExprNode::SetIndex {
+            object: boxed(object),
+            index: boxed(index),
+            value: boxed(value),
+            line: 0,
+        },
+        ExprNode::This { .. } => ExprNode::This { line: 0 },
+        ExprNode::Super { method, .. } => ExprNode::Super {
+            method: method.clone(),
+            line: 0,
+        },
+    }
+}
+
+/// the operator on line 0, like [`strip_stmt`]
+fn strip_operator(operator: &Operator) -> Operator {
+    match operator {
+        Operator::Add { .. } => Operator::Add { line: 0 },
+        Operator::Subtract { .. } => Operator::Subtract { line: 0 },
+        Operator::Multiply { .. } => Operator::Multiply { line: 0 },
+        Operator::Divide { .. } => Operator::Divide { line: 0 },
+        Operator::GreaterThan { .. } => Operator::GreaterThan { line: 0 },
+        Operator::GreaterEqual { .. } => Operator::GreaterEqual { line: 0 },
+        Operator::LessThan { .. } => Operator::LessThan { line: 0 },
+        Operator::LessEqual { .. } => Operator::LessEqual { line: 0 },
+        Operator::Equal { .. } => Operator::Equal { line: 0 },
+        Operator::EqualEqual { .. } => Operator::EqualEqual { line: 0 },
+        Operator::NotEqual { .. } => Operator::NotEqual { line: 0 },
+        Operator::And { .. } => Operator::And { line: 0 },
+        Operator::Or { .. } => Operator::Or { line: 0 },
+        Operator::Bang { .. } => Operator::Bang { line: 0 },
+    }
+}
+
+/// A statement, method or expression of a program, as found by [`Ast`].
+#[derive(Debug, Clone, Copy)]
+pub enum Node<'a> {
+    Stmt(&'a StmtNode),
+    /// a method of a class, which unlike a function declared with `fun` is not a statement
+    Method(&'a FunctionDecl),
+    Expr(&'a ExprNode),
+}
+
+impl<'a> Node<'a> {
+    /// the nodes directly inside of this one, in the order they are written
+    pub fn children(self) -> Vec<Node<'a>> {
+        let stmts = |statements: &'a [StmtNode]| statements.iter().map(Node::Stmt);
+        match self {
+            Node::Stmt(stmt) => match stmt {
+                StmtNode::PrintStmt(expr)
+                | StmtNode::ExprStmt(expr)
+                | StmtNode::ConstStmt {
+                    initializer: expr, ..
+                }
+                | StmtNode::ThrowStmt { value: expr, .. } => vec![Node::Expr(expr)],
+                StmtNode::VarStmt { initializer, .. } => {
+                    initializer.iter().map(Node::Expr).collect()
+                }
+                StmtNode::ReturnStmt { value, .. } => value.iter().map(Node::Expr).collect(),
+                StmtNode::ErrStmt(_)
+                | StmtNode::ImportStmt { .. }
+                | StmtNode::BreakStmt { .. }
+                | StmtNode::ContinueStmt { .. } => Vec::new(),
+                StmtNode::BlockStmt(statements) => stmts(statements).collect(),
+                StmtNode::IfStmt {
+                    condition,
+                    then_branch,
+                    else_branch,
+                } => [Node::Expr(condition), Node::Stmt(then_branch)]
+                    .into_iter()
+                    .chain(else_branch.as_deref().map(Node::Stmt))
+                    .collect(),
+                StmtNode::WhileStmt {
+                    condition,
+                    body,
+                    increment,
+                } => [Node::Expr(condition), Node::Stmt(body)]
+                    .into_iter()
+                    .chain(increment.iter().map(Node::Expr))
+                    .collect(),
+                StmtNode::DoWhileStmt { body, condition } => {
+                    vec![Node::Stmt(body), Node::Expr(condition)]
+                }
+                StmtNode::FunctionStmt(declaration) => stmts(&declaration.body).collect(),
+                StmtNode::ClassStmt(declaration) => declaration
+                    .superclass
+                    .iter()
+                    .map(Node::Expr)
+                    .chain(
+                        declaration
+                            .methods
+                            .iter()
+                            .map(|method| Node::Method(method)),
+                    )
+                    .chain(
+                        declaration
+                            .class_methods
+                            .iter()
+                            .map(|method| Node::Method(method)),
+                    )
+                    .collect(),
+                StmtNode::TryStmt { body, handler, .. } => {
+                    stmts(body).chain(stmts(handler)).collect()
+                }
+                StmtNode::Commented { statement, .. } | StmtNode::ExportStmt { statement, .. } => {
+                    vec![Node::Stmt(statement)]
+                }
+            },
+            Node::Method(declaration) => stmts(&declaration.body).collect(),
+            Node::Expr(expr) => {
+                let exprs: Vec<&ExprNode> = match expr {
+                    ExprNode::Literal(_)
+                    | ExprNode::Variable { .. }
+                    | ExprNode::This { .. }
+                    | ExprNode::Super { .. } => Vec::new(),
+                    ExprNode::Grouping(expr)
+                    | ExprNode::UnaryExpr { right: expr, .. }
+                    | ExprNode::Assign { value: expr, .. }
+                    | ExprNode::Get { object: expr, .. } => vec![expr],
+                    ExprNode::BinaryExpr { left, right, .. }
+                    | ExprNode::Logical { left, right, .. } => vec![left, right],
+                    ExprNode::Call {
+                        callee, arguments, ..
+                    } => std::iter::once(callee.as_ref()).chain(arguments).collect(),
+                    ExprNode::Set { object, value, .. } => vec![object, value],
+                    ExprNode::List { elements, .. } => elements.iter().collect(),
+                    ExprNode::Map { entries, .. } => entries
+                        .iter()
+                        .flat_map(|(key, value)| [key, value])
+                        .collect(),
+                    ExprNode::Index { object, index, .. } => vec![object, index],
+                    ExprNode::Slice {
+                        object, start, end, ..
+                    } => std::iter::once(object)
+                        .chain(start)
+                        .chain(end)
+                        .map(|expr| expr.as_ref())
+                        .collect(),
+                    ExprNode::SetIndex {
+                        object,
+                        index,
+                        value,
+                        ..
+                    } => vec![object, index, value],
+                };
+                exprs.into_iter().map(Node::Expr).collect()
+            }
+        }
+    }
+
+    /// identifies the node by its address, which an expression can share with the statement
+    /// holding it, so the kind of node is part of it
+    fn key(self) -> (u8, *const ()) {
+        match self {
+            Node::Stmt(stmt) => (0, stmt as *const StmtNode as *const ()),
+            Node::Method(method) => (1, method as *const FunctionDecl as *const ()),
+            Node::Expr(expr) => (2, expr as *const ExprNode as *const ()),
+        }
+    }
+}
+
+/// A kind of node looked for by [`Ast::find`], see [`kind`].
+pub trait NodeKind {
+    /// whether the node is of this kind
+    fn matches(node: Node<'_>) -> bool;
+}
+
+/// The kinds of nodes, named like the variants of [`StmtNode`] and [`ExprNode`], e.g.
+/// `ast.find::<kind::Call>()`.
+pub mod kind {
+    use super::{ExprNode, Node, NodeKind, StmtNode};
+
+    macro_rules! kinds {
+        ($($(#[$doc:meta])* $kind:ident => $pattern:pat,)*) => {
+            $(
+                $(#[$doc])*
+                pub struct $kind;
+
+                impl NodeKind for $kind {
+                    fn matches(node: Node<'_>) -> bool {
+                        matches!(node, $pattern)
+                    }
+                }
+            )*
+        };
+    }
+
+    kinds! {
+        /// every statement
+        Stmt => Node::Stmt(_),
+        /// every expression
+        Expr => Node::Expr(_),
+        PrintStmt => Node::Stmt(StmtNode::PrintStmt(_)),
+        ExprStmt => Node::Stmt(StmtNode::ExprStmt(_)),
+        ErrStmt => Node::Stmt(StmtNode::ErrStmt(_)),
+        VarStmt => Node::Stmt(StmtNode::VarStmt { .. }),
+        ConstStmt => Node::Stmt(StmtNode::ConstStmt { .. }),
+        ImportStmt => Node::Stmt(StmtNode::ImportStmt { .. }),
+        ExportStmt => Node::Stmt(StmtNode::ExportStmt { .. }),
+        BlockStmt => Node::Stmt(StmtNode::BlockStmt(_)),
+        IfStmt => Node::Stmt(StmtNode::IfStmt { .. }),
+        WhileStmt => Node::Stmt(StmtNode::WhileStmt { .. }),
+        DoWhileStmt => Node::Stmt(StmtNode::DoWhileStmt { .. }),
+        BreakStmt => Node::Stmt(StmtNode::BreakStmt { .. }),
+        ContinueStmt => Node::Stmt(StmtNode::ContinueStmt { .. }),
+        FunctionStmt => Node::Stmt(StmtNode::FunctionStmt(_)),
+        ClassStmt => Node::Stmt(StmtNode::ClassStmt(_)),
+        ReturnStmt => Node::Stmt(StmtNode::ReturnStmt { .. }),
+        ThrowStmt => Node::Stmt(StmtNode::ThrowStmt { .. }),
+        TryStmt => Node::Stmt(StmtNode::TryStmt { .. }),
+        Commented => Node::Stmt(StmtNode::Commented { .. }),
+        /// the methods and class methods of classes
+        Method => Node::Method(_),
+        Literal => Node::Expr(ExprNode::Literal(_)),
+        Grouping => Node::Expr(ExprNode::Grouping(_)),
+        UnaryExpr => Node::Expr(ExprNode::UnaryExpr { .. }),
+        BinaryExpr => Node::Expr(ExprNode::BinaryExpr { .. }),
+        Logical => Node::Expr(ExprNode::Logical { .. }),
+        Variable => Node::Expr(ExprNode::Variable { .. }),
+        Assign => Node::Expr(ExprNode::Assign { .. }),
+        Call => Node::Expr(ExprNode::Call { .. }),
+        Get => Node::Expr(ExprNode::Get { .. }),
+        Set => Node::Expr(ExprNode::Set { .. }),
+        List => Node::Expr(ExprNode::List { .. }),
+        Map => Node::Expr(ExprNode::Map { .. }),
+        Index => Node::Expr(ExprNode::Index { .. }),
+        Slice => Node::Expr(ExprNode::Slice { .. }),
+        SetIndex => Node::Expr(ExprNode::SetIndex { .. }),
+        This => Node::Expr(ExprNode::This { .. }),
+        Super => Node::Expr(ExprNode::Super { .. }),
+    }
+}
+
+/// A parsed program indexed so that tools can find its nodes by kind and move from a node to the
+/// ones around it, without walking the syntax tree by hand. Nodes only know their line and not where
+/// in the line they are, so there is no lookup of the node at an offset of the source.
+pub struct Ast<'a> {
+    /// every node in the order they are written, each before the nodes inside of it
+    nodes: Vec<Node<'a>>,
+    /// the index of the parent of each node in `nodes`, None for the top level statements
+    parents: Vec<Option<usize>>,
+    /// the index of each node in `nodes` by its key
+    indices: HashMap<(u8, *const ()), usize>,
+}
+
+impl<'a> Ast<'a> {
+    /// indexes the statements of a parsed program
+    pub fn new(program: &'a [StmtNode]) -> Self {
+        let mut ast = Ast {
+            nodes: Vec::new(),
+            parents: Vec::new(),
+            indices: HashMap::new(),
+        };
+        for statement in program {
+            ast.add(Node::Stmt(statement), None);
+        }
+        ast
+    }
+
+    fn add(&mut self, node: Node<'a>, parent: Option<usize>) {
+        let index = self.nodes.len();
+        self.nodes.push(node);
+        self.parents.push(parent);
+        self.indices.insert(node.key(), index);
+        for child in node.children() {
+            self.add(child, Some(index));
+        }
+    }
+
+    /// every node of the program in the order they are written, each before the nodes inside of it
+    pub fn nodes(&self) -> &[Node<'a>] {
+        &self.nodes
+    }
+
+    /// the nodes of the kind in the order they are written
+    pub fn find<K: NodeKind>(&self) -> Vec<Node<'a>> {
+        self.nodes
+            .iter()
+            .copied()
+            .filter(|node| K::matches(*node))
+            .collect()
+    }
+
+    /// the node the node is directly inside of, or None for a top level statement or a node which
+    /// is not part of the program
+    pub fn parent(&self, node: Node<'a>) -> Option<Node<'a>> {
+        let index = self.parents[*self.indices.get(&node.key())?]?;
+        Some(self.nodes[index])
+    }
+
+    /// the nodes the node is inside of, innermost first
+    pub fn ancestors(&self, node: Node<'a>) -> impl Iterator<Item = Node<'a>> + '_ {
+        std::iter::successors(self.parent(node), |node| self.parent(*node))
+    }
+
+    /// the innermost node of the kind the node is inside of, e.g. the function a `return` is in
+    pub fn enclosing<K: NodeKind>(&self, node: Node<'a>) -> Option<Node<'a>> {
+        self.ancestors(node).find(|ancestor| K::matches(*ancestor))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn print(value: f64) -> StmtNode {
+        StmtNode::PrintStmt(ExprNode::Literal(Literal::Number(value)))
+    }
+
+    #[test]
+    fn diff_unchanged_test() {
+        let program = vec![print(1.0), print(2.0)];
+        assert_eq!(diff(&program, &program), Vec::new());
+    }
+
+    #[test]
+    fn diff_modified_test() {
+        let old = vec![print(1.0), print(2.0), print(3.0)];
+        let new = vec![print(1.0), print(5.0), print(3.0)];
+        assert_eq!(
+            diff(&old, &new),
+            vec![Change::Modified {
+                old_index: 1,
+                new_index: 1,
+                old: print(2.0),
+                new: print(5.0),
+            }]
+        );
+    }
+
+    #[test]
+    fn diff_inserted_removed_test() {
+        let old = vec![print(1.0), print(2.0)];
+        let new = vec![print(0.0), print(1.0), print(2.0), print(3.0)];
+        assert_eq!(
+            diff(&old, &new),
+            vec![
+                Change::Inserted {
+                    index: 0,
+                    statement: print(0.0),
+                },
+                Change::Inserted {
+                    index: 3,
+                    statement: print(3.0),
+                },
+            ]
+        );
+
+        assert_eq!(
+            diff(&new, &old),
+            vec![
+                Change::Removed {
+                    index: 0,
+                    statement: print(0.0),
+                },
+                Change::Removed {
+                    index: 3,
+                    statement: print(3.0),
+                },
+            ]
+        );
+    }
+
+    fn parse(source: &str) -> Vec<StmtNode> {
+        let mut lexer = crate::lexer::Lexer::new().with_comments(true);
+        let tokens = lexer.lex(source).unwrap();
+        crate::parser::Parser::new()
+            .with_comments(lexer.take_comments())
+            .parse(tokens)
+    }
+
+    #[test]
+    fn diff_lines_comments_test() {
+        let source =
+            "var a = 1 + 2;\nfun f(x) { return x * a; }\nclass C { m() { print this.x; } }\n";
+        let old = parse(source);
+        assert_eq!(diff(&old, &parse(&format!("\n\n{}", source))), Vec::new());
+        assert_eq!(
+            diff(&old, &parse(&source.replace("var a", "// hi\nvar a"))),
+            Vec::new()
+        );
+        assert_eq!(
+            diff(&old, &parse(&source.replace("fun f", "/// doc\nfun f"))),
+            Vec::new()
+        );
+        assert_eq!(diff(&old, &parse(&source.replace("2;", "3;"))).len(), 1);
+    }
+
+    #[test]
+    /// tests that nodes are found by their kind and lead to the nodes they are inside of
+    fn ast_query_test() {
+        let program = parse(
+            "fun f(x) {\n  print g(x + 1);\n}\n\
+             class C < B {\n  m() { return this.h([1, 2]); }\n}\n\
+             // call it\nf(3);",
+        );
+        let ast = Ast::new(&program);
+
+        let calls = ast.find::<kind::Call>();
+        assert_eq!(calls.len(), 3);
+        assert!(matches!(
+            ast.parent(calls[0]),
+            Some(Node::Stmt(StmtNode::PrintStmt(_)))
+        ));
+        assert!(matches!(
+            ast.enclosing::<kind::FunctionStmt>(calls[0]),
+            Some(Node::Stmt(StmtNode::FunctionStmt(declaration))) if declaration.name == "f"
+        ));
+        assert!(matches!(
+            ast.enclosing::<kind::Method>(calls[1]),
+            Some(Node::Method(declaration)) if declaration.name == "m"
+        ));
+        // the call at the top level is only inside of its statement and the comment before it
+        let kinds: Vec<_> = ast
+            .ancestors(calls[2])
+            .map(|node| match node {
+                Node::Stmt(StmtNode::ExprStmt(_)) => "expression",
+                Node::Stmt(StmtNode::Commented { .. }) => "commented",
+                node => panic!("unexpected ancestor {:?}", node),
+            })
+            .collect();
+        assert_eq!(kinds, vec!["expression", "commented"]);
+
+        let list = ast.find::<kind::List>()[0];
+        assert_eq!(ast.find::<kind::Literal>().len(), 4);
+        assert_eq!(list.children().len(), 2);
+        assert!(matches!(
+            ast.parent(list.children()[0]),
+            Some(Node::Expr(ExprNode::List { .. }))
+        ));
+        assert_eq!(ast.find::<kind::Stmt>().len(), 6);
+
+        // nodes which are not part of the program have no parent
+        let this = ExprNode::This { line: 5 };
+        assert!(ast.parent(Node::Expr(&this)).is_none());
+        assert!(ast.parent(Node::Stmt(&program[0])).is_none());
+    }
 }
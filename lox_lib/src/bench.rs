@@ -0,0 +1,126 @@
+//!
+//! This module contains the results of `bench(name, function, iterations)`, the native for
+//! benchmarking Lox code without host tooling. The function is called a few times to warm up first,
+//! and then every iteration is timed on its own, so that the report can give the spread of the
+//! timings as well as their mean.
+//!
+//! `lox bench script.lox` runs a script and prints the results of all of its benchmarks at the end.
+
+use std::fmt;
+use std::time::Duration;
+
+/// the number of calls to warm up with before timing the given number of iterations, a tenth of
+/// them but at least one
+pub fn warmup_iterations(iterations: usize) -> usize {
+    iterations.div_ceil(10)
+}
+
+/// the timings of a benchmark.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchResult {
+    pub name: String,
+    pub iterations: usize,
+    pub warmup: usize,
+    pub mean: Duration,
+    /// the standard deviation of the timings of the iterations
+    pub stddev: Duration,
+    pub line: u32,
+}
+
+impl BenchResult {
+    /// summarizes the timings of every iteration of a benchmark, of which there is at least one
+    pub fn from_samples(name: String, warmup: usize, samples: &[Duration], line: u32) -> Self {
+        let seconds: Vec<f64> = samples.iter().map(Duration::as_secs_f64).collect();
+        let mean = seconds.iter().sum::<f64>() / seconds.len() as f64;
+        let variance = seconds
+            .iter()
+            .map(|sample| (sample - mean).powi(2))
+            .sum::<f64>()
+            / seconds.len() as f64;
+        Self {
+            name,
+            iterations: samples.len(),
+            warmup,
+            mean: Duration::from_secs_f64(mean),
+            stddev: Duration::from_secs_f64(variance.sqrt()),
+            line,
+        }
+    }
+}
+
+/// the benchmarks run by a program, in the order they ran.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BenchReport {
+    pub results: Vec<BenchResult>,
+}
+
+/// a duration in the largest unit in which it is at least 1, with 3 decimals
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs_f64();
+    if seconds >= 1.0 {
+        format!("{:.3} s", seconds)
+    } else if seconds >= 1e-3 {
+        format!("{:.3} ms", seconds * 1e3)
+    } else if seconds >= 1e-6 {
+        format!("{:.3} us", seconds * 1e6)
+    } else {
+        format!("{:.3} ns", seconds * 1e9)
+    }
+}
+
+/// prints a row for every benchmark, with the names lined up
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "benchmarks: {}", self.results.len())?;
+        let width = self
+            .results
+            .iter()
+            .map(|result| result.name.len())
+            .max()
+            .unwrap_or_default();
+        for result in &self.results {
+            writeln!(
+                f,
+                "  {:width$}  mean {:>12}  stddev {:>12}  ({} iterations after {} warmup, line {})",
+                result.name,
+                format_duration(result.mean),
+                format_duration(result.stddev),
+                result.iterations,
+                result.warmup,
+                result.line,
+                width = width
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    /// tests the statistics of the samples and how they are printed
+    fn bench_result_test() {
+        let samples = [2, 4, 4, 4, 5, 5, 7, 9].map(Duration::from_millis);
+        let result = BenchResult::from_samples("sort".to_string(), 1, &samples, 3);
+        assert_eq!(result.iterations, 8);
+        assert_eq!(result.mean, Duration::from_millis(5));
+        assert_eq!(result.stddev, Duration::from_millis(2));
+
+        let report = BenchReport {
+            results: vec![
+                result,
+                BenchResult::from_samples("fib".to_string(), 1, &[Duration::from_nanos(1500)], 9),
+            ],
+        };
+        assert_eq!(
+            report.to_string(),
+            "benchmarks: 2\n  \
+            sort  mean     5.000 ms  stddev     2.000 ms  (8 iterations after 1 warmup, line 3)\n  \
+            fib   mean     1.500 us  stddev     0.000 ns  (1 iterations after 1 warmup, line 9)\n"
+        );
+        assert_eq!(warmup_iterations(1), 1);
+        assert_eq!(warmup_iterations(100), 10);
+    }
+}
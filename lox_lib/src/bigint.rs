@@ -0,0 +1,134 @@
+//!
+//! This module contains the arithmetic of big integers, whole numbers of any size written like
+//! `123n`, for number theory which overflows the doubles Lox numbers are. They are only built with
+//! the `bigint` feature.
+//!
+//! Big integers support `+`, `-`, `*`, `/` and the comparisons. Division truncates towards zero like
+//! integer division does in Rust. A big integer can be combined with a number as long as the number
+//! is whole, which makes the result a big integer, so `2n * 3` is `6n`.
+
+use crate::ast::{Literal, Operator};
+use crate::messages::MessageId;
+use num_bigint::BigInt;
+use num_traits::{FromPrimitive, Zero};
+
+/// why an operator can not be applied to a big integer
+#[derive(Debug, Clone, PartialEq)]
+pub enum BigIntError {
+    /// the other operand is a number with a fractional part
+    Fraction(f64),
+    DivisionByZero,
+}
+
+impl BigIntError {
+    pub fn id(&self) -> MessageId {
+        match self {
+            BigIntError::Fraction(_) => MessageId::BigIntWithFraction,
+            BigIntError::DivisionByZero => MessageId::BigIntDivisionByZero,
+        }
+    }
+
+    pub fn args(&self) -> Vec<String> {
+        match self {
+            BigIntError::Fraction(number) => vec![number.to_string()],
+            BigIntError::DivisionByZero => Vec::new(),
+        }
+    }
+}
+
+/// the big integer a value stands for, if it is a big integer or a whole number
+fn to_bigint(value: &Literal) -> Option<Result<BigInt, BigIntError>> {
+    match value {
+        Literal::BigInt(bigint) => Some(Ok(bigint.clone())),
+        Literal::Number(number) => Some(
+            BigInt::from_f64(*number)
+                .filter(|_| number.fract() == 0.0)
+                .ok_or(BigIntError::Fraction(*number)),
+        ),
+        _ => None,
+    }
+}
+
+/// applies an arithmetic or comparison operator when one operand is a big integer and the other is
+/// a big integer or a number. Other operands and operators are left to the rules for numbers and
+/// strings, which is None.
+pub fn binary(
+    operator: &Operator,
+    left: &Literal,
+    right: &Literal,
+) -> Option<Result<Literal, BigIntError>> {
+    if !matches!(left, Literal::BigInt(_)) && !matches!(right, Literal::BigInt(_)) {
+        return None;
+    }
+    let (left, right) = match (to_bigint(left)?, to_bigint(right)?) {
+        (Ok(left), Ok(right)) => (left, right),
+        (Err(err), _) | (_, Err(err)) => return Some(Err(err)),
+    };
+    let result = match operator {
+        Operator::Add { .. } => Literal::BigInt(left + right),
+        Operator::Subtract { .. } => Literal::BigInt(left - right),
+        Operator::Multiply { .. } => Literal::BigInt(left * right),
+        Operator::Divide { .. } if right.is_zero() => {
+            return Some(Err(BigIntError::DivisionByZero))
+        }
+        Operator::Divide { .. } => Literal::BigInt(left / right),
+        Operator::GreaterThan { .. } => Literal::Boolean(left > right),
+        Operator::GreaterEqual { .. } => Literal::Boolean(left >= right),
+        Operator::LessThan { .. } => Literal::Boolean(left < right),
+        Operator::LessEqual { .. } => Literal::Boolean(left <= right),
+        _ => return None,
+    };
+    Some(Ok(result))
+}
+
+/// whether a big integer and a number are the same whole number
+pub fn equals_number(bigint: &BigInt, number: f64) -> bool {
+    number.fract() == 0.0 && BigInt::from_f64(number).as_ref() == Some(bigint)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    /// tests that big integers combine with each other and with whole numbers
+    fn binary_test() {
+        let bigint = |value: i64| Literal::BigInt(BigInt::from(value));
+        let add = Operator::Add { line: 1 };
+        let divide = Operator::Divide { line: 1 };
+        assert_eq!(binary(&add, &bigint(2), &bigint(3)), Some(Ok(bigint(5))));
+        assert_eq!(
+            binary(&add, &Literal::Number(2.0), &bigint(3)),
+            Some(Ok(bigint(5)))
+        );
+        assert_eq!(
+            binary(&divide, &bigint(-7), &bigint(2)),
+            Some(Ok(bigint(-3)))
+        );
+        assert_eq!(
+            binary(
+                &Operator::LessThan { line: 1 },
+                &bigint(1),
+                &Literal::Number(2.0)
+            ),
+            Some(Ok(Literal::Boolean(true)))
+        );
+        assert_eq!(
+            binary(&add, &bigint(1), &Literal::Number(0.5)),
+            Some(Err(BigIntError::Fraction(0.5)))
+        );
+        assert_eq!(
+            binary(&divide, &bigint(1), &bigint(0)),
+            Some(Err(BigIntError::DivisionByZero))
+        );
+        // operands which are not numbers are left to the usual rules
+        assert_eq!(binary(&add, &bigint(1), &Literal::Nil), None);
+        assert_eq!(
+            binary(&add, &Literal::Number(1.0), &Literal::Number(2.0)),
+            None
+        );
+
+        assert!(equals_number(&BigInt::from(3), 3.0));
+        assert!(!equals_number(&BigInt::from(3), 3.5));
+    }
+}
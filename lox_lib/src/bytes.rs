@@ -0,0 +1,197 @@
+//!
+//! This module contains bytes, the values holding binary data such as the contents of a file or a
+//! network response. They are written like `b"GIF89a\x00"`, where `\xNN` is a byte given by two hex
+//! digits and `\\` is a backslash, or built from a list of whole numbers with `bytes([71, 73])`.
+//!
+//! Like strings, bytes can not be changed. `b[i]` is the byte at an index as a number from 0 to 255
+//! and `b[a:b]` is new bytes. `encode(value, encoding)` and `decode(value, encoding)` convert
+//! between bytes and text in one of the [`Encoding`]s, where encoding goes from the text to the
+//! bytes for utf8 and from the bytes to the text for hex and base64, like it does everywhere else.
+
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::str::FromStr;
+
+/// A way to turn bytes into text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// the text the bytes are the UTF-8 encoding of
+    Utf8,
+    /// two lowercase hex digits per byte
+    Hex,
+    /// the standard base64 alphabet, padded with `=`
+    Base64,
+}
+
+impl FromStr for Encoding {
+    type Err = anyhow::Error;
+
+    fn from_str(encoding: &str) -> Result<Self> {
+        match encoding {
+            "utf8" => Ok(Encoding::Utf8),
+            "hex" => Ok(Encoding::Hex),
+            "base64" => Ok(Encoding::Base64),
+            encoding => Err(anyhow!(
+                "Unknown encoding {}, expected utf8, hex or base64",
+                encoding
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Encoding::Utf8 => write!(f, "utf8"),
+            Encoding::Hex => write!(f, "hex"),
+            Encoding::Base64 => write!(f, "base64"),
+        }
+    }
+}
+
+impl Encoding {
+    /// the text of bytes in the encoding, which fails for bytes which are not UTF-8
+    pub fn to_text(self, bytes: &[u8]) -> Result<String> {
+        match self {
+            Encoding::Utf8 => String::from_utf8(bytes.to_vec()).map_err(|err| {
+                anyhow!(
+                    "the bytes are not valid utf8 from byte {}",
+                    err.utf8_error().valid_up_to()
+                )
+            }),
+            Encoding::Hex => Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect()),
+            Encoding::Base64 => Ok(to_base64(bytes)),
+        }
+    }
+
+    /// the bytes of text in the encoding
+    pub fn to_bytes(self, text: &str) -> Result<Vec<u8>> {
+        match self {
+            Encoding::Utf8 => Ok(text.as_bytes().to_vec()),
+            Encoding::Hex => from_hex(text),
+            Encoding::Base64 => from_base64(text),
+        }
+    }
+}
+
+fn from_hex(text: &str) -> Result<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return Err(anyhow!(
+            "hex needs two digits per byte, got {} digits",
+            text.len()
+        ));
+    }
+    text.as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| anyhow!("{} are not hex digits", String::from_utf8_lossy(pair)))
+        })
+        .collect()
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn to_base64(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, byte)| {
+            group | (*byte as u32) << (16 - 8 * i)
+        });
+        // a chunk of n bytes fills n + 1 digits, the rest is padding
+        for i in 0..4 {
+            if i <= chunk.len() {
+                text.push(BASE64[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+fn from_base64(text: &str) -> Result<Vec<u8>> {
+    let digits = text.trim_end_matches('=');
+    if !text.len().is_multiple_of(4) || text.len() - digits.len() > 2 {
+        return Err(anyhow!("base64 needs groups of four digits, got {}", text));
+    }
+    let mut bytes = Vec::with_capacity(digits.len() * 3 / 4);
+    for chunk in digits.as_bytes().chunks(4) {
+        let mut group = 0u32;
+        for (i, digit) in chunk.iter().enumerate() {
+            let value = BASE64
+                .iter()
+                .position(|base64| base64 == digit)
+                .ok_or_else(|| anyhow!("{} is not a base64 digit", *digit as char))?;
+            group |= (value as u32) << (18 - 6 * i);
+        }
+        // n digits hold n - 1 whole bytes
+        for i in 0..chunk.len() - 1 {
+            bytes.push((group >> (16 - 8 * i)) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+/// writes bytes the way they are written in a program, like `b"GIF\x00"`
+pub fn write_bytes(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    write!(f, "b\"")?;
+    for byte in bytes {
+        match byte {
+            b'\\' => write!(f, "\\\\")?,
+            // quotes end a literal, so they are escaped as well
+            b' '..=b'~' if *byte != b'"' && *byte != b'\'' => write!(f, "{}", *byte as char)?,
+            byte => write!(f, "\\x{:02x}", byte)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    /// tests that bytes survive a round trip through every encoding
+    fn encoding_test() {
+        let bytes = b"\x00hi\xff\x10";
+        for encoding in [Encoding::Hex, Encoding::Base64] {
+            let text = encoding.to_text(bytes).unwrap();
+            assert_eq!(encoding.to_bytes(&text).unwrap(), bytes);
+        }
+        assert_eq!(Encoding::Hex.to_text(bytes).unwrap(), "006869ff10");
+        assert_eq!(Encoding::Hex.to_bytes("FF").unwrap(), [255]);
+        assert!(Encoding::Hex.to_bytes("abc").is_err());
+        assert!(Encoding::Hex.to_bytes("zz").is_err());
+
+        // the examples of RFC 4648
+        let base64 = [
+            "", "Zg==", "Zm8=", "Zm9v", "Zm9vYg==", "Zm9vYmE=", "Zm9vYmFy",
+        ];
+        for (length, text) in base64.iter().enumerate() {
+            assert_eq!(
+                Encoding::Base64.to_text(&b"foobar"[..length]).unwrap(),
+                *text
+            );
+            assert_eq!(
+                Encoding::Base64.to_bytes(text).unwrap(),
+                &b"foobar"[..length]
+            );
+        }
+        assert!(Encoding::Base64.to_bytes("Zm9").is_err());
+        assert!(Encoding::Base64.to_bytes("Zm9*").is_err());
+
+        assert_eq!(Encoding::Utf8.to_bytes("é").unwrap(), [0xc3, 0xa9]);
+        assert_eq!(Encoding::Utf8.to_text(&[0xc3, 0xa9]).unwrap(), "é");
+        assert_eq!(
+            Encoding::Utf8
+                .to_text(&[b'a', 0xff])
+                .unwrap_err()
+                .to_string(),
+            "the bytes are not valid utf8 from byte 1"
+        );
+        assert!("latin1".parse::<Encoding>().is_err());
+    }
+}
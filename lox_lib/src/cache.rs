@@ -0,0 +1,946 @@
+//!
+//! This module contains the parse cache, which keeps the syntax trees of scripts on disk so that
+//! running an unchanged script again skips lexing and parsing it. `lox` caches the scripts it runs
+//! in the user cache directory unless it is given `--no-cache`, and `--stats` prints how often the
+//! cache was hit.
+//!
+//! Entries are keyed by a hash of the source and of everything else the syntax tree depends on: the
+//! version of lox, the options of the lexer and parser and the defines. An entry also holds the
+//! source it was parsed from, which is compared before the entry is used, so a collision of hashes
+//! can not run the wrong program. Entries which can't be read are treated like missing ones and
+//! written again.
+
+use crate::ast::{ClassDecl, ExprNode, FunctionDecl, Literal, Operator, StmtNode};
+use crate::lexer::Comment;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// the first bytes of every entry, followed by the version of the format
+const MAGIC: &[u8; 4] = b"LOXC";
+const FORMAT_VERSION: u32 = 4;
+
+/// How often the cache was used since it was created.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// the scripts whose syntax tree was read from the cache
+    pub hits: usize,
+    /// the scripts which had to be parsed
+    pub misses: usize,
+    /// the syntax trees written to the cache
+    pub writes: usize,
+}
+
+impl fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "parse cache: {} hits, {} misses, {} writes",
+            self.hits, self.misses, self.writes
+        )
+    }
+}
+
+/// What the front end made of a script, which is what the cache keeps.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parsed {
+    pub statements: Vec<StmtNode>,
+    /// the defines once the script is parsed, including the ones it declares itself
+    pub defines: HashMap<String, Literal>,
+}
+
+/// A directory of cached syntax trees.
+#[derive(Debug, Clone)]
+pub struct ParseCache {
+    dir: PathBuf,
+    stats: CacheStats,
+}
+
+impl ParseCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        ParseCache {
+            dir: dir.into(),
+            stats: CacheStats::default(),
+        }
+    }
+
+    /// the cache in the cache directory of the user, which is `$XDG_CACHE_HOME/lox`,
+    /// `~/.cache/lox` or `%LOCALAPPDATA%\lox`, or None if none of them is set
+    pub fn in_user_dir() -> Option<Self> {
+        let var = |name: &str| std::env::var_os(name).filter(|value| !value.is_empty());
+        let dir = var("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| var("HOME").map(|home| Path::new(&home).join(".cache")))
+            .or_else(|| var("LOCALAPPDATA").map(PathBuf::from))?;
+        Some(ParseCache::new(dir.join("lox")))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    /// the syntax tree of a source parsed with options, if it is in the cache
+    pub fn get(&mut self, source: &str, options: &str) -> Option<Parsed> {
+        let parsed = fs::read(self.path(source, options))
+            .ok()
+            .and_then(|entry| decode(&entry, source));
+        match parsed {
+            Some(_) => self.stats.hits += 1,
+            None => self.stats.misses += 1,
+        }
+        parsed
+    }
+
+    /// keeps the syntax tree of a source parsed with options. Failing to write it only means it is
+    /// parsed again next time, so errors are ignored, as are trees holding values which can't be
+    /// written.
+    pub fn put(
+        &mut self,
+        source: &str,
+        options: &str,
+        statements: &[StmtNode],
+        defines: &HashMap<String, Literal>,
+    ) {
+        let Some(entry) = encode(source, statements, defines) else {
+            return;
+        };
+        let path = self.path(source, options);
+        // writing to a temporary file first means a crash never leaves half an entry behind
+        let temporary = path.with_extension(format!("{}.tmp", std::process::id()));
+        let written = fs::create_dir_all(&self.dir)
+            .and_then(|_| fs::write(&temporary, entry))
+            .and_then(|_| fs::rename(&temporary, &path));
+        if written.is_ok() {
+            self.stats.writes += 1;
+        } else {
+            let _ = fs::remove_file(&temporary);
+        }
+    }
+
+    fn path(&self, source: &str, options: &str) -> PathBuf {
+        let mut hash = Fnv::default();
+        hash.write(env!("CARGO_PKG_VERSION").as_bytes());
+        hash.write(&[0]);
+        hash.write(options.as_bytes());
+        hash.write(&[0]);
+        hash.write(source.as_bytes());
+        self.dir.join(format!("{:016x}.ast", hash.0))
+    }
+}
+
+/// The 64 bit FNV-1a hash, which unlike the hasher of the standard library is the same in every
+/// build of lox.
+struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
+
+/// an entry for a source and its syntax tree, or None if the tree holds a value which can't be
+/// written
+fn encode(
+    source: &str,
+    statements: &[StmtNode],
+    defines: &HashMap<String, Literal>,
+) -> Option<Vec<u8>> {
+    let mut writer = Writer::default();
+    writer.0.extend_from_slice(MAGIC);
+    writer.u32(FORMAT_VERSION);
+    writer.str(source);
+    let mut defines: Vec<_> = defines.iter().collect();
+    defines.sort_by_key(|(name, _)| *name);
+    writer.u32(defines.len() as u32);
+    for (name, value) in defines {
+        writer.str(name);
+        writer.literal(value)?;
+    }
+    writer.stmts(statements)?;
+    Some(writer.0)
+}
+
+/// the syntax tree of an entry, or None if the entry is not for the source or can't be read
+fn decode(entry: &[u8], source: &str) -> Option<Parsed> {
+    let mut reader = Reader(entry);
+    if reader.bytes(MAGIC.len())? != MAGIC || reader.u32()? != FORMAT_VERSION {
+        return None;
+    }
+    if reader.str()? != source {
+        return None;
+    }
+    let defines = (0..reader.u32()?)
+        .map(|_| Some((reader.str()?, reader.literal()?)))
+        .collect::<Option<_>>()?;
+    let statements = reader.stmts()?;
+    reader.0.is_empty().then_some(Parsed {
+        statements,
+        defines,
+    })
+}
+
+/// Writes syntax trees as bytes, where every node is a tag followed by its fields. Numbers are
+/// little endian and strings and lists are prefixed with their length.
+#[derive(Default)]
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn str(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.0.extend_from_slice(value.as_bytes());
+    }
+
+    fn strs(&mut self, values: &[String]) {
+        self.u32(values.len() as u32);
+        values.iter().for_each(|value| self.str(value));
+    }
+
+    fn literal(&mut self, literal: &Literal) -> Option<()> {
+        match literal {
+            Literal::Nil => self.u8(0),
+            Literal::Boolean(boolean) => {
+                self.u8(1);
+                self.u8(*boolean as u8);
+            }
+            Literal::Number(number) => {
+                self.u8(2);
+                self.0.extend_from_slice(&number.to_bits().to_le_bytes());
+            }
+            Literal::String(string) => {
+                self.u8(3);
+                self.str(string);
+            }
+            Literal::Bytes(bytes) => {
+                self.u8(4);
+                self.u32(bytes.len() as u32);
+                self.0.extend_from_slice(bytes);
+            }
+            #[cfg(feature = "bigint")]
+            Literal::BigInt(bigint) => {
+                self.u8(5);
+                self.str(&bigint.to_string());
+            }
+            // values which only exist at runtime are never in a syntax tree written by the parser
+            _ => return None,
+        }
+        Some(())
+    }
+
+    fn operator(&mut self, operator: &Operator) {
+        let tag = match operator {
+            Operator::Add { .. } => 0,
+            Operator::Subtract { .. } => 1,
+            Operator::Multiply { .. } => 2,
+            Operator::Divide { .. } => 3,
+            Operator::GreaterThan { .. } => 4,
+            Operator::GreaterEqual { .. } => 5,
+            Operator::LessThan { .. } => 6,
+            Operator::LessEqual { .. } => 7,
+            Operator::Equal { .. } => 8,
+            Operator::EqualEqual { .. } => 9,
+            Operator::NotEqual { .. } => 10,
+            Operator::And { .. } => 11,
+            Operator::Or { .. } => 12,
+            Operator::Bang { .. } => 13,
+        };
+        self.u8(tag);
+        self.u32(operator.line());
+    }
+
+    fn comments(&mut self, comments: &[Comment]) {
+        self.u32(comments.len() as u32);
+        for comment in comments {
+            self.str(&comment.text);
+            self.u32(comment.line);
+        }
+    }
+
+    fn stmts(&mut self, statements: &[StmtNode]) -> Option<()> {
+        self.u32(statements.len() as u32);
+        statements
+            .iter()
+            .try_for_each(|statement| self.stmt(statement))
+    }
+
+    fn stmt(&mut self, statement: &StmtNode) -> Option<()> {
+        match statement {
+            StmtNode::PrintStmt(expr) => {
+                self.u8(0);
+                self.expr(expr)?;
+            }
+            StmtNode::ExprStmt(expr) => {
+                self.u8(1);
+                self.expr(expr)?;
+            }
+            StmtNode::ErrStmt(message) => {
+                self.u8(2);
+                self.str(message);
+            }
+            StmtNode::VarStmt { name, initializer } => {
+                self.u8(3);
+                self.str(name);
+                self.optional_expr(initializer.as_ref())?;
+            }
+            StmtNode::ConstStmt { name, initializer } => {
+                self.u8(16);
+                self.str(name);
+                self.expr(initializer)?;
+            }
+            StmtNode::ImportStmt {
+                path,
+                name,
+                names,
+                line,
+            } => {
+                self.u8(17);
+                self.str(path);
+                self.optional_str(name.as_deref());
+                self.strs(names);
+                self.u32(*line);
+            }
+            StmtNode::ExportStmt { statement, line } => {
+                self.u8(18);
+                self.stmt(statement)?;
+                self.u32(*line);
+            }
+            StmtNode::BlockStmt(statements) => {
+                self.u8(4);
+                self.stmts(statements)?;
+            }
+            StmtNode::IfStmt {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.u8(5);
+                self.expr(condition)?;
+                self.stmt(then_branch)?;
+                self.u8(else_branch.is_some() as u8);
+                if let Some(else_branch) = else_branch {
+                    self.stmt(else_branch)?;
+                }
+            }
+            StmtNode::WhileStmt {
+                condition,
+                body,
+                increment,
+            } => {
+                self.u8(6);
+                self.expr(condition)?;
+                self.stmt(body)?;
+                self.optional_expr(increment.as_ref())?;
+            }
+            StmtNode::BreakStmt { line } => {
+                self.u8(7);
+                self.u32(*line);
+            }
+            StmtNode::ContinueStmt { line } => {
+                self.u8(8);
+                self.u32(*line);
+            }
+            StmtNode::FunctionStmt(function) => {
+                self.u8(9);
+                self.function(function)?;
+            }
+            StmtNode::ClassStmt(class) => {
+                self.u8(10);
+                self.str(&class.name);
+                self.optional_expr(class.superclass.as_ref())?;
+                self.u32(class.methods.len() as u32);
+                class
+                    .methods
+                    .iter()
+                    .try_for_each(|method| self.function(method))?;
+                self.u32(class.class_methods.len() as u32);
+                class
+                    .class_methods
+                    .iter()
+                    .try_for_each(|method| self.function(method))?;
+                self.u32(class.line);
+                self.optional_str(class.doc.as_deref());
+            }
+            StmtNode::ReturnStmt { value, line } => {
+                self.u8(11);
+                self.optional_expr(value.as_ref())?;
+                self.u32(*line);
+            }
+            StmtNode::Commented {
+                leading,
+                statement,
+                trailing,
+            } => {
+                self.u8(12);
+                self.comments(leading);
+                self.stmt(statement)?;
+                self.comments(trailing);
+            }
+            StmtNode::DoWhileStmt { body, condition } => {
+                self.u8(13);
+                self.stmt(body)?;
+                self.expr(condition)?;
+            }
+            StmtNode::ThrowStmt { value, line } => {
+                self.u8(14);
+                self.expr(value)?;
+                self.u32(*line);
+            }
+            StmtNode::TryStmt {
+                body,
+                name,
+                handler,
+            } => {
+                self.u8(15);
+                self.stmts(body)?;
+                self.str(name);
+                self.stmts(handler)?;
+            }
+        }
+        Some(())
+    }
+
+    fn function(&mut self, function: &FunctionDecl) -> Option<()> {
+        self.str(&function.name);
+        self.strs(&function.params);
+        self.optional_str(function.rest.as_deref());
+        self.stmts(&function.body)?;
+        self.u32(function.line);
+        self.optional_str(function.doc.as_deref());
+        Some(())
+    }
+
+    fn optional_str(&mut self, value: Option<&str>) {
+        self.u8(value.is_some() as u8);
+        if let Some(value) = value {
+            self.str(value);
+        }
+    }
+
+    fn optional_expr(&mut self, expr: Option<&ExprNode>) -> Option<()> {
+        self.u8(expr.is_some() as u8);
+        expr.map_or(Some(()), |expr| self.expr(expr))
+    }
+
+    fn exprs(&mut self, exprs: &[ExprNode]) -> Option<()> {
+        self.u32(exprs.len() as u32);
+        exprs.iter().try_for_each(|expr| self.expr(expr))
+    }
+
+    fn expr(&mut self, expr: &ExprNode) -> Option<()> {
+        match expr {
+            ExprNode::Literal(literal) => {
+                self.u8(0);
+                self.literal(literal)?;
+            }
+            ExprNode::Grouping(expr) => {
+                self.u8(1);
+                self.expr(expr)?;
+            }
+            ExprNode::UnaryExpr { operator, right } => {
+                self.u8(2);
+                self.operator(operator);
+                self.expr(right)?;
+            }
+            ExprNode::BinaryExpr {
+                left,
+                operator,
+                right,
+            } => {
+                self.u8(3);
+                self.expr(left)?;
+                self.operator(operator);
+                self.expr(right)?;
+            }
+            ExprNode::Logical {
+                left,
+                operator,
+                right,
+            } => {
+                self.u8(4);
+                self.expr(left)?;
+                self.operator(operator);
+                self.expr(right)?;
+            }
+            ExprNode::Variable { name, line } => {
+                self.u8(5);
+                self.str(name);
+                self.u32(*line);
+            }
+            ExprNode::Assign { name, value, line } => {
+                self.u8(6);
+                self.str(name);
+                self.expr(value)?;
+                self.u32(*line);
+            }
+            ExprNode::Call {
+                callee,
+                arguments,
+                line,
+            } => {
+                self.u8(7);
+                self.expr(callee)?;
+                self.exprs(arguments)?;
+                self.u32(*line);
+            }
+            ExprNode::Get { object, name, line } => {
+                self.u8(8);
+                self.expr(object)?;
+                self.str(name);
+                self.u32(*line);
+            }
+            ExprNode::Set {
+                object,
+                name,
+                value,
+                line,
+            } => {
+                self.u8(9);
+                self.expr(object)?;
+                self.str(name);
+                self.expr(value)?;
+                self.u32(*line);
+            }
+            ExprNode::List { elements, line } => {
+                self.u8(10);
+                self.exprs(elements)?;
+                self.u32(*line);
+            }
+            ExprNode::Map { entries, line } => {
+                self.u8(11);
+                self.u32(entries.len() as u32);
+                for (key, value) in entries {
+                    self.expr(key)?;
+                    self.expr(value)?;
+                }
+                self.u32(*line);
+            }
+            ExprNode::Index {
+                object,
+                index,
+                line,
+            } => {
+                self.u8(12);
+                self.expr(object)?;
+                self.expr(index)?;
+                self.u32(*line);
+            }
+            ExprNode::Slice {
+                object,
+                start,
+                end,
+                line,
+            } => {
+                self.u8(13);
+                self.expr(object)?;
+                self.optional_expr(start.as_deref())?;
+                self.optional_expr(end.as_deref())?;
+                self.u32(*line);
+            }
+            ExprNode::SetIndex {
+                object,
+                index,
+                value,
+                line,
+            } => {
+                self.u8(14);
+                self.expr(object)?;
+                self.expr(index)?;
+                self.expr(value)?;
+                self.u32(*line);
+            }
+            ExprNode::This { line } => {
+                self.u8(15);
+                self.u32(*line);
+            }
+            ExprNode::Super { method, line } => {
+                self.u8(16);
+                self.str(method);
+                self.u32(*line);
+            }
+        }
+        Some(())
+    }
+}
+
+/// Reads the syntax trees written by a [`Writer`], where every read is None once the bytes run out
+/// or don't make sense.
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn bytes(&mut self, length: usize) -> Option<&[u8]> {
+        if length > self.0.len() {
+            return None;
+        }
+        let (bytes, rest) = self.0.split_at(length);
+        self.0 = rest;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.bytes(1)?[0])
+    }
+
+    fn bool(&mut self) -> Option<bool> {
+        match self.u8()? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.bytes(4)?.try_into().ok()?))
+    }
+
+    fn str(&mut self) -> Option<String> {
+        let length = self.u32()? as usize;
+        String::from_utf8(self.bytes(length)?.to_vec()).ok()
+    }
+
+    /// reads a length and then that many items
+    fn list<T>(&mut self, mut item: impl FnMut(&mut Self) -> Option<T>) -> Option<Vec<T>> {
+        let length = self.u32()?;
+        // the length is not trusted for the capacity, every item takes at least a byte
+        let mut items = Vec::with_capacity((length as usize).min(self.0.len()));
+        for _ in 0..length {
+            items.push(item(self)?);
+        }
+        Some(items)
+    }
+
+    fn literal(&mut self) -> Option<Literal> {
+        Some(match self.u8()? {
+            0 => Literal::Nil,
+            1 => Literal::Boolean(self.bool()?),
+            2 => Literal::Number(f64::from_bits(u64::from_le_bytes(
+                self.bytes(8)?.try_into().ok()?,
+            ))),
+            3 => Literal::String(self.str()?),
+            4 => {
+                let length = self.u32()? as usize;
+                Literal::Bytes(self.bytes(length)?.into())
+            }
+            #[cfg(feature = "bigint")]
+            5 => Literal::BigInt(self.str()?.parse().ok()?),
+            _ => return None,
+        })
+    }
+
+    fn operator(&mut self) -> Option<Operator> {
+        let tag = self.u8()?;
+        let line = self.u32()?;
+        Some(match tag {
+            0 => Operator::Add { line },
+            1 => Operator::Subtract { line },
+            2 => Operator::Multiply { line },
+            3 => Operator::Divide { line },
+            4 => Operator::GreaterThan { line },
+            5 => Operator::GreaterEqual { line },
+            6 => Operator::LessThan { line },
+            7 => Operator::LessEqual { line },
+            8 => Operator::Equal { line },
+            9 => Operator::EqualEqual { line },
+            10 => Operator::NotEqual { line },
+            11 => Operator::And { line },
+            12 => Operator::Or { line },
+            13 => Operator::Bang { line },
+            _ => return None,
+        })
+    }
+
+    fn comments(&mut self) -> Option<Vec<Comment>> {
+        self.list(|reader| {
+            Some(Comment {
+                text: reader.str()?,
+                line: reader.u32()?,
+            })
+        })
+    }
+
+    fn stmts(&mut self) -> Option<Vec<StmtNode>> {
+        self.list(Reader::stmt)
+    }
+
+    fn stmt(&mut self) -> Option<StmtNode> {
+        Some(match self.u8()? {
+            0 => StmtNode::PrintStmt(self.expr()?),
+            1 => StmtNode::ExprStmt(self.expr()?),
+            2 => StmtNode::ErrStmt(self.str()?),
+            3 => StmtNode::VarStmt {
+                name: self.str()?,
+                initializer: self.optional_expr()?,
+            },
+            4 => StmtNode::BlockStmt(self.stmts()?),
+            5 => StmtNode::IfStmt {
+                condition: self.expr()?,
+                then_branch: Box::new(self.stmt()?),
+                else_branch: match self.bool()? {
+                    true => Some(Box::new(self.stmt()?)),
+                    false => None,
+                },
+            },
+            6 => StmtNode::WhileStmt {
+                condition: self.expr()?,
+                body: Box::new(self.stmt()?),
+                increment: self.optional_expr()?,
+            },
+            7 => StmtNode::BreakStmt { line: self.u32()? },
+            8 => StmtNode::ContinueStmt { line: self.u32()? },
+            9 => StmtNode::FunctionStmt(Rc::new(self.function()?)),
+            10 => StmtNode::ClassStmt(Rc::new(ClassDecl {
+                name: self.str()?,
+                superclass: self.optional_expr()?,
+                methods: self.list(|reader| reader.function().map(Rc::new))?,
+                class_methods: self.list(|reader| reader.function().map(Rc::new))?,
+                line: self.u32()?,
+                doc: self.optional_str()?,
+            })),
+            11 => StmtNode::ReturnStmt {
+                value: self.optional_expr()?,
+                line: self.u32()?,
+            },
+            12 => StmtNode::Commented {
+                leading: self.comments()?,
+                statement: Box::new(self.stmt()?),
+                trailing: self.comments()?,
+            },
+            13 => StmtNode::DoWhileStmt {
+                body: Box::new(self.stmt()?),
+                condition: self.expr()?,
+            },
+            14 => StmtNode::ThrowStmt {
+                value: self.expr()?,
+                line: self.u32()?,
+            },
+            15 => StmtNode::TryStmt {
+                body: self.stmts()?,
+                name: self.str()?,
+                handler: self.stmts()?,
+            },
+            16 => StmtNode::ConstStmt {
+                name: self.str()?,
+                initializer: self.expr()?,
+            },
+            17 => StmtNode::ImportStmt {
+                path: self.str()?,
+                name: self.optional_str()?,
+                names: self.list(Reader::str)?,
+                line: self.u32()?,
+            },
+            18 => StmtNode::ExportStmt {
+                statement: Box::new(self.stmt()?),
+                line: self.u32()?,
+            },
+            _ => return None,
+        })
+    }
+
+    fn function(&mut self) -> Option<FunctionDecl> {
+        Some(FunctionDecl {
+            name: self.str()?,
+            params: self.list(Reader::str)?,
+            rest: self.optional_str()?,
+            body: self.stmts()?,
+            line: self.u32()?,
+            doc: self.optional_str()?,
+        })
+    }
+
+    fn optional_str(&mut self) -> Option<Option<String>> {
+        match self.bool()? {
+            true => Some(Some(self.str()?)),
+            false => Some(None),
+        }
+    }
+
+    fn optional_expr(&mut self) -> Option<Option<ExprNode>> {
+        match self.bool()? {
+            true => Some(Some(self.expr()?)),
+            false => Some(None),
+        }
+    }
+
+    fn boxed(&mut self) -> Option<Box<ExprNode>> {
+        self.expr().map(Box::new)
+    }
+
+    fn expr(&mut self) -> Option<ExprNode> {
+        Some(match self.u8()? {
+            0 => ExprNode::Literal(self.literal()?),
+            1 => ExprNode::Grouping(self.boxed()?),
+            2 => ExprNode::UnaryExpr {
+                operator: self.operator()?,
+                right: self.boxed()?,
+            },
+            3 => ExprNode::BinaryExpr {
+                left: self.boxed()?,
+                operator: self.operator()?,
+                right: self.boxed()?,
+            },
+            4 => ExprNode::Logical {
+                left: self.boxed()?,
+                operator: self.operator()?,
+                right: self.boxed()?,
+            },
+            5 => ExprNode::Variable {
+                name: self.str()?,
+                line: self.u32()?,
+            },
+            6 => ExprNode::Assign {
+                name: self.str()?,
+                value: self.boxed()?,
+                line: self.u32()?,
+            },
+            7 => ExprNode::Call {
+                callee: self.boxed()?,
+                arguments: self.list(Reader::expr)?,
+                line: self.u32()?,
+            },
+            8 => ExprNode::Get {
+                object: self.boxed()?,
+                name: self.str()?,
+                line: self.u32()?,
+            },
+            9 => ExprNode::Set {
+                object: self.boxed()?,
+                name: self.str()?,
+                value: self.boxed()?,
+                line: self.u32()?,
+            },
+            10 => ExprNode::List {
+                elements: self.list(Reader::expr)?,
+                line: self.u32()?,
+            },
+            11 => ExprNode::Map {
+                entries: self.list(|reader| Some((reader.expr()?, reader.expr()?)))?,
+                line: self.u32()?,
+            },
+            12 => ExprNode::Index {
+                object: self.boxed()?,
+                index: self.boxed()?,
+                line: self.u32()?,
+            },
+            13 => ExprNode::Slice {
+                object: self.boxed()?,
+                start: self.optional_expr()?.map(Box::new),
+                end: self.optional_expr()?.map(Box::new),
+                line: self.u32()?,
+            },
+            14 => ExprNode::SetIndex {
+                object: self.boxed()?,
+                index: self.boxed()?,
+                value: self.boxed()?,
+                line: self.u32()?,
+            },
+            15 => ExprNode::This { line: self.u32()? },
+            16 => ExprNode::Super {
+                method: self.str()?,
+                line: self.u32()?,
+            },
+            _ => return None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    /// the syntax tree of a source as the interpreter parses it
+    fn parse(source: &str) -> Parsed {
+        let tokens = Lexer::new().lex(source).unwrap();
+        let mut parser = Parser::new();
+        let statements = parser.parse(tokens);
+        Parsed {
+            statements,
+            defines: parser.defines().clone(),
+        }
+    }
+
+    #[test]
+    /// tests that every kind of node survives a round trip through an entry
+    fn codec_test() {
+        let source = "define LIMIT 3;\nvar xs = [1, \"two\", nil, true, b\"\\x00\"];\n\
+            var m = {\"a\": -1};\nxs[0] = m[\"a\"] + xs[1:][0:LIMIT].len;\n\
+            fun f(a, b) { if (!a or b) return a; else { while (a < b) { a = a * 2; if (a > 9) break; else continue; } } return; }\n\
+            class A { init() { this.x = 1; } class make() { return A(); } }\n\
+            class B < A { init() { super.init(); this.x = this.x / 2; } }\n\
+            for (var i = 0; i != 2; i = i - 1) print f(i, 2) == 1 and i >= 0 or i <= 0;\ndo xs = nil; while (false);\ntry { throw xs; } catch (e) { print e; }\nconst c = 1;\nimport \"lib.lox\";\nimport lib;\n\
+            print (1);\nprint 1 +;";
+        let parsed = parse(source);
+        assert!(matches!(
+            parsed.statements.last(),
+            Some(StmtNode::ErrStmt(_))
+        ));
+        let entry = encode(source, &parsed.statements, &parsed.defines).unwrap();
+        assert_eq!(decode(&entry, source), Some(parsed));
+
+        // an entry is only used for the source it was written for
+        assert_eq!(decode(&entry, "print 2;"), None);
+        assert_eq!(decode(&entry[..entry.len() - 1], source), None);
+        assert_eq!(decode(b"LOXC", source), None);
+
+        // runtime values can not be written
+        let mut parsed = parse("print 1;");
+        parsed
+            .defines
+            .insert("list".to_string(), Literal::List(Default::default()));
+        assert_eq!(
+            encode("print 1;", &parsed.statements, &parsed.defines),
+            None
+        );
+    }
+
+    #[test]
+    /// tests that a cached syntax tree is found again for the same source and options only
+    fn cache_test() {
+        let dir = std::env::temp_dir().join(format!("lox-cache-test-{}", std::process::id()));
+        let mut cache = ParseCache::new(&dir);
+        let parsed = parse("var a = 1;\nprint a;");
+
+        assert_eq!(cache.get("var a = 1;\nprint a;", ""), None);
+        cache.put(
+            "var a = 1;\nprint a;",
+            "",
+            &parsed.statements,
+            &parsed.defines,
+        );
+        assert_eq!(cache.get("var a = 1;\nprint a;", ""), Some(parsed));
+        assert_eq!(
+            cache.get("var a = 1;\nprint a;", "automatic semicolons"),
+            None
+        );
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 1,
+                misses: 2,
+                writes: 1
+            }
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,41 @@
+//!
+//! This module contains the reads of the clock of the host, which only builds with the `host`
+//! feature have. Builds without it, e.g. for wasm where reading the clock panics, still time the
+//! stages of the [`crate::pipeline::Pipeline`], but every stopwatch reads zero and the time of day
+//! is unknown.
+
+use std::time::Duration;
+#[cfg(feature = "host")]
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// measures how long something takes from when it was started
+#[derive(Debug, Clone, Copy)]
+pub struct Stopwatch {
+    #[cfg(feature = "host")]
+    start: Instant,
+}
+
+impl Stopwatch {
+    pub fn start() -> Self {
+        Stopwatch {
+            #[cfg(feature = "host")]
+            start: Instant::now(),
+        }
+    }
+
+    /// the time since the stopwatch was started, or zero without a clock
+    pub fn elapsed(&self) -> Duration {
+        #[cfg(feature = "host")]
+        return self.start.elapsed();
+        #[cfg(not(feature = "host"))]
+        Duration::ZERO
+    }
+}
+
+/// the time since the start of 1970, or None without a clock or if the clock is set before then
+pub fn since_epoch() -> Option<Duration> {
+    #[cfg(feature = "host")]
+    return SystemTime::now().duration_since(UNIX_EPOCH).ok();
+    #[cfg(not(feature = "host"))]
+    None
+}
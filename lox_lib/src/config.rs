@@ -0,0 +1,267 @@
+//!
+//! This module contains the project config, a `lox.toml` file which lets a project keep the options
+//! it is run with next to its code instead of on the command line:
+//!
+//! ```text
+//! # the script `lox` runs when it isn't given one
+//! entry = "src/main.lox"
+//! # where scripts are looked for, relative to the project
+//! paths = ["src", "vendor"]
+//! # behave like jlox: stop at the first error instead of carrying on with the next statement, and
+//! # only add strings to other strings
+//! strict = true
+//! # the plugins to enable, out of the ones lox was built with
+//! plugins = ["http"]
+//! # do arithmetic in decimal, so that 0.1 + 0.2 == 0.3, instead of binary floating point
+//! numbers = "decimal"
+//!
+//! # compile time constants, like --define NAME=VALUE
+//! [defines]
+//! DEBUG = true
+//!
+//! # the level of a lint by its code: allow, warn or deny
+//! [lints]
+//! L0002 = "deny"
+//! ```
+//!
+//! The file is a small subset of TOML, with one `key = value` per line, and blank lines and lines
+//! starting with `#` ignored.
+
+use crate::ast::Literal;
+use crate::interpreter::Interpreter;
+use crate::loader::SearchPathLoader;
+use crate::messages::{LintLevel, MessageId};
+use crate::number::NumberMode;
+use crate::parser::parse_define;
+use crate::plugin::{self, LoxPlugin};
+use anyhow::{anyhow, Context, Result};
+use std::path::{Path, PathBuf};
+
+/// The name of the project config, which is looked for in the directory `lox` runs in and its
+/// parents.
+pub const CONFIG_FILE: &str = "lox.toml";
+
+/// The options of a project.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProjectConfig {
+    /// the directory of the config, which the paths in it are relative to
+    pub root: PathBuf,
+    pub entry: Option<PathBuf>,
+    pub paths: Vec<PathBuf>,
+    pub strict: bool,
+    pub plugins: Vec<String>,
+    pub numbers: NumberMode,
+    pub defines: Vec<(String, Literal)>,
+    pub lints: Vec<(MessageId, LintLevel)>,
+}
+
+/// the sections of the config
+enum Section {
+    Project,
+    Defines,
+    Lints,
+}
+
+/// strips the double quotes around a string value
+fn unquote(value: &str) -> Result<&str> {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .ok_or_else(|| anyhow!("expected a quoted string but found {}", value))
+}
+
+/// the strings of a list value, e.g. `["a", "b"]`
+fn strings(value: &str) -> Result<Vec<&str>> {
+    value
+        .strip_prefix('[')
+        .and_then(|value| value.strip_suffix(']'))
+        .ok_or_else(|| anyhow!("expected a list of strings but found {}", value))?
+        .split(',')
+        .map(str::trim)
+        .filter(|string| !string.is_empty())
+        .map(unquote)
+        .collect()
+}
+
+impl ProjectConfig {
+    /// reads the config of the project in the given directory.
+    pub fn load(dir: &Path) -> Result<Self> {
+        let path = dir.join(CONFIG_FILE);
+        let source = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read in file from {}", path.display()))?;
+        let config = ProjectConfig::parse(&source)
+            .with_context(|| format!("Invalid project config {}", path.display()))?;
+        Ok(Self {
+            root: dir.to_path_buf(),
+            ..config
+        })
+    }
+
+    /// reads the config of the project the directory is in, looking for it in the directory and then
+    /// in each of its parents. Returns None outside of a project.
+    pub fn discover(dir: &Path) -> Result<Option<Self>> {
+        match dir.ancestors().find(|dir| dir.join(CONFIG_FILE).is_file()) {
+            Some(root) => ProjectConfig::load(root).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// parses a config, whose root is the current directory.
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut config = ProjectConfig::default();
+        let mut section = Section::Project;
+        for (i, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match line {
+                "[defines]" => section = Section::Defines,
+                "[lints]" => section = Section::Lints,
+                line if line.starts_with('[') => {
+                    return Err(anyhow!("Unknown section {} in line {}", line, i + 1))
+                }
+                line => {
+                    let (key, value) = line
+                        .split_once('=')
+                        .ok_or_else(|| anyhow!("Expected key = value in line {}", i + 1))?;
+                    config
+                        .set(&section, key.trim(), value.trim())
+                        .map_err(|err| anyhow!("{} in line {}", err, i + 1))?;
+                }
+            }
+        }
+        Ok(config)
+    }
+
+    fn set(&mut self, section: &Section, key: &str, value: &str) -> Result<()> {
+        match (section, key) {
+            (Section::Project, "entry") => self.entry = Some(PathBuf::from(unquote(value)?)),
+            (Section::Project, "paths") => {
+                self.paths = strings(value)?.into_iter().map(PathBuf::from).collect()
+            }
+            (Section::Project, "plugins") => {
+                self.plugins = strings(value)?.into_iter().map(String::from).collect()
+            }
+            (Section::Project, "strict") => {
+                self.strict = value
+                    .parse()
+                    .map_err(|_| anyhow!("strict must be true or false"))?
+            }
+            (Section::Project, "numbers") => self.numbers = unquote(value)?.parse()?,
+            (Section::Project, key) => return Err(anyhow!("Unknown key {}", key)),
+            (Section::Defines, name) => self
+                .defines
+                .push(parse_define(&format!("{}={}", name, value))?),
+            (Section::Lints, code) => {
+                let id = MessageId::lint(code).ok_or_else(|| anyhow!("Unknown lint {}", code))?;
+                self.lints.push((id, unquote(value)?.parse()?));
+            }
+        }
+        Ok(())
+    }
+
+    /// the entry point, relative to the directory `lox` runs in
+    pub fn entry(&self) -> Option<PathBuf> {
+        self.entry.as_ref().map(|entry| self.root.join(entry))
+    }
+
+    /// configures the interpreter with the options of the project, enabling its plugins out of the
+    /// ones available. Options given on the command line afterwards take precedence.
+    pub fn apply(
+        &self,
+        mut interpreter: Interpreter,
+        plugins: &[&dyn LoxPlugin],
+    ) -> Result<Interpreter> {
+        if !self.paths.is_empty() {
+            let paths = self.paths.iter().map(|path| self.root.join(path)).collect();
+            interpreter = interpreter.with_module_loader(Box::new(SearchPathLoader::new(paths)));
+        }
+        for (name, value) in &self.defines {
+            interpreter = interpreter.with_define(name.clone(), value.clone());
+        }
+        for (id, level) in &self.lints {
+            interpreter = interpreter.with_lint_level(*id, *level);
+        }
+        for name in &self.plugins {
+            interpreter = interpreter.with_plugin(plugin::find(plugins, name)?)?;
+        }
+        Ok(interpreter
+            .with_number_mode(self.numbers)
+            .with_continue_on_error(!self.strict)
+            .with_string_concatenation(!self.strict))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn config_test() {
+        let config = ProjectConfig::parse(
+            "# a project\nentry = \"src/main.lox\"\npaths = [\"src\", \"vendor\"]\nstrict = true\n\
+            numbers = \"decimal\"\n\n\
+            [defines]\nDEBUG = true\nNAME = \"lox\"\n\n[lints]\nL0002 = \"deny\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            ProjectConfig {
+                root: PathBuf::new(),
+                entry: Some(PathBuf::from("src/main.lox")),
+                paths: vec![PathBuf::from("src"), PathBuf::from("vendor")],
+                strict: true,
+                plugins: Vec::new(),
+                numbers: NumberMode::Decimal,
+                defines: vec![
+                    ("DEBUG".to_string(), Literal::Boolean(true)),
+                    ("NAME".to_string(), Literal::String("lox".to_string())),
+                ],
+                lints: vec![(MessageId::SingleQuotedString, LintLevel::Deny)],
+            }
+        );
+
+        let err =
+            ProjectConfig::parse("entry = \"main.lox\"\n[lints]\nL0002 = \"never\"").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unknown lint level never, expected allow, warn or deny in line 3"
+        );
+        assert_eq!(
+            ProjectConfig::parse("plugins = [\"http\", \"sqlite\"]")
+                .unwrap()
+                .plugins,
+            vec!["http", "sqlite"]
+        );
+        let config = ProjectConfig::parse("plugins = [\"http\"]").unwrap();
+        let err = config.apply(Interpreter::new(), &[]).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Unknown plugin http, the plugins available are: none"
+        );
+        let config = ProjectConfig::parse("strict = true").unwrap();
+        let mut interpreter = config.apply(Interpreter::new(), &[]).unwrap();
+        let result = interpreter.eval("var a = \"a\" + 1;").unwrap();
+        assert_eq!(result.errors.len(), 1);
+        assert!(result.errors[0].contains("[E0004]"), "{:?}", result.errors);
+        assert!(ProjectConfig::parse("[lints]\nE0001 = \"deny\"").is_err());
+        assert!(ProjectConfig::parse("color = \"blue\"").is_err());
+        assert!(ProjectConfig::parse("numbers = \"float\"").is_err());
+        assert!(ProjectConfig::parse("[build]").is_err());
+    }
+
+    #[test]
+    fn discover_test() {
+        let root = std::env::temp_dir().join(format!("lox-config-test-{}", std::process::id()));
+        let nested = root.join("src").join("lib");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(root.join(CONFIG_FILE), "entry = \"src/main.lox\"\n").unwrap();
+        let config = ProjectConfig::discover(&nested);
+        std::fs::remove_dir_all(&root).unwrap();
+
+        let config = config.unwrap().unwrap();
+        assert_eq!(config.root, root);
+        assert_eq!(config.entry(), Some(root.join("src/main.lox")));
+    }
+}
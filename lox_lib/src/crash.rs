@@ -0,0 +1,296 @@
+//!
+//! This module contains the crash reporter, which turns a panic inside the interpreter into a report
+//! file that can be attached to a bug report instead of a bare panic message.
+//!
+//! A report holds the program that was running, the phase it crashed in and the backtrace of the
+//! panic. When minimization is enabled, the statements of the program are also reduced with delta
+//! debugging to a smaller program that still crashes the interpreter.
+
+use crate::lexer::{Token, TokenType};
+#[cfg(feature = "host")]
+use anyhow::{Context, Result};
+use std::backtrace::Backtrace;
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+#[cfg(feature = "host")]
+use std::path::PathBuf;
+use std::sync::Once;
+#[cfg(feature = "host")]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The phase the interpreter was in when it crashed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Lexing,
+    Parsing,
+    Interpreting,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Phase::Lexing => write!(f, "lexing"),
+            Phase::Parsing => write!(f, "parsing"),
+            Phase::Interpreting => write!(f, "interpreting"),
+        }
+    }
+}
+
+/// A panic caught by [`guard`].
+#[derive(Debug, Clone)]
+pub struct Panic {
+    pub message: String,
+    pub backtrace: String,
+}
+
+/// Everything known about a crash, which is written out by a [`CrashReporter`].
+#[derive(Debug, Clone)]
+pub struct CrashReport {
+    /// the path of the script, or None for source typed into the REPL
+    pub path: Option<String>,
+    pub source: String,
+    pub phase: Phase,
+    pub panic: Panic,
+    /// a smaller program which crashes the interpreter as well, if one was looked for
+    pub reproducer: Option<String>,
+}
+
+impl CrashReport {
+    pub fn render(&self) -> String {
+        let mut out = String::from("Lox interpreter crash report\n\n");
+        out.push_str(&format!("phase: {}\n", self.phase));
+        out.push_str(&format!("panic: {}\n", self.panic.message));
+        if let Some(path) = &self.path {
+            out.push_str(&format!("script: {}\n", path));
+        }
+        out.push_str(&format!("\n--- source ---\n{}\n", self.source));
+        if let Some(reproducer) = &self.reproducer {
+            out.push_str(&format!("\n--- minimized reproducer ---\n{}\n", reproducer));
+        }
+        out.push_str(&format!("\n--- backtrace ---\n{}\n", self.panic.backtrace));
+        out
+    }
+}
+
+/// Writes crash reports to files in a directory.
+#[cfg(feature = "host")]
+#[derive(Debug, Clone)]
+pub struct CrashReporter {
+    dir: PathBuf,
+    minimize: bool,
+}
+
+#[cfg(feature = "host")]
+impl CrashReporter {
+    /// creates a reporter which writes its reports to the given directory.
+    pub fn new(dir: PathBuf) -> Self {
+        CrashReporter {
+            dir,
+            minimize: false,
+        }
+    }
+
+    /// also looks for a minimized reproducer of every crash. This reruns the program many times, so
+    /// it is disabled by default.
+    pub fn with_minimization(mut self, minimize: bool) -> Self {
+        self.minimize = minimize;
+        self
+    }
+
+    pub fn minimize(&self) -> bool {
+        self.minimize
+    }
+
+    /// writes the report to a new file in the directory of the reporter, returning its path.
+    pub fn write(&self, report: &CrashReport) -> Result<PathBuf> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_millis())
+            .unwrap_or_default();
+        let path = self.dir.join(format!(
+            "lox-crash-{}-{}.txt",
+            timestamp,
+            std::process::id()
+        ));
+        std::fs::write(&path, report.render())
+            .with_context(|| format!("Failed to write crash report to {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+thread_local! {
+    /// how many calls to guard are running on this thread
+    static GUARDED: Cell<usize> = const { Cell::new(0) };
+    /// the last panic raised inside of guard on this thread
+    static LAST_PANIC: RefCell<Option<Panic>> = const { RefCell::new(None) };
+}
+
+/// installs a panic hook which captures the backtrace of panics raised inside of [`guard`]. Panics
+/// raised anywhere else are passed on to the hook that was installed before.
+fn install_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if GUARDED.with(|guarded| guarded.get()) == 0 {
+                return previous(info);
+            }
+            let payload = info
+                .payload()
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| info.payload().downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            let message = match info.location() {
+                Some(location) => format!("{} at {}", payload, location),
+                None => payload,
+            };
+            let backtrace = Backtrace::force_capture().to_string();
+            LAST_PANIC.with(|last| *last.borrow_mut() = Some(Panic { message, backtrace }));
+        }));
+    });
+}
+
+/// runs f, returning the panic instead of unwinding any further if it panics. The panic is not
+/// printed, it is up to the caller to report it.
+pub fn guard<T>(f: impl FnOnce() -> T) -> std::result::Result<T, Panic> {
+    install_hook();
+    GUARDED.with(|guarded| guarded.set(guarded.get() + 1));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    GUARDED.with(|guarded| guarded.set(guarded.get() - 1));
+
+    result.map_err(|_| {
+        LAST_PANIC
+            .with(|last| last.borrow_mut().take())
+            .unwrap_or_else(|| Panic {
+                message: "unknown panic".to_string(),
+                backtrace: String::new(),
+            })
+    })
+}
+
+/// splits a program into the source of its top level statements, rebuilt from their tokens.
+pub fn statements(tokens: &[Token]) -> Vec<String> {
+    let mut statements = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    let mut depth = 0usize;
+
+    for (i, token) in tokens.iter().enumerate() {
+        if token.token_type == TokenType::Eof {
+            break;
+        }
+        current.push(token.lexeme());
+        let ends_statement = match token.token_type {
+            TokenType::LeftBrace => {
+                depth += 1;
+                false
+            }
+            TokenType::RightBrace => {
+                depth = depth.saturating_sub(1);
+                // an if statement carries on into its else branch
+                depth == 0
+                    && tokens.get(i + 1).map(|next| &next.token_type) != Some(&TokenType::Else)
+            }
+            TokenType::Semicolon => depth == 0,
+            _ => false,
+        };
+        if ends_statement {
+            statements.push(current.join(" "));
+            current.clear();
+        }
+    }
+    if !current.is_empty() {
+        statements.push(current.join(" "));
+    }
+    statements
+}
+
+/// Reduces the statements of a program with delta debugging, keeping only the ones needed for
+/// `crashes` to hold. Chunks of statements are removed for as long as the remaining program still
+/// crashes, halving the size of the chunks whenever none of them can be removed.
+pub fn minimize(
+    mut statements: Vec<String>,
+    mut crashes: impl FnMut(&[String]) -> bool,
+) -> Vec<String> {
+    let mut chunks = 2;
+    while statements.len() >= 2 {
+        let chunk_size = statements.len().div_ceil(chunks);
+        let reduced = (0..statements.len())
+            .step_by(chunk_size)
+            .map(|start| {
+                let end = (start + chunk_size).min(statements.len());
+                [&statements[..start], &statements[end..]].concat()
+            })
+            .find(|complement| !complement.is_empty() && crashes(complement));
+
+        match reduced {
+            Some(complement) => {
+                statements = complement;
+                chunks = (chunks - 1).max(2);
+            }
+            None if chunks >= statements.len() => break,
+            None => chunks = (chunks * 2).min(statements.len()),
+        }
+    }
+    statements
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lexer::Lexer;
+
+    #[test]
+    fn guard_test() {
+        assert_eq!(guard(|| 1).unwrap(), 1);
+
+        let panic = guard(|| -> u32 { panic!("boom") }).unwrap_err();
+        assert!(panic.message.starts_with("boom at "));
+        assert!(!panic.backtrace.is_empty());
+    }
+
+    #[test]
+    fn statements_test() {
+        let tokens = Lexer::new()
+            .lex("var a = \"x\";\nprint a;\n{ print 1; }")
+            .unwrap();
+        assert_eq!(
+            statements(&tokens),
+            vec!["var a = \"x\" ;", "print a ;", "{ print 1 ; }"]
+        );
+    }
+
+    #[test]
+    /// tests that only the statements needed to reproduce the crash are kept
+    fn minimize_test() {
+        let statements = (0..10).map(|i| format!("print {};", i)).collect();
+        let crashes = |program: &[String]| {
+            program.contains(&"print 3;".to_string()) && program.contains(&"print 7;".to_string())
+        };
+        assert_eq!(minimize(statements, crashes), vec!["print 3;", "print 7;"]);
+    }
+
+    #[test]
+    #[cfg(feature = "host")]
+    fn write_report_test() {
+        let report = CrashReport {
+            path: Some("main.lox".to_string()),
+            source: "print 1;".to_string(),
+            phase: Phase::Interpreting,
+            panic: Panic {
+                message: "boom".to_string(),
+                backtrace: "0: main".to_string(),
+            },
+            reproducer: Some("print 1 ;".to_string()),
+        };
+        let path = CrashReporter::new(std::env::temp_dir())
+            .write(&report)
+            .unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(contents, report.render());
+        assert!(contents.contains("phase: interpreting\npanic: boom\nscript: main.lox\n"));
+        assert!(contents.contains("--- minimized reproducer ---\nprint 1 ;\n"));
+    }
+}
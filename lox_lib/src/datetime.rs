@@ -0,0 +1,311 @@
+//!
+//! This module contains date times, the values returned by `now()` and `datetime(seconds)`, so that
+//! scripts processing logs or scheduling work can handle dates without juggling epoch numbers.
+//!
+//! A date time is an instant in UTC with millisecond precision. Adding or subtracting a number of
+//! seconds gives a new date time, so `now() + 60` is a minute from now, and subtracting date times
+//! gives the seconds between them. Date times compare by the instant they stand for. They print in
+//! ISO 8601, like `2024-02-29T13:05:00Z`, and `format(date, pattern)` writes them with the
+//! directives of strftime listed in [`DateTime::format`].
+//!
+//! Dates are computed with the proleptic Gregorian calendar for the same range of about 275,000
+//! years either side of 1970 as dates in JavaScript.
+
+use crate::ast::{Literal, Operator};
+use anyhow::{anyhow, Result};
+use std::fmt;
+#[cfg(feature = "host")]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// the seconds from 1970 to the first and last instants a date time can be, 100,000,000 days
+const LIMIT: f64 = 8.64e12;
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+const WEEKDAYS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// An instant in UTC, as the seconds since the start of 1970.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct DateTime {
+    seconds: f64,
+}
+
+/// the calendar fields of a date time
+struct Fields {
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: i64,
+    minute: i64,
+    second: i64,
+    millisecond: i64,
+    /// from 0 for Sunday to 6 for Saturday
+    weekday: usize,
+    /// from 1 for the first of January
+    day_of_year: i64,
+}
+
+impl DateTime {
+    /// the date time of seconds since the start of 1970, which is rounded to the millisecond, or
+    /// None if it is outside the range of dates
+    pub fn new(seconds: f64) -> Option<DateTime> {
+        let seconds = (seconds * 1000.0).round() / 1000.0;
+        (seconds.abs() <= LIMIT).then_some(DateTime { seconds })
+    }
+
+    #[cfg(feature = "host")]
+    pub fn now() -> DateTime {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs_f64())
+            .unwrap_or_else(|err| -err.duration().as_secs_f64());
+        DateTime::new(seconds).unwrap_or(DateTime { seconds: 0.0 })
+    }
+
+    /// the seconds since the start of 1970
+    pub fn seconds(&self) -> f64 {
+        self.seconds
+    }
+
+    /// the date time a number of seconds later, or earlier for negative seconds
+    pub fn add(&self, seconds: f64) -> Option<DateTime> {
+        DateTime::new(self.seconds + seconds)
+    }
+
+    fn fields(&self) -> Fields {
+        let whole = self.seconds.floor() as i64;
+        let millisecond = ((self.seconds - self.seconds.floor()) * 1000.0).round() as i64;
+        let days = whole.div_euclid(SECONDS_PER_DAY);
+        let time = whole.rem_euclid(SECONDS_PER_DAY);
+        let (year, month, day) = civil_from_days(days);
+        Fields {
+            year,
+            month,
+            day,
+            hour: time / 3600,
+            minute: time / 60 % 60,
+            second: time % 60,
+            // a fraction which rounds up to a whole second stays within the second
+            millisecond: millisecond.min(999),
+            // the first of January 1970 was a Thursday
+            weekday: (days + 4).rem_euclid(7) as usize,
+            day_of_year: days - days_from_civil(year, 1, 1) + 1,
+        }
+    }
+
+    /// writes the date time with a pattern of strftime directives, which are
+    ///
+    /// - `%Y` the year, `%m` the month, `%d` the day, `%j` the day of the year
+    /// - `%H` the hour, `%M` the minute, `%S` the second, `%L` the millisecond
+    /// - `%A` and `%a` the full and short name of the weekday, `%B` and `%b` of the month
+    /// - `%s` the whole seconds since 1970 and `%%` a percent sign
+    pub fn format(&self, pattern: &str) -> Result<String> {
+        let fields = self.fields();
+        let mut out = String::with_capacity(pattern.len() + 16);
+        let mut chars = pattern.chars();
+        while let Some(char) = chars.next() {
+            if char != '%' {
+                out.push(char);
+                continue;
+            }
+            let directive = chars
+                .next()
+                .ok_or_else(|| anyhow!("the pattern ends with a lone %"))?;
+            let field = match directive {
+                'Y' => format!("{:04}", fields.year),
+                'm' => format!("{:02}", fields.month),
+                'd' => format!("{:02}", fields.day),
+                'j' => format!("{:03}", fields.day_of_year),
+                'H' => format!("{:02}", fields.hour),
+                'M' => format!("{:02}", fields.minute),
+                'S' => format!("{:02}", fields.second),
+                'L' => format!("{:03}", fields.millisecond),
+                'A' => WEEKDAYS[fields.weekday].to_string(),
+                'a' => WEEKDAYS[fields.weekday][..3].to_string(),
+                'B' => MONTHS[fields.month as usize - 1].to_string(),
+                'b' => MONTHS[fields.month as usize - 1][..3].to_string(),
+                's' => format!("{}", self.seconds.floor()),
+                '%' => "%".to_string(),
+                directive => {
+                    return Err(anyhow!(
+                        "%{} is not a directive, expected one of %Y %m %d %j %H %M %S %L %A %a %B %b %s %%",
+                        directive
+                    ))
+                }
+            };
+            out.push_str(&field);
+        }
+        Ok(out)
+    }
+}
+
+/// prints the date time in ISO 8601, with milliseconds only when there are some
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pattern = if self.fields().millisecond == 0 {
+            "%Y-%m-%dT%H:%M:%SZ"
+        } else {
+            "%Y-%m-%dT%H:%M:%S.%LZ"
+        };
+        write!(f, "{}", self.format(pattern).map_err(|_| fmt::Error)?)
+    }
+}
+
+/// the year, month and day of a number of days since 1970, following
+/// <https://howardhinnant.github.io/date_algorithms.html>
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    // counting from the first of March of the year 0 puts the leap day at the end of the year
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u32;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// the number of days since 1970 of a year, month and day, undoing [`civil_from_days`]
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month_from_march = (i64::from(month) + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// applies an arithmetic or comparison operator when an operand is a date time: adding seconds to
+/// a date time or subtracting them from it, subtracting date times to get the seconds between them
+/// and comparing date times. Other operands and operators are left to the rules for numbers and
+/// strings, which is None, and a result outside the range of dates is the seconds it would be at.
+pub fn binary(
+    operator: &Operator,
+    left: &Literal,
+    right: &Literal,
+) -> Option<Result<Literal, f64>> {
+    let date = |date: Option<DateTime>, seconds: f64| date.map(Literal::DateTime).ok_or(seconds);
+    let result = match (operator, left, right) {
+        (Operator::Add { .. }, Literal::DateTime(left), Literal::Number(right))
+        | (Operator::Add { .. }, Literal::Number(right), Literal::DateTime(left)) => {
+            date(left.add(*right), left.seconds + right)
+        }
+        (Operator::Subtract { .. }, Literal::DateTime(left), Literal::Number(right)) => {
+            date(left.add(-right), left.seconds - right)
+        }
+        (Operator::Subtract { .. }, Literal::DateTime(left), Literal::DateTime(right)) => {
+            Ok(Literal::Number(left.seconds - right.seconds))
+        }
+        (operator, Literal::DateTime(left), Literal::DateTime(right)) => {
+            Ok(Literal::Boolean(match operator {
+                Operator::GreaterThan { .. } => left > right,
+                Operator::GreaterEqual { .. } => left >= right,
+                Operator::LessThan { .. } => left < right,
+                Operator::LessEqual { .. } => left <= right,
+                _ => return None,
+            }))
+        }
+        _ => return None,
+    };
+    Some(result)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    /// tests that date times are split into the fields of the Gregorian calendar
+    fn format_test() {
+        let date = |seconds: f64| DateTime::new(seconds).unwrap();
+        assert_eq!(date(0.0).to_string(), "1970-01-01T00:00:00Z");
+        // a leap day, and a fraction of a second
+        assert_eq!(
+            date(1_709_211_900.25).to_string(),
+            "2024-02-29T13:05:00.250Z"
+        );
+        // before 1970 the seconds are negative
+        assert_eq!(date(-1.0).to_string(), "1969-12-31T23:59:59Z");
+        assert_eq!(date(951_782_400.0).to_string(), "2000-02-29T00:00:00Z");
+        assert_eq!(
+            date(1_709_211_900.0)
+                .format("%A %a %B %b %j %H:%M %s %%")
+                .unwrap(),
+            "Thursday Thu February Feb 060 13:05 1709211900 %"
+        );
+        assert!(date(0.0).format("%q").is_err());
+        assert!(date(0.0).format("100%").is_err());
+
+        assert!(DateTime::new(LIMIT).is_some());
+        assert!(DateTime::new(-LIMIT - 1.0).is_none());
+        assert!(DateTime::new(f64::NAN).is_none());
+        assert_eq!(date(-LIMIT).to_string(), "-271821-04-20T00:00:00Z");
+
+        for days in [-800_000, -1, 0, 59, 11_016, 2_932_896] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+    }
+
+    #[test]
+    /// tests that seconds are added to date times and that date times subtract to seconds
+    fn binary_test() {
+        let date = |seconds: f64| Literal::DateTime(DateTime::new(seconds).unwrap());
+        let add = Operator::Add { line: 1 };
+        let subtract = Operator::Subtract { line: 1 };
+        assert_eq!(
+            binary(&add, &date(10.0), &Literal::Number(5.0)),
+            Some(Ok(date(15.0)))
+        );
+        assert_eq!(
+            binary(&add, &Literal::Number(5.0), &date(10.0)),
+            Some(Ok(date(15.0)))
+        );
+        assert_eq!(
+            binary(&subtract, &date(10.0), &date(4.0)),
+            Some(Ok(Literal::Number(6.0)))
+        );
+        assert_eq!(
+            binary(&Operator::LessThan { line: 1 }, &date(1.0), &date(2.0)),
+            Some(Ok(Literal::Boolean(true)))
+        );
+        assert_eq!(
+            binary(&add, &date(LIMIT), &Literal::Number(1.0)),
+            Some(Err(LIMIT + 1.0))
+        );
+        // a number minus a date time is not a date time
+        assert_eq!(binary(&subtract, &Literal::Number(1.0), &date(1.0)), None);
+        assert_eq!(binary(&add, &date(1.0), &date(1.0)), None);
+    }
+}
@@ -0,0 +1,276 @@
+//!
+//! This module contains the structural diff of two runtime values, which `assertEquals` reports when
+//! it fails. Rather than only printing both values, which says nothing more than "Point instance" for
+//! an instance, the diff walks into instances and lists each field that differs by its path. Lists
+//! are walked into the same way, element by element, where the path of an element is its index, and
+//! so are maps, where the path of a value is its key.
+
+use crate::ast::Literal;
+use crate::map::MapKey;
+use std::collections::HashSet;
+use std::fmt;
+use std::rc::Rc;
+
+/// a place where two values differ, with the value on each side or None when a field is missing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Difference {
+    /// the fields, indices and keys leading to the difference, e.g. `.points[1]["x"]`, which is
+    /// empty for the values themselves
+    pub path: String,
+    pub expected: Option<Literal>,
+    pub actual: Option<Literal>,
+}
+
+/// the differences between an expected and an actual value. Instances of the same class are
+/// compared field by field, lists element by element, maps key by key, and other values with `==`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diff {
+    pub expected: Literal,
+    pub actual: Literal,
+    pub differences: Vec<Difference>,
+}
+
+impl Diff {
+    pub fn new(expected: &Literal, actual: &Literal) -> Self {
+        let mut differences = Vec::new();
+        diff_values(
+            String::new(),
+            expected,
+            actual,
+            &mut differences,
+            &mut HashSet::new(),
+        );
+        Self {
+            expected: expected.clone(),
+            actual: actual.clone(),
+            differences,
+        }
+    }
+
+    /// whether the values are structurally equal
+    pub fn is_empty(&self) -> bool {
+        self.differences.is_empty()
+    }
+}
+
+/// pushes the differences between two values found at the path. The pairs of instances and lists
+/// already being compared are seen, so that values holding themselves are not compared forever.
+fn diff_values(
+    path: String,
+    expected: &Literal,
+    actual: &Literal,
+    differences: &mut Vec<Difference>,
+    seen: &mut HashSet<(*const (), *const ())>,
+) {
+    match (expected, actual) {
+        (Literal::List(expected), Literal::List(actual)) => {
+            let pair = (Rc::as_ptr(expected).cast(), Rc::as_ptr(actual).cast());
+            if Rc::ptr_eq(expected, actual) || !seen.insert(pair) {
+                return;
+            }
+            let (expected, actual) = (expected.borrow(), actual.borrow());
+            for index in 0..expected.len().max(actual.len()) {
+                let path = format!("{}[{}]", path, index);
+                match (expected.get(index), actual.get(index)) {
+                    (Some(expected), Some(actual)) => {
+                        diff_values(path, expected, actual, differences, seen)
+                    }
+                    (expected, actual) => differences.push(Difference {
+                        path,
+                        expected: expected.cloned(),
+                        actual: actual.cloned(),
+                    }),
+                }
+            }
+        }
+        (Literal::Map(expected), Literal::Map(actual)) => {
+            let pair = (expected.as_ptr(), actual.as_ptr());
+            if pair.0 == pair.1 || !seen.insert(pair) {
+                return;
+            }
+            let (expected, actual) = (expected.entries(), actual.entries());
+            let mut keys: Vec<_> = expected.iter().chain(&actual).map(|(key, _)| key).collect();
+            keys.sort();
+            keys.dedup();
+            let value = |entries: &[(MapKey, Literal)], key| {
+                entries
+                    .iter()
+                    .find(|(other, _)| other == key)
+                    .map(|(_, value)| value.clone())
+            };
+            for key in keys {
+                let path = format!("{}[{}]", path, key);
+                match (value(&expected, key), value(&actual, key)) {
+                    (Some(expected), Some(actual)) => {
+                        diff_values(path, &expected, &actual, differences, seen)
+                    }
+                    (expected, actual) => differences.push(Difference {
+                        path,
+                        expected,
+                        actual,
+                    }),
+                }
+            }
+        }
+        (Literal::Instance(expected), Literal::Instance(actual))
+            if Rc::ptr_eq(&expected.class, &actual.class) =>
+        {
+            let pair = (Rc::as_ptr(expected).cast(), Rc::as_ptr(actual).cast());
+            if Rc::ptr_eq(expected, actual) || !seen.insert(pair) {
+                return;
+            }
+            let expected_fields = expected.fields();
+            let actual_fields = actual.fields();
+            let mut names: Vec<_> = expected_fields.keys().chain(actual_fields.keys()).collect();
+            names.sort();
+            names.dedup();
+            for name in names {
+                let path = format!("{}.{}", path, name);
+                match (expected_fields.get(name), actual_fields.get(name)) {
+                    (Some(expected), Some(actual)) => {
+                        diff_values(path, expected, actual, differences, seen)
+                    }
+                    (expected, actual) => differences.push(Difference {
+                        path,
+                        expected: expected.cloned(),
+                        actual: actual.cloned(),
+                    }),
+                }
+            }
+        }
+        (expected, actual) if expected == actual => {}
+        (expected, actual) => differences.push(Difference {
+            path,
+            expected: Some(expected.clone()),
+            actual: Some(actual.clone()),
+        }),
+    }
+}
+
+/// prints a value, quoting strings so that they can be told apart from other values
+pub fn show(value: &Literal) -> String {
+    match value {
+        Literal::String(string) => format!("\"{}\"", string),
+        value => value.to_string(),
+    }
+}
+
+/// prints a side of a difference, where a missing field has no value
+fn show_side(value: &Option<Literal>) -> String {
+    value
+        .as_ref()
+        .map(show)
+        .unwrap_or_else(|| "<missing>".to_string())
+}
+
+/// a difference in the values themselves is printed on one line, and differences in their fields on
+/// a line each below a heading
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let [difference] = self.differences.as_slice() {
+            if difference.path.is_empty() {
+                return write!(
+                    f,
+                    "expected {} but got {}",
+                    show_side(&difference.expected),
+                    show_side(&difference.actual)
+                );
+            }
+        }
+        write!(
+            f,
+            "expected {} but got {}, which differ in {} field{}:",
+            show(&self.expected),
+            show(&self.actual),
+            self.differences.len(),
+            if self.differences.len() == 1 { "" } else { "s" }
+        )?;
+        for difference in &self.differences {
+            write!(
+                f,
+                "\n  {}: expected {} but got {}",
+                difference.path,
+                show_side(&difference.expected),
+                show_side(&difference.actual)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::map::LoxMap;
+
+    #[test]
+    /// tests that values which are not instances are compared with ==
+    fn diff_test() {
+        let number = Literal::Number(1.0);
+        assert!(Diff::new(&number, &Literal::Number(1.0)).is_empty());
+
+        let diff = Diff::new(&number, &Literal::String("1".to_string()));
+        assert_eq!(
+            diff.differences,
+            vec![Difference {
+                path: String::new(),
+                expected: Some(number),
+                actual: Some(Literal::String("1".to_string())),
+            }]
+        );
+        assert_eq!(diff.to_string(), "expected 1 but got \"1\"");
+    }
+
+    #[test]
+    /// tests that lists are compared element by element, including lists which hold themselves
+    fn list_diff_test() {
+        let list =
+            |elements: Vec<Literal>| Literal::List(Rc::new(std::cell::RefCell::new(elements)));
+        let expected = list(vec![Literal::Number(1.0), list(vec![Literal::Nil])]);
+        let actual = list(vec![Literal::Number(1.0), list(vec![Literal::Nil])]);
+        assert!(Diff::new(&expected, &actual).is_empty());
+
+        let actual = list(vec![
+            Literal::Number(2.0),
+            list(vec![]),
+            Literal::Boolean(true),
+        ]);
+        assert_eq!(
+            Diff::new(&expected, &actual).to_string(),
+            "expected [1, [nil]] but got [2, [], true], which differ in 3 fields:\n  \
+            [0]: expected 1 but got 2\n  \
+            [1][0]: expected nil but got <missing>\n  \
+            [2]: expected <missing> but got true"
+        );
+
+        let (Literal::List(a), Literal::List(b)) = (list(vec![]), list(vec![])) else {
+            unreachable!()
+        };
+        a.borrow_mut().push(Literal::List(a.clone()));
+        b.borrow_mut().push(Literal::List(b.clone()));
+        assert!(Diff::new(&Literal::List(a.clone()), &Literal::List(b)).is_empty());
+        assert_eq!(Literal::List(a).to_string(), "[[...]]");
+    }
+
+    #[test]
+    /// tests that maps are compared key by key
+    fn map_diff_test() {
+        let map = |entries: Vec<(&str, Literal)>| {
+            Literal::Map(LoxMap::new(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (MapKey::String(key.to_string()), value))
+                    .collect(),
+            ))
+        };
+        let expected = map(vec![("a", Literal::Number(1.0)), ("b", Literal::Nil)]);
+        let actual = map(vec![("a", Literal::Number(2.0)), ("c", Literal::Nil)]);
+        assert_eq!(
+            Diff::new(&expected, &actual).to_string(),
+            "expected {\"a\": 1, \"b\": nil} but got {\"a\": 2, \"c\": nil}, which differ in 3 fields:\n  \
+            [\"a\"]: expected 1 but got 2\n  \
+            [\"b\"]: expected nil but got <missing>\n  \
+            [\"c\"]: expected <missing> but got nil"
+        );
+    }
+}
@@ -0,0 +1,128 @@
+//!
+//! This module contains dry runs, in which the natives needing a capability are replaced with stubs.
+//! A stub does not touch the outside world. It records the call as an [`Effect`] and returns a canned
+//! value instead, so that scripts which normally read files or talk to the network can be tested
+//! without doing so.
+//!
+//! The natives which are stubbed are the ones the interpreter defines with a capability, which are
+//! the ones [`crate::analyze`] audits, and once a script has run its effects can be read back as an
+//! [`EffectsTrace`].
+
+use crate::analyze::Capability;
+use crate::ast::Literal;
+use crate::diff::show;
+use crate::interpreter::{Interpreter, LoxCallable};
+use anyhow::Result;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+/// a stand-in for a native, which returns the same canned value whatever it is called with.
+#[derive(Debug)]
+pub struct Stub {
+    pub name: String,
+    pub capability: Capability,
+    pub returns: Literal,
+}
+
+/// stubs take any number of arguments, since they never look at them
+impl LoxCallable for Stub {
+    fn arity(&self) -> Option<usize> {
+        None
+    }
+
+    fn call(
+        self: Rc<Self>,
+        interpreter: &mut Interpreter,
+        arguments: Vec<Literal>,
+        line: u32,
+    ) -> Result<Literal> {
+        interpreter.record_effect(Effect {
+            native: self.name.clone(),
+            capability: self.capability,
+            arguments,
+            returned: self.returns.clone(),
+            line,
+        });
+        Ok(self.returns.clone())
+    }
+
+    fn is_native(&self) -> bool {
+        true
+    }
+
+    fn capability(&self) -> Option<Capability> {
+        Some(self.capability)
+    }
+}
+
+impl fmt::Display for Stub {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+/// the canned values the stubs of a dry run return. Stubs without a canned value return nil.
+#[derive(Debug, Clone, Default)]
+pub struct DryRun {
+    values: HashMap<String, Literal>,
+}
+
+impl DryRun {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// sets the value the stub of a native returns
+    pub fn with_value(mut self, native: impl Into<String>, value: Literal) -> Self {
+        self.values.insert(native.into(), value);
+        self
+    }
+
+    /// a stub for each of the natives needing a capability, by name
+    pub fn stubs<'a>(
+        &'a self,
+        natives: &'a HashMap<String, Capability>,
+    ) -> impl Iterator<Item = Stub> + 'a {
+        natives.iter().map(|(name, capability)| Stub {
+            name: name.clone(),
+            capability: *capability,
+            returns: self.values.get(name).cloned().unwrap_or(Literal::Nil),
+        })
+    }
+}
+
+/// a call of a stub during a dry run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Effect {
+    pub native: String,
+    pub capability: Capability,
+    pub arguments: Vec<Literal>,
+    pub returned: Literal,
+    pub line: u32,
+}
+
+/// the calls of stubs during a dry run, in the order they were made.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EffectsTrace {
+    pub effects: Vec<Effect>,
+}
+
+impl fmt::Display for EffectsTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "effects: {}", self.effects.len())?;
+        for effect in &self.effects {
+            let arguments: Vec<_> = effect.arguments.iter().map(show).collect();
+            writeln!(
+                f,
+                "  [line {}] {}({}) -> {} ({})",
+                effect.line,
+                effect.native,
+                arguments.join(", "),
+                show(&effect.returned),
+                effect.capability
+            )?;
+        }
+        Ok(())
+    }
+}
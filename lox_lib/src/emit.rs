@@ -0,0 +1,670 @@
+//!
+//! This module contains the functions used to print the intermediate representations of a program,
+//! which back the `--emit` flag of the command line.
+
+use crate::ast::{ExprNode, ExprVisitor, FunctionDecl, Literal, Operator, StmtNode};
+use crate::lexer::{Comment, Token, TokenType};
+use crate::semantic::{SemanticToken, MODIFIER_LEGEND};
+use anyhow::{anyhow, Result};
+use std::str::FromStr;
+
+/// The intermediate representations of a program that can be emitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Emit {
+    Tokens,
+    Ast,
+    /// the identifiers classified by what they name, see [`crate::semantic`]
+    SemanticTokens,
+    Bytecode,
+}
+
+/// The formats an intermediate representation can be emitted in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmitFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl FromStr for Emit {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "tokens" => Ok(Emit::Tokens),
+            "ast" => Ok(Emit::Ast),
+            "semantic-tokens" => Ok(Emit::SemanticTokens),
+            "bytecode" => Ok(Emit::Bytecode),
+            _ => Err(anyhow!(
+                "unknown emit kind {}, expected tokens, ast, semantic-tokens or bytecode",
+                s
+            )),
+        }
+    }
+}
+
+impl FromStr for EmitFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(EmitFormat::Text),
+            "json" => Ok(EmitFormat::Json),
+            _ => Err(anyhow!("unknown emit format {}, expected text or json", s)),
+        }
+    }
+}
+
+/// parses the value of the `--emit` flag, which is the representation optionally followed by the
+/// format, e.g. `tokens`, `ast:json`.
+pub fn parse_emit_flag(value: &str) -> Result<(Emit, EmitFormat)> {
+    match value.split_once(':') {
+        Some((emit, format)) => Ok((emit.parse()?, format.parse()?)),
+        None => Ok((value.parse()?, EmitFormat::default())),
+    }
+}
+
+/// emits the tokens of a program, one token per line in the text format.
+pub fn tokens(tokens: &[Token], format: EmitFormat) -> String {
+    match format {
+        EmitFormat::Text => tokens
+            .iter()
+            .map(|token| {
+                format!(
+                    "{:>4} {:<14} {}\n",
+                    token.line,
+                    token_type_name(&token.token_type),
+                    token.lexeme()
+                )
+            })
+            .collect(),
+        EmitFormat::Json => {
+            let tokens = tokens
+                .iter()
+                .map(|token| {
+                    format!(
+                        "{{\"type\":{},\"lexeme\":{},\"line\":{}}}",
+                        json_string(&token_type_name(&token.token_type)),
+                        json_string(token.lexeme()),
+                        token.line
+                    )
+                })
+                .collect::<Vec<_>>();
+            format!("[{}]\n", tokens.join(","))
+        }
+    }
+}
+
+/// emits the semantic tokens of a program, one token per line with its modifiers in the text format.
+pub fn semantic_tokens(tokens: &[SemanticToken], format: EmitFormat) -> String {
+    let modifiers = |token: &SemanticToken| {
+        MODIFIER_LEGEND
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| token.modifiers() & (1 << i) != 0)
+            .map(|(_, modifier)| *modifier)
+            .collect::<Vec<_>>()
+    };
+    match format {
+        EmitFormat::Text => tokens
+            .iter()
+            .map(|token| {
+                let mut line = format!(
+                    "{:>4}:{:<4} {:<10} {}",
+                    token.line,
+                    token.column,
+                    token.kind.to_string(),
+                    token.name
+                );
+                for modifier in modifiers(token) {
+                    line.push(' ');
+                    line.push_str(modifier);
+                }
+                line + "\n"
+            })
+            .collect(),
+        EmitFormat::Json => {
+            let tokens = tokens
+                .iter()
+                .map(|token| {
+                    format!(
+                        "{{\"name\":{},\"line\":{},\"column\":{},\"type\":{},\"modifiers\":[{}]}}",
+                        json_string(&token.name),
+                        token.line,
+                        token.column,
+                        json_string(&token.kind.to_string()),
+                        modifiers(token)
+                            .iter()
+                            .map(|modifier| json_string(modifier))
+                            .collect::<Vec<_>>()
+                            .join(",")
+                    )
+                })
+                .collect::<Vec<_>>();
+            format!("[{}]\n", tokens.join(","))
+        }
+    }
+}
+
+/// emits the syntax tree of a program.
+pub fn ast(statements: &[StmtNode], format: EmitFormat) -> String {
+    match format {
+        EmitFormat::Text => format!("{:#?}\n", statements),
+        EmitFormat::Json => format!("{}\n", JsonEmitter.stmts(statements)),
+    }
+}
+
+/// the name of a token type without the value of literal tokens
+fn token_type_name(token_type: &TokenType) -> String {
+    match token_type {
+        TokenType::String(_) => "String".to_string(),
+        TokenType::Number(_) => "Number".to_string(),
+        TokenType::BigInt(_) => "BigInt".to_string(),
+        TokenType::Bytes(_) => "Bytes".to_string(),
+        // the remaining token types hold no values so their debug name is just the variant name
+        _ => format!("{:?}", token_type),
+    }
+}
+
+pub(crate) fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for chr in value.chars() {
+        match chr {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            chr if (chr as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", chr as u32)),
+            chr => out.push(chr),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Converts a syntax tree into JSON objects which have a `type` field naming the kind of node.
+struct JsonEmitter;
+
+impl JsonEmitter {
+    fn stmt(&mut self, statement: &StmtNode) -> String {
+        match statement {
+            StmtNode::PrintStmt(expr) => format!(
+                "{{\"type\":\"Print\",\"expression\":{}}}",
+                self.visit_expr_node(expr)
+            ),
+            StmtNode::ExprStmt(expr) => format!(
+                "{{\"type\":\"Expression\",\"expression\":{}}}",
+                self.visit_expr_node(expr)
+            ),
+            StmtNode::ErrStmt(message) => {
+                format!(
+                    "{{\"type\":\"Error\",\"message\":{}}}",
+                    json_string(message)
+                )
+            }
+            StmtNode::VarStmt { name, initializer } => format!(
+                "{{\"type\":\"Var\",\"name\":{},\"initializer\":{}}}",
+                json_string(name),
+                initializer
+                    .as_ref()
+                    .map(|expr| self.visit_expr_node(expr))
+                    .unwrap_or_else(|| "null".to_string())
+            ),
+            StmtNode::ConstStmt { name, initializer } => format!(
+                "{{\"type\":\"Const\",\"name\":{},\"initializer\":{}}}",
+                json_string(name),
+                self.visit_expr_node(initializer)
+            ),
+            StmtNode::ImportStmt {
+                path,
+                name,
+                names,
+                line,
+            } => format!(
+                "{{\"type\":\"Import\",\"line\":{},\"path\":{},\"name\":{},\"names\":[{}]}}",
+                line,
+                json_string(path),
+                name.as_deref()
+                    .map(json_string)
+                    .unwrap_or_else(|| "null".to_string()),
+                names
+                    .iter()
+                    .map(|name| json_string(name))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+            StmtNode::ExportStmt { statement, line } => format!(
+                "{{\"type\":\"Export\",\"line\":{},\"statement\":{}}}",
+                line,
+                self.stmt(statement)
+            ),
+            StmtNode::BlockStmt(statements) => format!(
+                "{{\"type\":\"Block\",\"statements\":{}}}",
+                self.stmts(statements)
+            ),
+            StmtNode::IfStmt {
+                condition,
+                then_branch,
+                else_branch,
+            } => format!(
+                "{{\"type\":\"If\",\"condition\":{},\"then\":{},\"else\":{}}}",
+                self.visit_expr_node(condition),
+                self.stmt(then_branch),
+                else_branch
+                    .as_ref()
+                    .map(|stmt| self.stmt(stmt))
+                    .unwrap_or_else(|| "null".to_string())
+            ),
+            StmtNode::WhileStmt {
+                condition,
+                body,
+                increment,
+            } => format!(
+                "{{\"type\":\"While\",\"condition\":{},\"body\":{},\"increment\":{}}}",
+                self.visit_expr_node(condition),
+                self.stmt(body),
+                increment
+                    .as_ref()
+                    .map(|expr| self.visit_expr_node(expr))
+                    .unwrap_or_else(|| "null".to_string())
+            ),
+            StmtNode::DoWhileStmt { body, condition } => format!(
+                "{{\"type\":\"DoWhile\",\"body\":{},\"condition\":{}}}",
+                self.stmt(body),
+                self.visit_expr_node(condition)
+            ),
+            StmtNode::BreakStmt { line } => format!("{{\"type\":\"Break\",\"line\":{}}}", line),
+            StmtNode::ContinueStmt { line } => {
+                format!("{{\"type\":\"Continue\",\"line\":{}}}", line)
+            }
+            StmtNode::FunctionStmt(declaration) => self.function(declaration),
+            StmtNode::ClassStmt(declaration) => format!(
+                "{{\"type\":\"Class\",\"name\":{},\"line\":{},\"superclass\":{},\"methods\":[{}],\"classMethods\":[{}]}}",
+                json_string(&declaration.name),
+                declaration.line,
+                declaration
+                    .superclass
+                    .as_ref()
+                    .map(|expr| self.visit_expr_node(expr))
+                    .unwrap_or_else(|| "null".to_string()),
+                declaration
+                    .methods
+                    .iter()
+                    .map(|method| self.function(method))
+                    .collect::<Vec<_>>()
+                    .join(","),
+                declaration
+                    .class_methods
+                    .iter()
+                    .map(|method| self.function(method))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+            StmtNode::ThrowStmt { value, line } => format!(
+                "{{\"type\":\"Throw\",\"line\":{},\"value\":{}}}",
+                line,
+                self.visit_expr_node(value)
+            ),
+            StmtNode::TryStmt {
+                body,
+                name,
+                handler,
+            } => format!(
+                "{{\"type\":\"Try\",\"body\":{},\"name\":{},\"handler\":{}}}",
+                self.stmts(body),
+                json_string(name),
+                self.stmts(handler)
+            ),
+            StmtNode::ReturnStmt { value, line } => format!(
+                "{{\"type\":\"Return\",\"line\":{},\"value\":{}}}",
+                line,
+                value
+                    .as_ref()
+                    .map(|expr| self.visit_expr_node(expr))
+                    .unwrap_or_else(|| "null".to_string())
+            ),
+            StmtNode::Commented {
+                leading,
+                statement,
+                trailing,
+            } => format!(
+                "{{\"type\":\"Commented\",\"leading\":{},\"statement\":{},\"trailing\":{}}}",
+                Self::comments(leading),
+                self.stmt(statement),
+                Self::comments(trailing)
+            ),
+        }
+    }
+
+    fn comments(comments: &[Comment]) -> String {
+        let comments = comments
+            .iter()
+            .map(|comment| {
+                format!(
+                    "{{\"text\":{},\"line\":{}}}",
+                    json_string(&comment.text),
+                    comment.line
+                )
+            })
+            .collect::<Vec<_>>();
+        format!("[{}]", comments.join(","))
+    }
+
+    /// a function declaration, which is also how the methods of a class are emitted
+    fn function(&mut self, declaration: &FunctionDecl) -> String {
+        format!(
+            "{{\"type\":\"Function\",\"name\":{},\"params\":[{}],\"rest\":{},\"line\":{},\"body\":{}}}",
+            json_string(&declaration.name),
+            declaration
+                .params
+                .iter()
+                .map(|param| json_string(param))
+                .collect::<Vec<_>>()
+                .join(","),
+            declaration
+                .rest
+                .as_deref()
+                .map_or("null".to_string(), json_string),
+            declaration.line,
+            self.stmts(&declaration.body)
+        )
+    }
+
+    fn stmts(&mut self, statements: &[StmtNode]) -> String {
+        let statements = statements
+            .iter()
+            .map(|statement| self.stmt(statement))
+            .collect::<Vec<_>>();
+        format!("[{}]", statements.join(","))
+    }
+
+    fn operator(operator: &Operator) -> String {
+        format!(
+            "\"operator\":{},\"line\":{}",
+            json_string(operator.lexeme()),
+            operator.line()
+        )
+    }
+}
+
+impl ExprVisitor for JsonEmitter {
+    type Output = String;
+
+    fn visit_literal(&mut self, literal: &Literal) -> Self::Output {
+        let value = match literal {
+            Literal::Number(number) if number.is_finite() => number.to_string(),
+            Literal::Number(number) => json_string(&number.to_string()),
+            Literal::Decimal(decimal) => decimal.to_string(),
+            Literal::String(string) => json_string(string),
+            Literal::Boolean(boolean) => boolean.to_string(),
+            Literal::Nil => "null".to_string(),
+            Literal::Callable(_)
+            | Literal::Instance(_)
+            | Literal::Handle(_)
+            | Literal::List(_)
+            | Literal::Map(_)
+            | Literal::Bytes(_)
+            | Literal::DateTime(_) => json_string(&literal.to_string()),
+            #[cfg(feature = "bigint")]
+            Literal::BigInt(bigint) => json_string(&format!("{}n", bigint)),
+        };
+        format!("{{\"type\":\"Literal\",\"value\":{}}}", value)
+    }
+
+    fn visit_grouping(&mut self, grouping: &ExprNode) -> Self::Output {
+        format!(
+            "{{\"type\":\"Grouping\",\"expression\":{}}}",
+            self.visit_expr_node(grouping)
+        )
+    }
+
+    fn visit_binary_expr(
+        &mut self,
+        left: &ExprNode,
+        operator: &Operator,
+        right: &ExprNode,
+    ) -> Self::Output {
+        format!(
+            "{{\"type\":\"Binary\",{},\"left\":{},\"right\":{}}}",
+            JsonEmitter::operator(operator),
+            self.visit_expr_node(left),
+            self.visit_expr_node(right)
+        )
+    }
+
+    fn visit_unary_expr(&mut self, operator: &Operator, child: &ExprNode) -> Self::Output {
+        format!(
+            "{{\"type\":\"Unary\",{},\"right\":{}}}",
+            JsonEmitter::operator(operator),
+            self.visit_expr_node(child)
+        )
+    }
+
+    fn visit_logical_expr(
+        &mut self,
+        left: &ExprNode,
+        operator: &Operator,
+        right: &ExprNode,
+    ) -> Self::Output {
+        format!(
+            "{{\"type\":\"Logical\",{},\"left\":{},\"right\":{}}}",
+            JsonEmitter::operator(operator),
+            self.visit_expr_node(left),
+            self.visit_expr_node(right)
+        )
+    }
+
+    fn visit_variable(&mut self, name: &str, line: u32) -> Self::Output {
+        format!(
+            "{{\"type\":\"Variable\",\"name\":{},\"line\":{}}}",
+            json_string(name),
+            line
+        )
+    }
+
+    fn visit_assign(&mut self, name: &str, value: &ExprNode, line: u32) -> Self::Output {
+        format!(
+            "{{\"type\":\"Assign\",\"name\":{},\"line\":{},\"value\":{}}}",
+            json_string(name),
+            line,
+            self.visit_expr_node(value)
+        )
+    }
+
+    fn visit_call(&mut self, callee: &ExprNode, arguments: &[ExprNode], line: u32) -> Self::Output {
+        let arguments = arguments
+            .iter()
+            .map(|argument| self.visit_expr_node(argument))
+            .collect::<Vec<_>>();
+        format!(
+            "{{\"type\":\"Call\",\"line\":{},\"callee\":{},\"arguments\":[{}]}}",
+            line,
+            self.visit_expr_node(callee),
+            arguments.join(",")
+        )
+    }
+
+    fn visit_get(&mut self, object: &ExprNode, name: &str, line: u32) -> Self::Output {
+        format!(
+            "{{\"type\":\"Get\",\"line\":{},\"object\":{},\"name\":{}}}",
+            line,
+            self.visit_expr_node(object),
+            json_string(name)
+        )
+    }
+
+    fn visit_set(
+        &mut self,
+        object: &ExprNode,
+        name: &str,
+        value: &ExprNode,
+        line: u32,
+    ) -> Self::Output {
+        format!(
+            "{{\"type\":\"Set\",\"line\":{},\"object\":{},\"name\":{},\"value\":{}}}",
+            line,
+            self.visit_expr_node(object),
+            json_string(name),
+            self.visit_expr_node(value)
+        )
+    }
+
+    fn visit_list(&mut self, elements: &[ExprNode], line: u32) -> Self::Output {
+        let elements = elements
+            .iter()
+            .map(|element| self.visit_expr_node(element))
+            .collect::<Vec<_>>();
+        format!(
+            "{{\"type\":\"List\",\"line\":{},\"elements\":[{}]}}",
+            line,
+            elements.join(",")
+        )
+    }
+
+    fn visit_map(&mut self, entries: &[(ExprNode, ExprNode)], line: u32) -> Self::Output {
+        let entries = entries
+            .iter()
+            .map(|(key, value)| {
+                let key = self.visit_expr_node(key);
+                format!(
+                    "{{\"key\":{},\"value\":{}}}",
+                    key,
+                    self.visit_expr_node(value)
+                )
+            })
+            .collect::<Vec<_>>();
+        format!(
+            "{{\"type\":\"Map\",\"line\":{},\"entries\":[{}]}}",
+            line,
+            entries.join(",")
+        )
+    }
+
+    fn visit_index(&mut self, object: &ExprNode, index: &ExprNode, line: u32) -> Self::Output {
+        format!(
+            "{{\"type\":\"Index\",\"line\":{},\"object\":{},\"index\":{}}}",
+            line,
+            self.visit_expr_node(object),
+            self.visit_expr_node(index)
+        )
+    }
+
+    fn visit_slice(
+        &mut self,
+        object: &ExprNode,
+        start: Option<&ExprNode>,
+        end: Option<&ExprNode>,
+        line: u32,
+    ) -> Self::Output {
+        let object = self.visit_expr_node(object);
+        let mut bound = |bound: Option<&ExprNode>| match bound {
+            Some(bound) => self.visit_expr_node(bound),
+            None => "null".to_string(),
+        };
+        let start = bound(start);
+        format!(
+            "{{\"type\":\"Slice\",\"line\":{},\"object\":{},\"start\":{},\"end\":{}}}",
+            line,
+            object,
+            start,
+            bound(end)
+        )
+    }
+
+    fn visit_set_index(
+        &mut self,
+        object: &ExprNode,
+        index: &ExprNode,
+        value: &ExprNode,
+        line: u32,
+    ) -> Self::Output {
+        format!(
+            "{{\"type\":\"SetIndex\",\"line\":{},\"object\":{},\"index\":{},\"value\":{}}}",
+            line,
+            self.visit_expr_node(object),
+            self.visit_expr_node(index),
+            self.visit_expr_node(value)
+        )
+    }
+
+    fn visit_this(&mut self, line: u32) -> Self::Output {
+        format!("{{\"type\":\"This\",\"line\":{}}}", line)
+    }
+
+    fn visit_super(&mut self, method: &str, line: u32) -> Self::Output {
+        format!(
+            "{{\"type\":\"Super\",\"method\":{},\"line\":{}}}",
+            json_string(method),
+            line
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+    #[cfg(feature = "host")]
+    use crate::semantic;
+
+    #[test]
+    fn parse_emit_flag_test() {
+        assert_eq!(
+            parse_emit_flag("tokens").unwrap(),
+            (Emit::Tokens, EmitFormat::Text)
+        );
+        assert_eq!(
+            parse_emit_flag("ast:json").unwrap(),
+            (Emit::Ast, EmitFormat::Json)
+        );
+        assert!(parse_emit_flag("ir").is_err());
+        assert!(parse_emit_flag("ast:xml").is_err());
+    }
+
+    #[test]
+    fn emit_tokens_test() {
+        let lexed = Lexer::new().lex("print \"a\";").unwrap();
+        assert_eq!(
+            tokens(&lexed, EmitFormat::Text),
+            "   1 Print          print\n   1 String         \"a\"\n   1 Semicolon      ;\n   1 Eof            \n"
+        );
+        assert_eq!(
+            tokens(&lexed, EmitFormat::Json),
+            "[{\"type\":\"Print\",\"lexeme\":\"print\",\"line\":1},\
+            {\"type\":\"String\",\"lexeme\":\"\\\"a\\\"\",\"line\":1},\
+            {\"type\":\"Semicolon\",\"lexeme\":\";\",\"line\":1},\
+            {\"type\":\"Eof\",\"lexeme\":\"\",\"line\":1}]\n"
+        );
+    }
+
+    #[test]
+    fn emit_ast_json_test() {
+        let lexed = Lexer::new().lex("print -(1 + nil);").unwrap();
+        let statements = Parser::new().parse(lexed);
+        assert_eq!(
+            ast(&statements, EmitFormat::Json),
+            "[{\"type\":\"Print\",\"expression\":{\"type\":\"Unary\",\"operator\":\"-\",\"line\":1,\
+            \"right\":{\"type\":\"Grouping\",\"expression\":{\"type\":\"Binary\",\"operator\":\"+\",\"line\":1,\
+            \"left\":{\"type\":\"Literal\",\"value\":1},\"right\":{\"type\":\"Literal\",\"value\":null}}}}}]\n"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "host")]
+    fn emit_semantic_tokens_test() {
+        let source = "var a = clock();";
+        let classified = semantic::classify(source, &Lexer::new().lex(source).unwrap());
+        assert_eq!(
+            semantic_tokens(&classified, EmitFormat::Text),
+            "   1:4    variable   a declaration\n   1:8    function   clock defaultLibrary\n"
+        );
+        assert_eq!(
+            semantic_tokens(&classified, EmitFormat::Json),
+            "[{\"name\":\"a\",\"line\":1,\"column\":4,\"type\":\"variable\",\"modifiers\":[\"declaration\"]},\
+            {\"name\":\"clock\",\"line\":1,\"column\":8,\"type\":\"function\",\"modifiers\":[\"defaultLibrary\"]}]\n"
+        );
+    }
+}
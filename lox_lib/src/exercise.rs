@@ -0,0 +1,204 @@
+//!
+//! This module contains the scaffolding for exercises, for instructors building coursework on Lox.
+//!
+//! `lox new-exercise name` creates a directory laid out the way [`crate::grade`] expects it:
+//!
+//! ```text
+//! name/
+//!   exercise.toml          the runner config, read by `lox grade --exercise name/`
+//!   starter.lox            the program handed out to students
+//!   expected/example.in    the stdin of the example case
+//!   expected/example.out   the output the example case expects
+//!   tests/hidden_test.lox  checks run with the assert natives, not handed out to students
+//! ```
+//!
+//! The hidden test is run against every submission after importing it, see [`crate::grade`].
+
+use anyhow::{anyhow, Context, Result};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// The name of the runner config in the directory of an exercise.
+pub const CONFIG_FILE: &str = "exercise.toml";
+
+/// The path of the hidden test in the directory of an exercise.
+pub const HIDDEN_TEST: &str = "tests/hidden_test.lox";
+
+/// The runner config of an exercise, written as `key = value` lines.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExerciseConfig {
+    pub name: String,
+    /// the directory of expected outputs, relative to the exercise
+    pub expected: PathBuf,
+    /// how long a submission can run for on a single case
+    pub timeout: Duration,
+    /// the seed of the random natives in every run of a submission, so that programs using random
+    /// numbers print the same output on every run
+    pub seed: Option<u64>,
+}
+
+impl ExerciseConfig {
+    pub fn new(name: &str) -> Self {
+        ExerciseConfig {
+            name: name.to_string(),
+            expected: PathBuf::from("expected"),
+            timeout: Duration::from_secs(5),
+            seed: None,
+        }
+    }
+
+    /// reads the config of the exercise in the given directory.
+    pub fn load(dir: &Path) -> Result<Self> {
+        let path = dir.join(CONFIG_FILE);
+        let source = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read in file from {}", path.display()))?;
+        ExerciseConfig::parse(&source)
+    }
+
+    /// parses a config, where blank lines and lines starting with `#` are ignored.
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut config = ExerciseConfig::new("");
+        for (i, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Expected key = value in line {}", i + 1))?;
+            let value = value.trim();
+            match key.trim() {
+                "name" => config.name = unquote(value).to_string(),
+                "expected" => config.expected = PathBuf::from(unquote(value)),
+                "timeout" => {
+                    let seconds =
+                        value
+                            .parse::<f64>()
+                            .ok()
+                            .filter(|s| *s >= 0.0)
+                            .ok_or_else(|| {
+                                anyhow!("timeout must be a number of seconds in line {}", i + 1)
+                            })?;
+                    config.timeout = Duration::from_secs_f64(seconds);
+                }
+                "seed" => {
+                    config.seed =
+                        Some(value.parse().map_err(|_| {
+                            anyhow!("seed must be a whole number in line {}", i + 1)
+                        })?);
+                }
+                key => return Err(anyhow!("Unknown key {} in line {}", key, i + 1)),
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "# the runner config of this exercise, read by `lox grade --exercise`\n\
+            name = \"{}\"\nexpected = \"{}\"\ntimeout = {}\n",
+            self.name,
+            self.expected.display(),
+            self.timeout.as_secs_f64()
+        );
+        if let Some(seed) = self.seed {
+            out.push_str(&format!("seed = {}\n", seed));
+        }
+        out
+    }
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+/// creates the directory of a new exercise inside of parent, returning its path. Fails instead of
+/// overwriting anything if the directory already exists.
+pub fn scaffold(parent: &Path, name: &str) -> Result<PathBuf> {
+    if name.is_empty() || name.contains(['/', '\\']) || name == "." || name == ".." {
+        return Err(anyhow!("{} is not a valid exercise name", name));
+    }
+    let dir = parent.join(name);
+    if dir.exists() {
+        return Err(anyhow!("{} already exists", dir.display()));
+    }
+
+    let config = ExerciseConfig::new(name);
+    let files = [
+        (PathBuf::from(CONFIG_FILE), config.render()),
+        (
+            PathBuf::from("starter.lox"),
+            format!(
+                "// {}\n//\n// TODO: print the answer for the example case.\n",
+                name
+            ),
+        ),
+        (config.expected.join("example.in"), String::new()),
+        (config.expected.join("example.out"), String::new()),
+        (
+            PathBuf::from(HIDDEN_TEST),
+            "// checks that are not handed out to students, written with the assert natives. The\n\
+            // submission is imported before this runs, so its functions can be called here.\n\
+            fun test_example() {\n  \
+              assert(true, \"replace this with the checks of the exercise\");\n\
+            }\n"
+            .to_string(),
+        ),
+    ];
+    for (path, contents) in files {
+        let path = dir.join(path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory {}", parent.display()))?;
+        }
+        std::fs::write(&path, contents)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    Ok(dir)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn config_test() {
+        let config = ExerciseConfig::new("fizzbuzz");
+        assert_eq!(ExerciseConfig::parse(&config.render()).unwrap(), config);
+
+        let config = ExerciseConfig::parse("# comment\n\nname = loops\ntimeout = 0.5\n").unwrap();
+        assert_eq!(config.name, "loops");
+        assert_eq!(config.timeout, Duration::from_millis(500));
+        assert_eq!(config.expected, PathBuf::from("expected"));
+
+        let mut config = ExerciseConfig::new("dice");
+        config.seed = Some(7);
+        assert_eq!(ExerciseConfig::parse(&config.render()).unwrap(), config);
+
+        assert!(ExerciseConfig::parse("timeout = soon").is_err());
+        assert!(ExerciseConfig::parse("seed = -1").is_err());
+        assert!(ExerciseConfig::parse("color = blue").is_err());
+    }
+
+    #[test]
+    fn scaffold_test() {
+        let parent = std::env::temp_dir().join(format!("lox-exercise-test-{}", std::process::id()));
+        let dir = scaffold(&parent, "fizzbuzz").unwrap();
+        let config = ExerciseConfig::load(&dir).unwrap();
+        let cases = crate::grade::load_cases(&dir.join(&config.expected)).unwrap();
+        let starter_exists = dir.join("starter.lox").is_file();
+        let test_exists = dir.join(HIDDEN_TEST).is_file();
+        let again = scaffold(&parent, "fizzbuzz");
+        std::fs::remove_dir_all(&parent).unwrap();
+
+        assert_eq!(config.name, "fizzbuzz");
+        assert_eq!(cases.len(), 1);
+        assert_eq!(cases[0].input, Some(String::new()));
+        assert!(starter_exists && test_exists);
+        assert!(again.is_err());
+        assert!(scaffold(&parent, "../escape").is_err());
+    }
+}
@@ -0,0 +1,453 @@
+//!
+//! This module contains the fixes behind `lox fix`, which rewrites a script to get rid of the lints
+//! that can be fixed mechanically. A [`Fix`] is a lint together with the edits of the source which
+//! fix it, so the rest of the source, including its comments and layout, is left as it was.
+//!
+//! The lints with fixes are single quoted strings, local variables which are never used, missing
+//! semicolons and conditions which assign a constant where they most likely meant to compare it.
+//! `lox fix --dry-run script.lox` prints the changes as a diff instead of writing them.
+
+use crate::lexer::{positions, Position, Token, TokenType};
+use crate::messages::{Language, MessageId};
+use std::collections::HashMap;
+use std::fmt;
+
+/// replaces the source from the start up to the end, which is not included, with the replacement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    pub start: Position,
+    pub end: Position,
+    pub replacement: String,
+}
+
+/// a lint and the edits which fix it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fix {
+    pub id: MessageId,
+    pub line: u32,
+    pub message: String,
+    pub edits: Vec<Edit>,
+}
+
+/// whether a token is a literal, which does nothing when it is evaluated
+fn is_literal(token_type: &TokenType) -> bool {
+    matches!(
+        token_type,
+        TokenType::Number(_)
+            | TokenType::BigInt(_)
+            | TokenType::String(_)
+            | TokenType::Bytes(_)
+            | TokenType::True
+            | TokenType::False
+            | TokenType::Nil
+    )
+}
+
+/// the position right after a token
+fn after(position: Position, token: &Token) -> Position {
+    Position {
+        line: position.line,
+        column: position.column + token.lexeme().len(),
+    }
+}
+
+/// finds the lints of the source which can be fixed. The tokens are the ones of the source, and the
+/// inferred semicolons the ones a parser with automatic semicolons inferred while parsing them.
+pub fn find_fixes(
+    source: &str,
+    tokens: &[Token],
+    inferred_semicolons: &[usize],
+    language: Language,
+) -> Vec<Fix> {
+    let positions = positions(source, tokens);
+    let mut fixes = Vec::new();
+    fixes.extend(single_quoted_strings(tokens, &positions, language));
+    fixes.extend(unused_variables(source, tokens, &positions, language));
+    fixes.extend(missing_semicolons(
+        tokens,
+        &positions,
+        inferred_semicolons,
+        language,
+    ));
+    fixes.extend(constant_conditions(tokens, &positions, language));
+    fixes.sort_by_key(|fix| fix.line);
+    fixes
+}
+
+/// writes single quoted strings with double quotes
+fn single_quoted_strings(
+    tokens: &[Token],
+    positions: &[Option<Position>],
+    language: Language,
+) -> Vec<Fix> {
+    tokens
+        .iter()
+        .zip(positions)
+        .filter_map(|(token, position)| {
+            let TokenType::String(value) = &token.token_type else {
+                return None;
+            };
+            let position = (*position)?;
+            if !token.lexeme().starts_with('\'') {
+                return None;
+            }
+            let replacement = format!("\"{}\"", value);
+            Some(Fix {
+                id: MessageId::SingleQuotedString,
+                line: token.line,
+                message: MessageId::SingleQuotedString.render(language, &[&replacement]),
+                edits: vec![Edit {
+                    start: position,
+                    end: after(position, token),
+                    replacement,
+                }],
+            })
+        })
+        .collect()
+}
+
+/// removes the declarations of variables inside of blocks whose name is used nowhere else in the
+/// source. Only declarations without an initializer or with a literal one are removed, since
+/// anything else could do something when it runs. Global variables are left alone because other
+/// scripts can use them.
+fn unused_variables(
+    source: &str,
+    tokens: &[Token],
+    positions: &[Option<Position>],
+    language: Language,
+) -> Vec<Fix> {
+    let mut uses: HashMap<&str, usize> = HashMap::new();
+    for token in tokens {
+        if token.token_type == TokenType::Identifier {
+            *uses.entry(token.lexeme()).or_default() += 1;
+        }
+    }
+
+    let lines: Vec<&str> = source.lines().collect();
+    let mut fixes = Vec::new();
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        match token.token_type {
+            TokenType::LeftBrace => depth += 1,
+            TokenType::RightBrace => depth -= 1,
+            TokenType::Var if depth > 0 => {
+                let Some(name) = tokens.get(i + 1) else {
+                    continue;
+                };
+                if uses.get(name.lexeme()) != Some(&1) {
+                    continue;
+                }
+                // var NAME ; | var NAME = literal ; | var NAME = - literal ;
+                let end = match tokens[i + 2..]
+                    .iter()
+                    .take(4)
+                    .map(|token| &token.token_type)
+                    .collect::<Vec<_>>()
+                    .as_slice()
+                {
+                    [TokenType::Semicolon, ..] => i + 2,
+                    [TokenType::Equal, literal, TokenType::Semicolon, ..]
+                        if is_literal(literal) =>
+                    {
+                        i + 4
+                    }
+                    [TokenType::Equal, TokenType::Minus, TokenType::Number(_), TokenType::Semicolon, ..] => {
+                        i + 5
+                    }
+                    _ => continue,
+                };
+                let (Some(mut start), Some(semicolon)) = (positions[i], positions[end]) else {
+                    continue;
+                };
+                let mut end = after(semicolon, &tokens[end]);
+                // a declaration on lines of its own is removed along with its lines
+                let before = &lines[start.line as usize - 1][..start.column];
+                let rest = &lines[end.line as usize - 1][end.column..];
+                if before.trim().is_empty() && rest.trim().is_empty() {
+                    start.column = 0;
+                    end = Position {
+                        line: end.line + 1,
+                        column: 0,
+                    };
+                }
+                fixes.push(Fix {
+                    id: MessageId::UnusedVariable,
+                    line: token.line,
+                    message: MessageId::UnusedVariable.render(language, &[name.lexeme()]),
+                    edits: vec![Edit {
+                        start,
+                        end,
+                        replacement: String::new(),
+                    }],
+                });
+            }
+            _ => {}
+        }
+    }
+    fixes
+}
+
+/// writes the semicolons which were inferred, after the token ending their statement
+fn missing_semicolons(
+    tokens: &[Token],
+    positions: &[Option<Position>],
+    inferred_semicolons: &[usize],
+    language: Language,
+) -> Vec<Fix> {
+    inferred_semicolons
+        .iter()
+        .filter_map(|index| {
+            let previous = index.checked_sub(1)?;
+            let token = tokens.get(previous)?;
+            let position = after((*positions.get(previous)?)?, token);
+            Some(Fix {
+                id: MessageId::MissingSemicolon,
+                line: token.line,
+                message: MessageId::MissingSemicolon.render(language, &[token.lexeme()]),
+                edits: vec![Edit {
+                    start: position,
+                    end: position,
+                    replacement: ";".to_string(),
+                }],
+            })
+        })
+        .collect()
+}
+
+/// replaces the `=` of an `if` or `while` condition which assigns a literal to a variable, and so is
+/// always the same, with `==`
+fn constant_conditions(
+    tokens: &[Token],
+    positions: &[Option<Position>],
+    language: Language,
+) -> Vec<Fix> {
+    tokens
+        .windows(6)
+        .zip(positions.windows(6))
+        .filter_map(|(window, positions)| {
+            let [keyword, left, name, equal, literal, right] = window else {
+                return None;
+            };
+            let condition = matches!(keyword.token_type, TokenType::If | TokenType::While)
+                && left.token_type == TokenType::LeftParen
+                && name.token_type == TokenType::Identifier
+                && equal.token_type == TokenType::Equal
+                && is_literal(&literal.token_type)
+                && right.token_type == TokenType::RightParen;
+            if !condition {
+                return None;
+            }
+            let position = positions[3]?;
+            Some(Fix {
+                id: MessageId::ConstantCondition,
+                line: equal.line,
+                message: MessageId::ConstantCondition
+                    .render(language, &[name.lexeme(), literal.lexeme()]),
+                edits: vec![Edit {
+                    start: position,
+                    end: after(position, equal),
+                    replacement: "==".to_string(),
+                }],
+            })
+        })
+        .collect()
+}
+
+/// the byte offset of a position in the source, where positions past its end are at its end
+fn offset(line_starts: &[usize], source: &str, position: Position) -> usize {
+    match line_starts.get(position.line as usize - 1) {
+        Some(start) => (start + position.column).min(source.len()),
+        None => source.len(),
+    }
+}
+
+/// applies the fixes to the source, returning the fixed source and the fixes which were applied. A fix
+/// with an edit overlapping one of a fix applied before it is skipped, running the fixes again will
+/// find it if it is still needed.
+pub fn apply(source: &str, fixes: Vec<Fix>) -> (String, Vec<Fix>) {
+    let line_starts = line_starts(source);
+    let mut edits: Vec<(usize, usize, String)> = Vec::new();
+    let mut applied = Vec::new();
+    for fix in fixes {
+        let ranges: Vec<_> = fix
+            .edits
+            .iter()
+            .map(|edit| {
+                (
+                    offset(&line_starts, source, edit.start),
+                    offset(&line_starts, source, edit.end),
+                    edit.replacement.clone(),
+                )
+            })
+            .collect();
+        let overlaps = ranges.iter().any(|(start, end, _)| {
+            edits.iter().any(|(other_start, other_end, _)| {
+                (start < other_end && other_start < end) || start == other_start
+            })
+        });
+        if !overlaps {
+            edits.extend(ranges);
+            applied.push(fix);
+        }
+    }
+
+    (splice(source, edits), applied)
+}
+
+/// applies edits which do not overlap to the source
+pub fn apply_edits(source: &str, edits: &[Edit]) -> String {
+    let line_starts = line_starts(source);
+    let edits = edits
+        .iter()
+        .map(|edit| {
+            (
+                offset(&line_starts, source, edit.start),
+                offset(&line_starts, source, edit.end),
+                edit.replacement.clone(),
+            )
+        })
+        .collect();
+    splice(source, edits)
+}
+
+/// the byte offset at which each line of the source starts
+fn line_starts(source: &str) -> Vec<usize> {
+    let mut line_starts = vec![0];
+    line_starts.extend(source.match_indices('\n').map(|(index, _)| index + 1));
+    line_starts
+}
+
+/// replaces the byte ranges of the source which do not overlap with their replacements
+fn splice(source: &str, mut edits: Vec<(usize, usize, String)>) -> String {
+    edits.sort_by_key(|(start, _, _)| *start);
+    let mut fixed = String::with_capacity(source.len());
+    let mut copied = 0;
+    for (start, end, replacement) in edits {
+        fixed.push_str(&source[copied..start]);
+        fixed.push_str(&replacement);
+        copied = end;
+    }
+    fixed.push_str(&source[copied..]);
+    fixed
+}
+
+/// the lines removed from and added to the source by fixing it, each change under a header with the
+/// line it starts in, like a unified diff without context.
+pub fn line_diff(before: &str, after: &str) -> String {
+    let before: Vec<&str> = before.lines().collect();
+    let after: Vec<&str> = after.lines().collect();
+
+    // the length of the longest common subsequence of the lines after i and j
+    let mut common = vec![vec![0; after.len() + 1]; before.len() + 1];
+    for i in (0..before.len()).rev() {
+        for j in (0..after.len()).rev() {
+            common[i][j] = if before[i] == after[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = String::new();
+    let (mut i, mut j) = (0, 0);
+    let mut in_change = false;
+    while i < before.len() || j < after.len() {
+        if i < before.len() && j < after.len() && before[i] == after[j] {
+            in_change = false;
+            i += 1;
+            j += 1;
+            continue;
+        }
+        if !in_change {
+            out.push_str(&format!("@@ line {} @@\n", i + 1));
+            in_change = true;
+        }
+        if j == after.len() || (i < before.len() && common[i + 1][j] >= common[i][j + 1]) {
+            out.push_str(&format!("-{}\n", before[i]));
+            i += 1;
+        } else {
+            out.push_str(&format!("+{}\n", after[j]));
+            j += 1;
+        }
+    }
+    out
+}
+
+/// the fixes applied to a script.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixReport {
+    pub name: String,
+    pub source: String,
+    pub fixed: String,
+    pub fixes: Vec<Fix>,
+}
+
+impl FixReport {
+    /// the changes made to the source, see [`line_diff`]
+    pub fn diff(&self) -> String {
+        line_diff(&self.source, &self.fixed)
+    }
+}
+
+/// prints a line for every fix and then the count
+impl fmt::Display for FixReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for fix in &self.fixes {
+            writeln!(f, "[line {}] Fixed : {}", fix.line, fix.message)?;
+        }
+        writeln!(f, "{}: {} fixes", self.name, self.fixes.len())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    /// fixes the source like `lox fix` does
+    fn fix(source: &str) -> (String, Vec<MessageId>) {
+        let tokens = Lexer::new().lex(source).unwrap();
+        let mut parser = Parser::new().with_automatic_semicolons(true);
+        parser.parse(tokens.clone());
+        let fixes = find_fixes(
+            source,
+            &tokens,
+            parser.inferred_semicolons(),
+            Language::English,
+        );
+        let (fixed, applied) = apply(source, fixes);
+        (fixed, applied.iter().map(|fix| fix.id).collect())
+    }
+
+    #[test]
+    /// tests every fix and that the rest of the source is left as it was
+    fn fix_test() {
+        let source =
+            "fun f() {\n  var unused = 1;\n  var b = 'b' // kept\n  if (b = \"b\") print b;\n}\n";
+        let (fixed, ids) = fix(source);
+        assert_eq!(
+            fixed,
+            "fun f() {\n  var b = \"b\"; // kept\n  if (b == \"b\") print b;\n}\n"
+        );
+        assert_eq!(
+            ids,
+            vec![
+                MessageId::UnusedVariable,
+                MessageId::SingleQuotedString,
+                MessageId::MissingSemicolon,
+                MessageId::ConstantCondition,
+            ]
+        );
+
+        // globals and initializers which could do something are left alone
+        let source = "var global = 1;\n{ var called = f(); }\n";
+        assert_eq!(fix(source), (source.to_string(), Vec::new()));
+
+        assert_eq!(
+            line_diff(source, "var global = 1;\n{ }\nprint 1;\n"),
+            "@@ line 2 @@\n-{ var called = f(); }\n+{ }\n+print 1;\n"
+        );
+    }
+}
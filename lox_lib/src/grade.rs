@@ -0,0 +1,612 @@
+//!
+//! This module contains the grading harness, which runs the Lox programs of a group of students and
+//! compares what they print against the expected output of an exercise.
+//!
+//! The expected directory holds a test case for every `<case>.out` file in it, with the stdin of the
+//! case in `<case>.in` if there is one, which programs read with `readLine()`. Every `.lox` file in
+//! the submissions directory is run once per case in a separate process, so an endless loop or a
+//! crash only fails the case it happened in. A case passes when the stdout of the program matches
+//! the expected output, ignoring line endings and trailing blank lines.
+//!
+//! An exercise can also have a hidden test, a script of `test_` functions which check a submission
+//! with the assert natives. It is run with `lox test` after importing the submission, so it can call
+//! the functions the submission declares, and counts as one more case named `hidden_test`.
+
+use crate::emit::json_string;
+use anyhow::{anyhow, Context, Result};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A test case of the exercise being graded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Case {
+    pub name: String,
+    /// what is written to the stdin of the program
+    pub input: Option<String>,
+    pub expected: String,
+}
+
+/// How a submission did on a single case.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Passed,
+    /// the output did not match, with a diff from the expected to the actual output, or the hidden
+    /// test failed, with what `lox test` printed
+    Failed {
+        diff: String,
+    },
+    /// the program was killed after running for longer than the timeout
+    TimedOut,
+    /// the program could not be run at all
+    Error(String),
+}
+
+impl Outcome {
+    fn name(&self) -> &'static str {
+        match self {
+            Outcome::Passed => "passed",
+            Outcome::Failed { .. } => "failed",
+            Outcome::TimedOut => "timed out",
+            Outcome::Error(_) => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaseResult {
+    pub case: String,
+    pub outcome: Outcome,
+}
+
+/// The results of one student's program across all of the cases.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubmissionReport {
+    pub name: String,
+    pub cases: Vec<CaseResult>,
+}
+
+impl SubmissionReport {
+    pub fn passed(&self) -> usize {
+        self.cases
+            .iter()
+            .filter(|result| result.outcome == Outcome::Passed)
+            .count()
+    }
+
+    /// the share of cases passed, from 0 to 1.
+    pub fn score(&self) -> f64 {
+        match self.cases.len() {
+            0 => 0.0,
+            total => self.passed() as f64 / total as f64,
+        }
+    }
+}
+
+/// The formats a [`GradeReport`] can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Json,
+    Csv,
+}
+
+impl std::str::FromStr for ReportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(format: &str) -> Result<Self> {
+        match format {
+            "json" => Ok(ReportFormat::Json),
+            "csv" => Ok(ReportFormat::Csv),
+            _ => Err(anyhow!(
+                "Unknown report format '{}', expected json or csv",
+                format
+            )),
+        }
+    }
+}
+
+/// The results of every submission, in the order of their file names.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GradeReport {
+    pub submissions: Vec<SubmissionReport>,
+}
+
+impl GradeReport {
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Json => self.to_json(),
+            ReportFormat::Csv => self.to_csv(),
+        }
+    }
+
+    /// the full report, including the outcome of every case and the diffs of failed ones.
+    pub fn to_json(&self) -> String {
+        let submissions = self
+            .submissions
+            .iter()
+            .map(|submission| {
+                let cases = submission
+                    .cases
+                    .iter()
+                    .map(|result| {
+                        let detail = match &result.outcome {
+                            Outcome::Failed { diff } => format!(",\"diff\":{}", json_string(diff)),
+                            Outcome::Error(err) => format!(",\"error\":{}", json_string(err)),
+                            _ => String::new(),
+                        };
+                        format!(
+                            "{{\"case\":{},\"outcome\":{}{}}}",
+                            json_string(&result.case),
+                            json_string(result.outcome.name()),
+                            detail
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                format!(
+                    "{{\"name\":{},\"passed\":{},\"total\":{},\"score\":{:.2},\"cases\":[{}]}}",
+                    json_string(&submission.name),
+                    submission.passed(),
+                    submission.cases.len(),
+                    submission.score(),
+                    cases.join(",")
+                )
+            })
+            .collect::<Vec<_>>();
+        format!("{{\"submissions\":[{}]}}\n", submissions.join(","))
+    }
+
+    /// a summary with one row per submission, for importing into a spreadsheet.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("name,passed,total,score\n");
+        for submission in &self.submissions {
+            out.push_str(&format!(
+                "{},{},{},{:.2}\n",
+                csv_field(&submission.name),
+                submission.passed(),
+                submission.cases.len(),
+                submission.score()
+            ));
+        }
+        out
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Runs submissions against the cases of an exercise.
+#[derive(Debug, Clone)]
+pub struct Grader {
+    command: PathBuf,
+    args: Vec<String>,
+    timeout: Duration,
+    hidden_test: Option<PathBuf>,
+}
+
+impl Grader {
+    /// creates a grader which runs every submission with `command <args>... <submission>`, usually
+    /// the lox binary itself.
+    pub fn new(command: PathBuf) -> Self {
+        Grader {
+            command,
+            args: Vec::new(),
+            timeout: Duration::from_secs(5),
+            hidden_test: None,
+        }
+    }
+
+    /// passes extra arguments to the command before the path of the submission.
+    pub fn with_args(mut self, args: Vec<String>) -> Self {
+        self.args = args;
+        self
+    }
+
+    /// sets how long a submission can run for on a single case. Defaults to 5 seconds.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// runs the hidden test against every submission as well, see the module documentation.
+    pub fn with_hidden_test(mut self, hidden_test: PathBuf) -> Self {
+        self.hidden_test = Some(hidden_test);
+        self
+    }
+
+    /// runs every `.lox` file in the submissions directory against the cases in the expected
+    /// directory, and the hidden test if there is one.
+    pub fn grade(&self, submissions: &Path, expected: &Path) -> Result<GradeReport> {
+        let cases = load_cases(expected)?;
+        let hidden_test = match &self.hidden_test {
+            Some(path) => Some(
+                std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read in file from {}", path.display()))?,
+            ),
+            None => None,
+        };
+        let mut report = GradeReport::default();
+        for path in files_with_extension(submissions, "lox")? {
+            let mut cases: Vec<CaseResult> = cases
+                .iter()
+                .map(|case| CaseResult {
+                    case: case.name.clone(),
+                    outcome: self.grade_case(&path, case),
+                })
+                .collect();
+            if let Some(hidden_test) = &hidden_test {
+                cases.push(CaseResult {
+                    case: "hidden_test".to_string(),
+                    outcome: self.grade_hidden_test(&path, hidden_test),
+                });
+            }
+            report.submissions.push(SubmissionReport {
+                name: file_stem(&path),
+                cases,
+            });
+        }
+        Ok(report)
+    }
+
+    fn grade_case(&self, script: &Path, case: &Case) -> Outcome {
+        match self.run(None, script, case.input.as_deref()) {
+            Ok(Some((output, _))) if normalize(&output) == normalize(&case.expected) => {
+                Outcome::Passed
+            }
+            Ok(Some((output, _))) => Outcome::Failed {
+                diff: diff(&normalize(&case.expected), &normalize(&output)),
+            },
+            Ok(None) => Outcome::TimedOut,
+            Err(err) => Outcome::Error(err.to_string()),
+        }
+    }
+
+    /// runs the hidden test with `lox test` from a script next to the submission which imports it
+    /// first, passing when every test does.
+    fn grade_hidden_test(&self, script: &Path, hidden_test: &str) -> Outcome {
+        let test = script.with_file_name(format!(
+            ".{}_hidden_test_{}.lox",
+            file_stem(script),
+            std::process::id()
+        ));
+        let source = format!(
+            "import \"{}\";\n{}",
+            script.file_name().unwrap_or_default().to_string_lossy(),
+            hidden_test
+        );
+        let result = std::fs::write(&test, source)
+            .with_context(|| format!("Failed to write {}", test.display()))
+            .and_then(|_| self.run(Some("test"), &test, None));
+        let _ = std::fs::remove_file(&test);
+        match result {
+            Ok(Some((_, true))) => Outcome::Passed,
+            Ok(Some((output, false))) => Outcome::Failed {
+                diff: normalize(&output),
+            },
+            Ok(None) => Outcome::TimedOut,
+            Err(err) => Outcome::Error(err.to_string()),
+        }
+    }
+
+    /// runs a script with the given stdin, after the subcommand if there is one, returning its stdout
+    /// and whether it exited successfully, or None if it timed out.
+    fn run(
+        &self,
+        subcommand: Option<&str>,
+        script: &Path,
+        input: Option<&str>,
+    ) -> Result<Option<(String, bool)>> {
+        let mut child = Command::new(&self.command)
+            .args(&self.args)
+            .args(subcommand)
+            .arg(script)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .with_context(|| format!("Failed to run {}", self.command.display()))?;
+
+        // stdin and stdout are handled on their own threads so that a program which fills up one
+        // pipe while the other is waiting on it can't block the grader
+        let mut stdin = child.stdin.take().context("Failed to open stdin")?;
+        let input = input.unwrap_or_default().to_string();
+        thread::spawn(move || stdin.write_all(input.as_bytes()));
+        let mut stdout = child.stdout.take().context("Failed to open stdout")?;
+        let reader = thread::spawn(move || {
+            let mut output = Vec::new();
+            stdout.read_to_end(&mut output).map(|_| output)
+        });
+
+        let deadline = Instant::now() + self.timeout;
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if Instant::now() >= deadline {
+                child.kill()?;
+                child.wait()?;
+                return Ok(None);
+            }
+            thread::sleep(Duration::from_millis(5));
+        };
+        let output = reader
+            .join()
+            .map_err(|_| anyhow!("Failed to read the output of {}", script.display()))??;
+        Ok(Some((
+            String::from_utf8_lossy(&output).into_owned(),
+            status.success(),
+        )))
+    }
+}
+
+/// reads in the cases of an exercise from its `.out` files and their matching `.in` files.
+pub fn load_cases(dir: &Path) -> Result<Vec<Case>> {
+    let cases = files_with_extension(dir, "out")?
+        .into_iter()
+        .map(|path| {
+            let expected = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read in file from {}", path.display()))?;
+            let input_path = path.with_extension("in");
+            let input = match input_path.exists() {
+                true => Some(std::fs::read_to_string(&input_path).with_context(|| {
+                    format!("Failed to read in file from {}", input_path.display())
+                })?),
+                false => None,
+            };
+            Ok(Case {
+                name: file_stem(&path),
+                input,
+                expected,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if cases.is_empty() {
+        return Err(anyhow!(
+            "No expected outputs (.out files) found in {}",
+            dir.display()
+        ));
+    }
+    Ok(cases)
+}
+
+/// the files in a directory with the given extension, sorted by name.
+fn files_with_extension(dir: &Path, extension: &str) -> Result<Vec<PathBuf>> {
+    let mut files = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read directory {}", dir.display()))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    files.retain(|path| path.is_file() && path.extension().is_some_and(|ext| ext == extension));
+    files.sort();
+    Ok(files)
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// makes outputs comparable across platforms by ignoring line endings and trailing blank lines.
+fn normalize(output: &str) -> String {
+    output
+        .replace("\r\n", "\n")
+        .trim_end_matches('\n')
+        .to_string()
+}
+
+/// a line by line diff from the expected to the actual text. Lines only in the expected text start
+/// with `-`, lines only in the actual text with `+` and lines in both with a space.
+pub fn diff(expected: &str, actual: &str) -> String {
+    let expected = expected.lines().collect::<Vec<_>>();
+    let actual = actual.lines().collect::<Vec<_>>();
+
+    // common[i][j] is the length of the longest common subsequence of expected[i..] and actual[j..]
+    let mut common = vec![vec![0usize; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            common[i][j] = if expected[i] == actual[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            out.push_str(&format!(" {}\n", expected[i]));
+            i += 1;
+            j += 1;
+        } else if j < actual.len() && (i == expected.len() || common[i][j + 1] >= common[i + 1][j])
+        {
+            out.push_str(&format!("+{}\n", actual[j]));
+            j += 1;
+        } else {
+            out.push_str(&format!("-{}\n", expected[i]));
+            i += 1;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn diff_test() {
+        assert_eq!(diff("1\n2\n3", "1\n2\n3"), " 1\n 2\n 3\n");
+        assert_eq!(diff("1\n2\n3", "1\n4\n3\n5"), " 1\n+4\n-2\n 3\n+5\n");
+        assert_eq!(diff("", "1"), "+1\n");
+    }
+
+    #[test]
+    fn report_test() {
+        let report = GradeReport {
+            submissions: vec![SubmissionReport {
+                name: "ada, l".to_string(),
+                cases: vec![
+                    CaseResult {
+                        case: "one".to_string(),
+                        outcome: Outcome::Passed,
+                    },
+                    CaseResult {
+                        case: "two".to_string(),
+                        outcome: Outcome::Failed {
+                            diff: "-1\n+2\n".to_string(),
+                        },
+                    },
+                    CaseResult {
+                        case: "three".to_string(),
+                        outcome: Outcome::TimedOut,
+                    },
+                ],
+            }],
+        };
+        assert_eq!(
+            report.render(ReportFormat::Csv),
+            "name,passed,total,score\n\"ada, l\",1,3,0.33\n"
+        );
+        assert_eq!(
+            report.render(ReportFormat::Json),
+            "{\"submissions\":[{\"name\":\"ada, l\",\"passed\":1,\"total\":3,\"score\":0.33,\"cases\":[\
+            {\"case\":\"one\",\"outcome\":\"passed\"},\
+            {\"case\":\"two\",\"outcome\":\"failed\",\"diff\":\"-1\\n+2\\n\"},\
+            {\"case\":\"three\",\"outcome\":\"timed out\"}]}]}\n"
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    /// grades shell scripts instead of Lox programs, so the test does not depend on the lox binary
+    fn grade_test() {
+        let root = std::env::temp_dir().join(format!("lox-grade-test-{}", std::process::id()));
+        let submissions = root.join("submissions");
+        let expected = root.join("expected");
+        std::fs::create_dir_all(&submissions).unwrap();
+        std::fs::create_dir_all(&expected).unwrap();
+        std::fs::write(expected.join("echo.in"), "hello\n").unwrap();
+        std::fs::write(expected.join("echo.out"), "hello\n").unwrap();
+        std::fs::write(expected.join("count.out"), "1\n2\n").unwrap();
+        // echoes its input and counts to 3 when there is none
+        std::fs::write(
+            submissions.join("a.lox"),
+            "read line\necho $line\n[ -z \"$line\" ] && printf '1\\r\\n2\\r\\n3\\n'\n",
+        )
+        .unwrap();
+        // never reads its input, so it hangs until it is killed
+        std::fs::write(submissions.join("b.lox"), "sleep 10\n").unwrap();
+        std::fs::write(submissions.join("notes.txt"), "not a submission").unwrap();
+
+        let report = Grader::new(PathBuf::from("sh"))
+            .with_timeout(Duration::from_millis(200))
+            .grade(&submissions, &expected)
+            .unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(report.submissions.len(), 2);
+        let a = &report.submissions[0];
+        assert_eq!(a.name, "a");
+        assert_eq!(
+            a.cases,
+            vec![
+                CaseResult {
+                    case: "count".to_string(),
+                    outcome: Outcome::Failed {
+                        diff: "+\n 1\n 2\n+3\n".to_string(),
+                    },
+                },
+                CaseResult {
+                    case: "echo".to_string(),
+                    outcome: Outcome::Passed,
+                },
+            ]
+        );
+        assert_eq!(a.score(), 0.5);
+        let b = &report.submissions[1];
+        assert!(b
+            .cases
+            .iter()
+            .all(|result| result.outcome == Outcome::TimedOut));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    /// grades with a shell script standing in for lox, which as `lox test` passes when the
+    /// submission imported by the hidden test prints ok
+    fn hidden_test_test() {
+        let root = std::env::temp_dir().join(format!("lox-hidden-test-{}", std::process::id()));
+        let submissions = root.join("submissions");
+        let expected = root.join("expected");
+        std::fs::create_dir_all(&submissions).unwrap();
+        std::fs::create_dir_all(&expected).unwrap();
+        std::fs::write(expected.join("example.out"), "ok\n").unwrap();
+        std::fs::write(root.join("hidden_test.lox"), "fun test_ok() {}\n").unwrap();
+        std::fs::write(submissions.join("a.lox"), "ok").unwrap();
+        std::fs::write(submissions.join("b.lox"), "not ok").unwrap();
+
+        let script = "if [ \"$0\" = test ]; then\n\
+                cd \"$(dirname \"$1\")\" && grep -q 'fun test_ok' \"$1\" &&\n\
+                submission=$(sed -n 's/^import \"\\(.*\\)\";$/\\1/p' \"$1\") &&\n\
+                [ \"$(cat \"$submission\")\" = ok ] || { echo failed; exit 1; }\n\
+            else cat \"$0\"; fi";
+        let report = Grader::new(PathBuf::from("sh"))
+            .with_args(vec!["-c".to_string(), script.to_string()])
+            .with_hidden_test(root.join("hidden_test.lox"))
+            .grade(&submissions, &expected)
+            .unwrap();
+        let leftovers = std::fs::read_dir(&submissions).unwrap().count();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        let outcomes: Vec<_> = report
+            .submissions
+            .iter()
+            .map(|submission| {
+                let outcomes: Vec<_> = submission
+                    .cases
+                    .iter()
+                    .map(|result| (result.case.as_str(), result.outcome.clone()))
+                    .collect();
+                (submission.score(), outcomes)
+            })
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                (
+                    1.0,
+                    vec![
+                        ("example", Outcome::Passed),
+                        ("hidden_test", Outcome::Passed)
+                    ]
+                ),
+                (
+                    0.0,
+                    vec![
+                        (
+                            "example",
+                            Outcome::Failed {
+                                diff: "+not ok\n-ok\n".to_string()
+                            }
+                        ),
+                        (
+                            "hidden_test",
+                            Outcome::Failed {
+                                diff: "failed".to_string()
+                            }
+                        ),
+                    ]
+                ),
+            ]
+        );
+        assert_eq!(leftovers, 2);
+    }
+}
@@ -0,0 +1,129 @@
+//!
+//! This module contains the accounting behind the heap limit of the interpreter, see
+//! [`crate::interpreter::Interpreter::with_heap_limit`].
+//!
+//! Values are reference counted rather than garbage collected, so the interpreter can not know how
+//! much of the memory it allocated is still in use. Instead the places which allocate add an estimate
+//! of what they allocated to a running total, and only once the total passes the limit is the heap
+//! measured, by walking every value reachable from the environments of the program the way the mark
+//! phase of a garbage collector would. Values which are no longer reachable are not counted, so a
+//! program only runs out of memory when what it holds on to is over the limit.
+//!
+//! The sizes are estimates: every value takes the size of a [`Literal`] where it is stored, plus the
+//! bytes of its string, elements or fields. Values shared between several places are counted once.
+
+use crate::ast::Literal;
+use crate::interpreter::{Environment, LoxClass};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::mem::size_of;
+use std::rc::Rc;
+
+/// the bytes of a slot holding a value, e.g. an element of a list or a variable
+pub const SLOT: usize = size_of::<Literal>();
+
+/// measures the bytes of the values reachable from some roots, counting shared values once
+#[derive(Default)]
+pub struct HeapMeter {
+    seen: HashSet<*const ()>,
+    bytes: usize,
+}
+
+impl HeapMeter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// the bytes of everything measured so far
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// whether the object at the address is measured for the first time
+    fn first_visit<T: ?Sized>(&mut self, object: *const T) -> bool {
+        self.seen.insert(object as *const ())
+    }
+
+    /// measures a value held somewhere, without the slot holding it
+    pub fn literal(&mut self, value: &Literal) {
+        match value {
+            Literal::String(string) => self.bytes += string.len(),
+            Literal::List(list) => {
+                if self.first_visit(Rc::as_ptr(list)) {
+                    let list = list.borrow();
+                    self.bytes += list.len() * SLOT;
+                    list.iter().for_each(|element| self.literal(element));
+                }
+            }
+            Literal::Map(map) => {
+                if self.first_visit(map.as_ptr()) {
+                    for (key, value) in map.entries() {
+                        self.bytes += 2 * SLOT;
+                        self.literal(&key.to_literal());
+                        self.literal(&value);
+                    }
+                }
+            }
+            Literal::Instance(instance) => {
+                if self.first_visit(Rc::as_ptr(instance)) {
+                    for (name, value) in instance.fields() {
+                        self.bytes += name.len() + SLOT;
+                        self.literal(&value);
+                    }
+                    self.class(&instance.class);
+                }
+            }
+            Literal::Callable(callable) => {
+                if self.first_visit(Rc::as_ptr(callable)) {
+                    if let Some(closure) = callable.closure() {
+                        self.environment(&closure);
+                    }
+                    if let Some(class) = callable.clone().as_class() {
+                        self.class(&class);
+                    }
+                }
+            }
+            Literal::Bytes(bytes) => {
+                if self.first_visit(Rc::as_ptr(bytes)) {
+                    self.bytes += bytes.len();
+                }
+            }
+            #[cfg(feature = "bigint")]
+            Literal::BigInt(number) => self.bytes += number.bits().div_ceil(8) as usize,
+            Literal::Number(_)
+            | Literal::Decimal(_)
+            | Literal::Boolean(_)
+            | Literal::Nil
+            | Literal::Handle(_)
+            | Literal::DateTime(_) => {}
+        }
+    }
+
+    /// measures the variables of an environment and of the ones enclosing it
+    pub fn environment(&mut self, environment: &Rc<RefCell<Environment>>) {
+        if !self.first_visit(Rc::as_ptr(environment)) {
+            return;
+        }
+        let environment = environment.borrow();
+        for (name, value) in environment.iter() {
+            self.bytes += name.len() + SLOT;
+            self.literal(value);
+        }
+        if let Some(enclosing) = environment.enclosing_rc() {
+            self.environment(&enclosing);
+        }
+    }
+
+    /// measures the scopes the methods of a class and its superclasses were declared in
+    fn class(&mut self, class: &Rc<LoxClass>) {
+        if !self.first_visit(Rc::as_ptr(class)) {
+            return;
+        }
+        for closure in class.closures() {
+            self.environment(&closure);
+        }
+        if let Some(superclass) = &class.superclass {
+            self.class(superclass);
+        }
+    }
+}
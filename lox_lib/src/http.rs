@@ -0,0 +1,407 @@
+//!
+//! This module contains the HTTP plugin, which adds a blocking HTTP client to Lox with
+//! `httpGet(url)` and `httpPost(url, body)`. Both return a map with the `status`, the `headers` and
+//! the `body` of the response. The headers are a map from their names in lowercase to their values,
+//! where the values of a header sent more than once are joined with commas:
+//!
+//! ```text
+//! var response = httpGet("http://localhost:8080/health");
+//! if (response["status"] != 200) print response["headers"]["content-type"];
+//! ```
+//!
+//! The natives need the network capability, which the embedder grants with
+//! [`Interpreter::with_capability`], or `lox --allow network`. The client speaks plain HTTP/1.1
+//! over TCP and has no TLS, so `https` URLs are an error. The plugin is only built with the `net`
+//! feature.
+
+use crate::analyze::Capability;
+use crate::ast::Literal;
+use crate::interpreter::{Interpreter, LoxError};
+use crate::map::{LoxMap, MapKey};
+use crate::natives::NativeFunction;
+use crate::plugin::{LoxPlugin, Registry};
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// how long connecting, sending the request or waiting for the response can take
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The HTTP client plugin, enabled as `http`.
+pub struct HttpPlugin;
+
+impl LoxPlugin for HttpPlugin {
+    fn name(&self) -> &'static str {
+        "http"
+    }
+
+    fn register(&self, registry: &mut Registry) {
+        registry
+            .native(
+                NativeFunction::new("httpGet", 1, http_get)
+                    .with_doc(&["url"], "sends a GET request and returns the response")
+                    .with_capability(Capability::Network),
+            )
+            .native(
+                NativeFunction::new("httpPost", 2, http_post)
+                    .with_doc(
+                        &["url", "body"],
+                        "sends a POST request and returns the response",
+                    )
+                    .with_capability(Capability::Network),
+            );
+    }
+}
+
+fn http_get(interpreter: &mut Interpreter, arguments: &[Literal], line: u32) -> Result<Literal> {
+    request(interpreter, "httpGet", &arguments[0], None, line)
+}
+
+fn http_post(interpreter: &mut Interpreter, arguments: &[Literal], line: u32) -> Result<Literal> {
+    let Literal::String(body) = &arguments[1] else {
+        return Err(error(line, "httpPost expects the body to be a string"));
+    };
+    request(interpreter, "httpPost", &arguments[0], Some(body), line)
+}
+
+fn error(line: u32, message: impl Into<String>) -> anyhow::Error {
+    anyhow!(LoxError::new(line, message))
+}
+
+/// sends a GET request, or a POST request when there is a body, and returns the response as a map
+fn request(
+    interpreter: &mut Interpreter,
+    native: &str,
+    url: &Literal,
+    body: Option<&str>,
+    line: u32,
+) -> Result<Literal> {
+    interpreter.require_capability(Capability::Network, native, line)?;
+    let Literal::String(url) = url else {
+        return Err(error(
+            line,
+            format!("{} expects the url to be a string", native),
+        ));
+    };
+    let response = Url::parse(url)
+        .and_then(|url| send(&url, body))
+        .map_err(|err| error(line, format!("{} {} failed: {}", native, url, err)))?;
+
+    let headers = LoxMap::new(HashMap::new());
+    for (name, value) in response.headers {
+        let name = MapKey::String(name.to_lowercase());
+        let value = match headers.get(&name) {
+            Some(Literal::String(previous)) => format!("{}, {}", previous, value),
+            _ => value,
+        };
+        headers.insert(name, Literal::String(value));
+    }
+    let fields = [
+        ("status", Literal::Number(response.status as f64)),
+        ("headers", Literal::Map(headers)),
+        ("body", Literal::String(response.body)),
+    ];
+    Ok(Literal::Map(LoxMap::new(
+        fields
+            .into_iter()
+            .map(|(name, value)| (MapKey::String(name.to_string()), value))
+            .collect(),
+    )))
+}
+
+/// the parts of an `http://host[:port][/path]` URL the client needs
+#[derive(Debug, PartialEq)]
+struct Url {
+    host: String,
+    port: u16,
+    /// the path and query, which start with a `/`
+    path: String,
+}
+
+impl Url {
+    fn parse(url: &str) -> Result<Self> {
+        if url.starts_with("https://") {
+            return Err(anyhow!("https is not supported, only http"));
+        }
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| anyhow!("expected a URL starting with http://"))?;
+        let (authority, path) = match rest.find('/') {
+            Some(index) => rest.split_at(index),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (
+                host,
+                port.parse().map_err(|_| anyhow!("invalid port {}", port))?,
+            ),
+            None => (authority, 80),
+        };
+        if host.is_empty() {
+            return Err(anyhow!("expected a host"));
+        }
+        Ok(Url {
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+}
+
+/// a response as read off the connection
+#[derive(Debug, PartialEq)]
+struct Response {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl Response {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// parses a whole response, whose connection was closed by the server
+    fn parse(bytes: &[u8]) -> Result<Self> {
+        let end = bytes
+            .windows(4)
+            .position(|window| window == b"\r\n\r\n")
+            .ok_or_else(|| anyhow!("the response has no end of headers"))?;
+        let head = String::from_utf8_lossy(&bytes[..end]);
+        let mut lines = head.split("\r\n");
+        let status = lines
+            .next()
+            .and_then(|status_line| status_line.split(' ').nth(1))
+            .and_then(|status| status.parse().ok())
+            .ok_or_else(|| anyhow!("the response has no status"))?;
+        let headers = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+            .collect();
+        let mut response = Response {
+            status,
+            headers,
+            body: String::new(),
+        };
+
+        let body = &bytes[end + 4..];
+        let body = if response
+            .header("transfer-encoding")
+            .is_some_and(|encoding| encoding.eq_ignore_ascii_case("chunked"))
+        {
+            dechunk(body)?
+        } else {
+            match response.header("content-length") {
+                Some(length) => {
+                    let length: usize = length
+                        .parse()
+                        .map_err(|_| anyhow!("invalid content length {}", length))?;
+                    body.get(..length)
+                        .ok_or_else(|| anyhow!("the response body was cut short"))?
+                        .to_vec()
+                }
+                None => body.to_vec(),
+            }
+        };
+        response.body = String::from_utf8_lossy(&body).into_owned();
+        Ok(response)
+    }
+}
+
+/// joins the chunks of a body sent with `Transfer-Encoding: chunked`
+fn dechunk(mut body: &[u8]) -> Result<Vec<u8>> {
+    let mut joined = Vec::new();
+    loop {
+        let end = body
+            .windows(2)
+            .position(|window| window == b"\r\n")
+            .ok_or_else(|| anyhow!("the response body was cut short"))?;
+        let size = String::from_utf8_lossy(&body[..end]);
+        // chunk extensions after a `;` are ignored
+        let size = size.split(';').next().unwrap_or_default().trim();
+        let size =
+            usize::from_str_radix(size, 16).map_err(|_| anyhow!("invalid chunk size {}", size))?;
+        if size == 0 {
+            return Ok(joined);
+        }
+        let chunk = body
+            .get(end + 2..end + 2 + size)
+            .ok_or_else(|| anyhow!("the response body was cut short"))?;
+        joined.extend_from_slice(chunk);
+        body = body.get(end + 4 + size..).unwrap_or_default();
+    }
+}
+
+/// sends a request, reading the response until the server closes the connection
+fn send(url: &Url, body: Option<&str>) -> Result<Response> {
+    let address = (url.host.as_str(), url.port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| anyhow!("could not resolve {}", url.host))?;
+    let mut stream = TcpStream::connect_timeout(&address, TIMEOUT)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+
+    let method = if body.is_some() { "POST" } else { "GET" };
+    let mut request = format!(
+        "{} {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: lox\r\nConnection: close\r\n",
+        method, url.path, url.host
+    );
+    if let Some(body) = body {
+        request.push_str(&format!(
+            "Content-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\n",
+            body.len()
+        ));
+    }
+    request.push_str("\r\n");
+    request.push_str(body.unwrap_or_default());
+    stream.write_all(request.as_bytes())?;
+
+    let mut bytes = Vec::new();
+    stream.read_to_end(&mut bytes)?;
+    Response::parse(&bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// serves one connection, answering with the response and returning the request it read
+    fn serve(response: &'static str) -> (String, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0; 1024];
+            // reads the headers, and then the body they give the length of
+            let complete = |request: &[u8]| {
+                let request = String::from_utf8_lossy(request);
+                let Some((head, body)) = request.split_once("\r\n\r\n") else {
+                    return false;
+                };
+                let length = head
+                    .lines()
+                    .find_map(|line| line.strip_prefix("Content-Length: "))
+                    .map_or(0, |length| length.parse().unwrap());
+                body.len() >= length
+            };
+            while !complete(&request) {
+                let read = stream.read(&mut buffer).unwrap();
+                request.extend_from_slice(&buffer[..read]);
+            }
+            stream.write_all(response.as_bytes()).unwrap();
+            String::from_utf8(request).unwrap()
+        });
+        (format!("http://{}", address), handle)
+    }
+
+    /// calls the native with the given name
+    fn call(
+        interpreter: &mut Interpreter,
+        native: &str,
+        arguments: Vec<Literal>,
+        line: u32,
+    ) -> Result<Literal> {
+        let native = interpreter.globals().get(native).unwrap();
+        interpreter.call_value(native, arguments, line)
+    }
+
+    #[test]
+    fn url_test() {
+        assert_eq!(
+            Url::parse("http://localhost:8080/a?b=c").unwrap(),
+            Url {
+                host: "localhost".to_string(),
+                port: 8080,
+                path: "/a?b=c".to_string()
+            }
+        );
+        assert_eq!(Url::parse("http://example.com").unwrap().path, "/");
+        assert_eq!(Url::parse("http://example.com").unwrap().port, 80);
+        assert!(Url::parse("https://example.com").is_err());
+        assert!(Url::parse("example.com").is_err());
+        assert!(Url::parse("http://example.com:http/").is_err());
+    }
+
+    #[test]
+    /// tests that requests are sent and their responses read, including chunked ones
+    fn http_test() {
+        let mut interpreter = Interpreter::new()
+            .with_plugin(&HttpPlugin)
+            .unwrap()
+            .with_capability(Capability::Network);
+
+        let (url, server) = serve(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nX-Lox: yes\r\nx-lox: again\r\n\r\n\
+            3\r\nhel\r\n2;ext\r\nlo\r\n0\r\n\r\n",
+        );
+        let response = call(
+            &mut interpreter,
+            "httpGet",
+            vec![Literal::String(format!("{}/greeting", url))],
+            1,
+        )
+        .unwrap();
+        assert_eq!(
+            response.to_string(),
+            "{\"body\": \"hello\", \"headers\": {\"transfer-encoding\": \"chunked\", \
+            \"x-lox\": \"yes, again\"}, \"status\": 200}"
+        );
+        assert!(server
+            .join()
+            .unwrap()
+            .starts_with("GET /greeting HTTP/1.1\r\n"));
+
+        let (url, server) = serve("HTTP/1.1 404 Not Found\r\nContent-Length: 4\r\n\r\ngone");
+        let response = call(
+            &mut interpreter,
+            "httpPost",
+            vec![Literal::String(url), Literal::String("ping".to_string())],
+            1,
+        )
+        .unwrap();
+        let Literal::Map(response) = response else {
+            panic!("expected a response, got {}", response);
+        };
+        let field = |name: &str| response.get(&MapKey::String(name.to_string()));
+        assert_eq!(field("status"), Some(Literal::Number(404.0)));
+        assert_eq!(field("body"), Some(Literal::String("gone".to_string())));
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST / HTTP/1.1\r\n"));
+        assert!(request.contains("Content-Length: 4\r\n"));
+        assert!(request.ends_with("\r\n\r\nping"));
+    }
+
+    #[test]
+    /// tests that the natives are marked as needing the network capability, and fail without it
+    fn capability_test() {
+        assert!(Interpreter::new().native_capabilities().is_empty());
+        let mut interpreter = Interpreter::new().with_plugin(&HttpPlugin).unwrap();
+        assert_eq!(
+            interpreter.native_capabilities(),
+            HashMap::from([
+                ("httpGet".to_string(), Capability::Network),
+                ("httpPost".to_string(), Capability::Network)
+            ])
+        );
+        let err = call(
+            &mut interpreter,
+            "httpGet",
+            vec![Literal::String("http://localhost/".to_string())],
+            3,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "[line 3] Error : httpGet needs the network capability, which the interpreter was not granted"
+        );
+    }
+}
@@ -1,358 +1,5120 @@
-use crate::ast::{ExprNode, ExprVisitor, Literal, Operator, StmtNode, StmtVisitor};
-use crate::lexer::Lexer;
+use crate::analyze::{AuditReport, CallGraph, Capability, StringReport};
+use crate::ast::{
+    exports, ClassDecl, ExprNode, ExprVisitor, FunctionDecl, Literal, Operator, StmtNode,
+    StmtVisitor,
+};
+use crate::bench::{BenchReport, BenchResult};
+#[cfg(feature = "host")]
+use crate::cache::ParseCache;
+use crate::crash::Phase;
+#[cfg(feature = "host")]
+use crate::crash::{self, CrashReport, CrashReporter};
+use crate::diff::show;
+use crate::dry_run::{DryRun, Effect, EffectsTrace};
+use crate::emit::{self, Emit, EmitFormat};
+use crate::fix::{self, FixReport};
+use crate::heap::{self, HeapMeter};
+use crate::lexer::{Lexer, Position, Token, TokenType};
+#[cfg(feature = "host")]
+use crate::loader::FileSystemLoader;
+#[cfg(not(feature = "host"))]
+use crate::loader::InMemoryLoader;
+use crate::loader::ModuleLoader;
+use crate::map::{LoxMap, MapKey};
+use crate::messages::{Language, LintLevel, MessageId};
+use crate::minify;
+use crate::natives;
+use crate::number::NumberMode;
 use crate::parser::Parser;
-use anyhow::{anyhow, Context, Result};
+#[cfg(feature = "host")]
+use crate::pipeline::Resolved;
+use crate::pipeline::{Pipeline, Trace};
+use crate::plugin::{LoxPlugin, Registry};
+use crate::random::Random;
+use crate::refactor::RenameReport;
+use crate::semantic;
+use crate::stack::{Frame, StackTrace, TraceOptions};
+#[cfg(feature = "host")]
+use crate::testing::{self, TestReport, TestResult};
+use crate::unicode;
+use anyhow::{anyhow, Result};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{BufRead, LineWriter, Write};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+#[cfg(feature = "host")]
+use std::time::Instant;
 
 /// the interpreter is responsible for running lox programs either form a file or a REPL
-pub struct Interpreter;
+pub struct Interpreter {
+    /// the language that diagnostic messages are reported in
+    language: Language,
+    metrics: Metrics,
+    cancelled: CancellationToken,
+    /// where the output of print statements and reported errors is written to, a line at a time
+    output: LineWriter<Box<dyn Write + Send>>,
+    /// where `eprint` writes to
+    error_output: Box<dyn Write + Send>,
+    /// where `readLine` reads the lines of the program's input from
+    input: Box<dyn BufRead + Send>,
+    /// compile time constants handed to the parser, which persist between runs in the REPL
+    defines: HashMap<String, Literal>,
+    /// the defines given to the interpreter, which take precedence over the ones of the source
+    overrides: HashMap<String, Literal>,
+    /// resolves the paths of scripts to their source
+    loader: Box<dyn ModuleLoader + Send>,
+    /// the modules registered by plugins, which are found before the scripts of the loader
+    modules: HashMap<String, String>,
+    /// the names of the plugins enabled so far
+    plugins: Vec<&'static str>,
+    /// whether keywords are recognized regardless of their case
+    case_insensitive_keywords: bool,
+    /// whether statements can end at a line break instead of a semicolon
+    automatic_semicolons: bool,
+    /// whether to warn about code which relies on extensions to canonical Lox
+    pedantic: bool,
+    /// whether `+` turns the other operand into a string when one of them is a string
+    string_concatenation: bool,
+    /// the levels of the lints which were set, the others are warned about in pedantic mode and
+    /// allowed otherwise
+    lint_levels: HashMap<MessageId, LintLevel>,
+    /// whether to carry on with the next statement after one fails, rather than stopping
+    continue_on_error: bool,
+    /// the lints already reported in the current run and the lines they were reported for, so that
+    /// code which runs many times only warns once
+    warned: HashSet<(u32, MessageId)>,
+    /// how print statements format numbers
+    number_format: NumberFormat,
+    /// how the number operators do their arithmetic
+    number_mode: NumberMode,
+    /// the variables of the innermost scope being executed, which persist between runs in the REPL
+    environment: Rc<RefCell<Environment>>,
+    /// writes a report when the interpreter panics, instead of letting the panic unwind
+    #[cfg(feature = "host")]
+    crash_reporter: Option<CrashReporter>,
+    /// the phase the source being run is in, which is included in crash reports
+    phase: Phase,
+    /// whether the trace of a run keeps the tokens and syntax tree of the program
+    trace_artifacts: bool,
+    /// the trace of the pipeline for the last source that was run
+    last_trace: Option<Trace>,
+    /// the function calls currently running with the lines they were called in, from the outermost
+    /// to the innermost one
+    frames: Vec<(Rc<dyn LoxCallable>, u32)>,
+    /// where the last source run was when it was cancelled, if it was
+    interrupted_at: Option<StackTrace>,
+    /// the calls the error being reported was raised in, if it was raised inside of a call
+    error_trace: Option<StackTrace>,
+    /// how stack traces are shown
+    trace_options: TraceOptions,
+    /// the value of the return statement unwinding to the function call being run
+    return_value: Option<Literal>,
+    /// the call of the return statement unwinding to the function call being run, which makes it
+    /// in place of the call that returned, see [`Interpreter::call_function`]
+    tail_call: Option<(Rc<LoxFunction>, Vec<Literal>, u32)>,
+    /// the try statements around the statement being executed inside of the innermost call, which
+    /// have to catch what the calls of its return statements throw
+    try_depth: usize,
+    /// the value of the throw statement unwinding to the try statement catching it
+    thrown: Option<Literal>,
+    /// the canned values of the stubs when natives needing a capability are stubbed out
+    dry_run: Option<DryRun>,
+    /// the calls of stubs made during a dry run
+    effects: EffectsTrace,
+    /// the capabilities the embedder granted, which natives check before touching the outside world
+    capabilities: HashSet<Capability>,
+    /// the generator behind the random natives
+    random: Random,
+    /// the seed the embedder gave the generator, if any
+    seed: Option<u64>,
+    /// the results of the benchmarks run with `bench`
+    benchmarks: BenchReport,
+    /// the value of the last expression or print statement executed
+    last_value: Option<Literal>,
+    /// where the syntax trees of scripts are cached, if they are
+    #[cfg(feature = "host")]
+    parse_cache: Option<ParseCache>,
+    /// the environments holding the globals of the modules imported so far by path, so that every
+    /// module only runs once however often it is imported
+    imported: HashMap<String, Rc<RefCell<Environment>>>,
+    /// the names exported by the modules imported so far which export any, by path
+    exports: HashMap<String, HashSet<String>>,
+    /// the paths of the modules being imported, from the outermost to the innermost import, to
+    /// report circular imports
+    importing: Vec<String>,
+    /// the environments of the scopes which are waiting for a nested scope to finish, from the
+    /// outermost to the innermost one
+    suspended: Vec<Rc<RefCell<Environment>>>,
+    /// the bytes the values held by the program can take before it runs out of memory, if limited
+    heap_limit: Option<usize>,
+    /// the bytes in use when the heap was last measured plus the ones allocated since, see
+    /// [`crate::heap`]
+    heap_allocated: usize,
+}
 
-struct RuntimeErr {
-    message: String,
-    line: u32,
+/// the deepest function calls can be nested before the interpreter reports a stack overflow, since
+/// every call recurses on the Rust stack. Calls in tail position take the place of their caller
+/// instead, so they are never nested.
+pub const MAX_CALL_DEPTH: usize = 255;
+
+/// A value which can be called, such as a function declared in a Lox program or a native one. The
+/// interpreter checks the arity and the depth of calls before dispatching to [`LoxCallable::call`].
+pub trait LoxCallable: fmt::Debug + fmt::Display {
+    /// the number of arguments the callable takes, or None if it takes any number of them
+    fn arity(&self) -> Option<usize>;
+
+    /// calls the callable with arguments matching its arity. The line is the one of the call.
+    fn call(
+        self: Rc<Self>,
+        interpreter: &mut Interpreter,
+        arguments: Vec<Literal>,
+        line: u32,
+    ) -> Result<Literal>;
+
+    /// the callable as a class, which only classes are
+    fn as_class(self: Rc<Self>) -> Option<Rc<LoxClass>> {
+        None
+    }
+
+    /// the callable as a function declared in Lox, which only those are
+    fn as_function(self: Rc<Self>) -> Option<Rc<LoxFunction>> {
+        None
+    }
+
+    /// how the callable is called, as shown by `:doc` in the REPL
+    fn signature(&self) -> String {
+        self.to_string()
+    }
+
+    /// the doc comment of the callable, if it has one
+    fn doc(&self) -> Option<&str> {
+        None
+    }
+
+    /// the scope the callable was declared in, which keeps its values alive, see [`crate::heap`]
+    fn closure(&self) -> Option<Rc<RefCell<Environment>>> {
+        None
+    }
+
+    /// whether the callable is implemented in Rust rather than declared in Lox
+    fn is_native(&self) -> bool {
+        false
+    }
+
+    /// the capability the callable needs to reach the outside world, which only some natives do
+    fn capability(&self) -> Option<Capability> {
+        None
+    }
 }
 
-pub enum InterpreterMode {
-    Script(String),
-    Repl,
+/// a callable is only equal to itself, even if another one has the same declaration
+impl PartialEq for dyn LoxCallable {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::addr_eq(self, other)
+    }
 }
 
-impl Interpreter {
-    pub fn new() -> Self {
-        Interpreter
+impl PartialOrd for dyn LoxCallable {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        (self == other).then_some(std::cmp::Ordering::Equal)
     }
+}
 
-    pub fn run(&mut self, mode: InterpreterMode) -> Result<()> {
-        match mode {
-            InterpreterMode::Script(path) => self.run_script(path),
-            InterpreterMode::Repl => self.run_repl(),
+/// A function declared in a Lox program, together with the environment it was declared in.
+pub struct LoxFunction {
+    pub declaration: Rc<FunctionDecl>,
+    /// the scope the function was declared in, which its body can still see when it is called from
+    /// somewhere else
+    closure: Rc<RefCell<Environment>>,
+    /// whether this is the `init` method of a class, which always returns the instance
+    is_initializer: bool,
+}
+
+impl LoxFunction {
+    /// the parameters as they are declared, e.g. `a, b, ...rest`
+    fn params(&self) -> String {
+        let rest = self
+            .declaration
+            .rest
+            .iter()
+            .map(|rest| format!("...{}", rest));
+        self.declaration
+            .params
+            .iter()
+            .cloned()
+            .chain(rest)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// the method bound to an instance, whose body sees the instance as `this`
+    pub fn bind(&self, instance: Rc<LoxInstance>) -> LoxFunction {
+        let mut environment = Environment::with_enclosing(self.closure.clone());
+        environment.define("this", Literal::Instance(instance));
+        LoxFunction {
+            declaration: self.declaration.clone(),
+            closure: Rc::new(RefCell::new(environment)),
+            is_initializer: self.is_initializer,
         }
     }
+}
 
-    pub fn run_script(&mut self, path: String) -> Result<()> {
-        println!("Running script: {}", path);
-        let source = std::fs::read_to_string(&path)
-            .with_context(|| format!("Failed to read in file from {}", path))?;
-        self.run_on_string(source)
+/// a function with a rest parameter takes any number of arguments, and checks itself that there
+/// are enough of them for the other parameters
+impl LoxCallable for LoxFunction {
+    fn arity(&self) -> Option<usize> {
+        match self.declaration.rest {
+            Some(_) => None,
+            None => Some(self.declaration.params.len()),
+        }
     }
 
-    fn run_on_string(&mut self, source: String) -> Result<()> {
-        let mut lexer = Lexer::new();
-        let tokens = lexer.lex(&source)?;
+    fn call(
+        self: Rc<Self>,
+        interpreter: &mut Interpreter,
+        arguments: Vec<Literal>,
+        line: u32,
+    ) -> Result<Literal> {
+        interpreter.call_function(&self, arguments, line)
+    }
 
-        let mut parser = Parser::new();
-        let statement_list = parser.parse(tokens);
-        for statement in statement_list {
-            self.execute_statement(statement)?;
-        }
-        Ok(())
+    fn as_function(self: Rc<Self>) -> Option<Rc<LoxFunction>> {
+        Some(self)
     }
 
-    fn execute_statement(&mut self, statement: StmtNode) -> Result<()> {
-        self.visit_stmt(&statement);
-        Ok(())
+    fn signature(&self) -> String {
+        format!("{}({})", self.declaration.name, self.params())
     }
 
-    pub fn run_repl(&mut self) -> Result<()> {
-        // print!("\n>> ");
-        loop {
-            let mut buf = String::new();
+    fn doc(&self) -> Option<&str> {
+        self.declaration.doc.as_deref()
+    }
 
-            print!(">> ");
-            let _ = std::io::stdin().read_line(&mut buf)?;
-            let source = buf.trim().to_string();
+    fn closure(&self) -> Option<Rc<RefCell<Environment>>> {
+        Some(self.closure.clone())
+    }
+}
 
-            println!("{}", source);
-            if buf == "" {
-                break;
-            }
-            self.run_on_string(buf.clone())?;
-        }
-        Ok(())
+impl fmt::Display for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fn {}>", self.declaration.name)
     }
+}
 
-    pub fn error(line: u32, message: String) -> String {
-        Interpreter::report(line, "".into(), message)
+/// the closure is left out, since it usually holds the function itself
+impl fmt::Debug for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fn {}>", self.declaration.name)
     }
+}
 
-    pub fn report(line: u32, err_where: String, message: String) -> String {
-        format!("[line {}] Error {}: {}", line, err_where, message)
+/// A class declared in a Lox program. Calling it creates an instance.
+#[derive(Debug)]
+pub struct LoxClass {
+    pub name: String,
+    pub superclass: Option<Rc<LoxClass>>,
+    methods: HashMap<String, Rc<LoxFunction>>,
+    /// the methods called on the class itself, which have no `this`
+    class_methods: HashMap<String, Rc<LoxFunction>>,
+    doc: Option<String>,
+}
+
+impl LoxClass {
+    /// the method with the given name, looking in the superclasses when the class has none
+    pub fn find_method(&self, name: &str) -> Option<Rc<LoxFunction>> {
+        match self.methods.get(name) {
+            Some(method) => Some(method.clone()),
+            None => self.superclass.as_ref()?.find_method(name),
+        }
     }
 
-    fn check_type() -> Result<()> {
-        Ok(())
+    /// the scopes the methods of the class were declared in, without the ones of its superclasses
+    pub(crate) fn closures(&self) -> impl Iterator<Item = Rc<RefCell<Environment>>> + '_ {
+        self.methods
+            .values()
+            .chain(self.class_methods.values())
+            .map(|method| method.closure.clone())
     }
 
-    /// evaluates the addition of a left and right literal and returns the result
-    /// for two numbers this is a simple addition
-    /// for two strings this is a concatenation of right on the end of left
-    fn add_impl(left: Literal, right: Literal, line: u32) -> Result<Literal> {
-        match (left, right) {
-            (Literal::Number(l), Literal::Number(r)) => Ok(Literal::Number(l + r)),
-            (Literal::String(l), Literal::String(r)) => Ok(Literal::String(l + &r)),
-            (Literal::Number(left), _) => {
-                return Err(anyhow!(Interpreter::error(
-                    line,
-                    format!(
-                        "the left side number {} operand is being added to non left number",
-                        left
-                    )
-                )))
-            }
-            (Literal::String(left), _) => {
-                return Err(anyhow!(Interpreter::error(
-                    line,
-                    format!(
-                        "the left side string {} operand is being added to non left number",
-                        left
-                    )
-                )))
-            }
-            _ => {
-                return Err(anyhow!(Interpreter::error(
-                    line,
-                    "Operands must be two numbers or two strings".into()
-                )))
-            }
+    /// the class method with the given name, looking in the superclasses when the class has none
+    pub fn find_class_method(&self, name: &str) -> Option<Rc<LoxFunction>> {
+        match self.class_methods.get(name) {
+            Some(method) => Some(method.clone()),
+            None => self.superclass.as_ref()?.find_class_method(name),
         }
     }
 }
 
-impl StmtVisitor for Interpreter {
-    fn visit_print_stmt(&mut self, node: &ExprNode) {
-        let literal = self.visit_expr_node(node);
-        match literal {
-            Ok(lit) => println!("{:#?}", lit),
-            Err(err) => println!("{:#?}", err),
+/// a class takes the arguments of its initializer, or none if it has no `init` method
+impl LoxCallable for LoxClass {
+    fn arity(&self) -> Option<usize> {
+        match self.find_method("init") {
+            Some(initializer) => initializer.arity(),
+            None => Some(0),
         }
     }
 
-    fn visit_expr_stmt(&mut self, node: &ExprNode) {
-        let literal = self.visit_expr_node(node);
-        match literal {
-            Ok(_) => {}
-            Err(err) => println!("{:#?}", err),
+    fn call(
+        self: Rc<Self>,
+        interpreter: &mut Interpreter,
+        arguments: Vec<Literal>,
+        line: u32,
+    ) -> Result<Literal> {
+        interpreter.allocate(heap::SLOT, line)?;
+        let instance = Rc::new(LoxInstance {
+            class: self.clone(),
+            fields: RefCell::new(HashMap::new()),
+        });
+        if let Some(initializer) = self.find_method("init") {
+            Rc::new(initializer.bind(instance.clone())).call(interpreter, arguments, line)?;
         }
+        Ok(Literal::Instance(instance))
     }
 
-    fn visit_err_stmt(&mut self, err: String) {
-        println!("{}", err);
+    fn as_class(self: Rc<Self>) -> Option<Rc<LoxClass>> {
+        Some(self)
     }
-}
 
-impl ExprVisitor for Interpreter {
-    type Output = Result<Literal>;
+    /// the class with the parameters of its initializer and its superclass
+    fn signature(&self) -> String {
+        let params = match self.find_method("init") {
+            Some(initializer) => initializer.params(),
+            None => String::new(),
+        };
+        match &self.superclass {
+            Some(superclass) => format!("class {}({}) < {}", self.name, params, superclass.name),
+            None => format!("class {}({})", self.name, params),
+        }
+    }
 
-    fn visit_literal(&mut self, literal: &Literal) -> Self::Output {
-        Ok(literal.clone())
+    fn doc(&self) -> Option<&str> {
+        self.doc.as_deref()
     }
+}
 
-    fn visit_grouping(&mut self, grouping: &ExprNode) -> Self::Output {
-        self.visit_expr_node(grouping)
+impl fmt::Display for LoxClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
     }
+}
 
-    fn visit_binary_expr(
-        &mut self,
-        left: &ExprNode,
-        operator: &Operator,
-        right: &ExprNode,
-    ) -> Self::Output {
-        let left_literal = self.visit_expr_node(left)?;
-        let right_literal = self.visit_expr_node(right)?;
+/// An instance of a class, whose fields can be added and changed at any time.
+pub struct LoxInstance {
+    pub class: Rc<LoxClass>,
+    fields: RefCell<HashMap<String, Literal>>,
+}
 
-        match operator {
-            Operator::Add { line } => Interpreter::add_impl(left_literal, right_literal, *line),
-            Operator::Subtract { line } => match (left_literal, right_literal) {
-                (Literal::Number(l), Literal::Number(r)) => Ok(Literal::Number(l - r)),
-                _ => {
-                    return Err(anyhow!(Interpreter::error(
-                        *line,
-                        "Operands must be two numbers".into()
-                    )))
-                }
-            },
-            Operator::Multiply { line } => match (left_literal, right_literal) {
-                (Literal::Number(l), Literal::Number(r)) => Ok(Literal::Number(l * r)),
-                _ => {
-                    return Err(anyhow!(Interpreter::error(
-                        *line,
-                        "Operands must be two numbers".into()
-                    )))
-                }
-            },
-            Operator::Divide { line } => match (left_literal, right_literal) {
-                (Literal::Number(l), Literal::Number(r)) => Ok(Literal::Number(l / r)),
-                _ => {
-                    return Err(anyhow!(Interpreter::error(
-                        *line,
-                        "Operands must be two numbers".into()
-                    )))
-                }
-            },
-            Operator::GreaterThan { line } => match (left_literal, right_literal) {
-                (Literal::Number(l), Literal::Number(r)) => Ok(Literal::Boolean(l > r)),
-                (Literal::String(l), Literal::String(r)) => Ok(Literal::Boolean(l > r)),
-                _ => {
-                    return Err(anyhow!(Interpreter::error(
-                        *line,
-                        "Operands must be two numbers or two strings".into()
-                    )))
-                }
-            },
-            Operator::LessThan { line } => match (left_literal, right_literal) {
-                (Literal::Number(l), Literal::Number(r)) => Ok(Literal::Boolean(l < r)),
-                (Literal::String(l), Literal::String(r)) => Ok(Literal::Boolean(l < r)),
-                _ => {
-                    return Err(anyhow!(Interpreter::error(
-                        *line,
-                        "Operands must be two numbers or two strings".into()
-                    )))
-                }
-            },
-            Operator::Equal { line: _ } => todo!("only expressions are supported!"),
-            Operator::EqualEqual { line: _ } => {
-                Ok(Literal::Boolean(left_literal.is_equal(&right_literal)))
-            }
-            Operator::NotEqual { line: _ } => {
-                Ok(Literal::Boolean(!left_literal.is_equal(&right_literal)))
-            }
-            Operator::And { line: _ } => todo!("only expressions are supported!"),
-            Operator::Or { line: _ } => todo!("only expressions are supported!"),
-            _ => return Err(anyhow!("Unsupported operator")),
+impl LoxInstance {
+    /// the field with the given name, or else the method with that name bound to this instance.
+    /// Fields shadow methods.
+    pub fn get(self: &Rc<Self>, name: &str) -> Option<Literal> {
+        if let Some(value) = self.fields.borrow().get(name) {
+            return Some(value.clone());
         }
+        let method = self.class.find_method(name)?;
+        Some(Literal::Callable(Rc::new(method.bind(self.clone()))))
     }
 
-    fn visit_unary_expr(&mut self, operator: &Operator, child: &ExprNode) -> Self::Output {
-        let output = self.visit_expr_node(child)?;
+    pub fn set(&self, name: &str, value: Literal) {
+        self.fields.borrow_mut().insert(name.to_string(), value);
+    }
 
-        match operator {
-            Operator::Bang { line: _ } => return Ok(Literal::Boolean(!output.is_falsy())),
-            Operator::Subtract { line } => {
-                if let Literal::Number(value) = output {
-                    return Ok(Literal::Number(-value));
-                } else {
-                    return Err(anyhow!(format!(
-                        "Unary operator '-' can only be applied to numbers on line {}",
-                        line
-                    )));
-                }
-            }
-            _ => Err(anyhow!(format!(
-                "Unexpected operator of type {:?} in an Unary expression. Only",
-                operator
-            ))),
-        }
+    /// a copy of the fields, without the methods
+    pub fn fields(&self) -> HashMap<String, Literal> {
+        self.fields.borrow().clone()
     }
 }
 
-#[cfg(test)]
-mod test {
-    use super::*;
-
-    #[test]
-    // tests that the lexer and the parser and interpreter can all be used together to generate
-    // the expected output from some lox source code.
-    fn interpreter_integration_test_expression() {}
+/// the fields are left out, since they can hold the instance itself
+impl fmt::Debug for LoxInstance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<{} instance>", self.class.name)
+    }
+}
 
-    #[test]
-    fn unary_expr_test() {
-        let expr = "!5";
-        let result = get_parsed_expr(expr);
-        assert_eq!(result, Literal::Boolean(false));
+/// an instance is only equal to itself, even if another one has the same fields
+impl PartialEq for LoxInstance {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
 
-        let expr = "-2";
-        let result = get_parsed_expr(expr);
-        assert_eq!(result, Literal::Number(-2.0));
+impl PartialOrd for LoxInstance {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        (self == other).then_some(std::cmp::Ordering::Equal)
     }
+}
 
-    #[test]
-    fn add_sub_expr_test() {
-        let expr = "1 + 2";
-        let result = get_parsed_expr(expr);
-        assert_eq!(result, Literal::Number(3.0));
+/// Stores the values bound to variable names at runtime.
+///
+/// Environments form a chain of scopes. Looking up a name that is not declared in an environment
+/// continues in the environment enclosing it, up to the global environment which has none.
+#[derive(Debug, Default)]
+pub struct Environment {
+    values: HashMap<String, Literal>,
+    /// the names declared in this environment with `const`
+    constants: HashSet<String>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
+}
 
-        let expr = "1 - 2";
-        let result = get_parsed_expr(expr);
-        assert_eq!(result, Literal::Number(-1.0));
+impl Environment {
+    /// creates a global environment
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-        let expr = "1 + 2 - 3";
-        let result = get_parsed_expr(expr);
-        assert_eq!(result, Literal::Number(0.0));
+    /// creates an environment for a scope nested inside of the enclosing one
+    pub fn with_enclosing(enclosing: Rc<RefCell<Environment>>) -> Self {
+        Environment {
+            values: HashMap::new(),
+            constants: HashSet::new(),
+            enclosing: Some(enclosing),
+        }
     }
 
-    #[test]
-    fn mul_sub_expr_test() {
-        let expr = "1 * 2";
-        let result = get_parsed_expr(expr);
-        assert_eq!(result, Literal::Number(2.0));
+    /// binds a value to a name in this environment. Declaring a name that is already bound replaces
+    /// the value, so variables can be redeclared.
+    pub fn define(&mut self, name: &str, value: Literal) {
+        self.constants.remove(name);
+        self.values.insert(name.to_string(), value);
+    }
 
-        let expr = "1 / 2";
-        let result = get_parsed_expr(expr);
-        assert_eq!(result, Literal::Number(0.5));
+    /// binds a value to a name in this environment which can not be assigned to, see
+    /// [`Environment::is_constant`]. The parser refuses to declare a name again in the scope of a
+    /// constant, see [`Parser::with_constants`].
+    pub fn define_constant(&mut self, name: &str, value: Literal) {
+        self.values.insert(name.to_string(), value);
+        self.constants.insert(name.to_string());
+    }
 
-        let expr = "1 * 2 / 3";
-        let result = get_parsed_expr(expr);
-        assert_eq!(result, Literal::Number(2.0 / 3.0));
+    /// whether the innermost declaration of a name in this environment or the ones enclosing it is
+    /// a constant. Assigning to a constant is left to the interpreter to refuse.
+    pub fn is_constant(&self, name: &str) -> bool {
+        if self.values.contains_key(name) {
+            return self.constants.contains(name);
+        }
+        self.enclosing
+            .as_ref()
+            .is_some_and(|enclosing| enclosing.borrow().is_constant(name))
     }
 
-    #[test]
-    fn grouping_expr_test() {
-        let expr = "1 +(2 * 3)";
-        let result = get_parsed_expr(expr);
-        assert_eq!(result, Literal::Number(7.0));
+    /// rebinds a name that was already declared in this environment or the ones enclosing it,
+    /// returning false if it was never declared.
+    pub fn assign(&mut self, name: &str, value: Literal) -> bool {
+        if let Some(current) = self.values.get_mut(name) {
+            *current = value;
+            return true;
+        }
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign(name, value),
+            None => false,
+        }
+    }
 
-        let expr = "(1 + 2) * 3";
-        let result = get_parsed_expr(expr);
-        assert_eq!(result, Literal::Number(9.0));
+    /// looks up the value bound to a name in this environment or the ones enclosing it.
+    pub fn get(&self, name: &str) -> Option<Literal> {
+        match self.values.get(name) {
+            Some(value) => Some(value.clone()),
+            None => self
+                .enclosing
+                .as_ref()
+                .and_then(|enclosing| enclosing.borrow().get(name)),
+        }
+    }
 
-        let expr = "(1 + 2) * (3 + 4)";
-        let result = get_parsed_expr(expr);
-        assert_eq!(result, Literal::Number(21.0));
+    /// the names and values declared in this environment, without the ones enclosing it, in no
+    /// particular order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Literal)> {
+        self.values
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+    }
 
-        let expr = "3 * (1 + 2)";
-        let result = get_parsed_expr(expr);
-        assert_eq!(result, Literal::Number(9.0));
+    /// the environment enclosing this one, which the global environment has none of
+    pub fn enclosing(&self) -> Option<std::cell::Ref<'_, Environment>> {
+        self.enclosing.as_ref().map(|enclosing| enclosing.borrow())
     }
 
-    #[test]
-    /// tests that the ">", "<", ">=", and "<=" operators work as expected.
-    fn greater_less_than_expr_tests() {
-        let expr = "1 > 2";
-        let result = get_parsed_expr(expr);
-        assert_eq!(result, Literal::Boolean(false));
+    /// the shared environment enclosing this one, see [`Environment::enclosing`]
+    pub(crate) fn enclosing_rc(&self) -> Option<Rc<RefCell<Environment>>> {
+        self.enclosing.clone()
+    }
+}
 
-        let expr = "1 < 2";
-        let result = get_parsed_expr(expr);
-        assert_eq!(result, Literal::Boolean(true));
+/// Controls how print statements format numbers for people to read. The default format is the
+/// canonical one, where numbers print the same way as in the reference Lox implementations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberFormat {
+    /// the separator placed between groups of thousands, e.g. `Some(',')` prints 1,234,567
+    pub thousands_separator: Option<char>,
+    /// the character separating the integer part from the fractional part
+    pub decimal_point: char,
+    /// the number of digits after the decimal point, or None to print as many as needed
+    pub precision: Option<usize>,
+}
 
-        let expr = "1 >= 2";
-        let result = get_parsed_expr(expr);
-        assert_eq!(result, Literal::Boolean(false));
+impl Default for NumberFormat {
+    fn default() -> Self {
+        NumberFormat {
+            thousands_separator: None,
+            decimal_point: '.',
+            precision: None,
+        }
+    }
+}
 
-        let expr = "1 <= 2";
-        let result = get_parsed_expr(expr);
-        assert_eq!(result, Literal::Boolean(true));
+impl NumberFormat {
+    pub fn format(&self, number: f64) -> String {
+        let text = match self.precision {
+            Some(precision) => format!("{:.*}", precision, number),
+            None => number.to_string(),
+        };
+        if !number.is_finite() {
+            return text;
+        }
+
+        let (sign, unsigned) = match text.strip_prefix('-') {
+            Some(unsigned) => ("-", unsigned),
+            None => ("", text.as_str()),
+        };
+        let (integer, fraction) = match unsigned.split_once('.') {
+            Some((integer, fraction)) => (integer, Some(fraction)),
+            None => (unsigned, None),
+        };
+
+        let mut out = sign.to_string();
+        for (i, digit) in integer.chars().enumerate() {
+            if let Some(separator) = self.thousands_separator {
+                if i > 0 && (integer.len() - i) % 3 == 0 {
+                    out.push(separator);
+                }
+            }
+            out.push(digit);
+        }
+        if let Some(fraction) = fraction {
+            out.push(self.decimal_point);
+            out.push_str(fraction);
+        }
+        out
     }
+}
 
-    #[test]
-    /// tests that the "==" and "!=" operators work as expected.
-    fn equals_equals_expr_test() {
-        let expr = "1 == 1";
-        let result = get_parsed_expr(expr);
-        assert_eq!(result, Literal::Boolean(true));
+/// A snapshot of counters describing the work done by an interpreter so far. The interpreter only
+/// counts, it is up to the embedding application to export them to wherever it wants.
+///
+/// There is no count of garbage collection runs, since values are reference counted and freed as
+/// soon as the last reference to them is dropped, so there are no collections to count.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Metrics {
+    /// the number of statements that have been executed, including ones that failed at runtime
+    pub statements_executed: u64,
+    /// the number of function calls that have been made
+    pub calls_made: u64,
+    /// the number of lexical, syntax and runtime errors that have been reported
+    pub errors_raised: u64,
+    /// the number of values allocated on the heap, like strings made by concatenation, lists, maps,
+    /// instances and their fields, see [`Interpreter::allocate`]
+    pub allocations: u64,
+}
 
-        let expr = "1 == 2";
-        let result = get_parsed_expr(expr);
-        assert_eq!(result, Literal::Boolean(false));
+struct RuntimeErr {
+    message: String,
+    line: u32,
+}
 
-        let expr = "1 != 2";
-        let result = get_parsed_expr(expr);
-        assert_eq!(result, Literal::Boolean(true));
+/// Errors that stop the interpreter from running the rest of a program. They are returned wrapped
+/// in an `anyhow::Error` and can be recovered with `downcast_ref::<RuntimeError>()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuntimeError {
+    /// execution was stopped through a [`CancellationToken`]
+    Cancelled,
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuntimeError::Cancelled => write!(f, "Execution was cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+/// An error of the program being run, such as an operation on values of the wrong type or a native
+/// failing. The code is the one of the message in the catalog, see [`crate::messages`], which the
+/// errors of natives and plugins have none of.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoxError {
+    pub line: u32,
+    pub code: Option<&'static str>,
+    pub message: String,
+}
+
+impl LoxError {
+    /// an error without a code, as natives and plugins report them
+    pub fn new(line: u32, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            code: None,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for LoxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self.code {
+            Some(code) => format!("[{}] {}", code, self.message),
+            None => self.message.clone(),
+        };
+        write!(f, "{}", Interpreter::error(self.line, message))
+    }
+}
+
+impl std::error::Error for LoxError {}
+
+/// Signals which unwind the statements being executed up to the statement that handles them, like
+/// errors do. The parser makes sure there always is one, e.g. that a `break` is inside of a loop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Unwind {
+    Break,
+    Continue,
+    /// returns from the function being called. Errors have to be `Send`, which values are not, so
+    /// the value being returned is kept in [`Interpreter::return_value`] instead.
+    Return,
+}
+
+impl fmt::Display for Unwind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Unwind::Break => write!(f, "Can not use 'break' outside of a loop"),
+            Unwind::Continue => write!(f, "Can not use 'continue' outside of a loop"),
+            Unwind::Return => write!(f, "Can not return from top-level code"),
+        }
+    }
+}
+
+impl std::error::Error for Unwind {}
+
+/// A value thrown with `throw`, unwinding to the innermost `try` around it. Values are not `Send`,
+/// so the value is kept in [`Interpreter::thrown`] and the error only holds the message reported
+/// when nothing catches it. Runtime errors of operations and natives are caught as well, as a map of
+/// their code, message and line, while errors of the interpreter itself, such as a cancellation,
+/// never are, see [`is_catchable`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Thrown(String);
+
+/// whether a try statement catches the error, which it does for the values thrown and the runtime
+/// errors of the program, but not for the errors of the interpreter itself: a cancellation, the
+/// signals of control flow and failing to write the output.
+pub(crate) fn is_catchable(err: &anyhow::Error) -> bool {
+    !err.is::<RuntimeError>() && !err.is::<Unwind>() && !err.is::<std::io::Error>()
+}
+
+/// the value a try statement binds a caught error to, a map of its `code`, `message` and `line`,
+/// which are nil for an error without them
+fn caught(err: &anyhow::Error) -> Literal {
+    let (code, message, line) = match err.downcast_ref::<LoxError>() {
+        Some(err) => (
+            err.code
+                .map_or(Literal::Nil, |code| Literal::String(code.to_string())),
+            err.message.clone(),
+            Literal::Number(err.line as f64),
+        ),
+        None => (Literal::Nil, err.to_string(), Literal::Nil),
+    };
+    let fields = [
+        ("code", code),
+        ("message", Literal::String(message)),
+        ("line", line),
+    ];
+    Literal::Map(LoxMap::new(
+        fields
+            .into_iter()
+            .map(|(name, value)| (MapKey::String(name.to_string()), value))
+            .collect(),
+    ))
+}
+
+impl fmt::Display for Thrown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for Thrown {}
+
+/// A cloneable handle that can stop a running interpreter from another thread. The interpreter
+/// checks the handle before executing each statement.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// requests that the interpreter stops at the next statement.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// clears a cancellation request, returning whether there was one.
+    fn take(&self) -> bool {
+        self.cancelled.swap(false, Ordering::SeqCst)
+    }
+}
+
+#[cfg(feature = "host")]
+pub enum InterpreterMode {
+    Script(String),
+    Repl,
+}
+
+/// Which variables the sources run by [`Interpreter::eval_many`] can see.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchEnvironment {
+    /// every source runs in the environment of the interpreter, so later sources see the variables
+    /// of earlier ones, like lines in the REPL
+    Shared,
+    /// every source runs in an environment of its own, which is thrown away once it has run
+    PerSource,
+}
+
+/// The diagnostics of a single source run by [`Interpreter::eval_many`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceReport {
+    pub name: String,
+    /// the lexical, syntax and runtime errors of the source in the order they were raised
+    pub diagnostics: Vec<String>,
+}
+
+/// What running a source with [`Interpreter::eval`] got done, even when some of its statements
+/// failed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PartialResult {
+    /// the number of top-level statements executed, including the ones which failed
+    pub statements_executed: usize,
+    /// the lexical, syntax and runtime errors in the order they were raised
+    pub errors: Vec<String>,
+    /// the global environment once the source has run, natives included
+    pub globals: HashMap<String, Literal>,
+}
+
+/// The diagnostics of every source run by [`Interpreter::eval_many`], in the order they ran.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatchReport {
+    pub sources: Vec<SourceReport>,
+}
+
+impl BatchReport {
+    /// the diagnostics of the source with the given name, or None if no source had that name.
+    pub fn diagnostics(&self, name: &str) -> Option<&[String]> {
+        self.sources
+            .iter()
+            .find(|source| source.name == name)
+            .map(|source| source.diagnostics.as_slice())
+    }
+
+    /// the number of diagnostics across all of the sources.
+    pub fn error_count(&self) -> usize {
+        self.sources
+            .iter()
+            .map(|source| source.diagnostics.len())
+            .sum()
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.error_count() == 0
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Interpreter::new()
+    }
+}
+
+impl Interpreter {
+    /// creates an interpreter that reports diagnostics in the language selected by `LOX_LANG`.
+    pub fn new() -> Self {
+        Interpreter {
+            language: Language::from_env(),
+            metrics: Metrics::default(),
+            cancelled: CancellationToken::default(),
+            output: LineWriter::new(Box::new(std::io::stdout())),
+            error_output: Box::new(std::io::stderr()),
+            #[cfg(feature = "host")]
+            input: Box::new(std::io::BufReader::new(std::io::stdin())),
+            #[cfg(not(feature = "host"))]
+            input: Box::new(std::io::empty()),
+            defines: HashMap::new(),
+            overrides: HashMap::new(),
+            #[cfg(feature = "host")]
+            loader: Box::new(FileSystemLoader),
+            #[cfg(not(feature = "host"))]
+            loader: Box::new(InMemoryLoader::new()),
+            modules: HashMap::new(),
+            plugins: Vec::new(),
+            case_insensitive_keywords: false,
+            automatic_semicolons: false,
+            pedantic: false,
+            string_concatenation: true,
+            lint_levels: HashMap::new(),
+            continue_on_error: true,
+            warned: HashSet::new(),
+            number_format: NumberFormat::default(),
+            number_mode: NumberMode::default(),
+            environment: Rc::new(RefCell::new(natives::globals())),
+            #[cfg(feature = "host")]
+            crash_reporter: None,
+            phase: Phase::Lexing,
+            trace_artifacts: false,
+            last_trace: None,
+            frames: Vec::new(),
+            interrupted_at: None,
+            error_trace: None,
+            trace_options: TraceOptions::default(),
+            imported: HashMap::new(),
+            exports: HashMap::new(),
+            importing: Vec::new(),
+            suspended: Vec::new(),
+            heap_limit: None,
+            heap_allocated: 0,
+            return_value: None,
+            tail_call: None,
+            try_depth: 0,
+            thrown: None,
+            dry_run: None,
+            effects: EffectsTrace::default(),
+            capabilities: HashSet::new(),
+            random: Random::from_clock(),
+            seed: None,
+            benchmarks: BenchReport::default(),
+            last_value: None,
+            #[cfg(feature = "host")]
+            parse_cache: None,
+        }
+    }
+
+    /// catches panics inside of the interpreter and writes a crash report for them, after which the
+    /// panic is returned as an error.
+    #[cfg(feature = "host")]
+    pub fn with_crash_reporter(mut self, crash_reporter: CrashReporter) -> Self {
+        self.crash_reporter = Some(crash_reporter);
+        self
+    }
+
+    /// sets how print statements format numbers, e.g. with thousands separators or a fixed precision.
+    /// This only changes what is printed, not the values numbers hold or what `str` returns. Scripts
+    /// change it with the `setPrintPrecision` native.
+    pub fn with_number_format(mut self, number_format: NumberFormat) -> Self {
+        self.number_format = number_format;
+        self
+    }
+
+    /// caches the syntax trees of the scripts run with [`Interpreter::run_script`], so running one
+    /// again unchanged skips lexing and parsing it, see [`crate::cache`]. Lines of the REPL are not
+    /// cached, nor are scripts run while linting or keeping the artifacts of the trace.
+    #[cfg(feature = "host")]
+    pub fn with_parse_cache(mut self, parse_cache: ParseCache) -> Self {
+        self.parse_cache = Some(parse_cache);
+        self
+    }
+
+    /// the parse cache, whose statistics tell how often it was hit
+    #[cfg(feature = "host")]
+    pub fn parse_cache(&self) -> Option<&ParseCache> {
+        self.parse_cache.as_ref()
+    }
+
+    /// sets whether the number operators use binary floating point, which they do by default like
+    /// the reference Lox implementations, or decimal arithmetic so that `0.1 + 0.2 == 0.3`, see
+    /// [`crate::number`].
+    pub fn with_number_mode(mut self, number_mode: NumberMode) -> Self {
+        self.number_mode = number_mode;
+        self
+    }
+
+    /// formats a value the way a print statement outputs it.
+    fn format_value(&self, value: &Literal) -> String {
+        match value {
+            Literal::Number(number) => self.number_format.format(*number),
+            // decimals print their digits unless a precision is set for printing
+            Literal::Decimal(decimal) if self.number_format != NumberFormat::default() => {
+                self.number_format.format(decimal.to_f64())
+            }
+            value => value.to_string(),
+        }
+    }
+
+    /// lets programs write keywords in any case, e.g. `PRINT` or `If`. Disabled by default.
+    pub fn with_case_insensitive_keywords(mut self, case_insensitive_keywords: bool) -> Self {
+        self.case_insensitive_keywords = case_insensitive_keywords;
+        self
+    }
+
+    /// lets statements end at a line break instead of a semicolon, see
+    /// [`Parser::with_automatic_semicolons`]. Disabled by default.
+    pub fn with_automatic_semicolons(mut self, automatic_semicolons: bool) -> Self {
+        self.automatic_semicolons = automatic_semicolons;
+        self
+    }
+
+    /// warns about code which runs fine in this interpreter but relies on extensions to canonical Lox,
+    /// such as ordering strings or single quoted strings, so that programs can stay portable to the
+    /// implementations from the book. Disabled by default.
+    pub fn with_pedantic(mut self, pedantic: bool) -> Self {
+        self.pedantic = pedantic;
+        self
+    }
+
+    /// sets whether adding a string and a value of another type turns the value into a string, so
+    /// that `"a" + 1` is `"a1"`, which it does by default. Without it adding them is an error like in
+    /// canonical Lox.
+    pub fn with_string_concatenation(mut self, string_concatenation: bool) -> Self {
+        self.string_concatenation = string_concatenation;
+        self
+    }
+
+    /// sets how a lint is reported, overriding pedantic mode for it. A denied lint is an error which
+    /// stops the program like a runtime error does.
+    pub fn with_lint_level(mut self, id: MessageId, level: LintLevel) -> Self {
+        self.lint_levels.insert(id, level);
+        self
+    }
+
+    /// sets whether the statements after one which fails still run, which they do by default. When
+    /// they don't, the first error stops the rest of the program like it does in strict scripting
+    /// languages.
+    pub fn with_continue_on_error(mut self, continue_on_error: bool) -> Self {
+        self.continue_on_error = continue_on_error;
+        self
+    }
+
+    /// keeps the tokens and syntax tree of every run in its trace, see [`Interpreter::last_trace`].
+    /// Disabled by default.
+    pub fn with_trace_artifacts(mut self, trace_artifacts: bool) -> Self {
+        self.trace_artifacts = trace_artifacts;
+        self
+    }
+
+    /// replaces the natives needing a capability with stubs which record their calls and return
+    /// canned values, see [`crate::dry_run`]. A dry run also seeds the random natives with 0 unless
+    /// they were given a seed, so that running it again gives the same effects. Disabled by default.
+    pub fn with_dry_run(mut self, dry_run: DryRun) -> Self {
+        self.dry_run = Some(dry_run);
+        if self.seed.is_none() {
+            self.random.seed(0);
+        }
+        let natives = self.native_capabilities();
+        self.define_stubs(&natives, &mut self.environment.borrow_mut());
+        self
+    }
+
+    /// sets how stack traces are shown, e.g. to list at most some calls or to report errors raised
+    /// inside of calls with the calls they were raised in
+    pub fn with_trace_options(mut self, options: TraceOptions) -> Self {
+        self.trace_options = options;
+        self
+    }
+
+    /// limits the bytes the values held by a program can take. Going over the limit is a runtime
+    /// error, which the program can catch like any other. Unlimited by default.
+    pub fn with_heap_limit(mut self, bytes: usize) -> Self {
+        self.heap_limit = Some(bytes);
+        self
+    }
+
+    /// accounts for bytes about to be allocated for a value. Once the bytes allocated since the
+    /// last measurement pass the heap limit the heap is measured again, and if the values still
+    /// held are over the limit as well the allocation fails.
+    pub fn allocate(&mut self, bytes: usize, line: u32) -> Result<()> {
+        self.metrics.allocations += 1;
+        let Some(limit) = self.heap_limit else {
+            return Ok(());
+        };
+        self.heap_allocated += bytes;
+        if self.heap_allocated <= limit {
+            return Ok(());
+        }
+        self.heap_allocated = self.measure_heap() + bytes;
+        if self.heap_allocated <= limit {
+            return Ok(());
+        }
+        self.heap_allocated -= bytes;
+        Err(self.runtime_error(line, MessageId::OutOfMemory, &[&limit.to_string()]))
+    }
+
+    /// the bytes of the values reachable from the scopes and calls of the running program
+    pub fn measure_heap(&self) -> usize {
+        let mut meter = HeapMeter::new();
+        meter.environment(&self.environment);
+        for environment in self.suspended.iter().chain(self.imported.values()) {
+            meter.environment(environment);
+        }
+        for (callable, _) in &self.frames {
+            meter.literal(&Literal::Callable(callable.clone()));
+        }
+        for value in [&self.return_value, &self.thrown, &self.last_value]
+            .into_iter()
+            .flatten()
+        {
+            meter.literal(value);
+        }
+        if let Some((function, arguments, _)) = &self.tail_call {
+            meter.literal(&Literal::Callable(function.clone()));
+            arguments
+                .iter()
+                .for_each(|argument| meter.literal(argument));
+        }
+        meter.bytes()
+    }
+
+    /// seeds the generator behind `random()` and `randomInt(lo, hi)`, so that every run of a program
+    /// gets the same numbers. It is seeded from the clock by default, see [`crate::random`].
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self.random.seed(seed);
+        self
+    }
+
+    /// the generator behind the random natives
+    pub(crate) fn random(&mut self) -> &mut Random {
+        &mut self.random
+    }
+
+    /// the format print statements write numbers in, which `setPrintPrecision` changes
+    pub(crate) fn number_format(&mut self) -> &mut NumberFormat {
+        &mut self.number_format
+    }
+
+    /// grants a capability to the natives needing it, see [`crate::analyze::Capability`]. None are
+    /// granted by default.
+    pub fn with_capability(mut self, capability: Capability) -> Self {
+        self.capabilities.insert(capability);
+        self
+    }
+
+    /// checks that the native with the given name may use a capability, which is a runtime error in
+    /// the line of its call otherwise
+    pub fn require_capability(
+        &self,
+        capability: Capability,
+        native: &str,
+        line: u32,
+    ) -> Result<()> {
+        if self.capabilities.contains(&capability) {
+            Ok(())
+        } else {
+            Err(anyhow!(LoxError::new(
+                line,
+                format!(
+                    "{} needs the {} capability, which the interpreter was not granted",
+                    native, capability
+                )
+            )))
+        }
+    }
+
+    /// the calls of stubs made since the interpreter was created, which is empty unless it is doing a
+    /// dry run.
+    pub fn effects(&self) -> &EffectsTrace {
+        &self.effects
+    }
+
+    /// defines the stubs of a dry run for the natives needing a capability in an environment,
+    /// replacing the natives
+    fn define_stubs(&self, natives: &HashMap<String, Capability>, environment: &mut Environment) {
+        if let Some(dry_run) = &self.dry_run {
+            for stub in dry_run.stubs(natives) {
+                let name = stub.name.clone();
+                environment.define(&name, Literal::Callable(Rc::new(stub)));
+            }
+        }
+    }
+
+    /// records the call of a stub during a dry run
+    pub(crate) fn record_effect(&mut self, effect: Effect) {
+        self.effects.effects.push(effect);
+    }
+
+    /// the global environment, holding the natives and the global variables, functions and classes
+    /// of the programs run so far. It is the current environment whenever the interpreter is not
+    /// running a program, so tests can inspect it in between.
+    pub fn globals(&self) -> std::cell::Ref<'_, Environment> {
+        self.environment.borrow()
+    }
+
+    /// the natives defined in the global environment which need a capability, by name
+    pub fn native_capabilities(&self) -> HashMap<String, Capability> {
+        self.globals()
+            .iter()
+            .filter_map(|(name, value)| match value {
+                Literal::Callable(callable) => callable
+                    .capability()
+                    .map(|capability| (name.to_string(), capability)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// the value of the last expression statement or print statement executed, or None if there
+    /// has not been one yet
+    pub fn last_value(&self) -> Option<&Literal> {
+        self.last_value.as_ref()
+    }
+
+    /// the results of the benchmarks run since the interpreter was created, see [`crate::bench`]
+    pub fn benchmarks(&self) -> &BenchReport {
+        &self.benchmarks
+    }
+
+    /// records the result of a benchmark run with `bench`
+    pub(crate) fn record_benchmark(&mut self, result: BenchResult) {
+        self.benchmarks.results.push(result);
+    }
+
+    /// the per-stage timings of the last source that was run, or None if nothing has run yet or it
+    /// failed to lex.
+    pub fn last_trace(&self) -> Option<&Trace> {
+        self.last_trace.as_ref()
+    }
+
+    /// sets the loader used to read in scripts instead of reading them from the file system.
+    pub fn with_module_loader(mut self, loader: Box<dyn ModuleLoader + Send>) -> Self {
+        self.loader = loader;
+        self
+    }
+
+    /// enables a plugin, defining its natives, serving its modules and then running its preludes,
+    /// see [`crate::plugin`]. Enabling a plugin which is already enabled does nothing, and an error
+    /// in a prelude fails instead of being written to the output.
+    pub fn with_plugin(mut self, plugin: &dyn LoxPlugin) -> Result<Self> {
+        if self.plugins.contains(&plugin.name()) {
+            return Ok(self);
+        }
+        let mut registry = Registry::default();
+        plugin.register(&mut registry);
+
+        let environment = self.environment.clone();
+        for native in registry.natives {
+            environment
+                .borrow_mut()
+                .define(native.name, Literal::Callable(Rc::new(native)));
+        }
+        // the natives of a plugin are stubbed once they are defined during a dry run
+        let natives = self.native_capabilities();
+        self.define_stubs(&natives, &mut environment.borrow_mut());
+        self.modules.extend(registry.modules);
+        for prelude in &registry.preludes {
+            let result = self.eval(prelude)?;
+            if !result.errors.is_empty() {
+                return Err(anyhow!(
+                    "the prelude of the plugin {} failed: {}",
+                    plugin.name(),
+                    result.errors.join("\n")
+                ));
+            }
+        }
+        self.plugins.push(plugin.name());
+        Ok(self)
+    }
+
+    /// the names of the plugins enabled, in the order they were enabled
+    pub fn plugins(&self) -> &[&'static str] {
+        &self.plugins
+    }
+
+    /// reads in the source of a script, from the modules of the plugins or else with the loader
+    fn load(&self, path: &str) -> Result<String> {
+        match self.modules.get(path) {
+            Some(source) => Ok(source.clone()),
+            None => self.loader.load(path),
+        }
+    }
+
+    /// defines a compile time constant, as if `define name value;` was at the top of the program,
+    /// replacing the value the program defines it with.
+    pub fn with_define(mut self, name: String, value: Literal) -> Self {
+        self.defines.insert(name.clone(), value.clone());
+        self.overrides.insert(name, value);
+        self
+    }
+
+    /// sets where the output of the program is written to instead of stdout. The output is
+    /// buffered a line at a time and flushed once a program ends, before every REPL prompt and when
+    /// the program calls `flush()`.
+    pub fn with_output(mut self, output: Box<dyn Write + Send>) -> Self {
+        self.output = LineWriter::new(output);
+        self
+    }
+
+    /// sets where `eprint` writes to instead of stderr.
+    pub fn with_error_output(mut self, error_output: Box<dyn Write + Send>) -> Self {
+        self.error_output = error_output;
+        self
+    }
+
+    /// sets where `readLine` reads the program's input from instead of stdin, which builds without
+    /// the `host` feature have none of.
+    pub fn with_input(mut self, input: Box<dyn BufRead + Send>) -> Self {
+        self.input = input;
+        self
+    }
+
+    /// writes a value and a line break to the error output, like a print statement does to the
+    /// output. The output is flushed first so that the two stay in the order they were written in
+    /// when both go to the same terminal.
+    pub(crate) fn eprint(&mut self, value: &Literal) -> Result<()> {
+        self.output.flush()?;
+        writeln!(self.error_output, "{}", self.format_value(value))?;
+        self.error_output.flush()?;
+        Ok(())
+    }
+
+    /// writes out whatever is buffered in the output
+    pub(crate) fn flush(&mut self) -> Result<()> {
+        self.output.flush()?;
+        Ok(())
+    }
+
+    /// reads the next line of the input without its line ending, or None at the end of the input.
+    /// The output is flushed first, so that a prompt printed before is seen when the program waits.
+    pub(crate) fn read_line(&mut self) -> Result<Option<String>> {
+        self.output.flush()?;
+        let mut line = String::new();
+        if self.input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let length = line.trim_end_matches(['\n', '\r']).len();
+        line.truncate(length);
+        Ok(Some(line))
+    }
+
+    /// returns a handle which can be used to cancel the program being run by this interpreter.
+    /// Once the interpreter stops with [`RuntimeError::Cancelled`] the request is cleared so the
+    /// interpreter can be used to run another program.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancelled.clone()
+    }
+
+    /// returns a snapshot of the counters collected since the interpreter was created.
+    pub fn metrics(&self) -> Metrics {
+        self.metrics
+    }
+
+    /// sets the language diagnostic messages are reported in, overriding `LOX_LANG`.
+    pub fn with_language(mut self, language: Language) -> Self {
+        self.language = language;
+        self
+    }
+
+    #[cfg(feature = "host")]
+    pub fn run(&mut self, mode: InterpreterMode) -> Result<()> {
+        match mode {
+            InterpreterMode::Script(path) => self.run_script(path),
+            InterpreterMode::Repl => self.run_repl(),
+        }
+    }
+
+    /// runs the script at the path, which counts as imported while it runs, so that a module
+    /// importing it back is reported as a circular import
+    pub fn run_script(&mut self, path: String) -> Result<()> {
+        let resolved = self.resolve(&path);
+        let source = self.load(&resolved)?;
+        self.importing.push(resolved);
+        let result = self.run_source(source, Some(&path));
+        self.importing.pop();
+        result
+    }
+
+    fn run_on_string(&mut self, source: String) -> Result<()> {
+        self.run_source(source, None)
+    }
+
+    /// runs the source of a script, or of a line in the REPL when there is no path, reporting a
+    /// crash if the interpreter panics and has a crash reporter.
+    fn run_source(&mut self, source: String, path: Option<&str>) -> Result<()> {
+        #[cfg(feature = "host")]
+        if let Some(reporter) = self.crash_reporter.clone() {
+            return self.run_reporting_crashes(source, path, reporter);
+        }
+        let result = self.execute_source(&source, path.is_some());
+        self.output.flush()?;
+        result
+    }
+
+    /// runs a source like [`Interpreter::run_source`], but turns a panic into a crash report
+    #[cfg(feature = "host")]
+    fn run_reporting_crashes(
+        &mut self,
+        source: String,
+        path: Option<&str>,
+        reporter: CrashReporter,
+    ) -> Result<()> {
+        let cached = path.is_some();
+        let panic = match crash::guard(|| self.execute_source(&source, cached)) {
+            Ok(result) => {
+                self.output.flush()?;
+                return result;
+            }
+            Err(panic) => panic,
+        };
+
+        self.metrics.errors_raised += 1;
+        // a program which crashes the lexer can't be split into statements
+        let reproducer = if reporter.minimize() && self.phase != Phase::Lexing {
+            self.minimize_crash(&source)
+        } else {
+            None
+        };
+        let report = CrashReport {
+            path: path.map(str::to_string),
+            source,
+            phase: self.phase,
+            panic,
+            reproducer,
+        };
+        let file = reporter.write(&report)?;
+        Err(anyhow!(
+            "The interpreter crashed while {}: {}. A crash report was written to {}",
+            report.phase,
+            report.panic.message,
+            file.display()
+        ))
+    }
+
+    /// looks for the smallest set of statements of the source that crash a new interpreter with the
+    /// same options, returning them as a program.
+    #[cfg(feature = "host")]
+    fn minimize_crash(&self, source: &str) -> Option<String> {
+        let tokens = self.lexer().lex(source).ok()?;
+        let statements = crash::minimize(crash::statements(&tokens), |statements| {
+            let mut interpreter = Interpreter::new()
+                .with_output(Box::new(std::io::sink()))
+                .with_language(self.language)
+                .with_case_insensitive_keywords(self.case_insensitive_keywords)
+                .with_automatic_semicolons(self.automatic_semicolons);
+            // the defines of the source were known when it ran, so they keep their values
+            interpreter.defines = self.defines.clone();
+            interpreter.overrides = self.defines.clone();
+            crash::guard(|| interpreter.execute_source(&statements.join("\n"), false)).is_err()
+        });
+        Some(statements.join("\n"))
+    }
+
+    /// runs a source through the whole pipeline, or only interprets it if it is cached and the
+    /// cache is used for it. Sources which are not cached, like the lines of the REPL, keep their
+    /// comments so that `:doc` can show the doc comments of what they declare.
+    fn execute_source(&mut self, source: &str, cached: bool) -> Result<()> {
+        self.phase = Phase::Lexing;
+        self.last_trace = None;
+        self.interrupted_at = None;
+        self.error_trace = None;
+        let cached = cached && !self.linting() && !self.trace_artifacts;
+        #[cfg(feature = "host")]
+        let options = self.front_end_options();
+        #[cfg(feature = "host")]
+        if let Some(parsed) = self
+            .parse_cache
+            .as_mut()
+            .filter(|_| cached)
+            .and_then(|cache| cache.get(source, &options))
+        {
+            self.warned.clear();
+            self.defines = parsed.defines;
+            let trace =
+                Pipeline::from_artifact(Resolved(parsed.statements)).interpret_with(self)?;
+            self.last_trace = Some(trace);
+            return Ok(());
+        }
+
+        let mut lexer = self.lexer().with_comments(!cached);
+        let tokens = Pipeline::new(source)
+            .with_artifacts(self.trace_artifacts)
+            .lex_with(&mut lexer)
+            .inspect_err(|_| self.metrics.errors_raised += 1)?;
+
+        self.warned.clear();
+        if self.linting() {
+            self.lint_tokens(&tokens.artifact().0)
+                .inspect_err(|_| self.metrics.errors_raised += 1)?;
+        }
+
+        self.phase = Phase::Parsing;
+        let mut parser = self.parser().with_comments(lexer.take_comments());
+        let ast = tokens.parse_with(&mut parser);
+        self.defines = parser.defines().clone();
+
+        let resolved = ast.resolve()?;
+        #[cfg(feature = "host")]
+        if let Some(cache) = self.parse_cache.as_mut().filter(|_| cached) {
+            cache.put(source, &options, &resolved.artifact().0, &self.defines);
+        }
+        let trace = resolved.interpret_with(self)?;
+        self.last_trace = Some(trace);
+        Ok(())
+    }
+
+    /// executes already parsed statements, e.g. the output of a [`crate::pipeline::Pipeline`].
+    /// Variables persist between calls like they do between lines in the REPL.
+    ///
+    /// An error in a statement is reported to the output and execution carries on with the next
+    /// statement, unless the interpreter is set not to continue on errors. Cancelling the
+    /// interpreter always stops it early.
+    pub fn execute(&mut self, statements: Vec<StmtNode>) -> Result<()> {
+        self.execute_collecting(statements, None)?;
+        Ok(())
+    }
+
+    /// executes statements like [`Interpreter::execute`], but pushes their errors onto diagnostics
+    /// instead of writing them to the output when it is given. Returns the number of statements
+    /// executed, including the ones which failed.
+    fn execute_collecting(
+        &mut self,
+        statements: Vec<StmtNode>,
+        mut diagnostics: Option<&mut Vec<String>>,
+    ) -> Result<usize> {
+        self.phase = Phase::Interpreting;
+        let mut executed = 0;
+        for statement in statements {
+            executed += 1;
+            if let Err(err) = self.execute_statement(&statement) {
+                if err.downcast_ref::<RuntimeError>() == Some(&RuntimeError::Cancelled) {
+                    return Err(err);
+                }
+                self.metrics.errors_raised += 1;
+                let mut message = err.to_string();
+                if let Some(trace) = self.error_trace.take() {
+                    if self.trace_options.on_errors {
+                        message = format!("{}\n{}", message, trace.display(&self.trace_options));
+                        message.truncate(message.trim_end().len());
+                    }
+                }
+                match diagnostics.as_deref_mut() {
+                    Some(diagnostics) => diagnostics.push(message),
+                    None => writeln!(self.output, "{}", message)?,
+                }
+                if !self.continue_on_error {
+                    break;
+                }
+            }
+        }
+        Ok(executed)
+    }
+
+    /// lexes, parses and executes a source like running it does, but collects its errors instead of
+    /// writing them to the output and returns what got done. Unless the interpreter is set not to
+    /// continue on errors, the statements which did not fail have all run, so lenient batch
+    /// processing can keep their results.
+    pub fn eval(&mut self, source: &str) -> Result<PartialResult> {
+        let mut errors = Vec::new();
+        let statements_executed = self.eval_source(source, &mut errors)?;
+        let globals = self
+            .globals()
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect();
+        Ok(PartialResult {
+            statements_executed,
+            errors,
+            globals,
+        })
+    }
+
+    /// runs many named sources one after the other, e.g. the submissions to an exercise or the files
+    /// of a test suite. Every source is lexed and parsed once and then executed in either the shared
+    /// environment of the interpreter or an environment of its own.
+    ///
+    /// Printed values still go to the output, but errors are collected into the returned report
+    /// under the name of their source instead. Only cancelling the interpreter stops the batch early.
+    pub fn eval_many<N, S>(
+        &mut self,
+        sources: impl IntoIterator<Item = (N, S)>,
+        environment: BatchEnvironment,
+    ) -> Result<BatchReport>
+    where
+        N: Into<String>,
+        S: AsRef<str>,
+    {
+        let mut report = BatchReport::default();
+        for (name, source) in sources {
+            let mut diagnostics = Vec::new();
+            let result = match environment {
+                BatchEnvironment::Shared => self.eval_source(source.as_ref(), &mut diagnostics),
+                BatchEnvironment::PerSource => {
+                    let mut fresh = natives::globals();
+                    self.define_stubs(&self.native_capabilities(), &mut fresh);
+                    let shared =
+                        std::mem::replace(&mut self.environment, Rc::new(RefCell::new(fresh)));
+                    let defines = self.defines.clone();
+                    let result = self.eval_source(source.as_ref(), &mut diagnostics);
+                    self.environment = shared;
+                    self.defines = defines;
+                    result
+                }
+            };
+            result?;
+            report.sources.push(SourceReport {
+                name: name.into(),
+                diagnostics,
+            });
+        }
+        Ok(report)
+    }
+
+    /// lexes, parses and executes a single source of [`Interpreter::eval_many`], returning the
+    /// number of statements executed.
+    fn eval_source(&mut self, source: &str, diagnostics: &mut Vec<String>) -> Result<usize> {
+        self.phase = Phase::Lexing;
+        let tokens = match self.lexer().lex(source) {
+            Ok(tokens) => tokens,
+            Err(err) => {
+                self.metrics.errors_raised += 1;
+                diagnostics.push(err.to_string());
+                return Ok(0);
+            }
+        };
+
+        self.warned.clear();
+        if self.linting() {
+            if let Err(err) = self.lint_tokens(&tokens) {
+                self.metrics.errors_raised += 1;
+                diagnostics.push(err.to_string());
+                return Ok(0);
+            }
+        }
+
+        self.phase = Phase::Parsing;
+        let mut parser = self.parser();
+        let statements = parser.parse(tokens);
+        self.defines = parser.defines().clone();
+
+        self.execute_collecting(statements, Some(diagnostics))
+    }
+
+    /// creates a lexer configured with the options of the interpreter.
+    fn lexer(&self) -> Lexer {
+        // defines which hold a truthy value enable the `#if` sections of the same name
+        let flags = self
+            .defines
+            .iter()
+            .filter(|(_, value)| !matches!(value, Literal::Boolean(false) | Literal::Nil))
+            .map(|(name, _)| name.clone())
+            .collect();
+        Lexer::new()
+            .with_flags(flags)
+            .with_case_insensitive_keywords(self.case_insensitive_keywords)
+            .with_language(self.language)
+    }
+
+    /// the options the syntax tree of a source depends on besides the source, which key the parse
+    /// cache along with it
+    fn front_end_options(&self) -> String {
+        let mut defines: Vec<_> = self.defines.iter().collect();
+        defines.sort_by_key(|(name, _)| *name);
+        format!(
+            "case_insensitive_keywords={} automatic_semicolons={} bigint={} language={:?} defines={:?}",
+            self.case_insensitive_keywords,
+            self.automatic_semicolons,
+            cfg!(feature = "bigint"),
+            self.language,
+            defines
+        )
+    }
+
+    /// creates a parser configured with the options of the interpreter.
+    fn parser(&self) -> Parser {
+        Parser::new()
+            .with_defines(self.defines.clone())
+            .with_overrides(self.overrides.clone())
+            .with_constants(self.environment.borrow().constants.clone())
+            .with_automatic_semicolons(self.automatic_semicolons)
+            .with_language(self.language)
+    }
+
+    /// reads in a script and returns one of its intermediate representations instead of running it.
+    pub fn emit_script(&mut self, path: String, emit: Emit, format: EmitFormat) -> Result<String> {
+        let source = self.load(&path)?;
+        let mut lexer = self.lexer().with_comments(true);
+        let tokens = lexer.lex(&source)?;
+        match emit {
+            Emit::Tokens => Ok(emit::tokens(&tokens, format)),
+            Emit::Ast => {
+                let mut parser = self.parser().with_comments(lexer.take_comments());
+                Ok(emit::ast(&parser.parse(tokens), format))
+            }
+            Emit::SemanticTokens => Ok(emit::semantic_tokens(
+                &semantic::classify(&source, &tokens),
+                format,
+            )),
+            Emit::Bytecode => Err(anyhow!(
+                "bytecode can not be emitted, this interpreter runs the syntax tree directly"
+            )),
+        }
+    }
+
+    /// reads in a script and returns it as compact source with short local names, see
+    /// [`crate::minify`].
+    pub fn minify_script(&mut self, path: String) -> Result<String> {
+        let source = self.load(&path)?;
+        let tokens = self.lexer().lex(&source)?;
+        minify::minify(&self.parser().parse(tokens))
+    }
+
+    /// reads in a script and reports on its string literals, see [`crate::analyze`].
+    pub fn string_report(&mut self, path: String) -> Result<StringReport> {
+        let source = self.load(&path)?;
+        let tokens = self.lexer().lex(&source)?;
+        Ok(StringReport::new(path, &tokens))
+    }
+
+    /// reads in a script and fixes the lints in it which can be fixed mechanically, see
+    /// [`crate::fix`]. The script is parsed with automatic semicolons so that the missing ones can be
+    /// added, and a script with syntax errors is not fixed. Lints allowed with
+    /// [`Interpreter::with_lint_level`] are left alone.
+    pub fn fix_script(&mut self, path: String) -> Result<FixReport> {
+        let source = self.load(&path)?;
+        let tokens = self.lexer().lex(&source)?;
+        let mut parser = self.parser().with_automatic_semicolons(true);
+        let errors: Vec<_> = parser
+            .parse(tokens.clone())
+            .into_iter()
+            .filter_map(|statement| match statement {
+                StmtNode::ErrStmt(err) => Some(err),
+                _ => None,
+            })
+            .collect();
+        if !errors.is_empty() {
+            return Err(anyhow!(
+                "can not fix a script with syntax errors: {}",
+                errors.join("\n")
+            ));
+        }
+
+        let fixes = fix::find_fixes(
+            &source,
+            &tokens,
+            parser.inferred_semicolons(),
+            self.language,
+        );
+        let fixes = fixes
+            .into_iter()
+            .filter(|fix| self.lint_levels.get(&fix.id) != Some(&LintLevel::Allow))
+            .collect();
+        let (fixed, fixes) = fix::apply(&source, fixes);
+        Ok(FixReport {
+            name: path,
+            source,
+            fixed,
+            fixes,
+        })
+    }
+
+    /// reads in a script and renames what the name at the position in it names, along with every
+    /// reference to it, see [`crate::refactor`]. The script is left as it is, the report holds the
+    /// renamed source.
+    pub fn rename_in_script(
+        &mut self,
+        path: String,
+        position: Position,
+        new_name: &str,
+    ) -> Result<RenameReport> {
+        let source = self.load(&path)?;
+        RenameReport::new(path, &mut self.lexer(), source, position, new_name)
+    }
+
+    /// reads in a script and reports the capabilities it and the modules it imports require, see
+    /// [`crate::analyze`]. Imports resolve the same way they do when the script runs, and the natives
+    /// audited are the ones defined in the interpreter.
+    pub fn audit_script(&mut self, path: String) -> Result<AuditReport> {
+        let resolved = self.resolve(&path);
+        let source = self.load(&resolved)?;
+        let tokens = self.lexer().lex(&source)?;
+        let statements = self.parser().parse(tokens);
+        let natives = self.native_capabilities();
+        AuditReport::new(path, &statements, &natives, |importer, import| {
+            self.parse_module(importer.unwrap_or(&resolved), import)
+        })
+    }
+
+    /// reads in a script and builds the call graph of it and the modules it imports, see
+    /// [`crate::analyze::CallGraph`]. Imports resolve the same way they do when the script runs, and
+    /// the script goes by its resolved path like the modules do, so a module importing it is not
+    /// mistaken for importing another file.
+    pub fn call_graph(&mut self, path: String) -> Result<CallGraph> {
+        let resolved = self.resolve(&path);
+        let source = self.load(&resolved)?;
+        let tokens = self.lexer().lex(&source)?;
+        let statements = self.parser().parse(tokens);
+        CallGraph::new(resolved.clone(), &statements, |importer, import| {
+            self.parse_module(importer.unwrap_or(&resolved), import)
+        })
+    }
+
+    /// resolves a module imported by the module at the resolved path `importer`, returning its
+    /// resolved path and its statements without running it
+    fn parse_module(&mut self, importer: &str, import: &str) -> Result<(String, Vec<StmtNode>)> {
+        self.importing.push(importer.to_string());
+        let module = self.resolve(import);
+        self.importing.pop();
+        let source = self.load(&module)?;
+        let tokens = self.lexer().lex(&source)?;
+        Ok((module, self.parser().parse(tokens)))
+    }
+
+    /// reads in a script, runs it and then runs its test functions, see [`crate::testing`]. Errors of
+    /// the top-level code are written to the output like they are when running the script, and a
+    /// script which does not parse has no tests to run.
+    #[cfg(feature = "host")]
+    pub fn test_script(&mut self, path: String) -> Result<TestReport> {
+        let resolved = self.resolve(&path);
+        let source = self.load(&resolved)?;
+        // like in run_script, the script counts as imported so its imports resolve relative to it
+        self.importing.push(resolved);
+        let result = self.test_source(source, path);
+        self.importing.pop();
+        result
+    }
+
+    #[cfg(feature = "host")]
+    fn test_source(&mut self, source: String, path: String) -> Result<TestReport> {
+        let tokens = self.lexer().lex(&source)?;
+        let mut parser = self.parser();
+        let statements = parser.parse(tokens);
+        self.defines = parser.defines().clone();
+        let errors: Vec<_> = statements
+            .iter()
+            .filter_map(|statement| match statement {
+                StmtNode::ErrStmt(err) => Some(err.as_str()),
+                _ => None,
+            })
+            .collect();
+        if !errors.is_empty() {
+            return Err(anyhow!(errors.join("\n")));
+        }
+
+        let tests = testing::discover(&statements);
+        self.execute(statements)?;
+        let mut report = TestReport::new(path);
+        for (name, line) in tests {
+            let test = self.environment.borrow().get(&name).unwrap_or(Literal::Nil);
+            let start = Instant::now();
+            let failure = match self.call_value(test, Vec::new(), line) {
+                Ok(_) => None,
+                Err(err) if err.downcast_ref::<RuntimeError>().is_some() => return Err(err),
+                Err(err) => Some(err.to_string()),
+            };
+            report.results.push(TestResult {
+                name,
+                failure,
+                duration: start.elapsed(),
+            });
+        }
+        Ok(report)
+    }
+
+    /// executes a single statement, including the ones nested inside of other statements, checking
+    /// for cancellation first. Errors stop the statements enclosing it as well.
+    fn execute_statement(&mut self, statement: &StmtNode) -> Result<()> {
+        if self.cancelled.take() {
+            self.interrupted_at = Some(self.stack_trace());
+            return Err(anyhow!(RuntimeError::Cancelled));
+        }
+        self.metrics.statements_executed += 1;
+        self.visit_stmt(statement)
+    }
+
+    /// calls a value with arguments which are already evaluated, checking it like a call in Lox is
+    /// checked, e.g. for natives which call back into Lox. The line is the one of the call.
+    pub fn call_value(
+        &mut self,
+        callee: Literal,
+        arguments: Vec<Literal>,
+        line: u32,
+    ) -> Result<Literal> {
+        let Literal::Callable(callable) = callee else {
+            return Err(self.runtime_error(line, MessageId::NotCallable, &[]));
+        };
+        self.check_arity(callable.as_ref(), &arguments, line)?;
+        if self.frames.len() >= MAX_CALL_DEPTH {
+            return Err(self.runtime_error(line, MessageId::StackOverflow, &[]));
+        }
+
+        self.metrics.calls_made += 1;
+        self.frames.push((callable.clone(), line));
+        let result = callable.call(self, arguments, line);
+        if let Err(err) = &result {
+            if self.error_trace.is_none() && !err.is::<Unwind>() && !err.is::<RuntimeError>() {
+                self.error_trace = Some(self.stack_trace());
+            }
+        }
+        self.frames.pop();
+        result
+    }
+
+    /// fails if the callable does not take that many arguments
+    fn check_arity(
+        &self,
+        callable: &dyn LoxCallable,
+        arguments: &[Literal],
+        line: u32,
+    ) -> Result<()> {
+        match callable.arity() {
+            Some(arity) if arity != arguments.len() => Err(self.runtime_error(
+                line,
+                MessageId::WrongArity,
+                &[&arity.to_string(), &arguments.len().to_string()],
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// the path a module is imported by, taken from the script importing it. The modules of plugins
+    /// are found by their names as they are.
+    fn resolve(&self, path: &str) -> String {
+        if self.modules.contains_key(path) {
+            return path.to_string();
+        }
+        self.loader
+            .resolve(self.importing.last().map(String::as_str), path)
+    }
+
+    /// runs the module at the path unless it was imported before, returning its resolved path and
+    /// the environment holding its globals. A module runs in a scope of its own inside of the global
+    /// environment, so it sees the natives and globals while its own declarations stay in the module.
+    fn import(&mut self, path: &str, line: u32) -> Result<(String, Rc<RefCell<Environment>>)> {
+        let resolved = self.resolve(path);
+        let path = resolved.as_str();
+        if let Some(module) = self.imported.get(path) {
+            return Ok((resolved.clone(), module.clone()));
+        }
+        if let Some(start) = self
+            .importing
+            .iter()
+            .position(|importing| importing == path)
+        {
+            let cycle = self.importing[start..]
+                .iter()
+                .map(String::as_str)
+                .chain([path])
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            return Err(self.runtime_error(line, MessageId::CircularImport, &[path, &cycle]));
+        }
+        let statements = self
+            .load(path)
+            .and_then(|source| self.lexer().lex(&source))
+            .map(|tokens| self.parser().parse(tokens))
+            .map_err(|err| {
+                self.runtime_error(line, MessageId::ImportFailed, &[path, &err.to_string()])
+            })?;
+        if let Some(exports) = exports(&statements) {
+            self.exports.insert(path.to_string(), exports);
+        }
+
+        let mut globals = self.environment.clone();
+        loop {
+            let enclosing = globals.borrow().enclosing.clone();
+            match enclosing {
+                Some(enclosing) => globals = enclosing,
+                None => break,
+            }
+        }
+        let module = Rc::new(RefCell::new(Environment::with_enclosing(globals)));
+        let previous = std::mem::replace(&mut self.environment, module.clone());
+        self.suspended.push(previous);
+        self.importing.push(path.to_string());
+        let result = statements
+            .iter()
+            .try_for_each(|statement| self.execute_statement(statement));
+        self.importing.pop();
+        if let Some(previous) = self.suspended.pop() {
+            self.environment = previous;
+        }
+        result?;
+        self.imported.insert(path.to_string(), module.clone());
+        Ok((resolved, module))
+    }
+
+    /// the function calls currently running, from the innermost to the outermost one
+    pub fn stack_trace(&self) -> StackTrace {
+        let frames = self
+            .frames
+            .iter()
+            .rev()
+            .map(|(callable, line)| Frame {
+                function: callable.to_string(),
+                line: *line,
+                native: callable.is_native(),
+            })
+            .collect();
+        StackTrace { frames }
+    }
+
+    /// how stack traces are shown
+    pub fn trace_options(&self) -> &TraceOptions {
+        &self.trace_options
+    }
+
+    /// where the last source run was when it was stopped through the [`CancellationToken`], or None
+    /// if it was not cancelled
+    pub fn interrupted_at(&self) -> Option<&StackTrace> {
+        self.interrupted_at.as_ref()
+    }
+
+    /// Calls a function declared in Lox. A return statement whose value is a call of another such
+    /// function, outside of any try statement, leaves that call to this loop, which makes it in
+    /// place of the call that returned. Calls in tail position thus take no stack, so recursing
+    /// through them runs in constant space however deep it goes.
+    fn call_function(
+        &mut self,
+        function: &LoxFunction,
+        arguments: Vec<Literal>,
+        line: u32,
+    ) -> Result<Literal> {
+        let try_depth = std::mem::take(&mut self.try_depth);
+        let mut result = self.run_function(function, arguments, line);
+        while result.is_ok() {
+            let Some((function, arguments, line)) = self.tail_call.take() else {
+                break;
+            };
+            if let Err(err) = self.check_arity(function.as_ref(), &arguments, line) {
+                result = Err(err);
+                break;
+            }
+            self.metrics.calls_made += 1;
+            if let Some(frame) = self.frames.last_mut() {
+                *frame = (function.clone(), line);
+            }
+            result = self.run_function(&function, arguments, line);
+        }
+        self.tail_call = None;
+        self.try_depth = try_depth;
+        result
+    }
+
+    /// runs the body of a function with the arguments bound to its parameters
+    fn run_function(
+        &mut self,
+        function: &LoxFunction,
+        mut arguments: Vec<Literal>,
+        line: u32,
+    ) -> Result<Literal> {
+        let params = &function.declaration.params;
+        let mut environment = Environment::with_enclosing(function.closure.clone());
+        if let Some(rest) = &function.declaration.rest {
+            if arguments.len() < params.len() {
+                return Err(self.runtime_error(
+                    line,
+                    MessageId::TooFewArguments,
+                    &[&params.len().to_string(), &arguments.len().to_string()],
+                ));
+            }
+            let rest_arguments = arguments.split_off(params.len());
+            self.allocate(rest_arguments.len() * heap::SLOT, line)?;
+            environment.define(rest, Literal::List(Rc::new(RefCell::new(rest_arguments))));
+        }
+        for (param, argument) in params.iter().zip(arguments) {
+            environment.define(param, argument);
+        }
+        let value = match self.execute_block(&function.declaration.body, environment) {
+            Ok(()) => Literal::Nil,
+            Err(err) if err.downcast_ref::<Unwind>() == Some(&Unwind::Return) => {
+                self.return_value.take().unwrap_or(Literal::Nil)
+            }
+            Err(err) => return Err(err),
+        };
+        // an initializer can only return without a value, and returns the instance instead
+        if function.is_initializer {
+            return Ok(function
+                .closure
+                .borrow()
+                .get("this")
+                .unwrap_or(Literal::Nil));
+        }
+        Ok(value)
+    }
+
+    /// executes statements in the given environment, restoring the current one afterwards even if
+    /// a statement fails.
+    fn execute_block(&mut self, statements: &[StmtNode], environment: Environment) -> Result<()> {
+        let previous = std::mem::replace(&mut self.environment, Rc::new(RefCell::new(environment)));
+        self.suspended.push(previous);
+        let result = statements
+            .iter()
+            .try_for_each(|statement| self.execute_statement(statement));
+        if let Some(previous) = self.suspended.pop() {
+            self.environment = previous;
+        }
+        result
+    }
+
+    #[cfg(feature = "host")]
+    pub fn run_repl(&mut self) -> Result<()> {
+        let stdin = std::io::stdin();
+        self.repl(stdin.lock())
+    }
+
+    /// runs a read-eval-print loop over the lines read from input until it reaches the end of input.
+    /// Errors in a line are reported to the output and the loop carries on with the next line.
+    ///
+    /// Entering `:paste` switches to paste mode, where every line up to a lone `:end` line is read
+    /// without a prompt and then run as a single piece of source.
+    ///
+    /// `:doc name` shows the signature and doc comment of the native, function or class of the name.
+    #[cfg(feature = "host")]
+    pub fn repl<R: BufRead>(&mut self, mut input: R) -> Result<()> {
+        loop {
+            let mut buf = String::new();
+
+            write!(self.output, ">> ")?;
+            self.output.flush()?;
+            if input.read_line(&mut buf)? == 0 {
+                break;
+            }
+
+            if buf.trim() == ":paste" {
+                buf = Interpreter::read_paste(&mut input)?;
+            }
+            if let Some(name) = buf.trim().strip_prefix(":doc ") {
+                match self.doc(name.trim()) {
+                    Ok(doc) => writeln!(self.output, "{}", doc)?,
+                    Err(err) => writeln!(self.output, "{}", err)?,
+                }
+                continue;
+            }
+
+            if let Err(err) = self.run_on_string(buf) {
+                if err.downcast_ref::<RuntimeError>() == Some(&RuntimeError::Cancelled) {
+                    return Err(err);
+                }
+                writeln!(self.output, "{}", err)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// the signature of the callable with the name in the current scope, followed by its doc
+    /// comment on the lines after it if it has one
+    pub fn doc(&self, name: &str) -> Result<String> {
+        let value = self
+            .environment
+            .borrow()
+            .get(name)
+            .ok_or_else(|| anyhow!("{} is not defined", name))?;
+        let Literal::Callable(callable) = value else {
+            return Err(anyhow!(
+                "{} is not a function or class, it is {}",
+                name,
+                show(&value)
+            ));
+        };
+        Ok(match callable.doc() {
+            Some(doc) => format!("{}\n{}", callable.signature(), doc),
+            None => callable.signature(),
+        })
+    }
+
+    /// reads the lines of a paste up to (and not including) a lone `:end` line or the end of input.
+    #[cfg(feature = "host")]
+    fn read_paste<R: BufRead>(input: &mut R) -> Result<String> {
+        let mut source = String::new();
+        loop {
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 || line.trim() == ":end" {
+                return Ok(source);
+            }
+            source.push_str(&line);
+        }
+    }
+
+    pub fn error(line: u32, message: String) -> String {
+        Interpreter::report(line, "".into(), message)
+    }
+
+    pub fn report(line: u32, err_where: String, message: String) -> String {
+        format!("[line {}] Error {}: {}", line, err_where, message)
+    }
+
+    /// whether any lint can be reported, so that the checks for them can be skipped otherwise
+    fn linting(&self) -> bool {
+        self.pedantic || !self.lint_levels.is_empty()
+    }
+
+    fn lint_level(&self, id: MessageId) -> LintLevel {
+        match self.lint_levels.get(&id) {
+            Some(level) => *level,
+            None if self.pedantic => LintLevel::Warn,
+            None => LintLevel::Allow,
+        }
+    }
+
+    /// reports a lint at its level. A warning is written to the output unless it was already reported
+    /// for the same line, and a denied lint is returned as an error.
+    fn lint(&mut self, line: u32, id: MessageId, args: &[&str]) -> Result<()> {
+        match self.lint_level(id) {
+            LintLevel::Allow => Ok(()),
+            LintLevel::Warn => {
+                if self.warned.insert((line, id)) {
+                    let message = id.render(self.language, args);
+                    let _ = writeln!(self.output, "[line {}] Warning : {}", line, message);
+                }
+                Ok(())
+            }
+            LintLevel::Deny => Err(self.runtime_error(line, id, args)),
+        }
+    }
+
+    /// lints the lexical extensions to canonical Lox used in a program.
+    fn lint_tokens(&mut self, tokens: &[Token]) -> Result<()> {
+        for token in tokens {
+            if let TokenType::String(value) = &token.token_type {
+                if token.lexeme().starts_with('\'') {
+                    let suggestion = format!("\"{}\"", value);
+                    self.lint(token.line, MessageId::SingleQuotedString, &[&suggestion])?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn check_type() -> Result<()> {
+        Ok(())
+    }
+
+    /// builds a runtime error from the message catalog in the language of the interpreter.
+    fn runtime_error(&self, line: u32, id: MessageId, args: &[&str]) -> anyhow::Error {
+        anyhow!(LoxError {
+            line,
+            code: Some(id.code()),
+            message: id.message(self.language, args),
+        })
+    }
+
+    /// checks that a value is a whole number which is a valid index into a list or string of the
+    /// length
+    fn list_index(&self, index: &Literal, length: usize, line: u32) -> Result<usize> {
+        self.bounded_index(index, length, false, line)
+    }
+
+    /// checks that the bounds of a slice are whole numbers from 0 up to and including the length,
+    /// which default to the start and end, with the start not after the end
+    fn slice_bounds(
+        &self,
+        start: Option<Literal>,
+        end: Option<Literal>,
+        length: usize,
+        line: u32,
+    ) -> Result<(usize, usize)> {
+        let start = match start {
+            Some(start) => self.bounded_index(&start, length, true, line)?,
+            None => 0,
+        };
+        let end = match end {
+            Some(end) => self.bounded_index(&end, length, true, line)?,
+            None => length,
+        };
+        if start > end {
+            return Err(self.runtime_error(
+                line,
+                MessageId::SliceStartAfterEnd,
+                &[&start.to_string(), &end.to_string()],
+            ));
+        }
+        Ok((start, end))
+    }
+
+    /// checks that a value is a whole number below the length, or up to it including the length
+    /// itself for the end of a slice
+    fn bounded_index(
+        &self,
+        index: &Literal,
+        length: usize,
+        inclusive: bool,
+        line: u32,
+    ) -> Result<usize> {
+        let Some(number) = index.as_number() else {
+            return Err(self.runtime_error(
+                line,
+                MessageId::IndexMustBeAWholeNumber,
+                &[&show(index)],
+            ));
+        };
+        if number.fract() != 0.0 {
+            return Err(self.runtime_error(
+                line,
+                MessageId::IndexMustBeAWholeNumber,
+                &[&number.to_string()],
+            ));
+        }
+        if number < 0.0 || number > length as f64 || (number == length as f64 && !inclusive) {
+            return Err(self.runtime_error(
+                line,
+                MessageId::IndexOutOfBounds,
+                &[&number.to_string(), &length.to_string()],
+            ));
+        }
+        Ok(number as usize)
+    }
+
+    /// checks that a value can be the key of a map
+    fn map_key(&self, key: &Literal, line: u32) -> Result<MapKey> {
+        MapKey::new(key)
+            .ok_or_else(|| self.runtime_error(line, MessageId::InvalidMapKey, &[&show(key)]))
+    }
+
+    /// applies the operator of a binary expression to its evaluated operands
+    fn binary(
+        &mut self,
+        left_literal: Literal,
+        operator: &Operator,
+        right_literal: Literal,
+    ) -> Result<Literal> {
+        if self.linting() {
+            if let (Literal::String(_), Literal::String(_)) = (&left_literal, &right_literal) {
+                if let Operator::GreaterThan { line }
+                | Operator::GreaterEqual { line }
+                | Operator::LessThan { line }
+                | Operator::LessEqual { line } = operator
+                {
+                    self.lint(*line, MessageId::StringOrdering, &[operator.lexeme()])?;
+                }
+            }
+        }
+
+        if let Some(result) =
+            crate::number::binary(self.number_mode, operator, &left_literal, &right_literal)
+        {
+            return Ok(result);
+        }
+        // a decimal meeting a value which is not a number, such as a date time, stands for the
+        // double closest to it, except that it is concatenated to strings with all of its digits
+        let concatenation = matches!(left_literal, Literal::String(_))
+            || matches!(right_literal, Literal::String(_));
+        let [left_literal, right_literal] =
+            [left_literal, right_literal].map(|value| match value {
+                Literal::Decimal(decimal) if !concatenation => Literal::Number(decimal.to_f64()),
+                value => value,
+            });
+
+        #[cfg(feature = "bigint")]
+        if let Some(result) = crate::bigint::binary(operator, &left_literal, &right_literal) {
+            return result.map_err(|err| {
+                let args = err.args();
+                let args: Vec<&str> = args.iter().map(String::as_str).collect();
+                self.runtime_error(operator.line(), err.id(), &args)
+            });
+        }
+
+        if let Some(result) = crate::datetime::binary(operator, &left_literal, &right_literal) {
+            return result.map_err(|seconds| {
+                self.runtime_error(
+                    operator.line(),
+                    MessageId::DateOutOfRange,
+                    &[&seconds.to_string()],
+                )
+            });
+        }
+
+        match operator {
+            Operator::Add { line } => self.add_impl(left_literal, right_literal, *line),
+            Operator::Subtract { line } => match (left_literal, right_literal) {
+                (Literal::Number(l), Literal::Number(r)) => Ok(Literal::Number(l - r)),
+                _ => Err(self.runtime_error(*line, MessageId::OperandsMustBeNumbers, &[])),
+            },
+            Operator::Multiply { line } => match (left_literal, right_literal) {
+                (Literal::Number(l), Literal::Number(r)) => Ok(Literal::Number(l * r)),
+                _ => Err(self.runtime_error(*line, MessageId::OperandsMustBeNumbers, &[])),
+            },
+            Operator::Divide { line } => match (left_literal, right_literal) {
+                (Literal::Number(l), Literal::Number(r)) => Ok(Literal::Number(l / r)),
+                _ => Err(self.runtime_error(*line, MessageId::OperandsMustBeNumbers, &[])),
+            },
+            Operator::GreaterThan { line } => match (left_literal, right_literal) {
+                (Literal::Number(l), Literal::Number(r)) => Ok(Literal::Boolean(l > r)),
+                (Literal::String(l), Literal::String(r)) => Ok(Literal::Boolean(l > r)),
+                _ => Err(self.runtime_error(*line, MessageId::OperandsMustBeNumbersOrStrings, &[])),
+            },
+            Operator::GreaterEqual { line } => match (left_literal, right_literal) {
+                (Literal::Number(l), Literal::Number(r)) => Ok(Literal::Boolean(l >= r)),
+                (Literal::String(l), Literal::String(r)) => Ok(Literal::Boolean(l >= r)),
+                _ => Err(self.runtime_error(*line, MessageId::OperandsMustBeNumbersOrStrings, &[])),
+            },
+            Operator::LessThan { line } => match (left_literal, right_literal) {
+                (Literal::Number(l), Literal::Number(r)) => Ok(Literal::Boolean(l < r)),
+                (Literal::String(l), Literal::String(r)) => Ok(Literal::Boolean(l < r)),
+                _ => Err(self.runtime_error(*line, MessageId::OperandsMustBeNumbersOrStrings, &[])),
+            },
+            Operator::LessEqual { line } => match (left_literal, right_literal) {
+                (Literal::Number(l), Literal::Number(r)) => Ok(Literal::Boolean(l <= r)),
+                (Literal::String(l), Literal::String(r)) => Ok(Literal::Boolean(l <= r)),
+                _ => Err(self.runtime_error(*line, MessageId::OperandsMustBeNumbersOrStrings, &[])),
+            },
+            Operator::EqualEqual { line: _ } => {
+                Ok(Literal::Boolean(left_literal.is_equal(&right_literal)))
+            }
+            Operator::NotEqual { line: _ } => {
+                Ok(Literal::Boolean(!left_literal.is_equal(&right_literal)))
+            }
+            _ => Err(self.runtime_error(
+                operator.line(),
+                MessageId::UnsupportedOperator,
+                &[&format!("{:?}", operator)],
+            )),
+        }
+    }
+
+    /// evaluates the addition of a left and right literal and returns the result
+    /// for two numbers this is a simple addition
+    /// for two strings this is a concatenation of right on the end of left
+    fn add_impl(&mut self, left: Literal, right: Literal, line: u32) -> Result<Literal> {
+        match (left, right) {
+            (Literal::Number(l), Literal::Number(r)) => Ok(Literal::Number(l + r)),
+            (Literal::String(l), Literal::String(r)) => {
+                self.allocate(l.len() + r.len(), line)?;
+                Ok(Literal::String(l + &r))
+            }
+            (Literal::String(left), right) if self.string_concatenation => {
+                self.lint(line, MessageId::StringConcatenation, &[&right.to_string()])?;
+                Ok(Literal::String(left + &right.to_string()))
+            }
+            (left, Literal::String(right)) if self.string_concatenation => {
+                self.lint(line, MessageId::StringConcatenation, &[&left.to_string()])?;
+                Ok(Literal::String(left.to_string() + &right))
+            }
+            (Literal::Number(left), _) => {
+                Err(self.runtime_error(line, MessageId::AddNumberToNonNumber, &[&left.to_string()]))
+            }
+            (Literal::Decimal(left), _) => {
+                Err(self.runtime_error(line, MessageId::AddNumberToNonNumber, &[&left.to_string()]))
+            }
+            (Literal::String(left), _) => {
+                Err(self.runtime_error(line, MessageId::AddStringToNonString, &[&left]))
+            }
+            _ => Err(self.runtime_error(line, MessageId::OperandsMustBeNumbersOrStrings, &[])),
+        }
+    }
+}
+
+impl StmtVisitor for Interpreter {
+    type Output = Result<()>;
+
+    fn visit_print_stmt(&mut self, node: &ExprNode) -> Self::Output {
+        let value = self.visit_expr_node(node)?;
+        writeln!(self.output, "{}", self.format_value(&value))?;
+        self.last_value = Some(value);
+        Ok(())
+    }
+
+    fn visit_expr_stmt(&mut self, node: &ExprNode) -> Self::Output {
+        self.last_value = Some(self.visit_expr_node(node)?);
+        Ok(())
+    }
+
+    fn visit_err_stmt(&mut self, err: String) -> Self::Output {
+        Err(anyhow!(err))
+    }
+
+    fn visit_var_stmt(&mut self, name: &str, initializer: Option<&ExprNode>) -> Self::Output {
+        let value = match initializer {
+            Some(expr) => self.visit_expr_node(expr)?,
+            None => Literal::Nil,
+        };
+        self.environment.borrow_mut().define(name, value);
+        Ok(())
+    }
+
+    fn visit_const_stmt(&mut self, name: &str, initializer: &ExprNode) -> Self::Output {
+        let value = self.visit_expr_node(initializer)?;
+        self.environment.borrow_mut().define_constant(name, value);
+        Ok(())
+    }
+
+    /// only the globals a module exports are imported, and importing a name it does not export is an
+    /// error
+    fn visit_import_stmt(
+        &mut self,
+        path: &str,
+        name: Option<&str>,
+        names: &[String],
+        line: u32,
+    ) -> Self::Output {
+        let (resolved, module) = self.import(path, line)?;
+        let module = module.borrow();
+        let exports = self.exports.get(&resolved);
+        let exported = |global: &str| exports.is_none_or(|exports| exports.contains(global));
+        let missing = names
+            .iter()
+            .find(|global| !exported(global) || !module.values.contains_key(*global));
+        if let Some(missing) = missing {
+            return Err(self.runtime_error(line, MessageId::NotExported, &[missing, path]));
+        }
+        let globals = module.iter().filter(|(global, _)| {
+            exported(global) && (names.is_empty() || names.iter().any(|name| name == global))
+        });
+        let mut environment = self.environment.borrow_mut();
+        match name {
+            Some(name) => {
+                let class = LoxClass {
+                    name: name.to_string(),
+                    superclass: None,
+                    methods: HashMap::new(),
+                    class_methods: HashMap::new(),
+                    doc: None,
+                };
+                let fields = globals
+                    .map(|(global, value)| (global.to_string(), value.clone()))
+                    .collect();
+                let namespace = LoxInstance {
+                    class: Rc::new(class),
+                    fields: RefCell::new(fields),
+                };
+                environment.define(name, Literal::Instance(Rc::new(namespace)));
+            }
+            None => {
+                for (global, value) in globals {
+                    if module.constants.contains(global) {
+                        environment.define_constant(global, value.clone());
+                    } else {
+                        environment.define(global, value.clone());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_export_stmt(&mut self, statement: &StmtNode, _line: u32) -> Self::Output {
+        self.visit_stmt(statement)
+    }
+
+    fn visit_block_stmt(&mut self, statements: &[StmtNode]) -> Self::Output {
+        let environment = Environment::with_enclosing(self.environment.clone());
+        self.execute_block(statements, environment)
+    }
+
+    fn visit_if_stmt(
+        &mut self,
+        condition: &ExprNode,
+        then_branch: &StmtNode,
+        else_branch: Option<&StmtNode>,
+    ) -> Self::Output {
+        if self.visit_expr_node(condition)?.is_truthy() {
+            self.execute_statement(then_branch)
+        } else if let Some(else_branch) = else_branch {
+            self.execute_statement(else_branch)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn visit_while_stmt(
+        &mut self,
+        condition: &ExprNode,
+        body: &StmtNode,
+        increment: Option<&ExprNode>,
+    ) -> Self::Output {
+        while self.visit_expr_node(condition)?.is_truthy() {
+            if let Err(err) = self.execute_statement(body) {
+                match err.downcast_ref::<Unwind>() {
+                    Some(Unwind::Break) => break,
+                    Some(Unwind::Continue) => {}
+                    Some(Unwind::Return) | None => return Err(err),
+                }
+            }
+            if let Some(increment) = increment {
+                self.visit_expr_node(increment)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_do_while_stmt(&mut self, body: &StmtNode, condition: &ExprNode) -> Self::Output {
+        loop {
+            if let Err(err) = self.execute_statement(body) {
+                match err.downcast_ref::<Unwind>() {
+                    Some(Unwind::Break) => break,
+                    Some(Unwind::Continue) => {}
+                    Some(Unwind::Return) | None => return Err(err),
+                }
+            }
+            if !self.visit_expr_node(condition)?.is_truthy() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_break_stmt(&mut self, _line: u32) -> Self::Output {
+        Err(Unwind::Break.into())
+    }
+
+    fn visit_continue_stmt(&mut self, _line: u32) -> Self::Output {
+        Err(Unwind::Continue.into())
+    }
+
+    fn visit_function_stmt(&mut self, declaration: &Rc<FunctionDecl>) -> Self::Output {
+        let function = LoxFunction {
+            declaration: declaration.clone(),
+            closure: self.environment.clone(),
+            is_initializer: false,
+        };
+        self.environment
+            .borrow_mut()
+            .define(&declaration.name, Literal::Callable(Rc::new(function)));
+        Ok(())
+    }
+
+    fn visit_class_stmt(&mut self, declaration: &Rc<ClassDecl>) -> Self::Output {
+        let superclass = match &declaration.superclass {
+            Some(superclass) => match self.visit_expr_node(superclass)? {
+                Literal::Callable(callable) => callable.as_class(),
+                _ => None,
+            }
+            .map(Some)
+            .ok_or_else(|| {
+                self.runtime_error(declaration.line, MessageId::SuperclassMustBeAClass, &[])
+            })?,
+            None => None,
+        };
+        // the methods of a subclass close over an environment binding `super` to the superclass
+        let closure = match &superclass {
+            Some(superclass) => {
+                let mut environment = Environment::with_enclosing(self.environment.clone());
+                environment.define("super", Literal::Callable(superclass.clone()));
+                Rc::new(RefCell::new(environment))
+            }
+            None => self.environment.clone(),
+        };
+        // only an `init` method is an initializer, a class method named init is not
+        let functions = |methods: &[Rc<FunctionDecl>], can_initialize: bool| {
+            methods
+                .iter()
+                .map(|method| {
+                    let function = LoxFunction {
+                        declaration: method.clone(),
+                        closure: closure.clone(),
+                        is_initializer: can_initialize && method.name == "init",
+                    };
+                    (method.name.clone(), Rc::new(function))
+                })
+                .collect()
+        };
+        let class = LoxClass {
+            name: declaration.name.clone(),
+            superclass,
+            methods: functions(&declaration.methods, true),
+            class_methods: functions(&declaration.class_methods, false),
+            doc: declaration.doc.clone(),
+        };
+        if self.linting() {
+            for method in &declaration.class_methods {
+                self.lint(method.line, MessageId::ClassMethod, &[&method.name])?;
+            }
+        }
+        self.environment
+            .borrow_mut()
+            .define(&declaration.name, Literal::Callable(Rc::new(class)));
+        Ok(())
+    }
+
+    fn visit_return_stmt(&mut self, value: Option<&ExprNode>, _line: u32) -> Self::Output {
+        let value = match value {
+            Some(ExprNode::Call {
+                callee,
+                arguments,
+                line,
+            }) if self.try_depth == 0 && !self.frames.is_empty() => {
+                let callee = self.visit_expr_node(callee)?;
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| self.visit_expr_node(argument))
+                    .collect::<Result<Vec<_>>>()?;
+                match &callee {
+                    Literal::Callable(callable) => match callable.clone().as_function() {
+                        Some(function) => {
+                            self.tail_call = Some((function, arguments, *line));
+                            return Err(Unwind::Return.into());
+                        }
+                        None => self.call_value(callee, arguments, *line)?,
+                    },
+                    _ => self.call_value(callee, arguments, *line)?,
+                }
+            }
+            Some(expr) => self.visit_expr_node(expr)?,
+            None => Literal::Nil,
+        };
+        self.return_value = Some(value);
+        Err(Unwind::Return.into())
+    }
+
+    fn visit_throw_stmt(&mut self, value: &ExprNode, line: u32) -> Self::Output {
+        let value = self.visit_expr_node(value)?;
+        let message = self
+            .runtime_error(line, MessageId::UncaughtException, &[&show(&value)])
+            .to_string();
+        self.thrown = Some(value);
+        Err(Thrown(message).into())
+    }
+
+    fn visit_try_stmt(
+        &mut self,
+        body: &[StmtNode],
+        name: &str,
+        handler: &[StmtNode],
+    ) -> Self::Output {
+        self.try_depth += 1;
+        let result = self.visit_block_stmt(body);
+        self.try_depth -= 1;
+        match result {
+            Err(err) if is_catchable(&err) => {
+                let value = match err.is::<Thrown>() {
+                    true => self.thrown.take().unwrap_or(Literal::Nil),
+                    false => caught(&err),
+                };
+                self.error_trace = None;
+                let mut environment = Environment::with_enclosing(self.environment.clone());
+                environment.define(name, value);
+                self.execute_block(handler, environment)
+            }
+            result => result,
+        }
+    }
+}
+
+impl ExprVisitor for Interpreter {
+    type Output = Result<Literal>;
+
+    fn visit_literal(&mut self, literal: &Literal) -> Self::Output {
+        Ok(literal.clone())
+    }
+
+    fn visit_grouping(&mut self, grouping: &ExprNode) -> Self::Output {
+        self.visit_expr_node(grouping)
+    }
+
+    /// evaluates a chain such as `1 + 2 + 3` from its innermost operation out, without recursing
+    /// into the left operands, so that a chain of any length fits on the stack.
+    fn visit_binary_expr(
+        &mut self,
+        left: &ExprNode,
+        operator: &Operator,
+        right: &ExprNode,
+    ) -> Self::Output {
+        let mut operations = vec![(operator, right)];
+        let mut leftmost = left;
+        while let ExprNode::BinaryExpr {
+            left,
+            operator,
+            right,
+        } = leftmost
+        {
+            operations.push((operator, right));
+            leftmost = left;
+        }
+
+        let mut value = self.visit_expr_node(leftmost)?;
+        for (operator, right) in operations.into_iter().rev() {
+            let right = self.visit_expr_node(right)?;
+            value = self.binary(value, operator, right)?;
+        }
+        Ok(value)
+    }
+
+    fn visit_unary_expr(&mut self, operator: &Operator, child: &ExprNode) -> Self::Output {
+        let output = self.visit_expr_node(child)?;
+
+        match operator {
+            Operator::Bang { line: _ } => Ok(Literal::Boolean(!output.is_truthy())),
+            Operator::Subtract { line } => match output {
+                Literal::Number(value) => Ok(Literal::Number(-value)),
+                Literal::Decimal(value) => Ok(Literal::Decimal(-value)),
+                #[cfg(feature = "bigint")]
+                Literal::BigInt(value) => Ok(Literal::BigInt(-value)),
+                _ => Err(self.runtime_error(*line, MessageId::UnaryMinusOperand, &[])),
+            },
+            _ => Err(self.runtime_error(
+                operator.line(),
+                MessageId::UnsupportedOperator,
+                &[&format!("{:?}", operator)],
+            )),
+        }
+    }
+
+    /// returns the left operand if it decides the result, so `nil or "default"` is `"default"` and
+    /// `nil and 1` is `nil`, without evaluating the right operand. Like a binary expression, a chain
+    /// such as `a or b or c` is evaluated without recursing into its left operands.
+    fn visit_logical_expr(
+        &mut self,
+        left: &ExprNode,
+        operator: &Operator,
+        right: &ExprNode,
+    ) -> Self::Output {
+        let mut operations = vec![(operator, right)];
+        let mut leftmost = left;
+        while let ExprNode::Logical {
+            left,
+            operator,
+            right,
+        } = leftmost
+        {
+            operations.push((operator, right));
+            leftmost = left;
+        }
+
+        let mut value = self.visit_expr_node(leftmost)?;
+        for (operator, right) in operations.into_iter().rev() {
+            let short_circuits = match operator {
+                Operator::Or { .. } => value.is_truthy(),
+                Operator::And { .. } => !value.is_truthy(),
+                _ => {
+                    return Err(self.runtime_error(
+                        operator.line(),
+                        MessageId::UnsupportedOperator,
+                        &[&format!("{:?}", operator)],
+                    ))
+                }
+            };
+            if !short_circuits {
+                value = self.visit_expr_node(right)?;
+            }
+        }
+        Ok(value)
+    }
+
+    fn visit_variable(&mut self, name: &str, line: u32) -> Self::Output {
+        self.environment
+            .borrow()
+            .get(name)
+            .ok_or_else(|| self.runtime_error(line, MessageId::UndefinedVariable, &[name]))
+    }
+
+    fn visit_assign(&mut self, name: &str, value: &ExprNode, line: u32) -> Self::Output {
+        let value = self.visit_expr_node(value)?;
+        if self.environment.borrow().is_constant(name) {
+            return Err(self.runtime_error(line, MessageId::AssignToConstant, &[name]));
+        }
+        if self.environment.borrow_mut().assign(name, value.clone()) {
+            Ok(value)
+        } else {
+            Err(self.runtime_error(line, MessageId::UndefinedVariable, &[name]))
+        }
+    }
+
+    fn visit_call(&mut self, callee: &ExprNode, arguments: &[ExprNode], line: u32) -> Self::Output {
+        let callee = self.visit_expr_node(callee)?;
+        let arguments = arguments
+            .iter()
+            .map(|argument| self.visit_expr_node(argument))
+            .collect::<Result<Vec<_>>>()?;
+        self.call_value(callee, arguments, line)
+    }
+
+    /// reads a property of an instance, or a class method of a class
+    fn visit_get(&mut self, object: &ExprNode, name: &str, line: u32) -> Self::Output {
+        let property = match self.visit_expr_node(object)? {
+            Literal::Instance(instance) => instance.get(name),
+            Literal::Callable(callable) => match callable.as_class() {
+                Some(class) => class
+                    .find_class_method(name)
+                    .map(|method| Literal::Callable(method)),
+                None => {
+                    return Err(self.runtime_error(
+                        line,
+                        MessageId::OnlyInstancesHaveProperties,
+                        &[],
+                    ))
+                }
+            },
+            _ => return Err(self.runtime_error(line, MessageId::OnlyInstancesHaveProperties, &[])),
+        };
+        property.ok_or_else(|| self.runtime_error(line, MessageId::UndefinedProperty, &[name]))
+    }
+
+    fn visit_set(
+        &mut self,
+        object: &ExprNode,
+        name: &str,
+        value: &ExprNode,
+        line: u32,
+    ) -> Self::Output {
+        let Literal::Instance(instance) = self.visit_expr_node(object)? else {
+            return Err(self.runtime_error(line, MessageId::OnlyInstancesHaveFields, &[]));
+        };
+        let value = self.visit_expr_node(value)?;
+        self.allocate(name.len() + heap::SLOT, line)?;
+        instance.set(name, value.clone());
+        Ok(value)
+    }
+
+    fn visit_list(&mut self, elements: &[ExprNode], line: u32) -> Self::Output {
+        let elements = elements
+            .iter()
+            .map(|element| self.visit_expr_node(element))
+            .collect::<Result<Vec<_>>>()?;
+        self.allocate(elements.len() * heap::SLOT, line)?;
+        Ok(Literal::List(Rc::new(RefCell::new(elements))))
+    }
+
+    fn visit_map(&mut self, entries: &[(ExprNode, ExprNode)], line: u32) -> Self::Output {
+        let mut map = HashMap::with_capacity(entries.len());
+        for (key, value) in entries {
+            let key = self.visit_expr_node(key)?;
+            let key = self.map_key(&key, line)?;
+            map.insert(key, self.visit_expr_node(value)?);
+        }
+        self.allocate(map.len() * 2 * heap::SLOT, line)?;
+        Ok(Literal::Map(LoxMap::new(map)))
+    }
+
+    fn visit_index(&mut self, object: &ExprNode, index: &ExprNode, line: u32) -> Self::Output {
+        let object = self.visit_expr_node(object)?;
+        let index = self.visit_expr_node(index)?;
+        match object {
+            Literal::List(list) => {
+                let list = list.borrow();
+                let index = self.list_index(&index, list.len(), line)?;
+                Ok(list[index].clone())
+            }
+            Literal::Map(map) => {
+                let key = self.map_key(&index, line)?;
+                map.get(&key).ok_or_else(|| {
+                    self.runtime_error(line, MessageId::UndefinedKey, &[&key.to_string()])
+                })
+            }
+            // strings are indexed by character rather than by byte
+            Literal::String(string) => {
+                let characters = unicode::characters(&string);
+                let index = self.list_index(&index, characters.len(), line)?;
+                Ok(Literal::String(characters[index].to_string()))
+            }
+            Literal::Bytes(bytes) => {
+                let index = self.list_index(&index, bytes.len(), line)?;
+                Ok(Literal::Number(bytes[index] as f64))
+            }
+            _ => Err(self.runtime_error(line, MessageId::NotIndexable, &[])),
+        }
+    }
+
+    fn visit_slice(
+        &mut self,
+        object: &ExprNode,
+        start: Option<&ExprNode>,
+        end: Option<&ExprNode>,
+        line: u32,
+    ) -> Self::Output {
+        let object = self.visit_expr_node(object)?;
+        let start = start.map(|start| self.visit_expr_node(start)).transpose()?;
+        let end = end.map(|end| self.visit_expr_node(end)).transpose()?;
+        match object {
+            Literal::List(list) => {
+                let list = list.borrow();
+                let (start, end) = self.slice_bounds(start, end, list.len(), line)?;
+                self.allocate((end - start) * heap::SLOT, line)?;
+                Ok(Literal::List(Rc::new(RefCell::new(
+                    list[start..end].to_vec(),
+                ))))
+            }
+            Literal::String(string) => {
+                let (start, end) = self.slice_bounds(start, end, unicode::length(&string), line)?;
+                Ok(Literal::String(unicode::slice(&string, start, end)))
+            }
+            Literal::Bytes(bytes) => {
+                let (start, end) = self.slice_bounds(start, end, bytes.len(), line)?;
+                Ok(Literal::Bytes(bytes[start..end].into()))
+            }
+            _ => Err(self.runtime_error(line, MessageId::NotSliceable, &[])),
+        }
+    }
+
+    fn visit_set_index(
+        &mut self,
+        object: &ExprNode,
+        index: &ExprNode,
+        value: &ExprNode,
+        line: u32,
+    ) -> Self::Output {
+        let object = self.visit_expr_node(object)?;
+        let index = self.visit_expr_node(index)?;
+        let list = match object {
+            Literal::List(list) => list,
+            Literal::Map(map) => {
+                let key = self.map_key(&index, line)?;
+                let value = self.visit_expr_node(value)?;
+                self.allocate(2 * heap::SLOT, line)?;
+                map.insert(key, value.clone());
+                return Ok(value);
+            }
+            Literal::String(_) => {
+                return Err(self.runtime_error(line, MessageId::StringsAreImmutable, &[]))
+            }
+            Literal::Bytes(_) => {
+                return Err(self.runtime_error(line, MessageId::BytesAreImmutable, &[]))
+            }
+            _ => return Err(self.runtime_error(line, MessageId::NotIndexable, &[])),
+        };
+        let value = self.visit_expr_node(value)?;
+        // the value can change the length of the list, so the index is checked once it is evaluated
+        let index = self.list_index(&index, list.borrow().len(), line)?;
+        list.borrow_mut()[index] = value.clone();
+        Ok(value)
+    }
+
+    fn visit_this(&mut self, line: u32) -> Self::Output {
+        self.visit_variable("this", line)
+    }
+
+    /// looks the method up from the superclass of the class the method using `super` is declared
+    /// in, rather than from the class of the instance
+    fn visit_super(&mut self, method: &str, line: u32) -> Self::Output {
+        let superclass = self.visit_variable("super", line)?;
+        let this = self.visit_variable("this", line)?;
+        // the parser only allows super inside of the methods of a subclass, so this only fails for
+        // syntax trees which were not made by the parser
+        let (Literal::Callable(superclass), Literal::Instance(instance)) = (superclass, this)
+        else {
+            return Err(self.runtime_error(line, MessageId::SuperclassMustBeAClass, &[]));
+        };
+        let Some(superclass) = superclass.as_class() else {
+            return Err(self.runtime_error(line, MessageId::SuperclassMustBeAClass, &[]));
+        };
+        match superclass.find_method(method) {
+            Some(found) => Ok(Literal::Callable(Rc::new(found.bind(instance)))),
+            None => Err(self.runtime_error(line, MessageId::UndefinedProperty, &[method])),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::loader::InMemoryLoader;
+    use crate::pipeline::Stage;
+
+    #[test]
+    // tests that the lexer and the parser and interpreter can all be used together to generate
+    // the expected output from some lox source code.
+    fn interpreter_integration_test_expression() {}
+
+    #[test]
+    fn unary_expr_test() {
+        let expr = "!5";
+        let result = get_parsed_expr(expr);
+        assert_eq!(result, Literal::Boolean(false));
+
+        let expr = "-2";
+        let result = get_parsed_expr(expr);
+        assert_eq!(result, Literal::Number(-2.0));
+    }
+
+    #[test]
+    fn add_sub_expr_test() {
+        let expr = "1 + 2";
+        let result = get_parsed_expr(expr);
+        assert_eq!(result, Literal::Number(3.0));
+
+        let expr = "1 - 2";
+        let result = get_parsed_expr(expr);
+        assert_eq!(result, Literal::Number(-1.0));
+
+        let expr = "1 + 2 - 3";
+        let result = get_parsed_expr(expr);
+        assert_eq!(result, Literal::Number(0.0));
+    }
+
+    #[test]
+    fn mul_sub_expr_test() {
+        let expr = "1 * 2";
+        let result = get_parsed_expr(expr);
+        assert_eq!(result, Literal::Number(2.0));
+
+        let expr = "1 / 2";
+        let result = get_parsed_expr(expr);
+        assert_eq!(result, Literal::Number(0.5));
+
+        let expr = "1 * 2 / 3";
+        let result = get_parsed_expr(expr);
+        assert_eq!(result, Literal::Number(2.0 / 3.0));
+    }
+
+    #[test]
+    fn grouping_expr_test() {
+        let expr = "1 +(2 * 3)";
+        let result = get_parsed_expr(expr);
+        assert_eq!(result, Literal::Number(7.0));
+
+        let expr = "(1 + 2) * 3";
+        let result = get_parsed_expr(expr);
+        assert_eq!(result, Literal::Number(9.0));
+
+        let expr = "(1 + 2) * (3 + 4)";
+        let result = get_parsed_expr(expr);
+        assert_eq!(result, Literal::Number(21.0));
+
+        let expr = "3 * (1 + 2)";
+        let result = get_parsed_expr(expr);
+        assert_eq!(result, Literal::Number(9.0));
+    }
+
+    #[test]
+    /// tests that the ">", "<", ">=", and "<=" operators work as expected.
+    fn greater_less_than_expr_tests() {
+        let expr = "1 > 2";
+        let result = get_parsed_expr(expr);
+        assert_eq!(result, Literal::Boolean(false));
+
+        let expr = "1 < 2";
+        let result = get_parsed_expr(expr);
+        assert_eq!(result, Literal::Boolean(true));
+
+        let expr = "1 >= 2";
+        let result = get_parsed_expr(expr);
+        assert_eq!(result, Literal::Boolean(false));
+
+        let expr = "1 <= 2";
+        let result = get_parsed_expr(expr);
+        assert_eq!(result, Literal::Boolean(true));
+
+        // the inclusive comparisons hold for equal operands where the strict ones don't
+        let expr = "2 >= 2";
+        let result = get_parsed_expr(expr);
+        assert_eq!(result, Literal::Boolean(true));
+
+        let expr = "2 <= 2";
+        let result = get_parsed_expr(expr);
+        assert_eq!(result, Literal::Boolean(true));
+
+        let expr = "2 > 2";
+        let result = get_parsed_expr(expr);
+        assert_eq!(result, Literal::Boolean(false));
+    }
+
+    #[test]
+    /// tests that the "==" and "!=" operators work as expected.
+    fn equals_equals_expr_test() {
+        let expr = "1 == 1";
+        let result = get_parsed_expr(expr);
+        assert_eq!(result, Literal::Boolean(true));
+
+        let expr = "1 == 2";
+        let result = get_parsed_expr(expr);
+        assert_eq!(result, Literal::Boolean(false));
+
+        let expr = "1 != 2";
+        let result = get_parsed_expr(expr);
+        assert_eq!(result, Literal::Boolean(true));
 
         let expr = "1 != 1";
         let result = get_parsed_expr(expr);
         assert_eq!(result, Literal::Boolean(false));
     }
 
+    #[test]
+    /// tests that a print expression evaluates to the same value with or without parenthesis
+    fn print_parenthesis_test() {
+        assert_eq!(get_parsed_expr("(\"hello\")"), get_parsed_expr("\"hello\""));
+        assert_eq!(get_parsed_expr("(1 + 2)"), get_parsed_expr("1 + 2"));
+    }
+
+    #[test]
+    /// tests that runtime errors keep their message code while the text follows the selected language
+    fn localized_runtime_error_test() {
+        let mut lexer = Lexer::new();
+        let mut tokens = lexer.lex("1 - \"a\"").unwrap();
+        let node = Parser::new().expression(&mut tokens).unwrap();
+
+        let mut interpreter = Interpreter::new().with_language(Language::English);
+        let err = interpreter.visit_expr_node(&node).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "[line 1] Error : [E0001] Operands must be two numbers"
+        );
+
+        let mut interpreter = Interpreter::new().with_language(Language::Spanish);
+        let err = interpreter.visit_expr_node(&node).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "[line 1] Error : [E0001] Los operandos deben ser dos números"
+        );
+    }
+
+    #[test]
+    /// tests that the errors of the lexer and the parser follow the selected language too
+    fn localized_syntax_error_test() {
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::Spanish);
+        interpreter.run_on_string("var = 1;".to_string()).unwrap();
+        assert_eq!(
+            output.take(),
+            "[S0044] Se esperaba un nombre de variable después de 'var' en la línea 1\n"
+        );
+
+        let err = interpreter
+            .run_on_string("print \"a;".to_string())
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "\"[S0006] Literal de cadena sin terminar a;\" (line 1)"
+        );
+    }
+
+    #[test]
+    /// tests that executed statements, raised errors and allocations are counted in the metrics
+    /// snapshot
+    fn metrics_test() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(interpreter.metrics(), Metrics::default());
+
+        interpreter
+            .run_on_string("1 + 2;\n1 - \"a\";\n) 3;".to_string())
+            .unwrap();
+        let metrics = interpreter.metrics();
+        assert_eq!(metrics.statements_executed, 3);
+        assert_eq!(metrics.errors_raised, 2);
+        assert_eq!(metrics.calls_made, 0);
+        assert_eq!(metrics.allocations, 0);
+
+        interpreter
+            .run_on_string(
+                "var l = [1, 2];\nvar s = \"a\" + \"b\";\nvar m = {\"k\": l};".to_string(),
+            )
+            .unwrap();
+        assert_eq!(interpreter.metrics().allocations, 3);
+
+        // lexical errors are counted as well
+        assert!(interpreter.run_on_string("#".to_string()).is_err());
+        assert_eq!(interpreter.metrics().errors_raised, 3);
+    }
+
+    #[test]
+    /// tests that a cancelled interpreter stops before the next statement and can be reused afterwards
+    fn cancellation_test() {
+        let mut interpreter = Interpreter::new();
+        let token = interpreter.cancellation_token();
+        token.clone().cancel();
+        assert!(token.is_cancelled());
+
+        let err = interpreter
+            .run_on_string("1 + 2;\n3 + 4;".to_string())
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<RuntimeError>(),
+            Some(&RuntimeError::Cancelled)
+        );
+        assert_eq!(interpreter.metrics().statements_executed, 0);
+
+        // the cancellation request is cleared once the interpreter has stopped
+        assert!(!token.is_cancelled());
+        interpreter
+            .run_on_string("1 + 2;\n3 + 4;".to_string())
+            .unwrap();
+        assert_eq!(interpreter.metrics().statements_executed, 2);
+    }
+
+    #[test]
+    /// tests that defines given to the interpreter take precedence over the ones in the source
+    fn define_test() {
+        let source = "define DEBUG false;\nprint DEBUG;";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_define("DEBUG".to_string(), Literal::Boolean(true));
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(output.take(), "true\n");
+
+        // defines persist between runs like in the REPL
+        interpreter
+            .run_on_string("define N 2;".to_string())
+            .unwrap();
+        interpreter
+            .run_on_string("print N * 2;".to_string())
+            .unwrap();
+        assert_eq!(output.take(), "4\n");
+
+        // but a later line can not define them again
+        interpreter
+            .run_on_string("define N 3;\nprint N;".to_string())
+            .unwrap();
+        let output = output.take();
+        assert!(output.contains("[S0091]"));
+        assert!(output.ends_with("2\n"));
+    }
+
+    #[test]
+    /// tests that `#if` sections are driven by the defines given to the interpreter
+    fn conditional_section_test() {
+        let source = "print 1;\n#if DEBUG\nprint 2;\n#end";
+        let mut output = SharedOutput::default();
+
+        let mut interpreter = Interpreter::new().with_output(Box::new(output.clone()));
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(output.take(), "1\n");
+
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_define("DEBUG".to_string(), Literal::Boolean(false));
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(output.take(), "1\n");
+
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_define("DEBUG".to_string(), Literal::Boolean(true));
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(output.take(), "1\n2\n");
+    }
+
+    #[test]
+    /// tests that scripts are read in through the module loader of the interpreter
+    fn module_loader_test() {
+        let loader = InMemoryLoader::new().with_module("main.lox", "print 1 + 1;");
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_module_loader(Box::new(loader));
+
+        interpreter.run_script("main.lox".to_string()).unwrap();
+        assert_eq!(output.take(), "2\n");
+        assert!(interpreter.run_script("missing.lox".to_string()).is_err());
+    }
+
+    #[test]
+    /// tests that modules run once however often they are imported, into the current scope or as a
+    /// namespace, and that circular imports are reported
+    fn import_test() {
+        let loader = InMemoryLoader::new()
+            .with_module(
+                "math.lox",
+                "print \"loading math\";\nconst pi = 3;\nfun square(x) { return x * x; }",
+            )
+            .with_module("a.lox", "import \"b.lox\";")
+            .with_module("b.lox", "print \"in b\";\nimport \"a.lox\";");
+        let source =
+            "import \"math.lox\";\nprint square(pi);\nimport math;\nprint math.square(2);\n\
+            { import \"math.lox\" as m; print m.pi; }\npi = 4;\nprint m;\nimport \"a.lox\";\n\
+            import \"missing.lox\";";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English)
+            .with_module_loader(Box::new(loader));
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(
+            output.take(),
+            "loading math\n9\n4\n3\n\
+            [line 6] Error : [E0028] Can not assign to pi, since it is a constant\n\
+            [line 7] Error : [E0007] Undefined variable 'm'\nin b\n\
+            [line 2] Error : [E0030] Circular import of a.lox: a.lox -> b.lox -> a.lox\n\
+            [line 9] Error : [E0029] Can not import missing.lox: No module found for missing.lox\n"
+        );
+
+        // a module which failed is not cached, so the next import runs it again
+        interpreter
+            .run_on_string("import \"b.lox\";".to_string())
+            .unwrap();
+        assert_eq!(
+            output.take(),
+            "in b\n[line 1] Error : [E0030] Circular import of b.lox: b.lox -> a.lox -> b.lox\n"
+        );
+    }
+
+    #[test]
+    /// tests that a module which exports names only gives those, to namespaces and to imports of
+    /// names alike, and that it can export names it imports itself
+    fn export_test() {
+        let loader = InMemoryLoader::new()
+            .with_module(
+                "math.lox",
+                "export const pi = 3;\nexport fun sqrt(x) { return x / 2; }\nfun helper() {}",
+            )
+            .with_module(
+                "geometry.lox",
+                "export import { pi } from \"math.lox\";\nexport fun area(r) { return pi * r * r; }",
+            )
+            .with_module("all.lox", "var a = 1;\nvar b = 2;");
+        let source = "import { sqrt, pi } from \"math.lox\";\nprint sqrt(pi * 2);\n\
+            import \"math.lox\" as math;\nprint math.helper;\nimport { helper } from \"math.lox\";\n\
+            import { area, pi } from \"geometry.lox\";\nprint area(1);\nimport { a } from \"all.lox\";\n\
+            print a;\nprint b;\nimport { c } from \"all.lox\";\npi = 4;";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English)
+            .with_module_loader(Box::new(loader));
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(
+            output.take(),
+            "3\n\
+            [line 4] Error : [E0011] Undefined property 'helper'\n\
+            [line 5] Error : [E0033] Can not import helper from math.lox, since the module does not export it\n\
+            3\n1\n\
+            [line 10] Error : [E0007] Undefined variable 'b'\n\
+            [line 11] Error : [E0033] Can not import c from all.lox, since the module does not export it\n\
+            [line 12] Error : [E0028] Can not assign to pi, since it is a constant\n"
+        );
+    }
+
+    #[test]
+    /// tests that imports are taken from the directory of the importing module, that two paths to
+    /// one module run it once and that a module importing the script being run is circular
+    fn import_paths_test() {
+        let loader = InMemoryLoader::new()
+            .with_module(
+                "main.lox",
+                "import \"sub/s.lox\";\nimport \"m.lox\";\nimport \"./m.lox\";",
+            )
+            .with_module("sub/s.lox", "import \"../m.lox\";\nprint \"in s\";")
+            .with_module("m.lox", "print \"loading m\";")
+            .with_module("a.lox", "print \"in a\";\nimport \"b.lox\";")
+            .with_module("b.lox", "import \"./a.lox\";");
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English)
+            .with_module_loader(Box::new(loader));
+        interpreter.run_script("main.lox".to_string()).unwrap();
+        assert_eq!(output.take(), "loading m\nin s\n");
+
+        interpreter.run_script("a.lox".to_string()).unwrap();
+        assert_eq!(
+            output.take(),
+            "in a\n[line 1] Error : [E0030] Circular import of a.lox: a.lox -> b.lox -> a.lox\n"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "host")]
+    /// tests that scripts on the file system import relative to themselves rather than to the
+    /// working directory
+    fn import_file_paths_test() {
+        let dir = std::env::temp_dir().join(format!("lox-import-paths-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("lib")).unwrap();
+        std::fs::write(
+            dir.join("main.lox"),
+            "import \"lib/m.lox\";\nimport \"./util.lox\";\nprint twice(2);",
+        )
+        .unwrap();
+        std::fs::write(dir.join("lib/m.lox"), "import \"../util.lox\";").unwrap();
+        std::fs::write(
+            dir.join("util.lox"),
+            "print \"loading util\";\nfun twice(n) { return 2 * n; }",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("lib/m_test.lox"),
+            "import \"../util.lox\";\nfun test_twice() { assertEquals(4, twice(2)); }",
+        )
+        .unwrap();
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new().with_output(Box::new(output.clone()));
+        let result = interpreter.run_script(dir.join("main.lox").to_string_lossy().into_owned());
+        let report = Interpreter::new()
+            .with_output(Box::new(std::io::sink()))
+            .test_script(dir.join("lib/m_test.lox").to_string_lossy().into_owned());
+        std::fs::remove_dir_all(&dir).unwrap();
+        result.unwrap();
+        assert_eq!(output.take(), "loading util\n4\n");
+        assert_eq!(report.unwrap().results[0].failure, None);
+    }
+
+    #[test]
+    #[cfg(feature = "host")]
+    /// tests that a script run again unchanged is interpreted from the cached syntax tree, with the
+    /// defines it declares
+    fn parse_cache_test() {
+        let dir = std::env::temp_dir().join(format!("lox-parse-cache-{}", std::process::id()));
+        let source =
+            "define GREETING \"hi\";\nfun f(n) { return n * 2; }\nprint GREETING;\nprint f(21);";
+        let run = |source: &str| {
+            let loader = InMemoryLoader::new().with_module("main.lox", source);
+            let mut output = SharedOutput::default();
+            let mut interpreter = Interpreter::new()
+                .with_output(Box::new(output.clone()))
+                .with_module_loader(Box::new(loader))
+                .with_parse_cache(ParseCache::new(&dir));
+            interpreter.run_script("main.lox".to_string()).unwrap();
+            (output.take(), interpreter.parse_cache().unwrap().stats())
+        };
+
+        let (output, stats) = run(source);
+        assert_eq!(output, "hi\n42\n");
+        assert_eq!((stats.hits, stats.misses, stats.writes), (0, 1, 1));
+        let (output, stats) = run(source);
+        assert_eq!(output, "hi\n42\n");
+        assert_eq!((stats.hits, stats.misses, stats.writes), (1, 0, 0));
+        // a changed script is parsed again
+        let (output, stats) = run("print 1;");
+        assert_eq!(output, "1\n");
+        assert_eq!(stats.hits, 0);
+
+        // lines of the REPL are never cached
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(std::io::sink()))
+            .with_parse_cache(ParseCache::new(&dir));
+        interpreter.repl("print 1;\n".as_bytes()).unwrap();
+        assert_eq!(
+            interpreter.parse_cache().unwrap().stats(),
+            Default::default()
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "host")]
+    /// tests that pasted lines are run together once the paste is ended
+    fn repl_paste_test() {
+        let input = "print 1;\n:paste\nprint\n2;\n:end\nprint 3;\n";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new().with_output(Box::new(output.clone()));
+        interpreter.repl(input.as_bytes()).unwrap();
+        assert_eq!(output.take(), ">> 1\n>> 2\n>> 3\n>> ");
+    }
+
+    #[test]
+    #[cfg(feature = "host")]
+    /// tests that `:doc` shows the signature and `///` doc comment of natives, functions and classes
+    fn repl_doc_test() {
+        let input = ":doc len\n:doc clock\n:paste\n/// greets someone\nfun greet(name) {}\n:end\n\
+                     class A { init(x) {} } /// an A\nclass B < A {} // not a doc comment\nvar n = 1;\n\
+                     :doc greet\n:doc A\n:doc B\n:doc n\n:doc missing\n";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new().with_output(Box::new(output.clone()));
+        interpreter.repl(input.as_bytes()).unwrap();
+        assert_eq!(
+            output.take(),
+            ">> len(value)\nthe number of elements of a list, entries of a map, characters of a \
+             string or bytes of\nbytes\n\
+             >> clock()\nthe seconds since the Unix epoch, with a fractional part\n\
+             >> >> >> >> >> greet(name)\ngreets someone\n\
+             >> class A(x)\nan A\n\
+             >> class B(x) < A\n\
+             >> n is not a function or class, it is 1\n\
+             >> missing is not defined\n>> "
+        );
+    }
+
+    #[test]
+    fn print_format_test() {
+        let source = "print 1234567.5;\nprint -1234;\nprint \"a\";\nprint nil;\nprint 1 == 1;";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new().with_output(Box::new(output.clone()));
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(output.take(), "1234567.5\n-1234\na\nnil\ntrue\n");
+
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_number_format(NumberFormat {
+                thousands_separator: Some('.'),
+                decimal_point: ',',
+                precision: Some(2),
+            });
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(output.take(), "1.234.567,50\n-1.234,00\na\nnil\ntrue\n");
+
+        let mut interpreter = Interpreter::new().with_output(Box::new(output.clone()));
+        interpreter
+            .run_on_string(
+                "setPrintPrecision(2);\nprint 1234567.5;\nprint str(1234567.5);\nprint [1000];\n\
+                setPrintPrecision(nil);\nprint 1234567.5;\nsetPrintPrecision(-1);"
+                    .to_string(),
+            )
+            .unwrap();
+        assert_eq!(
+            output.take(),
+            "1,234,567.50\n1234567.5\n[1000]\n1234567.5\n\
+            [line 7] Error : setPrintPrecision expects from 0 to 100 digits, got -1\n"
+        );
+    }
+
+    #[test]
+    /// tests that the intermediate representations are emitted with the options of the interpreter
+    fn emit_script_test() {
+        let loader = InMemoryLoader::new().with_module("main.lox", "#if DEBUG\nprint 1;\n#end");
+        let mut interpreter = Interpreter::new().with_module_loader(Box::new(loader));
+
+        let tokens = interpreter
+            .emit_script("main.lox".to_string(), Emit::Tokens, EmitFormat::Json)
+            .unwrap();
+        assert_eq!(tokens, "[{\"type\":\"Eof\",\"lexeme\":\"\",\"line\":0}]\n");
+
+        let mut interpreter = interpreter.with_define("DEBUG".to_string(), Literal::Boolean(true));
+        let ast = interpreter
+            .emit_script("main.lox".to_string(), Emit::Ast, EmitFormat::Json)
+            .unwrap();
+        assert_eq!(
+            ast,
+            "[{\"type\":\"Print\",\"expression\":{\"type\":\"Literal\",\"value\":1}}]\n"
+        );
+
+        assert!(interpreter
+            .emit_script("main.lox".to_string(), Emit::Bytecode, EmitFormat::Text)
+            .is_err());
+    }
+
+    #[test]
+    /// tests that scripts are fixed and that scripts with syntax errors are not
+    fn fix_script_test() {
+        let loader = InMemoryLoader::new()
+            .with_module("main.lox", "print 'a'\nprint 1;\n")
+            .with_module("broken.lox", "print (1;");
+        let mut interpreter = Interpreter::new().with_module_loader(Box::new(loader));
+
+        let report = interpreter.fix_script("main.lox".to_string()).unwrap();
+        assert_eq!(report.fixed, "print \"a\";\nprint 1;\n");
+        assert_eq!(
+            report.to_string(),
+            "[line 1] Fixed : [L0002] canonical Lox has no single quoted strings, write \"a\" with double quotes instead\n\
+            [line 1] Fixed : [L0005] missing ';' after ''a''\n\
+            main.lox: 2 fixes\n"
+        );
+        assert_eq!(report.diff(), "@@ line 1 @@\n-print 'a'\n+print \"a\";\n");
+
+        assert!(interpreter.fix_script("broken.lox".to_string()).is_err());
+    }
+
+    #[test]
+    /// tests that declared variables can be read back, including between runs like in the REPL
+    fn var_stmt_test() {
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new().with_output(Box::new(output.clone()));
+        interpreter
+            .run_on_string("var a = 1;\nvar b;\nprint a + 2;\nprint b;".to_string())
+            .unwrap();
+        assert_eq!(output.take(), "3\nnil\n");
+
+        interpreter
+            .run_on_string("var a = \"redeclared\";\nprint a;".to_string())
+            .unwrap();
+        assert_eq!(output.take(), "redeclared\n");
+
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English);
+        interpreter.run_on_string("print c;".to_string()).unwrap();
+        assert_eq!(
+            output.take(),
+            "[line 1] Error : [E0007] Undefined variable 'c'\n"
+        );
+        assert_eq!(interpreter.metrics().errors_raised, 1);
+    }
+
+    #[test]
+    /// tests that assignment changes a declared variable and evaluates to the assigned value
+    fn assignment_test() {
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English);
+        interpreter
+            .run_on_string(
+                "var a = 1;\nvar b;\na = 2;\nprint b = a + 1;\nprint a;\nprint b;".to_string(),
+            )
+            .unwrap();
+        assert_eq!(output.take(), "3\n2\n3\n");
+
+        interpreter.run_on_string("c = 1;".to_string()).unwrap();
+        assert_eq!(
+            output.take(),
+            "[line 1] Error : [E0007] Undefined variable 'c'\n"
+        );
+    }
+
+    #[test]
+    /// tests that compound assignments update variables and fields, and report errors at their line
+    fn compound_assignment_test() {
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English);
+        interpreter
+            .run_on_string(
+                "var a = 10;\na += 5;\na -= 3;\na *= 2;\nprint a /= 4;\n\
+                var s = \"lo\";\ns += \"x\";\nprint s;\n\
+                class P {}\nvar p = P();\np.x = 1;\np.x += 2;\nprint p.x;\n\
+                var n = nil;\nn\n+= 1;"
+                    .to_string(),
+            )
+            .unwrap();
+        assert_eq!(
+            output.take(),
+            "6\nlox\n3\n[line 16] Error : [E0002] Operands must be two numbers or two strings\n"
+        );
+    }
+
+    #[test]
+    /// tests that lists are shared values whose elements are read and set by a bounds-checked index
+    fn list_test() {
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English);
+        interpreter
+            .run_on_string(
+                "var xs = [1, \"a\", [true]];\nprint xs;\nprint xs[2][0];\n\
+                var ys = xs;\nys[0] = 5;\nxs[0] += 1;\nprint xs[0];\n\
+                push(xs, nil);\nprint len(ys);\nprint [] == [];\n\
+                print xs[4];\nprint xs[-1];\nprint xs[0.5];\nprint 1[0];\nxs[\"a\"] = 1;"
+                    .to_string(),
+            )
+            .unwrap();
+        assert_eq!(
+            output.take(),
+            "[1, \"a\", [true]]\ntrue\n6\n4\nfalse\n\
+            [line 11] Error : [E0017] Index 4 is out of bounds for length 4\n\
+            [line 12] Error : [E0017] Index -1 is out of bounds for length 4\n\
+            [line 13] Error : [E0016] An index must be a whole number, got 0.5\n\
+            [line 14] Error : [E0015] Only lists, maps, strings and bytes can be indexed\n\
+            [line 15] Error : [E0016] An index must be a whole number, got \"a\"\n"
+        );
+    }
+
+    #[test]
+    /// tests that decimal mode makes the number operators exact for numbers written in decimal
+    fn number_mode_test() {
+        let source = "print 0.1 + 0.2 == 0.3;\nprint 1.15 * 100;\nprint 0.3 / 0.1 - 3;";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new().with_output(Box::new(output.clone()));
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(
+            output.take(),
+            "false\n114.99999999999999\n-0.0000000000000004440892098500626\n"
+        );
+
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_number_mode(NumberMode::Decimal);
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(output.take(), "true\n115\n0\n");
+
+        // the results stay decimals, past the precision of a double, and work where numbers do
+        interpreter
+            .run_on_string(
+                "var total = 0;\nfor (var i = 0; i < 10; i = i + 1) total = total + 0.1;\n\
+                print total == 1;\nprint 10000000000000000 + 1;\nprint 1 / 3;\nprint -(0.1 + 0.2);\n\
+                var xs = [\"a\", \"b\"];\nprint xs[total];\nprint \"$\" + 0.1 * 3;\n\
+                var prices = {};\nprices[0.1 + 0.2] = \"x\";\nprint prices[0.3];\n\
+                print 1 / 0;\nprint len(xs) * 0.5;"
+                    .to_string(),
+            )
+            .unwrap();
+        assert_eq!(
+            output.take(),
+            "true\n10000000000000001\n0.3333333333333333333333333333333333\n-0.3\nb\n$0.3\nx\ninf\n1\n"
+        );
+    }
+
+    #[test]
+    /// tests that strings and lists are indexed and sliced by character and element, with bounds checks
+    fn slice_test() {
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English);
+        interpreter
+            .run_on_string(
+                "var s = \"héllo\";\nprint s[1] + s[4];\nprint s[1:3];\nprint s[:2] + s[3:];\n\
+                var xs = [1, 2, 3];\nvar ys = xs[1:];\nys[0] = 5;\nprint xs;\nprint ys;\nprint s[5:] == \"\";\n\
+                print s[5];\nprint s[2:6];\nprint s[3:1];\nprint nil[0:1];\ns[0] = \"j\";"
+                    .to_string(),
+            )
+            .unwrap();
+        assert_eq!(
+            output.take(),
+            "éo\nél\nhélo\n[1, 2, 3]\n[5, 3]\ntrue\n\
+            [line 11] Error : [E0017] Index 5 is out of bounds for length 5\n\
+            [line 12] Error : [E0017] Index 6 is out of bounds for length 5\n\
+            [line 13] Error : [E0023] The start 3 of a slice is after its end 1\n\
+            [line 14] Error : [E0022] Only lists, strings and bytes can be sliced\n\
+            [line 15] Error : [E0024] Strings can not be changed, build a new string instead\n"
+        );
+    }
+
+    #[test]
+    /// tests that strings are made of characters rather than bytes, which emoji and accents are
+    fn unicode_test() {
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(std::io::sink()))
+            .with_language(Language::English);
+        let result = interpreter
+            .eval(
+                "var crab = \"🦀 rust\";\nvar length = len(crab);\nvar first = crab[0];\n\
+                var rest = crab[2:];\nvar letters = chars(\"né🦀\");\n\
+                var joined = len(crab + \"é\") == len(crab) + len(\"é\");\n\
+                var ordered = \"é\" < \"🦀\";\nvar accent = len(\"e\u{301}\");\nlen(1);",
+            )
+            .unwrap();
+        let global = |name: &str| show(&result.globals[name]);
+        assert_eq!(global("length"), "6");
+        assert_eq!(global("first"), "\"🦀\"");
+        assert_eq!(global("rest"), "\"rust\"");
+        assert_eq!(global("letters"), "[\"n\", \"é\", \"🦀\"]");
+        assert_eq!(global("joined"), "true");
+        assert_eq!(global("ordered"), "true");
+        // an e followed by a combining accent is one grapheme cluster of two scalar values
+        let accent = if cfg!(feature = "graphemes") {
+            "1"
+        } else {
+            "2"
+        };
+        assert_eq!(global("accent"), accent);
+        assert_eq!(
+            result.errors,
+            vec!["[line 9] Error : len expects a list, a map, a string or bytes, got 1"]
+        );
+    }
+
+    #[test]
+    /// tests that bytes are indexed and sliced like strings, and converted to and from text
+    fn bytes_test() {
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(std::io::sink()))
+            .with_language(Language::English);
+        let result = interpreter
+            .eval(
+                "var gif = b\"GIF\\x00\\xff\";\nvar first = gif[0];\nvar tail = gif[3:];\n\
+                var built = bytes([71, 73, 70]) == gif[:3];\nvar hex = encode(gif, \"hex\");\n\
+                var base64 = decode(encode(gif, \"base64\"), \"base64\") == gif;\n\
+                var text = decode(encode(\"héllo\", \"utf8\"), \"utf8\");\nvar length = len(gif);\n\
+                gif[0] = 1;\nbytes([256]);\ndecode(gif, \"utf8\");\nencode(\"a\", \"hex\");\n\
+                encode(gif, \"latin1\");",
+            )
+            .unwrap();
+        let global = |name: &str| result.globals[name].to_string();
+        assert_eq!(global("first"), "71");
+        assert_eq!(global("tail"), "b\"\\x00\\xff\"");
+        assert_eq!(global("built"), "true");
+        assert_eq!(global("hex"), "47494600ff");
+        assert_eq!(global("base64"), "true");
+        assert_eq!(global("text"), "héllo");
+        assert_eq!(global("length"), "5");
+        assert_eq!(
+            result.errors,
+            vec![
+                "[line 9] Error : [E0025] Bytes can not be changed, build new bytes instead",
+                "[line 10] Error : bytes expects whole numbers from 0 to 255, got 256",
+                "[line 11] Error : decode failed: the bytes are not valid utf8 from byte 4",
+                "[line 12] Error : encode expects bytes to hex, got \"a\"",
+                "[line 13] Error : encode failed: Unknown encoding latin1, expected utf8, hex or base64",
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "host")]
+    /// tests that date times are made from seconds, moved by seconds and formatted
+    fn datetime_test() {
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(std::io::sink()))
+            .with_language(Language::English);
+        let result = interpreter
+            .eval(
+                "var start = datetime(1709211900);\nvar later = start + 3600 * 24;\n\
+                var day = format(later, \"%a %Y-%m-%d %H:%M\");\nvar between = later - start;\n\
+                var ordered = start < later and start == later - 86400;\nvar seconds = epoch(start);\n\
+                var recent = now() > start;\nstart - 10000000000000;\nformat(1, \"%Y\");\nformat(start, \"%Q\");",
+            )
+            .unwrap();
+        let global = |name: &str| result.globals[name].to_string();
+        assert_eq!(global("start"), "2024-02-29T13:05:00Z");
+        assert_eq!(global("day"), "Fri 2024-03-01 13:05");
+        assert_eq!(global("between"), "86400");
+        assert_eq!(global("ordered"), "true");
+        assert_eq!(global("seconds"), "1709211900");
+        assert_eq!(global("recent"), "true");
+        assert_eq!(
+            result.errors,
+            vec![
+                "[line 8] Error : [E0026] The date -9998290788100 seconds from 1970 is out of the range of dates",
+                "[line 9] Error : format expects a date time, got 1",
+                "[line 10] Error : format failed: %Q is not a directive, expected one of %Y %m %d %j %H %M %S %L %A %a %B %b %s %%",
+            ]
+        );
+    }
+
+    #[test]
+    /// tests that eprint writes to the error output, in order with the output when both share a
+    /// terminal
+    fn eprint_test() {
+        let mut output = SharedOutput::default();
+        let mut errors = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_error_output(Box::new(errors.clone()));
+        interpreter
+            .run_on_string("print 1;\neprint(\"oops\");\nflush();\nprint 2;".to_string())
+            .unwrap();
+        assert_eq!(output.take(), "1\n2\n");
+        assert_eq!(errors.take(), "oops\n");
+
+        let mut terminal = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(terminal.clone()))
+            .with_error_output(Box::new(terminal.clone()));
+        interpreter
+            .run_on_string("print 1;\neprint(2);\nprint 3;".to_string())
+            .unwrap();
+        assert_eq!(terminal.take(), "1\n2\n3\n");
+    }
+
+    #[test]
+    /// tests that readLine reads the input a line at a time without line endings, and nil at its end
+    fn read_line_test() {
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_input(Box::new("ada\r\n\nlast".as_bytes()));
+        interpreter
+            .run_on_string(
+                "var line = readLine();\nwhile (line != nil) {\n  print \"[\" + line + \"]\";\n  \
+                line = readLine();\n}\nprint readLine();"
+                    .to_string(),
+            )
+            .unwrap();
+        assert_eq!(output.take(), "[ada]\n[]\n[last]\nnil\n");
+    }
+
+    #[test]
+    /// tests that maps are shared values read and set by key, which are equal when their entries are
+    fn map_test() {
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English);
+        interpreter
+            .run_on_string(
+                "var m = {\"a\": 1, 2: [true], nil: {},};\nprint m;\n\
+                m[\"a\"] += 1;\nvar n = m;\nn[nil][\"k\"] = 3;\nprint m[\"a\"] + m[nil][\"k\"];\n\
+                print len(m);\nprint keys({\"b\": 1, \"a\": 2});\nprint has(m, 2) and !has(m, []);\n\
+                print {\"x\": [1]} == {\"x\": [1]};\nprint {\"x\": 1} == {\"x\": 1};\n\
+                print m[\"b\"];\nprint {[]: 1};"
+                    .to_string(),
+            )
+            .unwrap();
+        assert_eq!(
+            output.take(),
+            "{nil: {}, 2: [true], \"a\": 1}\n5\n3\n[\"a\", \"b\"]\ntrue\nfalse\ntrue\n\
+            [line 12] Error : [E0021] Undefined key \"b\"\n\
+            [line 13] Error : [E0020] Only numbers, strings, booleans and nil can be map keys, got []\n"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "bigint")]
+    /// tests that big integers do arithmetic past the precision of numbers
+    fn bigint_test() {
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English);
+        interpreter
+            .run_on_string(
+                "fun factorial(n) { if (n < 2) return 1n; return n * factorial(n - 1); }\n\
+                print factorial(30);\nprint -7n / 2;\nprint 1n == 1;\nprint 2n > 1.5 or 3n != 3;\n\
+                print \"n = \" + 10n;\nprint 1n + 0.5;\nprint 1n / 0;"
+                    .to_string(),
+            )
+            .unwrap();
+        assert_eq!(
+            output.take(),
+            "265252859812191058636308480000000\n-3\ntrue\n\
+            [line 5] Error : [E0018] Big integers can only be combined with whole numbers, got 1.5\n\
+            n = 10\n\
+            [line 7] Error : [E0018] Big integers can only be combined with whole numbers, got 0.5\n\
+            [line 8] Error : [E0019] Division of a big integer by zero\n"
+        );
+    }
+
+    #[test]
+    /// tests that if statements follow the truthiness rules of Lox, where only false and nil are falsy
+    fn if_stmt_test() {
+        let source = "if (0) print \"0\"; else print \"no\";\n\
+            if (\"\") print \"empty\";\n\
+            if (nil) print \"nil\"; else print \"not nil\";\n\
+            if (false) print \"false\";\n\
+            if (1 > 2) print \"a\"; else if (2 > 1) print \"b\"; else print \"c\";";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new().with_output(Box::new(output.clone()));
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(output.take(), "0\nempty\nnot nil\nb\n");
+    }
+
+    #[test]
+    fn while_stmt_test() {
+        let source =
+            "var i = 0;\nvar sum = 0;\nwhile (i < 5) { sum = sum + i; i = i + 1; }\nprint sum;\n\
+            while (false) print \"never\";";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new().with_output(Box::new(output.clone()));
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(output.take(), "10\n");
+    }
+
+    #[test]
+    /// tests that logical operators return one of their operands and skip the right one when they can
+    fn logical_expr_test() {
+        let source = "print nil or \"default\";\nprint 1 and 2;\nprint nil and 1;\nprint false or nil;\n\
+            var a = 0;\ntrue or (a = 1);\nfalse and (a = 2);\nprint a;\nprint false or true and a == 0;";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new().with_output(Box::new(output.clone()));
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(output.take(), "default\n2\nnil\nnil\n0\ntrue\n");
+    }
+
+    #[test]
+    /// tests that long chains of operations are evaluated without running out of stack
+    fn long_chain_test() {
+        let sum: String = (1..200).map(|i| format!(" + {}", i)).collect();
+        let concatenation = " + \"ab\"".repeat(139);
+        let or = " or false".repeat(2000);
+        let source = format!(
+            "print 0{};\nprint \"\"{};\nprint false{} or \"done\";",
+            sum, concatenation, or
+        );
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new().with_output(Box::new(output.clone()));
+        interpreter.run_on_string(source).unwrap();
+        assert_eq!(
+            output.take(),
+            format!("19900\n{}\ndone\n", "ab".repeat(139))
+        );
+    }
+
+    #[test]
+    /// tests that for loops run through their desugared while loop and scope their variable to it
+    fn for_stmt_test() {
+        let source = "var a = 0;\nvar b = 1;\nfor (var i = 0; i < 5; i = i + 1) { print a; var t = a; a = b; b = t + b; }\n\
+            print i;";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English);
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(
+            output.take(),
+            "0\n1\n1\n2\n3\n[line 4] Error : [E0007] Undefined variable 'i'\n"
+        );
+    }
+
+    #[test]
+    /// tests that break leaves only the innermost loop, along with the blocks it is nested in
+    fn break_stmt_test() {
+        let source = "var a = \"outer\";\nfor (var i = 0; i < 3; i = i + 1) {\n\
+            var j = 0;\nwhile (true) { var a = \"inner\"; if (j == i) break; j = j + 1; }\n\
+            print j;\n}\nprint a;\nwhile (true) { break; print \"unreachable\"; }";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new().with_output(Box::new(output.clone()));
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(output.take(), "0\n1\n2\nouter\n");
+    }
+
+    #[test]
+    /// tests that continue skips the rest of the body but still runs the increment of a for loop
+    fn continue_stmt_test() {
+        let source =
+            "for (var i = 0; i < 5; i = i + 1) { if (i == 1 or i == 3) continue; print i; }\n\
+            var j = 0;\nwhile (j < 3) { j = j + 1; if (j == 2) { continue; } print j; }";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new().with_output(Box::new(output.clone()));
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(output.take(), "0\n2\n4\n1\n3\n");
+    }
+
+    #[test]
+    /// tests that the body of a do loop runs before its condition is checked, and that continue
+    /// moves on to the condition
+    fn do_while_stmt_test() {
+        let source = "do print \"once\"; while (false);\n\
+            var k = 0;\ndo { k = k + 1; if (k == 2) continue; print k; } while (k < 3);\n\
+            do { break; print \"unreachable\"; } while (true);\nprint \"after\";";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new().with_output(Box::new(output.clone()));
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(output.take(), "once\n1\n3\nafter\n");
+    }
+
+    #[test]
+    /// tests that constants can not be assigned to or declared again, unless a variable of the same
+    /// name shadows them in a nested scope
+    fn const_stmt_test() {
+        let source = "const a = 1;\na = 2;\nprint a;\n\
+            fun f() { a = 3; }\nf();\n{ var a = 4; a = 5; print a; }\nconst a = 6;\nprint a;\n\
+            var a = 7;\na = 8;\nprint a;";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English);
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(
+            output.take(),
+            "[line 2] Error : [E0028] Can not assign to a, since it is a constant\n1\n\
+            [line 4] Error : [E0028] Can not assign to a, since it is a constant\n5\n\
+            [S0053] Can not declare a again, since it is a constant declared in the same scope in line 7\n1\n\
+            [S0053] Can not declare a again, since it is a constant declared in the same scope in line 9\n\
+            [line 10] Error : [E0028] Can not assign to a, since it is a constant\n1\n"
+        );
+
+        // a later run declaring the constant again is refused too
+        interpreter
+            .run_on_string("const c = 1;".to_string())
+            .unwrap();
+        interpreter
+            .run_on_string("var c = 2;\nc = 3;\nprint c;".to_string())
+            .unwrap();
+        assert_eq!(
+            output.take(),
+            "[S0053] Can not declare c again, since it is a constant declared in the same scope in line 1\n\
+            [line 2] Error : [E0028] Can not assign to c, since it is a constant\n1\n"
+        );
+    }
+
+    #[test]
+    /// tests that a rest parameter collects the arguments after the other parameters, which still
+    /// need an argument each
+    fn varargs_test() {
+        let source = "fun join(separator, ...parts) {\n\
+            var out = \"\";\nfor (var i = 0; i < len(parts); i = i + 1) {\n\
+            if (i > 0) out = out + separator;\nout = out + parts[i];\n}\nreturn out;\n}\n\
+            print join(\", \");\nprint join(\", \", \"a\", \"b\", \"c\");\n\
+            class Bag { init(...items) { this.items = items; } }\nprint Bag(1, 2).items;\njoin();";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English);
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(
+            output.take(),
+            "\na, b, c\n[1, 2]\n[line 13] Error : [E0032] Expected at least 1 arguments but got 0\n"
+        );
+        assert_eq!(
+            interpreter.doc("join").unwrap(),
+            "join(separator, ...parts)"
+        );
+    }
+
+    #[test]
+    /// tests that calls in tail position take no stack, so recursing through them goes deeper than
+    /// the calls can be nested, while a try statement around one still catches what it throws
+    fn tail_call_test() {
+        let source = "fun count(n, total) { if (n == 0) return total; return count(n - 1, total + 1); }\n\
+            print count(100000, 0);\n\
+            fun isEven(n) { if (n == 0) return true; return isOdd(n - 1); }\n\
+            fun isOdd(n) { if (n == 0) return false; return isEven(n - 1); }\n\
+            print isEven(10001);\n\
+            fun fib(n, a, b) { if (n == 0) return a; return fib(n - 1, b, a + b); }\nprint fib(50, 0, 1);\n\
+            fun fail(n) { if (n == 0) throw \"done\"; return fail(n - 1); }\n\
+            fun guarded() { try { return fail(3); } catch (e) { return \"caught \" + e; } }\n\
+            print guarded();\nfun pair(a, b) { return a + b; }\nfun wrong() { return pair(1); }\nwrong();";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English);
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(
+            output.take(),
+            "100000\nfalse\n12586269025\ncaught done\n\
+            [line 12] Error : [E0009] Expected 2 arguments but got 1\n"
+        );
+        assert_eq!(interpreter.stack_trace(), StackTrace::default());
+    }
+
+    #[test]
+    /// tests that catch gets the value thrown or the runtime error raised anywhere inside of its try
+    /// block, while errors of the interpreter and the other statements unwinding through it carry on
+    fn try_stmt_test() {
+        let source = "fun check(n) { if (n < 0) throw \"negative \" + n; return n; }\n\
+            try { print check(1); print check(-2); print \"skipped\"; } catch (e) { print e; }\n\
+            var e = \"outer\";\ntry { throw [1, 2]; } catch (e) { print len(e); }\nprint e;\n\
+            for (var i = 0; i < 3; i = i + 1) { try { if (i == 1) break; } catch (err) {} print i; }\n\
+            try { try { throw 1; } catch (e) { throw e + 1; } } catch (e) { print e; }\n\
+            try { print 1 + nil; } catch (e) { print e; }\nthrow nil;\n\
+            try { [][5]; } catch (e) { print e[\"message\"]; }\ntry { 1 - \"a\"; } catch (e) { print \"caught\"; }\n\
+            try { assert(false, \"m\"); } catch (e) { print e; }\n\
+            try { print x; } catch (e) { print e[\"code\"] + \" \" + e[\"message\"]; }";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English);
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(
+            output.take(),
+            "1\nnegative -2\n2\nouter\n0\n2\n\
+            {\"code\": \"E0003\", \"line\": 8, \"message\": \"the left side number 1 operand is being added to non left number\"}\n\
+            [line 9] Error : [E0027] Uncaught exception nil\n\
+            Index 5 is out of bounds for length 0\ncaught\n\
+            {\"code\": nil, \"line\": 12, \"message\": \"assert failed: m\"}\n\
+            E0007 Undefined variable 'x'\n"
+        );
+
+        // a cancellation is not an error of the program, so it is never caught
+        let token = interpreter.cancellation_token();
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            token.cancel();
+        });
+        let source = "try { while (true) {} } catch (e) { print \"caught\"; }";
+        interpreter.run_on_string(source.to_string()).unwrap_err();
+        canceller.join().unwrap();
+        assert_eq!(output.take(), "");
+    }
+
+    #[test]
+    /// tests recursion, closures and return values of functions
+    fn function_test() {
+        let source = "fun fib(n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); }\n\
+            print fib(10);\n\
+            fun make_counter() { var count = 0; fun counter() { count = count + 1; return count; } return counter; }\n\
+            var counter = make_counter();\ncounter();\nprint counter();\n\
+            fun nothing() { return; }\nprint nothing();\nfun empty() {}\nprint empty();\n\
+            fun early(n) { while (true) { if (n > 2) return n; n = n + 1; } }\nprint early(0);\n\
+            print fib;\nprint fib == fib;\nprint make_counter() == make_counter();";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new().with_output(Box::new(output.clone()));
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(output.take(), "55\n2\nnil\nnil\n3\n<fn fib>\ntrue\nfalse\n");
+        // fib(10) makes 177 calls, plus the other 8 calls
+        assert_eq!(interpreter.metrics().calls_made, 185);
+    }
+
+    #[test]
+    /// tests the runtime errors of calls, including running out of stack in an endless recursion
+    fn call_error_test() {
+        let source = "var a = 1;\na();\nfun f(x) { return x; }\nf(1, 2);\n\
+            fun forever(n) { forever(n + 1); }\nforever(0);\nprint \"still running\";";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English);
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(
+            output.take(),
+            "[line 2] Error : [E0008] Can only call functions and classes\n\
+            [line 4] Error : [E0009] Expected 1 arguments but got 2\n\
+            [line 5] Error : [E0010] Stack overflow\n\
+            still running\n"
+        );
+    }
+
+    #[test]
+    /// tests that the source of the lexer benchmark runs now that functions are supported
+    fn benchmark_source_test() {
+        let source = "fun main() {\n    var a = 1;\n    var b = 2;\n\n    var c = a + b;\n    \
+            print(\"Hello World!\")\n}\nmain()";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_automatic_semicolons(true);
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(output.take(), "Hello World!\n");
+    }
+
+    #[test]
+    /// tests classes, their instances and methods, including initializers and `this`
+    fn class_test() {
+        let source = "class Counter {\n\
+                init(start) { this.count = start; }\n\
+                increment() { this.count = this.count + 1; return this; }\n\
+                get() { return this.count; }\n\
+            }\n\
+            var counter = Counter(10);\n\
+            print counter.increment().increment().get();\n\
+            var get = counter.get;\ncounter.count = 0;\nprint get();\n\
+            print counter;\nprint Counter;\nprint counter.init(5) == counter;\nprint counter.count;\n\
+            class Empty {}\nvar empty = Empty();\nempty.field = \"set\";\nprint empty.field;\n\
+            print Empty() == Empty();\n\
+            class Early { init() { this.a = 1; return; this.a = 2; } }\nprint Early().a;";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new().with_output(Box::new(output.clone()));
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(
+            output.take(),
+            "12\n0\nCounter instance\nCounter\ntrue\n5\nset\nfalse\n1\n"
+        );
+    }
+
+    #[test]
+    /// tests that subclasses inherit methods, and that super calls the superclass of the class the
+    /// method is declared in
+    fn inheritance_test() {
+        let source = "class A { method() { return \"A method\"; } name() { return \"A\"; }\n\
+                describe() { return \"I am \" + this.name(); } }\n\
+            class B < A { method() { return \"B method\"; } test() { return super.method(); }\n\
+                name() { return \"B\"; } }\n\
+            class C < B {}\nprint C().test();\nprint C().method();\nprint B().describe();\n\
+            class Point { init(x) { this.x = x; } }\n\
+            class Point2 < Point { init(x, y) { super.init(x); this.y = y; } }\n\
+            var point = Point2(1, 2);\nprint point.x + point.y;\nclass Same < Point {}\nprint Same(3).x;\n\
+            var NotAClass = \"A\";\nclass D < NotAClass {}\n\
+            class E < A { missing() { return super.nope(); } }\nE().missing();";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English);
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(
+            output.take(),
+            "A method\nB method\nI am B\n3\n3\n\
+            [line 16] Error : [E0014] Superclass must be a class\n\
+            [line 17] Error : [E0011] Undefined property 'nope'\n"
+        );
+    }
+
+    #[test]
+    /// tests that class methods are called on the class, are inherited, and are not methods of its
+    /// instances
+    fn class_method_test() {
+        let source = "class Math {\n\
+                class square(n) { return n * n; }\n\
+                class cube(n) { return n * Math.square(n); }\n\
+                half(n) { return n / 2; }\n\
+                class init() { return \"not an initializer\"; }\n\
+            }\n\
+            print Math.square(3);\nprint Math.cube(2);\nclass Geometry < Math {}\nprint Geometry.square(4);\n\
+            print Math().half(3);\nprint Math.init();\nprint Math().square;\nprint Math.half;";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English)
+            .with_pedantic(true);
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(
+            output.take(),
+            "[line 2] Warning : [L0003] canonical Lox has no class methods, declare square as a function outside of the class instead\n\
+            [line 3] Warning : [L0003] canonical Lox has no class methods, declare cube as a function outside of the class instead\n\
+            [line 5] Warning : [L0003] canonical Lox has no class methods, declare init as a function outside of the class instead\n\
+            9\n8\n16\n1.5\nnot an initializer\n\
+            [line 13] Error : [E0011] Undefined property 'square'\n\
+            [line 14] Error : [E0011] Undefined property 'half'\n"
+        );
+    }
+
+    #[test]
+    /// tests the runtime errors of properties and of instantiating classes
+    fn property_error_test() {
+        let source = "class A { init(x) {} }\nA();\nvar a = A(1);\nprint a.missing;\n\
+            var n = 1;\nprint n.field;\nn.field = 2;";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English);
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(
+            output.take(),
+            "[line 2] Error : [E0009] Expected 1 arguments but got 0\n\
+            [line 4] Error : [E0011] Undefined property 'missing'\n\
+            [line 6] Error : [E0012] Only instances have properties\n\
+            [line 7] Error : [E0013] Only instances have fields\n"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "host")]
+    /// tests that the native clock can time code written in Lox, and is checked like other callables
+    fn clock_test() {
+        let source = "var start = clock();\nvar sum = 0;\nfor (var i = 0; i < 100; i = i + 1) sum = sum + i;\n\
+            var elapsed = clock() - start;\nprint elapsed >= 0 and elapsed < 60;\nprint clock() > 1000000000;\n\
+            print clock;\nclock(1);";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English);
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(
+            output.take(),
+            "true\ntrue\n<native fn clock>\n[line 8] Error : [E0009] Expected 0 arguments but got 1\n"
+        );
+        assert_eq!(interpreter.metrics().calls_made, 3);
+    }
+
+    #[test]
+    /// tests that scripts log through the host's logger, and that the level is checked
+    fn log_test() {
+        log::set_logger(&LOGGER).unwrap();
+        log::set_max_level(log::LevelFilter::Debug);
+
+        let source = "log(\"info\", \"started\");\nfor (var i = 0; i < 2; i = i + 1) log(\"debug\", i);\n\
+            log(\"warn\", nil);\nlog(\"error\", \"failed\");\nprint log(\"info\", true);\nlog(\"fatal\", \"bye\");";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new().with_output(Box::new(output.clone()));
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(
+            output.take(),
+            "nil\n[line 6] Error : Log level must be \"debug\", \"info\", \"warn\" or \"error\", got fatal\n"
+        );
+        assert_eq!(
+            *LOGGER.records.lock().unwrap(),
+            vec![
+                (log::Level::Info, "started".to_string()),
+                (log::Level::Debug, "0".to_string()),
+                (log::Level::Debug, "1".to_string()),
+                (log::Level::Warn, "nil".to_string()),
+                (log::Level::Error, "failed".to_string()),
+                (log::Level::Info, "true".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    /// tests that assert fails with its message and the line of the call when the condition is falsy
+    fn assert_test() {
+        let source = "assert(1 < 2, \"ordered\");\nprint \"passed\";\n\
+            assert(nil, \"nil is falsy\");\nprint \"after\";";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English);
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(
+            output.take(),
+            "passed\n[line 3] Error : assert failed: nil is falsy\nafter\n"
+        );
+    }
+
+    #[test]
+    /// tests that assertEquals compares instances field by field, and reports the fields differing
+    fn assert_equals_test() {
+        let source = "class Point { init(x, y) { this.x = x; this.y = y; } }\n\
+            class Line { init(from, to) { this.from = from; this.to = to; this.line = this; } }\n\
+            assertEquals(Line(Point(0, 0), Point(1, 2)), Line(Point(0, 0), Point(1, 2)));\n\
+            assertEquals(\"a\", \"a\");\nprint \"passed\";\n\
+            var actual = Line(Point(0, 1), Point(1, \"2\"));\nactual.from.z = nil;\n\
+            assertEquals(Line(Point(0, 0), Point(1, 2)), actual);\n\
+            assertEquals(Point(0, 0), Line(nil, nil));\nassertEquals(1, 2);";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English);
+        for line in source.lines() {
+            interpreter.run_on_string(line.to_string()).unwrap();
+        }
+        assert_eq!(
+            output.take(),
+            "passed\n\
+            [line 1] Error : assertEquals failed, expected Line instance but got Line instance, which differ in 3 fields:\n  \
+            .from.y: expected 0 but got 1\n  .from.z: expected <missing> but got nil\n  \
+            .to.y: expected 2 but got \"2\"\n\
+            [line 1] Error : assertEquals failed, expected Point instance but got Line instance\n\
+            [line 1] Error : assertEquals failed, expected 1 but got 2\n"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "host")]
+    /// tests that lox test runs the top-level code and then every test function, and that the
+    /// assert natives fail the tests they are called in
+    fn test_script_test() {
+        let source = "var total = 0;\nfun add(a, b) { return a + b; }\n\
+            fun test_add() { assertEquals(3, add(1, 2)); assertTrue(add(1, 1) == 2); }\n\
+            fun test_broken() { assertTrue(add(1, 1) == 3); }\nfun helper() { assertTrue(false); }\n\
+            fun test_throws() { var message = assertThrows(fun_that_fails); print message; }\n\
+            fun fun_that_fails() { return 1 + nil; }\n\
+            fun test_no_throw() { assertThrows(helper_ok); }\nfun helper_ok() { return \"ok\"; }\n\
+            fun test_not_a_function() { assertThrows(1); }\nprint \"loaded\";";
+        let loader = InMemoryLoader::new().with_module("math_test.lox", source);
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English)
+            .with_module_loader(Box::new(loader));
+
+        let report = interpreter
+            .test_script("math_test.lox".to_string())
+            .unwrap();
+        assert_eq!(
+            output.take(),
+            "loaded\n[line 7] Error : [E0003] the left side number 1 operand is being added to non left number\n"
+        );
+        assert_eq!((report.passed(), report.failed()), (2, 3));
+        assert_eq!(
+            report.to_string(),
+            "test_add ... ok\ntest_broken ... FAILED\n    [line 4] Error : assertTrue failed, got false\n\
+            test_throws ... ok\ntest_no_throw ... FAILED\n    \
+            [line 8] Error : assertThrows failed, the function returned \"ok\" without an error\n\
+            test_not_a_function ... FAILED\n    \
+            [line 10] Error : assertThrows expects a function taking no arguments, got 1\n\
+            math_test.lox: 2 passed, 3 failed\n"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "host")]
+    /// tests that bench calls the function to warm up and then once per iteration, recording the
+    /// result
+    fn bench_test() {
+        let source = "var calls = 0;\nfun count() { calls = calls + 1; }\n\
+            var mean = bench(\"count\", count, 20);\nprint calls;\nprint mean >= 0;\n\
+            bench(1, count, 1);\nbench(\"count\", count, 0);\nbench(\"count\", count, 1.5);\n\
+            bench(\"count\", clock, 1);\nprint calls;";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new().with_output(Box::new(output.clone()));
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(
+            output.take(),
+            "22\ntrue\n[line 6] Error : bench expects a name, got 1\n\
+            [line 7] Error : bench expects a positive whole number of iterations, got 0\n\
+            [line 8] Error : bench expects a positive whole number of iterations, got 1.5\n22\n"
+        );
+        let results = &interpreter.benchmarks().results;
+        assert_eq!(results.len(), 2);
+        assert_eq!(
+            (
+                results[0].name.as_str(),
+                results[0].iterations,
+                results[0].warmup,
+                results[0].line
+            ),
+            ("count", 20, 2, 3)
+        );
+        assert_eq!((results[1].iterations, results[1].warmup), (1, 1));
+    }
+
+    #[test]
+    /// tests that the global environment and the last value can be inspected in between runs
+    fn state_inspection_test() {
+        let mut interpreter = Interpreter::new().with_output(Box::new(SharedOutput::default()));
+        assert_eq!(interpreter.last_value(), None);
+        let source =
+            "var a = 1;\n{ var b = 2; }\nfun f() { return a; }\na + 41;\n{ var c = 3; c + nil; }";
+        interpreter.run_on_string(source.to_string()).unwrap();
+
+        assert_eq!(interpreter.last_value(), Some(&Literal::Number(42.0)));
+        let globals = interpreter.globals();
+        assert!(globals.enclosing().is_none());
+        assert_eq!(globals.get("a"), Some(Literal::Number(1.0)));
+        let mut names: Vec<_> = globals
+            .iter()
+            .map(|(name, _)| name)
+            .filter(|name| natives::NATIVES.iter().all(|native| native.name != *name))
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["a", "f"]);
+        drop(globals);
+
+        interpreter.run_on_string("print f();".to_string()).unwrap();
+        assert_eq!(interpreter.last_value(), Some(&Literal::Number(1.0)));
+    }
+
+    #[test]
+    /// tests that eval returns what got done when some statements fail, and that the failures stop
+    /// the rest of the source when the interpreter is not set to continue on errors
+    fn partial_result_test() {
+        let source = "var a = 1;\nprint a + nil;\nvar b = a + 1;\nb = b - \"x\";\nvar c = 3;";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English);
+        let result = interpreter.eval(source).unwrap();
+        assert_eq!(result.statements_executed, 5);
+        assert_eq!(result.errors.len(), 2);
+        assert!(result.errors[0].starts_with("[line 2] Error"));
+        assert!(result.errors[1].starts_with("[line 4] Error"));
+        assert_eq!(result.globals.get("b"), Some(&Literal::Number(2.0)));
+        assert_eq!(result.globals.get("c"), Some(&Literal::Number(3.0)));
+        assert_eq!(output.take(), "");
+
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_continue_on_error(false);
+        let result = interpreter.eval(source).unwrap();
+        assert_eq!(result.statements_executed, 2);
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.globals.get("a"), Some(&Literal::Number(1.0)));
+        assert_eq!(result.globals.get("b"), None);
+
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(output.take().lines().count(), 1);
+    }
+
+    #[test]
+    /// tests that the random natives give the same numbers for the same seed
+    fn random_test() {
+        let source =
+            "var a = random();\nvar b = randomInt(1, 6);\nseedRandom(3);\nvar c = random();\n\
+            randomInt(2, 1);\nrandomInt(0.5, 1);\nseedRandom(nil);";
+        let run = |interpreter: Interpreter| {
+            let mut interpreter = interpreter.with_output(Box::new(std::io::sink()));
+            let result = interpreter.eval(source).unwrap();
+            let globals: Vec<_> = ["a", "b", "c"]
+                .iter()
+                .map(|name| result.globals[*name].clone())
+                .collect();
+            (globals, result.errors)
+        };
+
+        let (first, errors) = run(Interpreter::new().with_seed(42));
+        let (second, _) = run(Interpreter::new().with_seed(42));
+        assert_eq!(first, second);
+        assert!(matches!(first[0], Literal::Number(a) if (0.0..1.0).contains(&a)));
+        assert!(
+            matches!(first[1], Literal::Number(b) if (1.0..=6.0).contains(&b) && b.fract() == 0.0)
+        );
+        assert_eq!(
+            errors,
+            vec![
+                "[line 5] Error : randomInt expects lo to be at most hi, got 2 and 1",
+                "[line 6] Error : randomInt expects a whole number, got 0.5",
+                "[line 7] Error : seedRandom expects a whole number, got nil",
+            ]
+        );
+
+        // seedRandom restarts the numbers whatever the interpreter was seeded with
+        let (other, _) = run(Interpreter::new().with_seed(7));
+        assert_ne!(other[0], first[0]);
+        assert_eq!(other[2], first[2]);
+
+        // dry runs are reproducible without a seed
+        let (dry, _) = run(Interpreter::new().with_dry_run(DryRun::new()));
+        let (again, _) = run(Interpreter::new().with_dry_run(DryRun::new()));
+        assert_eq!(dry, again);
+    }
+
+    #[test]
+    /// tests that a dry run records the calls of stubs and returns their canned values
+    fn dry_run_test() {
+        let source = "fun load(path) { return readFile(path); }\n\
+            print load(\"config.txt\");\nfetch(\"https://example.com\", 3);\nprint len;";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English)
+            .with_dry_run(
+                DryRun::new().with_value("readFile", Literal::String("debug = true".to_string())),
+            )
+            .with_plugin(&OutsidePlugin)
+            .unwrap();
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(output.take(), "debug = true\n<native fn len>\n");
+        assert_eq!(
+            interpreter.effects().to_string(),
+            "effects: 2\n  \
+            [line 1] readFile(\"config.txt\") -> \"debug = true\" (file I/O)\n  \
+            [line 3] fetch(\"https://example.com\", 3) -> nil (network)\n"
+        );
+
+        // the stubs are there for sources run in environments of their own as well
+        let report = interpreter
+            .eval_many(
+                [("a.lox", "fetch(\"url\", 1);")],
+                BatchEnvironment::PerSource,
+            )
+            .unwrap();
+        assert!(report.is_ok());
+        assert_eq!(interpreter.effects().effects.len(), 3);
+
+        // only the natives the interpreter defines are stubbed
+        interpreter
+            .run_on_string("system(\"ls\");".to_string())
+            .unwrap();
+        assert!(output.take().contains("Undefined variable 'system'"));
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_plugin(&OutsidePlugin)
+            .unwrap()
+            .with_dry_run(DryRun::new());
+        interpreter
+            .run_on_string("print readFile(\"a\");".to_string())
+            .unwrap();
+        assert_eq!(output.take(), "nil\n");
+
+        // without a dry run the natives run
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_plugin(&OutsidePlugin)
+            .unwrap();
+        interpreter
+            .run_on_string("readFile(\"a\");".to_string())
+            .unwrap();
+        assert!(output.take().contains("readFile read a file"));
+        assert!(interpreter.effects().effects.is_empty());
+    }
+
+    #[test]
+    /// tests that an endless loop can still be cancelled since every iteration checks for it
+    fn while_cancellation_test() {
+        let mut interpreter = Interpreter::new().with_output(Box::new(std::io::sink()));
+        let token = interpreter.cancellation_token();
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            token.cancel();
+        });
+        let err = interpreter
+            .run_on_string("while (true) {}".to_string())
+            .unwrap_err();
+        canceller.join().unwrap();
+        assert_eq!(
+            err.downcast_ref::<RuntimeError>(),
+            Some(&RuntimeError::Cancelled)
+        );
+    }
+
+    #[test]
+    /// tests that going over the heap limit is an error which can be caught, while values which are
+    /// no longer held do not count towards it
+    fn heap_limit_test() {
+        let source = "var xs = [];\ntry { while (true) push(xs, 1); } catch (e) { print e[\"code\"]; }\n\
+            xs = nil;\nfor (var i = 0; i < 1000; i = i + 1) { var t = [i, i, i, i]; }\nprint \"freed\";\n\
+            var s = \"x\";\nwhile (true) s = s + s;";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English)
+            .with_heap_limit(10_000);
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(
+            output.take(),
+            "E0031\nfreed\n\
+            [line 7] Error : [E0031] Out of memory, the values held take more than the limit of \
+            10000 bytes\n"
+        );
+
+        // shared values are measured once
+        let before = interpreter.measure_heap();
+        interpreter
+            .run_on_string("var a = [1, 2, 3];\nvar b = a;".to_string())
+            .unwrap();
+        let measured = interpreter.measure_heap() - before;
+        assert!((3 * heap::SLOT..4 * heap::SLOT).contains(&(measured - 2 * heap::SLOT - 2)));
+    }
+
+    #[test]
+    /// tests that errors raised inside of calls are reported with the calls when asked to, while
+    /// thrown values which were caught leave no trace behind
+    fn error_trace_test() {
+        let source = "fun f(n) {\n  f(n + 1);\n}\nf(0);\n\
+            fun g() { throw 1; }\ntry { g(); } catch (e) {}\nprint 1 / nil;";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English)
+            .with_trace_options(TraceOptions {
+                on_errors: true,
+                max_frames: Some(3),
+                ..TraceOptions::default()
+            });
+        interpreter.run_on_string(source.to_string()).unwrap();
+        let output = output.take();
+        let mut lines = output.lines();
+        assert_eq!(
+            lines.next(),
+            Some("[line 2] Error : [E0010] Stack overflow")
+        );
+        assert_eq!(
+            lines.collect::<Vec<_>>(),
+            [
+                "  at <fn f>, called in line 2",
+                &format!("  ... {} more", MAX_CALL_DEPTH - 2),
+                "  at <fn f>, called in line 4",
+                "  at the top level",
+                "[line 7] Error : [E0001] Operands must be two numbers",
+            ]
+        );
+    }
+
+    #[test]
+    /// tests that a cancelled program tells the calls it was in when it stopped
+    fn interrupted_at_test() {
+        let mut interpreter = Interpreter::new().with_output(Box::new(std::io::sink()));
+        let token = interpreter.cancellation_token();
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            token.cancel();
+        });
+        let source = "fun spin() { while (true) {} }\nfun outer() {\n  spin();\n}\nouter();";
+        interpreter.run_on_string(source.to_string()).unwrap_err();
+        canceller.join().unwrap();
+        assert_eq!(
+            interpreter.interrupted_at().map(ToString::to_string),
+            Some(
+                "  at <fn spin>, called in line 3\n  at <fn outer>, called in line 5\n  \
+                at the top level\n"
+                    .to_string()
+            )
+        );
+
+        // the trace is cleared by the next run, and calls that returned are off the stack
+        interpreter.run_on_string("outer;".to_string()).unwrap();
+        assert_eq!(interpreter.interrupted_at(), None);
+        assert_eq!(interpreter.stack_trace(), StackTrace::default());
+    }
+
+    #[test]
+    /// tests that blocks get their own scope and that an error stops the rest of the block
+    fn block_test() {
+        let source = "var a = \"global\";\n\
+            { var a = \"inner\"; var b = 1; print a; a = \"changed\"; print a; }\n\
+            print a;\n\
+            { a = \"assigned\"; }\n\
+            print a;\n\
+            { print b; print \"unreachable\"; }\n\
+            print \"after\";";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English);
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(
+            output.take(),
+            "inner\nchanged\nglobal\nassigned\n\
+            [line 6] Error : [E0007] Undefined variable 'b'\nafter\n"
+        );
+        assert_eq!(interpreter.metrics().errors_raised, 1);
+    }
+
+    #[test]
+    fn environment_test() {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        globals.borrow_mut().define("a", Literal::Number(1.0));
+        globals.borrow_mut().define("b", Literal::Number(2.0));
+
+        let mut local = Environment::with_enclosing(globals.clone());
+        local.define("a", Literal::Boolean(true));
+        assert_eq!(local.get("a"), Some(Literal::Boolean(true)));
+        assert_eq!(local.get("b"), Some(Literal::Number(2.0)));
+        assert_eq!(local.get("c"), None);
+        // shadowing a variable leaves the enclosing one untouched
+        assert_eq!(globals.borrow().get("a"), Some(Literal::Number(1.0)));
+
+        // assigning a variable changes it in the scope that declared it
+        assert!(local.assign("b", Literal::Nil));
+        assert_eq!(globals.borrow().get("b"), Some(Literal::Nil));
+        assert!(!local.assign("c", Literal::Nil));
+    }
+
+    #[test]
+    #[cfg(feature = "host")]
+    /// tests that line breaks end statements in the REPL once automatic semicolons are enabled
+    fn automatic_semicolons_test() {
+        let input = "var a = 1\nprint a\nprint a + 1\n";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_automatic_semicolons(true);
+        interpreter.repl(input.as_bytes()).unwrap();
+        assert_eq!(output.take(), ">> >> 1\n>> 2\n>> ");
+
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_automatic_semicolons(true);
+        interpreter
+            .run_on_string("var a = 1\nprint a +\n2\nprint a; print 3".to_string())
+            .unwrap();
+        assert_eq!(output.take(), "3\n1\n3\n");
+    }
+
+    #[test]
+    /// tests that pedantic mode warns once per line about extensions while still running the program
+    fn pedantic_test() {
+        let source = "var a = 'x';\nprint a < \"y\";\nprint a < \"y\";\nprint 1 < 2;";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English)
+            .with_pedantic(true);
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(
+            output.take(),
+            "[line 1] Warning : [L0002] canonical Lox has no single quoted strings, write \"x\" with double quotes instead\n\
+            [line 2] Warning : [L0001] canonical Lox can only compare numbers with '<', strings can only be compared with '==' and '!='\n\
+            true\n\
+            [line 3] Warning : [L0001] canonical Lox can only compare numbers with '<', strings can only be compared with '==' and '!='\n\
+            true\ntrue\n"
+        );
+        // every run starts over, since lines in the REPL are all numbered from 1
+        interpreter
+            .run_on_string("print 'a' < 'b';".to_string())
+            .unwrap();
+        assert_eq!(output.take().lines().count(), 3);
+
+        let mut interpreter = Interpreter::new().with_output(Box::new(output.clone()));
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(output.take(), "true\ntrue\ntrue\n");
+    }
+
+    #[test]
+    /// tests that lint levels override pedantic mode, and that a denied lint is an error
+    fn lint_level_test() {
+        let source = "print \"a\" < \"b\";\nprint 'c';";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English)
+            .with_pedantic(true)
+            .with_lint_level(MessageId::SingleQuotedString, LintLevel::Allow)
+            .with_lint_level(MessageId::StringOrdering, LintLevel::Deny);
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(
+            output.take(),
+            "[line 1] Error : [L0001] canonical Lox can only compare numbers with '<', strings can only be compared with '==' and '!='\n\
+            c\n"
+        );
+
+        // a denied lexical lint stops the program before it runs
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_lint_level(MessageId::SingleQuotedString, LintLevel::Deny);
+        assert!(interpreter.run_on_string(source.to_string()).is_err());
+        assert_eq!(output.take(), "");
+    }
+
+    #[test]
+    /// tests that adding a string to another value turns the value into a string, unless that is
+    /// disabled to match canonical Lox
+    fn string_concatenation_test() {
+        let source = "print \"a\" + 1.5;\nprint nil + \"b\";\nprint 1 + true;";
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English);
+        interpreter.run_on_string(source.to_string()).unwrap();
+        assert_eq!(
+            output.take(),
+            "a1.5\nnilb\n[line 3] Error : [E0003] the left side number 1 operand is being added to non left number\n"
+        );
+
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English)
+            .with_pedantic(true);
+        interpreter
+            .run_on_string("print \"a\" + 1;".to_string())
+            .unwrap();
+        assert_eq!(
+            output.take(),
+            "[line 1] Warning : [L0007] canonical Lox can only add a string to another string, 1 is not one\na1\n"
+        );
+
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English)
+            .with_string_concatenation(false);
+        interpreter
+            .run_on_string("print \"a\" + 1;".to_string())
+            .unwrap();
+        assert_eq!(
+            output.take(),
+            "[line 1] Error : [E0004] the left side string a operand is being added to non left string\n"
+        );
+    }
+
+    #[test]
+    /// tests that the trace of the last run is kept, with its artifacts only when asked for
+    fn last_trace_test() {
+        let mut interpreter = Interpreter::new().with_output(Box::new(std::io::sink()));
+        assert!(interpreter.last_trace().is_none());
+        interpreter.run_on_string("print 1;".to_string()).unwrap();
+        let trace = interpreter.last_trace().unwrap();
+        assert_eq!(trace.stages.len(), 4);
+        assert_eq!(trace.stage(Stage::Interpret).unwrap().count, 1);
+        assert!(trace.tokens.is_none());
+
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(std::io::sink()))
+            .with_trace_artifacts(true);
+        interpreter.run_on_string("print 1;".to_string()).unwrap();
+        let trace = interpreter.last_trace().unwrap();
+        assert_eq!(trace.tokens.as_ref().unwrap().len(), 4);
+        assert_eq!(trace.ast.as_ref().unwrap().len(), 1);
+
+        // a program which fails to lex leaves no trace behind
+        assert!(interpreter.run_on_string("\"a".to_string()).is_err());
+        assert!(interpreter.last_trace().is_none());
+    }
+
+    #[test]
+    /// tests that errors are reported under the name of their source and that sources only see each
+    /// other's variables in a shared environment
+    fn eval_many_test() {
+        let sources = [
+            ("a.lox", "var a = 1;\nprint a;"),
+            ("b.lox", "print a;\nprint b;"),
+            ("c.lox", "print \"c"),
+        ];
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English);
+        let report = interpreter
+            .eval_many(sources, BatchEnvironment::Shared)
+            .unwrap();
+        assert_eq!(output.take(), "1\n1\n");
+        assert_eq!(report.sources.len(), 3);
+        assert_eq!(report.diagnostics("a.lox"), Some(&[][..]));
+        assert_eq!(report.diagnostics("b.lox").unwrap().len(), 1);
+        assert!(report.diagnostics("b.lox").unwrap()[0].contains("'b'"));
+        assert_eq!(report.diagnostics("c.lox").unwrap().len(), 1);
+        assert_eq!(report.diagnostics("d.lox"), None);
+        assert_eq!(report.error_count(), 2);
+        assert_eq!(interpreter.metrics().errors_raised, 2);
+
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_language(Language::English);
+        let report = interpreter
+            .eval_many(sources, BatchEnvironment::PerSource)
+            .unwrap();
+        assert_eq!(output.take(), "1\n");
+        assert_eq!(report.diagnostics("b.lox").unwrap().len(), 2);
+        // the variables of the sources do not leak into the interpreter either
+        interpreter.run_on_string("print a;".to_string()).unwrap();
+        assert!(output.take().contains("'a'"));
+
+        let report = interpreter
+            .eval_many([("ok.lox", "print 2;")], BatchEnvironment::PerSource)
+            .unwrap();
+        assert!(report.is_ok());
+    }
+
+    /// a plugin adding a native, a class written in Lox on top of it and a module
+    struct GreetPlugin;
+
+    impl LoxPlugin for GreetPlugin {
+        fn name(&self) -> &'static str {
+            "greet"
+        }
+
+        fn register(&self, registry: &mut Registry) {
+            registry
+                .native(natives::NativeFunction::new("shout", 1, |_, args, _| {
+                    Ok(Literal::String(format!("{}!", args[0]).to_uppercase()))
+                }))
+                .prelude("class Greeter { greet(name) { return shout(\"hello \" + name); } }")
+                .module("greet/main.lox", "print Greeter().greet(\"lox\");");
+        }
+    }
+
+    /// a plugin adding natives which need capabilities
+    struct OutsidePlugin;
+
+    impl LoxPlugin for OutsidePlugin {
+        fn name(&self) -> &'static str {
+            "outside"
+        }
+
+        fn register(&self, registry: &mut Registry) {
+            registry
+                .native(
+                    natives::NativeFunction::new("readFile", 1, |_, _, line| {
+                        Err(anyhow!(Interpreter::error(
+                            line,
+                            "readFile read a file".to_string()
+                        )))
+                    })
+                    .with_capability(Capability::FileIo),
+                )
+                .native(
+                    natives::NativeFunction::new("fetch", 2, |_, _, _| Ok(Literal::Nil))
+                        .with_capability(Capability::Network),
+                );
+        }
+    }
+
+    /// a plugin whose prelude fails
+    struct BrokenPlugin;
+
+    impl LoxPlugin for BrokenPlugin {
+        fn name(&self) -> &'static str {
+            "broken"
+        }
+
+        fn register(&self, registry: &mut Registry) {
+            registry.prelude("print missing;");
+        }
+    }
+
+    #[test]
+    /// tests that a plugin's natives and preludes are defined and its modules can be run
+    fn plugin_test() {
+        let mut output = SharedOutput::default();
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_plugin(&GreetPlugin)
+            .unwrap()
+            .with_plugin(&GreetPlugin)
+            .unwrap();
+        assert_eq!(interpreter.plugins(), ["greet"]);
+        interpreter
+            .run_on_string("print shout(\"a\"); print Greeter;".to_string())
+            .unwrap();
+        assert_eq!(output.take(), "A!\nGreeter\n");
+        interpreter
+            .run_script("greet/main.lox".to_string())
+            .unwrap();
+        assert_eq!(output.take(), "HELLO LOX!\n");
+
+        let err = Interpreter::new()
+            .with_output(Box::new(output.clone()))
+            .with_plugin(&BrokenPlugin)
+            .err()
+            .unwrap();
+        assert!(err
+            .to_string()
+            .starts_with("the prelude of the plugin broken failed"));
+
+        let plugins: [&dyn LoxPlugin; 2] = [&GreetPlugin, &BrokenPlugin];
+        assert_eq!(
+            crate::plugin::find(&plugins, "greet").unwrap().name(),
+            "greet"
+        );
+        assert_eq!(
+            crate::plugin::find(&plugins, "http")
+                .err()
+                .unwrap()
+                .to_string(),
+            "Unknown plugin http, the plugins available are: greet, broken"
+        );
+    }
+
+    /// an output sink for tests which can be read back after it is handed to an interpreter
+    #[derive(Clone, Default)]
+    struct SharedOutput(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl SharedOutput {
+        fn take(&mut self) -> String {
+            String::from_utf8(std::mem::take(&mut *self.0.lock().unwrap())).unwrap()
+        }
+    }
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// a logger keeping the records logged by scripts
+    struct TestLogger {
+        records: std::sync::Mutex<Vec<(log::Level, String)>>,
+    }
+
+    impl log::Log for TestLogger {
+        fn enabled(&self, metadata: &log::Metadata) -> bool {
+            metadata.target() == "lox"
+        }
+
+        fn log(&self, record: &log::Record) {
+            if self.enabled(record.metadata()) {
+                let mut records = self.records.lock().unwrap();
+                records.push((record.level(), record.args().to_string()));
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: TestLogger = TestLogger {
+        records: std::sync::Mutex::new(Vec::new()),
+    };
+
     fn get_parsed_expr(expr: &str) -> Literal {
         let mut lexer = Lexer::new();
         let mut tokens = lexer.lex(expr).unwrap();
@@ -1,4 +1,11 @@
-use std::{collections::HashMap, iter::Peekable, str::Chars};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    iter::Peekable,
+    str::Chars,
+};
+
+use crate::messages::{Language, MessageId};
 
 use lazy_static::lazy_static;
 
@@ -21,6 +28,15 @@ lazy_static! {
         m.insert("this", TokenType::This);
         m.insert("while", TokenType::While);
         m.insert("print", TokenType::Print);
+        m.insert("define", TokenType::Define);
+        m.insert("break", TokenType::Break);
+        m.insert("continue", TokenType::Continue);
+        m.insert("do", TokenType::Do);
+        m.insert("throw", TokenType::Throw);
+        m.insert("try", TokenType::Try);
+        m.insert("catch", TokenType::Catch);
+        m.insert("const", TokenType::Const);
+        m.insert("import", TokenType::Import);
         m
     };
 }
@@ -35,6 +51,9 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
+    Colon,
     Comma,
     Dot,
     Minus,
@@ -52,11 +71,21 @@ pub enum TokenType {
     GreaterEqual,
     Less,
     LessEqual,
+    PlusEqual,
+    MinusEqual,
+    StarEqual,
+    SlashEqual,
+    /// the `...` before the rest parameter of a function
+    Ellipsis,
 
     // Literals
     Identifier,
     String(String),
-    Number(f32),
+    Number(f64),
+    /// the digits of a big integer literal like `123n`
+    BigInt(String),
+    /// the bytes of a bytes literal like `b"GIF\x00"`
+    Bytes(Vec<u8>),
 
     // Keywords.
     And,
@@ -75,6 +104,15 @@ pub enum TokenType {
     True,
     Var,
     While,
+    Define,
+    Break,
+    Continue,
+    Do,
+    Throw,
+    Try,
+    Catch,
+    Const,
+    Import,
 
     Eof,
 }
@@ -85,7 +123,7 @@ pub struct Token {
     pub token_type: TokenType,
     // the line of the file that was parsed that this token was found on
     pub line: u32,
-    /// Used solely for debugging purposes to print the token literall to the console
+    /// the source text of the token, which is also the name of identifier tokens
     lexeme: Option<String>,
 }
 
@@ -98,28 +136,170 @@ impl Token {
             line,
         }
     }
+
+    /// the text of the token as it was written in the source code.
+    pub fn lexeme(&self) -> &str {
+        self.lexeme.as_deref().unwrap_or_default()
+    }
+}
+
+/// A `//` comment, which the lexer only keeps when asked to so that tools reprinting code can put it
+/// back where it was written. `/* */` comments are always thrown away.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd)]
+pub struct Comment {
+    /// the text after the `//`, as it was written
+    pub text: String,
+    pub line: u32,
+}
+
+/// a place in the source, where the column is a byte offset into the line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub line: u32,
+    pub column: usize,
+}
+
+/// the position of every token in the source, or None for a token which can't be found where it is
+/// expected. Tokens are written exactly as their lexemes and only whitespace separates them, so each
+/// one starts after the whitespace following the one before it on the same line.
+pub fn positions(source: &str, tokens: &[Token]) -> Vec<Option<Position>> {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut line = 0;
+    let mut column = 0;
+    tokens
+        .iter()
+        .map(|token| {
+            if token.token_type == TokenType::Eof {
+                return None;
+            }
+            if token.line != line {
+                line = token.line;
+                column = 0;
+            }
+            let text = lines.get(line as usize - 1)?;
+            let rest = text.get(column..)?;
+            column += rest.len() - rest.trim_start_matches([' ', '\r', '\t']).len();
+            if !text[column..].starts_with(token.lexeme()) {
+                return None;
+            }
+            let position = Position { line, column };
+            column += token.lexeme().len();
+            Some(position)
+        })
+        .collect()
 }
 
 /// A lexer (or scanner) is responsible for breaking a program into a sequence of tokens.
+#[derive(Default)]
 pub struct Lexer {
     debug: bool,
+    /// the flags which are enabled for `#if FLAG ... #end` conditional sections
+    flags: HashSet<String>,
+    /// whether keywords are recognized regardless of their case, e.g. `IF` or `Print`
+    case_insensitive_keywords: bool,
+    /// whether comments are kept, see [`Lexer::take_comments`]
+    keep_comments: bool,
+    comments: Vec<Comment>,
+    /// the line the open `/* */` comment started on and how deeply it is nested, while the lexer is
+    /// inside one
+    block_comment: Option<(u32, usize)>,
+    /// the language lexical errors are reported in
+    language: Language,
 }
 
 /// the lexer is responsible for breaking an input program into a sequence of tokens. The program is represented
 /// as a string of characters which adhere to the Lox language syntax.
 impl Lexer {
     pub fn new() -> Self {
-        Self { debug: false }
+        Self {
+            debug: false,
+            flags: HashSet::new(),
+            case_insensitive_keywords: false,
+            keep_comments: false,
+            comments: Vec::new(),
+            block_comment: None,
+            language: Language::English,
+        }
+    }
+
+    /// when enabled the comments of lexed input are kept instead of being thrown away, so that they
+    /// can be attached to the syntax tree with [`crate::parser::Parser::with_comments`]. Disabled by
+    /// default.
+    pub fn with_comments(mut self, keep_comments: bool) -> Self {
+        self.keep_comments = keep_comments;
+        self
+    }
+
+    /// the comments kept since they were last taken, in the order they were written.
+    pub fn take_comments(&mut self) -> Vec<Comment> {
+        std::mem::take(&mut self.comments)
+    }
+
+    /// when enabled keywords are matched regardless of their case, so `IF` and `If` both lex to the
+    /// `if` keyword. The lexeme of the token keeps the case it was written in. Disabled by default.
+    pub fn with_case_insensitive_keywords(mut self, case_insensitive_keywords: bool) -> Self {
+        self.case_insensitive_keywords = case_insensitive_keywords;
+        self
+    }
+
+    /// sets the language lexical errors are reported in, English by default.
+    pub fn with_language(mut self, language: Language) -> Self {
+        self.language = language;
+        self
+    }
+
+    /// sets the flags that enable `#if FLAG ... #end` sections. Lines in a section whose flag is not
+    /// enabled are skipped as if they were comments.
+    pub fn with_flags(mut self, flags: HashSet<String>) -> Self {
+        self.flags = flags;
+        self
     }
 
     /// break a string-slice of utf8-characters into a sequence of tokens.
     pub fn lex(&mut self, input: &str) -> Result<Vec<Token>> {
-        let nested_tokens = input
-            .lines()
-            .enumerate()
-            .map(|(line_number, line)| self.lex_chars(line.chars(), 1 + line_number as u32))
-            .collect::<Result<Vec<_>>>()?;
-        let mut tokens = nested_tokens.into_iter().flatten().collect::<Vec<_>>();
+        let mut tokens = Vec::new();
+        // the line each open `#if` is on and whether its section is included
+        let mut sections: Vec<(u32, bool)> = Vec::new();
+        self.block_comment = None;
+
+        for (line_number, line) in input.lines().enumerate() {
+            let line_number = 1 + line_number as u32;
+            let included = sections.iter().all(|(_, included)| *included);
+
+            // a directive in a block comment is commented out like the rest of it
+            if self.block_comment.is_some() && included {
+                tokens.extend(self.lex_chars(line.chars(), line_number)?);
+                continue;
+            }
+
+            match Lexer::directive(line) {
+                Some(("if", flag)) if !flag.is_empty() => {
+                    sections.push((line_number, self.flags.contains(flag)));
+                }
+                Some(("end", "")) => {
+                    sections.pop().ok_or_else(|| {
+                        self.lexical_error(MessageId::UnmatchedEnd, &[], line_number)
+                    })?;
+                }
+                Some(_) => {
+                    return Err(self.lexical_error(
+                        MessageId::InvalidDirective,
+                        &[&line.trim()],
+                        line_number,
+                    ))
+                }
+                None if included => tokens.extend(self.lex_chars(line.chars(), line_number)?),
+                None => {}
+            }
+        }
+
+        if let Some((line_number, _)) = sections.last() {
+            return Err(self.lexical_error(MessageId::UnterminatedIf, &[], *line_number));
+        }
+        if let Some((line_number, _)) = self.block_comment.take() {
+            return Err(self.lexical_error(MessageId::UnterminatedBlockComment, &[], line_number));
+        }
+
         let final_line = match tokens.last() {
             Some(token) => token.line,
             None => 0,
@@ -129,12 +309,21 @@ impl Lexer {
         Ok(tokens)
     }
 
-    // TODOOOO: Handle comments
+    /// splits a `#directive argument` line into the directive name and its argument, returns None
+    /// for lines which are not directives.
+    fn directive(line: &str) -> Option<(&str, &str)> {
+        let directive = line.trim().strip_prefix('#')?;
+        Some(match directive.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, argument.trim()),
+            None => (directive, ""),
+        })
+    }
+
     /// Handles lexing/scanning on a character by character basis. This way multi-character tokens can be either split into multiple smaller tokens or into a larger identifier token.
     ///
     /// ### Note
     /// The lexer
-    fn lex_chars(&self, word: Chars, line_number: u32) -> Result<Vec<Token>> {
+    fn lex_chars(&mut self, word: Chars, line_number: u32) -> Result<Vec<Token>> {
         /*
         Use a Peekable iterator to allow us to peek at the next character in the input without consuming the iterator at the current character
         This is useful for determining whether or not a token is a multi-character token or a comment.
@@ -142,10 +331,13 @@ impl Lexer {
         */
         let mut peek: Peekable<_> = word.peekable();
         let mut tokens = Vec::new();
+        self.skip_block_comment(&mut peek);
 
         // keep looping until we reach the end of the iterator
         while let Some(char) = peek.next() {
             let lexeme = char.to_string();
+            // the one token of three characters needs a second character of lookahead
+            let is_ellipsis = char == '.' && peek.clone().take(2).eq(['.', '.']);
             let next_peek = peek.peek();
 
             let out = match char {
@@ -153,21 +345,57 @@ impl Lexer {
                 ')' => Ok(Token::new(TokenType::RightParen, lexeme, line_number)),
                 '{' => Ok(Token::new(TokenType::LeftBrace, lexeme, line_number)),
                 '}' => Ok(Token::new(TokenType::RightBrace, lexeme, line_number)),
+                '[' => Ok(Token::new(TokenType::LeftBracket, lexeme, line_number)),
+                ']' => Ok(Token::new(TokenType::RightBracket, lexeme, line_number)),
+                ':' => Ok(Token::new(TokenType::Colon, lexeme, line_number)),
                 ',' => Ok(Token::new(TokenType::Comma, lexeme, line_number)),
+                '.' if is_ellipsis => {
+                    peek.next();
+                    peek.next();
+                    Ok(Token::new(
+                        TokenType::Ellipsis,
+                        "...".to_string(),
+                        line_number,
+                    ))
+                }
                 '.' => Ok(Token::new(TokenType::Dot, lexeme, line_number)),
+                '-' | '+' | '*' if next_peek == Some(&'=') => {
+                    peek.next();
+                    let token_type = match char {
+                        '-' => TokenType::MinusEqual,
+                        '+' => TokenType::PlusEqual,
+                        _ => TokenType::StarEqual,
+                    };
+                    Ok(Token::new(token_type, format!("{}=", char), line_number))
+                }
                 '-' => Ok(Token::new(TokenType::Minus, lexeme, line_number)),
                 '+' => Ok(Token::new(TokenType::Plus, lexeme, line_number)),
                 ';' => Ok(Token::new(TokenType::Semicolon, lexeme, line_number)),
                 '*' => Ok(Token::new(TokenType::Star, lexeme, line_number)),
                 '/' => {
                     if next_peek == Some(&'/') {
-                        // ignore comments
-                        while let Some(char) = peek.next() {
-                            if char == '\n' {
-                                break;
-                            }
+                        // a comment runs to the end of the line and is skipped unless it is kept
+                        peek.next();
+                        let text: String = peek.by_ref().collect();
+                        if self.keep_comments {
+                            self.comments.push(Comment {
+                                text,
+                                line: line_number,
+                            });
                         }
                         continue;
+                    } else if next_peek == Some(&'*') {
+                        peek.next();
+                        self.block_comment = Some((line_number, 1));
+                        self.skip_block_comment(&mut peek);
+                        continue;
+                    } else if next_peek == Some(&'=') {
+                        peek.next();
+                        Ok(Token::new(
+                            TokenType::SlashEqual,
+                            "/=".to_string(),
+                            line_number,
+                        ))
                     } else {
                         Ok(Token::new(TokenType::Slash, lexeme, line_number))
                     }
@@ -224,49 +452,117 @@ impl Lexer {
                     // ignore whitespace characters
                     continue;
                 }
-                '\'' | '\"' => Lexer::lex_string_literals(lexeme, &mut peek, line_number),
-                num if num.is_numeric() => {
-                    Lexer::lex_number_literals(lexeme, &mut peek, line_number)
+                '\'' | '\"' => self.lex_string_literals(lexeme, &mut peek, line_number),
+                'b' if matches!(next_peek, Some(&'\'') | Some(&'"')) => {
+                    self.lex_bytes_literals(lexeme, &mut peek, line_number)
                 }
+                num if num.is_numeric() => self.lex_number_literals(lexeme, &mut peek, line_number),
                 chr if chr.is_alphabetic() => {
-                    Lexer::lex_identifier_literals(lexeme, &mut peek, line_number)
+                    self.lex_identifier_literals(lexeme, &mut peek, line_number)
+                }
+                _ => {
+                    Err(self.lexical_error(MessageId::UnexpectedCharacter, &[&lexeme], line_number))
                 }
-                _ => Err(anyhow!(Lexer::lexical_error(
-                    format!("unexpected character! {}", lexeme),
-                    line_number
-                ))),
             }?;
             tokens.push(out);
         }
         Ok(tokens)
     }
 
+    /// consumes the characters of the open block comment, if there is one, until it is closed or
+    /// the line ends. Block comments nest, so each `/*` in one needs its own `*/`.
+    fn skip_block_comment(&mut self, peek: &mut Peekable<Chars>) {
+        while let Some((_, depth)) = &mut self.block_comment {
+            match (peek.next(), peek.peek()) {
+                (None, _) => return,
+                (Some('/'), Some('*')) => {
+                    peek.next();
+                    *depth += 1;
+                }
+                (Some('*'), Some('/')) => {
+                    peek.next();
+                    *depth -= 1;
+                    if *depth == 0 {
+                        self.block_comment = None;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
     // TODO: Handle string literals with different identifiers " " vs ' ' and " '. Should enforce that the string is terminated by the same identifier.
     //
     /// keep consuming the set of characters inside of peek until another " character is found or the end of the string is reached which results in an error.
     fn lex_string_literals(
+        &self,
         lexeme: String,
         peek: &mut Peekable<Chars>,
         line_number: u32,
     ) -> Result<Token> {
         let mut val = String::with_capacity(10);
 
-        while let Some(char) = peek.peek() {
-            if char == &'\"' || char == &'\'' {
-                let char = peek.next().unwrap(); // consume the terminating string literal\
+        for char in peek.by_ref() {
+            if char == '\"' || char == '\'' {
                 let lexeme = format!("{}{}{}", lexeme.clone(), val.clone(), char);
                 return Ok(Token::new(TokenType::String(val), lexeme, line_number));
             }
-            val.push(char.clone());
-            peek.next();
+            val.push(char);
         }
-        Err(anyhow!(Lexer::lexical_error(
-            format!("Unterminated string literal {}", val),
-            line_number
-        )))
+        Err(self.lexical_error(MessageId::UnterminatedString, &[&val], line_number))
+    }
+
+    /// the bytes of a literal like `b"GIF\x00"` once its b is consumed, where `\xNN` is the byte
+    /// of two hex digits, `\\` is a backslash and other characters are their UTF-8 bytes
+    fn lex_bytes_literals(
+        &self,
+        mut lexeme: String,
+        peek: &mut Peekable<Chars>,
+        line_number: u32,
+    ) -> Result<Token> {
+        let mut val = Vec::with_capacity(10);
+        lexeme.extend(peek.next()); // the opening quote
+
+        while let Some(char) = peek.next() {
+            lexeme.push(char);
+            match char {
+                '\"' | '\'' => return Ok(Token::new(TokenType::Bytes(val), lexeme, line_number)),
+                '\\' => match peek.next() {
+                    Some('\\') => {
+                        lexeme.push('\\');
+                        val.push(b'\\');
+                    }
+                    Some('x') => {
+                        let digits: String = peek.take(2).collect();
+                        lexeme.push('x');
+                        lexeme.push_str(&digits);
+                        let byte = u8::from_str_radix(&digits, 16)
+                            .ok()
+                            .filter(|_| digits.len() == 2)
+                            .ok_or_else(|| {
+                                self.lexical_error(MessageId::InvalidByte, &[&digits], line_number)
+                            })?;
+                        val.push(byte);
+                    }
+                    escape => {
+                        return Err(self.lexical_error(
+                            MessageId::InvalidBytesEscape,
+                            &[&escape.map(String::from).unwrap_or_default()],
+                            line_number,
+                        ))
+                    }
+                },
+                char => {
+                    let mut buffer = [0; 4];
+                    val.extend_from_slice(char.encode_utf8(&mut buffer).as_bytes());
+                }
+            }
+        }
+        Err(self.lexical_error(MessageId::UnterminatedBytes, &[&lexeme], line_number))
     }
 
     fn lex_number_literals(
+        &self,
         lexeme: String,
         peek: &mut Peekable<Chars>,
         line_number: u32,
@@ -275,27 +571,27 @@ impl Lexer {
         val.push_str(&lexeme);
 
         while let Some(char) = peek.peek() {
+            if *char == 'n' && !val.contains('.') {
+                peek.next();
+                let lexeme = format!("{}n", val);
+                return Ok(Token::new(TokenType::BigInt(val), lexeme, line_number));
+            }
             if (!char.is_numeric()) && (*char != '.') {
-                return Ok(Token::new(
-                    TokenType::Number(val.parse::<f32>().unwrap()),
-                    val,
-                    line_number,
-                ));
+                break;
             }
             val.push(*char);
             peek.next();
         }
-        match val.parse::<f32>() {
+        // a number with several dots, like 1.2.3, is not a number at all
+        match val.parse::<f64>() {
             Ok(num) => Ok(Token::new(TokenType::Number(num), val, line_number)),
-            Err(_) => Err(anyhow!(Lexer::lexical_error(
-                format!("Invalid number literal {}", val),
-                line_number
-            ))),
+            Err(_) => Err(self.lexical_error(MessageId::InvalidNumber, &[&val], line_number)),
         }
         // Err(anyhow!(Lexer::lexical_error(format!("Malformed number literal {}", val) ,line_number)
     }
 
     fn lex_identifier_literals(
+        &self,
         lexeme: String,
         peek: &mut Peekable<Chars>,
         line_number: u32,
@@ -304,13 +600,16 @@ impl Lexer {
         val.push_str(&lexeme);
 
         let check_keyword = |val: String| -> Token {
-            if RESERVED_KEYWORDS.contains_key(&*val) {
-                let token_type = RESERVED_KEYWORDS.get(&*val).unwrap();
+            let keyword = if self.case_insensitive_keywords {
+                RESERVED_KEYWORDS.get(&*val.to_lowercase())
+            } else {
+                RESERVED_KEYWORDS.get(&*val)
+            };
+            match keyword {
                 // token type does not implement copy since one of the members is a String
                 // but we clone here when it can't be string so it is very cheap to do so
-                return Token::new(token_type.clone(), val, line_number);
-            } else {
-                return Token::new(TokenType::Identifier, val, line_number);
+                Some(token_type) => Token::new(token_type.clone(), val, line_number),
+                None => Token::new(TokenType::Identifier, val, line_number),
             }
         };
         // keep adding the identifier
@@ -328,8 +627,18 @@ impl Lexer {
         Ok(check_keyword(val))
     }
 
-    fn lexical_error(message: String, line_number: u32) -> String {
-        format!("{:#?} (line {})", message, line_number)
+    /// an error from the message catalog, in the language of the lexer
+    fn lexical_error(
+        &self,
+        id: MessageId,
+        args: &[&dyn Display],
+        line_number: u32,
+    ) -> anyhow::Error {
+        anyhow!(format!(
+            "{:#?} (line {})",
+            id.render_args(self.language, args),
+            line_number
+        ))
     }
 }
 
@@ -339,12 +648,28 @@ mod test {
 
     #[test]
     fn lexer_error_test() {
-        todo!()
+        let mut lexer = Lexer::new();
+        let err = lexer.lex("a = 1 # 2").unwrap_err();
+        assert!(err.to_string().contains("unexpected character! #"));
+        assert!(err.to_string().contains("(line 1)"));
     }
 
     #[test]
     fn lexer_lex_line_error_test() {
-        todo!()
+        // the error reports the line the unterminated string literal was found on
+        let mut lexer = Lexer::new();
+        let err = lexer.lex("print 1;\nprint \"hello;").unwrap_err();
+        assert!(err.to_string().contains("Unterminated string literal"));
+        assert!(err.to_string().contains("(line 2)"));
+    }
+
+    #[test]
+    /// tests that a number with several dots is an error rather than a crash
+    fn invalid_number_test() {
+        let mut lexer = Lexer::new();
+        let err = lexer.lex("print 1;\nprint 1.2.3;").unwrap_err();
+        assert!(err.to_string().contains("Invalid number literal 1.2.3"));
+        assert!(err.to_string().contains("(line 2)"));
     }
 
     #[test]
@@ -352,7 +677,7 @@ mod test {
         let source_code = "({ )}\n+ - !";
         let mut lexer = Lexer::new();
         let tokens = lexer.lex(source_code).unwrap();
-        let expected = vec![
+        let expected = [
             Token::new(TokenType::LeftParen, "(".to_string(), 1),
             Token::new(TokenType::LeftBrace, "{".to_string(), 1),
             Token::new(TokenType::RightParen, ")".to_string(), 1),
@@ -360,7 +685,7 @@ mod test {
             Token::new(TokenType::Plus, "+".to_string(), 2),
             Token::new(TokenType::Minus, "-".to_string(), 2),
             Token::new(TokenType::Bang, "!".to_string(), 2),
-            Token::new(TokenType::Eof, "".to_string(), 0),
+            Token::new(TokenType::Eof, "".to_string(), 2),
         ];
 
         tokens.iter().zip(expected.iter()).for_each(|(t, e)| {
@@ -374,9 +699,9 @@ mod test {
         let mut lexer = Lexer::new();
         let tokens = lexer.lex(source_code).unwrap();
 
-        let expected = vec![
+        let expected = [
             Token::new(TokenType::Identifier, "foobar".to_string(), 1),
-            Token::new(TokenType::Eof, "".to_string(), 0),
+            Token::new(TokenType::Eof, "".to_string(), 1),
         ];
 
         tokens.iter().zip(expected.iter()).for_each(|(t, e)| {
@@ -390,11 +715,11 @@ mod test {
         let mut lexer = Lexer::new();
         let tokens = lexer.lex(source_code).unwrap();
 
-        let expected = vec![
+        let expected = [
             Token::new(TokenType::Identifier, "a".to_string(), 1),
             Token::new(TokenType::Plus, "+".to_string(), 1),
             Token::new(TokenType::Identifier, "b".to_string(), 1),
-            Token::new(TokenType::Eof, "".to_string(), 0),
+            Token::new(TokenType::Eof, "".to_string(), 1),
         ];
 
         tokens
@@ -407,11 +732,11 @@ mod test {
         let source_code = "a==b";
         let tokens = lexer.lex(source_code).unwrap();
 
-        let expected = vec![
+        let expected = [
             Token::new(TokenType::Identifier, "a".to_string(), 1),
             Token::new(TokenType::EqualEqual, "==".to_string(), 1),
             Token::new(TokenType::Identifier, "b".to_string(), 1),
-            Token::new(TokenType::Eof, "".to_string(), 0),
+            Token::new(TokenType::Eof, "".to_string(), 1),
         ];
 
         tokens
@@ -424,11 +749,11 @@ mod test {
         let source_code = "a!=b";
         let tokens = lexer.lex(source_code).unwrap();
 
-        let expected = vec![
+        let expected = [
             Token::new(TokenType::Identifier, "a".to_string(), 1),
             Token::new(TokenType::BangEqual, "!=".to_string(), 1),
             Token::new(TokenType::Identifier, "b".to_string(), 1),
-            Token::new(TokenType::Eof, "".to_string(), 0),
+            Token::new(TokenType::Eof, "".to_string(), 1),
         ];
 
         tokens
@@ -441,11 +766,11 @@ mod test {
         let source_code = "123/45.45";
         let tokens = lexer.lex(source_code).unwrap();
 
-        let expected = vec![
+        let expected = [
             Token::new(TokenType::Number(123.0), "123".to_string(), 1),
             Token::new(TokenType::Slash, "/".to_string(), 1),
             Token::new(TokenType::Number(45.45), "45.45".to_string(), 1),
-            Token::new(TokenType::Eof, "".to_string(), 0),
+            Token::new(TokenType::Eof, "".to_string(), 1),
         ];
 
         tokens
@@ -456,15 +781,47 @@ mod test {
             });
     }
 
+    #[test]
+    /// tests that a b before a string makes it a bytes literal, with escapes for any byte
+    fn lexer_bytes_literal_test() {
+        let tokens = Lexer::new().lex("b\"GIF\\x00\\\\é\" b").unwrap();
+        assert_eq!(
+            tokens[0],
+            Token::new(
+                TokenType::Bytes(vec![b'G', b'I', b'F', 0, b'\\', 0xc3, 0xa9]),
+                "b\"GIF\\x00\\\\é\"".to_string(),
+                1
+            )
+        );
+        assert_eq!(tokens[1].token_type, TokenType::Identifier);
+
+        assert!(Lexer::new().lex("b\"\\xz1\"").is_err());
+        assert!(Lexer::new().lex("b\"\\n\"").is_err());
+        assert!(Lexer::new().lex("b\"abc").is_err());
+    }
+
+    #[test]
+    /// tests that a whole number followed by n is a big integer, whether or not they are supported
+    fn lexer_bigint_literal_test() {
+        let tokens = Lexer::new().lex("123n 1.5n").unwrap();
+        assert_eq!(
+            tokens[0],
+            Token::new(TokenType::BigInt("123".to_string()), "123n".to_string(), 1)
+        );
+        // a fraction is a number followed by the identifier n
+        assert_eq!(tokens[1].token_type, TokenType::Number(1.5));
+        assert_eq!(tokens[2].token_type, TokenType::Identifier);
+    }
+
     #[test]
     fn lexer_number_literal_test() {
         let number_literals = "123.456\n123";
         let mut lexer = Lexer::new();
         let tokens = lexer.lex(number_literals).unwrap();
-        let expected = vec![
+        let expected = [
             Token::new(TokenType::Number(123.456), "123.456".to_string(), 1),
             Token::new(TokenType::Number(123.0), "123".to_string(), 2),
-            Token::new(TokenType::Eof, "".to_string(), 0),
+            Token::new(TokenType::Eof, "".to_string(), 2),
         ];
 
         tokens
@@ -481,7 +838,7 @@ mod test {
         let mut lexer = Lexer::new();
         let tokens = lexer.lex(source_code).unwrap();
 
-        let expected = vec![
+        let expected = [
             Token::new(TokenType::Var, "var".to_string(), 1),
             Token::new(TokenType::Identifier, "a".to_string(), 1),
             Token::new(TokenType::Equal, "=".to_string(), 1),
@@ -490,7 +847,7 @@ mod test {
                 "\"hello world\"".to_string(),
                 1,
             ),
-            Token::new(TokenType::Eof, "".to_string(), 0),
+            Token::new(TokenType::Eof, "".to_string(), 1),
         ];
 
         tokens
@@ -501,6 +858,65 @@ mod test {
             });
     }
 
+    #[test]
+    /// tests that `#if` sections are only lexed when their flag is enabled
+    fn conditional_section_test() {
+        let source_code = "a\n#if DEBUG\nb\n#if TRACE\nc\n#end\n#end\nd";
+        let tokens = Lexer::new().lex(source_code).unwrap();
+        let expected = [
+            Token::new(TokenType::Identifier, "a".to_string(), 1),
+            Token::new(TokenType::Identifier, "d".to_string(), 8),
+            Token::new(TokenType::Eof, "".to_string(), 8),
+        ];
+        assert_eq!(tokens, expected);
+
+        let flags = ["DEBUG".to_string()].into_iter().collect();
+        let tokens = Lexer::new().with_flags(flags).lex(source_code).unwrap();
+        let expected = [
+            Token::new(TokenType::Identifier, "a".to_string(), 1),
+            Token::new(TokenType::Identifier, "b".to_string(), 3),
+            Token::new(TokenType::Identifier, "d".to_string(), 8),
+            Token::new(TokenType::Eof, "".to_string(), 8),
+        ];
+        assert_eq!(tokens, expected);
+    }
+
+    #[test]
+    fn conditional_section_error_test() {
+        let err = Lexer::new().lex("a\n#if DEBUG\nb").unwrap_err();
+        assert!(err.to_string().contains("Unterminated #if"));
+        assert!(err.to_string().contains("(line 2)"));
+
+        let err = Lexer::new().lex("a\n#end").unwrap_err();
+        assert!(err.to_string().contains("#end without a matching #if"));
+
+        let err = Lexer::new().lex("#else").unwrap_err();
+        assert!(err.to_string().contains("invalid directive"));
+    }
+
+    #[test]
+    fn case_insensitive_keywords_test() {
+        let source_code = "IF Print";
+        let tokens = Lexer::new().lex(source_code).unwrap();
+        let expected = [
+            Token::new(TokenType::Identifier, "IF".to_string(), 1),
+            Token::new(TokenType::Identifier, "Print".to_string(), 1),
+            Token::new(TokenType::Eof, "".to_string(), 1),
+        ];
+        assert_eq!(tokens, expected);
+
+        let tokens = Lexer::new()
+            .with_case_insensitive_keywords(true)
+            .lex(source_code)
+            .unwrap();
+        let expected = [
+            Token::new(TokenType::If, "IF".to_string(), 1),
+            Token::new(TokenType::Print, "Print".to_string(), 1),
+            Token::new(TokenType::Eof, "".to_string(), 1),
+        ];
+        assert_eq!(tokens, expected);
+    }
+
     #[test]
     fn comment_test() {
         let comment = "// this is a comment";
@@ -508,20 +924,20 @@ mod test {
         let tokens = lexer.lex(comment).unwrap();
         // the end-of-file token is always in the returned token
         assert_eq!(
-            tokens.get(0).unwrap(),
+            tokens.first().unwrap(),
             &Token::new(TokenType::Eof, "".to_string(), 0)
         );
 
         let source_code = "// this is a comment\n a + b = 0";
         let tokens = lexer.lex(source_code).unwrap();
 
-        let expected = vec![
+        let expected = [
             Token::new(TokenType::Identifier, "a".to_string(), 2),
             Token::new(TokenType::Plus, "+".to_string(), 2),
             Token::new(TokenType::Identifier, "b".to_string(), 2),
             Token::new(TokenType::Equal, "=".to_string(), 2),
             Token::new(TokenType::Number(0.0), "0".to_string(), 2),
-            Token::new(TokenType::Eof, "".to_string(), 0),
+            Token::new(TokenType::Eof, "".to_string(), 2),
         ];
 
         tokens
@@ -531,4 +947,31 @@ mod test {
                 assert_eq!(token, expected_token);
             });
     }
+
+    #[test]
+    /// tests that block comments span lines and nest, and that an unterminated one is reported at
+    /// the line it starts on
+    fn block_comment_test() {
+        let source = "a /* one\n/* two */ still\n#if DEBUG\n*/ b /**/ c\nd / e";
+        let tokens = Lexer::new().lex(source).unwrap();
+        let lexemes: Vec<(&str, u32)> = tokens
+            .iter()
+            .map(|token| (token.lexeme(), token.line))
+            .collect();
+        assert_eq!(
+            lexemes,
+            [
+                ("a", 1),
+                ("b", 4),
+                ("c", 4),
+                ("d", 5),
+                ("/", 5),
+                ("e", 5),
+                ("", 5)
+            ]
+        );
+
+        let err = Lexer::new().lex("a\n/* /* */\nb").unwrap_err().to_string();
+        assert_eq!(err, "\"[S0004] Unterminated block comment\" (line 2)");
+    }
 }
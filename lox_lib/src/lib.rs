@@ -2,9 +2,54 @@
 
 pub mod interpreter;
 
+pub mod analyze;
 pub mod ast;
+pub mod bench;
+#[cfg(feature = "bigint")]
+pub mod bigint;
+pub mod bytes;
+#[cfg(feature = "host")]
+pub mod cache;
+pub mod clock;
+#[cfg(feature = "host")]
+pub mod config;
+pub mod crash;
+pub mod datetime;
+pub mod diff;
+pub mod dry_run;
+pub mod emit;
+#[cfg(feature = "host")]
+pub mod exercise;
+pub mod fix;
+#[cfg(feature = "host")]
+pub mod grade;
+pub mod heap;
+#[cfg(feature = "net")]
+pub mod http;
 /// the interpreter can be run in one of two modes.
 /// either it can be running a single script that is specified or
 /// it can be running in interactive mode where it functions as a REPL.
 pub mod lexer;
+pub mod loader;
+pub mod map;
+pub mod messages;
+pub mod minify;
+pub mod natives;
+pub mod number;
+#[cfg(test)]
+mod panic_free;
 pub mod parser;
+pub mod pipeline;
+pub mod plugin;
+pub mod random;
+pub mod refactor;
+pub mod semantic;
+#[cfg(feature = "host")]
+pub mod server;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod stack;
+pub mod stdlib;
+#[cfg(feature = "host")]
+pub mod testing;
+pub mod unicode;
@@ -0,0 +1,178 @@
+//!
+//! This module contains the loaders the interpreter uses to read in the source of Lox scripts.
+//!
+//! Embedders can implement [`ModuleLoader`] to serve scripts from somewhere other than the file
+//! system, such as memory, an archive or a database.
+
+#[cfg(feature = "host")]
+use anyhow::Context;
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+/// Resolves the path of a script to its source code.
+pub trait ModuleLoader {
+    fn load(&self, path: &str) -> Result<String>;
+
+    /// The path of the script imported with the path by the importing script, or by the program
+    /// itself when there is none. Scripts run once per resolved path and circular imports are found
+    /// through it, so every path to the same script has to resolve to the same one. By default a
+    /// relative path is taken from the directory of the importing script, see [`relative_to`].
+    fn resolve(&self, importer: Option<&str>, path: &str) -> String {
+        relative_to(importer, path)
+    }
+}
+
+/// the path taken from the directory of the importing script if it is relative, with the `.` and
+/// `..` in it worked out without looking at a file system, e.g. `../m.lox` imported by `sub/s.lox`
+/// is `m.lox`.
+pub fn relative_to(importer: Option<&str>, path: &str) -> String {
+    let directory = importer
+        .and_then(|importer| Path::new(importer).parent())
+        .unwrap_or(Path::new(""));
+    let mut resolved = PathBuf::new();
+    for component in directory.join(path).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match resolved.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    resolved.pop();
+                }
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => resolved.push(".."),
+            },
+            component => resolved.push(component),
+        }
+    }
+    resolved.to_string_lossy().into_owned()
+}
+
+/// the path of a script which exists with the links in it followed, so that it is the same however
+/// the script was reached
+#[cfg(feature = "host")]
+fn canonical(path: &Path) -> Option<String> {
+    std::fs::canonicalize(path)
+        .ok()
+        .map(|path| path.to_string_lossy().into_owned())
+}
+
+/// Loads scripts from the file system. This is the loader the interpreter uses by default, builds
+/// without the `host` feature have no file system and start out with an empty [`InMemoryLoader`].
+#[cfg(feature = "host")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FileSystemLoader;
+
+#[cfg(feature = "host")]
+impl ModuleLoader for FileSystemLoader {
+    fn load(&self, path: &str) -> Result<String> {
+        std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read in file from {}", path))
+    }
+
+    fn resolve(&self, importer: Option<&str>, path: &str) -> String {
+        let resolved = relative_to(importer, path);
+        canonical(Path::new(&resolved)).unwrap_or(resolved)
+    }
+}
+
+/// Loads scripts from the file system, looking for a relative path in each of the search paths in
+/// order before falling back to the path itself.
+#[cfg(feature = "host")]
+#[derive(Debug, Clone, Default)]
+pub struct SearchPathLoader {
+    paths: Vec<PathBuf>,
+}
+
+#[cfg(feature = "host")]
+impl SearchPathLoader {
+    pub fn new(paths: Vec<PathBuf>) -> Self {
+        Self { paths }
+    }
+}
+
+#[cfg(feature = "host")]
+impl ModuleLoader for SearchPathLoader {
+    fn load(&self, path: &str) -> Result<String> {
+        let found = self
+            .paths
+            .iter()
+            .map(|dir| dir.join(path))
+            .find(|candidate| candidate.is_file());
+        match found {
+            Some(found) => std::fs::read_to_string(&found)
+                .with_context(|| format!("Failed to read in file from {}", found.display())),
+            None => FileSystemLoader.load(path),
+        }
+    }
+
+    /// a script next to the importing one comes first, then the ones in the search paths
+    fn resolve(&self, importer: Option<&str>, path: &str) -> String {
+        let resolved = relative_to(importer, path);
+        canonical(Path::new(&resolved))
+            .or_else(|| {
+                self.paths
+                    .iter()
+                    .map(|dir| dir.join(path))
+                    .find(|candidate| candidate.is_file())
+                    .and_then(|found| canonical(&found))
+            })
+            .unwrap_or(resolved)
+    }
+}
+
+/// Loads scripts from a map of paths to sources kept in memory.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryLoader {
+    modules: HashMap<String, String>,
+}
+
+impl InMemoryLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// adds a script which can then be loaded with the given path.
+    pub fn with_module(mut self, path: &str, source: &str) -> Self {
+        self.modules.insert(path.to_string(), source.to_string());
+        self
+    }
+}
+
+impl ModuleLoader for InMemoryLoader {
+    fn load(&self, path: &str) -> Result<String> {
+        self.modules
+            .get(path)
+            .cloned()
+            .ok_or_else(|| anyhow!("No module found for {}", path))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn in_memory_loader_test() {
+        let loader = InMemoryLoader::new().with_module("main.lox", "print 1;");
+        assert_eq!(loader.load("main.lox").unwrap(), "print 1;");
+        assert!(loader.load("other.lox").is_err());
+    }
+
+    #[test]
+    /// tests that relative paths are taken from the directory of the importing script
+    fn relative_to_test() {
+        assert_eq!(relative_to(None, "./m.lox"), "m.lox");
+        assert_eq!(relative_to(Some("sub/s.lox"), "../m.lox"), "m.lox");
+        assert_eq!(relative_to(Some("sub/s.lox"), "./t.lox"), "sub/t.lox");
+        assert_eq!(relative_to(Some("s.lox"), "../up.lox"), "../up.lox");
+        assert_eq!(relative_to(Some("/a/b.lox"), "/c/d.lox"), "/c/d.lox");
+        assert_eq!(relative_to(Some("/b.lox"), "../../c.lox"), "/c.lox");
+    }
+
+    #[test]
+    #[cfg(feature = "host")]
+    fn file_system_loader_test() {
+        let err = FileSystemLoader.load("does/not/exist.lox").unwrap_err();
+        assert!(err.to_string().contains("does/not/exist.lox"));
+    }
+}
@@ -0,0 +1,227 @@
+//!
+//! This module contains maps, the values created by literals like `{"a": 1, 2: [true]}`. Like lists,
+//! a map is shared between every value holding it, so `m["a"] = 2` is seen through all of them.
+//!
+//! Only numbers, strings, booleans and nil can be keys, since those are compared by value. Two maps
+//! are equal when they hold equal values for the same keys, and maps are printed with their keys in
+//! order so that printing one gives the same output on every run.
+
+use crate::ast::Literal;
+use std::cell::{Ref, RefCell};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+/// A key of a map, which is a value compared by its contents.
+#[derive(Debug, Clone)]
+pub enum MapKey {
+    Nil,
+    Boolean(bool),
+    Number(f64),
+    String(String),
+}
+
+impl MapKey {
+    /// the key for a value, or None if the value can not be a key. NaN is never equal to itself, so
+    /// it can not be a key either.
+    pub fn new(value: &Literal) -> Option<MapKey> {
+        match value {
+            Literal::Nil => Some(MapKey::Nil),
+            Literal::Boolean(boolean) => Some(MapKey::Boolean(*boolean)),
+            Literal::Number(number) if number.is_nan() => None,
+            // -0 and 0 are equal numbers, so they are the same key
+            Literal::Number(number) => Some(MapKey::Number(*number + 0.0)),
+            // a decimal is the same key as the number closest to it
+            Literal::Decimal(decimal) => Some(MapKey::Number(decimal.to_f64() + 0.0)),
+            Literal::String(string) => Some(MapKey::String(string.clone())),
+            _ => None,
+        }
+    }
+
+    pub fn to_literal(&self) -> Literal {
+        match self {
+            MapKey::Nil => Literal::Nil,
+            MapKey::Boolean(boolean) => Literal::Boolean(*boolean),
+            MapKey::Number(number) => Literal::Number(*number),
+            MapKey::String(string) => Literal::String(string.clone()),
+        }
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            MapKey::Nil => 0,
+            MapKey::Boolean(_) => 1,
+            MapKey::Number(_) => 2,
+            MapKey::String(_) => 3,
+        }
+    }
+}
+
+impl PartialEq for MapKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for MapKey {}
+
+impl Hash for MapKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.rank().hash(state);
+        match self {
+            MapKey::Nil => {}
+            MapKey::Boolean(boolean) => boolean.hash(state),
+            MapKey::Number(number) => number.to_bits().hash(state),
+            MapKey::String(string) => string.hash(state),
+        }
+    }
+}
+
+/// keys are ordered by their type first, nil, booleans, numbers and then strings
+impl Ord for MapKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (MapKey::Boolean(a), MapKey::Boolean(b)) => a.cmp(b),
+            (MapKey::Number(a), MapKey::Number(b)) => a.total_cmp(b),
+            (MapKey::String(a), MapKey::String(b)) => a.cmp(b),
+            (a, b) => a.rank().cmp(&b.rank()),
+        }
+    }
+}
+
+impl PartialOrd for MapKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// prints the key the way it is written in a program, quoting strings
+impl fmt::Display for MapKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapKey::String(string) => write!(f, "\"{}\"", string),
+            key => write!(f, "{}", key.to_literal()),
+        }
+    }
+}
+
+/// A map from keys to values, shared between every value holding it.
+#[derive(Debug, Clone, Default)]
+pub struct LoxMap(Rc<RefCell<HashMap<MapKey, Literal>>>);
+
+impl LoxMap {
+    pub fn new(entries: HashMap<MapKey, Literal>) -> Self {
+        LoxMap(Rc::new(RefCell::new(entries)))
+    }
+
+    pub fn get(&self, key: &MapKey) -> Option<Literal> {
+        self.0.borrow().get(key).cloned()
+    }
+
+    pub fn insert(&self, key: MapKey, value: Literal) {
+        self.0.borrow_mut().insert(key, value);
+    }
+
+    pub fn contains(&self, key: &MapKey) -> bool {
+        self.0.borrow().contains_key(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.borrow().is_empty()
+    }
+
+    /// the entries of the map, ordered by their keys
+    pub fn entries(&self) -> Vec<(MapKey, Literal)> {
+        let mut entries: Vec<_> = self
+            .0
+            .borrow()
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        entries
+    }
+
+    /// the address of the map, which identifies it among the maps holding themselves
+    pub fn as_ptr(&self) -> *const () {
+        Rc::as_ptr(&self.0).cast()
+    }
+
+    fn borrow(&self) -> Ref<'_, HashMap<MapKey, Literal>> {
+        self.0.borrow()
+    }
+}
+
+/// whether two maps hold equal values for the same keys. The pairs of maps already being compared
+/// are seen, so that maps holding themselves are not compared forever.
+fn maps_equal(a: &LoxMap, b: &LoxMap, seen: &mut Vec<(*const (), *const ())>) -> bool {
+    if Rc::ptr_eq(&a.0, &b.0) || seen.contains(&(a.as_ptr(), b.as_ptr())) {
+        return true;
+    }
+    seen.push((a.as_ptr(), b.as_ptr()));
+    let (a, b) = (a.borrow(), b.borrow());
+    let equal = a.len() == b.len()
+        && a.iter().all(|(key, a)| match (a, b.get(key)) {
+            (Literal::Map(a), Some(Literal::Map(b))) => maps_equal(a, b, seen),
+            (a, Some(b)) => a.is_equal(b),
+            (_, None) => false,
+        });
+    seen.pop();
+    equal
+}
+
+impl PartialEq for LoxMap {
+    fn eq(&self, other: &Self) -> bool {
+        maps_equal(self, other, &mut Vec::new())
+    }
+}
+
+impl PartialOrd for LoxMap {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        (self == other).then_some(Ordering::Equal)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    /// tests that keys compare by value and that maps compare by their entries
+    fn map_test() {
+        let key = |value: Literal| MapKey::new(&value).unwrap();
+        assert_eq!(key(Literal::Number(-0.0)), key(Literal::Number(0.0)));
+        assert_ne!(
+            key(Literal::Number(1.0)),
+            key(Literal::String("1".to_string()))
+        );
+        assert!(MapKey::new(&Literal::Number(f64::NAN)).is_none());
+        assert!(MapKey::new(&Literal::List(Default::default())).is_none());
+
+        let map = |entries: Vec<(MapKey, Literal)>| LoxMap::new(entries.into_iter().collect());
+        let a = map(vec![
+            (key(Literal::String("b".to_string())), Literal::Nil),
+            (key(Literal::Number(2.0)), Literal::Boolean(true)),
+            (MapKey::Nil, Literal::Number(1.0)),
+        ]);
+        let b = map(a.entries());
+        assert_eq!(a, b);
+        let keys: Vec<_> = a.entries().iter().map(|(key, _)| key.to_string()).collect();
+        assert_eq!(keys, ["nil", "2", "\"b\""]);
+
+        b.insert(MapKey::Nil, Literal::Number(2.0));
+        assert_ne!(a, b);
+
+        // maps holding themselves are equal when the rest of their entries are
+        let (c, d) = (LoxMap::default(), LoxMap::default());
+        c.insert(MapKey::Nil, Literal::Map(c.clone()));
+        d.insert(MapKey::Nil, Literal::Map(d.clone()));
+        assert_eq!(c, d);
+    }
+}
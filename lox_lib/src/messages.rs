@@ -0,0 +1,1141 @@
+//!
+//! This module contains the catalog of diagnostic messages reported by the interpreter.
+//!
+//! Every message has a stable [`MessageId`] whose code (e.g. `E0001`) never changes, so tooling can
+//! match on it, while the human readable text is looked up for the selected [`Language`].
+//!
+//! Codes starting with `E` are runtime errors, `S` syntax errors reported by the lexer and the
+//! parser and `L` lints. The errors raised by natives and plugins are not in the catalog, they are
+//! reported in English whatever the language.
+
+use anyhow::{anyhow, Result};
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// The environment variable used to pick the language of diagnostic messages, e.g. `LOX_LANG=es`.
+pub const LANG_ENV_VAR: &str = "LOX_LANG";
+
+/// The languages that diagnostic messages can be reported in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Language {
+    #[default]
+    English,
+    Spanish,
+}
+
+impl Language {
+    /// Parses a language code such as `en`, `es` or a locale like `es_MX.UTF-8`.
+    /// Returns None for languages without a catalog.
+    pub fn from_code(code: &str) -> Option<Self> {
+        let code = code.trim().to_lowercase();
+        let primary = code.split(['_', '-', '.']).next().unwrap_or_default();
+        match primary {
+            "en" => Some(Language::English),
+            "es" => Some(Language::Spanish),
+            _ => None,
+        }
+    }
+
+    /// Reads the language from the `LOX_LANG` environment variable, falling back to English when it
+    /// is unset or names an unsupported language.
+    pub fn from_env() -> Self {
+        std::env::var(LANG_ENV_VAR)
+            .ok()
+            .and_then(|code| Language::from_code(&code))
+            .unwrap_or_default()
+    }
+}
+
+/// The identifier of a diagnostic message. The id and its code are stable across languages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MessageId {
+    OperandsMustBeNumbers,
+    OperandsMustBeNumbersOrStrings,
+    AddNumberToNonNumber,
+    AddStringToNonString,
+    UnaryMinusOperand,
+    UnsupportedOperator,
+    UndefinedVariable,
+    NotCallable,
+    WrongArity,
+    StackOverflow,
+    UndefinedProperty,
+    OnlyInstancesHaveProperties,
+    OnlyInstancesHaveFields,
+    SuperclassMustBeAClass,
+    NotIndexable,
+    IndexMustBeAWholeNumber,
+    IndexOutOfBounds,
+    BigIntWithFraction,
+    BigIntDivisionByZero,
+    InvalidMapKey,
+    UndefinedKey,
+    NotSliceable,
+    SliceStartAfterEnd,
+    StringsAreImmutable,
+    BytesAreImmutable,
+    DateOutOfRange,
+    UncaughtException,
+    AssignToConstant,
+    ImportFailed,
+    CircularImport,
+    OutOfMemory,
+    TooFewArguments,
+    NotExported,
+    /// syntax errors reported by the lexer and the parser
+    UnmatchedEnd,
+    InvalidDirective,
+    UnterminatedIf,
+    UnterminatedBlockComment,
+    UnexpectedCharacter,
+    UnterminatedString,
+    InvalidByte,
+    InvalidBytesEscape,
+    UnterminatedBytes,
+    InvalidNumber,
+    AssignToCallProperty,
+    AssignToCallElement,
+    InvalidAssignmentTarget,
+    ExpectedIndexEnd,
+    ExpectedListEnd,
+    ExpectedMapColon,
+    ExpectedMapEnd,
+    ExpectedPropertyName,
+    TooManyArguments,
+    ExpectedArgumentsEnd,
+    ThisOutsideClass,
+    ThisInClassMethod,
+    ExpectedDotAfterSuper,
+    ExpectedSuperclassMethod,
+    SuperOutsideClass,
+    SuperWithoutSuperclass,
+    SuperInClassMethod,
+    ExpectedClassName,
+    ExpectedBodyStart,
+    ExpectedBodyEnd,
+    InheritFromItself,
+    ExpectedSuperclassName,
+    ExpectedMethodName,
+    ExpectedFunctionName,
+    ExpectedFunctionParameters,
+    ExpectedMethodParameters,
+    TooManyParameters,
+    ExpectedParameterName,
+    RestParameterNotLast,
+    ExpectedParametersEnd,
+    DuplicateParameter,
+    ExpectedIdentifier,
+    VariableNamedLikeDefine,
+    ExpectedVariableName,
+    ExpectedImportAlias,
+    ExpectedImportPath,
+    ExpectedSemicolonAfterImport,
+    ExpectedConstantName,
+    ConstantNamedLikeDefine,
+    UninitializedConstant,
+    ExpectedDefineName,
+    DefineValueNotLiteral,
+    DefineNotAtTopLevel,
+    Redefined,
+    ExpectedImportName,
+    ExpectedImportFrom,
+    ExportNotAtTopLevel,
+    ExpectedExportDeclaration,
+    RedeclaredConstant,
+    PrintWithArguments,
+    ExpectedStatement,
+    ExpectedSemicolonAfterExpression,
+    ExpectedParenAfterIf,
+    ExpectedParenAfterIfCondition,
+    ExpectedParenAfterWhile,
+    ExpectedParenAfterWhileCondition,
+    ExpectedWhileAfterDo,
+    ExpectedSemicolonAfterDo,
+    ExpectedParenAfterFor,
+    ExpectedSemicolonAfterLoopCondition,
+    ExpectedParenAfterForClauses,
+    BreakOutsideLoop,
+    ExpectedSemicolonAfterBreak,
+    ContinueOutsideLoop,
+    ExpectedSemicolonAfterContinue,
+    ReturnOutsideFunction,
+    ReturnValueFromInitializer,
+    ExpectedSemicolonAfterReturn,
+    ExpectedSemicolonAfterThrow,
+    ExpectedCatch,
+    ExpectedParenAfterCatch,
+    ExpectedExceptionName,
+    ExpectedParenAfterExceptionName,
+    ExpectedBlock,
+    UnclosedBlock,
+    NestedTooDeeply,
+    UnexpectedToken,
+    UnexpectedEnd,
+    InvalidBigInt,
+    BigIntUnsupported,
+    ExpectedGroupingEnd,
+    UnsupportedToken,
+    ExpectedExpression,
+    /// lints reported in pedantic mode for behavior which is not part of canonical Lox
+    StringOrdering,
+    SingleQuotedString,
+    ClassMethod,
+    StringConcatenation,
+    /// lints which `lox fix` can fix
+    UnusedVariable,
+    MissingSemicolon,
+    ConstantCondition,
+}
+
+/// How a lint is reported. Lints are allowed by default, unless pedantic mode warns about them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    /// reported as an error which stops the program like a runtime error does
+    Deny,
+}
+
+impl FromStr for LintLevel {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "allow" => Ok(LintLevel::Allow),
+            "warn" => Ok(LintLevel::Warn),
+            "deny" => Ok(LintLevel::Deny),
+            _ => Err(anyhow!(
+                "unknown lint level {}, expected allow, warn or deny",
+                s
+            )),
+        }
+    }
+}
+
+impl MessageId {
+    /// the lints, which are the messages whose codes start with `L`
+    pub const LINTS: [MessageId; 7] = [
+        MessageId::StringOrdering,
+        MessageId::SingleQuotedString,
+        MessageId::ClassMethod,
+        MessageId::UnusedVariable,
+        MessageId::MissingSemicolon,
+        MessageId::ConstantCondition,
+        MessageId::StringConcatenation,
+    ];
+
+    /// the lint with the given code, e.g. `L0002`
+    pub fn lint(code: &str) -> Option<MessageId> {
+        MessageId::LINTS.into_iter().find(|id| id.code() == code)
+    }
+
+    /// the stable code of the message which is included in every rendered diagnostic.
+    pub fn code(&self) -> &'static str {
+        match self {
+            MessageId::OperandsMustBeNumbers => "E0001",
+            MessageId::OperandsMustBeNumbersOrStrings => "E0002",
+            MessageId::AddNumberToNonNumber => "E0003",
+            MessageId::AddStringToNonString => "E0004",
+            MessageId::UnaryMinusOperand => "E0005",
+            MessageId::UnsupportedOperator => "E0006",
+            MessageId::UndefinedVariable => "E0007",
+            MessageId::NotCallable => "E0008",
+            MessageId::WrongArity => "E0009",
+            MessageId::StackOverflow => "E0010",
+            MessageId::UndefinedProperty => "E0011",
+            MessageId::OnlyInstancesHaveProperties => "E0012",
+            MessageId::OnlyInstancesHaveFields => "E0013",
+            MessageId::SuperclassMustBeAClass => "E0014",
+            MessageId::NotIndexable => "E0015",
+            MessageId::IndexMustBeAWholeNumber => "E0016",
+            MessageId::IndexOutOfBounds => "E0017",
+            MessageId::BigIntWithFraction => "E0018",
+            MessageId::BigIntDivisionByZero => "E0019",
+            MessageId::InvalidMapKey => "E0020",
+            MessageId::UndefinedKey => "E0021",
+            MessageId::NotSliceable => "E0022",
+            MessageId::SliceStartAfterEnd => "E0023",
+            MessageId::StringsAreImmutable => "E0024",
+            MessageId::BytesAreImmutable => "E0025",
+            MessageId::DateOutOfRange => "E0026",
+            MessageId::UncaughtException => "E0027",
+            MessageId::AssignToConstant => "E0028",
+            MessageId::ImportFailed => "E0029",
+            MessageId::CircularImport => "E0030",
+            MessageId::OutOfMemory => "E0031",
+            MessageId::TooFewArguments => "E0032",
+            MessageId::NotExported => "E0033",
+            MessageId::UnmatchedEnd => "S0001",
+            MessageId::InvalidDirective => "S0002",
+            MessageId::UnterminatedIf => "S0003",
+            MessageId::UnterminatedBlockComment => "S0004",
+            MessageId::UnexpectedCharacter => "S0005",
+            MessageId::UnterminatedString => "S0006",
+            MessageId::InvalidByte => "S0007",
+            MessageId::InvalidBytesEscape => "S0008",
+            MessageId::UnterminatedBytes => "S0009",
+            MessageId::InvalidNumber => "S0010",
+            MessageId::AssignToCallProperty => "S0011",
+            MessageId::AssignToCallElement => "S0012",
+            MessageId::InvalidAssignmentTarget => "S0013",
+            MessageId::ExpectedIndexEnd => "S0014",
+            MessageId::ExpectedListEnd => "S0015",
+            MessageId::ExpectedMapColon => "S0016",
+            MessageId::ExpectedMapEnd => "S0017",
+            MessageId::ExpectedPropertyName => "S0018",
+            MessageId::TooManyArguments => "S0019",
+            MessageId::ExpectedArgumentsEnd => "S0020",
+            MessageId::ThisOutsideClass => "S0021",
+            MessageId::ThisInClassMethod => "S0022",
+            MessageId::ExpectedDotAfterSuper => "S0023",
+            MessageId::ExpectedSuperclassMethod => "S0024",
+            MessageId::SuperOutsideClass => "S0025",
+            MessageId::SuperWithoutSuperclass => "S0026",
+            MessageId::SuperInClassMethod => "S0027",
+            MessageId::ExpectedClassName => "S0028",
+            MessageId::ExpectedBodyStart => "S0029",
+            MessageId::ExpectedBodyEnd => "S0030",
+            MessageId::InheritFromItself => "S0031",
+            MessageId::ExpectedSuperclassName => "S0032",
+            MessageId::ExpectedMethodName => "S0033",
+            MessageId::ExpectedFunctionName => "S0034",
+            MessageId::ExpectedFunctionParameters => "S0035",
+            MessageId::ExpectedMethodParameters => "S0036",
+            MessageId::TooManyParameters => "S0037",
+            MessageId::ExpectedParameterName => "S0038",
+            MessageId::RestParameterNotLast => "S0039",
+            MessageId::ExpectedParametersEnd => "S0040",
+            MessageId::DuplicateParameter => "S0041",
+            MessageId::ExpectedIdentifier => "S0042",
+            MessageId::VariableNamedLikeDefine => "S0043",
+            MessageId::ExpectedVariableName => "S0044",
+            MessageId::ExpectedImportAlias => "S0045",
+            MessageId::ExpectedImportPath => "S0046",
+            MessageId::ExpectedSemicolonAfterImport => "S0047",
+            MessageId::ExpectedConstantName => "S0048",
+            MessageId::ConstantNamedLikeDefine => "S0049",
+            MessageId::UninitializedConstant => "S0050",
+            MessageId::ExpectedDefineName => "S0051",
+            MessageId::DefineValueNotLiteral => "S0052",
+            MessageId::RedeclaredConstant => "S0053",
+            MessageId::PrintWithArguments => "S0056",
+            MessageId::ExpectedStatement => "S0057",
+            MessageId::ExpectedSemicolonAfterExpression => "S0058",
+            MessageId::ExpectedParenAfterIf => "S0059",
+            MessageId::ExpectedParenAfterIfCondition => "S0060",
+            MessageId::ExpectedParenAfterWhile => "S0061",
+            MessageId::ExpectedParenAfterWhileCondition => "S0062",
+            MessageId::ExpectedWhileAfterDo => "S0063",
+            MessageId::ExpectedSemicolonAfterDo => "S0064",
+            MessageId::ExpectedParenAfterFor => "S0065",
+            MessageId::ExpectedSemicolonAfterLoopCondition => "S0066",
+            MessageId::ExpectedParenAfterForClauses => "S0067",
+            MessageId::BreakOutsideLoop => "S0068",
+            MessageId::ExpectedSemicolonAfterBreak => "S0069",
+            MessageId::ContinueOutsideLoop => "S0070",
+            MessageId::ExpectedSemicolonAfterContinue => "S0071",
+            MessageId::ReturnOutsideFunction => "S0072",
+            MessageId::ReturnValueFromInitializer => "S0073",
+            MessageId::ExpectedSemicolonAfterReturn => "S0074",
+            MessageId::ExpectedSemicolonAfterThrow => "S0075",
+            MessageId::ExpectedCatch => "S0076",
+            MessageId::ExpectedParenAfterCatch => "S0077",
+            MessageId::ExpectedExceptionName => "S0078",
+            MessageId::ExpectedParenAfterExceptionName => "S0079",
+            MessageId::ExpectedBlock => "S0080",
+            MessageId::UnclosedBlock => "S0081",
+            MessageId::NestedTooDeeply => "S0082",
+            MessageId::UnexpectedToken => "S0083",
+            MessageId::UnexpectedEnd => "S0084",
+            MessageId::InvalidBigInt => "S0085",
+            MessageId::BigIntUnsupported => "S0086",
+            MessageId::ExpectedGroupingEnd => "S0087",
+            MessageId::UnsupportedToken => "S0088",
+            MessageId::ExpectedExpression => "S0089",
+            MessageId::DefineNotAtTopLevel => "S0090",
+            MessageId::Redefined => "S0091",
+            MessageId::ExpectedImportName => "S0092",
+            MessageId::ExpectedImportFrom => "S0093",
+            MessageId::ExportNotAtTopLevel => "S0094",
+            MessageId::ExpectedExportDeclaration => "S0095",
+            MessageId::StringOrdering => "L0001",
+            MessageId::SingleQuotedString => "L0002",
+            MessageId::ClassMethod => "L0003",
+            MessageId::UnusedVariable => "L0004",
+            MessageId::MissingSemicolon => "L0005",
+            MessageId::ConstantCondition => "L0006",
+            MessageId::StringConcatenation => "L0007",
+        }
+    }
+
+    /// the message template for the given language. Placeholders are written as `{0}`, `{1}`, ...
+    pub fn template(&self, language: Language) -> &'static str {
+        match (self, language) {
+            (MessageId::OperandsMustBeNumbers, Language::English) => "Operands must be two numbers",
+            (MessageId::OperandsMustBeNumbers, Language::Spanish) => {
+                "Los operandos deben ser dos números"
+            }
+            (MessageId::OperandsMustBeNumbersOrStrings, Language::English) => {
+                "Operands must be two numbers or two strings"
+            }
+            (MessageId::OperandsMustBeNumbersOrStrings, Language::Spanish) => {
+                "Los operandos deben ser dos números o dos cadenas"
+            }
+            (MessageId::AddNumberToNonNumber, Language::English) => {
+                "the left side number {0} operand is being added to non left number"
+            }
+            (MessageId::AddNumberToNonNumber, Language::Spanish) => {
+                "el número {0} del lado izquierdo se está sumando a un valor que no es un número"
+            }
+            (MessageId::AddStringToNonString, Language::English) => {
+                "the left side string {0} operand is being added to non left string"
+            }
+            (MessageId::AddStringToNonString, Language::Spanish) => {
+                "la cadena {0} del lado izquierdo se está sumando a un valor que no es una cadena"
+            }
+            (MessageId::UnaryMinusOperand, Language::English) => {
+                "Unary operator '-' can only be applied to numbers"
+            }
+            (MessageId::UnaryMinusOperand, Language::Spanish) => {
+                "El operador unario '-' solo se puede aplicar a números"
+            }
+            (MessageId::UnsupportedOperator, Language::English) => "Unsupported operator {0}",
+            (MessageId::UnsupportedOperator, Language::Spanish) => "Operador no soportado {0}",
+            (MessageId::UndefinedVariable, Language::English) => "Undefined variable '{0}'",
+            (MessageId::UndefinedVariable, Language::Spanish) => "Variable no definida '{0}'",
+            (MessageId::NotCallable, Language::English) => "Can only call functions and classes",
+            (MessageId::NotCallable, Language::Spanish) => {
+                "Solo se pueden llamar funciones y clases"
+            }
+            (MessageId::WrongArity, Language::English) => "Expected {0} arguments but got {1}",
+            (MessageId::WrongArity, Language::Spanish) => {
+                "Se esperaban {0} argumentos pero se recibieron {1}"
+            }
+            (MessageId::StackOverflow, Language::English) => "Stack overflow",
+            (MessageId::StackOverflow, Language::Spanish) => "Desbordamiento de pila",
+            (MessageId::UndefinedProperty, Language::English) => "Undefined property '{0}'",
+            (MessageId::UndefinedProperty, Language::Spanish) => "Propiedad no definida '{0}'",
+            (MessageId::OnlyInstancesHaveProperties, Language::English) => {
+                "Only instances have properties"
+            }
+            (MessageId::OnlyInstancesHaveProperties, Language::Spanish) => {
+                "Solo las instancias tienen propiedades"
+            }
+            (MessageId::SuperclassMustBeAClass, Language::English) => "Superclass must be a class",
+            (MessageId::SuperclassMustBeAClass, Language::Spanish) => {
+                "La superclase debe ser una clase"
+            }
+            (MessageId::OnlyInstancesHaveFields, Language::English) => "Only instances have fields",
+            (MessageId::OnlyInstancesHaveFields, Language::Spanish) => {
+                "Solo las instancias tienen campos"
+            }
+            (MessageId::NotIndexable, Language::English) => {
+                "Only lists, maps, strings and bytes can be indexed"
+            }
+            (MessageId::NotIndexable, Language::Spanish) => {
+                "Solo las listas, los mapas, las cadenas y los bytes se pueden indexar"
+            }
+            (MessageId::IndexMustBeAWholeNumber, Language::English) => {
+                "An index must be a whole number, got {0}"
+            }
+            (MessageId::IndexMustBeAWholeNumber, Language::Spanish) => {
+                "Un índice debe ser un número entero, se obtuvo {0}"
+            }
+            (MessageId::IndexOutOfBounds, Language::English) => {
+                "Index {0} is out of bounds for length {1}"
+            }
+            (MessageId::IndexOutOfBounds, Language::Spanish) => {
+                "El índice {0} está fuera de los límites para la longitud {1}"
+            }
+            (MessageId::NotSliceable, Language::English) => {
+                "Only lists, strings and bytes can be sliced"
+            }
+            (MessageId::NotSliceable, Language::Spanish) => {
+                "Solo las listas, las cadenas y los bytes se pueden recortar"
+            }
+            (MessageId::SliceStartAfterEnd, Language::English) => {
+                "The start {0} of a slice is after its end {1}"
+            }
+            (MessageId::SliceStartAfterEnd, Language::Spanish) => {
+                "El inicio {0} de un recorte está después de su final {1}"
+            }
+            (MessageId::StringsAreImmutable, Language::English) => {
+                "Strings can not be changed, build a new string instead"
+            }
+            (MessageId::StringsAreImmutable, Language::Spanish) => {
+                "Las cadenas no se pueden cambiar, construye una cadena nueva"
+            }
+            (MessageId::BytesAreImmutable, Language::English) => {
+                "Bytes can not be changed, build new bytes instead"
+            }
+            (MessageId::BytesAreImmutable, Language::Spanish) => {
+                "Los bytes no se pueden cambiar, construye bytes nuevos"
+            }
+            (MessageId::DateOutOfRange, Language::English) => {
+                "The date {0} seconds from 1970 is out of the range of dates"
+            }
+            (MessageId::DateOutOfRange, Language::Spanish) => {
+                "La fecha a {0} segundos de 1970 está fuera del rango de fechas"
+            }
+            (MessageId::UncaughtException, Language::English) => "Uncaught exception {0}",
+            (MessageId::UncaughtException, Language::Spanish) => "Excepción no capturada {0}",
+            (MessageId::AssignToConstant, Language::English) => {
+                "Can not assign to {0}, since it is a constant"
+            }
+            (MessageId::AssignToConstant, Language::Spanish) => {
+                "No se puede asignar a {0}, ya que es una constante"
+            }
+            (MessageId::ImportFailed, Language::English) => "Can not import {0}: {1}",
+            (MessageId::ImportFailed, Language::Spanish) => "No se puede importar {0}: {1}",
+            (MessageId::CircularImport, Language::English) => "Circular import of {0}: {1}",
+            (MessageId::CircularImport, Language::Spanish) => "Importación circular de {0}: {1}",
+            (MessageId::OutOfMemory, Language::English) => {
+                "Out of memory, the values held take more than the limit of {0} bytes"
+            }
+            (MessageId::OutOfMemory, Language::Spanish) => {
+                "Sin memoria, los valores retenidos ocupan más que el límite de {0} bytes"
+            }
+            (MessageId::TooFewArguments, Language::English) => {
+                "Expected at least {0} arguments but got {1}"
+            }
+            (MessageId::TooFewArguments, Language::Spanish) => {
+                "Se esperaban al menos {0} argumentos pero se recibieron {1}"
+            }
+            (MessageId::NotExported, Language::English) => {
+                "Can not import {0} from {1}, since the module does not export it"
+            }
+            (MessageId::NotExported, Language::Spanish) => {
+                "No se puede importar {0} de {1}, ya que el módulo no lo exporta"
+            }
+            (MessageId::BigIntWithFraction, Language::English) => {
+                "Big integers can only be combined with whole numbers, got {0}"
+            }
+            (MessageId::BigIntWithFraction, Language::Spanish) => {
+                "Los enteros grandes solo se pueden combinar con números enteros, se obtuvo {0}"
+            }
+            (MessageId::BigIntDivisionByZero, Language::English) => {
+                "Division of a big integer by zero"
+            }
+            (MessageId::BigIntDivisionByZero, Language::Spanish) => {
+                "División de un entero grande por cero"
+            }
+            (MessageId::InvalidMapKey, Language::English) => {
+                "Only numbers, strings, booleans and nil can be map keys, got {0}"
+            }
+            (MessageId::InvalidMapKey, Language::Spanish) => {
+                "Solo los números, las cadenas, los booleanos y nil pueden ser claves de un mapa, se obtuvo {0}"
+            }
+            (MessageId::UndefinedKey, Language::English) => "Undefined key {0}",
+            (MessageId::UndefinedKey, Language::Spanish) => "Clave indefinida {0}",
+            (MessageId::StringOrdering, Language::English) => {
+                "canonical Lox can only compare numbers with '{0}', strings can only be compared with '==' and '!='"
+            }
+            (MessageId::StringOrdering, Language::Spanish) => {
+                "Lox canónico solo puede comparar números con '{0}', las cadenas solo se pueden comparar con '==' y '!='"
+            }
+            (MessageId::SingleQuotedString, Language::English) => {
+                "canonical Lox has no single quoted strings, write {0} with double quotes instead"
+            }
+            (MessageId::SingleQuotedString, Language::Spanish) => {
+                "Lox canónico no tiene cadenas con comillas simples, escriba {0} con comillas dobles"
+            }
+            (MessageId::ClassMethod, Language::English) => {
+                "canonical Lox has no class methods, declare {0} as a function outside of the class instead"
+            }
+            (MessageId::ClassMethod, Language::Spanish) => {
+                "Lox canónico no tiene métodos de clase, declare {0} como una función fuera de la clase"
+            }
+            (MessageId::UnusedVariable, Language::English) => {
+                "the local variable '{0}' is never used"
+            }
+            (MessageId::UnusedVariable, Language::Spanish) => {
+                "la variable local '{0}' nunca se usa"
+            }
+            (MessageId::MissingSemicolon, Language::English) => "missing ';' after '{0}'",
+            (MessageId::MissingSemicolon, Language::Spanish) => "falta ';' después de '{0}'",
+            (MessageId::ConstantCondition, Language::English) => {
+                "the condition assigns {1} to '{0}' and so is always the same, compare them with '==' instead"
+            }
+            (MessageId::ConstantCondition, Language::Spanish) => {
+                "la condición asigna {1} a '{0}' y por eso siempre es igual, compárelos con '==' en su lugar"
+            }
+            (MessageId::StringConcatenation, Language::English) => {
+                "canonical Lox can only add a string to another string, {0} is not one"
+            }
+            (MessageId::StringConcatenation, Language::Spanish) => {
+                "Lox canónico solo puede sumar una cadena a otra cadena, {0} no lo es"
+            }
+            (MessageId::UnmatchedEnd, Language::English) => "#end without a matching #if",
+            (MessageId::UnmatchedEnd, Language::Spanish) => "#end sin un #if correspondiente",
+            (MessageId::InvalidDirective, Language::English) => "invalid directive {0}",
+            (MessageId::InvalidDirective, Language::Spanish) => "directiva no válida {0}",
+            (MessageId::UnterminatedIf, Language::English) => "Unterminated #if",
+            (MessageId::UnterminatedIf, Language::Spanish) => "#if sin terminar",
+            (MessageId::UnterminatedBlockComment, Language::English) => {
+                "Unterminated block comment"
+            }
+            (MessageId::UnterminatedBlockComment, Language::Spanish) => {
+                "Comentario de bloque sin terminar"
+            }
+            (MessageId::UnexpectedCharacter, Language::English) => "unexpected character! {0}",
+            (MessageId::UnexpectedCharacter, Language::Spanish) => "¡carácter inesperado! {0}",
+            (MessageId::UnterminatedString, Language::English) => "Unterminated string literal {0}",
+            (MessageId::UnterminatedString, Language::Spanish) => {
+                "Literal de cadena sin terminar {0}"
+            }
+            (MessageId::InvalidByte, Language::English) => "Invalid byte \\x{0} in bytes literal",
+            (MessageId::InvalidByte, Language::Spanish) => {
+                "Byte no válido \\x{0} en el literal de bytes"
+            }
+            (MessageId::InvalidBytesEscape, Language::English) => {
+                "Invalid escape \\{0} in bytes literal, expected \\xNN or \\\\"
+            }
+            (MessageId::InvalidBytesEscape, Language::Spanish) => {
+                "Escape no válido \\{0} en el literal de bytes, se esperaba \\xNN o \\\\"
+            }
+            (MessageId::UnterminatedBytes, Language::English) => "Unterminated bytes literal {0}",
+            (MessageId::UnterminatedBytes, Language::Spanish) => {
+                "Literal de bytes sin terminar {0}"
+            }
+            (MessageId::InvalidNumber, Language::English) => "Invalid number literal {0}",
+            (MessageId::InvalidNumber, Language::Spanish) => "Literal numérico no válido {0}",
+            (MessageId::AssignToCallProperty, Language::English) => {
+                "Can not use '{0}' on a property of an object created by a call in line {1}"
+            }
+            (MessageId::AssignToCallProperty, Language::Spanish) => {
+                "No se puede usar '{0}' en una propiedad de un objeto creado por una llamada en la línea {1}"
+            }
+            (MessageId::AssignToCallElement, Language::English) => {
+                "Can not use '{0}' on an element whose list or index contains a call in line {1}"
+            }
+            (MessageId::AssignToCallElement, Language::Spanish) => {
+                "No se puede usar '{0}' en un elemento cuya lista o índice contiene una llamada en la línea {1}"
+            }
+            (MessageId::InvalidAssignmentTarget, Language::English) => {
+                "Invalid assignment target in line {0}"
+            }
+            (MessageId::InvalidAssignmentTarget, Language::Spanish) => {
+                "Destino de asignación no válido en la línea {0}"
+            }
+            (MessageId::ExpectedIndexEnd, Language::English) => {
+                "Expected ']' after the index in line {0}"
+            }
+            (MessageId::ExpectedIndexEnd, Language::Spanish) => {
+                "Se esperaba ']' después del índice en la línea {0}"
+            }
+            (MessageId::ExpectedListEnd, Language::English) => {
+                "Expected ']' after the elements of the list in line {0}"
+            }
+            (MessageId::ExpectedListEnd, Language::Spanish) => {
+                "Se esperaba ']' después de los elementos de la lista en la línea {0}"
+            }
+            (MessageId::ExpectedMapColon, Language::English) => {
+                "Expected ':' after the key of a map entry in line {0}"
+            }
+            (MessageId::ExpectedMapColon, Language::Spanish) => {
+                "Se esperaba ':' después de la clave de una entrada del mapa en la línea {0}"
+            }
+            (MessageId::ExpectedMapEnd, Language::English) => {
+                "Expected '}' after the entries of the map in line {0}"
+            }
+            (MessageId::ExpectedMapEnd, Language::Spanish) => {
+                "Se esperaba '}' después de las entradas del mapa en la línea {0}"
+            }
+            (MessageId::ExpectedPropertyName, Language::English) => {
+                "Expected a property name after '.' in line {0}"
+            }
+            (MessageId::ExpectedPropertyName, Language::Spanish) => {
+                "Se esperaba un nombre de propiedad después de '.' en la línea {0}"
+            }
+            (MessageId::TooManyArguments, Language::English) => {
+                "Can not have more than {0} arguments in line {1}"
+            }
+            (MessageId::TooManyArguments, Language::Spanish) => {
+                "No se pueden tener más de {0} argumentos en la línea {1}"
+            }
+            (MessageId::ExpectedArgumentsEnd, Language::English) => {
+                "Expected ')' after the arguments in line {0}"
+            }
+            (MessageId::ExpectedArgumentsEnd, Language::Spanish) => {
+                "Se esperaba ')' después de los argumentos en la línea {0}"
+            }
+            (MessageId::ThisOutsideClass, Language::English) => {
+                "Can not use 'this' outside of a class in line {0}"
+            }
+            (MessageId::ThisOutsideClass, Language::Spanish) => {
+                "No se puede usar 'this' fuera de una clase en la línea {0}"
+            }
+            (MessageId::ThisInClassMethod, Language::English) => {
+                "Can not use 'this' in a class method in line {0}"
+            }
+            (MessageId::ThisInClassMethod, Language::Spanish) => {
+                "No se puede usar 'this' en un método de clase en la línea {0}"
+            }
+            (MessageId::ExpectedDotAfterSuper, Language::English) => {
+                "Expected '.' after 'super' in line {0}"
+            }
+            (MessageId::ExpectedDotAfterSuper, Language::Spanish) => {
+                "Se esperaba '.' después de 'super' en la línea {0}"
+            }
+            (MessageId::ExpectedSuperclassMethod, Language::English) => {
+                "Expected a superclass method name after 'super.' in line {0}"
+            }
+            (MessageId::ExpectedSuperclassMethod, Language::Spanish) => {
+                "Se esperaba el nombre de un método de la superclase después de 'super.' en la línea {0}"
+            }
+            (MessageId::SuperOutsideClass, Language::English) => {
+                "Can not use 'super' outside of a class in line {0}"
+            }
+            (MessageId::SuperOutsideClass, Language::Spanish) => {
+                "No se puede usar 'super' fuera de una clase en la línea {0}"
+            }
+            (MessageId::SuperWithoutSuperclass, Language::English) => {
+                "Can not use 'super' in a class with no superclass in line {0}"
+            }
+            (MessageId::SuperWithoutSuperclass, Language::Spanish) => {
+                "No se puede usar 'super' en una clase sin superclase en la línea {0}"
+            }
+            (MessageId::SuperInClassMethod, Language::English) => {
+                "Can not use 'super' in a class method in line {0}"
+            }
+            (MessageId::SuperInClassMethod, Language::Spanish) => {
+                "No se puede usar 'super' en un método de clase en la línea {0}"
+            }
+            (MessageId::ExpectedClassName, Language::English) => {
+                "Expected a class name after 'class' in line {0}"
+            }
+            (MessageId::ExpectedClassName, Language::Spanish) => {
+                "Se esperaba un nombre de clase después de 'class' en la línea {0}"
+            }
+            (MessageId::ExpectedBodyStart, Language::English) => {
+                "Expected '{' before the body of {0} in line {1}"
+            }
+            (MessageId::ExpectedBodyStart, Language::Spanish) => {
+                "Se esperaba '{' antes del cuerpo de {0} en la línea {1}"
+            }
+            (MessageId::ExpectedBodyEnd, Language::English) => {
+                "Expected '}' after the body of {0} in line {1}"
+            }
+            (MessageId::ExpectedBodyEnd, Language::Spanish) => {
+                "Se esperaba '}' después del cuerpo de {0} en la línea {1}"
+            }
+            (MessageId::InheritFromItself, Language::English) => {
+                "A class can not inherit from itself in line {0}"
+            }
+            (MessageId::InheritFromItself, Language::Spanish) => {
+                "Una clase no puede heredar de sí misma en la línea {0}"
+            }
+            (MessageId::ExpectedSuperclassName, Language::English) => {
+                "Expected a superclass name after '<' in line {0}"
+            }
+            (MessageId::ExpectedSuperclassName, Language::Spanish) => {
+                "Se esperaba el nombre de una superclase después de '<' en la línea {0}"
+            }
+            (MessageId::ExpectedMethodName, Language::English) => {
+                "Expected a method name in line {0}"
+            }
+            (MessageId::ExpectedMethodName, Language::Spanish) => {
+                "Se esperaba un nombre de método en la línea {0}"
+            }
+            (MessageId::ExpectedFunctionName, Language::English) => {
+                "Expected a function name after 'fun' in line {0}"
+            }
+            (MessageId::ExpectedFunctionName, Language::Spanish) => {
+                "Se esperaba un nombre de función después de 'fun' en la línea {0}"
+            }
+            (MessageId::ExpectedFunctionParameters, Language::English) => {
+                "Expected '(' after the function name in line {0}"
+            }
+            (MessageId::ExpectedFunctionParameters, Language::Spanish) => {
+                "Se esperaba '(' después del nombre de la función en la línea {0}"
+            }
+            (MessageId::ExpectedMethodParameters, Language::English) => {
+                "Expected '(' after the method name in line {0}"
+            }
+            (MessageId::ExpectedMethodParameters, Language::Spanish) => {
+                "Se esperaba '(' después del nombre del método en la línea {0}"
+            }
+            (MessageId::TooManyParameters, Language::English) => {
+                "Can not have more than {0} parameters in line {1}"
+            }
+            (MessageId::TooManyParameters, Language::Spanish) => {
+                "No se pueden tener más de {0} parámetros en la línea {1}"
+            }
+            (MessageId::ExpectedParameterName, Language::English) => {
+                "Expected a parameter name in line {0}"
+            }
+            (MessageId::ExpectedParameterName, Language::Spanish) => {
+                "Se esperaba un nombre de parámetro en la línea {0}"
+            }
+            (MessageId::RestParameterNotLast, Language::English) => {
+                "Expected ')' after the rest parameter {0} in line {1}, it must be the last parameter"
+            }
+            (MessageId::RestParameterNotLast, Language::Spanish) => {
+                "Se esperaba ')' después del parámetro resto {0} en la línea {1}, debe ser el último parámetro"
+            }
+            (MessageId::ExpectedParametersEnd, Language::English) => {
+                "Expected ')' after the parameters in line {0}"
+            }
+            (MessageId::ExpectedParametersEnd, Language::Spanish) => {
+                "Se esperaba ')' después de los parámetros en la línea {0}"
+            }
+            (MessageId::DuplicateParameter, Language::English) => {
+                "Duplicate parameter {0} in line {1}"
+            }
+            (MessageId::DuplicateParameter, Language::Spanish) => {
+                "Parámetro duplicado {0} en la línea {1}"
+            }
+            (MessageId::ExpectedIdentifier, Language::English) => "Expected an identifier",
+            (MessageId::ExpectedIdentifier, Language::Spanish) => "Se esperaba un identificador",
+            (MessageId::VariableNamedLikeDefine, Language::English) => {
+                "Can not declare a variable named {0} since it is already a define in line {1}"
+            }
+            (MessageId::VariableNamedLikeDefine, Language::Spanish) => {
+                "No se puede declarar una variable llamada {0} porque ya es un define en la línea {1}"
+            }
+            (MessageId::ExpectedVariableName, Language::English) => {
+                "Expected a variable name after 'var' in line {0}"
+            }
+            (MessageId::ExpectedVariableName, Language::Spanish) => {
+                "Se esperaba un nombre de variable después de 'var' en la línea {0}"
+            }
+            (MessageId::ExpectedImportAlias, Language::English) => {
+                "Expected a name after 'as' in line {0}"
+            }
+            (MessageId::ExpectedImportAlias, Language::Spanish) => {
+                "Se esperaba un nombre después de 'as' en la línea {0}"
+            }
+            (MessageId::ExpectedImportPath, Language::English) => {
+                "Expected a path or a module name after 'import' in line {0}"
+            }
+            (MessageId::ExpectedImportPath, Language::Spanish) => {
+                "Se esperaba una ruta o un nombre de módulo después de 'import' en la línea {0}"
+            }
+            (MessageId::ExpectedSemicolonAfterImport, Language::English) => {
+                "Expected ';' after the import in line {0}"
+            }
+            (MessageId::ExpectedSemicolonAfterImport, Language::Spanish) => {
+                "Se esperaba ';' después del import en la línea {0}"
+            }
+            (MessageId::ExpectedConstantName, Language::English) => {
+                "Expected a constant name after 'const' in line {0}"
+            }
+            (MessageId::ExpectedConstantName, Language::Spanish) => {
+                "Se esperaba un nombre de constante después de 'const' en la línea {0}"
+            }
+            (MessageId::ConstantNamedLikeDefine, Language::English) => {
+                "Can not declare a constant named {0} since it is already a define in line {1}"
+            }
+            (MessageId::ConstantNamedLikeDefine, Language::Spanish) => {
+                "No se puede declarar una constante llamada {0} porque ya es un define en la línea {1}"
+            }
+            (MessageId::UninitializedConstant, Language::English) => {
+                "Expected '=' after the constant {0} in line {1}, constants must be initialized"
+            }
+            (MessageId::UninitializedConstant, Language::Spanish) => {
+                "Se esperaba '=' después de la constante {0} en la línea {1}, las constantes deben inicializarse"
+            }
+            (MessageId::ExpectedDefineName, Language::English) => {
+                "Expected a name after 'define' in line {0}"
+            }
+            (MessageId::ExpectedDefineName, Language::Spanish) => {
+                "Se esperaba un nombre después de 'define' en la línea {0}"
+            }
+            (MessageId::DefineValueNotLiteral, Language::English) => {
+                "The value of define {0} must be a constant literal in line {1}"
+            }
+            (MessageId::DefineValueNotLiteral, Language::Spanish) => {
+                "El valor del define {0} debe ser un literal constante en la línea {1}"
+            }
+            (MessageId::DefineNotAtTopLevel, Language::English) => {
+                "Can not define {0} in line {1}, defines are only allowed at the top level"
+            }
+            (MessageId::DefineNotAtTopLevel, Language::Spanish) => {
+                "No se puede definir {0} en la línea {1}, los defines solo se permiten en el nivel superior"
+            }
+            (MessageId::Redefined, Language::English) => {
+                "Can not define {0} again in line {1}, it is already defined"
+            }
+            (MessageId::Redefined, Language::Spanish) => {
+                "No se puede definir {0} otra vez en la línea {1}, ya está definido"
+            }
+            (MessageId::ExpectedImportName, Language::English) => {
+                "Expected the names to import between '{' and '}' in line {0}"
+            }
+            (MessageId::ExpectedImportName, Language::Spanish) => {
+                "Se esperaban los nombres a importar entre '{' y '}' en la línea {0}"
+            }
+            (MessageId::ExpectedImportFrom, Language::English) => {
+                "Expected 'from' and a path after the names to import in line {0}"
+            }
+            (MessageId::ExpectedImportFrom, Language::Spanish) => {
+                "Se esperaba 'from' y una ruta después de los nombres a importar en la línea {0}"
+            }
+            (MessageId::ExportNotAtTopLevel, Language::English) => {
+                "Can not export in line {0}, exports are only allowed at the top level"
+            }
+            (MessageId::ExportNotAtTopLevel, Language::Spanish) => {
+                "No se puede exportar en la línea {0}, las exportaciones solo se permiten en el nivel superior"
+            }
+            (MessageId::ExpectedExportDeclaration, Language::English) => {
+                "Expected a declaration or an import with names after 'export' in line {0}"
+            }
+            (MessageId::ExpectedExportDeclaration, Language::Spanish) => {
+                "Se esperaba una declaración o un import con nombres después de 'export' en la línea {0}"
+            }
+            (MessageId::RedeclaredConstant, Language::English) => {
+                "Can not declare {0} again, since it is a constant declared in the same scope in line {1}"
+            }
+            (MessageId::RedeclaredConstant, Language::Spanish) => {
+                "No se puede declarar {0} de nuevo, ya que es una constante declarada en el mismo ámbito en la línea {1}"
+            }
+            (MessageId::PrintWithArguments, Language::English) => {
+                "print is a statement that takes a single expression, not a list of arguments (line {0})"
+            }
+            (MessageId::PrintWithArguments, Language::Spanish) => {
+                "print es una sentencia que recibe una sola expresión, no una lista de argumentos (línea {0})"
+            }
+            (MessageId::ExpectedStatement, Language::English) => {
+                "Expected a statement but found no tokens"
+            }
+            (MessageId::ExpectedStatement, Language::Spanish) => {
+                "Se esperaba una sentencia pero no se encontraron tokens"
+            }
+            (MessageId::ExpectedSemicolonAfterExpression, Language::English) => {
+                "Expected ';' after an expression"
+            }
+            (MessageId::ExpectedSemicolonAfterExpression, Language::Spanish) => {
+                "Se esperaba ';' después de una expresión"
+            }
+            (MessageId::ExpectedParenAfterIf, Language::English) => {
+                "Expected '(' after 'if' in line {0}"
+            }
+            (MessageId::ExpectedParenAfterIf, Language::Spanish) => {
+                "Se esperaba '(' después de 'if' en la línea {0}"
+            }
+            (MessageId::ExpectedParenAfterIfCondition, Language::English) => {
+                "Expected ')' after the if condition in line {0}"
+            }
+            (MessageId::ExpectedParenAfterIfCondition, Language::Spanish) => {
+                "Se esperaba ')' después de la condición del if en la línea {0}"
+            }
+            (MessageId::ExpectedParenAfterWhile, Language::English) => {
+                "Expected '(' after 'while' in line {0}"
+            }
+            (MessageId::ExpectedParenAfterWhile, Language::Spanish) => {
+                "Se esperaba '(' después de 'while' en la línea {0}"
+            }
+            (MessageId::ExpectedParenAfterWhileCondition, Language::English) => {
+                "Expected ')' after the while condition in line {0}"
+            }
+            (MessageId::ExpectedParenAfterWhileCondition, Language::Spanish) => {
+                "Se esperaba ')' después de la condición del while en la línea {0}"
+            }
+            (MessageId::ExpectedWhileAfterDo, Language::English) => {
+                "Expected 'while' after the body of the do loop in line {0}"
+            }
+            (MessageId::ExpectedWhileAfterDo, Language::Spanish) => {
+                "Se esperaba 'while' después del cuerpo del bucle do en la línea {0}"
+            }
+            (MessageId::ExpectedSemicolonAfterDo, Language::English) => {
+                "Expected ';' after the do loop in line {0}"
+            }
+            (MessageId::ExpectedSemicolonAfterDo, Language::Spanish) => {
+                "Se esperaba ';' después del bucle do en la línea {0}"
+            }
+            (MessageId::ExpectedParenAfterFor, Language::English) => {
+                "Expected '(' after 'for' in line {0}"
+            }
+            (MessageId::ExpectedParenAfterFor, Language::Spanish) => {
+                "Se esperaba '(' después de 'for' en la línea {0}"
+            }
+            (MessageId::ExpectedSemicolonAfterLoopCondition, Language::English) => {
+                "Expected ';' after the loop condition in line {0}"
+            }
+            (MessageId::ExpectedSemicolonAfterLoopCondition, Language::Spanish) => {
+                "Se esperaba ';' después de la condición del bucle en la línea {0}"
+            }
+            (MessageId::ExpectedParenAfterForClauses, Language::English) => {
+                "Expected ')' after the for clauses in line {0}"
+            }
+            (MessageId::ExpectedParenAfterForClauses, Language::Spanish) => {
+                "Se esperaba ')' después de las cláusulas del for en la línea {0}"
+            }
+            (MessageId::BreakOutsideLoop, Language::English) => {
+                "Can not use 'break' outside of a loop in line {0}"
+            }
+            (MessageId::BreakOutsideLoop, Language::Spanish) => {
+                "No se puede usar 'break' fuera de un bucle en la línea {0}"
+            }
+            (MessageId::ExpectedSemicolonAfterBreak, Language::English) => {
+                "Expected ';' after 'break' in line {0}"
+            }
+            (MessageId::ExpectedSemicolonAfterBreak, Language::Spanish) => {
+                "Se esperaba ';' después de 'break' en la línea {0}"
+            }
+            (MessageId::ContinueOutsideLoop, Language::English) => {
+                "Can not use 'continue' outside of a loop in line {0}"
+            }
+            (MessageId::ContinueOutsideLoop, Language::Spanish) => {
+                "No se puede usar 'continue' fuera de un bucle en la línea {0}"
+            }
+            (MessageId::ExpectedSemicolonAfterContinue, Language::English) => {
+                "Expected ';' after 'continue' in line {0}"
+            }
+            (MessageId::ExpectedSemicolonAfterContinue, Language::Spanish) => {
+                "Se esperaba ';' después de 'continue' en la línea {0}"
+            }
+            (MessageId::ReturnOutsideFunction, Language::English) => {
+                "Can not return from top-level code in line {0}"
+            }
+            (MessageId::ReturnOutsideFunction, Language::Spanish) => {
+                "No se puede retornar desde el código de nivel superior en la línea {0}"
+            }
+            (MessageId::ReturnValueFromInitializer, Language::English) => {
+                "Can not return a value from an initializer in line {0}"
+            }
+            (MessageId::ReturnValueFromInitializer, Language::Spanish) => {
+                "No se puede retornar un valor desde un inicializador en la línea {0}"
+            }
+            (MessageId::ExpectedSemicolonAfterReturn, Language::English) => {
+                "Expected ';' after the return value in line {0}"
+            }
+            (MessageId::ExpectedSemicolonAfterReturn, Language::Spanish) => {
+                "Se esperaba ';' después del valor retornado en la línea {0}"
+            }
+            (MessageId::ExpectedSemicolonAfterThrow, Language::English) => {
+                "Expected ';' after the thrown value in line {0}"
+            }
+            (MessageId::ExpectedSemicolonAfterThrow, Language::Spanish) => {
+                "Se esperaba ';' después del valor lanzado en la línea {0}"
+            }
+            (MessageId::ExpectedCatch, Language::English) => {
+                "Expected 'catch' after the try block in line {0}"
+            }
+            (MessageId::ExpectedCatch, Language::Spanish) => {
+                "Se esperaba 'catch' después del bloque try en la línea {0}"
+            }
+            (MessageId::ExpectedParenAfterCatch, Language::English) => {
+                "Expected '(' after 'catch' in line {0}"
+            }
+            (MessageId::ExpectedParenAfterCatch, Language::Spanish) => {
+                "Se esperaba '(' después de 'catch' en la línea {0}"
+            }
+            (MessageId::ExpectedExceptionName, Language::English) => {
+                "Expected the name of the exception after 'catch' in line {0}"
+            }
+            (MessageId::ExpectedExceptionName, Language::Spanish) => {
+                "Se esperaba el nombre de la excepción después de 'catch' en la línea {0}"
+            }
+            (MessageId::ExpectedParenAfterExceptionName, Language::English) => {
+                "Expected ')' after the name of the exception in line {0}"
+            }
+            (MessageId::ExpectedParenAfterExceptionName, Language::Spanish) => {
+                "Se esperaba ')' después del nombre de la excepción en la línea {0}"
+            }
+            (MessageId::ExpectedBlock, Language::English) => "Expected '{' after '{0}' in line {1}",
+            (MessageId::ExpectedBlock, Language::Spanish) => {
+                "Se esperaba '{' después de '{0}' en la línea {1}"
+            }
+            (MessageId::UnclosedBlock, Language::English) => {
+                "Expected '}' to close the block starting in line {0}"
+            }
+            (MessageId::UnclosedBlock, Language::Spanish) => {
+                "Se esperaba '}' para cerrar el bloque que empieza en la línea {0}"
+            }
+            (MessageId::NestedTooDeeply, Language::English) => {
+                "Code is nested too deeply, at most {0} levels are allowed in line {1}"
+            }
+            (MessageId::NestedTooDeeply, Language::Spanish) => {
+                "El código está anidado demasiado, se permiten como máximo {0} niveles en la línea {1}"
+            }
+            (MessageId::UnexpectedToken, Language::English) => {
+                "Expected {0} in line {1} but found other expression"
+            }
+            (MessageId::UnexpectedToken, Language::Spanish) => {
+                "Se esperaba {0} en la línea {1} pero se encontró otra expresión"
+            }
+            (MessageId::UnexpectedEnd, Language::English) => {
+                "Expected {0} but reached the end of the token stream"
+            }
+            (MessageId::UnexpectedEnd, Language::Spanish) => {
+                "Se esperaba {0} pero se alcanzó el final de los tokens"
+            }
+            (MessageId::InvalidBigInt, Language::English) => "Invalid big integer {0}n in line {1}",
+            (MessageId::InvalidBigInt, Language::Spanish) => {
+                "Entero grande no válido {0}n en la línea {1}"
+            }
+            (MessageId::BigIntUnsupported, Language::English) => {
+                "Big integers like {0}n need lox to be built with the bigint feature in line {1}"
+            }
+            (MessageId::BigIntUnsupported, Language::Spanish) => {
+                "Los enteros grandes como {0}n necesitan que lox se compile con la característica bigint en la línea {1}"
+            }
+            (MessageId::ExpectedGroupingEnd, Language::English) => "Expected ')' after expression",
+            (MessageId::ExpectedGroupingEnd, Language::Spanish) => {
+                "Se esperaba ')' después de la expresión"
+            }
+            (MessageId::UnsupportedToken, Language::English) => {
+                "unsupported token {0} in expression"
+            }
+            (MessageId::UnsupportedToken, Language::Spanish) => {
+                "token no soportado {0} en la expresión"
+            }
+            (MessageId::ExpectedExpression, Language::English) => {
+                "Expected an expression but reached the end of the token stream"
+            }
+            (MessageId::ExpectedExpression, Language::Spanish) => {
+                "Se esperaba una expresión pero se alcanzó el final de los tokens"
+            }
+        }
+    }
+
+    /// renders the message in the given language, replacing the `{n}` placeholders with `args[n]`.
+    pub fn render(&self, language: Language, args: &[&str]) -> String {
+        format!("[{}] {}", self.code(), self.message(language, args))
+    }
+
+    /// the template of the message with the arguments filled in, without the code in front of it
+    pub fn message(&self, language: Language, args: &[&str]) -> String {
+        args.iter()
+            .enumerate()
+            .fold(self.template(language).to_string(), |text, (i, arg)| {
+                text.replace(&format!("{{{}}}", i), arg)
+            })
+    }
+
+    /// renders the message like [`MessageId::render`], from arguments of any type that can be
+    /// displayed.
+    pub fn render_args(&self, language: Language, args: &[&dyn Display]) -> String {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        self.render(language, &args)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn language_from_code_test() {
+        assert_eq!(Language::from_code("en"), Some(Language::English));
+        assert_eq!(Language::from_code("ES"), Some(Language::Spanish));
+        assert_eq!(Language::from_code("es_MX.UTF-8"), Some(Language::Spanish));
+        assert_eq!(Language::from_code("fr"), None);
+    }
+
+    #[test]
+    /// tests that the code of a message stays the same while the text changes with the language
+    fn render_test() {
+        let id = MessageId::AddNumberToNonNumber;
+        assert_eq!(
+            id.render(Language::English, &["1"]),
+            "[E0003] the left side number 1 operand is being added to non left number"
+        );
+        assert_eq!(
+            id.render(Language::Spanish, &["1"]),
+            "[E0003] el número 1 del lado izquierdo se está sumando a un valor que no es un número"
+        );
+    }
+}
@@ -0,0 +1,699 @@
+//!
+//! This module contains the minifier, which turns a parsed program back into the shortest source
+//! that runs the same way, e.g. to embed scripts in small payloads.
+//!
+//! Comments and whitespace are already gone once a program is lexed, so the minifier only has to
+//! print the syntax tree compactly. Along the way it renames the variables declared inside of blocks
+//! to the shortest names which are free in their scope. Global variables keep their names, since
+//! other scripts and the REPL can refer to them.
+
+use crate::ast::{
+    ClassDecl, ExprNode, ExprVisitor, FunctionDecl, Literal, Operator, StmtNode, StmtVisitor,
+};
+use anyhow::{anyhow, Result};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+/// the words which can not be used as the name of a variable
+const KEYWORDS: &[&str] = &[
+    "and", "break", "class", "continue", "define", "else", "false", "for", "fun", "if", "nil",
+    "or", "print", "return", "super", "this", "true", "var", "while",
+];
+
+/// prints a program as compact source. Programs with syntax errors can not be minified.
+pub fn minify(statements: &[StmtNode]) -> Result<String> {
+    let mut names = HashSet::new();
+    for statement in statements {
+        collect_names(statement, &mut names);
+    }
+    let mut minifier = Minifier {
+        scopes: Vec::new(),
+        reserved: names,
+    };
+    minifier.stmts(statements)
+}
+
+/// collects every name used in a statement, which the minifier then avoids when renaming locals so
+/// that a renamed local never shadows a global.
+fn collect_names(statement: &StmtNode, names: &mut HashSet<String>) {
+    struct Names<'a>(&'a mut HashSet<String>);
+
+    impl ExprVisitor for Names<'_> {
+        type Output = ();
+
+        fn visit_literal(&mut self, _literal: &Literal) {}
+
+        fn visit_grouping(&mut self, grouping: &ExprNode) {
+            self.visit_expr_node(grouping)
+        }
+
+        fn visit_binary_expr(&mut self, left: &ExprNode, _operator: &Operator, right: &ExprNode) {
+            self.visit_expr_node(left);
+            self.visit_expr_node(right);
+        }
+
+        fn visit_unary_expr(&mut self, _operator: &Operator, child: &ExprNode) {
+            self.visit_expr_node(child)
+        }
+
+        fn visit_logical_expr(&mut self, left: &ExprNode, _operator: &Operator, right: &ExprNode) {
+            self.visit_expr_node(left);
+            self.visit_expr_node(right);
+        }
+
+        fn visit_variable(&mut self, name: &str, _line: u32) {
+            self.0.insert(name.to_string());
+        }
+
+        fn visit_assign(&mut self, name: &str, value: &ExprNode, _line: u32) {
+            self.0.insert(name.to_string());
+            self.visit_expr_node(value)
+        }
+
+        fn visit_call(&mut self, callee: &ExprNode, arguments: &[ExprNode], _line: u32) {
+            self.visit_expr_node(callee);
+            for argument in arguments {
+                self.visit_expr_node(argument);
+            }
+        }
+
+        // properties are not variables, so their names never collide with renamed locals
+        fn visit_get(&mut self, object: &ExprNode, _name: &str, _line: u32) {
+            self.visit_expr_node(object)
+        }
+
+        fn visit_set(&mut self, object: &ExprNode, _name: &str, value: &ExprNode, _line: u32) {
+            self.visit_expr_node(object);
+            self.visit_expr_node(value);
+        }
+
+        fn visit_list(&mut self, elements: &[ExprNode], _line: u32) {
+            for element in elements {
+                self.visit_expr_node(element);
+            }
+        }
+
+        fn visit_map(&mut self, entries: &[(ExprNode, ExprNode)], _line: u32) {
+            for (key, value) in entries {
+                self.visit_expr_node(key);
+                self.visit_expr_node(value);
+            }
+        }
+
+        fn visit_index(&mut self, object: &ExprNode, index: &ExprNode, _line: u32) {
+            self.visit_expr_node(object);
+            self.visit_expr_node(index);
+        }
+
+        fn visit_slice(
+            &mut self,
+            object: &ExprNode,
+            start: Option<&ExprNode>,
+            end: Option<&ExprNode>,
+            _line: u32,
+        ) {
+            self.visit_expr_node(object);
+            for bound in start.into_iter().chain(end) {
+                self.visit_expr_node(bound);
+            }
+        }
+
+        fn visit_set_index(
+            &mut self,
+            object: &ExprNode,
+            index: &ExprNode,
+            value: &ExprNode,
+            _line: u32,
+        ) {
+            self.visit_expr_node(object);
+            self.visit_expr_node(index);
+            self.visit_expr_node(value);
+        }
+
+        fn visit_this(&mut self, _line: u32) {}
+
+        fn visit_super(&mut self, _method: &str, _line: u32) {}
+    }
+
+    let mut visit = |expr: &ExprNode| Names(names).visit_expr_node(expr);
+    match statement {
+        StmtNode::PrintStmt(expr) | StmtNode::ExprStmt(expr) => visit(expr),
+        StmtNode::VarStmt { name, initializer } => {
+            initializer.iter().for_each(visit);
+            names.insert(name.clone());
+        }
+        StmtNode::ImportStmt {
+            name,
+            names: imported,
+            ..
+        } => {
+            names.extend(name.clone());
+            names.extend(imported.iter().cloned());
+        }
+        StmtNode::ExportStmt { statement, .. } => collect_names(statement, names),
+        StmtNode::ConstStmt { name, initializer } => {
+            visit(initializer);
+            names.insert(name.clone());
+        }
+        StmtNode::BlockStmt(statements) => statements
+            .iter()
+            .for_each(|statement| collect_names(statement, names)),
+        StmtNode::IfStmt {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            visit(condition);
+            collect_names(then_branch, names);
+            if let Some(else_branch) = else_branch {
+                collect_names(else_branch, names);
+            }
+        }
+        StmtNode::WhileStmt {
+            condition,
+            body,
+            increment,
+        } => {
+            visit(condition);
+            increment.iter().for_each(visit);
+            collect_names(body, names);
+        }
+        StmtNode::DoWhileStmt { body, condition } => {
+            visit(condition);
+            collect_names(body, names);
+        }
+        StmtNode::FunctionStmt(declaration) => {
+            names.insert(declaration.name.clone());
+            collect_function_names(declaration, names);
+        }
+        StmtNode::ClassStmt(declaration) => {
+            declaration.superclass.iter().for_each(visit);
+            names.insert(declaration.name.clone());
+            for method in declaration.methods.iter().chain(&declaration.class_methods) {
+                collect_function_names(method, names);
+            }
+        }
+        StmtNode::ReturnStmt { value, .. } => value.iter().for_each(visit),
+        StmtNode::ThrowStmt { value, .. } => visit(value),
+        StmtNode::TryStmt {
+            body,
+            name,
+            handler,
+        } => {
+            names.insert(name.clone());
+            for statement in body.iter().chain(handler) {
+                collect_names(statement, names);
+            }
+        }
+        StmtNode::Commented { statement, .. } => collect_names(statement, names),
+        StmtNode::ErrStmt(_) | StmtNode::BreakStmt { .. } | StmtNode::ContinueStmt { .. } => {}
+    }
+}
+
+/// collects the names used by the parameters and the body of a function
+fn collect_function_names(declaration: &FunctionDecl, names: &mut HashSet<String>) {
+    names.extend(declaration.params.iter().chain(&declaration.rest).cloned());
+    declaration
+        .body
+        .iter()
+        .for_each(|statement| collect_names(statement, names));
+}
+
+/// the nth shortest name made of lowercase letters: a, b, ..., z, aa, ab, ...
+fn short_name(mut n: usize) -> String {
+    let mut name = Vec::new();
+    loop {
+        name.push(b'a' + (n % 26) as u8);
+        if n < 26 {
+            break;
+        }
+        n = n / 26 - 1;
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap_or_default()
+}
+
+struct Minifier {
+    /// the renamed locals of every block being printed, from the outermost to the innermost one
+    scopes: Vec<HashMap<String, String>>,
+    /// the names of the program which short names must not collide with
+    reserved: HashSet<String>,
+}
+
+impl Minifier {
+    /// prints the parameters and body of a function or method, whose parameters are in a scope of
+    /// their own
+    fn function(&mut self, declaration: &FunctionDecl) -> Result<String> {
+        self.scopes.push(HashMap::new());
+        let mut params = declaration
+            .params
+            .iter()
+            .map(|param| self.declare(param))
+            .collect::<Vec<_>>();
+        if let Some(rest) = &declaration.rest {
+            params.push(format!("...{}", self.declare(rest)));
+        }
+        let body = self.stmts(&declaration.body);
+        self.scopes.pop();
+        Ok(format!("({}){{{}}}", params.join(","), body?))
+    }
+
+    fn stmts(&mut self, statements: &[StmtNode]) -> Result<String> {
+        statements
+            .iter()
+            .map(|statement| self.visit_stmt(statement))
+            .collect()
+    }
+
+    /// the name a variable is printed with, which is the name of the innermost local declaring it or
+    /// its own name for globals.
+    fn name(&self, name: &str) -> String {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .cloned()
+            .unwrap_or_else(|| name.to_string())
+    }
+
+    /// picks the short name of a local declared in the innermost scope. Names of outer locals stay
+    /// taken, while sibling scopes reuse the same names.
+    fn declare(&mut self, name: &str) -> String {
+        let Some(scope) = self.scopes.last() else {
+            return name.to_string();
+        };
+        if let Some(short) = scope.get(name) {
+            return short.clone();
+        }
+        let taken = self
+            .scopes
+            .iter()
+            .flat_map(|scope| scope.values())
+            .collect::<HashSet<_>>();
+        let short = (0..)
+            .map(short_name)
+            .find(|short| {
+                !taken.contains(short)
+                    && !self.reserved.contains(short)
+                    && !KEYWORDS.contains(&short.as_str())
+            })
+            .unwrap_or_else(|| name.to_string());
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), short.clone());
+        }
+        short
+    }
+
+    fn number(number: f64) -> String {
+        if number.is_nan() {
+            "(0/0)".to_string()
+        } else if number.is_infinite() {
+            format!("({}1/0)", if number < 0.0 { "-" } else { "" })
+        } else {
+            number.to_string()
+        }
+    }
+}
+
+impl StmtVisitor for Minifier {
+    type Output = Result<String>;
+
+    fn visit_print_stmt(&mut self, node: &ExprNode) -> Self::Output {
+        Ok(format!("print {};", self.visit_expr_node(node)))
+    }
+
+    fn visit_expr_stmt(&mut self, node: &ExprNode) -> Self::Output {
+        Ok(format!("{};", self.visit_expr_node(node)))
+    }
+
+    fn visit_err_stmt(&mut self, err: String) -> Self::Output {
+        Err(anyhow!("Can not minify a program with errors: {}", err))
+    }
+
+    fn visit_var_stmt(&mut self, name: &str, initializer: Option<&ExprNode>) -> Self::Output {
+        // the initializer still sees the variable being shadowed, so it is printed first
+        let initializer = initializer.map(|expr| format!("={}", self.visit_expr_node(expr)));
+        let name = self.declare(name);
+        Ok(format!("var {}{};", name, initializer.unwrap_or_default()))
+    }
+
+    /// the names imported from a module are the ones it exports, so they keep their names even
+    /// inside of blocks
+    fn visit_import_stmt(
+        &mut self,
+        path: &str,
+        name: Option<&str>,
+        names: &[String],
+        _line: u32,
+    ) -> Self::Output {
+        if !names.is_empty() {
+            if let Some(scope) = self.scopes.last_mut() {
+                scope.extend(names.iter().map(|name| (name.clone(), name.clone())));
+            }
+            return Ok(format!("import{{{}}}from\"{}\";", names.join(","), path));
+        }
+        Ok(match name {
+            Some(name) => format!("import \"{}\" as {};", path, self.declare(name)),
+            None => format!("import \"{}\";", path),
+        })
+    }
+
+    fn visit_export_stmt(&mut self, statement: &StmtNode, _line: u32) -> Self::Output {
+        Ok(format!("export {}", self.visit_stmt(statement)?))
+    }
+
+    fn visit_const_stmt(&mut self, name: &str, initializer: &ExprNode) -> Self::Output {
+        let initializer = self.visit_expr_node(initializer);
+        let name = self.declare(name);
+        Ok(format!("const {}={};", name, initializer))
+    }
+
+    fn visit_block_stmt(&mut self, statements: &[StmtNode]) -> Self::Output {
+        self.scopes.push(HashMap::new());
+        let statements = self.stmts(statements);
+        self.scopes.pop();
+        Ok(format!("{{{}}}", statements?))
+    }
+
+    fn visit_if_stmt(
+        &mut self,
+        condition: &ExprNode,
+        then_branch: &StmtNode,
+        else_branch: Option<&StmtNode>,
+    ) -> Self::Output {
+        let mut out = format!(
+            "if({}){}",
+            self.visit_expr_node(condition),
+            self.visit_stmt(then_branch)?
+        );
+        if let Some(else_branch) = else_branch {
+            out.push_str(&format!("else {}", self.visit_stmt(else_branch)?));
+        }
+        Ok(out)
+    }
+
+    fn visit_while_stmt(
+        &mut self,
+        condition: &ExprNode,
+        body: &StmtNode,
+        increment: Option<&ExprNode>,
+    ) -> Self::Output {
+        let condition = self.visit_expr_node(condition);
+        match increment {
+            // only a for loop can have an increment
+            Some(increment) => Ok(format!(
+                "for(;{};{}){}",
+                condition,
+                self.visit_expr_node(increment),
+                self.visit_stmt(body)?
+            )),
+            None => Ok(format!("while({}){}", condition, self.visit_stmt(body)?)),
+        }
+    }
+
+    fn visit_do_while_stmt(&mut self, body: &StmtNode, condition: &ExprNode) -> Self::Output {
+        let body = self.visit_stmt(body)?;
+        Ok(format!(
+            "do {} while({});",
+            body,
+            self.visit_expr_node(condition)
+        ))
+    }
+
+    fn visit_break_stmt(&mut self, _line: u32) -> Self::Output {
+        Ok("break;".to_string())
+    }
+
+    fn visit_continue_stmt(&mut self, _line: u32) -> Self::Output {
+        Ok("continue;".to_string())
+    }
+
+    fn visit_function_stmt(&mut self, declaration: &Rc<FunctionDecl>) -> Self::Output {
+        // the name is declared before the body so that the function can call itself
+        let name = self.declare(&declaration.name);
+        Ok(format!("fun {}{}", name, self.function(declaration)?))
+    }
+
+    fn visit_class_stmt(&mut self, declaration: &Rc<ClassDecl>) -> Self::Output {
+        let superclass = match &declaration.superclass {
+            Some(superclass) => format!("<{}", self.visit_expr_node(superclass)),
+            None => String::new(),
+        };
+        let name = self.declare(&declaration.name);
+        // methods keep their names, since they are looked up as properties
+        let methods = declaration
+            .methods
+            .iter()
+            .map(|method| ("", method))
+            .chain(
+                declaration
+                    .class_methods
+                    .iter()
+                    .map(|method| ("class ", method)),
+            )
+            .map(|(prefix, method)| {
+                Ok(format!(
+                    "{}{}{}",
+                    prefix,
+                    method.name,
+                    self.function(method)?
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(format!(
+            "class {}{}{{{}}}",
+            name,
+            superclass,
+            methods.concat()
+        ))
+    }
+
+    fn visit_return_stmt(&mut self, value: Option<&ExprNode>, _line: u32) -> Self::Output {
+        match value {
+            Some(value) => Ok(format!("return {};", self.visit_expr_node(value))),
+            None => Ok("return;".to_string()),
+        }
+    }
+
+    fn visit_throw_stmt(&mut self, value: &ExprNode, _line: u32) -> Self::Output {
+        Ok(format!("throw {};", self.visit_expr_node(value)))
+    }
+
+    fn visit_try_stmt(
+        &mut self,
+        body: &[StmtNode],
+        name: &str,
+        handler: &[StmtNode],
+    ) -> Self::Output {
+        let body = self.visit_block_stmt(body)?;
+        self.scopes.push(HashMap::new());
+        let name = self.declare(name);
+        let handler = self.stmts(handler);
+        self.scopes.pop();
+        Ok(format!("try{}catch({}){{{}}}", body, name, handler?))
+    }
+}
+
+impl ExprVisitor for Minifier {
+    type Output = String;
+
+    fn visit_literal(&mut self, literal: &Literal) -> Self::Output {
+        match literal {
+            Literal::Number(number) => Minifier::number(*number),
+            Literal::String(string) => format!("\"{}\"", string),
+            #[cfg(feature = "bigint")]
+            Literal::BigInt(bigint) => format!("{}n", bigint),
+            literal => literal.to_string(),
+        }
+    }
+
+    fn visit_grouping(&mut self, grouping: &ExprNode) -> Self::Output {
+        format!("({})", self.visit_expr_node(grouping))
+    }
+
+    fn visit_binary_expr(
+        &mut self,
+        left: &ExprNode,
+        operator: &Operator,
+        right: &ExprNode,
+    ) -> Self::Output {
+        format!(
+            "{}{}{}",
+            self.visit_expr_node(left),
+            operator.lexeme(),
+            self.visit_expr_node(right)
+        )
+    }
+
+    fn visit_unary_expr(&mut self, operator: &Operator, child: &ExprNode) -> Self::Output {
+        format!("{}{}", operator.lexeme(), self.visit_expr_node(child))
+    }
+
+    fn visit_logical_expr(
+        &mut self,
+        left: &ExprNode,
+        operator: &Operator,
+        right: &ExprNode,
+    ) -> Self::Output {
+        format!(
+            "{} {} {}",
+            self.visit_expr_node(left),
+            operator.lexeme(),
+            self.visit_expr_node(right)
+        )
+    }
+
+    fn visit_variable(&mut self, name: &str, _line: u32) -> Self::Output {
+        self.name(name)
+    }
+
+    fn visit_assign(&mut self, name: &str, value: &ExprNode, _line: u32) -> Self::Output {
+        let value = self.visit_expr_node(value);
+        format!("{}={}", self.name(name), value)
+    }
+
+    fn visit_get(&mut self, object: &ExprNode, name: &str, _line: u32) -> Self::Output {
+        format!("{}.{}", self.visit_expr_node(object), name)
+    }
+
+    fn visit_set(
+        &mut self,
+        object: &ExprNode,
+        name: &str,
+        value: &ExprNode,
+        _line: u32,
+    ) -> Self::Output {
+        let object = self.visit_expr_node(object);
+        format!("{}.{}={}", object, name, self.visit_expr_node(value))
+    }
+
+    fn visit_list(&mut self, elements: &[ExprNode], _line: u32) -> Self::Output {
+        let elements = elements
+            .iter()
+            .map(|element| self.visit_expr_node(element))
+            .collect::<Vec<_>>();
+        format!("[{}]", elements.join(","))
+    }
+
+    fn visit_map(&mut self, entries: &[(ExprNode, ExprNode)], _line: u32) -> Self::Output {
+        let entries = entries
+            .iter()
+            .map(|(key, value)| {
+                let key = self.visit_expr_node(key);
+                format!("{}:{}", key, self.visit_expr_node(value))
+            })
+            .collect::<Vec<_>>();
+        format!("{{{}}}", entries.join(","))
+    }
+
+    fn visit_index(&mut self, object: &ExprNode, index: &ExprNode, _line: u32) -> Self::Output {
+        let object = self.visit_expr_node(object);
+        format!("{}[{}]", object, self.visit_expr_node(index))
+    }
+
+    fn visit_slice(
+        &mut self,
+        object: &ExprNode,
+        start: Option<&ExprNode>,
+        end: Option<&ExprNode>,
+        _line: u32,
+    ) -> Self::Output {
+        let object = self.visit_expr_node(object);
+        let start = start.map(|start| self.visit_expr_node(start));
+        let end = end.map(|end| self.visit_expr_node(end));
+        format!(
+            "{}[{}:{}]",
+            object,
+            start.unwrap_or_default(),
+            end.unwrap_or_default()
+        )
+    }
+
+    fn visit_set_index(
+        &mut self,
+        object: &ExprNode,
+        index: &ExprNode,
+        value: &ExprNode,
+        _line: u32,
+    ) -> Self::Output {
+        let object = self.visit_expr_node(object);
+        let index = self.visit_expr_node(index);
+        format!("{}[{}]={}", object, index, self.visit_expr_node(value))
+    }
+
+    fn visit_this(&mut self, _line: u32) -> Self::Output {
+        "this".to_string()
+    }
+
+    fn visit_super(&mut self, method: &str, _line: u32) -> Self::Output {
+        format!("super.{}", method)
+    }
+
+    fn visit_call(
+        &mut self,
+        callee: &ExprNode,
+        arguments: &[ExprNode],
+        _line: u32,
+    ) -> Self::Output {
+        let arguments = arguments
+            .iter()
+            .map(|argument| self.visit_expr_node(argument))
+            .collect::<Vec<_>>();
+        format!("{}({})", self.visit_expr_node(callee), arguments.join(","))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::interpreter::Interpreter;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn parse(source: &str) -> Vec<StmtNode> {
+        Parser::new().parse(Lexer::new().lex(source).unwrap())
+    }
+
+    #[test]
+    fn short_name_test() {
+        assert_eq!(short_name(0), "a");
+        assert_eq!(short_name(25), "z");
+        assert_eq!(short_name(26), "aa");
+        assert_eq!(short_name(27), "ab");
+    }
+
+    #[test]
+    fn minify_test() {
+        let source = "// counts up\nvar total = 0;\n{\n  var count = 1; // starts at one\n  \
+            var a = 'hi';\n  { var count = count + 1; total = count; }\n  \
+            print count and !(total - -1 > 2);\n}\n\
+            for (var index = 0; index < 3; index = index + 1) { if (index == 1) continue; else print index; }";
+        let minified = minify(&parse(source)).unwrap();
+        assert_eq!(
+            minified,
+            "var total=0;{var b=1;var c=\"hi\";{var d=b+1;total=d;}print b and !(total--1>2);}\
+            {var b=0;for(;b<3;b=b+1){if(b==1)continue;else print b;}}"
+        );
+
+        // the minified program runs the same way as the original one
+        let run = |source: &str| {
+            let output = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+            struct Shared(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+            impl std::io::Write for Shared {
+                fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                    self.0.lock().unwrap().write(buf)
+                }
+                fn flush(&mut self) -> std::io::Result<()> {
+                    Ok(())
+                }
+            }
+            let mut interpreter = Interpreter::new().with_output(Box::new(Shared(output.clone())));
+            interpreter.execute(parse(source)).unwrap();
+            let output = output.lock().unwrap().clone();
+            String::from_utf8(output).unwrap()
+        };
+        assert_eq!(run(source), run(&minified));
+
+        assert!(minify(&parse("print ;")).is_err());
+    }
+}
@@ -0,0 +1,815 @@
+//!
+//! This module contains the native functions, which are written in Rust and defined in the global
+//! environment of every interpreter when it starts, e.g. `clock()` for writing benchmarks in Lox,
+//! or `assertEquals(expected, actual)`, `assertTrue(value)` and `assertThrows(function)` for testing
+//! it with `lox test`, see [`crate::testing`]. `assert(condition, message)` fails with the message
+//! when the condition is falsy, like the assertions of other languages' test corpora. `bench(name, function, iterations)` times Lox code,
+//! see [`crate::bench`]. `len(value)` is the number of elements of a list, entries of a map,
+//! characters of a string or bytes of bytes, `chars(string)` splits a string into a list of its
+//! characters, `bytes(list)` builds bytes from numbers and `encode(value, encoding)` and
+//! `decode(value, encoding)` convert between bytes and text, see [`crate::bytes`]. `now()` and
+//! `datetime(seconds)` are date times, which `format(date, pattern)` writes and `epoch(date)` turns
+//! back into seconds, see [`crate::datetime`].
+//! `push(list, value)` appends a value to a list, `keys(map)` lists the keys of a map in order and
+//! `has(map, key)` checks for one. `random()`, `randomInt(lo, hi)` and `seedRandom(n)` draw from the
+//! seedable generator of the interpreter, see [`crate::random`].
+//!
+//! `eprint(value)` prints a value to stderr rather than stdout and `flush()` writes out what the
+//! program printed so far, which is otherwise written a line at a time. `readLine()` reads a line of
+//! stdin, or nil once it has all been read, which is how programs graded by `lox grade` get the
+//! input of a case.
+//!
+//! `log(level, message)` writes through the [`log`] crate with the target `lox`, so that scripts
+//! embedded in an application end up in its logs, whichever logger the application installed.
+
+use crate::analyze::Capability;
+use crate::ast::Literal;
+#[cfg(feature = "host")]
+use crate::bench::{self, BenchResult};
+use crate::bytes::Encoding;
+use crate::datetime::DateTime;
+use crate::diff::{show, Diff};
+use crate::heap;
+use crate::interpreter::{
+    is_catchable, Environment, Interpreter, LoxCallable, LoxError, NumberFormat,
+};
+use crate::map::{LoxMap, MapKey};
+use crate::unicode;
+use anyhow::{anyhow, Result};
+use std::any::Any;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+#[cfg(feature = "host")]
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// the Rust function behind a native, which is given the interpreter so that it can call back into
+/// Lox, and the line of the call to report its errors in.
+pub type NativeFn = fn(&mut Interpreter, &[Literal], u32) -> Result<Literal>;
+
+/// a function written in Rust which Lox programs can call like their own functions.
+#[derive(Debug, Clone, Copy)]
+pub struct NativeFunction {
+    pub name: &'static str,
+    pub arity: usize,
+    function: NativeFn,
+    /// the names of the parameters, shown in its signature by `:doc` in the REPL
+    params: &'static [&'static str],
+    doc: &'static str,
+    /// the capability the native needs, which the audit of a script reports, see [`crate::analyze`]
+    capability: Option<Capability>,
+}
+
+impl NativeFunction {
+    pub const fn new(name: &'static str, arity: usize, function: NativeFn) -> Self {
+        Self {
+            name,
+            arity,
+            function,
+            params: &[],
+            doc: "",
+            capability: None,
+        }
+    }
+
+    /// documents the native with the names of its parameters and what it does
+    pub const fn with_doc(mut self, params: &'static [&'static str], doc: &'static str) -> Self {
+        self.params = params;
+        self.doc = doc;
+        self
+    }
+
+    /// marks the native as needing a capability, which it still has to check with
+    /// [`Interpreter::require_capability`] when it is called
+    pub const fn with_capability(mut self, capability: Capability) -> Self {
+        self.capability = Some(capability);
+        self
+    }
+}
+
+impl LoxCallable for NativeFunction {
+    fn arity(&self) -> Option<usize> {
+        Some(self.arity)
+    }
+
+    fn call(
+        self: Rc<Self>,
+        interpreter: &mut Interpreter,
+        arguments: Vec<Literal>,
+        line: u32,
+    ) -> Result<Literal> {
+        (self.function)(interpreter, &arguments, line)
+    }
+
+    /// natives without the names of their parameters only show their name
+    fn signature(&self) -> String {
+        if self.params.len() == self.arity {
+            format!("{}({})", self.name, self.params.join(", "))
+        } else {
+            self.to_string()
+        }
+    }
+
+    fn doc(&self) -> Option<&str> {
+        (!self.doc.is_empty()).then_some(self.doc)
+    }
+
+    fn is_native(&self) -> bool {
+        true
+    }
+
+    fn capability(&self) -> Option<Capability> {
+        self.capability
+    }
+}
+
+impl fmt::Display for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+/// An object owned by Rust, such as a database connection, which natives hand to Lox programs as a
+/// value they can only pass back to natives. Handles are only equal to themselves.
+#[derive(Clone)]
+pub struct Handle {
+    /// the kind of object, which the handle is printed as
+    pub name: &'static str,
+    object: Rc<dyn Any>,
+}
+
+impl Handle {
+    pub fn new<T: Any>(name: &'static str, object: T) -> Self {
+        Self {
+            name,
+            object: Rc::new(object),
+        }
+    }
+
+    /// the object, if it is a `T`
+    pub fn downcast<T: Any>(&self) -> Option<&T> {
+        self.object.downcast_ref()
+    }
+}
+
+impl fmt::Debug for Handle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<{}>", self.name)
+    }
+}
+
+impl fmt::Display for Handle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<{}>", self.name)
+    }
+}
+
+impl PartialEq for Handle {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.object, &other.object)
+    }
+}
+
+impl PartialOrd for Handle {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        (self == other).then_some(std::cmp::Ordering::Equal)
+    }
+}
+
+/// the natives defined in the global environment of every interpreter. The ones reading the clock
+/// are left out of builds without the `host` feature.
+pub const NATIVES: &[NativeFunction] = &[
+    #[cfg(feature = "host")]
+    NativeFunction::new("clock", 0, clock).with_doc(
+        &[],
+        "the seconds since the Unix epoch, with a fractional part",
+    ),
+    NativeFunction::new("log", 2, log).with_doc(
+        &["level", "message"],
+        "writes a message to the host's logger at the level debug, info, warn or error",
+    ),
+    NativeFunction::new("eprint", 1, eprint).with_doc(
+        &["value"],
+        "prints a value to the error output",
+    ),
+    NativeFunction::new("flush", 0, flush).with_doc(
+        &[],
+        "writes out what the program printed so far",
+    ),
+    NativeFunction::new("readLine", 0, read_line).with_doc(
+        &[],
+        "reads a line of the input without its line ending, or nil at the end of the input",
+    ),
+    NativeFunction::new("assertEquals", 2, assert_equals).with_doc(
+        &["expected", "actual"],
+        "fails with a diff of the values unless they are structurally equal",
+    ),
+    NativeFunction::new("assert", 2, assert).with_doc(
+        &["condition", "message"],
+        "fails with the message unless the condition is truthy",
+    ),
+    NativeFunction::new("assertTrue", 1, assert_true).with_doc(
+        &["value"],
+        "fails unless the value is truthy",
+    ),
+    NativeFunction::new("assertThrows", 1, assert_throws).with_doc(
+        &["function"],
+        "calls a function taking no arguments and fails unless it raises a runtime error,\nreturning the message of the error",
+    ),
+    #[cfg(feature = "host")]
+    NativeFunction::new("bench", 3, bench).with_doc(
+        &["name", "function", "iterations"],
+        "times the given number of calls of a function taking no arguments, returning their\nmean in seconds",
+    ),
+    NativeFunction::new("str", 1, to_str).with_doc(
+        &["value"],
+        "the value as a string, written the canonical way whatever the print precision is",
+    ),
+    NativeFunction::new("setPrintPrecision", 1, set_print_precision).with_doc(
+        &["n"],
+        "makes print write numbers with n digits after the decimal point and their thousands\nseparated by commas, or the canonical way again when n is nil",
+    ),
+    NativeFunction::new("len", 1, len).with_doc(
+        &["value"],
+        "the number of elements of a list, entries of a map, characters of a string or bytes of\nbytes",
+    ),
+    NativeFunction::new("chars", 1, chars).with_doc(
+        &["string"],
+        "a new list of the characters of a string, as strings of one character",
+    ),
+    NativeFunction::new("bytes", 1, bytes).with_doc(
+        &["list"],
+        "bytes built from a list of whole numbers from 0 to 255",
+    ),
+    NativeFunction::new("encode", 2, encode).with_doc(
+        &["value", "encoding"],
+        "the bytes of a string in utf8, or the text of bytes in hex or base64",
+    ),
+    NativeFunction::new("decode", 2, decode).with_doc(
+        &["value", "encoding"],
+        "the string of bytes in utf8, or the bytes of text in hex or base64, undoing encode",
+    ),
+    #[cfg(feature = "host")]
+    NativeFunction::new("now", 0, now).with_doc(
+        &[],
+        "the current date time",
+    ),
+    NativeFunction::new("datetime", 1, datetime).with_doc(
+        &["seconds"],
+        "the date time of a number of seconds since the start of 1970",
+    ),
+    NativeFunction::new("format", 2, format).with_doc(
+        &["date", "pattern"],
+        "a date time written with a pattern of strftime directives like %Y-%m-%d",
+    ),
+    NativeFunction::new("epoch", 1, epoch).with_doc(
+        &["date"],
+        "the seconds since the start of 1970 of a date time",
+    ),
+    NativeFunction::new("push", 2, push).with_doc(
+        &["list", "value"],
+        "appends a value to the end of a list",
+    ),
+    NativeFunction::new("keys", 1, keys).with_doc(
+        &["map"],
+        "a new list of the keys of a map, in the order maps are printed in",
+    ),
+    NativeFunction::new("has", 2, has).with_doc(
+        &["map", "key"],
+        "whether a map holds a value for a key",
+    ),
+    NativeFunction::new("random", 0, random).with_doc(
+        &[],
+        "a random number from 0 up to but not including 1",
+    ),
+    NativeFunction::new("randomInt", 2, random_int).with_doc(
+        &["lo", "hi"],
+        "a random whole number from lo up to and including hi",
+    ),
+    NativeFunction::new("seedRandom", 1, seed_random).with_doc(
+        &["n"],
+        "restarts the random numbers from a seed, so that the ones after it are the same on\nevery run",
+    ),
+];
+
+/// the most digits after the decimal point `setPrintPrecision` accepts
+const MAX_PRINT_PRECISION: i64 = 100;
+
+/// a global environment with every native defined in it
+pub fn globals() -> Environment {
+    let mut environment = Environment::new();
+    for native in NATIVES {
+        environment.define(native.name, Literal::Callable(Rc::new(*native)));
+    }
+    environment
+}
+
+/// a runtime error of a native in the line it was called in
+fn error(line: u32, message: String) -> anyhow::Error {
+    anyhow!(LoxError::new(line, message))
+}
+
+/// the seconds since the Unix epoch, with a fractional part
+#[cfg(feature = "host")]
+fn clock(_interpreter: &mut Interpreter, _arguments: &[Literal], line: u32) -> Result<Literal> {
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|err| {
+            error(
+                line,
+                format!("the system clock is set before the Unix epoch: {}", err),
+            )
+        })?;
+    Ok(Literal::Number(elapsed.as_secs_f64()))
+}
+
+/// prints a value to the error output
+fn eprint(interpreter: &mut Interpreter, arguments: &[Literal], line: u32) -> Result<Literal> {
+    interpreter
+        .eprint(&arguments[0])
+        .map_err(|err| error(line, format!("eprint failed: {}", err)))?;
+    Ok(Literal::Nil)
+}
+
+fn flush(interpreter: &mut Interpreter, _arguments: &[Literal], line: u32) -> Result<Literal> {
+    interpreter
+        .flush()
+        .map_err(|err| error(line, format!("flush failed: {}", err)))?;
+    Ok(Literal::Nil)
+}
+
+fn read_line(interpreter: &mut Interpreter, _arguments: &[Literal], line: u32) -> Result<Literal> {
+    let input = interpreter
+        .read_line()
+        .map_err(|err| error(line, format!("readLine failed: {}", err)))?;
+    Ok(input.map_or(Literal::Nil, Literal::String))
+}
+
+/// the current date time
+#[cfg(feature = "host")]
+fn now(_interpreter: &mut Interpreter, _arguments: &[Literal], _line: u32) -> Result<Literal> {
+    Ok(Literal::DateTime(DateTime::now()))
+}
+
+/// the date time of a number of seconds since the start of 1970
+fn datetime(_interpreter: &mut Interpreter, arguments: &[Literal], line: u32) -> Result<Literal> {
+    arguments[0]
+        .as_number()
+        .and_then(DateTime::new)
+        .map(Literal::DateTime)
+        .ok_or_else(|| {
+            error(
+                line,
+                format!(
+                    "datetime expects the seconds since 1970 of a date, got {}",
+                    show(&arguments[0])
+                ),
+            )
+        })
+}
+
+/// a date time written with a pattern of strftime directives like `%Y-%m-%d`
+fn format(_interpreter: &mut Interpreter, arguments: &[Literal], line: u32) -> Result<Literal> {
+    let date = expect_date("format", &arguments[0], line)?;
+    let Literal::String(pattern) = &arguments[1] else {
+        return Err(error(
+            line,
+            format!(
+                "format expects the pattern to be a string, got {}",
+                show(&arguments[1])
+            ),
+        ));
+    };
+    date.format(pattern)
+        .map(Literal::String)
+        .map_err(|err| error(line, format!("format failed: {}", err)))
+}
+
+/// the seconds since the start of 1970 of a date time
+fn epoch(_interpreter: &mut Interpreter, arguments: &[Literal], line: u32) -> Result<Literal> {
+    let date = expect_date("epoch", &arguments[0], line)?;
+    Ok(Literal::Number(date.seconds()))
+}
+
+fn expect_date(native: &str, argument: &Literal, line: u32) -> Result<DateTime> {
+    match argument {
+        Literal::DateTime(date) => Ok(*date),
+        argument => Err(error(
+            line,
+            format!("{} expects a date time, got {}", native, show(argument)),
+        )),
+    }
+}
+
+/// the value as a string, written the canonical way whatever the print precision is
+fn to_str(_interpreter: &mut Interpreter, arguments: &[Literal], _line: u32) -> Result<Literal> {
+    Ok(Literal::String(arguments[0].to_string()))
+}
+
+/// makes print write numbers with n digits after the decimal point and their thousands separated
+/// by commas, or the canonical way again when n is nil
+fn set_print_precision(
+    interpreter: &mut Interpreter,
+    arguments: &[Literal],
+    line: u32,
+) -> Result<Literal> {
+    *interpreter.number_format() = match &arguments[0] {
+        Literal::Nil => NumberFormat::default(),
+        precision => match whole_number("setPrintPrecision", precision, line)? {
+            precision @ 0..=MAX_PRINT_PRECISION => NumberFormat {
+                thousands_separator: Some(','),
+                decimal_point: '.',
+                precision: Some(precision as usize),
+            },
+            precision => {
+                return Err(error(
+                    line,
+                    format!(
+                        "setPrintPrecision expects from 0 to {} digits, got {}",
+                        MAX_PRINT_PRECISION, precision
+                    ),
+                ))
+            }
+        },
+    };
+    Ok(Literal::Nil)
+}
+
+/// the number of elements of a list, entries of a map, characters of a string or bytes of bytes
+fn len(_interpreter: &mut Interpreter, arguments: &[Literal], line: u32) -> Result<Literal> {
+    match &arguments[0] {
+        Literal::List(list) => Ok(Literal::Number(list.borrow().len() as f64)),
+        Literal::Map(map) => Ok(Literal::Number(map.len() as f64)),
+        Literal::String(string) => Ok(Literal::Number(unicode::length(string) as f64)),
+        Literal::Bytes(bytes) => Ok(Literal::Number(bytes.len() as f64)),
+        value => Err(error(
+            line,
+            format!(
+                "len expects a list, a map, a string or bytes, got {}",
+                show(value)
+            ),
+        )),
+    }
+}
+
+/// a new list of the characters of a string, as strings of one character
+fn chars(interpreter: &mut Interpreter, arguments: &[Literal], line: u32) -> Result<Literal> {
+    let Literal::String(string) = &arguments[0] else {
+        return Err(error(
+            line,
+            format!("chars expects a string, got {}", show(&arguments[0])),
+        ));
+    };
+    let characters: Vec<_> = unicode::characters(string)
+        .into_iter()
+        .map(|character| Literal::String(character.to_string()))
+        .collect();
+    interpreter.allocate(characters.len() * heap::SLOT + string.len(), line)?;
+    Ok(Literal::List(Rc::new(RefCell::new(characters))))
+}
+
+/// bytes built from a list of whole numbers from 0 to 255
+fn bytes(_interpreter: &mut Interpreter, arguments: &[Literal], line: u32) -> Result<Literal> {
+    let Literal::List(list) = &arguments[0] else {
+        return Err(error(
+            line,
+            format!("bytes expects a list, got {}", show(&arguments[0])),
+        ));
+    };
+    let bytes = list
+        .borrow()
+        .iter()
+        .map(|element| match element.as_number() {
+            Some(number) if number.fract() == 0.0 && (0.0..=255.0).contains(&number) => {
+                Ok(number as u8)
+            }
+            _ => Err(error(
+                line,
+                format!(
+                    "bytes expects whole numbers from 0 to 255, got {}",
+                    show(element)
+                ),
+            )),
+        })
+        .collect::<Result<Vec<u8>>>()?;
+    Ok(Literal::Bytes(bytes.into()))
+}
+
+/// the bytes of a string in utf8, or the text of bytes in hex or base64
+fn encode(_interpreter: &mut Interpreter, arguments: &[Literal], line: u32) -> Result<Literal> {
+    let encoding = expect_encoding("encode", &arguments[1], line)?;
+    match (encoding, &arguments[0]) {
+        (Encoding::Utf8, Literal::String(string)) => Ok(Literal::Bytes(string.as_bytes().into())),
+        (Encoding::Hex | Encoding::Base64, Literal::Bytes(bytes)) => encoding
+            .to_text(bytes)
+            .map(Literal::String)
+            .map_err(|err| error(line, format!("encode failed: {}", err))),
+        (Encoding::Utf8, value) => Err(error(
+            line,
+            format!("encode expects a string to utf8, got {}", show(value)),
+        )),
+        (encoding, value) => Err(error(
+            line,
+            format!("encode expects bytes to {}, got {}", encoding, show(value)),
+        )),
+    }
+}
+
+/// the string of bytes in utf8, or the bytes of text in hex or base64, undoing encode
+fn decode(_interpreter: &mut Interpreter, arguments: &[Literal], line: u32) -> Result<Literal> {
+    let encoding = expect_encoding("decode", &arguments[1], line)?;
+    let decoded = match (encoding, &arguments[0]) {
+        (Encoding::Utf8, Literal::Bytes(bytes)) => encoding.to_text(bytes).map(Literal::String),
+        (Encoding::Hex | Encoding::Base64, Literal::String(text)) => encoding
+            .to_bytes(text)
+            .map(|bytes| Literal::Bytes(bytes.into())),
+        (Encoding::Utf8, value) => {
+            return Err(error(
+                line,
+                format!("decode expects bytes from utf8, got {}", show(value)),
+            ))
+        }
+        (encoding, value) => {
+            return Err(error(
+                line,
+                format!(
+                    "decode expects a string from {}, got {}",
+                    encoding,
+                    show(value)
+                ),
+            ))
+        }
+    };
+    decoded.map_err(|err| error(line, format!("decode failed: {}", err)))
+}
+
+fn expect_encoding(native: &str, argument: &Literal, line: u32) -> Result<Encoding> {
+    match argument {
+        Literal::String(encoding) => encoding
+            .parse()
+            .map_err(|err| error(line, format!("{} failed: {}", native, err))),
+        argument => Err(error(
+            line,
+            format!(
+                "{} expects the encoding to be a string, got {}",
+                native,
+                show(argument)
+            ),
+        )),
+    }
+}
+
+/// a new list of the keys of a map, in the order maps are printed in
+fn keys(interpreter: &mut Interpreter, arguments: &[Literal], line: u32) -> Result<Literal> {
+    let map = expect_map("keys", &arguments[0], line)?;
+    interpreter.allocate(map.len() * heap::SLOT, line)?;
+    let keys = map
+        .entries()
+        .iter()
+        .map(|(key, _)| key.to_literal())
+        .collect();
+    Ok(Literal::List(Rc::new(RefCell::new(keys))))
+}
+
+/// whether a map holds a value for a key, so that it can be checked before indexing the map
+fn has(_interpreter: &mut Interpreter, arguments: &[Literal], line: u32) -> Result<Literal> {
+    let map = expect_map("has", &arguments[0], line)?;
+    // values which can not be keys are never in a map
+    let found = MapKey::new(&arguments[1]).is_some_and(|key| map.contains(&key));
+    Ok(Literal::Boolean(found))
+}
+
+fn expect_map<'a>(native: &str, argument: &'a Literal, line: u32) -> Result<&'a LoxMap> {
+    match argument {
+        Literal::Map(map) => Ok(map),
+        argument => Err(error(
+            line,
+            format!("{} expects a map, got {}", native, show(argument)),
+        )),
+    }
+}
+
+/// appends a value to the end of a list
+fn push(interpreter: &mut Interpreter, arguments: &[Literal], line: u32) -> Result<Literal> {
+    match &arguments[0] {
+        Literal::List(list) => {
+            interpreter.allocate(heap::SLOT, line)?;
+            list.borrow_mut().push(arguments[1].clone());
+            Ok(Literal::Nil)
+        }
+        value => Err(error(
+            line,
+            format!("push expects a list, got {}", show(value)),
+        )),
+    }
+}
+
+/// a random number from 0 up to but not including 1
+fn random(interpreter: &mut Interpreter, _arguments: &[Literal], _line: u32) -> Result<Literal> {
+    Ok(Literal::Number(interpreter.random().next_f64()))
+}
+
+/// a random whole number from lo up to and including hi
+fn random_int(interpreter: &mut Interpreter, arguments: &[Literal], line: u32) -> Result<Literal> {
+    let lo = whole_number("randomInt", &arguments[0], line)?;
+    let hi = whole_number("randomInt", &arguments[1], line)?;
+    if lo > hi {
+        return Err(error(
+            line,
+            format!(
+                "randomInt expects lo to be at most hi, got {} and {}",
+                lo, hi
+            ),
+        ));
+    }
+    Ok(Literal::Number(interpreter.random().range(lo, hi) as f64))
+}
+
+/// restarts the random numbers from a seed, so that the ones after it are the same on every run
+fn seed_random(interpreter: &mut Interpreter, arguments: &[Literal], line: u32) -> Result<Literal> {
+    let seed = whole_number("seedRandom", &arguments[0], line)?;
+    interpreter.random().seed(seed as u64);
+    Ok(Literal::Nil)
+}
+
+/// checks that the argument of a native is a whole number which fits in 64 bits
+fn whole_number(native: &str, argument: &Literal, line: u32) -> Result<i64> {
+    match argument.as_number() {
+        Some(number) if number.fract() == 0.0 && number.abs() < i64::MAX as f64 => {
+            Ok(number as i64)
+        }
+        _ => Err(error(
+            line,
+            format!("{} expects a whole number, got {}", native, show(argument)),
+        )),
+    }
+}
+
+/// writes a message to the host's logger at the level debug, info, warn or error
+fn log(_interpreter: &mut Interpreter, arguments: &[Literal], line: u32) -> Result<Literal> {
+    let level = match &arguments[0] {
+        Literal::String(level) if level == "debug" => log::Level::Debug,
+        Literal::String(level) if level == "info" => log::Level::Info,
+        Literal::String(level) if level == "warn" => log::Level::Warn,
+        Literal::String(level) if level == "error" => log::Level::Error,
+        level => {
+            return Err(error(
+                line,
+                format!(
+                    "Log level must be \"debug\", \"info\", \"warn\" or \"error\", got {}",
+                    level
+                ),
+            ))
+        }
+    };
+    log::log!(target: "lox", level, "{}", arguments[1]);
+    Ok(Literal::Nil)
+}
+
+/// fails with a diff of the values unless they are structurally equal, i.e. equal or instances of
+/// the same class whose fields are structurally equal
+fn assert_equals(
+    _interpreter: &mut Interpreter,
+    arguments: &[Literal],
+    line: u32,
+) -> Result<Literal> {
+    let diff = Diff::new(&arguments[0], &arguments[1]);
+    if diff.is_empty() {
+        Ok(Literal::Nil)
+    } else {
+        Err(error(line, format!("assertEquals failed, {}", diff)))
+    }
+}
+
+/// fails unless the value is truthy
+fn assert_true(
+    _interpreter: &mut Interpreter,
+    arguments: &[Literal],
+    line: u32,
+) -> Result<Literal> {
+    if arguments[0].is_truthy() {
+        Ok(Literal::Nil)
+    } else {
+        Err(error(
+            line,
+            format!("assertTrue failed, got {}", show(&arguments[0])),
+        ))
+    }
+}
+
+/// fails with the message, written like `print` writes it, unless the condition is truthy
+fn assert(_interpreter: &mut Interpreter, arguments: &[Literal], line: u32) -> Result<Literal> {
+    if arguments[0].is_truthy() {
+        Ok(Literal::Nil)
+    } else {
+        Err(error(line, format!("assert failed: {}", arguments[1])))
+    }
+}
+
+/// calls a function taking no arguments and fails unless it raises a runtime error, returning the
+/// message of the error so that it can be checked as well. Cancelling the interpreter is not an
+/// error of the function, so it is passed on.
+fn assert_throws(
+    interpreter: &mut Interpreter,
+    arguments: &[Literal],
+    line: u32,
+) -> Result<Literal> {
+    // calling something which can not be called with no arguments would raise an error as well
+    expect_no_argument_function("assertThrows", &arguments[0], line)?;
+    match interpreter.call_value(arguments[0].clone(), Vec::new(), line) {
+        Ok(value) => Err(error(
+            line,
+            format!(
+                "assertThrows failed, the function returned {} without an error",
+                show(&value)
+            ),
+        )),
+        Err(err) if !is_catchable(&err) => Err(err),
+        Err(err) => Ok(Literal::String(err.to_string())),
+    }
+}
+
+/// checks that the argument of a native is a function which can be called with no arguments
+fn expect_no_argument_function(native: &str, argument: &Literal, line: u32) -> Result<()> {
+    match argument {
+        Literal::Callable(callable) if matches!(callable.arity(), None | Some(0)) => Ok(()),
+        argument => Err(error(
+            line,
+            format!(
+                "{} expects a function taking no arguments, got {}",
+                native,
+                show(argument)
+            ),
+        )),
+    }
+}
+
+/// calls a function taking no arguments to warm up and then times the given number of calls,
+/// returning their mean in seconds. The result is recorded for `lox bench`.
+#[cfg(feature = "host")]
+fn bench(interpreter: &mut Interpreter, arguments: &[Literal], line: u32) -> Result<Literal> {
+    let Literal::String(name) = &arguments[0] else {
+        return Err(error(
+            line,
+            format!("bench expects a name, got {}", show(&arguments[0])),
+        ));
+    };
+    expect_no_argument_function("bench", &arguments[1], line)?;
+    let iterations = match arguments[2].as_number() {
+        Some(iterations) if iterations >= 1.0 && iterations.fract() == 0.0 => iterations as usize,
+        _ => {
+            return Err(error(
+                line,
+                format!(
+                    "bench expects a positive whole number of iterations, got {}",
+                    show(&arguments[2])
+                ),
+            ))
+        }
+    };
+
+    let warmup = bench::warmup_iterations(iterations);
+    for _ in 0..warmup {
+        interpreter.call_value(arguments[1].clone(), Vec::new(), line)?;
+    }
+    let mut samples = Vec::with_capacity(iterations);
+    for _ in 0..iterations {
+        let start = Instant::now();
+        interpreter.call_value(arguments[1].clone(), Vec::new(), line)?;
+        samples.push(start.elapsed());
+    }
+    let result = BenchResult::from_samples(name.clone(), warmup, &samples, line);
+    let mean = result.mean.as_secs_f64();
+    interpreter.record_benchmark(result);
+    Ok(Literal::Number(mean))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "host")]
+    /// tests that the natives are defined in the global environment
+    fn globals_test() {
+        let environment = globals();
+        let Some(Literal::Callable(clock)) = environment.get("clock") else {
+            panic!("clock is not defined");
+        };
+        assert_eq!(clock.arity(), Some(0));
+        assert_eq!(clock.to_string(), "<native fn clock>");
+    }
+
+    #[test]
+    #[cfg(not(feature = "host"))]
+    /// tests that builds without the host feature have no natives reading the clock, while the
+    /// others are still defined
+    fn core_globals_test() {
+        let environment = globals();
+        for name in ["clock", "now", "bench"] {
+            assert_eq!(environment.get(name), None);
+        }
+        assert!(environment.get("len").is_some());
+        assert!(environment.get("datetime").is_some());
+    }
+}
@@ -0,0 +1,457 @@
+//!
+//! This module contains the arithmetic behind the number operators, which is either the binary
+//! floating point of the reference Lox implementations or decimal arithmetic, chosen with
+//! [`crate::interpreter::Interpreter::with_number_mode`].
+//!
+//! In decimal mode the results of the number operators are [`Decimal`]s, which are kept as a whole
+//! number of up to 34 digits times a power of ten from then on, like the decimal128 of IEEE 754. A
+//! number written in the program or returned by a native becomes the decimal it prints as when it
+//! meets another number, and every operation is exact unless its result has more than 34
+//! significant digits, which is rounded to 34 digits, half to even. So `0.1 + 0.2 == 0.3`,
+//! `1.15 * 100 == 115` and `10000000000000000 + 1` is `10000000000000001`, which makes it suited to
+//! teaching and to scripts handling money. `1 / 3` is `0.3333333333333333333333333333333333`, so
+//! `1 / 3 * 3` is still not `1`.
+//!
+//! Decimals are numbers to the rest of the language: they compare with and equal the numbers of the
+//! same value, and natives taking numbers take them as the closest double, see
+//! [`crate::ast::Literal::as_number`]. Infinities and NaN are doubles only, so dividing by zero or
+//! combining a decimal with them is done in binary floating point.
+
+use crate::ast::{Literal, Operator};
+use anyhow::{anyhow, Result};
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+use std::str::FromStr;
+
+/// How the number operators do their arithmetic.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NumberMode {
+    /// binary floating point, like the reference Lox implementations
+    #[default]
+    Binary,
+    /// decimal arithmetic, which makes the results of the number operators decimals so that
+    /// `0.1 + 0.2 == 0.3`
+    Decimal,
+}
+
+impl FromStr for NumberMode {
+    type Err = anyhow::Error;
+
+    fn from_str(mode: &str) -> Result<Self> {
+        match mode {
+            "binary" => Ok(NumberMode::Binary),
+            "decimal" => Ok(NumberMode::Decimal),
+            mode => Err(anyhow!(
+                "Unknown number mode {}, expected binary or decimal",
+                mode
+            )),
+        }
+    }
+}
+
+impl fmt::Display for NumberMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NumberMode::Binary => write!(f, "binary"),
+            NumberMode::Decimal => write!(f, "decimal"),
+        }
+    }
+}
+
+/// applies an arithmetic or comparison operator to two numbers in decimal arithmetic, when one of
+/// them is a decimal or the mode is decimal. Other operands and operators are left to the rules for
+/// numbers and strings, which is None, and so is dividing by zero or a number which is infinite or
+/// NaN.
+pub fn binary(
+    mode: NumberMode,
+    operator: &Operator,
+    left: &Literal,
+    right: &Literal,
+) -> Option<Literal> {
+    let is_decimal = |value: &Literal| matches!(value, Literal::Decimal(_));
+    if mode == NumberMode::Binary && !is_decimal(left) && !is_decimal(right) {
+        return None;
+    }
+    let decimal = |value: &Literal| match value {
+        Literal::Decimal(decimal) => Some(*decimal),
+        Literal::Number(number) => Decimal::from_f64(*number),
+        _ => None,
+    };
+    let (left, right) = (decimal(left)?, decimal(right)?);
+    Some(match operator {
+        Operator::Add { .. } => Literal::Decimal(left + right),
+        Operator::Subtract { .. } => Literal::Decimal(left - right),
+        Operator::Multiply { .. } => Literal::Decimal(left * right),
+        Operator::Divide { .. } => Literal::Decimal(left.divide(right)?),
+        Operator::GreaterThan { .. } => Literal::Boolean(left > right),
+        Operator::GreaterEqual { .. } => Literal::Boolean(left >= right),
+        Operator::LessThan { .. } => Literal::Boolean(left < right),
+        Operator::LessEqual { .. } => Literal::Boolean(left <= right),
+        _ => return None,
+    })
+}
+
+/// the number of significant digits a decimal keeps
+const PRECISION: usize = 34;
+
+/// A decimal number, which is the mantissa times ten to the power of the exponent. The mantissa
+/// has at most [`PRECISION`] digits and no trailing zeros, so equal decimals have the same mantissa
+/// and exponent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decimal {
+    mantissa: i128,
+    exponent: i32,
+}
+
+impl Decimal {
+    const ZERO: Decimal = Decimal {
+        mantissa: 0,
+        exponent: 0,
+    };
+
+    /// the decimal a double prints as, which is the shortest one that reads back as the double, or
+    /// None for infinities and NaN
+    pub fn from_f64(number: f64) -> Option<Decimal> {
+        if !number.is_finite() {
+            return None;
+        }
+        format!("{:e}", number).parse().ok()
+    }
+
+    /// the double closest to the decimal, which parsing rounds correctly
+    pub fn to_f64(self) -> f64 {
+        format!("{}e{}", self.mantissa, self.exponent)
+            .parse()
+            .unwrap_or(f64::NAN)
+    }
+
+    /// the quotient rounded to 34 significant digits, or None when dividing by zero
+    pub fn divide(self, other: Decimal) -> Option<Decimal> {
+        if other.mantissa == 0 {
+            return None;
+        }
+        let (dividend, divisor) = (self.mantissa.unsigned_abs(), other.mantissa.unsigned_abs());
+        let mut digits = (dividend / divisor).to_string();
+        let mut remainder = dividend % divisor;
+        let mut exponent = self.exponent as i64 - other.exponent as i64;
+        // long division until there is a digit past the precision to round by, which keeps the
+        // remainder below the divisor and so below 10^35
+        while remainder != 0 && digits.trim_start_matches('0').len() <= PRECISION {
+            remainder *= 10;
+            digits.push(char::from(b'0' + (remainder / divisor) as u8));
+            remainder %= divisor;
+            exponent -= 1;
+        }
+        let negative = (self.mantissa < 0) != (other.mantissa < 0);
+        Some(round_digits(negative, &digits, exponent, remainder != 0))
+    }
+}
+
+impl Neg for Decimal {
+    type Output = Decimal;
+
+    fn neg(self) -> Decimal {
+        Decimal {
+            mantissa: -self.mantissa,
+            ..self
+        }
+    }
+}
+
+impl Add for Decimal {
+    type Output = Decimal;
+
+    fn add(self, other: Decimal) -> Decimal {
+        if self.mantissa == 0 {
+            return other;
+        }
+        if other.mantissa == 0 {
+            return self;
+        }
+        let (high, low) = if self.exponent >= other.exponent {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        // the high mantissa is scaled by up to as many digits as keep the sum in an i128, which is
+        // at least 3 since it has at most 34
+        let room = 37 - digits(high.mantissa) as i64;
+        let shift = high.exponent as i64 - low.exponent as i64;
+        if shift <= room {
+            let high_mantissa = high.mantissa * 10i128.pow(shift as u32);
+            return round(high_mantissa + low.mantissa, low.exponent as i64);
+        }
+        // the low operand is less than a hundredth of the high one, so its digits below the scaled
+        // high mantissa only matter for rounding. They are cut off and stand in as a last digit
+        // which is not 0, which rounds the sum the same way as all of them would.
+        let exponent = high.exponent as i64 - room;
+        let cut = exponent - low.exponent as i64;
+        let divisor = u32::try_from(cut)
+            .ok()
+            .and_then(|cut| 10i128.checked_pow(cut));
+        let (mut low_mantissa, inexact) = match divisor {
+            Some(divisor) => (low.mantissa / divisor, low.mantissa % divisor != 0),
+            None => (0, true),
+        };
+        if inexact && low_mantissa % 10 == 0 {
+            low_mantissa += low.mantissa.signum();
+        }
+        let high_mantissa = high.mantissa * 10i128.pow(room as u32);
+        round(high_mantissa + low_mantissa, exponent)
+    }
+}
+
+impl Sub for Decimal {
+    type Output = Decimal;
+
+    fn sub(self, other: Decimal) -> Decimal {
+        self + -other
+    }
+}
+
+impl Mul for Decimal {
+    type Output = Decimal;
+
+    fn mul(self, other: Decimal) -> Decimal {
+        let exponent = self.exponent as i64 + other.exponent as i64;
+        if let Some(mantissa) = self.mantissa.checked_mul(other.mantissa) {
+            return round(mantissa, exponent);
+        }
+        // the product of two mantissas of up to 34 digits, in limbs of 17 digits
+        const LIMB: u128 = 100_000_000_000_000_000;
+        let (left, right) = (self.mantissa.unsigned_abs(), other.mantissa.unsigned_abs());
+        let (left_high, left_low) = (left / LIMB, left % LIMB);
+        let (right_high, right_low) = (right / LIMB, right % LIMB);
+        let low = left_low * right_low;
+        let middle = left_high * right_low + left_low * right_high + low / LIMB;
+        let high = left_high * right_high + middle / LIMB;
+        let digits = format!(
+            "{}{:017}{:017}{:017}",
+            high / LIMB,
+            high % LIMB,
+            middle % LIMB,
+            low % LIMB
+        );
+        let negative = (self.mantissa < 0) != (other.mantissa < 0);
+        round_digits(negative, &digits, exponent, false)
+    }
+}
+
+/// the number of digits of a mantissa, where 0 has none
+fn digits(mantissa: i128) -> usize {
+    mantissa
+        .unsigned_abs()
+        .checked_ilog10()
+        .map_or(0, |log| log as usize + 1)
+}
+
+/// the decimal closest to the mantissa times ten to the power of the exponent
+fn round(mantissa: i128, exponent: i64) -> Decimal {
+    round_digits(
+        mantissa < 0,
+        &mantissa.unsigned_abs().to_string(),
+        exponent,
+        false,
+    )
+}
+
+/// the decimal closest to the digits times ten to the power of the exponent, rounded to the
+/// precision half to even. Sticky says there were digits which are not 0 below the ones given, so
+/// that the value is a little more than they are. An exponent too large or small for a decimal makes it 0, which only
+/// happens long after the doubles it is used with overflow.
+fn round_digits(negative: bool, digits: &str, exponent: i64, sticky: bool) -> Decimal {
+    let digits = digits.trim_start_matches('0');
+    let (kept, dropped) = digits.split_at(digits.len().min(PRECISION));
+    let mut mantissa: i128 = kept.parse().unwrap_or(0);
+    let round_up = match dropped.as_bytes().first() {
+        Some(b'6'..=b'9') => true,
+        Some(b'5') => {
+            sticky || dropped[1..].bytes().any(|digit| digit != b'0') || mantissa % 2 == 1
+        }
+        _ => false,
+    };
+    if round_up {
+        mantissa += 1;
+    }
+    let mut exponent = exponent + dropped.len() as i64;
+    while mantissa != 0 && mantissa % 10 == 0 {
+        mantissa /= 10;
+        exponent += 1;
+    }
+    match i32::try_from(exponent) {
+        Ok(exponent) if mantissa != 0 => Decimal {
+            mantissa: if negative { -mantissa } else { mantissa },
+            exponent,
+        },
+        _ => Decimal::ZERO,
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal {
+    /// the sign of the difference, which rounding never changes
+    fn cmp(&self, other: &Self) -> Ordering {
+        (*self - *other).mantissa.cmp(&0)
+    }
+}
+
+/// reads a decimal written like a number literal, with an optional sign and exponent, e.g. `-1.5`
+/// or `15e-1`
+impl FromStr for Decimal {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let error = || anyhow!("{} is not a decimal", text);
+        let (negative, unsigned) = match text.strip_prefix('-') {
+            Some(unsigned) => (true, unsigned),
+            None => (false, text),
+        };
+        let (digits, exponent) = match unsigned.split_once(['e', 'E']) {
+            Some((digits, exponent)) => (digits, exponent.parse::<i64>().map_err(|_| error())?),
+            None => (unsigned, 0),
+        };
+        let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        let digits = format!("{}{}", integer, fraction);
+        if digits.is_empty() || !digits.bytes().all(|digit| digit.is_ascii_digit()) {
+            return Err(error());
+        }
+        Ok(round_digits(
+            negative,
+            &digits,
+            exponent - fraction.len() as i64,
+            false,
+        ))
+    }
+}
+
+/// writes the decimal without an exponent, like numbers are printed, e.g. `0.3` or `115`
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.mantissa < 0 {
+            write!(f, "-")?;
+        }
+        let digits = self.mantissa.unsigned_abs().to_string();
+        if self.exponent >= 0 {
+            return write!(f, "{}{}", digits, "0".repeat(self.exponent as usize));
+        }
+        let fraction = self.exponent.unsigned_abs() as usize;
+        match digits.len().checked_sub(fraction) {
+            Some(0) | None => write!(f, "0.{}{}", "0".repeat(fraction - digits.len()), digits),
+            Some(integer) => write!(f, "{}.{}", &digits[..integer], &digits[integer..]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// the decimal written in the text
+    fn decimal(text: &str) -> Decimal {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    /// tests that decimals are exact up to 34 significant digits and rounded half to even past them
+    fn decimal_test() {
+        assert_eq!(decimal("0.1") + decimal("0.2"), decimal("0.3"));
+        assert_eq!(decimal("1.15") * decimal("100"), decimal("115"));
+        assert_eq!(
+            (decimal("1e16") + decimal("1")).to_string(),
+            "10000000000000001"
+        );
+        assert_eq!(decimal("0.30").to_string(), "0.3");
+        assert_eq!(decimal("-0.025").to_string(), "-0.025");
+        assert_eq!(decimal("1.5e3").to_string(), "1500");
+        assert_eq!(decimal("0.3") + decimal("-0.3"), Decimal::ZERO);
+        assert!("1.2.3".parse::<Decimal>().is_err());
+        assert!("".parse::<Decimal>().is_err());
+
+        let third = decimal("1").divide(decimal("3")).unwrap();
+        assert_eq!(third.to_string(), format!("0.{}", "3".repeat(34)));
+        assert_eq!(
+            decimal("2").divide(decimal("3")).unwrap().to_string(),
+            format!("0.{}7", "6".repeat(33))
+        );
+        assert_ne!(third * decimal("3"), decimal("1"));
+        assert_eq!(decimal("0.3").divide(decimal("0.1")), Some(decimal("3")));
+        assert_eq!(decimal("1").divide(Decimal::ZERO), None);
+
+        // 35 digits round half to even, and digits far below the precision still break a tie
+        assert_eq!(decimal(&format!("1{}5", "0".repeat(33))), decimal("1e34"));
+        assert_eq!(
+            decimal(&format!("1{}15", "0".repeat(32))),
+            decimal(&format!("1{}2e1", "0".repeat(32)))
+        );
+        assert_eq!(decimal("1e300") + decimal("1e-300"), decimal("1e300"));
+        assert_eq!(
+            decimal("5e33") + decimal("0.5000000001"),
+            decimal(&format!("5{}1", "0".repeat(32)))
+        );
+        // the product of mantissas which overflow an i128 is rounded
+        let big = decimal(&"9".repeat(34));
+        assert_eq!(
+            (big * big).to_string(),
+            format!("{}8{}", "9".repeat(33), "0".repeat(34))
+        );
+
+        assert!(decimal("0.1") < decimal("0.2"));
+        assert!(decimal("-1") < decimal("1e-300"));
+        assert_eq!(Decimal::from_f64(0.1), Some(decimal("0.1")));
+        assert_eq!(Decimal::from_f64(f64::NAN), None);
+        assert_eq!(decimal("0.3").to_f64(), 0.3);
+    }
+
+    #[test]
+    /// tests that the operators use decimals in decimal mode or when an operand is a decimal
+    fn binary_test() {
+        let add = Operator::Add { line: 1 };
+        let number = |number| Literal::Number(number);
+        let decimal_mode = NumberMode::Decimal;
+        assert_eq!(
+            binary(decimal_mode, &add, &number(0.1), &number(0.2)),
+            Some(Literal::Decimal(decimal("0.3")))
+        );
+        assert_eq!(
+            binary(NumberMode::Binary, &add, &number(0.1), &number(0.2)),
+            None
+        );
+        assert_eq!(
+            binary(
+                NumberMode::Binary,
+                &Operator::LessThan { line: 1 },
+                &Literal::Decimal(decimal("0.3")),
+                &number(0.2)
+            ),
+            Some(Literal::Boolean(false))
+        );
+        let divide = Operator::Divide { line: 1 };
+        assert_eq!(
+            binary(decimal_mode, &divide, &number(1.0), &number(0.0)),
+            None
+        );
+        assert_eq!(
+            binary(decimal_mode, &add, &number(f64::INFINITY), &number(1.0)),
+            None
+        );
+        assert_eq!(
+            binary(
+                decimal_mode,
+                &add,
+                &Literal::String("a".to_string()),
+                &number(1.0)
+            ),
+            None
+        );
+
+        assert_eq!(NumberMode::default(), NumberMode::Binary);
+        assert_eq!("decimal".parse::<NumberMode>().unwrap(), decimal_mode);
+        assert!("float".parse::<NumberMode>().is_err());
+    }
+}
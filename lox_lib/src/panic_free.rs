@@ -0,0 +1,177 @@
+//!
+//! This module contains the tests which check that the public API never panics, however malformed
+//! its input is. Embedders run untrusted sources, so a bad source has to come back as an error.
+//!
+//! The sources are the scripts of the fuzz corpus in `test_files/fuzz` and mutations of them: the
+//! prefixes of each script, and copies with spans deleted, duplicated or replaced by fragments which
+//! tend to trip up lexers and parsers. The mutations come from a seeded generator, so a failure
+//! always reproduces.
+
+use crate::analyze::{AuditReport, CallGraph, StringReport};
+use crate::emit::{self, EmitFormat};
+use crate::fix::find_fixes;
+use crate::interpreter::{CancellationToken, Interpreter};
+use crate::lexer::Lexer;
+use crate::messages::Language;
+use crate::minify::minify;
+use crate::parser::{parse_define, Parser};
+use crate::random::Random;
+use crate::semantic;
+use anyhow::anyhow;
+use std::collections::HashMap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// the fragments spliced into the sources
+const FRAGMENTS: &[&str] = &[
+    "(", ")", "{", "}", "[", "]", ":", ",", ".", "...", ";", "=", "==", "+", "-", "*", "/", "!",
+    "<", "\"", "'", "b\"", "\\x", "\\", "1.2.3", "0.", ".5", "1e999", "9n", "-0", "nil", "this",
+    "super", "super.", "return", "break", "class", "fun", "var", "const", "import", "try", "catch",
+    "throw", "é", "日本", "\u{200d}", "\n", "\t", "//", "/*", "*/", "\0",
+];
+
+/// the mutations made of every script besides its prefixes
+const MUTATIONS: usize = 300;
+
+/// the scripts of the fuzz corpus with their names
+fn corpus() -> Vec<(String, String)> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../test_files/fuzz");
+    let mut scripts = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| {
+            let path = entry.unwrap().path();
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            (name, std::fs::read_to_string(&path).unwrap())
+        })
+        .collect::<Vec<_>>();
+    scripts.sort();
+    scripts
+}
+
+/// the most prefixes taken of a script, which keeps the long scripts of the corpus fast to run
+const MAX_PREFIXES: usize = 1000;
+
+/// the prefixes of the source, cut at every character, or evenly spread if it is longer than
+/// [`MAX_PREFIXES`] characters
+fn prefixes(source: &str) -> impl Iterator<Item = &str> {
+    let step = source.chars().count().div_ceil(MAX_PREFIXES).max(1);
+    source
+        .char_indices()
+        .step_by(step)
+        .map(move |(i, _)| &source[..i])
+}
+
+/// a random index into something of the length, which must not be empty
+fn index(random: &mut Random, length: usize) -> usize {
+    random.range(0, length as i64 - 1) as usize
+}
+
+/// a copy of the source with a random span deleted, duplicated or replaced by a fragment
+fn mutate(source: &str, random: &mut Random) -> String {
+    let boundaries = source
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain([source.len()])
+        .collect::<Vec<_>>();
+    let a = boundaries[index(random, boundaries.len())];
+    let b = boundaries[index(random, boundaries.len())];
+    let (start, end) = (a.min(b), a.max(b).min(a.min(b) + 16));
+    let end = boundaries
+        .iter()
+        .copied()
+        .find(|&i| i >= end)
+        .unwrap_or(source.len());
+    let fragment = FRAGMENTS[index(random, FRAGMENTS.len())];
+    match index(random, 3) {
+        0 => format!("{}{}", &source[..start], &source[end..]),
+        1 => format!("{}{}", &source[..end], &source[start..]),
+        _ => format!("{}{}{}", &source[..start], fragment, &source[end..]),
+    }
+}
+
+/// runs the source through every stage of the pipeline the public API offers, returning the
+/// message of the panic if one of them panicked
+fn run_everything(source: &str, watchdog: &Mutex<Option<CancellationToken>>) -> Option<String> {
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        let _ = parse_define(source);
+        let mut lexer = Lexer::new().with_comments(true);
+        let Ok(tokens) = lexer.lex(source) else {
+            return;
+        };
+        let _ = emit::tokens(&tokens, EmitFormat::Json);
+        let semantic_tokens = semantic::classify(source, &tokens);
+        let _ = semantic::encode(source, &semantic_tokens);
+        let _ = StringReport::new("fuzz", &tokens);
+
+        let mut parser = Parser::new()
+            .with_comments(lexer.take_comments())
+            .with_automatic_semicolons(true);
+        let statements = parser.parse(tokens.clone());
+        let _ = find_fixes(
+            source,
+            &tokens,
+            parser.inferred_semicolons(),
+            Language::English,
+        );
+        let _ = emit::ast(&statements, EmitFormat::Json);
+        let _ = minify(&statements);
+        let _ = AuditReport::new("fuzz", &statements, &HashMap::new(), |_, path| {
+            Err(anyhow!("no module {}", path))
+        });
+        let _ = CallGraph::new("fuzz", &statements, |_, path| {
+            Err(anyhow!("no module {}", path))
+        });
+
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(std::io::sink()))
+            .with_heap_limit(1 << 20);
+        *watchdog.lock().unwrap() = Some(interpreter.cancellation_token());
+        let _ = interpreter.eval(source);
+    }));
+    result.err().map(|panic| {
+        panic
+            .downcast_ref::<&str>()
+            .map(ToString::to_string)
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_default()
+    })
+}
+
+#[test]
+/// tests that no source of the corpus, no prefix of one and no mutation of one makes the public
+/// API panic. Sources which loop forever are cancelled by a watchdog.
+fn no_panics_test() {
+    let watchdog: Arc<Mutex<Option<CancellationToken>>> = Arc::default();
+    let done = Arc::new(Mutex::new(false));
+    let canceller = {
+        let (watchdog, done) = (watchdog.clone(), done.clone());
+        std::thread::spawn(move || {
+            while !*done.lock().unwrap() {
+                std::thread::sleep(Duration::from_millis(20));
+                if let Some(token) = watchdog.lock().unwrap().as_ref() {
+                    token.cancel();
+                }
+            }
+        })
+    };
+
+    let mut random = Random::new(3290);
+    let mut panics = Vec::new();
+    for (name, script) in corpus() {
+        let mutations = (0..MUTATIONS).map(|_| mutate(&script, &mut random));
+        let sources = prefixes(&script)
+            .map(str::to_string)
+            .chain([script.clone()])
+            .chain(mutations);
+        for source in sources {
+            if let Some(message) = run_everything(&source, &watchdog) {
+                panics.push(format!("{}: {:?} panicked with {}", name, source, message));
+            }
+        }
+    }
+    *done.lock().unwrap() = true;
+    canceller.join().unwrap();
+    assert!(panics.is_empty(), "{}", panics.join("\n"));
+}
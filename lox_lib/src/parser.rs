@@ -1,20 +1,91 @@
-use crate::ast::{ExprNode, Literal, Operator, StmtNode};
-use crate::lexer::{Token, TokenType};
+use crate::ast::{ClassDecl, ExprNode, FunctionDecl, Literal, Operator, StmtNode};
+use crate::lexer::{Comment, Lexer, Token, TokenType};
+use crate::messages::{Language, MessageId};
 use anyhow::{anyhow, Result};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Display;
+use std::rc::Rc;
 
 /// a parser for the Lox language. It creates an Abstract Syntax Tree (AST) from a token stream.
+#[derive(Default)]
 pub struct Parser {
     panic_mode: bool,
     errors: Vec<String>,
+    /// compile time constants which are substituted for their names while parsing
+    defines: HashMap<String, Literal>,
+    /// the defines given from outside of the source, which keep their value when it defines them
+    overrides: HashSet<String>,
+    /// how deeply nested the code currently being parsed is
+    depth: usize,
+    /// whether a statement can end at the end of a line without a semicolon
+    automatic_semicolons: bool,
+    /// the line of the last token that was consumed
+    previous_line: u32,
+    /// how many loops the statement currently being parsed is inside of, within its function
+    loop_depth: usize,
+    /// the kind of the innermost function the statement currently being parsed is inside of, if any
+    function_kind: Option<FunctionKind>,
+    /// the kinds of the classes the statement currently being parsed is inside of, innermost last
+    classes: Vec<ClassKind>,
+    /// whether the statement which failed last was parsed to its end, such as a declaration of a name
+    /// taken by a constant, so that recovering from the error must not skip the statement after it
+    statement_complete: bool,
+    /// the constants declared at the top level, which can not be declared again in the same scope
+    constants: HashSet<String>,
+    /// the constants declared in each block the code being parsed is in, outermost first
+    block_constants: Vec<HashSet<String>>,
+    /// the comments of the source which have not been attached to a statement yet
+    comments: VecDeque<Comment>,
+    /// the number of tokens given to the last parse
+    token_count: usize,
+    /// the indices of the tokens before which a semicolon was inferred in the last parse
+    inferred_semicolons: Vec<usize>,
+    /// the language syntax errors are reported in
+    language: Language,
+}
+
+/// the kinds of functions, which differ in what their bodies are allowed to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FunctionKind {
+    Function,
+    Method,
+    /// the `init` method of a class, which can not return a value
+    Initializer,
+    /// a method marked with `class`, which is called on the class and so has no `this`
+    ClassMethod,
+}
+
+/// the kinds of classes, which differ in whether their methods can use `super`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ClassKind {
+    Class,
+    Subclass,
 }
 
 type ParserBinaryFn = fn(&mut Parser, &mut Vec<Token>) -> Result<ExprNode>;
 
+/// the methods of a class, or its class methods
+type Methods = Vec<Rc<FunctionDecl>>;
+
+/// the deepest that groupings, unary operators, statements and chains of operations can be nested
+/// before the parser reports an error, since parsing and evaluating them recurses on the Rust stack. A level costs
+/// around 12KB of stack in debug builds, so this keeps parsing within a 2MB thread stack.
+pub const MAX_NESTING_DEPTH: usize = 128;
+
+/// the most parameters a function can have and arguments a call can pass.
+pub const MAX_ARGUMENTS: usize = 255;
+
 /*
  Reference Lox Expression Grammar (So far)
 
 
-    expression     -> equality ;
+    expression     -> assignment ;
+
+    assignment     -> ( call "." )? IDENTIFIER "=" assignment | logic_or ;
+
+    logic_or       -> logic_and ( "or" logic_and )* ;
+
+    logic_and      -> equality ( "and" equality )* ;
 
     equality       -> comparison ( ("!=" | "==") comparison )* ;
 
@@ -24,18 +95,99 @@ type ParserBinaryFn = fn(&mut Parser, &mut Vec<Token>) -> Result<ExprNode>;
 
     factor         -> unary ( ("*" | "/") unary)* ;
 
-    unary 		    -> ("!" | "-")  unary | primary ;
+    unary 		    -> ("!" | "-")  unary | call ;
+
+    call           -> primary ( "(" arguments? ")" | "." IDENTIFIER )* ;
+
+    arguments      -> expression ( "," expression )* ;
 
-    primary         -> NUMBER | STRING | "True" | "False" | "Nil" | "("expression")" ;
+    primary         -> NUMBER | STRING | "True" | "False" | "Nil" | "this" | "("expression")"
+                       | IDENTIFIER ;
 */
 impl Parser {
     pub fn new() -> Self {
         Self {
             panic_mode: false,
             errors: Vec::new(),
+            defines: HashMap::new(),
+            overrides: HashSet::new(),
+            depth: 0,
+            automatic_semicolons: false,
+            previous_line: 0,
+            loop_depth: 0,
+            function_kind: None,
+            classes: Vec::new(),
+            statement_complete: false,
+            constants: HashSet::new(),
+            block_constants: Vec::new(),
+            comments: VecDeque::new(),
+            token_count: 0,
+            inferred_semicolons: Vec::new(),
+            language: Language::English,
         }
     }
 
+    /// attaches the comments of the source, kept with [`Lexer::with_comments`], to the statements
+    /// they are written around. A comment goes to the statement after it, or to the statement it is
+    /// written in if it is inside of one, and the comments at the end of a block or of the program go
+    /// to the last statement before them.
+    pub fn with_comments(mut self, comments: Vec<Comment>) -> Self {
+        self.comments = comments.into();
+        self
+    }
+
+    /// lets statements end at the end of a line without a semicolon, when the statement would
+    /// otherwise be complete. This is mostly meant to make the REPL friendlier, by default semicolons
+    /// are mandatory like in canonical Lox.
+    ///
+    /// The rule is conservative: a semicolon is only inferred where the parser would otherwise report
+    /// a missing one, and only when the next token is on a later line, is a `}` or ends the program.
+    /// Since expressions are parsed greedily, a line starting with an operator such as `-` continues
+    /// the expression on the line before it.
+    pub fn with_automatic_semicolons(mut self, automatic_semicolons: bool) -> Self {
+        self.automatic_semicolons = automatic_semicolons;
+        self
+    }
+
+    /// sets the language syntax errors are reported in, English by default.
+    pub fn with_language(mut self, language: Language) -> Self {
+        self.language = language;
+        self
+    }
+
+    /// adds compile time constants defined before the source, such as by the earlier lines of the
+    /// REPL, which the source can not define again.
+    pub fn with_defines(mut self, defines: HashMap<String, Literal>) -> Self {
+        self.defines.extend(defines);
+        self
+    }
+
+    /// adds compile time constants which take precedence over the ones of the source, such as the
+    /// ones given with `--define` on the command line.
+    pub fn with_overrides(mut self, overrides: HashMap<String, Literal>) -> Self {
+        self.overrides.extend(overrides.keys().cloned());
+        self.defines.extend(overrides);
+        self
+    }
+
+    /// adds the constants declared before the code being parsed in the scope it runs in, such as the
+    /// global constants declared by earlier lines of the REPL, which it can not declare again.
+    pub fn with_constants(mut self, constants: HashSet<String>) -> Self {
+        self.constants.extend(constants);
+        self
+    }
+
+    /// the indices in the tokens of the last parse of the tokens before which a semicolon was inferred,
+    /// see [`Parser::with_automatic_semicolons`].
+    pub fn inferred_semicolons(&self) -> &[usize] {
+        &self.inferred_semicolons
+    }
+
+    /// the compile time constants known to the parser, including the ones defined in parsed source.
+    pub fn defines(&self) -> &HashMap<String, Literal> {
+        &self.defines
+    }
+
     /// This function is used to simplify the implementation of binary expressions. By taking  
     /// advantage of the fact that the grammar for most binary expressions is very similiar
     ///
@@ -53,7 +205,7 @@ impl Parser {
         while let Some(operator) = self.match_operator_tokens(token_types, tokens) {
             let right = precedence_fn(self, tokens)?;
             node = ExprNode::BinaryExpr {
-                operator: operator,
+                operator,
                 left: Box::new(node),
                 right: Box::new(right),
             };
@@ -62,7 +214,134 @@ impl Parser {
     }
 
     pub(crate) fn expression(&mut self, tokens: &mut Vec<Token>) -> Result<ExprNode> {
-        self.equality(tokens)
+        self.assignment(tokens)
+    }
+
+    /// assignment -> ( ( call "." )? IDENTIFIER | call "[" expression "]" )
+    ///               ( "=" | "+=" | "-=" | "*=" | "/=" ) assignment
+    ///               | logic_or ;
+    ///
+    /// The target is parsed as an ordinary expression first, since the parser only finds out that it
+    /// is an assignment once it reaches the `=`. Only a variable, a property or an element of a list
+    /// or map is a valid target.
+    ///
+    /// A compound assignment such as `a += 1` is desugared into `a = a + 1`, where the operator keeps
+    /// the line of the `+=` so that errors of the operation point at it. Since the target is evaluated
+    /// twice, the object of a property target and the list and index of an element target can't
+    /// contain a call, whose side effects would happen twice.
+    fn assignment(&mut self, tokens: &mut Vec<Token>) -> Result<ExprNode> {
+        let target = self.logic_or(tokens)?;
+
+        let operator: Option<fn(u32) -> Operator> =
+            match Parser::peek(tokens).map(|token| &token.token_type) {
+                Some(TokenType::Equal) => None,
+                Some(TokenType::PlusEqual) => Some(|line| Operator::Add { line }),
+                Some(TokenType::MinusEqual) => Some(|line| Operator::Subtract { line }),
+                Some(TokenType::StarEqual) => Some(|line| Operator::Multiply { line }),
+                Some(TokenType::SlashEqual) => Some(|line| Operator::Divide { line }),
+                _ => return Ok(target),
+            };
+        let equals = self.advance(tokens);
+        // assignment is right associative so `a = b = 1` assigns 1 to b and then to a
+        let mut value = self.nested(tokens, Parser::assignment)?;
+
+        if let Some(operator) = operator {
+            match &target {
+                ExprNode::Get { object, .. } if !Parser::without_calls(object) => {
+                    return Err(self.error(
+                        MessageId::AssignToCallProperty,
+                        &[&equals.lexeme(), &equals.line],
+                    ));
+                }
+                ExprNode::Index { object, index, .. }
+                    if !Parser::without_calls(object) || !Parser::without_calls(index) =>
+                {
+                    return Err(self.error(
+                        MessageId::AssignToCallElement,
+                        &[&equals.lexeme(), &equals.line],
+                    ));
+                }
+                _ => {}
+            }
+            value = ExprNode::BinaryExpr {
+                left: Box::new(target.clone()),
+                operator: operator(equals.line),
+                right: Box::new(value),
+            };
+        }
+
+        match target {
+            ExprNode::Variable { name, .. } => Ok(ExprNode::Assign {
+                name,
+                value: Box::new(value),
+                line: equals.line,
+            }),
+            ExprNode::Get { object, name, .. } => Ok(ExprNode::Set {
+                object,
+                name,
+                value: Box::new(value),
+                line: equals.line,
+            }),
+            ExprNode::Index { object, index, .. } => Ok(ExprNode::SetIndex {
+                object,
+                index,
+                value: Box::new(value),
+                line: equals.line,
+            }),
+            _ => Err(self.error(MessageId::InvalidAssignmentTarget, &[&equals.line])),
+        }
+    }
+
+    /// whether an expression only reads values, such as a variable, `this`, a literal or a property
+    /// or element of one, and so can be evaluated twice
+    fn without_calls(expr: &ExprNode) -> bool {
+        match expr {
+            ExprNode::Variable { .. } | ExprNode::This { .. } | ExprNode::Literal(_) => true,
+            ExprNode::Grouping(expr) | ExprNode::UnaryExpr { right: expr, .. } => {
+                Parser::without_calls(expr)
+            }
+            ExprNode::BinaryExpr { left, right, .. } | ExprNode::Logical { left, right, .. } => {
+                Parser::without_calls(left) && Parser::without_calls(right)
+            }
+            ExprNode::Get { object, .. } => Parser::without_calls(object),
+            ExprNode::Index { object, index, .. } => {
+                Parser::without_calls(object) && Parser::without_calls(index)
+            }
+            _ => false,
+        }
+    }
+
+    /// logic_or -> logic_and ( "or" logic_and )* ;
+    fn logic_or(&mut self, tokens: &mut Vec<Token>) -> Result<ExprNode> {
+        self.logical_expression_match(Parser::logic_and, TokenType::Or, tokens)
+    }
+
+    /// logic_and -> equality ( "and" equality )* ;
+    fn logic_and(&mut self, tokens: &mut Vec<Token>) -> Result<ExprNode> {
+        self.logical_expression_match(Parser::equality, TokenType::And, tokens)
+    }
+
+    /// like [`Parser::binary_expression_match`], but produces logical nodes since the right operand
+    /// of `and` and `or` is only evaluated when it is needed.
+    fn logical_expression_match(
+        &mut self,
+        precedence_fn: ParserBinaryFn,
+        token_type: TokenType,
+        tokens: &mut Vec<Token>,
+    ) -> Result<ExprNode> {
+        let mut node = precedence_fn(self, tokens)?;
+
+        while let Some(operator) =
+            self.match_operator_tokens(std::slice::from_ref(&token_type), tokens)
+        {
+            let right = precedence_fn(self, tokens)?;
+            node = ExprNode::Logical {
+                operator,
+                left: Box::new(node),
+                right: Box::new(right),
+            };
+        }
+        Ok(node)
     }
 
     /// Performs a binary equality operation on possible expressions. It follows the following grammar.
@@ -105,247 +384,1605 @@ impl Parser {
         if let Some(operator) =
             self.match_operator_tokens(&[TokenType::Bang, TokenType::Minus], tokens)
         {
-            let right = self.unary(tokens)?;
+            let right = self.nested(tokens, Parser::unary)?;
             let expr = ExprNode::UnaryExpr {
-                operator: operator,
+                operator,
                 right: Box::new(right),
             };
             return Ok(expr);
         };
-        self.primary(tokens)
+        self.call(tokens)
     }
 
-    // primary -> NUMBER | STRING | "True" | "False" | "Nil" | "("expression")" ;
-    fn primary(&mut self, tokens: &mut Vec<Token>) -> Result<ExprNode> {
-        return self.match_literals(tokens);
+    /// call -> primary ( "(" arguments? ")" | "." IDENTIFIER | index )* ;
+    ///
+    /// a call can be called again, e.g. `make_counter()()` calls the function the first call returns,
+    /// and properties and indices can be chained, e.g. `a.b[0].c()`.
+    fn call(&mut self, tokens: &mut Vec<Token>) -> Result<ExprNode> {
+        let mut expr = self.primary(tokens)?;
+        loop {
+            let link = match Parser::peek(tokens).map(|token| &token.token_type) {
+                Some(TokenType::LeftParen) => Parser::finish_call,
+                Some(TokenType::Dot) => Parser::property,
+                Some(TokenType::LeftBracket) => Parser::index,
+                _ => return Ok(expr),
+            };
+            expr = link(self, tokens, expr)?;
+        }
     }
 
-    fn print_stmt(&mut self, tokens: &mut Vec<Token>) -> Result<StmtNode> {
-        let _ = tokens.remove(0); // remove print token
-        let expr = self.expression(tokens)?;
-        match Parser::consume(TokenType::Semicolon, tokens) {
-            Ok(_) => Ok(StmtNode::PrintStmt(expr)),
-            Err(e) => Err(e),
-        }
+    /// parses an index or a slice, starting at the opening bracket
+    ///
+    /// index -> "[" expression "]" | "[" expression? ":" expression? "]" ;
+    fn index(&mut self, tokens: &mut Vec<Token>, object: ExprNode) -> Result<ExprNode> {
+        let bracket = self.advance(tokens);
+        let start = match Parser::peek(tokens).map(|token| &token.token_type) {
+            Some(TokenType::Colon) => None,
+            _ => Some(self.nested(tokens, Parser::expression)?),
+        };
+        let is_slice =
+            Parser::peek(tokens).map(|token| &token.token_type) == Some(&TokenType::Colon);
+        let node = match start {
+            Some(index) if !is_slice => ExprNode::Index {
+                object: Box::new(object),
+                index: Box::new(index),
+                line: bracket.line,
+            },
+            start => {
+                self.advance(tokens);
+                let end = match Parser::peek(tokens).map(|token| &token.token_type) {
+                    Some(TokenType::RightBracket) => None,
+                    _ => Some(self.nested(tokens, Parser::expression)?),
+                };
+                ExprNode::Slice {
+                    object: Box::new(object),
+                    start: start.map(Box::new),
+                    end: end.map(Box::new),
+                    line: bracket.line,
+                }
+            }
+        };
+        self.consume(TokenType::RightBracket, tokens)
+            .map_err(|_| self.error(MessageId::ExpectedIndexEnd, &[&bracket.line]))?;
+        Ok(node)
     }
 
-    /// This function will match tokens with the possible
-    pub(crate) fn statement(&mut self, tokens: &mut Vec<Token>) -> StmtNode {
-        // again using a Dequeue would make this much faster
-        if Parser::match_token(
-            TokenType::Print,
-            tokens.get(0).expect("No tokens in statement"),
-        ) {
-            match self.print_stmt(tokens) {
-                Ok(print_stmt) => return print_stmt,
-                Err(err) => StmtNode::ErrStmt(err.to_string()),
-            }
-        } else {
-            match self.expression(tokens) {
-                Ok(expr) => match Parser::consume(TokenType::Semicolon, tokens) {
-                    Ok(_) => StmtNode::ExprStmt(expr),
-                    Err(_) => {
-                        StmtNode::ErrStmt(anyhow!("Expected ';' after an expression").to_string())
-                    }
-                },
-                Err(err) => StmtNode::ErrStmt(err.to_string()),
+    /// list -> "[" ( expression ( "," expression )* ","? )? "]" ;
+    fn list(&mut self, tokens: &mut Vec<Token>) -> Result<ExprNode> {
+        let bracket = self.advance(tokens);
+        let mut elements = Vec::new();
+        while Parser::peek(tokens).map(|token| &token.token_type) != Some(&TokenType::RightBracket)
+        {
+            elements.push(self.nested(tokens, Parser::expression)?);
+            if Parser::peek(tokens).map(|token| &token.token_type) != Some(&TokenType::Comma) {
+                break;
             }
+            self.advance(tokens);
         }
+        self.consume(TokenType::RightBracket, tokens)
+            .map_err(|_| self.error(MessageId::ExpectedListEnd, &[&bracket.line]))?;
+        Ok(ExprNode::List {
+            elements,
+            line: bracket.line,
+        })
     }
 
-    /// returns true or false if the token matches the token_type that is passed in
-    fn match_token(token_type: TokenType, token: &Token) -> bool {
-        return token.token_type == token_type;
+    /// map -> "{" ( entry ( "," entry )* ","? )? "}" ;
+    /// entry -> expression ":" expression ;
+    fn map(&mut self, tokens: &mut Vec<Token>) -> Result<ExprNode> {
+        let brace = self.advance(tokens);
+        let mut entries = Vec::new();
+        while Parser::peek(tokens).map(|token| &token.token_type) != Some(&TokenType::RightBrace) {
+            let key = self.nested(tokens, Parser::expression)?;
+            self.consume(TokenType::Colon, tokens)
+                .map_err(|_| self.error(MessageId::ExpectedMapColon, &[&self.previous_line]))?;
+            entries.push((key, self.nested(tokens, Parser::expression)?));
+            if Parser::peek(tokens).map(|token| &token.token_type) != Some(&TokenType::Comma) {
+                break;
+            }
+            self.advance(tokens);
+        }
+        self.consume(TokenType::RightBrace, tokens)
+            .map_err(|_| self.error(MessageId::ExpectedMapEnd, &[&brace.line]))?;
+        Ok(ExprNode::Map {
+            entries,
+            line: brace.line,
+        })
     }
 
-    /// consumes a token from the tokens vector stream if it matches the TokenType that is expected passed in
-    /// otherwise returns an error with the actual
-    fn consume(expected_token: TokenType, tokens: &mut Vec<Token>) -> Result<()> {
-        // token is not copy because of the the string literal not being copy. Otherwise clones are fine and
-        // not expected to do much here
-        let token_match = Parser::match_token(
-            expected_token.clone(),
-            tokens.get(0).expect("Expected token in fn consume"),
-        );
+    /// parses the property of an object, starting at the dot. Properties are not variables, so
+    /// defines do not apply to them.
+    fn property(&mut self, tokens: &mut Vec<Token>, object: ExprNode) -> Result<ExprNode> {
+        let dot = self.advance(tokens);
+        let name = match Parser::peek(tokens) {
+            Some(token) if token.token_type == TokenType::Identifier => token.lexeme().to_string(),
+            _ => return Err(self.error(MessageId::ExpectedPropertyName, &[&dot.line])),
+        };
+        self.advance(tokens);
+        Ok(ExprNode::Get {
+            object: Box::new(object),
+            name,
+            line: dot.line,
+        })
+    }
 
-        if token_match {
-            // TODO use a dequeue here for easy speedups
-            tokens.remove(0);
-            return Ok(());
-        } else {
-            return Err(anyhow!(
-                "Expected {:?}  in line {:?} but found other expression",
-                expected_token,
-                tokens.get(0).unwrap().line
-            ));
+    /// arguments -> expression ( "," expression )* ;
+    ///
+    /// parses the arguments of a call to the callee, starting at the opening parenthesis.
+    fn finish_call(&mut self, tokens: &mut Vec<Token>, callee: ExprNode) -> Result<ExprNode> {
+        let paren = self.advance(tokens);
+        let mut arguments = Vec::new();
+        if Parser::peek(tokens).map(|token| &token.token_type) != Some(&TokenType::RightParen) {
+            loop {
+                if arguments.len() >= MAX_ARGUMENTS {
+                    return Err(
+                        self.error(MessageId::TooManyArguments, &[&MAX_ARGUMENTS, &paren.line])
+                    );
+                }
+                arguments.push(self.nested(tokens, Parser::expression)?);
+                if Parser::peek(tokens).map(|token| &token.token_type) != Some(&TokenType::Comma) {
+                    break;
+                }
+                self.advance(tokens);
+            }
         }
+        self.consume(TokenType::RightParen, tokens)
+            .map_err(|_| self.error(MessageId::ExpectedArgumentsEnd, &[&paren.line]))?;
+        Ok(ExprNode::Call {
+            callee: Box::new(callee),
+            arguments,
+            line: self.previous_line,
+        })
     }
 
-    /// Generates a syntax tree from a stream of tokens.
-    pub fn parse(&mut self, mut tokens: Vec<Token>) -> Vec<StmtNode> {
-        let mut statements = Vec::new();
-
-        while tokens.get(0).unwrap().token_type != TokenType::Eof {
-            let statement = self.statement(&mut tokens);
-            statements.push(statement);
-
-            if self.panic_mode {
-                self.synchronize(&mut tokens);
+    // primary -> NUMBER | STRING | "True" | "False" | "Nil" | "this" | "super" "." IDENTIFIER
+    //            | "("expression")" | list | map | IDENTIFIER ;
+    //
+    // identifiers naming a define are replaced by its value, any other identifier is a variable.
+    fn primary(&mut self, tokens: &mut Vec<Token>) -> Result<ExprNode> {
+        if let Some(token) = Parser::peek(tokens) {
+            if token.token_type == TokenType::This {
+                return self.this(tokens);
+            }
+            if token.token_type == TokenType::Super {
+                return self.super_method(tokens);
+            }
+            if token.token_type == TokenType::LeftBracket {
+                return self.list(tokens);
+            }
+            // a brace only starts a block where a statement is expected, so in an expression it is
+            // always a map
+            if token.token_type == TokenType::LeftBrace {
+                return self.map(tokens);
+            }
+            if token.token_type == TokenType::Identifier {
+                let node = match self.defines.get(token.lexeme()) {
+                    Some(value) => ExprNode::Literal(value.clone()),
+                    None => ExprNode::Variable {
+                        name: token.lexeme().to_string(),
+                        line: token.line,
+                    },
+                };
+                self.advance(tokens);
+                return Ok(node);
             }
         }
-        statements
+        self.match_literals(tokens)
     }
 
-    fn is_at_end(&self, tokens: &Vec<Token>) -> bool{ 
-        tokens.get(0).unwrap().token_type == TokenType::Eof
+    /// parses `this`, which is only allowed inside of a class
+    fn this(&mut self, tokens: &mut Vec<Token>) -> Result<ExprNode> {
+        let this_token = self.advance(tokens);
+        if self.classes.is_empty() {
+            return Err(self.error(MessageId::ThisOutsideClass, &[&this_token.line]));
+        }
+        if self.function_kind == Some(FunctionKind::ClassMethod) {
+            return Err(self.error(MessageId::ThisInClassMethod, &[&this_token.line]));
+        }
+        Ok(ExprNode::This {
+            line: this_token.line,
+        })
     }
 
-    /// called after the parser enters panic mode from failing to parse a file. It will try to discard all
-    /// tokens related to the parser error until a semi-colon is found or another expression start is found
-    fn synchronize(&mut self, tokens: &mut Vec<Token>) {
-
-        while !self.is_at_end(tokens){
-            if Parser::match_token(
-                TokenType::Semicolon,
-                tokens
-                    .get(0)
-                    .expect("Expected there to be a token in token stream"),
-            ) {
-                tokens.remove(0);
-                self.panic_mode = false;
-                return;
+    /// parses `super.method`, which is only allowed inside of a class with a superclass
+    fn super_method(&mut self, tokens: &mut Vec<Token>) -> Result<ExprNode> {
+        let super_token = self.advance(tokens);
+        self.consume(TokenType::Dot, tokens)
+            .map_err(|_| self.error(MessageId::ExpectedDotAfterSuper, &[&super_token.line]))?;
+        let method = self
+            .identifier(tokens)
+            .map_err(|_| self.error(MessageId::ExpectedSuperclassMethod, &[&super_token.line]))?;
+        match self.classes.last() {
+            None => Err(self.error(MessageId::SuperOutsideClass, &[&super_token.line])),
+            Some(ClassKind::Class) => {
+                Err(self.error(MessageId::SuperWithoutSuperclass, &[&super_token.line]))
             }
-            self.panic_mode = false; 
-            match tokens.get(0).unwrap().token_type {
-                TokenType::Class => return,
-                TokenType::Fun => return,
-                TokenType::Var => return,
-                TokenType::For => return,
-                TokenType::If => return,
-                TokenType::While => return,
-                TokenType::Print => return,
-                TokenType::Return => return,
-                _ => {self.panic_mode = true;}
+            Some(ClassKind::Subclass) if self.function_kind == Some(FunctionKind::ClassMethod) => {
+                Err(self.error(MessageId::SuperInClassMethod, &[&super_token.line]))
             }
-            tokens.remove(0);
+            Some(ClassKind::Subclass) => Ok(ExprNode::Super {
+                method,
+                line: super_token.line,
+            }),
         }
-        
     }
 
-    // TODO this is whack. Needs more type safety to prevent the wrong token type from being passed in and silently
-    // failing.
-    //
-    /// Tries to match the given tokens to the next token in the Iterator/Stream,
-    /// if the tokens match, it returns the operator token, otherwise it returns None/
-    ///
-    /// ### Panics
-    /// If the given tokens are not some sort of operator
-    fn match_operator_tokens(
-        &self,
-        match_tokens: &[TokenType],
-        tokens: &mut Vec<Token>,
-    ) -> Option<Operator> {
-        let mut out = None;
+    /// classDecl -> "class" IDENTIFIER ( "<" IDENTIFIER )? "{" ( "class"? function )* "}" ;
+    fn class_decl(&mut self, tokens: &mut Vec<Token>) -> Result<StmtNode> {
+        let class_token = self.advance(tokens); // remove class token
+        let name = self
+            .identifier(tokens)
+            .map_err(|_| self.error(MessageId::ExpectedClassName, &[&class_token.line]))?;
+        let superclass = self.superclass(tokens, class_token.line)?;
+        self.consume(TokenType::LeftBrace, tokens)
+            .map_err(|_| self.error(MessageId::ExpectedBodyStart, &[&name, &class_token.line]))?;
+
+        self.classes.push(match superclass {
+            Some(_) => ClassKind::Subclass,
+            None => ClassKind::Class,
+        });
+        let methods = self.methods(tokens);
+        self.classes.pop();
+        if methods.is_err() {
+            // skip the rest of the body, so that parsing carries on after the class
+            self.skip_body(tokens);
+        }
+        let (methods, class_methods) = methods?;
+        self.consume(TokenType::RightBrace, tokens)
+            .map_err(|_| self.error(MessageId::ExpectedBodyEnd, &[&name, &class_token.line]))?;
+
+        // the body is parsed first, so that parsing carries on after the class
+        if matches!(&superclass, Some(ExprNode::Variable { name: superclass, .. }) if *superclass == name)
+        {
+            return Err(self.error(MessageId::InheritFromItself, &[&class_token.line]));
+        }
+
+        Ok(StmtNode::ClassStmt(Rc::new(ClassDecl {
+            name,
+            superclass,
+            methods,
+            class_methods,
+            line: class_token.line,
+            doc: None,
+        })))
+    }
 
-        if let Some(token) = tokens.get(0) {
-            if match_tokens.contains(&token.token_type) {
-                out = Some(Operator::try_from(token).unwrap());
+    /// parses the `< Superclass` of a class declaration, if it has one
+    fn superclass(&mut self, tokens: &mut Vec<Token>, line: u32) -> Result<Option<ExprNode>> {
+        if !Parser::peek(tokens).is_some_and(|token| token.token_type == TokenType::Less) {
+            return Ok(None);
+        }
+        self.advance(tokens);
+        match Parser::peek(tokens) {
+            Some(token) if token.token_type == TokenType::Identifier => {
+                let superclass = ExprNode::Variable {
+                    name: token.lexeme().to_string(),
+                    line: token.line,
+                };
+                self.advance(tokens);
+                Ok(Some(superclass))
             }
+            _ => Err(self.error(MessageId::ExpectedSuperclassName, &[&line])),
         }
-        // this second match is done to remove the matched token from the iterator
-        match out {
-            Some(operator) => {
-                // TODO: using a Vec leads to constant O(n) time complexity for every match.
-                // quick fix is to use Deque
-                tokens.remove(0);
-                Some(operator)
+    }
+
+    /// parses the methods and the class methods of a class up to the closing brace of its body, which
+    /// is left to the class.
+    fn methods(&mut self, tokens: &mut Vec<Token>) -> Result<(Methods, Methods)> {
+        let mut methods = Vec::new();
+        let mut class_methods = Vec::new();
+        while let Some(token) = Parser::peek(tokens) {
+            if matches!(token.token_type, TokenType::RightBrace | TokenType::Eof) {
+                break;
+            }
+            let line = token.line;
+            let is_class_method = token.token_type == TokenType::Class;
+            if is_class_method {
+                self.advance(tokens);
+            }
+            let name = self
+                .identifier(tokens)
+                .map_err(|_| self.error(MessageId::ExpectedMethodName, &[&line]))?;
+            let kind = match name.as_str() {
+                _ if is_class_method => FunctionKind::ClassMethod,
+                "init" => FunctionKind::Initializer,
+                _ => FunctionKind::Method,
+            };
+            let method = self.function(tokens, name, kind, line)?;
+            match kind {
+                FunctionKind::ClassMethod => class_methods.push(method),
+                _ => methods.push(method),
             }
-            None => None,
         }
+        Ok((methods, class_methods))
     }
 
-    fn match_literals(&mut self, tokens: &mut Vec<Token>) -> Result<ExprNode> {
-        let mut node: Option<ExprNode> = None;
+    /// skips the tokens up to and including the closing brace of the body the parser is in
+    fn skip_body(&mut self, tokens: &mut Vec<Token>) {
+        let mut depth = 0;
+        while let Some(token) = Parser::peek(tokens) {
+            match token.token_type {
+                TokenType::Eof => return,
+                TokenType::LeftBrace => depth += 1,
+                TokenType::RightBrace if depth == 0 => {
+                    self.advance(tokens);
+                    return;
+                }
+                TokenType::RightBrace => depth -= 1,
+                _ => {}
+            }
+            self.advance(tokens);
+        }
+    }
 
-        if let Some(token) = tokens.get(0) {
-            match &token.token_type {
-                TokenType::Number(number) => {
-                    node = Some(ExprNode::Literal(Literal::Number(*number)))
+    /// funDecl -> "fun" function ;
+    fn fun_decl(&mut self, tokens: &mut Vec<Token>) -> Result<StmtNode> {
+        let fun_token = self.advance(tokens); // remove fun token
+        let name = self
+            .identifier(tokens)
+            .map_err(|_| self.error(MessageId::ExpectedFunctionName, &[&fun_token.line]))?;
+        let declaration = self.function(tokens, name, FunctionKind::Function, fun_token.line)?;
+        Ok(StmtNode::FunctionStmt(declaration))
+    }
+
+    /// function -> IDENTIFIER "(" parameters? ")" block ;
+    ///
+    /// parses the rest of a function or method once its name is consumed. The line is the one of its
+    /// declaration.
+    fn function(
+        &mut self,
+        tokens: &mut Vec<Token>,
+        name: String,
+        kind: FunctionKind,
+        line: u32,
+    ) -> Result<Rc<FunctionDecl>> {
+        self.consume(TokenType::LeftParen, tokens)
+            .map_err(|_| match kind {
+                FunctionKind::Function => {
+                    self.error(MessageId::ExpectedFunctionParameters, &[&line])
                 }
-                TokenType::String(string) => {
-                    node = Some(ExprNode::Literal(Literal::String(string.clone())))
+                _ => self.error(MessageId::ExpectedMethodParameters, &[&line]),
+            })?;
+
+        let mut params = Vec::new();
+        let mut rest = None;
+        let mut duplicate = None;
+        if Parser::peek(tokens).map(|token| &token.token_type) != Some(&TokenType::RightParen) {
+            loop {
+                if params.len() >= MAX_ARGUMENTS {
+                    return Err(self.error(MessageId::TooManyParameters, &[&MAX_ARGUMENTS, &line]));
                 }
-                TokenType::False => node = Some(ExprNode::Literal(Literal::Boolean(false))),
-                TokenType::True => node = Some(ExprNode::Literal(Literal::Boolean(true))),
-                TokenType::Nil => node = Some(ExprNode::Literal(Literal::Nil)),
-                _ => {
-                    // do nothing in case of left parenthesis which needs a mutable reference to tokens
+                let is_rest = Parser::peek(tokens).map(|token| &token.token_type)
+                    == Some(&TokenType::Ellipsis);
+                if is_rest {
+                    self.advance(tokens);
+                }
+                let param = self
+                    .identifier(tokens)
+                    .map_err(|_| self.error(MessageId::ExpectedParameterName, &[&line]))?;
+                // reported once the body is parsed, so that parsing carries on after the function
+                if params.contains(&param) && duplicate.is_none() {
+                    duplicate = Some(param.clone());
+                }
+                if is_rest {
+                    rest = Some(param);
+                    break;
+                }
+                params.push(param);
+                if Parser::peek(tokens).map(|token| &token.token_type) != Some(&TokenType::Comma) {
+                    break;
                 }
+                self.advance(tokens);
             }
         }
+        self.consume(TokenType::RightParen, tokens)
+            .map_err(|_| match &rest {
+                Some(rest) => self.error(MessageId::RestParameterNotLast, &[&rest, &line]),
+                None => self.error(MessageId::ExpectedParametersEnd, &[&line]),
+            })?;
+        if Parser::peek(tokens).map(|token| &token.token_type) != Some(&TokenType::LeftBrace) {
+            return Err(self.error(MessageId::ExpectedBodyStart, &[&name, &line]));
+        }
 
-        if let Some(literal_node) = node {
-            tokens.remove(0);
-            return Ok(literal_node);
+        // loops outside of the function can not be left from inside of it
+        let loop_depth = std::mem::take(&mut self.loop_depth);
+        let function_kind = self.function_kind.replace(kind);
+        let body = self.nested(tokens, Parser::block);
+        self.function_kind = function_kind;
+        self.loop_depth = loop_depth;
+        let body = match body? {
+            StmtNode::BlockStmt(statements) => statements,
+            statement => vec![statement],
+        };
+        if let Some(param) = duplicate {
+            return Err(self.error(MessageId::DuplicateParameter, &[&param, &line]));
         }
 
-        if tokens[0].token_type == TokenType::LeftParen {
-            tokens.remove(0);
-            let expr = self.expression(tokens)?;
-            if tokens[0].token_type == TokenType::RightParen {
-                tokens.remove(0);
-                return Ok(ExprNode::Grouping(Box::new(expr)));
+        Ok(Rc::new(FunctionDecl {
+            name,
+            params,
+            rest,
+            body,
+            line,
+            doc: None,
+        }))
+    }
+
+    /// consumes an identifier which is not the name of a define, returning its name.
+    fn identifier(&mut self, tokens: &mut Vec<Token>) -> Result<String> {
+        let name = match Parser::peek(tokens) {
+            Some(token) if token.token_type == TokenType::Identifier => token.lexeme().to_string(),
+            _ => return Err(self.error(MessageId::ExpectedIdentifier, &[])),
+        };
+        if self.defines.contains_key(&name) {
+            let line = Parser::peek(tokens)
+                .map(|token| token.line)
+                .unwrap_or_default();
+            return Err(self.error(MessageId::VariableNamedLikeDefine, &[&name, &line]));
+        }
+        self.advance(tokens);
+        Ok(name)
+    }
+
+    /// varDecl -> "var" IDENTIFIER ("=" expression)? ";" ;
+    fn var_decl(&mut self, tokens: &mut Vec<Token>) -> Result<StmtNode> {
+        let var_token = self.advance(tokens); // remove var token
+        let name = match Parser::peek(tokens) {
+            Some(token) if token.token_type == TokenType::Identifier => token.lexeme().to_string(),
+            _ => return Err(self.error(MessageId::ExpectedVariableName, &[&var_token.line])),
+        };
+        if self.defines.contains_key(&name) {
+            return Err(self.error(
+                MessageId::VariableNamedLikeDefine,
+                &[&name, &var_token.line],
+            ));
+        }
+        self.advance(tokens);
+
+        let initializer =
+            if Parser::peek(tokens).map(|token| &token.token_type) == Some(&TokenType::Equal) {
+                self.advance(tokens);
+                Some(self.expression(tokens)?)
             } else {
-                self.panic_mode = true;
-                self.send_err("Expected ')' after expression");
-                // TODO Synchronize
+                None
+            };
+        self.end_statement(tokens)?;
+        Ok(StmtNode::VarStmt { name, initializer })
+    }
+
+    /// importDecl -> "import" ( STRING ( "as" IDENTIFIER )? | IDENTIFIER
+    ///                 | "{" IDENTIFIER ( "," IDENTIFIER )* "}" "from" STRING ) ";" ;
+    ///
+    /// `as` and `from` are only special after the path and the names, so they can still name
+    /// variables.
+    fn import_decl(&mut self, tokens: &mut Vec<Token>) -> Result<StmtNode> {
+        let line = self.advance(tokens).line; // remove import token
+        let (path, name, names) = match Parser::peek(tokens).map(|token| &token.token_type) {
+            Some(TokenType::String(path)) => {
+                let path = path.clone();
+                self.advance(tokens);
+                let name = match Parser::peek(tokens) {
+                    Some(token)
+                        if token.token_type == TokenType::Identifier && token.lexeme() == "as" =>
+                    {
+                        self.advance(tokens);
+                        if Parser::peek(tokens).map(|token| &token.token_type)
+                            != Some(&TokenType::Identifier)
+                        {
+                            return Err(self.error(MessageId::ExpectedImportAlias, &[&line]));
+                        }
+                        Some(self.identifier(tokens)?)
+                    }
+                    _ => None,
+                };
+                (path, name, Vec::new())
+            }
+            Some(TokenType::Identifier) => {
+                let name = self.identifier(tokens)?;
+                (format!("{}.lox", name), Some(name), Vec::new())
+            }
+            Some(TokenType::LeftBrace) => {
+                self.advance(tokens);
+                let mut names = Vec::new();
+                loop {
+                    if Parser::peek(tokens).map(|token| &token.token_type)
+                        == Some(&TokenType::Identifier)
+                    {
+                        names.push(self.identifier(tokens)?);
+                    }
+                    match Parser::peek(tokens).map(|token| &token.token_type) {
+                        Some(TokenType::Comma) if !names.is_empty() => self.advance(tokens),
+                        Some(TokenType::RightBrace) if !names.is_empty() => {
+                            self.advance(tokens);
+                            break;
+                        }
+                        _ => {
+                            // the `}` is skipped as well, since it does not end a block
+                            while let Some(token) = Parser::peek(tokens) {
+                                match token.token_type {
+                                    TokenType::Semicolon | TokenType::Eof => break,
+                                    TokenType::RightBrace => {
+                                        self.advance(tokens);
+                                        break;
+                                    }
+                                    _ => self.advance(tokens),
+                                };
+                            }
+                            return Err(self.error(MessageId::ExpectedImportName, &[&line]));
+                        }
+                    };
+                }
+                match Parser::peek(tokens) {
+                    Some(token)
+                        if token.token_type == TokenType::Identifier
+                            && token.lexeme() == "from" =>
+                    {
+                        self.advance(tokens);
+                    }
+                    _ => return Err(self.error(MessageId::ExpectedImportFrom, &[&line])),
+                }
+                let path = match Parser::peek(tokens).map(|token| &token.token_type) {
+                    Some(TokenType::String(path)) => path.clone(),
+                    _ => return Err(self.error(MessageId::ExpectedImportFrom, &[&line])),
+                };
+                self.advance(tokens);
+                (path, None, names)
+            }
+            _ => return Err(self.error(MessageId::ExpectedImportPath, &[&line])),
+        };
+        self.end_statement(tokens)
+            .map_err(|_| self.error(MessageId::ExpectedSemicolonAfterImport, &[&line]))?;
+        Ok(StmtNode::ImportStmt {
+            path,
+            name,
+            names,
+            line,
+        })
+    }
+
+    /// exportDecl -> "export" ( classDecl | funDecl | varDecl | constDecl | importDecl ) ;
+    ///
+    /// `export` is only special before a declaration, so it can still name variables. An import
+    /// can only be exported when it has a name or names, since the names of a whole module are not
+    /// known before it runs.
+    fn export_decl(&mut self, tokens: &mut Vec<Token>) -> Result<StmtNode> {
+        let line = self.advance(tokens).line; // remove export token
+        let statement = match self.declaration(tokens) {
+            Some(StmtNode::ErrStmt(err)) => return Err(anyhow!(err)),
+            Some(statement) => statement,
+            None => return Err(self.error(MessageId::ExpectedExportDeclaration, &[&line])),
+        };
+        if self.depth > 0 {
+            self.statement_complete = true;
+            return Err(self.error(MessageId::ExportNotAtTopLevel, &[&line]));
+        }
+        if let StmtNode::ImportStmt {
+            name: None, names, ..
+        } = &statement
+        {
+            if names.is_empty() {
+                self.statement_complete = true;
+                return Err(self.error(MessageId::ExpectedExportDeclaration, &[&line]));
             }
         }
+        Ok(StmtNode::ExportStmt {
+            statement: Box::new(statement),
+            line,
+        })
+    }
 
-        self.panic_mode = true;
-        self.send_err(format!(
-            "an unsupported token was found! {:?}",
-            tokens.get(0)
-        ));
+    /// constDecl -> "const" IDENTIFIER "=" expression ";" ;
+    fn const_decl(&mut self, tokens: &mut Vec<Token>) -> Result<StmtNode> {
+        let const_token = self.advance(tokens); // remove const token
+        let name = match Parser::peek(tokens) {
+            Some(token) if token.token_type == TokenType::Identifier => token.lexeme().to_string(),
+            _ => return Err(self.error(MessageId::ExpectedConstantName, &[&const_token.line])),
+        };
+        if self.defines.contains_key(&name) {
+            return Err(self.error(
+                MessageId::ConstantNamedLikeDefine,
+                &[&name, &const_token.line],
+            ));
+        }
+        self.advance(tokens);
 
-        Err(anyhow!(format!(
-            "unsupported token {:?} in expression",
-            tokens.get(0).unwrap().token_type
-        )))
+        self.consume(TokenType::Equal, tokens).map_err(|_| {
+            self.error(
+                MessageId::UninitializedConstant,
+                &[&name, &const_token.line],
+            )
+        })?;
+        let initializer = self.expression(tokens)?;
+        self.end_statement(tokens)?;
+        Ok(StmtNode::ConstStmt { name, initializer })
     }
 
-    fn send_err<T: ToString>(&mut self, message: T) {
-        self.errors.push(message.to_string());
+    /// defineDecl -> "define" IDENTIFIER constant ";" ;
+    ///
+    /// Defines are resolved while parsing and never reach the interpreter, so they can only be at the
+    /// top level and a name can only be defined once. Only a define given from outside of the source,
+    /// see [`Parser::with_overrides`], replaces the value written in the script.
+    fn define_decl(&mut self, tokens: &mut Vec<Token>) -> Result<()> {
+        let define_token = self.advance(tokens); // remove define token
+        let name = match Parser::peek(tokens) {
+            Some(token) if token.token_type == TokenType::Identifier => token.lexeme().to_string(),
+            _ => return Err(self.error(MessageId::ExpectedDefineName, &[&define_token.line])),
+        };
+        self.advance(tokens);
+
+        let value = match self.unary(tokens)? {
+            ExprNode::Literal(literal) => literal,
+            ExprNode::UnaryExpr {
+                operator: Operator::Subtract { .. },
+                right,
+            } => match *right {
+                ExprNode::Literal(Literal::Number(number)) => Literal::Number(-number),
+                _ => return Err(self.define_value_error(&name, define_token.line)),
+            },
+            _ => return Err(self.define_value_error(&name, define_token.line)),
+        };
+        self.end_statement(tokens)?;
+
+        if self.depth > 0 {
+            self.statement_complete = true;
+            return Err(self.error(MessageId::DefineNotAtTopLevel, &[&name, &define_token.line]));
+        }
+        if self.overrides.contains(&name) {
+            return Ok(());
+        }
+        if self.defines.contains_key(&name) {
+            self.statement_complete = true;
+            return Err(self.error(MessageId::Redefined, &[&name, &define_token.line]));
+        }
+        self.defines.insert(name, value);
+        Ok(())
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
+    fn define_value_error(&self, name: &str, line: u32) -> anyhow::Error {
+        self.error(MessageId::DefineValueNotLiteral, &[&name, &line])
+    }
 
-    #[test]
-    fn error_recovery_test() { 
+    /// a declaration wrapped with the comments before it and inside of it, if there are any
+    fn commented_declaration(&mut self, tokens: &mut Vec<Token>) -> Option<StmtNode> {
+        let first_line = Parser::peek(tokens).map_or(0, |token| token.line);
+        // taken before parsing so that the statements nested in this one don't take them
+        let leading = self.comments_before(first_line);
+        let mut statement = self.declaration(tokens);
+        if matches!(statement, None | Some(StmtNode::ErrStmt(_))) {
+            for comment in leading.into_iter().rev() {
+                self.comments.push_front(comment);
+            }
+            return statement;
+        }
 
+        let trailing = self.comments_before(self.previous_line + 1);
+        if leading.is_empty() && trailing.is_empty() {
+            return statement;
+        }
+        let doc = Parser::doc_comment(&leading, first_line).or_else(|| match trailing.as_slice() {
+            [comment] => Parser::doc_text(comment).map(String::from),
+            _ => None,
+        });
+        match &mut statement {
+            Some(StmtNode::FunctionStmt(declaration)) => {
+                if let Some(declaration) = Rc::get_mut(declaration) {
+                    declaration.doc = doc;
+                }
+            }
+            Some(StmtNode::ClassStmt(declaration)) => {
+                if let Some(declaration) = Rc::get_mut(declaration) {
+                    declaration.doc = doc;
+                }
+            }
+            _ => {}
+        }
+        Some(StmtNode::Commented {
+            leading,
+            statement: Box::new(statement?),
+            trailing,
+        })
     }
 
-    #[test]
-    fn grouping_test() {
-        todo!()
+    /// the doc comment of a declaration starting in the line, which is the `///` comments on the lines
+    /// right before it without a blank line or another comment in between. A declaration without one
+    /// is documented by the `///` comment at the end of its last line instead, if it has one.
+    fn doc_comment(leading: &[Comment], line: u32) -> Option<String> {
+        let mut next = line;
+        let mut lines: Vec<&str> = leading
+            .iter()
+            .rev()
+            .take_while(|comment| {
+                let adjacent = comment.line + 1 == next;
+                next = comment.line;
+                adjacent
+            })
+            .map_while(Parser::doc_text)
+            .collect();
+        lines.reverse();
+        (!lines.is_empty()).then(|| lines.join("\n"))
     }
 
-    #[test]
-    /// testing that a statement without a semicolon returns errors as expected
-    fn statement_semi_colon_test() {}
+    /// the text of a `///` comment without the slashes and the space after them, or None for a plain
+    /// `//` comment, which is not a doc comment
+    fn doc_text(comment: &Comment) -> Option<&str> {
+        let text = comment.text.strip_prefix('/')?;
+        Some(text.strip_prefix(' ').unwrap_or(text))
+    }
 
-    #[test]
-    /// tests that using a print statement and an expression statement returns the expected syntax tree
-    fn statement_test() {
-        // "print(\"hello world\")";
-        let mut tokens = vec![
-            Token::new(TokenType::Print, "print".to_string(), 1),
-            Token::new(TokenType::LeftParen, "(".to_string(), 1),
-            Token::new(
-                TokenType::String("\"hello world\"".to_string()),
+    /// takes the comments which have not been attached yet and are written before the line
+    fn comments_before(&mut self, line: u32) -> Vec<Comment> {
+        let count = self
+            .comments
+            .iter()
+            .take_while(|comment| comment.line < line)
+            .count();
+        self.comments.drain(..count).collect()
+    }
+
+    /// attaches comments left over at the end of a block or program to the last statement before them
+    fn attach_trailing(statements: &mut [StmtNode], comments: Vec<Comment>) {
+        let Some(last) = statements.last_mut().filter(|_| !comments.is_empty()) else {
+            return;
+        };
+        match last {
+            StmtNode::Commented { trailing, .. } => trailing.extend(comments),
+            last => {
+                let statement = std::mem::replace(last, StmtNode::BlockStmt(Vec::new()));
+                *last = StmtNode::Commented {
+                    leading: Vec::new(),
+                    statement: Box::new(statement),
+                    trailing: comments,
+                };
+            }
+        }
+    }
+
+    /// declaration -> classDecl | funDecl | varDecl | constDecl | exportDecl | importDecl
+    ///                | defineDecl | statement ;
+    ///
+    /// returns None for declarations which are resolved by the parser and don't produce a statement.
+    fn declaration(&mut self, tokens: &mut Vec<Token>) -> Option<StmtNode> {
+        match Parser::peek(tokens).map(|token| &token.token_type) {
+            Some(TokenType::Class) => {
+                match self.class_decl(tokens).and_then(|stmt| self.declared(stmt)) {
+                    Ok(class_stmt) => Some(class_stmt),
+                    Err(err) => Some(StmtNode::ErrStmt(err.to_string())),
+                }
+            }
+            Some(TokenType::Fun) => {
+                match self.fun_decl(tokens).and_then(|stmt| self.declared(stmt)) {
+                    Ok(fun_stmt) => Some(fun_stmt),
+                    Err(err) => Some(StmtNode::ErrStmt(err.to_string())),
+                }
+            }
+            Some(TokenType::Var) => {
+                match self.var_decl(tokens).and_then(|stmt| self.declared(stmt)) {
+                    Ok(var_stmt) => Some(var_stmt),
+                    Err(err) => Some(StmtNode::ErrStmt(err.to_string())),
+                }
+            }
+            Some(TokenType::Const) => {
+                match self.const_decl(tokens).and_then(|stmt| self.declared(stmt)) {
+                    Ok(const_stmt) => Some(const_stmt),
+                    Err(err) => Some(StmtNode::ErrStmt(err.to_string())),
+                }
+            }
+            Some(TokenType::Identifier)
+                if Parser::peek(tokens).is_some_and(|token| token.lexeme() == "export")
+                    && matches!(
+                        tokens.get(1).map(|token| &token.token_type),
+                        Some(
+                            TokenType::Class
+                                | TokenType::Fun
+                                | TokenType::Var
+                                | TokenType::Const
+                                | TokenType::Import
+                        )
+                    ) =>
+            {
+                match self.export_decl(tokens) {
+                    Ok(export_stmt) => Some(export_stmt),
+                    Err(err) => Some(StmtNode::ErrStmt(err.to_string())),
+                }
+            }
+            Some(TokenType::Import) => match self.import_decl(tokens) {
+                Ok(import_stmt) => Some(import_stmt),
+                Err(err) => Some(StmtNode::ErrStmt(err.to_string())),
+            },
+            Some(TokenType::Define) => match self.define_decl(tokens) {
+                Ok(()) => None,
+                Err(err) => Some(StmtNode::ErrStmt(err.to_string())),
+            },
+            _ => Some(self.statement(tokens)),
+        }
+    }
+
+    /// A constant can not be declared again in its scope by any declaration, which would take away
+    /// that it is a constant.
+    fn declared(&mut self, statement: StmtNode) -> Result<StmtNode> {
+        let (name, line) = match &statement {
+            StmtNode::VarStmt { name, .. } | StmtNode::ConstStmt { name, .. } => {
+                (name.clone(), self.previous_line)
+            }
+            StmtNode::FunctionStmt(declaration) => (declaration.name.clone(), declaration.line),
+            StmtNode::ClassStmt(declaration) => (declaration.name.clone(), declaration.line),
+            _ => return Ok(statement),
+        };
+        let scope = match self.block_constants.last_mut() {
+            Some(scope) => scope,
+            None => &mut self.constants,
+        };
+        if scope.contains(&name) {
+            self.statement_complete = true;
+            return Err(self.error(MessageId::RedeclaredConstant, &[&name, &line]));
+        }
+        if let StmtNode::ConstStmt { .. } = statement {
+            scope.insert(name);
+        }
+        Ok(statement)
+    }
+
+    /// printStmt -> "print" expression ";" ;
+    ///
+    /// `print` is a statement and not a function, so `print("hello");` is parsed as a print statement
+    /// whose expression happens to be a grouping. It behaves exactly like `print "hello";`. Since
+    /// there are no call arguments, a comma inside the parenthesis is reported as an error.
+    fn print_stmt(&mut self, tokens: &mut Vec<Token>) -> Result<StmtNode> {
+        let print_token = self.advance(tokens); // remove print token
+        let expr = self.expression(tokens).map_err(|err| {
+            match Parser::peek(tokens).map(|token| &token.token_type) {
+                Some(TokenType::Comma) => {
+                    self.error(MessageId::PrintWithArguments, &[&print_token.line])
+                }
+                _ => err,
+            }
+        })?;
+        self.end_statement(tokens)?;
+        Ok(StmtNode::PrintStmt(expr))
+    }
+
+    /// statement -> expressionStmt | printStmt | ifStmt | whileStmt | doWhileStmt | forStmt
+    ///              | breakStmt | continueStmt | returnStmt | throwStmt | tryStmt | block ;
+    pub(crate) fn statement(&mut self, tokens: &mut Vec<Token>) -> StmtNode {
+        let statement = match Parser::peek(tokens).map(|token| &token.token_type) {
+            Some(TokenType::Print) => self.print_stmt(tokens),
+            Some(TokenType::If) => self.if_stmt(tokens),
+            Some(TokenType::While) => self.while_stmt(tokens),
+            Some(TokenType::Do) => self.do_while_stmt(tokens),
+            Some(TokenType::For) => self.for_stmt(tokens),
+            Some(TokenType::Break) => self.break_stmt(tokens),
+            Some(TokenType::Continue) => self.continue_stmt(tokens),
+            Some(TokenType::Return) => self.return_stmt(tokens),
+            Some(TokenType::Throw) => self.throw_stmt(tokens),
+            Some(TokenType::Try) => self.try_stmt(tokens),
+            Some(TokenType::LeftBrace) => self.nested(tokens, Parser::block),
+            Some(_) => self.expression_stmt(tokens),
+            None => {
+                return StmtNode::ErrStmt(self.error(MessageId::ExpectedStatement, &[]).to_string())
+            }
+        };
+        statement.unwrap_or_else(|err| StmtNode::ErrStmt(err.to_string()))
+    }
+
+    /// expressionStmt -> expression ";" ;
+    fn expression_stmt(&mut self, tokens: &mut Vec<Token>) -> Result<StmtNode> {
+        let expr = self.expression(tokens)?;
+        self.end_statement(tokens)
+            .map_err(|_| self.error(MessageId::ExpectedSemicolonAfterExpression, &[]))?;
+        Ok(StmtNode::ExprStmt(expr))
+    }
+
+    /// ifStmt -> "if" "(" expression ")" statement ( "else" statement )? ;
+    ///
+    /// an `else` belongs to the closest `if` before it.
+    fn if_stmt(&mut self, tokens: &mut Vec<Token>) -> Result<StmtNode> {
+        let if_token = self.advance(tokens); // remove if token
+        self.consume(TokenType::LeftParen, tokens)
+            .map_err(|_| self.error(MessageId::ExpectedParenAfterIf, &[&if_token.line]))?;
+        let condition = self.expression(tokens)?;
+        self.consume(TokenType::RightParen, tokens)
+            .map_err(|_| self.error(MessageId::ExpectedParenAfterIfCondition, &[&if_token.line]))?;
+
+        let then_branch = self.nested_statement(tokens)?;
+        let else_branch =
+            if Parser::peek(tokens).map(|token| &token.token_type) == Some(&TokenType::Else) {
+                self.advance(tokens);
+                Some(Box::new(self.nested_statement(tokens)?))
+            } else {
+                None
+            };
+        Ok(StmtNode::IfStmt {
+            condition,
+            then_branch: Box::new(then_branch),
+            else_branch,
+        })
+    }
+
+    /// whileStmt -> "while" "(" expression ")" statement ;
+    fn while_stmt(&mut self, tokens: &mut Vec<Token>) -> Result<StmtNode> {
+        let while_token = self.advance(tokens); // remove while token
+        self.consume(TokenType::LeftParen, tokens)
+            .map_err(|_| self.error(MessageId::ExpectedParenAfterWhile, &[&while_token.line]))?;
+        let condition = self.expression(tokens)?;
+        self.consume(TokenType::RightParen, tokens).map_err(|_| {
+            self.error(
+                MessageId::ExpectedParenAfterWhileCondition,
+                &[&while_token.line],
+            )
+        })?;
+        let body = self.loop_body(tokens)?;
+        Ok(StmtNode::WhileStmt {
+            condition,
+            body: Box::new(body),
+            increment: None,
+        })
+    }
+
+    /// doWhileStmt -> "do" statement "while" "(" expression ")" ";" ;
+    fn do_while_stmt(&mut self, tokens: &mut Vec<Token>) -> Result<StmtNode> {
+        let do_token = self.advance(tokens); // remove do token
+        let body = self.loop_body(tokens)?;
+        self.consume(TokenType::While, tokens)
+            .map_err(|_| self.error(MessageId::ExpectedWhileAfterDo, &[&do_token.line]))?;
+        self.consume(TokenType::LeftParen, tokens)
+            .map_err(|_| self.error(MessageId::ExpectedParenAfterWhile, &[&do_token.line]))?;
+        let condition = self.expression(tokens)?;
+        self.consume(TokenType::RightParen, tokens).map_err(|_| {
+            self.error(
+                MessageId::ExpectedParenAfterWhileCondition,
+                &[&do_token.line],
+            )
+        })?;
+        self.end_statement(tokens)
+            .map_err(|_| self.error(MessageId::ExpectedSemicolonAfterDo, &[&do_token.line]))?;
+        Ok(StmtNode::DoWhileStmt {
+            body: Box::new(body),
+            condition,
+        })
+    }
+
+    /// forStmt -> "for" "(" ( varDecl | expressionStmt | ";" ) expression? ";" expression? ")"
+    ///            statement ;
+    ///
+    /// A for loop is desugared into a while loop, so `for (var i = 0; i < 3; i = i + 1) body` becomes
+    /// `{ var i = 0; while (i < 3) body }` with `i = i + 1` as the increment of the while loop. A
+    /// missing condition loops forever.
+    fn for_stmt(&mut self, tokens: &mut Vec<Token>) -> Result<StmtNode> {
+        let for_token = self.advance(tokens); // remove for token
+        self.consume(TokenType::LeftParen, tokens)
+            .map_err(|_| self.error(MessageId::ExpectedParenAfterFor, &[&for_token.line]))?;
+
+        let initializer = match Parser::peek(tokens).map(|token| &token.token_type) {
+            Some(TokenType::Semicolon) => {
+                self.advance(tokens);
+                None
+            }
+            Some(TokenType::Var) => Some(self.var_decl(tokens)?),
+            _ => Some(self.expression_stmt(tokens)?),
+        };
+
+        let condition = match Parser::peek(tokens).map(|token| &token.token_type) {
+            Some(TokenType::Semicolon) => ExprNode::Literal(Literal::Boolean(true)),
+            _ => self.expression(tokens)?,
+        };
+        self.consume(TokenType::Semicolon, tokens).map_err(|_| {
+            self.error(
+                MessageId::ExpectedSemicolonAfterLoopCondition,
+                &[&for_token.line],
+            )
+        })?;
+
+        let increment = match Parser::peek(tokens).map(|token| &token.token_type) {
+            Some(TokenType::RightParen) => None,
+            _ => Some(self.expression(tokens)?),
+        };
+        self.consume(TokenType::RightParen, tokens)
+            .map_err(|_| self.error(MessageId::ExpectedParenAfterForClauses, &[&for_token.line]))?;
+
+        let body = self.loop_body(tokens)?;
+        let mut statement = StmtNode::WhileStmt {
+            condition,
+            body: Box::new(body),
+            increment,
+        };
+        if let Some(initializer) = initializer {
+            statement = StmtNode::BlockStmt(vec![initializer, statement]);
+        }
+        Ok(statement)
+    }
+
+    /// parses the body of a loop, in which `break` and `continue` can be used.
+    fn loop_body(&mut self, tokens: &mut Vec<Token>) -> Result<StmtNode> {
+        self.loop_depth += 1;
+        let body = self.nested_statement(tokens);
+        self.loop_depth -= 1;
+        body
+    }
+
+    /// breakStmt -> "break" ";" ;
+    fn break_stmt(&mut self, tokens: &mut Vec<Token>) -> Result<StmtNode> {
+        let break_token = self.advance(tokens); // remove break token
+        if self.loop_depth == 0 {
+            return Err(self.error(MessageId::BreakOutsideLoop, &[&break_token.line]));
+        }
+        self.end_statement(tokens).map_err(|_| {
+            self.error(MessageId::ExpectedSemicolonAfterBreak, &[&break_token.line])
+        })?;
+        Ok(StmtNode::BreakStmt {
+            line: break_token.line,
+        })
+    }
+
+    /// continueStmt -> "continue" ";" ;
+    fn continue_stmt(&mut self, tokens: &mut Vec<Token>) -> Result<StmtNode> {
+        let continue_token = self.advance(tokens); // remove continue token
+        if self.loop_depth == 0 {
+            return Err(self.error(MessageId::ContinueOutsideLoop, &[&continue_token.line]));
+        }
+        self.end_statement(tokens).map_err(|_| {
+            self.error(
+                MessageId::ExpectedSemicolonAfterContinue,
+                &[&continue_token.line],
+            )
+        })?;
+        Ok(StmtNode::ContinueStmt {
+            line: continue_token.line,
+        })
+    }
+
+    /// returnStmt -> "return" expression? ";" ;
+    fn return_stmt(&mut self, tokens: &mut Vec<Token>) -> Result<StmtNode> {
+        let return_token = self.advance(tokens); // remove return token
+        let Some(kind) = self.function_kind else {
+            return Err(self.error(MessageId::ReturnOutsideFunction, &[&return_token.line]));
+        };
+        let value = match self.at_statement_end(tokens) {
+            true => None,
+            false if kind == FunctionKind::Initializer => {
+                return Err(self.error(MessageId::ReturnValueFromInitializer, &[&return_token.line]))
+            }
+            false => Some(self.expression(tokens)?),
+        };
+        self.end_statement(tokens).map_err(|_| {
+            self.error(
+                MessageId::ExpectedSemicolonAfterReturn,
+                &[&return_token.line],
+            )
+        })?;
+        Ok(StmtNode::ReturnStmt {
+            value,
+            line: return_token.line,
+        })
+    }
+
+    /// throwStmt -> "throw" expression ";" ;
+    fn throw_stmt(&mut self, tokens: &mut Vec<Token>) -> Result<StmtNode> {
+        let line = self.advance(tokens).line; // remove throw token
+        let value = self.expression(tokens)?;
+        self.end_statement(tokens)
+            .map_err(|_| self.error(MessageId::ExpectedSemicolonAfterThrow, &[&line]))?;
+        Ok(StmtNode::ThrowStmt { value, line })
+    }
+
+    /// tryStmt -> "try" block "catch" "(" IDENTIFIER ")" block ;
+    fn try_stmt(&mut self, tokens: &mut Vec<Token>) -> Result<StmtNode> {
+        let line = self.advance(tokens).line; // remove try token
+        let body = self.try_block(tokens, "try", line)?;
+        self.consume(TokenType::Catch, tokens)
+            .map_err(|_| self.error(MessageId::ExpectedCatch, &[&line]))?;
+        self.consume(TokenType::LeftParen, tokens)
+            .map_err(|_| self.error(MessageId::ExpectedParenAfterCatch, &[&line]))?;
+        if Parser::peek(tokens).map(|token| &token.token_type) != Some(&TokenType::Identifier) {
+            return Err(self.error(MessageId::ExpectedExceptionName, &[&line]));
+        }
+        let name = self.identifier(tokens)?;
+        self.consume(TokenType::RightParen, tokens)
+            .map_err(|_| self.error(MessageId::ExpectedParenAfterExceptionName, &[&line]))?;
+        let handler = self.try_block(tokens, "catch", line)?;
+        Ok(StmtNode::TryStmt {
+            body,
+            name,
+            handler,
+        })
+    }
+
+    /// the statements of the block after `try` or `catch`, which must have braces
+    fn try_block(
+        &mut self,
+        tokens: &mut Vec<Token>,
+        keyword: &str,
+        line: u32,
+    ) -> Result<Vec<StmtNode>> {
+        if Parser::peek(tokens).map(|token| &token.token_type) != Some(&TokenType::LeftBrace) {
+            return Err(self.error(MessageId::ExpectedBlock, &[&keyword, &line]));
+        }
+        Ok(match self.nested(tokens, Parser::block)? {
+            StmtNode::BlockStmt(statements) => statements,
+            statement => vec![statement],
+        })
+    }
+
+    /// parses a statement which is part of another one, such as the branch of an if statement. A
+    /// syntax error in it is an error of the whole statement.
+    fn nested_statement(&mut self, tokens: &mut Vec<Token>) -> Result<StmtNode> {
+        match self.nested(tokens, |parser, tokens| Ok(parser.statement(tokens)))? {
+            StmtNode::ErrStmt(err) => Err(anyhow!(err)),
+            statement => Ok(statement),
+        }
+    }
+
+    /// block -> "{" declarations* "}" ;
+    ///
+    /// The parser recovers from errors inside of the block so that it can find the end of it, the
+    /// first error is then reported for the whole block.
+    fn block(&mut self, tokens: &mut Vec<Token>) -> Result<StmtNode> {
+        self.block_constants.push(HashSet::new());
+        let block = self.block_statements(tokens);
+        self.block_constants.pop();
+        block
+    }
+
+    /// parses the statements of a block, see [`Parser::block`]
+    fn block_statements(&mut self, tokens: &mut Vec<Token>) -> Result<StmtNode> {
+        let brace_token = self.advance(tokens); // remove left brace token
+        let mut statements = Vec::new();
+        let mut error = None;
+
+        loop {
+            match Parser::peek(tokens).map(|token| &token.token_type) {
+                Some(TokenType::RightBrace) => {
+                    let brace_line = self.advance(tokens).line;
+                    let comments = self.comments_before(brace_line);
+                    Parser::attach_trailing(&mut statements, comments);
+                    break;
+                }
+                None | Some(TokenType::Eof) => {
+                    return Err(self.error(MessageId::UnclosedBlock, &[&brace_token.line]))
+                }
+                Some(_) => {}
+            }
+
+            let remaining = tokens.len();
+            match self.commented_declaration(tokens) {
+                Some(StmtNode::ErrStmt(err)) => {
+                    error.get_or_insert(err);
+                    self.recover(tokens);
+                }
+                statement => statements.extend(statement),
+            }
+            // a statement that failed without consuming anything would otherwise be retried forever
+            if tokens.len() == remaining {
+                self.advance(tokens);
+            }
+        }
+
+        match error {
+            Some(err) => Err(anyhow!(err)),
+            None => Ok(StmtNode::BlockStmt(statements)),
+        }
+    }
+
+    /// runs parse_fn one nesting level deeper, reporting an error instead of recursing any further
+    /// once [`MAX_NESTING_DEPTH`] is reached.
+    fn nested<T>(
+        &mut self,
+        tokens: &mut Vec<Token>,
+        parse_fn: fn(&mut Parser, &mut Vec<Token>) -> Result<T>,
+    ) -> Result<T> {
+        let depth = self.depth;
+        let result = self.deepen(tokens).and_then(|_| parse_fn(self, tokens));
+        self.depth = depth;
+        result
+    }
+
+    /// goes one nesting level deeper, or reports an error once [`MAX_NESTING_DEPTH`] is reached
+    fn deepen(&mut self, tokens: &[Token]) -> Result<()> {
+        if self.depth >= MAX_NESTING_DEPTH {
+            self.panic_mode = true;
+            let line = Parser::peek(tokens)
+                .map(|token| token.line)
+                .unwrap_or_default();
+            return Err(self.error(MessageId::NestedTooDeeply, &[&MAX_NESTING_DEPTH, &line]));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// consumes the next token of the stream, keeping track of the line it is on.
+    fn advance(&mut self, tokens: &mut Vec<Token>) -> Token {
+        let token = tokens.remove(0);
+        self.previous_line = token.line;
+        token
+    }
+
+    /// consumes the semicolon ending a statement, or infers it when automatic semicolons are enabled
+    /// and the statement is followed by a line break, a `}` or the end of the program.
+    fn end_statement(&mut self, tokens: &mut Vec<Token>) -> Result<()> {
+        if Parser::peek(tokens).map(|token| &token.token_type) != Some(&TokenType::Semicolon)
+            && self.at_statement_end(tokens)
+        {
+            self.inferred_semicolons
+                .push(self.token_count - tokens.len());
+            return Ok(());
+        }
+        self.consume(TokenType::Semicolon, tokens)
+    }
+
+    /// whether the statement being parsed ends before the next token, either at a semicolon or where
+    /// an automatic semicolon would be inferred.
+    fn at_statement_end(&self, tokens: &[Token]) -> bool {
+        match Parser::peek(tokens) {
+            Some(token) if token.token_type == TokenType::Semicolon => true,
+            Some(token) if self.automatic_semicolons => {
+                token.line > self.previous_line
+                    || matches!(token.token_type, TokenType::Eof | TokenType::RightBrace)
+            }
+            _ => false,
+        }
+    }
+
+    /// returns the next token in the stream without consuming it. Only an empty token stream
+    /// returns None, a well formed stream always ends in an Eof token.
+    fn peek(tokens: &[Token]) -> Option<&Token> {
+        tokens.first()
+    }
+
+    /// returns true or false if the token matches the token_type that is passed in
+    fn match_token(token_type: TokenType, token: &Token) -> bool {
+        token.token_type == token_type
+    }
+
+    /// consumes a token from the tokens vector stream if it matches the TokenType that is expected passed in
+    /// otherwise returns an error with the actual
+    fn consume(&mut self, expected_token: TokenType, tokens: &mut Vec<Token>) -> Result<()> {
+        // token is not copy because of the the string literal not being copy. Otherwise clones are fine and
+        // not expected to do much here
+        match Parser::peek(tokens) {
+            Some(token) if Parser::match_token(expected_token.clone(), token) => {
+                // TODO use a dequeue here for easy speedups
+                self.advance(tokens);
+                Ok(())
+            }
+            Some(token) => Err(self.error(
+                MessageId::UnexpectedToken,
+                &[
+                    &format!("{:?}", expected_token),
+                    &format!("{:?}", token.line),
+                ],
+            )),
+            None => Err(self.error(
+                MessageId::UnexpectedEnd,
+                &[&format!("{:?}", expected_token)],
+            )),
+        }
+    }
+
+    /// Generates a syntax tree from a stream of tokens.
+    ///
+    /// Parsing stops at the first Eof token (or once the stream runs out), so an empty program or one
+    /// made up only of comments produces an empty list of statements.
+    pub fn parse(&mut self, mut tokens: Vec<Token>) -> Vec<StmtNode> {
+        let mut statements = Vec::new();
+        self.token_count = tokens.len();
+        self.inferred_semicolons.clear();
+
+        while !self.is_at_end(&tokens) {
+            let remaining = tokens.len();
+            let statement = self.commented_declaration(&mut tokens);
+            let is_err = matches!(statement, Some(StmtNode::ErrStmt(_)));
+            statements.extend(statement);
+
+            if self.panic_mode || is_err {
+                self.recover(&mut tokens);
+            }
+
+            // a statement that failed without consuming anything would otherwise be retried forever
+            if tokens.len() == remaining {
+                tokens.remove(0);
+            }
+        }
+        let comments = self.comments.drain(..).collect();
+        Parser::attach_trailing(&mut statements, comments);
+        statements
+    }
+
+    /// returns true once there are no more tokens to parse, either because the Eof token was reached
+    /// or because the token stream is empty.
+    fn is_at_end(&self, tokens: &[Token]) -> bool {
+        match Parser::peek(tokens) {
+            Some(token) => token.token_type == TokenType::Eof,
+            None => true,
+        }
+    }
+
+    /// skips to the start of the next statement after an error, unless the statement which failed
+    /// already ended
+    fn recover(&mut self, tokens: &mut Vec<Token>) {
+        if std::mem::take(&mut self.statement_complete) {
+            self.panic_mode = false;
+            return;
+        }
+        self.synchronize(tokens);
+    }
+
+    /// called after the parser enters panic mode from failing to parse a file. It will try to discard all
+    /// tokens related to the parser error until a semi-colon is found or another expression start is found
+    fn synchronize(&mut self, tokens: &mut Vec<Token>) {
+        self.panic_mode = false;
+
+        while let Some(token) = Parser::peek(tokens) {
+            match token.token_type {
+                // the end of a block is left for the block to consume
+                TokenType::Eof | TokenType::RightBrace => return,
+                TokenType::Semicolon => {
+                    self.advance(tokens);
+                    return;
+                }
+                TokenType::Class
+                | TokenType::Fun
+                | TokenType::Var
+                | TokenType::Const
+                | TokenType::Import
+                | TokenType::For
+                | TokenType::If
+                | TokenType::While
+                | TokenType::Do
+                | TokenType::Throw
+                | TokenType::Try
+                | TokenType::Print
+                | TokenType::Return
+                | TokenType::Break
+                | TokenType::Continue
+                | TokenType::Define => return,
+                _ => {
+                    self.advance(tokens);
+                }
+            }
+        }
+    }
+
+    /// Tries to match the given tokens to the next token in the Iterator/Stream,
+    /// if the tokens match, it returns the operator token, otherwise it returns None/
+    ///
+    /// Token types in `match_tokens` that have no corresponding [`Operator`] never match,
+    /// so passing the wrong token type in can not cause the parser to panic.
+    fn match_operator_tokens(
+        &mut self,
+        match_tokens: &[TokenType],
+        tokens: &mut Vec<Token>,
+    ) -> Option<Operator> {
+        let operator = Parser::peek(tokens)
+            .filter(|token| match_tokens.contains(&token.token_type))
+            .and_then(|token| Operator::try_from(token).ok())?;
+
+        // TODO: using a Vec leads to constant O(n) time complexity for every match.
+        // quick fix is to use Deque
+        self.advance(tokens);
+        Some(operator)
+    }
+
+    /// the value of a big integer literal, which is an error unless lox was built with the `bigint`
+    /// feature
+    fn bigint(&self, digits: &str, line: u32) -> Result<Literal> {
+        #[cfg(feature = "bigint")]
+        return digits
+            .parse()
+            .map(Literal::BigInt)
+            .map_err(|_| self.error(MessageId::InvalidBigInt, &[&digits, &line]));
+        #[cfg(not(feature = "bigint"))]
+        Err(self.error(MessageId::BigIntUnsupported, &[&digits, &line]))
+    }
+
+    fn match_literals(&mut self, tokens: &mut Vec<Token>) -> Result<ExprNode> {
+        let mut node: Option<ExprNode> = None;
+
+        if let Some(token) = Parser::peek(tokens) {
+            match &token.token_type {
+                TokenType::Number(number) => {
+                    node = Some(ExprNode::Literal(Literal::Number(*number)))
+                }
+                TokenType::BigInt(digits) => {
+                    node = Some(ExprNode::Literal(self.bigint(digits, token.line)?))
+                }
+                TokenType::String(string) => {
+                    node = Some(ExprNode::Literal(Literal::String(string.clone())))
+                }
+                TokenType::Bytes(bytes) => {
+                    node = Some(ExprNode::Literal(Literal::Bytes(bytes.as_slice().into())))
+                }
+                TokenType::False => node = Some(ExprNode::Literal(Literal::Boolean(false))),
+                TokenType::True => node = Some(ExprNode::Literal(Literal::Boolean(true))),
+                TokenType::Nil => node = Some(ExprNode::Literal(Literal::Nil)),
+                _ => {
+                    // do nothing in case of left parenthesis which needs a mutable reference to tokens
+                }
+            }
+        }
+
+        if let Some(literal_node) = node {
+            self.advance(tokens);
+            return Ok(literal_node);
+        }
+
+        if Parser::peek(tokens).map(|token| &token.token_type) == Some(&TokenType::LeftParen) {
+            self.advance(tokens);
+            let expr = self.nested(tokens, Parser::expression)?;
+            if Parser::peek(tokens).map(|token| &token.token_type) == Some(&TokenType::RightParen) {
+                self.advance(tokens);
+                return Ok(ExprNode::Grouping(Box::new(expr)));
+            } else {
+                self.panic_mode = true;
+                self.send_err("Expected ')' after expression");
+                return Err(self.error(MessageId::ExpectedGroupingEnd, &[]));
+            }
+        }
+
+        self.panic_mode = true;
+        self.send_err(format!(
+            "an unsupported token was found! {:?}",
+            Parser::peek(tokens)
+        ));
+
+        match Parser::peek(tokens) {
+            Some(token) => Err(self.error(
+                MessageId::UnsupportedToken,
+                &[&format!("{:?}", token.token_type)],
+            )),
+            None => Err(self.error(MessageId::ExpectedExpression, &[])),
+        }
+    }
+
+    /// a syntax error from the message catalog, in the language of the parser
+    fn error(&self, id: MessageId, args: &[&dyn Display]) -> anyhow::Error {
+        anyhow!(id.render_args(self.language, args))
+    }
+
+    fn send_err<T: ToString>(&mut self, message: T) {
+        self.errors.push(message.to_string());
+    }
+}
+
+/// parses a `NAME=VALUE` definition given on the command line. The value is lexed as a single Lox
+/// literal (`true`, `12`, `"text"`, `nil`), anything else is taken as a string.
+pub fn parse_define(definition: &str) -> Result<(String, Literal)> {
+    let (name, value) = definition.split_once('=').ok_or_else(|| {
+        anyhow!(
+            "Expected a define of the form NAME=VALUE but found {}",
+            definition
+        )
+    })?;
+    if name.is_empty() || !name.chars().all(|chr| chr.is_alphanumeric() || chr == '_') {
+        return Err(anyhow!("{} is not a valid define name", name));
+    }
+
+    let literal = match Lexer::new().lex(value).ok().as_deref() {
+        Some([token, eof]) if eof.token_type == TokenType::Eof => match &token.token_type {
+            TokenType::Number(number) => Literal::Number(*number),
+            #[cfg(feature = "bigint")]
+            TokenType::BigInt(digits) => Parser::new().bigint(digits, 1)?,
+            TokenType::String(string) => Literal::String(string.clone()),
+            TokenType::Bytes(bytes) => Literal::Bytes(bytes.as_slice().into()),
+            TokenType::True => Literal::Boolean(true),
+            TokenType::False => Literal::Boolean(false),
+            TokenType::Nil => Literal::Nil,
+            _ => Literal::String(value.to_string()),
+        },
+        _ => Literal::String(value.to_string()),
+    };
+    Ok((name.to_string(), literal))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    /// tests that the parser recovers from a malformed statement and keeps parsing the ones after it
+    fn error_recovery_test() {
+        // ") 1; print 2;"
+        let tokens = vec![
+            Token::new(TokenType::RightParen, ")".to_string(), 1),
+            Token::new(TokenType::Number(1.0), "1".to_string(), 1),
+            Token::new(TokenType::Semicolon, ";".to_string(), 1),
+            Token::new(TokenType::Print, "print".to_string(), 2),
+            Token::new(TokenType::Number(2.0), "2".to_string(), 2),
+            Token::new(TokenType::Semicolon, ";".to_string(), 2),
+            Token::new(TokenType::Eof, "".to_string(), 2),
+        ];
+
+        let mut parser = Parser::new();
+        let statements = parser.parse(tokens);
+        assert_eq!(statements.len(), 2);
+        assert!(matches!(statements[0], StmtNode::ErrStmt(_)));
+        assert_eq!(
+            statements[1],
+            StmtNode::PrintStmt(ExprNode::Literal(Literal::Number(2.0)))
+        );
+    }
+
+    #[test]
+    fn grouping_test() {
+        // (1 + 2) * 3
+        let mut tokens = vec![
+            Token::new(TokenType::LeftParen, "(".to_string(), 1),
+            Token::new(TokenType::Number(1.0), "1".to_string(), 1),
+            Token::new(TokenType::Plus, "+".to_string(), 1),
+            Token::new(TokenType::Number(2.0), "2".to_string(), 1),
+            Token::new(TokenType::RightParen, ")".to_string(), 1),
+            Token::new(TokenType::Star, "*".to_string(), 1),
+            Token::new(TokenType::Number(3.0), "3".to_string(), 1),
+            Token::new(TokenType::Eof, "".to_string(), 1),
+        ];
+        let mut parser = Parser::new();
+        let node = parser.expression(&mut tokens).unwrap();
+        let expected_node = ExprNode::BinaryExpr {
+            left: Box::new(ExprNode::Grouping(Box::new(ExprNode::BinaryExpr {
+                left: Box::new(ExprNode::Literal(Literal::Number(1.0))),
+                operator: Operator::Add { line: 1 },
+                right: Box::new(ExprNode::Literal(Literal::Number(2.0))),
+            }))),
+            operator: Operator::Multiply { line: 1 },
+            right: Box::new(ExprNode::Literal(Literal::Number(3.0))),
+        };
+        assert_eq!(node, expected_node);
+
+        // an unclosed grouping is an error rather than a panic
+        let mut tokens = vec![
+            Token::new(TokenType::LeftParen, "(".to_string(), 1),
+            Token::new(TokenType::Number(1.0), "1".to_string(), 1),
+            Token::new(TokenType::Eof, "".to_string(), 1),
+        ];
+        assert!(parser.expression(&mut tokens).is_err());
+    }
+
+    #[test]
+    /// testing that a statement without a semicolon returns errors as expected
+    fn statement_semi_colon_test() {
+        let mut tokens = vec![
+            Token::new(TokenType::Number(1.0), "1".to_string(), 1),
+            Token::new(TokenType::Eof, "".to_string(), 1),
+        ];
+        let mut parser = Parser::new();
+        let node = parser.statement(&mut tokens);
+        assert!(matches!(node, StmtNode::ErrStmt(_)));
+    }
+
+    #[test]
+    /// tests that a program with no statements parses to an empty list of statements
+    fn empty_program_test() {
+        let mut parser = Parser::new();
+        assert_eq!(parser.parse(Vec::new()), Vec::new());
+
+        let tokens = vec![Token::new(TokenType::Eof, "".to_string(), 0)];
+        assert_eq!(parser.parse(tokens), Vec::new());
+    }
+
+    #[test]
+    /// tests that comments before and after the statements of a program don't produce any statements
+    fn trailing_comment_test() {
+        let tokens = crate::lexer::Lexer::new()
+            .lex("// leading comment\nprint 1;\n// trailing comment")
+            .unwrap();
+        let mut parser = Parser::new();
+        let statements = parser.parse(tokens);
+        assert_eq!(
+            statements,
+            vec![StmtNode::PrintStmt(ExprNode::Literal(Literal::Number(1.0)))]
+        );
+
+        let tokens = crate::lexer::Lexer::new().lex("// only a comment").unwrap();
+        assert_eq!(parser.parse(tokens), Vec::new());
+    }
+
+    #[test]
+    /// tests that using a print statement and an expression statement returns the expected syntax tree
+    fn statement_test() {
+        // "print(\"hello world\")";
+        let mut tokens = vec![
+            Token::new(TokenType::Print, "print".to_string(), 1),
+            Token::new(TokenType::LeftParen, "(".to_string(), 1),
+            Token::new(
+                TokenType::String("\"hello world\"".to_string()),
                 "\"hello world\"".to_string(),
                 1,
             ),
@@ -354,12 +1991,1214 @@ mod test {
             Token::new(TokenType::Eof, "".to_string(), 1),
         ];
 
-        let mut parser = Parser::new();
-        let node = parser.statement(&mut tokens);
-        let expected_node = StmtNode::PrintStmt(ExprNode::Grouping(Box::new(ExprNode::Literal(
-            Literal::String("\"hello world\"".to_string()),
-        ))));
-        assert_eq!(node, expected_node);
+        let mut parser = Parser::new();
+        let node = parser.statement(&mut tokens);
+        let expected_node = StmtNode::PrintStmt(ExprNode::Grouping(Box::new(ExprNode::Literal(
+            Literal::String("\"hello world\"".to_string()),
+        ))));
+        assert_eq!(node, expected_node);
+    }
+
+    #[test]
+    /// tests that print with and without parenthesis parse to the same statement apart from the grouping
+    fn print_parenthesis_test() {
+        let mut parser = Parser::new();
+        let with_parens = crate::lexer::Lexer::new().lex("print(\"hello\");").unwrap();
+        let without_parens = crate::lexer::Lexer::new().lex("print \"hello\";").unwrap();
+
+        let hello = ExprNode::Literal(Literal::String("hello".to_string()));
+        assert_eq!(
+            parser.parse(with_parens),
+            vec![StmtNode::PrintStmt(ExprNode::Grouping(Box::new(
+                hello.clone()
+            )))]
+        );
+        assert_eq!(
+            parser.parse(without_parens),
+            vec![StmtNode::PrintStmt(hello)]
+        );
+
+        // print is not a function call so it does not take multiple arguments
+        let tokens = crate::lexer::Lexer::new()
+            .lex("print(\"hello\", \"world\");")
+            .unwrap();
+        let statements = parser.parse(tokens);
+        assert_eq!(statements.len(), 1);
+        match &statements[0] {
+            StmtNode::ErrStmt(err) => assert!(err.contains("single expression")),
+            stmt => panic!("expected an error statement but found {:?}", stmt),
+        }
+    }
+
+    #[test]
+    /// tests that defined names are replaced by their values and don't produce any statements
+    fn define_test() {
+        let tokens = Lexer::new()
+            .lex("define LIMIT 10;\ndefine NEG -1;\nprint LIMIT + NEG;")
+            .unwrap();
+        let mut parser = Parser::new();
+        let statements = parser.parse(tokens);
+        assert_eq!(
+            statements,
+            vec![StmtNode::PrintStmt(ExprNode::BinaryExpr {
+                left: Box::new(ExprNode::Literal(Literal::Number(10.0))),
+                operator: Operator::Add { line: 3 },
+                right: Box::new(ExprNode::Literal(Literal::Number(-1.0))),
+            })]
+        );
+
+        // a define given from outside of the source wins over the one in the source
+        let overrides = HashMap::from([("LIMIT".to_string(), Literal::Number(1.0))]);
+        let tokens = Lexer::new().lex("define LIMIT 10;\nprint LIMIT;").unwrap();
+        let statements = Parser::new().with_overrides(overrides).parse(tokens);
+        assert_eq!(
+            statements,
+            vec![StmtNode::PrintStmt(ExprNode::Literal(Literal::Number(1.0)))]
+        );
+
+        // a name can only be defined once, and only at the top level
+        let error = |source: &str, defines: HashMap<String, Literal>| {
+            let tokens = Lexer::new().lex(source).unwrap();
+            match &Parser::new().with_defines(defines).parse(tokens)[0] {
+                StmtNode::ErrStmt(err) => err.clone(),
+                stmt => panic!("expected an error statement but found {:?}", stmt),
+            }
+        };
+        assert_eq!(
+            error("define N 1;\ndefine N 2;", HashMap::new()),
+            "[S0091] Can not define N again in line 2, it is already defined"
+        );
+        let defines = HashMap::from([("N".to_string(), Literal::Number(1.0))]);
+        assert!(error("define N 1;", defines).contains("S0091"));
+        assert_eq!(
+            error("{ define Y 1; }", HashMap::new()),
+            "[S0090] Can not define Y in line 1, defines are only allowed at the top level"
+        );
+        assert!(error("fun f() { define Y 1; }", HashMap::new()).contains("S0090"));
+        let tokens = Lexer::new().lex("{ define Y 1; }\nprint Y;").unwrap();
+        let statements = Parser::new().parse(tokens);
+        assert_eq!(
+            statements[1],
+            StmtNode::PrintStmt(ExprNode::Variable {
+                name: "Y".to_string(),
+                line: 2
+            })
+        );
+
+        // only constants can be defined
+        let tokens = Lexer::new().lex("define LIMIT 1 + 2;").unwrap();
+        let statements = Parser::new().parse(tokens);
+        assert!(matches!(statements[0], StmtNode::ErrStmt(_)));
+    }
+
+    #[test]
+    fn assignment_test() {
+        let tokens = Lexer::new().lex("a = b = 1;").unwrap();
+        let statements = Parser::new().parse(tokens);
+        assert_eq!(
+            statements,
+            vec![StmtNode::ExprStmt(ExprNode::Assign {
+                name: "a".to_string(),
+                value: Box::new(ExprNode::Assign {
+                    name: "b".to_string(),
+                    value: Box::new(ExprNode::Literal(Literal::Number(1.0))),
+                    line: 1,
+                }),
+                line: 1,
+            })]
+        );
+
+        // only variables can be assigned to
+        for source in ["1 = 2;", "(a) = 2;", "a + b = 2;"] {
+            let statements = Parser::new().parse(Lexer::new().lex(source).unwrap());
+            match &statements[..] {
+                [StmtNode::ErrStmt(err)] => assert!(err.contains("Invalid assignment target")),
+                statements => panic!(
+                    "expected an error for {} but found {:?}",
+                    source, statements
+                ),
+            }
+        }
+    }
+
+    #[test]
+    /// tests that compound assignments are desugared, keeping the line of their operator
+    fn compound_assignment_test() {
+        let tokens = Lexer::new().lex("a\n-= 1;").unwrap();
+        let statements = Parser::new().parse(tokens);
+        assert_eq!(
+            statements,
+            vec![StmtNode::ExprStmt(ExprNode::Assign {
+                name: "a".to_string(),
+                value: Box::new(ExprNode::BinaryExpr {
+                    left: Box::new(ExprNode::Variable {
+                        name: "a".to_string(),
+                        line: 1,
+                    }),
+                    operator: Operator::Subtract { line: 2 },
+                    right: Box::new(ExprNode::Literal(Literal::Number(1.0))),
+                }),
+                line: 2,
+            })]
+        );
+
+        // the object of a property would be evaluated twice, so it can't hold a call
+        let tokens = Lexer::new().lex("a.b.c *= 2; f().b /= 2; 1 += 2;").unwrap();
+        let statements = Parser::new().parse(tokens);
+        assert!(matches!(
+            &statements[0],
+            StmtNode::ExprStmt(ExprNode::Set { .. })
+        ));
+        assert!(
+            matches!(&statements[1], StmtNode::ErrStmt(err) if err.contains("Can not use '/=' on a property"))
+        );
+        assert!(
+            matches!(&statements[2], StmtNode::ErrStmt(err) if err.contains("Invalid assignment target"))
+        );
+    }
+
+    #[test]
+    /// tests that brackets parse as list literals, indexing and index assignment
+    fn list_test() {
+        let tokens = Lexer::new().lex("xs[0] = [1, [],];").unwrap();
+        let statements = Parser::new().parse(tokens);
+        let number = |value: f64| ExprNode::Literal(Literal::Number(value));
+        assert_eq!(
+            statements,
+            vec![StmtNode::ExprStmt(ExprNode::SetIndex {
+                object: Box::new(ExprNode::Variable {
+                    name: "xs".to_string(),
+                    line: 1,
+                }),
+                index: Box::new(number(0.0)),
+                value: Box::new(ExprNode::List {
+                    elements: vec![
+                        number(1.0),
+                        ExprNode::List {
+                            elements: vec![],
+                            line: 1,
+                        },
+                    ],
+                    line: 1,
+                }),
+                line: 1,
+            })]
+        );
+
+        // the list and index of an element would be evaluated twice, so they can't hold a call
+        let tokens = Lexer::new()
+            .lex("xs[i + 1] += 1; xs[f()] += 1; [1, 2;\nxs[0;")
+            .unwrap();
+        let statements = Parser::new().parse(tokens);
+        assert!(matches!(
+            &statements[0],
+            StmtNode::ExprStmt(ExprNode::SetIndex { .. })
+        ));
+        assert!(
+            matches!(&statements[1], StmtNode::ErrStmt(err) if err.contains("Can not use '+=' on an element"))
+        );
+        assert!(
+            matches!(&statements[2], StmtNode::ErrStmt(err) if err == "[S0015] Expected ']' after the elements of the list in line 1")
+        );
+        assert!(
+            matches!(&statements[3], StmtNode::ErrStmt(err) if err == "[S0014] Expected ']' after the index in line 2")
+        );
+    }
+
+    #[test]
+    /// tests that a colon in the brackets of an index makes it a slice, whose bounds are optional
+    fn slice_test() {
+        let tokens = Lexer::new().lex("s[1:n]; s[:]; s[2:];").unwrap();
+        let statements = Parser::new().parse(tokens);
+        let variable = |name: &str| {
+            Box::new(ExprNode::Variable {
+                name: name.to_string(),
+                line: 1,
+            })
+        };
+        let slice = |start, end| {
+            StmtNode::ExprStmt(ExprNode::Slice {
+                object: variable("s"),
+                start,
+                end,
+                line: 1,
+            })
+        };
+        let number = |value: f64| Some(Box::new(ExprNode::Literal(Literal::Number(value))));
+        assert_eq!(
+            statements,
+            vec![
+                slice(number(1.0), Some(variable("n"))),
+                slice(None, None),
+                slice(number(2.0), None),
+            ]
+        );
+
+        // a slice is a new value, so it can not be assigned to
+        let tokens = Lexer::new().lex("s[1:2] = 3;").unwrap();
+        let statements = Parser::new().parse(tokens);
+        assert!(
+            matches!(&statements[0], StmtNode::ErrStmt(err) if err.contains("Invalid assignment target"))
+        );
+    }
+
+    #[test]
+    /// tests that a brace is a map in an expression and a block where a statement is expected
+    fn map_test() {
+        let tokens = Lexer::new().lex("print {1: {},};\n{ a; }").unwrap();
+        let statements = Parser::new().parse(tokens);
+        assert_eq!(
+            statements,
+            vec![
+                StmtNode::PrintStmt(ExprNode::Map {
+                    entries: vec![(
+                        ExprNode::Literal(Literal::Number(1.0)),
+                        ExprNode::Map {
+                            entries: vec![],
+                            line: 1,
+                        },
+                    )],
+                    line: 1,
+                }),
+                StmtNode::BlockStmt(vec![StmtNode::ExprStmt(ExprNode::Variable {
+                    name: "a".to_string(),
+                    line: 2,
+                })]),
+            ]
+        );
+
+        let tokens = Lexer::new().lex("print {1 2};\nprint {1: 2;").unwrap();
+        let statements = Parser::new().parse(tokens);
+        assert!(
+            matches!(&statements[0], StmtNode::ErrStmt(err) if err == "[S0016] Expected ':' after the key of a map entry in line 1")
+        );
+        assert!(
+            matches!(statements.last(), Some(StmtNode::ErrStmt(err)) if err == "[S0017] Expected '}' after the entries of the map in line 2")
+        );
+    }
+
+    #[test]
+    #[cfg(not(feature = "bigint"))]
+    /// tests that big integers are a parse error without the feature for them
+    fn bigint_test() {
+        let tokens = Lexer::new().lex("print 2n;").unwrap();
+        let statements = Parser::new().parse(tokens);
+        assert!(
+            matches!(&statements[0], StmtNode::ErrStmt(err) if err == "[S0086] Big integers like 2n need lox to be built with the bigint feature in line 1")
+        );
+    }
+
+    #[test]
+    /// tests that an else belongs to the closest if before it
+    fn if_stmt_test() {
+        let tokens = Lexer::new()
+            .lex("if (a) if (b) print 1; else { print 2; }")
+            .unwrap();
+        let statements = Parser::new().parse(tokens);
+        let variable = |name: &str| ExprNode::Variable {
+            name: name.to_string(),
+            line: 1,
+        };
+        let print = |value: f64| StmtNode::PrintStmt(ExprNode::Literal(Literal::Number(value)));
+        assert_eq!(
+            statements,
+            vec![StmtNode::IfStmt {
+                condition: variable("a"),
+                then_branch: Box::new(StmtNode::IfStmt {
+                    condition: variable("b"),
+                    then_branch: Box::new(print(1.0)),
+                    else_branch: Some(Box::new(StmtNode::BlockStmt(vec![print(2.0)]))),
+                }),
+                else_branch: None,
+            }]
+        );
+
+        for source in ["if a print 1;", "if (a print 1;", "if (a) else print 1;"] {
+            let statements = Parser::new().parse(Lexer::new().lex(source).unwrap());
+            assert!(
+                matches!(statements[0], StmtNode::ErrStmt(_)),
+                "expected an error for {} but found {:?}",
+                source,
+                statements
+            );
+        }
+    }
+
+    #[test]
+    fn while_stmt_test() {
+        let tokens = Lexer::new().lex("while (a) a = false;").unwrap();
+        let statements = Parser::new().parse(tokens);
+        assert_eq!(
+            statements,
+            vec![StmtNode::WhileStmt {
+                condition: ExprNode::Variable {
+                    name: "a".to_string(),
+                    line: 1,
+                },
+                body: Box::new(StmtNode::ExprStmt(ExprNode::Assign {
+                    name: "a".to_string(),
+                    value: Box::new(ExprNode::Literal(Literal::Boolean(false))),
+                    line: 1,
+                })),
+                increment: None,
+            }]
+        );
+
+        // a declaration is not a statement so it can't be the body of a loop. The parser recovers
+        // at the declaration and parses it on its own
+        let tokens = Lexer::new().lex("while (a) var b;\nprint 1;").unwrap();
+        let statements = Parser::new().parse(tokens);
+        assert_eq!(statements.len(), 3);
+        assert!(matches!(statements[0], StmtNode::ErrStmt(_)));
+        assert!(matches!(statements[1], StmtNode::VarStmt { .. }));
+    }
+
+    #[test]
+    /// tests that `and` binds tighter than `or`, which binds tighter than assignment
+    fn logical_expr_test() {
+        let tokens = Lexer::new().lex("a = 1 or 2 and 3 == 3;").unwrap();
+        let statements = Parser::new().parse(tokens);
+        let number = |value| Box::new(ExprNode::Literal(Literal::Number(value)));
+        assert_eq!(
+            statements,
+            vec![StmtNode::ExprStmt(ExprNode::Assign {
+                name: "a".to_string(),
+                value: Box::new(ExprNode::Logical {
+                    left: number(1.0),
+                    operator: Operator::Or { line: 1 },
+                    right: Box::new(ExprNode::Logical {
+                        left: number(2.0),
+                        operator: Operator::And { line: 1 },
+                        right: Box::new(ExprNode::BinaryExpr {
+                            left: number(3.0),
+                            operator: Operator::EqualEqual { line: 1 },
+                            right: number(3.0),
+                        }),
+                    }),
+                }),
+                line: 1,
+            })]
+        );
+    }
+
+    #[test]
+    /// tests that for loops are desugared into while loops
+    fn for_stmt_test() {
+        let tokens = Lexer::new()
+            .lex("for (var i = 0; i < 2; i = i + 1) print i;")
+            .unwrap();
+        let statements = Parser::new().parse(tokens);
+        let variable = || ExprNode::Variable {
+            name: "i".to_string(),
+            line: 1,
+        };
+        assert_eq!(
+            statements,
+            vec![StmtNode::BlockStmt(vec![
+                StmtNode::VarStmt {
+                    name: "i".to_string(),
+                    initializer: Some(ExprNode::Literal(Literal::Number(0.0))),
+                },
+                StmtNode::WhileStmt {
+                    condition: ExprNode::BinaryExpr {
+                        left: Box::new(variable()),
+                        operator: Operator::LessThan { line: 1 },
+                        right: Box::new(ExprNode::Literal(Literal::Number(2.0))),
+                    },
+                    body: Box::new(StmtNode::PrintStmt(variable())),
+                    increment: Some(ExprNode::Assign {
+                        name: "i".to_string(),
+                        value: Box::new(ExprNode::BinaryExpr {
+                            left: Box::new(variable()),
+                            operator: Operator::Add { line: 1 },
+                            right: Box::new(ExprNode::Literal(Literal::Number(1.0))),
+                        }),
+                        line: 1,
+                    }),
+                },
+            ])]
+        );
+
+        // every clause can be left out
+        let tokens = Lexer::new().lex("for (;;) print 1;").unwrap();
+        let statements = Parser::new().parse(tokens);
+        assert_eq!(
+            statements,
+            vec![StmtNode::WhileStmt {
+                condition: ExprNode::Literal(Literal::Boolean(true)),
+                body: Box::new(StmtNode::PrintStmt(ExprNode::Literal(Literal::Number(1.0)))),
+                increment: None,
+            }]
+        );
+
+        let tokens = Lexer::new().lex("for (var i = 0; i < 2) print i;").unwrap();
+        let statements = Parser::new().parse(tokens);
+        assert!(matches!(&statements[0], StmtNode::ErrStmt(err) if err.contains("';'")));
+    }
+
+    #[test]
+    /// tests that break is only allowed inside of a loop
+    fn break_stmt_test() {
+        let tokens = Lexer::new()
+            .lex("while (true) { if (a) break; }\nbreak;\nfor (;;) break;")
+            .unwrap();
+        let statements = Parser::new().parse(tokens);
+        assert_eq!(statements.len(), 3);
+        assert_eq!(
+            statements[0],
+            StmtNode::WhileStmt {
+                condition: ExprNode::Literal(Literal::Boolean(true)),
+                body: Box::new(StmtNode::BlockStmt(vec![StmtNode::IfStmt {
+                    condition: ExprNode::Variable {
+                        name: "a".to_string(),
+                        line: 1,
+                    },
+                    then_branch: Box::new(StmtNode::BreakStmt { line: 1 }),
+                    else_branch: None,
+                }])),
+                increment: None,
+            }
+        );
+        assert!(
+            matches!(&statements[1], StmtNode::ErrStmt(err) if err.contains("outside of a loop in line 2"))
+        );
+        assert!(matches!(&statements[2], StmtNode::WhileStmt { body, .. }
+            if **body == StmtNode::BreakStmt { line: 3 }));
+    }
+
+    #[test]
+    /// tests that continue is only allowed inside of a loop
+    fn continue_stmt_test() {
+        let tokens = Lexer::new().lex("for (;;) continue;\ncontinue;").unwrap();
+        let statements = Parser::new().parse(tokens);
+        assert_eq!(
+            statements[0],
+            StmtNode::WhileStmt {
+                condition: ExprNode::Literal(Literal::Boolean(true)),
+                body: Box::new(StmtNode::ContinueStmt { line: 1 }),
+                increment: None,
+            }
+        );
+        assert!(
+            matches!(&statements[1], StmtNode::ErrStmt(err) if err.contains("'continue' outside of a loop in line 2"))
+        );
+    }
+
+    #[test]
+    /// tests that a do loop takes its condition after the body and needs a semicolon after it
+    fn do_while_stmt_test() {
+        let tokens = Lexer::new()
+            .lex("do { break; } while (a);\ndo print 1; while (true)\nprint 2;")
+            .unwrap();
+        let statements = Parser::new().parse(tokens);
+        assert_eq!(
+            statements[0],
+            StmtNode::DoWhileStmt {
+                body: Box::new(StmtNode::BlockStmt(vec![StmtNode::BreakStmt { line: 1 }])),
+                condition: ExprNode::Variable {
+                    name: "a".to_string(),
+                    line: 1,
+                },
+            }
+        );
+        assert!(
+            matches!(&statements[1], StmtNode::ErrStmt(err) if err.contains("Expected ';' after the do loop in line 2"))
+        );
+        assert_eq!(
+            statements.last(),
+            Some(&StmtNode::PrintStmt(ExprNode::Literal(Literal::Number(
+                2.0
+            ))))
+        );
+    }
+
+    #[test]
+    /// tests that imports take a path with an optional name, or the name of a module
+    fn import_decl_test() {
+        let tokens = Lexer::new()
+            .lex("import \"lib/a.lox\";\nimport \"b.lox\" as b;\nimport c;\nimport;\nimport \"d.lox\" as;\nvar as = 1;")
+            .unwrap();
+        let statements = Parser::new().parse(tokens);
+        let import = |path: &str, name: Option<&str>, line| StmtNode::ImportStmt {
+            path: path.to_string(),
+            name: name.map(str::to_string),
+            names: Vec::new(),
+            line,
+        };
+        assert_eq!(
+            statements[..3],
+            [
+                import("lib/a.lox", None, 1),
+                import("b.lox", Some("b"), 2),
+                import("c.lox", Some("c"), 3),
+            ]
+        );
+        assert!(
+            matches!(&statements[3], StmtNode::ErrStmt(err) if err.contains("Expected a path or a module name after 'import' in line 4"))
+        );
+        assert!(
+            matches!(&statements[4], StmtNode::ErrStmt(err) if err.contains("Expected a name after 'as' in line 5"))
+        );
+        assert!(matches!(&statements[5], StmtNode::VarStmt { name, .. } if name == "as"));
+    }
+
+    #[test]
+    /// tests importing names from a module and exporting declarations at the top level
+    fn export_decl_test() {
+        let tokens = Lexer::new()
+            .lex(
+                "import { sqrt, pi } from \"math.lox\";\nexport fun f() {}\n\
+                export import { pi } from \"math.lox\";\nvar export = 1;\nexport = 2;\n\
+                import { } from \"a.lox\";\nimport { a } \"a.lox\";\nexport import \"a.lox\";\n\
+                { export var x; }",
+            )
+            .unwrap();
+        let statements = Parser::new().parse(tokens);
+        let import = |names: &[&str], line| StmtNode::ImportStmt {
+            path: "math.lox".to_string(),
+            name: None,
+            names: names.iter().map(|name| name.to_string()).collect(),
+            line,
+        };
+        assert_eq!(statements[0], import(&["sqrt", "pi"], 1));
+        assert!(matches!(
+            &statements[1],
+            StmtNode::ExportStmt { statement, line: 2 }
+                if matches!(statement.as_ref(), StmtNode::FunctionStmt(declaration) if declaration.name == "f")
+        ));
+        assert_eq!(
+            statements[2],
+            StmtNode::ExportStmt {
+                statement: Box::new(import(&["pi"], 3)),
+                line: 3
+            }
+        );
+        // export is only special before a declaration
+        assert!(matches!(&statements[3], StmtNode::VarStmt { name, .. } if name == "export"));
+        assert!(
+            matches!(&statements[4], StmtNode::ExprStmt(ExprNode::Assign { name, .. }) if name == "export")
+        );
+
+        let errors: Vec<_> = statements[5..]
+            .iter()
+            .map(|statement| match statement {
+                StmtNode::ErrStmt(err) => err.as_str(),
+                statement => panic!("expected an error but found {:?}", statement),
+            })
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                "[S0092] Expected the names to import between '{' and '}' in line 6",
+                "[S0093] Expected 'from' and a path after the names to import in line 7",
+                "[S0095] Expected a declaration or an import with names after 'export' in line 8",
+                "[S0094] Can not export in line 9, exports are only allowed at the top level",
+            ]
+        );
+    }
+
+    #[test]
+    /// tests that constants need a name and an initializer
+    fn const_decl_test() {
+        let tokens = Lexer::new()
+            .lex("const a = 1;\nconst b;\nconst = 2;\nprint 2;")
+            .unwrap();
+        let statements = Parser::new().parse(tokens);
+        assert_eq!(
+            statements[0],
+            StmtNode::ConstStmt {
+                name: "a".to_string(),
+                initializer: ExprNode::Literal(Literal::Number(1.0)),
+            }
+        );
+        assert!(
+            matches!(&statements[1], StmtNode::ErrStmt(err) if err.contains("Expected '=' after the constant b in line 2"))
+        );
+        assert!(
+            matches!(&statements[2], StmtNode::ErrStmt(err) if err.contains("Expected a constant name after 'const' in line 3"))
+        );
+        assert_eq!(
+            statements.last(),
+            Some(&StmtNode::PrintStmt(ExprNode::Literal(Literal::Number(
+                2.0
+            ))))
+        );
+    }
+
+    #[test]
+    /// tests that no declaration can take the name of a constant in its scope, while a nested scope
+    /// can shadow it
+    fn const_redeclaration_test() {
+        let tokens = Lexer::new()
+            .lex("const a = 1;\nfun a() {}\nclass a {}\n{ var a = 2; const b = 3; }\nvar b = 4;")
+            .unwrap();
+        let statements = Parser::new()
+            .with_constants(HashSet::from(["c".to_string()]))
+            .parse(tokens);
+        let errors: Vec<_> = statements
+            .iter()
+            .filter_map(|statement| match statement {
+                StmtNode::ErrStmt(err) => Some(err.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                "[S0053] Can not declare a again, since it is a constant declared in the same scope in line 2",
+                "[S0053] Can not declare a again, since it is a constant declared in the same scope in line 3",
+            ]
+        );
+        assert_eq!(statements.len(), 5);
+
+        let tokens = Lexer::new().lex("var c = 1;").unwrap();
+        let statements = Parser::new()
+            .with_constants(HashSet::from(["c".to_string()]))
+            .parse(tokens);
+        assert!(matches!(statements[0], StmtNode::ErrStmt(_)));
+    }
+
+    #[test]
+    /// tests that a rest parameter can only come last
+    fn rest_param_test() {
+        let tokens = Lexer::new()
+            .lex("fun f(a, ...b) {}\nfun g(...a, b) {}\nfun h(...) {}")
+            .unwrap();
+        let statements = Parser::new().parse(tokens);
+        assert!(matches!(
+            &statements[0],
+            StmtNode::FunctionStmt(function)
+                if function.params == ["a"] && function.rest.as_deref() == Some("b")
+        ));
+        let errors = statements
+            .iter()
+            .filter_map(|statement| match statement {
+                StmtNode::ErrStmt(err) => Some(err.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert!(errors.iter().any(|err| err.contains(
+            "Expected ')' after the rest parameter a in line 2, it must be the last parameter"
+        )));
+        assert!(errors
+            .iter()
+            .any(|err| err.contains("Expected a parameter name in line 3")));
+    }
+
+    #[test]
+    /// tests that try needs a block, a catch and the name of the exception
+    fn try_stmt_test() {
+        let tokens = Lexer::new()
+            .lex(
+                "try { throw 1; } catch (e) { print e; }\ntry print 1;\ntry {} catch () {}\nthrow;",
+            )
+            .unwrap();
+        let statements = Parser::new().parse(tokens);
+        assert_eq!(
+            statements[0],
+            StmtNode::TryStmt {
+                body: vec![StmtNode::ThrowStmt {
+                    value: ExprNode::Literal(Literal::Number(1.0)),
+                    line: 1,
+                }],
+                name: "e".to_string(),
+                handler: vec![StmtNode::PrintStmt(ExprNode::Variable {
+                    name: "e".to_string(),
+                    line: 1,
+                })],
+            }
+        );
+        let errors: Vec<_> = statements[1..]
+            .iter()
+            .filter_map(|statement| match statement {
+                StmtNode::ErrStmt(err) => Some(err.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            errors[..2],
+            [
+                "[S0080] Expected '{' after 'try' in line 2",
+                "[S0078] Expected the name of the exception after 'catch' in line 3",
+            ]
+        );
+        assert_eq!(
+            errors.last(),
+            Some(&"[S0088] unsupported token Semicolon in expression")
+        );
+    }
+
+    #[test]
+    /// tests function declarations, including returns and loops crossing the function boundary
+    fn fun_decl_test() {
+        let tokens = Lexer::new()
+            .lex(
+                "fun add(a, b) { return a + b; }\nfun f(a, a) {}\nreturn 1;\n\
+                while (true) { fun g() { break; } }\nfun h() { return; }",
+            )
+            .unwrap();
+        let statements = Parser::new().parse(tokens);
+        assert_eq!(statements.len(), 5);
+        assert_eq!(
+            statements[0],
+            StmtNode::FunctionStmt(Rc::new(FunctionDecl {
+                name: "add".to_string(),
+                params: vec!["a".to_string(), "b".to_string()],
+                rest: None,
+                body: vec![StmtNode::ReturnStmt {
+                    value: Some(ExprNode::BinaryExpr {
+                        left: Box::new(ExprNode::Variable {
+                            name: "a".to_string(),
+                            line: 1,
+                        }),
+                        operator: Operator::Add { line: 1 },
+                        right: Box::new(ExprNode::Variable {
+                            name: "b".to_string(),
+                            line: 1,
+                        }),
+                    }),
+                    line: 1,
+                }],
+                line: 1,
+                doc: None,
+            }))
+        );
+        assert!(
+            matches!(&statements[1], StmtNode::ErrStmt(err) if err.contains("Duplicate parameter a in line 2"))
+        );
+        assert!(
+            matches!(&statements[2], StmtNode::ErrStmt(err) if err.contains("return from top-level code in line 3"))
+        );
+        assert!(
+            matches!(&statements[3], StmtNode::ErrStmt(err) if err.contains("'break' outside of a loop in line 4"))
+        );
+        assert!(matches!(&statements[4], StmtNode::FunctionStmt(decl)
+            if decl.body == vec![StmtNode::ReturnStmt { value: None, line: 5 }]));
+    }
+
+    #[test]
+    /// tests class declarations, properties and where `this` and returns are allowed
+    fn class_decl_test() {
+        let tokens = Lexer::new()
+            .lex(
+                "class A { init(x) { this.x = x; } get() { return this.x; } }\na.b.c = d.e;\n\
+                print this;\nclass B { init() { return 1; } }",
+            )
+            .unwrap();
+        let statements = Parser::new().parse(tokens);
+        assert_eq!(statements.len(), 4);
+        let StmtNode::ClassStmt(class) = &statements[0] else {
+            panic!("expected a class, found {:?}", statements[0]);
+        };
+        assert_eq!(class.name, "A");
+        assert_eq!(
+            class
+                .methods
+                .iter()
+                .map(|method| method.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["init", "get"]
+        );
+        assert_eq!(
+            class.methods[0].body,
+            vec![StmtNode::ExprStmt(ExprNode::Set {
+                object: Box::new(ExprNode::This { line: 1 }),
+                name: "x".to_string(),
+                value: Box::new(ExprNode::Variable {
+                    name: "x".to_string(),
+                    line: 1,
+                }),
+                line: 1,
+            })]
+        );
+        assert!(
+            matches!(&statements[2], StmtNode::ErrStmt(err) if err.contains("'this' outside of a class in line 3"))
+        );
+        assert!(
+            matches!(&statements[3], StmtNode::ErrStmt(err) if err.contains("return a value from an initializer in line 4"))
+        );
+        let variable = |name: &str| {
+            Box::new(ExprNode::Variable {
+                name: name.to_string(),
+                line: 2,
+            })
+        };
+        assert_eq!(
+            statements[1],
+            StmtNode::ExprStmt(ExprNode::Set {
+                object: Box::new(ExprNode::Get {
+                    object: variable("a"),
+                    name: "b".to_string(),
+                    line: 2,
+                }),
+                name: "c".to_string(),
+                value: Box::new(ExprNode::Get {
+                    object: variable("d"),
+                    name: "e".to_string(),
+                    line: 2,
+                }),
+                line: 2,
+            })
+        );
+    }
+
+    #[test]
+    /// tests parsing subclasses and super, and the errors of super outside of a subclass
+    fn superclass_test() {
+        let tokens = Lexer::new()
+            .lex(
+                "class B < A { m() { return super.m; } }\nprint super.x;\n\
+                class C { m() { super.m(); } }\nclass D < D {}\nvar after = 1;",
+            )
+            .unwrap();
+        let statements = Parser::new().parse(tokens);
+        assert_eq!(statements.len(), 5);
+        let StmtNode::ClassStmt(class) = &statements[0] else {
+            panic!("expected a class, found {:?}", statements[0]);
+        };
+        assert_eq!(
+            class.superclass,
+            Some(ExprNode::Variable {
+                name: "A".to_string(),
+                line: 1,
+            })
+        );
+        assert_eq!(
+            class.methods[0].body,
+            vec![StmtNode::ReturnStmt {
+                value: Some(ExprNode::Super {
+                    method: "m".to_string(),
+                    line: 1,
+                }),
+                line: 1,
+            }]
+        );
+        assert!(
+            matches!(&statements[1], StmtNode::ErrStmt(err) if err.contains("'super' outside of a class in line 2"))
+        );
+        assert!(
+            matches!(&statements[2], StmtNode::ErrStmt(err) if err.contains("'super' in a class with no superclass in line 3"))
+        );
+        assert!(
+            matches!(&statements[3], StmtNode::ErrStmt(err) if err.contains("can not inherit from itself in line 4"))
+        );
+        assert!(matches!(&statements[4], StmtNode::VarStmt { name, .. } if name == "after"));
+    }
+
+    #[test]
+    /// tests parsing class methods, which can not use this or super
+    fn class_method_test() {
+        let tokens = Lexer::new()
+            .lex(
+                "class A { class make() { return A(); } get() { return 1; } }\n\
+                class B { class bad() { return this; } }\n\
+                class C < A { class bad() { return super.get; } }\nvar after = 1;",
+            )
+            .unwrap();
+        let statements = Parser::new().parse(tokens);
+        assert_eq!(statements.len(), 4);
+        let StmtNode::ClassStmt(class) = &statements[0] else {
+            panic!("expected a class, found {:?}", statements[0]);
+        };
+        assert_eq!(class.methods.len(), 1);
+        assert_eq!(class.methods[0].name, "get");
+        assert_eq!(class.class_methods.len(), 1);
+        assert_eq!(class.class_methods[0].name, "make");
+        assert!(
+            matches!(&statements[1], StmtNode::ErrStmt(err) if err.contains("'this' in a class method in line 2"))
+        );
+        assert!(
+            matches!(&statements[2], StmtNode::ErrStmt(err) if err.contains("'super' in a class method in line 3"))
+        );
+        assert!(matches!(&statements[3], StmtNode::VarStmt { name, .. } if name == "after"));
+    }
+
+    #[test]
+    /// tests that comments are attached to the statements they are written around
+    fn comment_test() {
+        let comment = |text: &str, line| Comment {
+            text: text.to_string(),
+            line,
+        };
+        let mut lexer = Lexer::new().with_comments(true);
+        let tokens = lexer
+            .lex(
+                "// the answer\nvar a = 1; // is one\n{\n  print a;\n  // left over\n}\nprint 2;\n// the end",
+            )
+            .unwrap();
+        let statements = Parser::new()
+            .with_comments(lexer.take_comments())
+            .parse(tokens);
+        let print = |value| StmtNode::PrintStmt(ExprNode::Literal(value));
+        assert_eq!(
+            statements,
+            vec![
+                StmtNode::Commented {
+                    leading: vec![comment(" the answer", 1)],
+                    statement: Box::new(StmtNode::VarStmt {
+                        name: "a".to_string(),
+                        initializer: Some(ExprNode::Literal(Literal::Number(1.0))),
+                    }),
+                    trailing: vec![comment(" is one", 2)],
+                },
+                StmtNode::BlockStmt(vec![StmtNode::Commented {
+                    leading: Vec::new(),
+                    statement: Box::new(StmtNode::PrintStmt(ExprNode::Variable {
+                        name: "a".to_string(),
+                        line: 4,
+                    })),
+                    trailing: vec![comment(" left over", 5)],
+                }]),
+                StmtNode::Commented {
+                    leading: Vec::new(),
+                    statement: Box::new(print(Literal::Number(2.0))),
+                    trailing: vec![comment(" the end", 8)],
+                },
+            ]
+        );
+
+        // comments are thrown away unless they are kept
+        let tokens = Lexer::new().lex("print 2; // two").unwrap();
+        assert_eq!(
+            Parser::new().parse(tokens),
+            vec![print(Literal::Number(2.0))]
+        );
+    }
+
+    #[test]
+    /// tests that the `///` comments right before a function or class, or the one after it, document
+    /// it while plain `//` comments don't
+    fn doc_comment_test() {
+        let source = "/// not a doc\n\n// not a doc\n/// adds\n/// two numbers\nfun add(a, b) { return a + b; }\n\
+                      class A {} /// a class\n// not a doc either\nfun f() {} // nor this";
+        let mut lexer = Lexer::new().with_comments(true);
+        let tokens = lexer.lex(source).unwrap();
+        let statements = Parser::new()
+            .with_comments(lexer.take_comments())
+            .parse(tokens);
+        let docs: Vec<_> = statements
+            .iter()
+            .map(|statement| match statement {
+                StmtNode::Commented { statement, .. } => statement.as_ref(),
+                statement => statement,
+            })
+            .map(|statement| match statement {
+                StmtNode::FunctionStmt(declaration) => declaration.doc.clone(),
+                StmtNode::ClassStmt(declaration) => declaration.doc.clone(),
+                statement => panic!("expected a declaration, got {:?}", statement),
+            })
+            .collect();
+        assert_eq!(
+            docs,
+            vec![
+                Some("adds\ntwo numbers".to_string()),
+                Some("a class".to_string()),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    /// tests that calls chain and take their arguments in order
+    fn call_test() {
+        let tokens = Lexer::new().lex("f(1)(2, 3)();").unwrap();
+        let statements = Parser::new().parse(tokens);
+        let inner = ExprNode::Call {
+            callee: Box::new(ExprNode::Variable {
+                name: "f".to_string(),
+                line: 1,
+            }),
+            arguments: vec![ExprNode::Literal(Literal::Number(1.0))],
+            line: 1,
+        };
+        let middle = ExprNode::Call {
+            callee: Box::new(inner),
+            arguments: vec![
+                ExprNode::Literal(Literal::Number(2.0)),
+                ExprNode::Literal(Literal::Number(3.0)),
+            ],
+            line: 1,
+        };
+        assert_eq!(
+            statements,
+            vec![StmtNode::ExprStmt(ExprNode::Call {
+                callee: Box::new(middle),
+                arguments: vec![],
+                line: 1,
+            })]
+        );
+    }
+
+    #[test]
+    /// tests that the parser recovers inside of a block and carries on after it
+    fn block_test() {
+        let tokens = Lexer::new()
+            .lex("{ var a = 1; { print a; } }\n{ print ); print 2; }\nprint 3;\n{ print 4;")
+            .unwrap();
+        let statements = Parser::new().parse(tokens);
+        assert_eq!(statements.len(), 4);
+        assert!(matches!(&statements[0], StmtNode::BlockStmt(inner) if inner.len() == 2));
+        assert!(matches!(statements[1], StmtNode::ErrStmt(_)));
+        assert_eq!(
+            statements[2],
+            StmtNode::PrintStmt(ExprNode::Literal(Literal::Number(3.0)))
+        );
+        match &statements[3] {
+            StmtNode::ErrStmt(err) => assert!(err.contains("Expected '}'")),
+            stmt => panic!("expected an unclosed block error but found {:?}", stmt),
+        }
+    }
+
+    #[test]
+    /// tests that a line break only ends a statement when automatic semicolons are enabled
+    fn automatic_semicolons_test() {
+        let source = "print 1\nprint 2 +\n3\nprint 4 print 5;";
+        let statements = Parser::new()
+            .with_automatic_semicolons(true)
+            .parse(Lexer::new().lex(source).unwrap());
+        assert_eq!(statements.len(), 4);
+        assert_eq!(
+            statements[0],
+            StmtNode::PrintStmt(ExprNode::Literal(Literal::Number(1.0)))
+        );
+        assert!(matches!(
+            statements[1],
+            StmtNode::PrintStmt(ExprNode::BinaryExpr { .. })
+        ));
+        // statements on the same line still need a semicolon between them
+        assert!(matches!(statements[2], StmtNode::ErrStmt(_)));
+        assert_eq!(
+            statements[3],
+            StmtNode::PrintStmt(ExprNode::Literal(Literal::Number(5.0)))
+        );
+
+        let statements = Parser::new().parse(Lexer::new().lex(source).unwrap());
+        assert!(matches!(statements[0], StmtNode::ErrStmt(_)));
+    }
+
+    #[test]
+    /// tests variable declarations with and without an initializer and reading variables back
+    fn var_decl_test() {
+        let tokens = Lexer::new()
+            .lex("var a;\nvar b = 1 + 2;\nprint b;")
+            .unwrap();
+        let statements = Parser::new().parse(tokens);
+        assert_eq!(
+            statements,
+            vec![
+                StmtNode::VarStmt {
+                    name: "a".to_string(),
+                    initializer: None,
+                },
+                StmtNode::VarStmt {
+                    name: "b".to_string(),
+                    initializer: Some(ExprNode::BinaryExpr {
+                        left: Box::new(ExprNode::Literal(Literal::Number(1.0))),
+                        operator: Operator::Add { line: 2 },
+                        right: Box::new(ExprNode::Literal(Literal::Number(2.0))),
+                    }),
+                },
+                StmtNode::PrintStmt(ExprNode::Variable {
+                    name: "b".to_string(),
+                    line: 3,
+                }),
+            ]
+        );
+
+        // a declaration needs a name and a semicolon, and the parser recovers after either is missing
+        let tokens = Lexer::new().lex("var = 1;\nvar c = 2\nprint 3;").unwrap();
+        let statements = Parser::new().parse(tokens);
+        assert_eq!(statements.len(), 3);
+        assert!(matches!(statements[0], StmtNode::ErrStmt(_)));
+        assert!(matches!(statements[1], StmtNode::ErrStmt(_)));
+        assert_eq!(
+            statements[2],
+            StmtNode::PrintStmt(ExprNode::Literal(Literal::Number(3.0)))
+        );
+    }
+
+    #[test]
+    fn parse_define_test() {
+        assert_eq!(
+            parse_define("DEBUG=true").unwrap(),
+            ("DEBUG".to_string(), Literal::Boolean(true))
+        );
+        assert_eq!(
+            parse_define("LIMIT=12").unwrap(),
+            ("LIMIT".to_string(), Literal::Number(12.0))
+        );
+        assert_eq!(
+            parse_define("NAME=\"lox\"").unwrap(),
+            ("NAME".to_string(), Literal::String("lox".to_string()))
+        );
+        assert_eq!(
+            parse_define("MODE=fast mode").unwrap(),
+            ("MODE".to_string(), Literal::String("fast mode".to_string()))
+        );
+        assert!(parse_define("DEBUG").is_err());
+        assert!(parse_define("=1").is_err());
+    }
+
+    #[test]
+    /// tests that programs nested past the limit are reported as errors instead of overflowing the
+    /// stack, while long chains are not limited
+    fn nesting_limit_test() {
+        let nested = |depth: usize, open: &str, close: &str| {
+            format!("print {}1{};", open.repeat(depth), close.repeat(depth))
+        };
+
+        let tokens = Lexer::new()
+            .lex(&nested(MAX_NESTING_DEPTH, "(", ")"))
+            .unwrap();
+        let statements = Parser::new().parse(tokens);
+        assert!(matches!(statements[0], StmtNode::PrintStmt(_)));
+
+        for (open, close) in [("(", ")"), ("-", ""), ("!", "")] {
+            let source = format!("\n{}", nested(MAX_NESTING_DEPTH * 10, open, close));
+            let tokens = Lexer::new().lex(&source).unwrap();
+            let statements = Parser::new().parse(tokens);
+            assert_eq!(statements.len(), 1);
+            match &statements[0] {
+                StmtNode::ErrStmt(err) => {
+                    assert!(err.contains("nested too deeply"));
+                    assert!(err.contains("line 2"));
+                }
+                stmt => panic!("expected an error statement but found {:?}", stmt),
+            }
+        }
+
+        // a chain of operations, calls or properties is parsed by a loop, so only what it nests counts
+        let chain = |length: usize, link: &str| format!("print a{};", link.repeat(length));
+        for link in [
+            " + 1", " * 1", " == 1", " and 1", " or 1", "()", ".a", "[0]",
+        ] {
+            let source = format!("{}\nprint 2;", chain(2000, link));
+            let tokens = Lexer::new().lex(&source).unwrap();
+            let statements = Parser::new().parse(tokens);
+            assert!(matches!(
+                statements[..],
+                [StmtNode::PrintStmt(_), StmtNode::PrintStmt(_)]
+            ));
+        }
+        let source = format!("print {}a;", "a + f(".repeat(MAX_NESTING_DEPTH * 10));
+        let statements = Parser::new().parse(Lexer::new().lex(&source).unwrap());
+        assert!(
+            matches!(&statements[0], StmtNode::ErrStmt(err) if err.contains("nested too deeply"))
+        );
+    }
+
+    #[test]
+    /// tests that there is no limit on the locals of a function or the different literals of a
+    /// program, which only a bytecode backend would have
+    fn no_limits_test() {
+        let parse = |source: String| Parser::new().parse(Lexer::new().lex(&source).unwrap());
+        let locals: String = (0..300).map(|i| format!("var v{} = nil;", i)).collect();
+        let source = format!("fun f(a) {{ {} }}", locals);
+        assert!(matches!(parse(source)[..], [StmtNode::FunctionStmt(_)]));
+
+        let strings: Vec<String> = (0..5000).map(|i| format!("\"s{}\"", i)).collect();
+        let source = format!("var l = [{}];", strings.join(", "));
+        assert!(matches!(parse(source)[..], [StmtNode::VarStmt { .. }]));
     }
 
     #[test]
@@ -394,6 +3233,46 @@ mod test {
         assert_eq!(node, expected_node);
     }
 
+    #[test]
+    /// tests that ">=" and "<=" parse to their own operators instead of the strict comparisons
+    fn inclusive_comparison_test() {
+        // 1 >= 2 <= 3
+        let mut tokens = vec![
+            Token::new(TokenType::Number(1.0), "1".to_string(), 1),
+            Token::new(TokenType::GreaterEqual, ">=".to_string(), 1),
+            Token::new(TokenType::Number(2.0), "2".to_string(), 1),
+            Token::new(TokenType::LessEqual, "<=".to_string(), 1),
+            Token::new(TokenType::Number(3.0), "3".to_string(), 1),
+            Token::new(TokenType::Eof, "".to_string(), 1),
+        ];
+        let mut parser = Parser::new();
+        let node = parser.expression(&mut tokens).unwrap();
+        let expected_node = ExprNode::BinaryExpr {
+            left: Box::new(ExprNode::BinaryExpr {
+                left: Box::new(ExprNode::Literal(Literal::Number(1.0))),
+                operator: Operator::GreaterEqual { line: 1 },
+                right: Box::new(ExprNode::Literal(Literal::Number(2.0))),
+            }),
+            operator: Operator::LessEqual { line: 1 },
+            right: Box::new(ExprNode::Literal(Literal::Number(3.0))),
+        };
+        assert_eq!(node, expected_node);
+    }
+
+    #[test]
+    /// tests that matching against token types which are not operators doesn't panic
+    fn match_non_operator_tokens_test() {
+        let mut tokens = vec![
+            Token::new(TokenType::LeftParen, "(".to_string(), 1),
+            Token::new(TokenType::Eof, "".to_string(), 1),
+        ];
+        let mut parser = Parser::new();
+        let operator = parser.match_operator_tokens(&[TokenType::LeftParen], &mut tokens);
+        assert_eq!(operator, None);
+        // the token is left in the stream for the rest of the parser
+        assert_eq!(tokens.len(), 2);
+    }
+
     #[test]
     fn crafting_interpreters_example_test() {
         // testing the node created from the following expression
@@ -0,0 +1,599 @@
+//!
+//! This module exposes the phases of running a Lox program as a pipeline of typed stages.
+//!
+//! ```
+//! use lox_lib::pipeline::Pipeline;
+//!
+//! let ast = Pipeline::new("print 1 + 2;").lex().unwrap().parse();
+//! assert_eq!(ast.artifact().0.len(), 1);
+//! ast.resolve().unwrap().interpret().unwrap();
+//! ```
+//!
+//! Every stage holds the artifact produced by the phase before it, which can be inspected with
+//! `artifact`, taken out with `into_artifact` or handed in with `from_artifact`. A phase can be
+//! swapped out by running it with a custom implementation through the `*_with` methods, e.g. a
+//! custom [`Resolver`] with [`Pipeline::resolve_with`].
+//!
+//! Along the way the pipeline records a [`Trace`] of how long every stage took and how much it
+//! produced. It can also keep the intermediate tokens and syntax tree, so tools can reuse them after
+//! the program has run.
+
+use crate::ast::{
+    ClassDecl, ExprNode, ExprVisitor, FunctionDecl, Literal, Operator, StmtNode, StmtVisitor,
+};
+use crate::clock::Stopwatch;
+use crate::interpreter::Interpreter;
+use crate::lexer::{Lexer, Token};
+use crate::parser::Parser;
+use anyhow::Result;
+use std::fmt;
+use std::rc::Rc;
+use std::time::Duration;
+
+/// The source code of a program, the input of the pipeline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Source(pub String);
+
+/// The tokens produced by lexing the source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tokens(pub Vec<Token>);
+
+/// The syntax tree produced by parsing the tokens. Syntax errors are kept in the tree as
+/// [`StmtNode::ErrStmt`] nodes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ast(pub Vec<StmtNode>);
+
+/// The syntax tree once it has been checked by a [`Resolver`] and is ready to be interpreted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resolved(pub Vec<StmtNode>);
+
+/// The phase between parsing and interpreting, which checks and annotates the syntax tree.
+pub trait Resolver {
+    fn resolve(&mut self, statements: Vec<StmtNode>) -> Result<Vec<StmtNode>>;
+}
+
+/// The resolver used by default. The tree-walk interpreter looks variables up at runtime, so there
+/// is nothing to resolve and the tree is passed on unchanged.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PassThroughResolver;
+
+impl Resolver for PassThroughResolver {
+    fn resolve(&mut self, statements: Vec<StmtNode>) -> Result<Vec<StmtNode>> {
+        Ok(statements)
+    }
+}
+
+/// The stages of the pipeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Lex,
+    Parse,
+    Resolve,
+    Interpret,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Stage::Lex => "lex",
+            Stage::Parse => "parse",
+            Stage::Resolve => "resolve",
+            Stage::Interpret => "interpret",
+        })
+    }
+}
+
+/// How long a stage took and how much it produced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StageStats {
+    pub stage: Stage,
+    pub duration: Duration,
+    /// the number of tokens lexed, syntax tree nodes parsed or resolved, or statements executed
+    pub count: usize,
+}
+
+/// A record of the stages a program went through in the pipeline.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Trace {
+    pub stages: Vec<StageStats>,
+    /// the tokens of the program, kept when the pipeline was asked to keep its artifacts
+    pub tokens: Option<Vec<Token>>,
+    /// the syntax tree of the program, kept when the pipeline was asked to keep its artifacts
+    pub ast: Option<Vec<StmtNode>>,
+    keep_artifacts: bool,
+}
+
+impl Trace {
+    pub fn stage(&self, stage: Stage) -> Option<&StageStats> {
+        self.stages.iter().find(|stats| stats.stage == stage)
+    }
+
+    /// the time taken by all of the stages that have run.
+    pub fn total(&self) -> Duration {
+        self.stages.iter().map(|stats| stats.duration).sum()
+    }
+
+    fn record(&mut self, stage: Stage, start: Stopwatch, count: usize) {
+        self.stages.push(StageStats {
+            stage,
+            duration: start.elapsed(),
+            count,
+        });
+    }
+}
+
+/// renders the trace as the table printed by `--stats`.
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for stats in &self.stages {
+            let unit = match stats.stage {
+                Stage::Lex => "tokens",
+                Stage::Parse | Stage::Resolve => "nodes",
+                Stage::Interpret => "statements",
+            };
+            writeln!(
+                f,
+                "{:<10} {:>10.3}ms {:>8} {}",
+                stats.stage,
+                stats.duration.as_secs_f64() * 1000.0,
+                stats.count,
+                unit
+            )?;
+        }
+        writeln!(
+            f,
+            "{:<10} {:>10.3}ms",
+            "total",
+            self.total().as_secs_f64() * 1000.0
+        )
+    }
+}
+
+/// counts the statement and expression nodes of a syntax tree
+struct NodeCounter;
+
+impl NodeCounter {
+    fn count(statements: &[StmtNode]) -> usize {
+        statements
+            .iter()
+            .map(|statement| NodeCounter.visit_stmt(statement))
+            .sum()
+    }
+}
+
+impl StmtVisitor for NodeCounter {
+    type Output = usize;
+
+    fn visit_print_stmt(&mut self, node: &ExprNode) -> usize {
+        1 + self.visit_expr_node(node)
+    }
+
+    fn visit_expr_stmt(&mut self, node: &ExprNode) -> usize {
+        1 + self.visit_expr_node(node)
+    }
+
+    fn visit_err_stmt(&mut self, _err: String) -> usize {
+        1
+    }
+
+    fn visit_var_stmt(&mut self, _name: &str, initializer: Option<&ExprNode>) -> usize {
+        1 + initializer.map_or(0, |expr| self.visit_expr_node(expr))
+    }
+
+    fn visit_import_stmt(
+        &mut self,
+        _path: &str,
+        _name: Option<&str>,
+        _names: &[String],
+        _line: u32,
+    ) -> usize {
+        1
+    }
+
+    fn visit_export_stmt(&mut self, statement: &StmtNode, _line: u32) -> usize {
+        1 + self.visit_stmt(statement)
+    }
+
+    fn visit_const_stmt(&mut self, _name: &str, initializer: &ExprNode) -> usize {
+        1 + self.visit_expr_node(initializer)
+    }
+
+    fn visit_block_stmt(&mut self, statements: &[StmtNode]) -> usize {
+        1 + NodeCounter::count(statements)
+    }
+
+    fn visit_if_stmt(
+        &mut self,
+        condition: &ExprNode,
+        then_branch: &StmtNode,
+        else_branch: Option<&StmtNode>,
+    ) -> usize {
+        1 + self.visit_expr_node(condition)
+            + self.visit_stmt(then_branch)
+            + else_branch.map_or(0, |stmt| self.visit_stmt(stmt))
+    }
+
+    fn visit_while_stmt(
+        &mut self,
+        condition: &ExprNode,
+        body: &StmtNode,
+        increment: Option<&ExprNode>,
+    ) -> usize {
+        1 + self.visit_expr_node(condition)
+            + self.visit_stmt(body)
+            + increment.map_or(0, |expr| self.visit_expr_node(expr))
+    }
+
+    fn visit_do_while_stmt(&mut self, body: &StmtNode, condition: &ExprNode) -> usize {
+        1 + self.visit_stmt(body) + self.visit_expr_node(condition)
+    }
+
+    fn visit_break_stmt(&mut self, _line: u32) -> usize {
+        1
+    }
+
+    fn visit_continue_stmt(&mut self, _line: u32) -> usize {
+        1
+    }
+
+    fn visit_function_stmt(&mut self, declaration: &Rc<FunctionDecl>) -> usize {
+        1 + NodeCounter::count(&declaration.body)
+    }
+
+    fn visit_class_stmt(&mut self, declaration: &Rc<ClassDecl>) -> usize {
+        let superclass = declaration
+            .superclass
+            .as_ref()
+            .map_or(0, |expr| self.visit_expr_node(expr));
+        1 + superclass
+            + declaration
+                .methods
+                .iter()
+                .chain(&declaration.class_methods)
+                .map(|method| 1 + NodeCounter::count(&method.body))
+                .sum::<usize>()
+    }
+
+    fn visit_return_stmt(&mut self, value: Option<&ExprNode>, _line: u32) -> usize {
+        1 + value.map_or(0, |expr| self.visit_expr_node(expr))
+    }
+
+    fn visit_throw_stmt(&mut self, value: &ExprNode, _line: u32) -> usize {
+        1 + self.visit_expr_node(value)
+    }
+
+    fn visit_try_stmt(&mut self, body: &[StmtNode], _name: &str, handler: &[StmtNode]) -> usize {
+        1 + NodeCounter::count(body) + NodeCounter::count(handler)
+    }
+}
+
+impl ExprVisitor for NodeCounter {
+    type Output = usize;
+
+    fn visit_literal(&mut self, _literal: &Literal) -> usize {
+        1
+    }
+
+    fn visit_grouping(&mut self, grouping: &ExprNode) -> usize {
+        1 + self.visit_expr_node(grouping)
+    }
+
+    fn visit_binary_expr(
+        &mut self,
+        left: &ExprNode,
+        _operator: &Operator,
+        right: &ExprNode,
+    ) -> usize {
+        1 + self.visit_expr_node(left) + self.visit_expr_node(right)
+    }
+
+    fn visit_unary_expr(&mut self, _operator: &Operator, child: &ExprNode) -> usize {
+        1 + self.visit_expr_node(child)
+    }
+
+    fn visit_logical_expr(
+        &mut self,
+        left: &ExprNode,
+        _operator: &Operator,
+        right: &ExprNode,
+    ) -> usize {
+        1 + self.visit_expr_node(left) + self.visit_expr_node(right)
+    }
+
+    fn visit_variable(&mut self, _name: &str, _line: u32) -> usize {
+        1
+    }
+
+    fn visit_assign(&mut self, _name: &str, value: &ExprNode, _line: u32) -> usize {
+        1 + self.visit_expr_node(value)
+    }
+
+    fn visit_call(&mut self, callee: &ExprNode, arguments: &[ExprNode], _line: u32) -> usize {
+        1 + self.visit_expr_node(callee)
+            + arguments
+                .iter()
+                .map(|argument| self.visit_expr_node(argument))
+                .sum::<usize>()
+    }
+
+    fn visit_get(&mut self, object: &ExprNode, _name: &str, _line: u32) -> usize {
+        1 + self.visit_expr_node(object)
+    }
+
+    fn visit_set(&mut self, object: &ExprNode, _name: &str, value: &ExprNode, _line: u32) -> usize {
+        1 + self.visit_expr_node(object) + self.visit_expr_node(value)
+    }
+
+    fn visit_list(&mut self, elements: &[ExprNode], _line: u32) -> usize {
+        1 + elements
+            .iter()
+            .map(|element| self.visit_expr_node(element))
+            .sum::<usize>()
+    }
+
+    fn visit_map(&mut self, entries: &[(ExprNode, ExprNode)], _line: u32) -> usize {
+        1 + entries
+            .iter()
+            .map(|(key, value)| self.visit_expr_node(key) + self.visit_expr_node(value))
+            .sum::<usize>()
+    }
+
+    fn visit_index(&mut self, object: &ExprNode, index: &ExprNode, _line: u32) -> usize {
+        1 + self.visit_expr_node(object) + self.visit_expr_node(index)
+    }
+
+    fn visit_slice(
+        &mut self,
+        object: &ExprNode,
+        start: Option<&ExprNode>,
+        end: Option<&ExprNode>,
+        _line: u32,
+    ) -> usize {
+        1 + self.visit_expr_node(object)
+            + start
+                .into_iter()
+                .chain(end)
+                .map(|bound| self.visit_expr_node(bound))
+                .sum::<usize>()
+    }
+
+    fn visit_set_index(
+        &mut self,
+        object: &ExprNode,
+        index: &ExprNode,
+        value: &ExprNode,
+        _line: u32,
+    ) -> usize {
+        1 + self.visit_expr_node(object) + self.visit_expr_node(index) + self.visit_expr_node(value)
+    }
+
+    fn visit_this(&mut self, _line: u32) -> usize {
+        1
+    }
+
+    fn visit_super(&mut self, _method: &str, _line: u32) -> usize {
+        1
+    }
+}
+
+/// A stage of the pipeline holding the artifact `A`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pipeline<A> {
+    artifact: A,
+    trace: Trace,
+}
+
+impl<A> Pipeline<A> {
+    /// starts a pipeline at the stage of the given artifact, e.g. from tokens produced elsewhere.
+    pub fn from_artifact(artifact: A) -> Self {
+        Pipeline {
+            artifact,
+            trace: Trace::default(),
+        }
+    }
+
+    /// keeps copies of the tokens and syntax tree in the trace as the pipeline produces them.
+    pub fn with_artifacts(mut self, keep_artifacts: bool) -> Self {
+        self.trace.keep_artifacts = keep_artifacts;
+        self
+    }
+
+    pub fn artifact(&self) -> &A {
+        &self.artifact
+    }
+
+    pub fn into_artifact(self) -> A {
+        self.artifact
+    }
+
+    /// the stages that have run so far.
+    pub fn trace(&self) -> &Trace {
+        &self.trace
+    }
+
+    /// moves on to the next stage, keeping the trace of the stages before it.
+    fn next<B>(self, artifact: B) -> Pipeline<B> {
+        Pipeline {
+            artifact,
+            trace: self.trace,
+        }
+    }
+}
+
+impl Pipeline<Source> {
+    pub fn new(source: &str) -> Self {
+        Pipeline::from_artifact(Source(source.to_string()))
+    }
+
+    pub fn lex(self) -> Result<Pipeline<Tokens>> {
+        self.lex_with(&mut Lexer::new())
+    }
+
+    pub fn lex_with(mut self, lexer: &mut Lexer) -> Result<Pipeline<Tokens>> {
+        let start = Stopwatch::start();
+        let tokens = lexer.lex(&self.artifact.0)?;
+        self.trace.record(Stage::Lex, start, tokens.len());
+        if self.trace.keep_artifacts {
+            self.trace.tokens = Some(tokens.clone());
+        }
+        Ok(self.next(Tokens(tokens)))
+    }
+}
+
+impl Pipeline<Tokens> {
+    pub fn parse(self) -> Pipeline<Ast> {
+        self.parse_with(&mut Parser::new())
+    }
+
+    pub fn parse_with(mut self, parser: &mut Parser) -> Pipeline<Ast> {
+        let start = Stopwatch::start();
+        let statements = parser.parse(std::mem::take(&mut self.artifact.0));
+        self.trace
+            .record(Stage::Parse, start, NodeCounter::count(&statements));
+        if self.trace.keep_artifacts {
+            self.trace.ast = Some(statements.clone());
+        }
+        self.next(Ast(statements))
+    }
+}
+
+impl Pipeline<Ast> {
+    pub fn resolve(self) -> Result<Pipeline<Resolved>> {
+        self.resolve_with(&mut PassThroughResolver)
+    }
+
+    pub fn resolve_with(mut self, resolver: &mut dyn Resolver) -> Result<Pipeline<Resolved>> {
+        let start = Stopwatch::start();
+        let statements = resolver.resolve(std::mem::take(&mut self.artifact.0))?;
+        self.trace
+            .record(Stage::Resolve, start, NodeCounter::count(&statements));
+        Ok(self.next(Resolved(statements)))
+    }
+}
+
+impl Pipeline<Resolved> {
+    /// runs the program on a new interpreter which prints to stdout, returning the trace of the run.
+    pub fn interpret(self) -> Result<Trace> {
+        self.interpret_with(&mut Interpreter::new())
+    }
+
+    pub fn interpret_with(mut self, interpreter: &mut Interpreter) -> Result<Trace> {
+        let start = Stopwatch::start();
+        let executed = interpreter.metrics().statements_executed;
+        interpreter.execute(self.artifact.0)?;
+        let executed = interpreter.metrics().statements_executed - executed;
+        self.trace
+            .record(Stage::Interpret, start, executed as usize);
+        Ok(self.trace)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ast::{ExprNode, Literal};
+    use anyhow::anyhow;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    /// a resolver which rejects programs that print anything
+    struct NoPrintResolver;
+
+    impl Resolver for NoPrintResolver {
+        fn resolve(&mut self, statements: Vec<StmtNode>) -> Result<Vec<StmtNode>> {
+            if statements
+                .iter()
+                .any(|stmt| matches!(stmt, StmtNode::PrintStmt(_)))
+            {
+                return Err(anyhow!("print is not allowed"));
+            }
+            Ok(statements)
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct SharedOutput(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn pipeline_test() {
+        let ast = Pipeline::new("var a = 2;\nprint a * 3;")
+            .lex()
+            .unwrap()
+            .parse();
+        assert_eq!(ast.artifact().0.len(), 2);
+
+        let output = SharedOutput::default();
+        let mut interpreter = Interpreter::new().with_output(Box::new(output.clone()));
+        ast.clone()
+            .resolve()
+            .unwrap()
+            .interpret_with(&mut interpreter)
+            .unwrap();
+        assert_eq!(output.0.lock().unwrap().as_slice(), b"6\n");
+
+        // swapping in a different resolver
+        assert!(ast.resolve_with(&mut NoPrintResolver).is_err());
+    }
+
+    #[test]
+    /// tests that every stage is recorded in the trace and artifacts are only kept when asked for
+    fn trace_test() {
+        let source = "var a = 1;\nwhile (a < 3) a = a + 1;";
+        let mut interpreter = Interpreter::new();
+        let trace = Pipeline::new(source)
+            .lex()
+            .unwrap()
+            .parse()
+            .resolve()
+            .unwrap()
+            .interpret_with(&mut interpreter)
+            .unwrap();
+
+        let counts = trace
+            .stages
+            .iter()
+            .map(|stats| (stats.stage, stats.count))
+            .collect::<Vec<_>>();
+        // 3 iterations of the loop body plus the declaration and the loop itself
+        assert_eq!(
+            counts,
+            vec![
+                (Stage::Lex, 18),
+                (Stage::Parse, 11),
+                (Stage::Resolve, 11),
+                (Stage::Interpret, 4),
+            ]
+        );
+        assert_eq!(trace.tokens, None);
+        assert_eq!(trace.ast, None);
+        assert_eq!(trace.to_string().lines().count(), 5);
+
+        let ast = Pipeline::new(source)
+            .with_artifacts(true)
+            .lex()
+            .unwrap()
+            .parse();
+        assert_eq!(ast.trace().tokens.as_ref().map(Vec::len), Some(18));
+        assert_eq!(ast.trace().ast.as_ref(), Some(&ast.artifact().0));
+    }
+
+    #[test]
+    /// tests that a pipeline can start from an artifact produced somewhere else
+    fn from_artifact_test() {
+        let statements = vec![StmtNode::ExprStmt(ExprNode::Literal(Literal::Nil))];
+        let resolved = Pipeline::from_artifact(Ast(statements.clone()))
+            .resolve_with(&mut NoPrintResolver)
+            .unwrap();
+        assert_eq!(resolved.into_artifact(), Resolved(statements));
+    }
+}
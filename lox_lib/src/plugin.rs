@@ -0,0 +1,73 @@
+//!
+//! This module contains the plugin system, which lets crates other than this one ship extensions to
+//! Lox, such as bindings to a database or an HTTP client. A plugin is a type implementing
+//! [`LoxPlugin`], which registers what it adds to the language:
+//!
+//! - natives, which are written in Rust like the ones in [`crate::natives`]
+//! - preludes, Lox source run when the plugin is enabled, which is how a plugin declares classes and
+//!   functions written in Lox on top of its natives
+//! - modules, Lox scripts the interpreter can load by path as if they were files
+//!
+//! A host enables plugins with [`crate::interpreter::Interpreter::with_plugin`], or by name from
+//! the `plugins` of a project config, see [`crate::config`], for the plugins it was built with.
+
+use crate::natives::NativeFunction;
+use anyhow::{anyhow, Result};
+
+/// An extension to Lox which can be enabled on an interpreter.
+pub trait LoxPlugin {
+    /// the name the plugin is enabled by in a project config, e.g. `http`
+    fn name(&self) -> &'static str;
+
+    /// adds the natives, preludes and modules of the plugin to the registry
+    fn register(&self, registry: &mut Registry);
+}
+
+/// What a plugin adds to an interpreter.
+#[derive(Debug, Clone, Default)]
+pub struct Registry {
+    pub(crate) natives: Vec<NativeFunction>,
+    pub(crate) preludes: Vec<String>,
+    pub(crate) modules: Vec<(String, String)>,
+}
+
+impl Registry {
+    /// defines a native in the global environment, replacing a global of the same name
+    pub fn native(&mut self, native: NativeFunction) -> &mut Self {
+        self.natives.push(native);
+        self
+    }
+
+    /// runs Lox source once the natives of the plugin are defined, in the global environment. The
+    /// preludes of a plugin run in the order they were registered.
+    pub fn prelude(&mut self, source: &str) -> &mut Self {
+        self.preludes.push(source.to_string());
+        self
+    }
+
+    /// serves a module at the given path, which is found before the scripts of the module loader
+    pub fn module(&mut self, path: &str, source: &str) -> &mut Self {
+        self.modules.push((path.to_string(), source.to_string()));
+        self
+    }
+}
+
+/// the plugin with the given name out of the ones available to a host
+pub fn find<'a>(plugins: &[&'a dyn LoxPlugin], name: &str) -> Result<&'a dyn LoxPlugin> {
+    plugins
+        .iter()
+        .find(|plugin| plugin.name() == name)
+        .copied()
+        .ok_or_else(|| {
+            let names: Vec<_> = plugins.iter().map(|plugin| plugin.name()).collect();
+            anyhow!(
+                "Unknown plugin {}, the plugins available are: {}",
+                name,
+                if names.is_empty() {
+                    "none".to_string()
+                } else {
+                    names.join(", ")
+                }
+            )
+        })
+}
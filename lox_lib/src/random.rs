@@ -0,0 +1,88 @@
+//!
+//! This module contains the random number generator behind `random()`, `randomInt(lo, hi)` and
+//! `seedRandom(n)`. Every interpreter has its own generator, seeded from the clock unless it is
+//! given a seed with [`crate::interpreter::Interpreter::with_seed`] or the program calls
+//! `seedRandom`, so that simulations and graded exercises can be run again with the same numbers.
+//!
+//! The generator is SplitMix64, which is fast, has a 64 bit state and is good enough for games and
+//! simulations, but not for cryptography.
+
+use crate::clock;
+
+/// A seedable generator of pseudo random numbers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Random { state: seed }
+    }
+
+    /// a generator seeded from the current time, which differs from run to run. Builds without a
+    /// clock always start from the same seed.
+    pub fn from_clock() -> Self {
+        let nanos = clock::since_epoch()
+            .map(|elapsed| elapsed.as_nanos() as u64)
+            .unwrap_or_default();
+        Random::new(nanos)
+    }
+
+    /// restarts the sequence of numbers from a seed
+    pub fn seed(&mut self, seed: u64) {
+        self.state = seed;
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// a number from 0 up to but not including 1
+    pub fn next_f64(&mut self) -> f64 {
+        // the top 53 bits fill the mantissa of the double exactly
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// a whole number from lo up to and including hi, where lo must not be greater than hi
+    pub fn range(&mut self, lo: i64, hi: i64) -> i64 {
+        let span = (hi as i128 - lo as i128 + 1) as u128;
+        // multiplying instead of taking the remainder keeps small spans free of bias
+        let offset = (self.next_u64() as u128 * span) >> 64;
+        (lo as i128 + offset as i128) as i64
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    /// tests that a seed always gives the same numbers, and that they stay within their ranges
+    fn random_test() {
+        let mut first = Random::new(42);
+        let mut second = Random::new(42);
+        let numbers: Vec<u64> = (0..5).map(|_| first.next_u64()).collect();
+        assert_eq!(
+            numbers,
+            (0..5).map(|_| second.next_u64()).collect::<Vec<_>>()
+        );
+        assert_ne!(Random::new(43).next_u64(), numbers[0]);
+
+        second.seed(42);
+        assert_eq!(second.next_u64(), numbers[0]);
+
+        let mut random = Random::new(7);
+        for _ in 0..1000 {
+            let number = random.next_f64();
+            assert!((0.0..1.0).contains(&number));
+            let whole = random.range(-2, 2);
+            assert!((-2..=2).contains(&whole));
+        }
+        assert_eq!(random.range(5, 5), 5);
+    }
+}
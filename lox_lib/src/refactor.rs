@@ -0,0 +1,298 @@
+//!
+//! This module contains the refactorings behind `lox rename`, which renames what an identifier names
+//! along with every reference to it. Identifiers are resolved to their declarations through the
+//! scopes of the program by [`crate::semantic`], so a local variable of the same name which shadows
+//! the renamed one is left alone.
+//!
+//! A rename is refused when the new name would change what any identifier resolves to, such as when
+//! it is shadowed by another declaration or takes over the name of a native. Methods and properties
+//! are looked up on objects when the program runs, so they can not be renamed safely. Only the
+//! script itself is renamed, the modules which import it and use the renamed name through their
+//! namespace have to be renamed on their own.
+//!
+//! [`encode`] gives the edits as the `TextEdit`s of the `textDocument/rename` responses of the
+//! language server protocol, which `lox rename --lsp` prints for editors.
+
+use crate::emit::json_string;
+use crate::fix::{apply_edits, line_diff, Edit};
+use crate::lexer::{Lexer, Position, TokenType};
+use crate::semantic::{classify, SemanticKind};
+use anyhow::{anyhow, Result};
+use std::fmt;
+
+/// an edit of the source which replaces an identifier with the new name
+pub type TextEdit = Edit;
+
+/// finds the edits which rename what the identifier at the position names to the new name, where
+/// the position can be anywhere in the identifier. The source is lexed with the lexer, so it has to
+/// be set up the same way as when the source runs.
+pub fn rename(
+    lexer: &mut Lexer,
+    source: &str,
+    position: Position,
+    new_name: &str,
+) -> Result<Vec<TextEdit>> {
+    let is_identifier = match lexer.lex(new_name)?.as_slice() {
+        [name, eof] => {
+            name.token_type == TokenType::Identifier
+                && name.lexeme() == new_name
+                && eof.token_type == TokenType::Eof
+        }
+        _ => false,
+    };
+    if !is_identifier {
+        return Err(anyhow!("{} is not a valid name", new_name));
+    }
+
+    let tokens = lexer.lex(source)?;
+    let classified = classify(source, &tokens);
+    let target = classified
+        .iter()
+        .find(|token| {
+            token.line == position.line
+                && (token.column..=token.column + token.name.len()).contains(&position.column)
+        })
+        .ok_or_else(|| {
+            anyhow!(
+                "there is no name to rename in line {} at column {}",
+                position.line,
+                position.column + 1
+            )
+        })?;
+    let binding = match (target.kind, target.binding) {
+        (SemanticKind::Method | SemanticKind::Property, _) => {
+            return Err(anyhow!(
+                "can not rename {} since it is a {}, which is looked up when the program runs",
+                target.name,
+                target.kind
+            ))
+        }
+        (_, Some(binding)) => binding,
+        (_, None) if target.default_library => {
+            return Err(anyhow!(
+                "can not rename {} since it is a native function",
+                target.name
+            ))
+        }
+        (_, None) => {
+            return Err(anyhow!(
+                "can not rename {} since it is not declared in the script",
+                target.name
+            ))
+        }
+    };
+    let edits: Vec<_> = classified
+        .iter()
+        .filter(|token| token.binding == Some(binding))
+        .map(|token| Edit {
+            start: Position {
+                line: token.line,
+                column: token.column,
+            },
+            end: Position {
+                line: token.line,
+                column: token.column + token.name.len(),
+            },
+            replacement: new_name.to_string(),
+        })
+        .collect();
+
+    // the rename is only safe when every identifier still resolves to the same declaration
+    let renamed = apply_edits(source, &edits);
+    let resolved = classify(&renamed, &lexer.lex(&renamed)?);
+    let changed = classified
+        .iter()
+        .zip(&resolved)
+        .find(|(before, after)| before.binding != after.binding);
+    if let Some((_, after)) = changed {
+        return Err(anyhow!(
+            "can not rename {} to {} since {} in line {} would refer to something else",
+            target.name,
+            new_name,
+            after.name,
+            after.line
+        ));
+    }
+    if classified.len() != resolved.len() {
+        return Err(anyhow!("can not rename {} to {}", target.name, new_name));
+    }
+    Ok(edits)
+}
+
+/// encodes the edits the way the language server protocol expects them, as a JSON list of
+/// `TextEdit`s whose ranges have lines starting at 0 and columns counted in UTF-16 code units of the
+/// source.
+pub fn encode(source: &str, edits: &[TextEdit]) -> String {
+    let lines: Vec<&str> = source.lines().collect();
+    let position = |position: Position| {
+        let text = lines
+            .get(position.line as usize - 1)
+            .copied()
+            .unwrap_or_default();
+        let character = text
+            .get(..position.column)
+            .unwrap_or_default()
+            .encode_utf16()
+            .count();
+        format!(
+            "{{\"line\":{},\"character\":{}}}",
+            position.line - 1,
+            character
+        )
+    };
+    let edits: Vec<_> = edits
+        .iter()
+        .map(|edit| {
+            format!(
+                "{{\"range\":{{\"start\":{},\"end\":{}}},\"newText\":{}}}",
+                position(edit.start),
+                position(edit.end),
+                json_string(&edit.replacement)
+            )
+        })
+        .collect();
+    format!("[{}]", edits.join(","))
+}
+
+/// a script with a name renamed, see [`rename`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenameReport {
+    pub name: String,
+    pub source: String,
+    pub renamed: String,
+    /// the name before it was renamed
+    pub from: String,
+    pub to: String,
+    pub edits: Vec<TextEdit>,
+}
+
+impl RenameReport {
+    /// renames what the identifier at the position in the source names, see [`rename`]
+    pub fn new(
+        name: impl Into<String>,
+        lexer: &mut Lexer,
+        source: String,
+        position: Position,
+        new_name: &str,
+    ) -> Result<Self> {
+        let edits = rename(lexer, &source, position, new_name)?;
+        let from = edits
+            .first()
+            .map(|edit| {
+                let line = source.lines().nth(edit.start.line as usize - 1);
+                let name = line.and_then(|line| line.get(edit.start.column..edit.end.column));
+                name.unwrap_or_default().to_string()
+            })
+            .unwrap_or_default();
+        Ok(Self {
+            name: name.into(),
+            renamed: apply_edits(&source, &edits),
+            source,
+            from,
+            to: new_name.to_string(),
+            edits,
+        })
+    }
+
+    /// the lines changed by the rename, see [`crate::fix::line_diff`]
+    pub fn diff(&self) -> String {
+        line_diff(&self.source, &self.renamed)
+    }
+}
+
+impl fmt::Display for RenameReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{}: renamed {} to {} in {} places",
+            self.name,
+            self.from,
+            self.to,
+            self.edits.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// renames the name at the line and column of the source, returning the renamed source
+    fn renamed(source: &str, line: u32, column: usize, new_name: &str) -> Result<String> {
+        let position = Position { line, column };
+        let edits = rename(&mut Lexer::new(), source, position, new_name)?;
+        Ok(apply_edits(source, &edits))
+    }
+
+    #[test]
+    /// tests that a name is renamed where it is declared and referenced, following the scopes
+    fn rename_test() {
+        let source = "var count = 0;\nfun add(count) { return count + 1; }\n\
+            fun bump() { count = add(count); }\nvar count = 2;\nprint count;";
+        assert_eq!(
+            renamed(source, 3, 26, "total").unwrap(),
+            "var total = 0;\nfun add(count) { return count + 1; }\n\
+            fun bump() { total = add(total); }\nvar total = 2;\nprint total;"
+        );
+        assert_eq!(
+            renamed(source, 2, 8, "n").unwrap(),
+            "var count = 0;\nfun add(n) { return n + 1; }\n\
+            fun bump() { count = add(count); }\nvar count = 2;\nprint count;"
+        );
+        assert_eq!(
+            renamed(source, 3, 21, "plus").unwrap(),
+            "var count = 0;\nfun plus(count) { return count + 1; }\n\
+            fun bump() { count = plus(count); }\nvar count = 2;\nprint count;"
+        );
+
+        let class = "class Point { init(x) { this.x = x; } }\nvar p = Point(1);\nprint p.x;";
+        assert_eq!(
+            renamed(class, 2, 8, "Vector").unwrap(),
+            "class Vector { init(x) { this.x = x; } }\nvar p = Vector(1);\nprint p.x;"
+        );
+    }
+
+    #[test]
+    /// tests that renames which would change what a name refers to are refused
+    fn rename_conflict_test() {
+        let source = "var a = 1;\nvar b = 2;\nfun f(x) { return a + x; }\nprint len(\"ab\");";
+        // a parameter would shadow the global
+        let err = renamed(source, 3, 6, "a").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "can not rename x to a since a in line 3 would refer to something else"
+        );
+        // two globals would be merged, and a native would be shadowed
+        assert!(renamed(source, 1, 4, "b").is_err());
+        assert!(renamed(source, 1, 4, "len").is_err());
+
+        let err = renamed(source, 4, 6, "size").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "can not rename len since it is a native function"
+        );
+        assert!(renamed("print p.x;", 1, 8, "y").is_err());
+        assert!(renamed("print missing;", 1, 6, "found").is_err());
+        assert!(renamed(source, 1, 4, "while").is_err());
+        assert!(renamed(source, 1, 4, "two words").is_err());
+        assert!(renamed(source, 2, 0, "c").is_err());
+    }
+
+    #[test]
+    /// tests that edits are encoded the way the language server protocol expects them
+    fn encode_test() {
+        let source = "var é = 1;\nprint é;";
+        let edits = rename(
+            &mut Lexer::new(),
+            source,
+            Position { line: 2, column: 6 },
+            "e",
+        )
+        .unwrap();
+        assert_eq!(
+            encode(source, &edits),
+            "[{\"range\":{\"start\":{\"line\":0,\"character\":4},\"end\":{\"line\":0,\"character\":5}},\"newText\":\"e\"},\
+            {\"range\":{\"start\":{\"line\":1,\"character\":6},\"end\":{\"line\":1,\"character\":7}},\"newText\":\"e\"}]"
+        );
+    }
+}
@@ -0,0 +1,492 @@
+//!
+//! This module contains the semantic tokens of a program, which classify every identifier by the
+//! role of what it names, such as a parameter, a function or a class, rather than only saying that
+//! it is an identifier. Editors use them to highlight identifiers by role.
+//!
+//! Identifiers are resolved to their declarations through the scopes of the program the same way
+//! the interpreter looks them up, so a local variable shadowing a function is still a variable.
+//! Names which are declared nowhere are classified as variables. The kinds and modifiers match the
+//! standard ones of the language server protocol, and [`encode`] gives the relative encoding its
+//! `textDocument/semanticTokens` responses use.
+//!
+//! Every classified identifier also records the declaration it resolves to, which is what
+//! [`crate::refactor`] renames by. Declarations at the top level are global, so declaring a name
+//! there again still resolves to the first declaration.
+
+use crate::lexer::{positions, Position, Token, TokenType};
+use crate::natives::NATIVES;
+use std::collections::HashMap;
+use std::fmt;
+
+/// the role of what an identifier names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SemanticKind {
+    Variable,
+    Parameter,
+    Function,
+    Method,
+    Class,
+    Property,
+}
+
+impl SemanticKind {
+    /// the kinds in the order of their index in the legend
+    pub const LEGEND: [SemanticKind; 6] = [
+        SemanticKind::Variable,
+        SemanticKind::Parameter,
+        SemanticKind::Function,
+        SemanticKind::Method,
+        SemanticKind::Class,
+        SemanticKind::Property,
+    ];
+
+    /// the index of the kind in [`SemanticKind::LEGEND`]
+    pub fn index(&self) -> u32 {
+        *self as u32
+    }
+}
+
+/// the names of the kinds in the language server protocol
+impl fmt::Display for SemanticKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SemanticKind::Variable => "variable",
+            SemanticKind::Parameter => "parameter",
+            SemanticKind::Function => "function",
+            SemanticKind::Method => "method",
+            SemanticKind::Class => "class",
+            SemanticKind::Property => "property",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// the names of the modifiers in the language server protocol, where the modifiers of a token are
+/// encoded as a bit set with bit n set for the modifier at index n
+pub const MODIFIER_LEGEND: [&str; 2] = ["declaration", "defaultLibrary"];
+
+/// an identifier and the role of what it names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SemanticToken {
+    pub name: String,
+    pub line: u32,
+    /// the byte offset of the identifier in its line
+    pub column: usize,
+    pub kind: SemanticKind,
+    /// whether this is where the name is declared
+    pub declaration: bool,
+    /// whether the name is a native function
+    pub default_library: bool,
+    /// the index of the identifier among the tokens
+    pub index: usize,
+    /// the index among the tokens of the declaration the identifier resolves to, or none for
+    /// natives, properties, methods and names which are declared nowhere
+    pub binding: Option<usize>,
+}
+
+impl SemanticToken {
+    /// the modifiers of the token as a bit set, see [`MODIFIER_LEGEND`]
+    pub fn modifiers(&self) -> u32 {
+        self.declaration as u32 | (self.default_library as u32) << 1
+    }
+}
+
+/// what a name in a scope was declared as
+#[derive(Debug, Clone, Copy)]
+struct Declaration {
+    kind: SemanticKind,
+    native: bool,
+    /// the index of the declaring identifier among the tokens, none for natives
+    index: Option<usize>,
+}
+
+/// a classified identifier by its index among the tokens
+struct Classified {
+    index: usize,
+    kind: SemanticKind,
+    declaration: bool,
+    default_library: bool,
+    binding: Option<usize>,
+}
+
+/// the names declared in a block, function or class body
+#[derive(Default)]
+struct Scope {
+    names: HashMap<String, Declaration>,
+    /// whether this is the body of a class, where the declarations are methods
+    class_body: bool,
+}
+
+/// classifies the identifiers of a program, in the order they appear in. Identifiers which can't be
+/// found in the source, which only happens for tokens which were not lexed from it, are left out.
+pub fn classify(source: &str, tokens: &[Token]) -> Vec<SemanticToken> {
+    Classifier::new(tokens).run(tokens, &positions(source, tokens))
+}
+
+struct Classifier {
+    scopes: Vec<Scope>,
+    /// the parameters which are declared in the function body opened by the next `{`, with their
+    /// indices among the tokens
+    parameters: Vec<(String, usize)>,
+    /// whether the next `{` opens the body of a class
+    class_body: bool,
+    classified: Vec<Classified>,
+}
+
+impl Classifier {
+    /// starts with the natives and every declaration at the top level in the global scope, since
+    /// functions can refer to the ones declared after them
+    fn new(tokens: &[Token]) -> Self {
+        let mut globals = Scope::default();
+        for native in NATIVES {
+            globals.names.insert(
+                native.name.to_string(),
+                Declaration {
+                    kind: SemanticKind::Function,
+                    native: true,
+                    index: None,
+                },
+            );
+        }
+        let mut depth = 0;
+        for (index, pair) in tokens.windows(2).enumerate() {
+            let kind = match pair[0].token_type {
+                TokenType::LeftBrace => {
+                    depth += 1;
+                    continue;
+                }
+                TokenType::RightBrace => {
+                    depth -= 1;
+                    continue;
+                }
+                TokenType::Fun => SemanticKind::Function,
+                TokenType::Class => SemanticKind::Class,
+                TokenType::Var | TokenType::Const => SemanticKind::Variable,
+                _ => continue,
+            };
+            let declared = globals.names.get(pair[1].lexeme());
+            if depth == 0
+                && pair[1].token_type == TokenType::Identifier
+                && declared.is_none_or(|declared| declared.native)
+            {
+                globals.names.insert(
+                    pair[1].lexeme().to_string(),
+                    Declaration {
+                        kind,
+                        native: false,
+                        index: Some(index + 1),
+                    },
+                );
+            }
+        }
+        Self {
+            scopes: vec![globals],
+            parameters: Vec::new(),
+            class_body: false,
+            classified: Vec::new(),
+        }
+    }
+
+    /// declares a name in the innermost scope, where declaring a global again keeps its first
+    /// declaration
+    fn declare(&mut self, index: usize, name: &str, kind: SemanticKind) {
+        let global = self.scopes.len() == 1;
+        let mut binding = Some(index);
+        if let Some(scope) = self.scopes.last_mut() {
+            let declared = scope
+                .names
+                .get(name)
+                .filter(|declared| global && !declared.native);
+            binding = declared.and_then(|declared| declared.index).or(binding);
+            scope.names.insert(
+                name.to_string(),
+                Declaration {
+                    kind,
+                    native: false,
+                    index: binding,
+                },
+            );
+        }
+        self.classify(index, kind, true, false, binding);
+    }
+
+    /// declares a name imported from another module in the innermost scope
+    fn import(&mut self, index: usize, name: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.names.insert(
+                name.to_string(),
+                Declaration {
+                    kind: SemanticKind::Variable,
+                    native: false,
+                    index: None,
+                },
+            );
+        }
+        self.classify(index, SemanticKind::Variable, true, false, None);
+    }
+
+    fn classify(
+        &mut self,
+        index: usize,
+        kind: SemanticKind,
+        declaration: bool,
+        default_library: bool,
+        binding: Option<usize>,
+    ) {
+        self.classified.push(Classified {
+            index,
+            kind,
+            declaration,
+            default_library,
+            binding,
+        });
+    }
+
+    /// classifies a reference to a name by its innermost declaration
+    fn reference(&mut self, index: usize, name: &str) {
+        let declaration = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.names.get(name))
+            .copied()
+            .unwrap_or(Declaration {
+                kind: SemanticKind::Variable,
+                native: false,
+                index: None,
+            });
+        self.classify(
+            index,
+            declaration.kind,
+            false,
+            declaration.native,
+            declaration.index,
+        );
+    }
+
+    /// classifies the parameters of a function starting at the `(` at the index, returning the index
+    /// of the closing `)`
+    fn parameters(&mut self, tokens: &[Token], start: usize) -> usize {
+        let mut index = start + 1;
+        while let Some(token) = tokens.get(index) {
+            match token.token_type {
+                TokenType::Identifier => {
+                    self.classify(index, SemanticKind::Parameter, true, false, Some(index));
+                    self.parameters.push((token.lexeme().to_string(), index));
+                }
+                TokenType::RightParen => break,
+                _ => {}
+            }
+            index += 1;
+        }
+        index
+    }
+
+    fn run(mut self, tokens: &[Token], positions: &[Option<Position>]) -> Vec<SemanticToken> {
+        let token_type = |index: usize| tokens.get(index).map(|token| &token.token_type);
+        let in_class_body = |scopes: &[Scope]| scopes.last().is_some_and(|scope| scope.class_body);
+
+        let mut index = 0;
+        while let Some(token) = tokens.get(index) {
+            let next = token_type(index + 1);
+            match &token.token_type {
+                TokenType::LeftBrace => {
+                    let mut scope = Scope {
+                        class_body: std::mem::take(&mut self.class_body),
+                        ..Scope::default()
+                    };
+                    for (parameter, index) in self.parameters.drain(..) {
+                        scope.names.insert(
+                            parameter,
+                            Declaration {
+                                kind: SemanticKind::Parameter,
+                                native: false,
+                                index: Some(index),
+                            },
+                        );
+                    }
+                    self.scopes.push(scope);
+                }
+                TokenType::RightBrace if self.scopes.len() > 1 => {
+                    self.scopes.pop();
+                }
+                TokenType::Fun | TokenType::Var | TokenType::Const
+                    if next == Some(&TokenType::Identifier) =>
+                {
+                    let kind = match token.token_type {
+                        TokenType::Fun => SemanticKind::Function,
+                        _ => SemanticKind::Variable,
+                    };
+                    index += 1;
+                    self.declare(index, tokens[index].lexeme(), kind);
+                    if kind == SemanticKind::Function
+                        && token_type(index + 1) == Some(&TokenType::LeftParen)
+                    {
+                        index = self.parameters(tokens, index + 1);
+                    }
+                }
+                TokenType::Class
+                    if next == Some(&TokenType::Identifier) && !in_class_body(&self.scopes) =>
+                {
+                    index += 1;
+                    self.declare(index, tokens[index].lexeme(), SemanticKind::Class);
+                    self.class_body = true;
+                }
+                // a method, which is declared in the class body but can only be called on the class
+                // or its instances, so its name is not added to the scope
+                TokenType::Identifier
+                    if in_class_body(&self.scopes) && next == Some(&TokenType::LeftParen) =>
+                {
+                    self.classify(index, SemanticKind::Method, true, false, None);
+                    index = self.parameters(tokens, index + 1);
+                }
+                TokenType::Dot if next == Some(&TokenType::Identifier) => {
+                    index += 1;
+                    let kind = match token_type(index + 1) {
+                        Some(TokenType::LeftParen) => SemanticKind::Method,
+                        _ => SemanticKind::Property,
+                    };
+                    self.classify(index, kind, false, false, None);
+                }
+                // the names imported from a module are declared there, so they have no binding here
+                TokenType::Import if next == Some(&TokenType::LeftBrace) => {
+                    index += 2;
+                    while let Some(token) = tokens.get(index) {
+                        match token.token_type {
+                            TokenType::Identifier => self.import(index, token.lexeme()),
+                            TokenType::Comma => {}
+                            _ => break,
+                        }
+                        index += 1;
+                    }
+                    if token_type(index) == Some(&TokenType::RightBrace)
+                        && tokens
+                            .get(index + 1)
+                            .is_some_and(|token| token.lexeme() == "from")
+                    {
+                        index += 1;
+                    }
+                }
+                TokenType::Identifier
+                    if token.lexeme() == "export"
+                        && matches!(
+                            next,
+                            Some(
+                                TokenType::Class
+                                    | TokenType::Fun
+                                    | TokenType::Var
+                                    | TokenType::Const
+                                    | TokenType::Import
+                            )
+                        ) => {}
+                TokenType::Identifier => self.reference(index, token.lexeme()),
+                _ => {}
+            }
+            index += 1;
+        }
+
+        self.classified
+            .into_iter()
+            .filter_map(|classified| {
+                let position = positions.get(classified.index).copied().flatten()?;
+                Some(SemanticToken {
+                    name: tokens[classified.index].lexeme().to_string(),
+                    line: position.line,
+                    column: position.column,
+                    kind: classified.kind,
+                    declaration: classified.declaration,
+                    default_library: classified.default_library,
+                    index: classified.index,
+                    binding: classified.binding,
+                })
+            })
+            .collect()
+    }
+}
+
+/// encodes the tokens the way the language server protocol expects them, as 5 numbers per token:
+/// the line relative to the previous token, the start relative to the previous token when it is on
+/// the same line, the length, the index of the kind and the bit set of modifiers. Lines start at 0
+/// and columns are counted in UTF-16 code units of the source.
+pub fn encode(source: &str, tokens: &[SemanticToken]) -> Vec<u32> {
+    let lines: Vec<&str> = source.lines().collect();
+    let utf16_len = |text: &str| text.encode_utf16().count() as u32;
+
+    let mut data = Vec::with_capacity(tokens.len() * 5);
+    let (mut previous_line, mut previous_start) = (0, 0);
+    for token in tokens {
+        let line = token.line - 1;
+        let text = lines.get(line as usize).copied().unwrap_or_default();
+        let start = utf16_len(text.get(..token.column).unwrap_or_default());
+        if line != previous_line {
+            previous_start = 0;
+        }
+        data.extend([
+            line - previous_line,
+            start - previous_start,
+            utf16_len(&token.name),
+            token.kind.index(),
+            token.modifiers(),
+        ]);
+        previous_line = line;
+        previous_start = start;
+    }
+    data
+}
+
+// the test highlights a call of clock, which builds without the host feature do not have
+#[cfg(all(test, feature = "host"))]
+mod test {
+    use super::*;
+    use crate::lexer::Lexer;
+
+    #[test]
+    /// tests that identifiers are classified by what they name, following the scopes
+    fn classify_test() {
+        let source = "var total = 0;\n\
+            class Counter {\n  add(amount) { this.count = amount + total; log(\"info\", clock()); }\n}\n\
+            fun twice(add) { add(1); return Counter().add(2); }";
+        let tokens = Lexer::new().lex(source).unwrap();
+        let classified: Vec<_> = classify(source, &tokens)
+            .iter()
+            .map(|token| {
+                format!(
+                    "{}:{} {} {}{}",
+                    token.line,
+                    token.column,
+                    token.name,
+                    token.kind,
+                    ["", " declaration", " defaultLibrary"][token.modifiers() as usize]
+                )
+            })
+            .collect();
+        assert_eq!(
+            classified,
+            vec![
+                "1:4 total variable declaration",
+                "2:6 Counter class declaration",
+                "3:2 add method declaration",
+                "3:6 amount parameter declaration",
+                "3:21 count property",
+                "3:29 amount parameter",
+                "3:38 total variable",
+                "3:45 log function defaultLibrary",
+                "3:57 clock function defaultLibrary",
+                "5:4 twice function declaration",
+                "5:10 add parameter declaration",
+                "5:17 add parameter",
+                "5:32 Counter class",
+                "5:42 add method",
+            ]
+        );
+
+        let tokens = classify(
+            "fun f() {}\n  f();",
+            &Lexer::new().lex("fun f() {}\n  f();").unwrap(),
+        );
+        assert_eq!(
+            encode("fun f() {}\n  f();", &tokens),
+            vec![0, 4, 1, 2, 1, 1, 2, 1, 2, 0]
+        );
+    }
+}
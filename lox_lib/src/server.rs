@@ -0,0 +1,213 @@
+//!
+//! This module contains a REPL server which lets remote tools drive evaluation over TCP.
+//!
+//! Every connection is a separate session with its own interpreter, so the globals defined by one
+//! client are never visible to another. A session reads lines of Lox source from the connection and
+//! writes the prompt, printed values and errors back to it, exactly like the local REPL.
+//!
+//! Clients are not trusted with the machine the server runs on. Sessions can not import scripts
+//! from its file system, natives needing a capability are not granted one, the values a session
+//! holds are limited by a heap limit and a session is cancelled once it has run for too long.
+//! Connections beyond the number of sessions served at once are turned away.
+
+use crate::interpreter::{Interpreter, RuntimeError};
+use crate::loader::InMemoryLoader;
+use anyhow::{Context, Result};
+use std::io::{BufReader, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// binds to the given address (e.g. `127.0.0.1:7070`) and serves REPL sessions until the process exits.
+pub fn listen(addr: &str) -> Result<()> {
+    let listener =
+        TcpListener::bind(addr).with_context(|| format!("Failed to listen on {}", addr))?;
+    serve(listener)
+}
+
+/// accepts connections from an already bound listener with the default limits, see [`Server`].
+pub fn serve(listener: TcpListener) -> Result<()> {
+    Server::new(listener).serve()
+}
+
+/// Serves REPL sessions from a bound listener, each on its own thread.
+#[derive(Debug)]
+pub struct Server {
+    listener: TcpListener,
+    max_sessions: usize,
+    timeout: Duration,
+    heap_limit: usize,
+}
+
+impl Server {
+    /// creates a server serving at most 16 sessions at once, each for up to 10 minutes and with up
+    /// to 64MB of values.
+    pub fn new(listener: TcpListener) -> Self {
+        Server {
+            listener,
+            max_sessions: 16,
+            timeout: Duration::from_secs(10 * 60),
+            heap_limit: 64 << 20,
+        }
+    }
+
+    /// sets how many sessions are served at once. Connections beyond them are told to try again
+    /// later and closed.
+    pub fn with_max_sessions(mut self, max_sessions: usize) -> Self {
+        self.max_sessions = max_sessions;
+        self
+    }
+
+    /// sets how long a session can last before it is cancelled, whether it is running something or
+    /// waiting for the client.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// sets the bytes of values a session can hold, see [`Interpreter::with_heap_limit`].
+    pub fn with_heap_limit(mut self, heap_limit: usize) -> Self {
+        self.heap_limit = heap_limit;
+        self
+    }
+
+    /// accepts connections until the listener fails.
+    pub fn serve(self) -> Result<()> {
+        let sessions = Arc::new(AtomicUsize::new(0));
+        for stream in self.listener.incoming() {
+            let mut stream = stream?;
+            if sessions.load(Ordering::SeqCst) >= self.max_sessions {
+                let _ = writeln!(stream, "Too many sessions, try again later");
+                continue;
+            }
+            sessions.fetch_add(1, Ordering::SeqCst);
+            let sessions = sessions.clone();
+            let (timeout, heap_limit) = (self.timeout, self.heap_limit);
+            thread::spawn(move || {
+                let peer = stream.peer_addr();
+                if let Err(err) = serve_session(stream, timeout, heap_limit) {
+                    eprintln!("REPL session {:?} ended with an error: {}", peer, err);
+                }
+                sessions.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+        Ok(())
+    }
+}
+
+/// runs a single REPL session on an isolated interpreter until the client closes the connection or
+/// the session times out.
+fn serve_session(stream: TcpStream, timeout: Duration, heap_limit: usize) -> Result<()> {
+    let mut output = stream.try_clone()?;
+    let mut interpreter = Interpreter::new()
+        .with_output(Box::new(output.try_clone()?))
+        .with_error_output(Box::new(stream.try_clone()?))
+        .with_module_loader(Box::new(InMemoryLoader::new()))
+        .with_heap_limit(heap_limit);
+
+    // cancels whatever the session is running once it times out, and stops it from waiting for
+    // the client any longer
+    let token = interpreter.cancellation_token();
+    let watched = stream.try_clone()?;
+    let (done, finished) = mpsc::channel::<()>();
+    let watchdog = thread::spawn(move || {
+        if finished.recv_timeout(timeout) == Err(RecvTimeoutError::Timeout) {
+            token.cancel();
+            let _ = watched.shutdown(Shutdown::Read);
+        }
+    });
+
+    let result = interpreter.repl(BufReader::new(stream));
+    drop(done);
+    let _ = watchdog.join();
+    match result {
+        Err(err) if err.downcast_ref::<RuntimeError>() == Some(&RuntimeError::Cancelled) => {
+            writeln!(output, "{}", err)?;
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Read;
+
+    /// sends the source to a new session and reads everything the server writes back
+    fn session(addr: std::net::SocketAddr, source: &str) -> String {
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .unwrap();
+        client.write_all(source.as_bytes()).unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    /// tests that a client can evaluate source over the connection and read the result back
+    fn remote_session_test() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || serve(listener));
+
+        assert_eq!(session(addr, "print 1 + 2;\n"), ">> 3\n>> ");
+    }
+
+    #[test]
+    /// tests that sessions can not import from the file system of the server, write their errors to
+    /// the client and run out of heap at the limit
+    fn sandbox_test() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || Server::new(listener).with_heap_limit(4096).serve());
+
+        let response = session(addr, "import \"/etc/hostname\";\n");
+        assert!(response.contains("[E0029]"), "{}", response);
+        assert_eq!(session(addr, "eprint(\"oops\");\n"), ">> oops\n>> ");
+        let response = session(
+            addr,
+            "var l = [];\nwhile (true) push(l, 1);\nprint len(l) > 0;\n",
+        );
+        assert!(response.contains("[E0031]"), "{}", response);
+        assert!(response.ends_with("true\n>> "), "{}", response);
+    }
+
+    #[test]
+    /// tests that a session running for too long is cancelled, and that connections beyond the
+    /// sessions served at once are turned away
+    fn limits_test() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            Server::new(listener)
+                .with_max_sessions(1)
+                .with_timeout(Duration::from_millis(300))
+                .serve()
+        });
+
+        let mut busy = TcpStream::connect(addr).unwrap();
+        busy.write_all(b"while (true) {}\n").unwrap();
+        // the prompt is written once the session has started
+        let mut prompt = [0; 3];
+        busy.read_exact(&mut prompt).unwrap();
+        // nothing is sent, since closing a connection with unread input resets it
+        let mut response = String::new();
+        TcpStream::connect(addr)
+            .unwrap()
+            .read_to_string(&mut response)
+            .unwrap();
+        assert_eq!(response, "Too many sessions, try again later\n");
+
+        let mut response = String::new();
+        busy.read_to_string(&mut response).unwrap();
+        assert_eq!(response, "Execution was cancelled\n");
+    }
+}
@@ -0,0 +1,265 @@
+//!
+//! This module contains the SQLite plugin, which lets Lox scripts work with SQLite databases:
+//!
+//! - `dbOpen(path)` opens the database file, creating it if needed, or an in-memory database for
+//!   `":memory:"`, and returns a handle to it
+//! - `dbExec(db, sql, params)` runs a statement and returns the number of rows it changed
+//! - `dbQuery(db, sql, params)` runs a query and returns a list of its rows, each a map from the
+//!   names of the columns to their values
+//!
+//! `params` is a list of the values of the `?` parameters of the statement, or nil when it has
+//! none. Opening a database file needs the file I/O capability, see
+//! [`Interpreter::with_capability`]. The plugin is only built with the `sqlite` feature.
+
+use crate::analyze::Capability;
+use crate::ast::Literal;
+use crate::interpreter::{Interpreter, LoxError};
+use crate::map::{LoxMap, MapKey};
+use crate::natives::{Handle, NativeFunction};
+use crate::plugin::{LoxPlugin, Registry};
+use anyhow::{anyhow, Result};
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// The SQLite plugin, enabled as `sqlite`.
+pub struct SqlitePlugin;
+
+impl LoxPlugin for SqlitePlugin {
+    fn name(&self) -> &'static str {
+        "sqlite"
+    }
+
+    fn register(&self, registry: &mut Registry) {
+        registry
+            .native(
+                NativeFunction::new("dbOpen", 1, db_open)
+                    .with_doc(&["path"], "opens the SQLite database at a path")
+                    .with_capability(Capability::FileIo),
+            )
+            .native(
+                NativeFunction::new("dbExec", 3, db_exec)
+                    .with_doc(
+                        &["db", "sql", "params"],
+                        "runs a statement with a list of parameters, returning the number of rows changed",
+                    )
+                    .with_capability(Capability::FileIo),
+            )
+            .native(
+                NativeFunction::new("dbQuery", 3, db_query)
+                    .with_doc(
+                        &["db", "sql", "params"],
+                        "runs a query with a list of parameters, returning its rows as a list of maps",
+                    )
+                    .with_capability(Capability::FileIo),
+            );
+    }
+}
+
+fn error(line: u32, message: impl Into<String>) -> anyhow::Error {
+    anyhow!(LoxError::new(line, message))
+}
+
+fn db_open(interpreter: &mut Interpreter, arguments: &[Literal], line: u32) -> Result<Literal> {
+    let Literal::String(path) = &arguments[0] else {
+        return Err(error(line, "dbOpen expects the path to be a string"));
+    };
+    let connection = if path == ":memory:" {
+        Connection::open_in_memory()
+    } else {
+        interpreter.require_capability(Capability::FileIo, "dbOpen", line)?;
+        Connection::open(path)
+    }
+    .map_err(|err| error(line, format!("dbOpen {} failed: {}", path, err)))?;
+    Ok(Literal::Handle(Handle::new("database", connection)))
+}
+
+fn db_exec(_interpreter: &mut Interpreter, arguments: &[Literal], line: u32) -> Result<Literal> {
+    let connection = database("dbExec", &arguments[0], line)?;
+    let sql = sql("dbExec", &arguments[1], line)?;
+    let params = params("dbExec", &arguments[2], line)?;
+    let changed = connection
+        .execute(sql, params_from_iter(params))
+        .map_err(|err| error(line, format!("dbExec failed: {}", err)))?;
+    Ok(Literal::Number(changed as f64))
+}
+
+fn db_query(_interpreter: &mut Interpreter, arguments: &[Literal], line: u32) -> Result<Literal> {
+    let connection = database("dbQuery", &arguments[0], line)?;
+    let sql = sql("dbQuery", &arguments[1], line)?;
+    let params = params("dbQuery", &arguments[2], line)?;
+    let rows = query(connection, sql, params)
+        .map_err(|err| error(line, format!("dbQuery failed: {}", err)))?;
+    let rows = rows
+        .into_iter()
+        .map(|row| {
+            let entries = row
+                .into_iter()
+                .map(|(column, value)| (MapKey::String(column), value))
+                .collect();
+            Literal::Map(LoxMap::new(entries))
+        })
+        .collect();
+    Ok(Literal::List(Rc::new(RefCell::new(rows))))
+}
+
+fn database<'a>(native: &str, argument: &'a Literal, line: u32) -> Result<&'a Connection> {
+    match argument {
+        Literal::Handle(handle) => handle.downcast::<Connection>(),
+        _ => None,
+    }
+    .ok_or_else(|| {
+        error(
+            line,
+            format!("{} expects a database opened with dbOpen", native),
+        )
+    })
+}
+
+fn sql<'a>(native: &str, argument: &'a Literal, line: u32) -> Result<&'a str> {
+    match argument {
+        Literal::String(sql) => Ok(sql),
+        _ => Err(error(
+            line,
+            format!("{} expects the SQL to be a string", native),
+        )),
+    }
+}
+
+/// the values bound to the parameters of a statement
+fn params(native: &str, argument: &Literal, line: u32) -> Result<Vec<Value>> {
+    let list = match argument {
+        Literal::Nil => return Ok(Vec::new()),
+        Literal::List(list) => list.borrow(),
+        _ => {
+            return Err(error(
+                line,
+                format!("{} expects the params to be a list or nil", native),
+            ))
+        }
+    };
+    list.iter()
+        .map(|value| {
+            to_sql(value).ok_or_else(|| {
+                error(
+                    line,
+                    format!(
+                        "{} can only bind numbers, strings, bytes, booleans and nil, got {}",
+                        native, value
+                    ),
+                )
+            })
+        })
+        .collect()
+}
+
+/// the SQL value of a Lox value, where whole numbers are bound as integers
+fn to_sql(value: &Literal) -> Option<Value> {
+    match value {
+        Literal::Nil => Some(Value::Null),
+        Literal::Number(number) if number.fract() == 0.0 && number.abs() < i64::MAX as f64 => {
+            Some(Value::Integer(*number as i64))
+        }
+        Literal::Number(number) => Some(Value::Real(*number)),
+        Literal::Decimal(decimal) => to_sql(&Literal::Number(decimal.to_f64())),
+        Literal::String(string) => Some(Value::Text(string.clone())),
+        Literal::Boolean(boolean) => Some(Value::Integer(*boolean as i64)),
+        Literal::Bytes(bytes) => Some(Value::Blob(bytes.to_vec())),
+        _ => None,
+    }
+}
+
+/// the Lox value of a SQL value
+fn from_sql(value: Value) -> Literal {
+    match value {
+        Value::Null => Literal::Nil,
+        Value::Integer(integer) => Literal::Number(integer as f64),
+        Value::Real(real) => Literal::Number(real),
+        Value::Text(text) => Literal::String(text),
+        Value::Blob(blob) => Literal::Bytes(blob.into()),
+    }
+}
+
+/// the rows of a query, as the columns and values of each row
+fn query(
+    connection: &Connection,
+    sql: &str,
+    params: Vec<Value>,
+) -> rusqlite::Result<Vec<Vec<(String, Literal)>>> {
+    let mut statement = connection.prepare(sql)?;
+    let columns: Vec<String> = statement
+        .column_names()
+        .into_iter()
+        .map(String::from)
+        .collect();
+    let mut rows = statement.query(params_from_iter(params))?;
+    let mut values = Vec::new();
+    while let Some(row) = rows.next()? {
+        let mut fields = Vec::with_capacity(columns.len());
+        for (index, column) in columns.iter().enumerate() {
+            fields.push((column.clone(), from_sql(row.get(index)?)));
+        }
+        values.push(fields);
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    /// tests that statements run against a database and that queries return their rows
+    fn sqlite_test() {
+        let source = "var db = dbOpen(\":memory:\");
+            dbExec(db, \"create table users (name text, age integer)\", nil);
+            dbExec(db, \"insert into users values (?, ?), (?, ?)\", [\"ada\", 36, \"alan\", 41]);
+            dbExec(db, \"insert into users values (?, 7)\", [\"grace\"]);
+            var updated = dbExec(db, \"update users set age = age + 1 where age > ?\", [10]);
+            var rows = dbQuery(db, \"select name, age from users where age > ? order by age\", [10]);
+            var count = len(rows);
+            var names = rows[0][\"name\"] + \" \" + rows[1][\"name\"];
+            var age = rows[1][\"age\"];
+            var all = len(dbQuery(db, \"select * from users\", nil));
+            var blob = dbQuery(db, \"select ? as data\", [b\"\\x00\\x01\"])[0][\"data\"];
+            var row = dbQuery(db, \"select name, age from users where age < ?\", [10])[0];
+            dbExec(db, \"select 1\", 1);
+            dbExec(nil, \"select 1\", nil);";
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(std::io::sink()))
+            .with_plugin(&SqlitePlugin)
+            .unwrap();
+        let result = interpreter.eval(source).unwrap();
+        let global = |name: &str| result.globals[name].to_string();
+        assert_eq!(global("db"), "<database>");
+        assert_eq!(global("updated"), "2");
+        assert_eq!(global("count"), "2");
+        assert_eq!(global("names"), "ada alan");
+        assert_eq!(global("age"), "42");
+        assert_eq!(global("all"), "3");
+        assert_eq!(global("blob"), "b\"\\x00\\x01\"");
+        assert_eq!(global("row"), "{\"age\": 7, \"name\": \"grace\"}");
+        assert_eq!(
+            result.errors,
+            vec![
+                "[line 13] Error : dbExec expects the params to be a list or nil",
+                "[line 14] Error : dbExec expects a database opened with dbOpen",
+            ]
+        );
+    }
+
+    #[test]
+    /// tests that opening a database file needs the file I/O capability
+    fn capability_test() {
+        let mut interpreter = Interpreter::new()
+            .with_output(Box::new(std::io::sink()))
+            .with_plugin(&SqlitePlugin)
+            .unwrap();
+        let result = interpreter.eval("dbOpen(\"data.db\");").unwrap();
+        assert_eq!(
+            result.errors,
+            vec!["[line 1] Error : dbOpen needs the file I/O capability, which the interpreter was not granted"]
+        );
+    }
+}
@@ -0,0 +1,244 @@
+//!
+//! This module contains the stack traces of Lox programs, which list the function calls a program
+//! was in when it stopped, e.g. when `lox` interrupts a script on Ctrl-C through its
+//! [`crate::interpreter::CancellationToken`].
+//!
+//! A stack overflow leaves hundreds of calls of the same function on the stack, so traces are
+//! shown through [`TraceOptions`], which can collapse the calls a recursion repeats into a single
+//! `... N more` line, limit how many calls are listed and mark the calls of natives.
+
+use std::fmt;
+
+/// a function call on the stack
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    /// the function being called, written like it is printed
+    pub function: String,
+    /// the line the function was called in
+    pub line: u32,
+    /// whether the function is a native one rather than one declared in Lox
+    pub native: bool,
+}
+
+/// the calls a program was in, from the innermost to the outermost one
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StackTrace {
+    pub frames: Vec<Frame>,
+}
+
+/// How stack traces are shown. By default every call is listed, except that the calls repeated by
+/// a recursion are collapsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceOptions {
+    /// the most calls listed before the rest are left out, if limited
+    pub max_frames: Option<usize>,
+    /// whether a group of calls repeated back to back is listed once, followed by how many calls
+    /// the repetitions took
+    pub collapse_recursion: bool,
+    /// whether the calls of natives are marked as such
+    pub mark_natives: bool,
+    /// whether runtime errors raised inside of function calls are reported with the calls
+    pub on_errors: bool,
+}
+
+impl Default for TraceOptions {
+    fn default() -> Self {
+        Self {
+            max_frames: None,
+            collapse_recursion: true,
+            mark_natives: false,
+            on_errors: false,
+        }
+    }
+}
+
+/// a line of a stack trace, either a call or the calls left out in its place
+enum Entry<'a> {
+    Call(&'a Frame),
+    Omitted(usize),
+}
+
+impl StackTrace {
+    /// the trace shown with the options
+    pub fn display<'a>(&'a self, options: &'a TraceOptions) -> impl fmt::Display + 'a {
+        TraceDisplay {
+            trace: self,
+            options,
+        }
+    }
+
+    /// the calls of the trace with the repetitions collapsed, each with the number of calls it
+    /// stands for
+    fn entries(&self, collapse_recursion: bool) -> Vec<(Entry<'_>, usize)> {
+        let mut entries = Vec::new();
+        let mut start = 0;
+        while start < self.frames.len() {
+            let (period, repeats) = if collapse_recursion {
+                repetition(&self.frames[start..])
+            } else {
+                (1, 1)
+            };
+            for frame in &self.frames[start..start + period] {
+                entries.push((Entry::Call(frame), 1));
+            }
+            if repeats > 1 {
+                let omitted = (repeats - 1) * period;
+                entries.push((Entry::Omitted(omitted), omitted));
+            }
+            start += period * repeats;
+        }
+        entries
+    }
+}
+
+/// the longest group of calls a recursion is looked for in, which keeps showing a deep trace fast
+const MAX_GROUP: usize = 16;
+
+/// the length of the group of calls at the start of the frames which repeats back to back over
+/// the most calls, and how many times it does. Shorter groups win ties, so a recursion on a single
+/// function is a group of one call.
+fn repetition(frames: &[Frame]) -> (usize, usize) {
+    let mut best = (1, 1);
+    for period in 1..=(frames.len() / 2).min(MAX_GROUP) {
+        let repeats = 1 + frames[period..]
+            .chunks_exact(period)
+            .take_while(|chunk| *chunk == &frames[..period])
+            .count();
+        if repeats > 1 && period * repeats > best.0 * best.1 {
+            best = (period, repeats);
+        }
+    }
+    best
+}
+
+/// a number with commas between the groups of thousands, e.g. 9,962
+fn with_separators(number: usize) -> String {
+    let digits = number.to_string();
+    let mut out = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(digit);
+    }
+    out
+}
+
+/// a stack trace shown with some options, see [`StackTrace::display`]
+struct TraceDisplay<'a> {
+    trace: &'a StackTrace,
+    options: &'a TraceOptions,
+}
+
+/// one call per line, ending with the top level of the program which made the outermost call
+impl fmt::Display for TraceDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut listed = 0;
+        let mut remaining = self.trace.frames.len();
+        for (entry, calls) in self.trace.entries(self.options.collapse_recursion) {
+            match entry {
+                Entry::Call(_) if self.options.max_frames.is_some_and(|max| listed >= max) => {
+                    writeln!(f, "  ... {} more", with_separators(remaining))?;
+                    break;
+                }
+                Entry::Call(frame) => {
+                    let marker = if self.options.mark_natives && frame.native {
+                        " [native]"
+                    } else {
+                        ""
+                    };
+                    writeln!(
+                        f,
+                        "  at {}{}, called in line {}",
+                        frame.function, marker, frame.line
+                    )?;
+                    listed += 1;
+                }
+                Entry::Omitted(omitted) => {
+                    writeln!(f, "  ... {} more", with_separators(omitted))?;
+                }
+            }
+            remaining -= calls;
+        }
+        writeln!(f, "  at the top level")
+    }
+}
+
+/// every call, with the repetitions of a recursion collapsed
+impl fmt::Display for StackTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.display(&TraceOptions::default()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn frame(function: &str, line: u32) -> Frame {
+        Frame {
+            function: function.to_string(),
+            line,
+            native: false,
+        }
+    }
+
+    #[test]
+    /// tests that the calls a recursion repeats are collapsed, also when several functions take turns
+    fn collapse_test() {
+        let mut frames = vec![frame("<fn f>", 2); 9963];
+        frames.push(frame("<fn f>", 4));
+        let trace = StackTrace { frames };
+        assert_eq!(
+            trace.to_string(),
+            "  at <fn f>, called in line 2\n  ... 9,962 more\n  at <fn f>, called in line 4\n  \
+            at the top level\n"
+        );
+
+        let mut frames = Vec::new();
+        for _ in 0..3 {
+            frames.extend([frame("<fn even>", 3), frame("<fn odd>", 7)]);
+        }
+        frames.push(frame("<fn main>", 9));
+        let trace = StackTrace { frames };
+        assert_eq!(
+            trace.to_string(),
+            "  at <fn even>, called in line 3\n  at <fn odd>, called in line 7\n  ... 4 more\n  \
+            at <fn main>, called in line 9\n  at the top level\n"
+        );
+    }
+
+    #[test]
+    /// tests that a limited trace leaves out the outermost calls, and that natives can be marked
+    fn options_test() {
+        let mut native = frame("<native fn push>", 3);
+        native.native = true;
+        let trace = StackTrace {
+            frames: vec![
+                native,
+                frame("<fn a>", 5),
+                frame("<fn b>", 6),
+                frame("<fn a>", 7),
+            ],
+        };
+        let options = TraceOptions {
+            max_frames: Some(2),
+            mark_natives: true,
+            ..TraceOptions::default()
+        };
+        assert_eq!(
+            trace.display(&options).to_string(),
+            "  at <native fn push> [native], called in line 3\n  at <fn a>, called in line 5\n  \
+            ... 2 more\n  at the top level\n"
+        );
+
+        let options = TraceOptions {
+            collapse_recursion: false,
+            ..TraceOptions::default()
+        };
+        let trace = StackTrace {
+            frames: vec![frame("<fn f>", 2); 3],
+        };
+        assert_eq!(trace.display(&options).to_string().lines().count(), 4);
+    }
+}
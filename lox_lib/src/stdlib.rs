@@ -0,0 +1,94 @@
+//!
+//! This module contains the parts of the standard library written in Lox, which are embedded in the
+//! binary as the module `std` of a plugin. Its prelude imports the module into the namespace `std`,
+//! so scripts call its collection helpers and string utilities like `std.join(list, ", ")`.
+//!
+//! `lox` enables the plugin at startup unless it is run with `--no-std`, which leaves only the
+//! natives for strict core runs. Embedders enable it with
+//! [`crate::interpreter::Interpreter::with_plugin`] like any other plugin.
+
+use crate::plugin::{LoxPlugin, Registry};
+
+/// the source of the module, see `std.lox`
+const SOURCE: &str = include_str!("std.lox");
+
+/// The standard library written in Lox, enabled as `std`.
+pub struct StdlibPlugin;
+
+impl LoxPlugin for StdlibPlugin {
+    fn name(&self) -> &'static str {
+        "std"
+    }
+
+    fn register(&self, registry: &mut Registry) {
+        registry
+            .module("std", SOURCE)
+            .prelude("import \"std\" as std;");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ast::Literal;
+    use crate::interpreter::Interpreter;
+
+    /// runs the source with the standard library enabled, returning the value of the global result
+    fn eval(source: &str) -> Literal {
+        let mut interpreter = Interpreter::new().with_plugin(&StdlibPlugin).unwrap();
+        let result = interpreter.eval(source).unwrap();
+        assert_eq!(result.errors, Vec::<String>::new());
+        result.globals["result"].clone()
+    }
+
+    /// the string a value is printed as
+    fn printed(source: &str) -> String {
+        eval(source).to_string()
+    }
+
+    #[test]
+    /// tests the collection helpers
+    fn collections_test() {
+        let helpers = "fun double(x) { return x * 2; }\nfun small(x) { return x < 3; }\n\
+            fun add(a, b) { return a + b; }\n";
+        let run = |source: &str| printed(&format!("{}var result = {};", helpers, source));
+        assert_eq!(run("std.map([1, 2, 3], double)"), "[2, 4, 6]");
+        assert_eq!(run("std.filter(std.range(0, 5), small)"), "[0, 1, 2]");
+        assert_eq!(run("std.reduce([1, 2, 3], add, 10)"), "16");
+        assert_eq!(run("std.indexOf([\"a\", \"b\"], \"b\")"), "1");
+        assert_eq!(run("std.indexOf([], 1)"), "-1");
+        assert_eq!(run("std.contains([1, 2], 3)"), "false");
+        assert_eq!(run("std.slice([1, 2, 3, 4], 1, 9)"), "[2, 3, 4]");
+        assert_eq!(run("std.reverse([1, 2, 3])"), "[3, 2, 1]");
+    }
+
+    #[test]
+    /// tests the string utilities
+    fn strings_test() {
+        let run = |source: &str| printed(&format!("var result = {};", source));
+        assert_eq!(run("std.join([1, \"a\", true], \", \")"), "1, a, true");
+        assert_eq!(
+            run("std.split(\"a,b,,c\", \",\")"),
+            "[\"a\", \"b\", \"\", \"c\"]"
+        );
+        assert_eq!(run("std.split(\"a--b\", \"--\")"), "[\"a\", \"b\"]");
+        assert_eq!(run("std.substring(\"hello\", 1, 3)"), "el");
+        assert_eq!(run("std.startsWith(\"hello\", \"he\")"), "true");
+        assert_eq!(run("std.endsWith(\"lo\", \"hello\")"), "false");
+        assert_eq!(run("std.repeat(\"ab\", 3)"), "ababab");
+        assert_eq!(run("std.trim(\" \thi there  \")"), "hi there");
+    }
+
+    #[test]
+    /// tests that only the namespace is defined, and that the library is left out without the plugin
+    fn namespace_test() {
+        let mut interpreter = Interpreter::new().with_plugin(&StdlibPlugin).unwrap();
+        let result = interpreter.eval("var result = 1;").unwrap();
+        assert!(result.globals.contains_key("std"));
+        assert!(!result.globals.contains_key("map"));
+        assert_eq!(interpreter.plugins(), ["std"]);
+
+        let result = Interpreter::new().eval("print std;").unwrap();
+        assert_eq!(result.errors.len(), 1);
+    }
+}
@@ -0,0 +1,695 @@
+//!
+//! This module contains the test runner behind `lox test`, which lets Lox programs be tested in Lox.
+//! A script's top-level code runs first, and then every function it declares at the top level whose
+//! name starts with `test_` is called with no arguments, in the order they are declared. A test
+//! passes unless it raises a runtime error, which is what the `assert*` natives in
+//! [`crate::natives`] do when they fail.
+//!
+//! A [`TestRunner`] runs the tests of many scripts at once on a pool of threads. Every script gets an
+//! interpreter of its own, so globals set by one script are never seen by another, and what a script
+//! prints is kept with its results instead of being interleaved with the output of the others.
+//!
+//! The results of a run can also be written as JUnit XML or JSON with [`SuiteReport::render`], which
+//! CI systems read to show the outcome and duration of every test.
+
+use crate::ast::StmtNode;
+use crate::emit::json_string;
+use crate::interpreter::Interpreter;
+use anyhow::{anyhow, Context, Result};
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// the prefix of the names of the functions which are tests
+pub const TEST_PREFIX: &str = "test_";
+
+/// the stack of the threads scripts run on. Spawned threads get 2MB by default, which a debug build
+/// runs out of before a deep recursion reaches [`crate::interpreter::MAX_CALL_DEPTH`], so they get
+/// more than the 8MB the main thread gets on Linux.
+const WORKER_STACK_SIZE: usize = 16 << 20;
+
+/// the test functions declared at the top level of a program, with the lines they are declared in
+pub fn discover(statements: &[StmtNode]) -> Vec<(String, u32)> {
+    statements
+        .iter()
+        .filter_map(|statement| match statement {
+            StmtNode::FunctionStmt(declaration) if declaration.name.starts_with(TEST_PREFIX) => {
+                Some((declaration.name.clone(), declaration.line))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// the outcome of a single test function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestResult {
+    pub name: String,
+    /// the error the test raised, or None if it passed
+    pub failure: Option<String>,
+    pub duration: Duration,
+}
+
+/// the outcomes of the tests of a script, in the order they ran.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TestReport {
+    pub name: String,
+    pub results: Vec<TestResult>,
+}
+
+impl TestReport {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            results: Vec::new(),
+        }
+    }
+
+    pub fn passed(&self) -> usize {
+        self.results.len() - self.failed()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|result| result.failure.is_some())
+            .count()
+    }
+}
+
+/// prints a line for every test, with the error of a failed test indented below it, and then the
+/// counts
+impl fmt::Display for TestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for result in &self.results {
+            match &result.failure {
+                None => writeln!(f, "{} ... ok", result.name)?,
+                Some(failure) => {
+                    writeln!(f, "{} ... FAILED", result.name)?;
+                    for line in failure.lines() {
+                        writeln!(f, "    {}", line)?;
+                    }
+                }
+            }
+        }
+        writeln!(
+            f,
+            "{}: {} passed, {} failed",
+            self.name,
+            self.passed(),
+            self.failed()
+        )
+    }
+}
+
+/// the scripts to test out of the paths given to `lox test`. A file is tested as it is, and a
+/// directory is searched for `.lox` files, including the ones in directories inside of it.
+pub fn find_scripts(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut scripts = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let start = scripts.len();
+            find_in_dir(path, &mut scripts)?;
+            scripts[start..].sort();
+        } else {
+            scripts.push(path.clone());
+        }
+    }
+    Ok(scripts)
+}
+
+fn find_in_dir(dir: &Path, scripts: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read directory {}", dir.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_in_dir(&path, scripts)?;
+        } else if path.extension().is_some_and(|ext| ext == "lox") {
+            scripts.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// How the tests of a script went.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptOutcome {
+    Ran(TestReport),
+    /// the script could not be tested, such as when it does not parse
+    Error(String),
+    /// the script was not run since an earlier one failed in fail fast mode
+    Skipped,
+}
+
+/// the outcome of the tests of a script, with what the script printed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptResult {
+    pub script: String,
+    pub output: String,
+    pub outcome: ScriptOutcome,
+    /// how long the script took, including its top-level code
+    pub duration: Duration,
+}
+
+impl ScriptResult {
+    fn failed(&self) -> bool {
+        match &self.outcome {
+            ScriptOutcome::Ran(report) => report.failed() > 0,
+            ScriptOutcome::Error(_) => true,
+            ScriptOutcome::Skipped => false,
+        }
+    }
+}
+
+/// the results of every script a [`TestRunner`] ran, in the order the scripts were given.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SuiteReport {
+    pub scripts: Vec<ScriptResult>,
+}
+
+impl SuiteReport {
+    fn reports(&self) -> impl Iterator<Item = &TestReport> {
+        self.scripts
+            .iter()
+            .filter_map(|result| match &result.outcome {
+                ScriptOutcome::Ran(report) => Some(report),
+                _ => None,
+            })
+    }
+
+    pub fn passed(&self) -> usize {
+        self.reports().map(TestReport::passed).sum()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.reports().map(TestReport::failed).sum()
+    }
+
+    /// the number of scripts which could not be tested
+    pub fn errors(&self) -> usize {
+        self.count(|outcome| matches!(outcome, ScriptOutcome::Error(_)))
+    }
+
+    pub fn skipped(&self) -> usize {
+        self.count(|outcome| *outcome == ScriptOutcome::Skipped)
+    }
+
+    /// whether every test of every script passed
+    pub fn is_success(&self) -> bool {
+        !self.scripts.iter().any(ScriptResult::failed) && self.skipped() == 0
+    }
+
+    fn count(&self, predicate: impl Fn(&ScriptOutcome) -> bool) -> usize {
+        self.scripts
+            .iter()
+            .filter(|result| predicate(&result.outcome))
+            .count()
+    }
+}
+
+/// The formats a [`SuiteReport`] can be written in for CI systems.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuiteFormat {
+    Junit,
+    Json,
+}
+
+impl SuiteFormat {
+    /// the format of a report file, from its extension
+    pub fn from_path(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("xml") => Ok(SuiteFormat::Junit),
+            Some("json") => Ok(SuiteFormat::Json),
+            _ => Err(anyhow!(
+                "Unknown report format of {}, expected a .xml or .json file",
+                path.display()
+            )),
+        }
+    }
+}
+
+impl SuiteReport {
+    pub fn render(&self, format: SuiteFormat) -> String {
+        match format {
+            SuiteFormat::Junit => self.to_junit(),
+            SuiteFormat::Json => self.to_json(),
+        }
+    }
+
+    /// a `<testsuite>` for every script with a `<testcase>` for every test. A script which could
+    /// not be tested or was skipped has a single test case named after it.
+    pub fn to_junit(&self) -> String {
+        let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        out.push_str(&format!(
+            "<testsuites tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{}\">\n",
+            self.passed() + self.failed() + self.errors() + self.skipped(),
+            self.failed(),
+            self.errors(),
+            self.skipped(),
+            seconds(self.scripts.iter().map(|result| result.duration).sum())
+        ));
+        for result in &self.scripts {
+            let script = xml_escape(&result.script);
+            let (tests, failures, errors, skipped) = match &result.outcome {
+                ScriptOutcome::Ran(report) => (report.results.len(), report.failed(), 0, 0),
+                ScriptOutcome::Error(_) => (1, 0, 1, 0),
+                ScriptOutcome::Skipped => (1, 0, 0, 1),
+            };
+            out.push_str(&format!(
+                "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{}\">\n",
+                script,
+                tests,
+                failures,
+                errors,
+                skipped,
+                seconds(result.duration)
+            ));
+            match &result.outcome {
+                ScriptOutcome::Ran(report) => {
+                    for test in &report.results {
+                        let case = format!(
+                            "    <testcase name=\"{}\" classname=\"{}\" time=\"{}\"",
+                            xml_escape(&test.name),
+                            script,
+                            seconds(test.duration)
+                        );
+                        match &test.failure {
+                            None => out.push_str(&format!("{}/>\n", case)),
+                            Some(failure) => out.push_str(&format!(
+                                "{}>\n      <failure message=\"{}\"/>\n    </testcase>\n",
+                                case,
+                                xml_attribute(failure)
+                            )),
+                        }
+                    }
+                }
+                ScriptOutcome::Error(err) => out.push_str(&format!(
+                    "    <testcase name=\"{0}\" classname=\"{0}\" time=\"{1}\">\n      <error message=\"{2}\"/>\n    </testcase>\n",
+                    script,
+                    seconds(result.duration),
+                    xml_attribute(err)
+                )),
+                ScriptOutcome::Skipped => out.push_str(&format!(
+                    "    <testcase name=\"{0}\" classname=\"{0}\" time=\"0.000\">\n      <skipped/>\n    </testcase>\n",
+                    script
+                )),
+            }
+            if !result.output.is_empty() {
+                out.push_str(&format!(
+                    "    <system-out>{}</system-out>\n",
+                    xml_escape(&result.output)
+                ));
+            }
+            out.push_str("  </testsuite>\n");
+        }
+        out.push_str("</testsuites>\n");
+        out
+    }
+
+    /// the totals of the suite and the outcome of every script and test, with durations in seconds
+    pub fn to_json(&self) -> String {
+        let scripts = self
+            .scripts
+            .iter()
+            .map(|result| {
+                let (outcome, detail) = match &result.outcome {
+                    ScriptOutcome::Ran(report) => {
+                        let tests = report
+                            .results
+                            .iter()
+                            .map(|test| {
+                                format!(
+                                    "{{\"name\":{},\"passed\":{},\"duration\":{},\"failure\":{}}}",
+                                    json_string(&test.name),
+                                    test.failure.is_none(),
+                                    seconds(test.duration),
+                                    test.failure
+                                        .as_deref()
+                                        .map_or("null".to_string(), json_string)
+                                )
+                            })
+                            .collect::<Vec<_>>();
+                        ("ran", format!(",\"tests\":[{}]", tests.join(",")))
+                    }
+                    ScriptOutcome::Error(err) => {
+                        ("error", format!(",\"error\":{}", json_string(err)))
+                    }
+                    ScriptOutcome::Skipped => ("skipped", String::new()),
+                };
+                format!(
+                    "{{\"script\":{},\"outcome\":\"{}\",\"duration\":{},\"output\":{}{}}}",
+                    json_string(&result.script),
+                    outcome,
+                    seconds(result.duration),
+                    json_string(&result.output),
+                    detail
+                )
+            })
+            .collect::<Vec<_>>();
+        format!(
+            "{{\"passed\":{},\"failed\":{},\"errors\":{},\"skipped\":{},\"scripts\":[{}]}}\n",
+            self.passed(),
+            self.failed(),
+            self.errors(),
+            self.skipped(),
+            scripts.join(",")
+        )
+    }
+}
+
+/// a duration in seconds to the millisecond, the precision JUnit reports use
+fn seconds(duration: Duration) -> String {
+    format!("{:.3}", duration.as_secs_f64())
+}
+
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// escapes text for an attribute, where line breaks would otherwise become spaces
+fn xml_attribute(text: &str) -> String {
+    xml_escape(text).replace('\n', "&#10;")
+}
+
+/// prints the output and the report of every script, and then the totals of the suite
+impl fmt::Display for SuiteReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for result in &self.scripts {
+            write!(f, "{}", result.output)?;
+            match &result.outcome {
+                ScriptOutcome::Ran(report) => write!(f, "{}", report)?,
+                ScriptOutcome::Error(err) => {
+                    writeln!(f, "{}: ERROR", result.script)?;
+                    for line in err.lines() {
+                        writeln!(f, "    {}", line)?;
+                    }
+                }
+                ScriptOutcome::Skipped => writeln!(f, "{}: skipped", result.script)?,
+            }
+        }
+        writeln!(
+            f,
+            "{} scripts: {} passed, {} failed, {} errors, {} skipped",
+            self.scripts.len(),
+            self.passed(),
+            self.failed(),
+            self.errors(),
+            self.skipped()
+        )
+    }
+}
+
+/// collects what an interpreter prints, so that it can be handed to another thread
+#[derive(Debug, Clone, Default)]
+struct CapturedOutput(Arc<Mutex<Vec<u8>>>);
+
+impl CapturedOutput {
+    fn take(&self) -> String {
+        let mut bytes = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        String::from_utf8_lossy(&std::mem::take(&mut *bytes)).into_owned()
+    }
+}
+
+impl Write for CapturedOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Runs the tests of many scripts on a pool of threads. Interpreters can not be moved between
+/// threads, so every script gets a new one from the function the runner is made with, called on the
+/// thread the script runs on.
+pub struct TestRunner<F> {
+    interpreter: F,
+    /// how many scripts run at the same time
+    jobs: usize,
+    /// whether to stop starting scripts once one of them has failed
+    fail_fast: bool,
+}
+
+impl<F: Fn() -> Result<Interpreter> + Sync> TestRunner<F> {
+    /// a runner with a job for every core of the machine
+    pub fn new(interpreter: F) -> Self {
+        Self {
+            interpreter,
+            jobs: thread::available_parallelism().map_or(1, |jobs| jobs.get()),
+            fail_fast: false,
+        }
+    }
+
+    pub fn with_jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs.max(1);
+        self
+    }
+
+    pub fn with_fail_fast(mut self, fail_fast: bool) -> Self {
+        self.fail_fast = fail_fast;
+        self
+    }
+
+    /// runs the tests of the scripts. The scripts left once one has failed in fail fast mode are
+    /// skipped, while the ones already running are finished.
+    pub fn run(&self, scripts: &[String]) -> SuiteReport {
+        let next = AtomicUsize::new(0);
+        let stop = AtomicBool::new(false);
+        let mut results: Vec<(usize, ScriptResult)> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..self.jobs.min(scripts.len()))
+                .map(|_| {
+                    let worker = thread::Builder::new().stack_size(WORKER_STACK_SIZE);
+                    let worker = worker.spawn_scoped(scope, || {
+                        let mut results = Vec::new();
+                        loop {
+                            let index = next.fetch_add(1, Ordering::SeqCst);
+                            let Some(script) = scripts.get(index) else {
+                                break;
+                            };
+                            let result = if stop.load(Ordering::SeqCst) {
+                                ScriptResult {
+                                    script: script.clone(),
+                                    output: String::new(),
+                                    outcome: ScriptOutcome::Skipped,
+                                    duration: Duration::ZERO,
+                                }
+                            } else {
+                                self.run_script(script)
+                            };
+                            if self.fail_fast && result.failed() {
+                                stop.store(true, Ordering::SeqCst);
+                            }
+                            results.push((index, result));
+                        }
+                        results
+                    });
+                    worker.expect("failed to spawn a thread to run scripts on")
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| match worker.join() {
+                    Ok(results) => results,
+                    Err(panic) => std::panic::resume_unwind(panic),
+                })
+                .collect()
+        });
+        results.sort_by_key(|(index, _)| *index);
+        SuiteReport {
+            scripts: results.into_iter().map(|(_, result)| result).collect(),
+        }
+    }
+
+    fn run_script(&self, script: &str) -> ScriptResult {
+        let output = CapturedOutput::default();
+        let start = Instant::now();
+        let outcome = match (self.interpreter)() {
+            Ok(interpreter) => {
+                // the interpreter is dropped before taking the output, flushing the last line
+                let mut interpreter = interpreter.with_output(Box::new(output.clone()));
+                match interpreter.test_script(script.to_string()) {
+                    Ok(report) => ScriptOutcome::Ran(report),
+                    Err(err) => ScriptOutcome::Error(err.to_string()),
+                }
+            }
+            Err(err) => ScriptOutcome::Error(err.to_string()),
+        };
+        ScriptResult {
+            script: script.to_string(),
+            output: output.take(),
+            outcome,
+            duration: start.elapsed(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::loader::InMemoryLoader;
+
+    fn runner(jobs: usize) -> TestRunner<impl Fn() -> Result<Interpreter> + Sync> {
+        TestRunner::new(|| {
+            let loader = InMemoryLoader::new()
+                .with_module(
+                    "a.lox",
+                    "var shared = 1;\nfun test_a() { assertEquals(1, shared); }\nprint \"a\";",
+                )
+                .with_module("b.lox", "fun test_b() { print shared; }")
+                .with_module(
+                    "c.lox",
+                    "fun test_c() { assertTrue(false); }\nfun test_d() {}",
+                )
+                .with_module("d.lox", "fun test_e() {");
+            Ok(Interpreter::new().with_module_loader(Box::new(loader)))
+        })
+        .with_jobs(jobs)
+    }
+
+    #[test]
+    /// tests that every script runs in an interpreter of its own, and that the results are in the
+    /// order the scripts were given whatever order they finished in
+    fn runner_test() {
+        let scripts: Vec<String> = ["a.lox", "b.lox", "c.lox", "d.lox"]
+            .iter()
+            .map(|script| script.to_string())
+            .collect();
+        let report = runner(3).run(&scripts);
+        assert_eq!(report.to_string(), runner(1).run(&scripts).to_string());
+        assert_eq!(
+            (report.passed(), report.failed(), report.errors()),
+            (2, 2, 1)
+        );
+        assert!(!report.is_success());
+        assert_eq!(report.scripts[0].output, "a\n");
+        let ScriptOutcome::Ran(b) = &report.scripts[1].outcome else {
+            panic!("b.lox did not run");
+        };
+        assert_eq!(b.failed(), 1);
+        assert!(report
+            .to_string()
+            .ends_with("d.lox: ERROR\n    [S0081] Expected '}' to close the block starting in line 1\n4 scripts: 2 passed, 2 failed, 1 errors, 0 skipped\n"));
+
+        // with a single job, the scripts after the first failure are skipped
+        let report = runner(1).with_fail_fast(true).run(&scripts);
+        let skipped: Vec<_> = report
+            .scripts
+            .iter()
+            .map(|result| result.outcome == ScriptOutcome::Skipped)
+            .collect();
+        assert_eq!(skipped, [false, false, true, true]);
+    }
+
+    #[test]
+    /// tests that a recursion in a test reaches the limit of calls rather than the end of the stack
+    /// of the thread it runs on
+    fn deep_recursion_test() {
+        let runner = TestRunner::new(|| {
+            let loader = InMemoryLoader::new().with_module(
+                "deep.lox",
+                "fun g(n) { if (n == 0) return 0; return 1 + g(n - 1); }\n\
+                fun test_overflow() { g(100000); }",
+            );
+            Ok(Interpreter::new().with_module_loader(Box::new(loader)))
+        });
+        let report = runner.run(&["deep.lox".to_string()]);
+        assert_eq!(report.failed(), 1);
+        assert!(report.to_string().contains("Stack overflow"));
+    }
+
+    #[test]
+    /// tests the JUnit XML and JSON reports, including the scripts which did not run
+    fn suite_format_test() {
+        let millis = Duration::from_millis;
+        let report = SuiteReport {
+            scripts: vec![
+                ScriptResult {
+                    script: "a.lox".to_string(),
+                    output: "<hi>\n".to_string(),
+                    outcome: ScriptOutcome::Ran(TestReport {
+                        name: "a.lox".to_string(),
+                        results: vec![
+                            TestResult {
+                                name: "test_a".to_string(),
+                                failure: None,
+                                duration: millis(2),
+                            },
+                            TestResult {
+                                name: "test_b".to_string(),
+                                failure: Some("[line 1] Error : \"x\"\nfailed".to_string()),
+                                duration: millis(1),
+                            },
+                        ],
+                    }),
+                    duration: millis(5),
+                },
+                ScriptResult {
+                    script: "b.lox".to_string(),
+                    output: String::new(),
+                    outcome: ScriptOutcome::Error("Expected ';'".to_string()),
+                    duration: millis(1),
+                },
+                ScriptResult {
+                    script: "c.lox".to_string(),
+                    output: String::new(),
+                    outcome: ScriptOutcome::Skipped,
+                    duration: Duration::ZERO,
+                },
+            ],
+        };
+        assert_eq!(
+            report.render(SuiteFormat::from_path(Path::new("out/junit.xml")).unwrap()),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+            <testsuites tests=\"4\" failures=\"1\" errors=\"1\" skipped=\"1\" time=\"0.006\">\n\
+            \x20 <testsuite name=\"a.lox\" tests=\"2\" failures=\"1\" errors=\"0\" skipped=\"0\" time=\"0.005\">\n\
+            \x20   <testcase name=\"test_a\" classname=\"a.lox\" time=\"0.002\"/>\n\
+            \x20   <testcase name=\"test_b\" classname=\"a.lox\" time=\"0.001\">\n\
+            \x20     <failure message=\"[line 1] Error : &quot;x&quot;&#10;failed\"/>\n\
+            \x20   </testcase>\n\
+            \x20   <system-out>&lt;hi&gt;\n</system-out>\n\
+            \x20 </testsuite>\n\
+            \x20 <testsuite name=\"b.lox\" tests=\"1\" failures=\"0\" errors=\"1\" skipped=\"0\" time=\"0.001\">\n\
+            \x20   <testcase name=\"b.lox\" classname=\"b.lox\" time=\"0.001\">\n\
+            \x20     <error message=\"Expected &apos;;&apos;\"/>\n\
+            \x20   </testcase>\n\
+            \x20 </testsuite>\n\
+            \x20 <testsuite name=\"c.lox\" tests=\"1\" failures=\"0\" errors=\"0\" skipped=\"1\" time=\"0.000\">\n\
+            \x20   <testcase name=\"c.lox\" classname=\"c.lox\" time=\"0.000\">\n\
+            \x20     <skipped/>\n\
+            \x20   </testcase>\n\
+            \x20 </testsuite>\n\
+            </testsuites>\n"
+        );
+        assert_eq!(
+            report.render(SuiteFormat::Json),
+            "{\"passed\":1,\"failed\":1,\"errors\":1,\"skipped\":1,\"scripts\":[\
+            {\"script\":\"a.lox\",\"outcome\":\"ran\",\"duration\":0.005,\"output\":\"<hi>\\n\",\"tests\":[\
+            {\"name\":\"test_a\",\"passed\":true,\"duration\":0.002,\"failure\":null},\
+            {\"name\":\"test_b\",\"passed\":false,\"duration\":0.001,\"failure\":\"[line 1] Error : \\\"x\\\"\\nfailed\"}]},\
+            {\"script\":\"b.lox\",\"outcome\":\"error\",\"duration\":0.001,\"output\":\"\",\"error\":\"Expected ';'\"},\
+            {\"script\":\"c.lox\",\"outcome\":\"skipped\",\"duration\":0.000,\"output\":\"\"}]}\n"
+        );
+        assert!(SuiteFormat::from_path(Path::new("report.txt")).is_err());
+    }
+}
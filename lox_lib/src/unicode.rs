@@ -0,0 +1,80 @@
+//!
+//! This module contains what a character of a Lox string is, which is what `len(s)`, `s[i]`,
+//! `s[a:b]` and `chars(s)` count and take. A string is a sequence of Unicode scalar values rather
+//! than of bytes, so `len("héllo")` is 5 and `"🦀!"[0]` is the crab, whatever the length of their
+//! UTF-8 encoding.
+//!
+//! Some characters people see are several scalar values, like an `e` followed by a combining accent
+//! or a family emoji joined from several people. When lox is built with the `graphemes` feature the
+//! characters are grapheme clusters instead, so those count as one character and are never split by
+//! indexing or slicing.
+//!
+//! Strings are compared scalar value by scalar value and concatenating strings joins their
+//! characters, so `len(a + b)` is `len(a) + len(b)` except when `b` starts with a combining character
+//! that joins the last character of `a` into one grapheme cluster.
+
+/// the characters of a string
+#[cfg(not(feature = "graphemes"))]
+pub fn characters(string: &str) -> Vec<&str> {
+    string
+        .char_indices()
+        .map(|(start, c)| &string[start..start + c.len_utf8()])
+        .collect()
+}
+
+/// the characters of a string
+#[cfg(feature = "graphemes")]
+pub fn characters(string: &str) -> Vec<&str> {
+    use unicode_segmentation::UnicodeSegmentation;
+    string.graphemes(true).collect()
+}
+
+/// the number of characters of a string
+pub fn length(string: &str) -> usize {
+    if cfg!(feature = "graphemes") {
+        characters(string).len()
+    } else {
+        string.chars().count()
+    }
+}
+
+/// the characters of a string from start up to but not including end, which must be in bounds
+pub fn slice(string: &str, start: usize, end: usize) -> String {
+    characters(string)[start..end].concat()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    /// tests that strings are counted and sliced by character rather than by byte
+    fn characters_test() {
+        assert_eq!(length("héllo"), 5);
+        assert_eq!(characters("🦀!"), ["🦀", "!"]);
+        assert_eq!(slice("a🦀b", 1, 3), "🦀b");
+        assert_eq!(slice("abc", 1, 1), "");
+        assert_eq!(length(""), 0);
+    }
+
+    #[test]
+    #[cfg(not(feature = "graphemes"))]
+    /// tests that an accent written as a combining character is a character of its own
+    fn scalar_values_test() {
+        // e followed by a combining acute accent
+        assert_eq!(length("e\u{301}"), 2);
+        assert_eq!(characters("e\u{301}"), ["e", "\u{301}"]);
+        // a family emoji is three people joined by two zero width joiners
+        assert_eq!(length("👨\u{200d}👩\u{200d}👧"), 5);
+    }
+
+    #[test]
+    #[cfg(feature = "graphemes")]
+    /// tests that the scalar values of a grapheme cluster are one character
+    fn graphemes_test() {
+        assert_eq!(length("e\u{301}"), 1);
+        assert_eq!(characters("ae\u{301}b"), ["a", "e\u{301}", "b"]);
+        assert_eq!(length("👨\u{200d}👩\u{200d}👧"), 1);
+        assert_eq!(slice("e\u{301}🦀", 1, 2), "🦀");
+    }
+}
@@ -1,20 +1,580 @@
 use anyhow::{anyhow, Result};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use lox_lib::interpreter::{Interpreter, InterpreterMode};
+use lox_lib::analyze::GraphFormat;
+use lox_lib::cache::ParseCache;
+use lox_lib::config::ProjectConfig;
+use lox_lib::crash::CrashReporter;
+use lox_lib::dry_run::DryRun;
+use lox_lib::emit::{parse_emit_flag, Emit, EmitFormat};
+use lox_lib::exercise::{self, ExerciseConfig};
+use lox_lib::grade::{Grader, ReportFormat};
+use lox_lib::interpreter::{CancellationToken, Interpreter, InterpreterMode};
+use lox_lib::lexer::Position;
+use lox_lib::parser::parse_define;
+use lox_lib::plugin::LoxPlugin;
+use lox_lib::refactor;
+use lox_lib::server;
+use lox_lib::stack::TraceOptions;
+use lox_lib::stdlib::StdlibPlugin;
+use lox_lib::testing::{self, SuiteFormat, TestRunner};
+
+/// the plugins this build of lox can enable from the `plugins` of a project config
+const PLUGINS: &[&dyn LoxPlugin] = &[
+    #[cfg(feature = "net")]
+    &lox_lib::http::HttpPlugin,
+    #[cfg(feature = "sqlite")]
+    &lox_lib::sqlite::SqlitePlugin,
+];
+
+/// writes what scripts log with `log(level, message)` to stderr
+struct StderrLogger;
+
+impl log::Log for StderrLogger {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        eprintln!("[{}] {}", record.level(), record.args());
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: StderrLogger = StderrLogger;
+
+/// lets Ctrl-C stop the script between two statements, so that lox can tell where it was. A second
+/// Ctrl-C before it stops exits right away, in case the script is stuck in a native.
+#[cfg(unix)]
+fn handle_interrupts(token: CancellationToken) {
+    use std::sync::OnceLock;
+
+    /// the token of the script being run, which Ctrl-C cancels
+    static INTERRUPT: OnceLock<CancellationToken> = OnceLock::new();
+
+    extern "C" fn interrupt(_signal: libc::c_int) {
+        if let Some(token) = INTERRUPT.get() {
+            if token.is_cancelled() {
+                // only async-signal-safe functions can be called here, which exit is not
+                unsafe { libc::_exit(130) };
+            }
+            token.cancel();
+        }
+    }
+    if INTERRUPT.set(token).is_ok() {
+        let handler: extern "C" fn(libc::c_int) = interrupt;
+        unsafe { libc::signal(libc::SIGINT, handler as libc::sighandler_t) };
+    }
+}
+
+/// Ctrl-C keeps killing lox where there are no signals
+#[cfg(not(unix))]
+fn handle_interrupts(_token: CancellationToken) {}
 
 fn main() -> Result<()> {
-    let matches = std::env::args().collect::<Vec<String>>();
+    // scripts log at info and above, unless LOX_LOG=off|error|warn|info|debug says otherwise
+    let level = match std::env::var("LOX_LOG") {
+        Ok(level) => level.parse().map_err(|_| {
+            anyhow!(
+                "LOX_LOG expects off, error, warn, info or debug, got {}",
+                level
+            )
+        })?,
+        Err(_) => log::LevelFilter::Info,
+    };
+    log::set_logger(&LOGGER).map_err(|err| anyhow!("{}", err))?;
+    log::set_max_level(level);
+
+    let args: Vec<String> = std::env::args().collect();
+    let (mut interpreter, flags) = configure(&args)?;
+    let Flags {
+        matches,
+        project,
+        emit,
+        minimize_crashes,
+        stats,
+        expected,
+        exercise,
+        timeout,
+        report_format,
+        dry_run,
+        mut seed,
+        parse_cache,
+        jobs,
+        fail_fast,
+        test_report,
+    } = flags;
+
+    // lox repl --listen 127.0.0.1:7070
+    if let [_, repl, listen, addr] = matches.as_slice() {
+        if repl == "repl" && listen == "--listen" {
+            return server::listen(addr);
+        }
+    }
+
+    // lox minify script
+    if let [_, minify, path] = matches.as_slice() {
+        if minify == "minify" {
+            println!("{}", interpreter.minify_script(path.clone())?);
+            return Ok(());
+        }
+    }
+
+    // lox analyze --strings script... | lox analyze --call-graph[=dot|json] script
+    if let [_, analyze, analysis, paths @ ..] = matches.as_slice() {
+        if analyze == "analyze" {
+            if let Some(format) = analysis.strip_prefix("--call-graph") {
+                let format = match format.strip_prefix('=') {
+                    Some(format) => format.parse::<GraphFormat>()?,
+                    None if format.is_empty() => GraphFormat::Dot,
+                    None => return Err(anyhow!("unknown analysis {}", analysis)),
+                };
+                let [path] = paths else {
+                    return Err(anyhow!(
+                        "analyze expects a script: Usage lox analyze --call-graph[=dot|json] <script>"
+                    ));
+                };
+                print!("{}", interpreter.call_graph(path.clone())?.render(format));
+                return Ok(());
+            }
+            if analysis != "--strings" || paths.is_empty() {
+                return Err(anyhow!(
+                    "analyze expects scripts: Usage lox analyze --strings <script>... | lox analyze --call-graph[=dot|json] <script>"
+                ));
+            }
+            for path in paths {
+                print!("{}", interpreter.string_report(path.clone())?);
+            }
+            return Ok(());
+        }
+    }
+
+    // lox audit script
+    if let [_, audit, path] = matches.as_slice() {
+        if audit == "audit" {
+            print!("{}", interpreter.audit_script(path.clone())?);
+            return Ok(());
+        }
+    }
+
+    // lox fix script, which only prints the changes with --dry-run
+    if let [_, fix, path] = matches.as_slice() {
+        if fix == "fix" {
+            let report = interpreter.fix_script(path.clone())?;
+            if dry_run.is_some() {
+                print!("{}", report.diff());
+            } else if report.fixed != report.source {
+                std::fs::write(path, &report.fixed)?;
+            }
+            print!("{}", report);
+            return Ok(());
+        }
+    }
 
-    let mode = match matches.len() {
-        1 => Ok(InterpreterMode::Repl),
-        2 => Ok(InterpreterMode::Script(matches.into_iter().nth(1).unwrap())),
-        _ => Err(anyhow!("too many arguments: Usage lox [script]")),
+    // lox rename [--dry-run | --lsp] script line:column name, which only prints the changes with
+    // --dry-run and prints them as the edits of a language server with --lsp
+    if let [_, rename, args @ ..] = matches.as_slice() {
+        if rename == "rename" {
+            let (lsp, args) = match args {
+                [lsp, args @ ..] if lsp == "--lsp" => (true, args),
+                args => (false, args),
+            };
+            let [path, position, name] = args else {
+                return Err(anyhow!("rename expects a script, a position and a name: Usage lox rename [--dry-run | --lsp] <script> <line>:<column> <name>"));
+            };
+            let position = parse_position(position)?;
+            let report = interpreter.rename_in_script(path.clone(), position, name)?;
+            if lsp {
+                println!("{}", refactor::encode(&report.source, &report.edits));
+                return Ok(());
+            }
+            if dry_run.is_some() {
+                print!("{}", report.diff());
+            } else {
+                std::fs::write(path, &report.renamed)?;
+            }
+            print!("{}", report);
+            return Ok(());
+        }
+    }
+
+    // lox test [--jobs N] [--fail-fast] [--report results.xml] (script | dir)...
+    if let [_, test, paths @ ..] = matches.as_slice() {
+        if test == "test" {
+            if paths.is_empty() {
+                return Err(anyhow!("test expects scripts: Usage lox test [--jobs N] [--fail-fast] [--report <file>] <script or dir>..."));
+            }
+            let paths: Vec<PathBuf> = paths.iter().map(PathBuf::from).collect();
+            let scripts: Vec<String> = testing::find_scripts(&paths)?
+                .iter()
+                .map(|script| script.to_string_lossy().into_owned())
+                .collect();
+            let mut runner = TestRunner::new(|| Ok(configure(&args)?.0)).with_fail_fast(fail_fast);
+            if let Some(jobs) = jobs {
+                runner = runner.with_jobs(jobs);
+            }
+            // the format is checked before the tests run, so a typo does not waste a whole run
+            let test_report = test_report
+                .map(|path| SuiteFormat::from_path(&path).map(|format| (path, format)))
+                .transpose()?;
+            let report = runner.run(&scripts);
+            print!("{}", report);
+            if let Some((path, format)) = test_report {
+                std::fs::write(&path, report.render(format))?;
+            }
+            if !report.is_success() {
+                return Err(anyhow!(
+                    "{} of {} tests failed, and {} of {} scripts could not be tested",
+                    report.failed(),
+                    report.passed() + report.failed(),
+                    report.errors() + report.skipped(),
+                    report.scripts.len()
+                ));
+            }
+            return Ok(());
+        }
+    }
+
+    // lox bench script
+    if let [_, bench, path] = matches.as_slice() {
+        if bench == "bench" {
+            interpreter.run(InterpreterMode::Script(path.clone()))?;
+            print!("{}", interpreter.benchmarks());
+            return Ok(());
+        }
+    }
+
+    // lox new-exercise name
+    if let [_, new_exercise, name] = matches.as_slice() {
+        if new_exercise == "new-exercise" {
+            let dir = exercise::scaffold(Path::new("."), name)?;
+            println!("Created exercise {}", dir.display());
+            return Ok(());
+        }
+    }
+
+    // lox grade submissions/ (--expected expected/ | --exercise exercise/) [--timeout SECS]
+    //     [--seed N] [--report=json|csv]
+    if let [_, grade, submissions] = matches.as_slice() {
+        if grade == "grade" {
+            let mut grader = Grader::new(std::env::current_exe()?);
+            let expected = match (expected, exercise) {
+                (Some(expected), _) => PathBuf::from(expected),
+                (None, Some(exercise)) => {
+                    let config = ExerciseConfig::load(Path::new(&exercise))?;
+                    grader = grader.with_timeout(config.timeout);
+                    let hidden_test = Path::new(&exercise).join(exercise::HIDDEN_TEST);
+                    if hidden_test.is_file() {
+                        grader = grader.with_hidden_test(hidden_test);
+                    }
+                    seed = seed.or(config.seed);
+                    Path::new(&exercise).join(config.expected)
+                }
+                (None, None) => return Err(anyhow!("grade expects the expected outputs: Usage lox grade <dir> (--expected <dir> | --exercise <dir>) [--timeout SECS] [--seed N] [--report=json|csv]")),
+            };
+            if let Some(timeout) = timeout {
+                grader = grader.with_timeout(timeout);
+            }
+            if let Some(seed) = seed {
+                grader = grader.with_args(vec!["--seed".to_string(), seed.to_string()]);
+            }
+            let report = grader.grade(Path::new(submissions), &expected)?;
+            print!("{}", report.render(report_format));
+            return Ok(());
+        }
+    }
+
+    if let Some((emit, format)) = emit {
+        let [_, path] = matches.as_slice() else {
+            return Err(anyhow!(
+                "--emit expects a script: Usage lox --emit=<ir>[:<format>] script"
+            ));
+        };
+        print!("{}", interpreter.emit_script(path.clone(), emit, format)?);
+        return Ok(());
+    }
+
+    if let Some(dry_run) = dry_run.clone() {
+        interpreter = interpreter.with_dry_run(dry_run);
+    }
+    interpreter = interpreter.with_crash_reporter(
+        CrashReporter::new(std::env::temp_dir()).with_minimization(minimize_crashes),
+    );
+
+    // without a script, lox runs the entry point of the project if it has one
+    let entry = project.as_ref().and_then(ProjectConfig::entry);
+    let mode = match matches.as_slice() {
+        [_] => Ok(match entry {
+            Some(entry) => InterpreterMode::Script(entry.to_string_lossy().into_owned()),
+            None => InterpreterMode::Repl,
+        }),
+        [_, repl] if repl == "repl" => Ok(InterpreterMode::Repl),
+        [_, path] => Ok(InterpreterMode::Script(path.clone())),
+        _ => Err(anyhow!(
+            "too many arguments: Usage lox [--define NAME=VALUE]... [--emit=<ir>[:<format>]] [--automatic-semicolons] [--pedantic] [--minimize-crashes] [--stats] [--no-cache] [--no-std] [--dry-run] [--stub NAME=VALUE]... [--seed N] [--numbers=binary|decimal] [--max-heap BYTES] [--stack-traces] [--trace-limit N] [--trace-natives] [--no-trace-collapse] [script] | lox repl [--listen <addr>] | lox grade <dir> (--expected <dir> | --exercise <dir>) [--timeout SECS] [--seed N] [--report=json|csv] | lox new-exercise <name> | lox minify <script> | lox analyze --strings <script>... | lox analyze --call-graph[=dot|json] <script> | lox audit <script> | lox fix [--dry-run] <script> | lox rename [--dry-run | --lsp] <script> <line>:<column> <name> | lox test [--jobs N] [--fail-fast] [--report <file>] <script or dir>... | lox bench <script>"
+        )),
     }?;
 
-    let mut interpreter = Interpreter::new();
-    interpreter.run(mode)?;
+    if parse_cache && matches!(mode, InterpreterMode::Script(_)) {
+        if let Some(cache) = ParseCache::in_user_dir() {
+            interpreter = interpreter.with_parse_cache(cache);
+        }
+    }
+
+    if matches!(mode, InterpreterMode::Script(_)) {
+        handle_interrupts(interpreter.cancellation_token());
+    }
+    let result = interpreter.run(mode);
+    if let Some(trace) = interpreter.interrupted_at() {
+        eprint!(
+            "Interrupted\n{}",
+            trace.display(interpreter.trace_options())
+        );
+        std::process::exit(130);
+    }
+    result?;
+    if dry_run.is_some() {
+        eprint!("{}", interpreter.effects());
+    }
+    if stats {
+        if let Some(trace) = interpreter.last_trace() {
+            eprint!("{}", trace);
+        }
+        if let Some(cache) = interpreter.parse_cache() {
+            eprint!("{}", cache.stats());
+        }
+    }
     Ok(())
 }
 
+/// the flags of the command line besides the ones configuring the interpreter
+struct Flags {
+    /// the arguments which are not flags, starting with the path of lox itself
+    matches: Vec<String>,
+    project: Option<ProjectConfig>,
+    emit: Option<(Emit, EmitFormat)>,
+    minimize_crashes: bool,
+    stats: bool,
+    expected: Option<String>,
+    exercise: Option<String>,
+    timeout: Option<Duration>,
+    report_format: ReportFormat,
+    dry_run: Option<DryRun>,
+    seed: Option<u64>,
+    parse_cache: bool,
+    jobs: Option<usize>,
+    fail_fast: bool,
+    /// where lox test writes a JUnit XML or JSON report of its results
+    test_report: Option<PathBuf>,
+}
+
+/// an interpreter configured by the project and the command line, which lox test calls again for
+/// every script it runs since interpreters can not be moved between threads
+fn configure(args: &[String]) -> Result<(Interpreter, Flags)> {
+    // a lox.toml in the directory or one of its parents configures the project, and the flags below
+    // override it
+    let project = ProjectConfig::discover(&std::env::current_dir()?)?;
+    let mut interpreter = Interpreter::new();
+    if let Some(project) = &project {
+        interpreter = project.apply(interpreter, PLUGINS)?;
+    }
+    let mut flags = Flags {
+        matches: Vec::new(),
+        project,
+        emit: None,
+        minimize_crashes: false,
+        stats: false,
+        expected: None,
+        exercise: None,
+        timeout: None,
+        report_format: ReportFormat::Json,
+        dry_run: None,
+        seed: None,
+        parse_cache: true,
+        jobs: None,
+        fail_fast: false,
+        test_report: None,
+    };
+
+    // the standard library written in Lox is loaded into std unless --no-std is given
+    let mut stdlib = true;
+
+    // --define NAME=VALUE can be given any number of times before or after the script
+    let mut args = args.iter().cloned();
+    while let Some(arg) = args.next() {
+        if arg == "--define" {
+            let definition = args
+                .next()
+                .ok_or_else(|| anyhow!("--define expects a NAME=VALUE argument"))?;
+            let (name, value) = parse_define(&definition)?;
+            interpreter = interpreter.with_define(name, value);
+        } else if let Some(value) = arg.strip_prefix("--emit=") {
+            // --emit=tokens|ast|semantic-tokens|bytecode[:text|json]
+            flags.emit = Some(parse_emit_flag(value)?);
+        } else if arg == "--automatic-semicolons" {
+            // statements can end at a line break, which is handy in the REPL
+            interpreter = interpreter.with_automatic_semicolons(true);
+        } else if arg == "--pedantic" {
+            // warn about extensions to canonical Lox
+            interpreter = interpreter.with_pedantic(true);
+        } else if arg == "--minimize-crashes" {
+            // crash reports include a minimized reproducer, which takes longer to write
+            flags.minimize_crashes = true;
+        } else if arg == "--expected" {
+            // the directory of expected outputs for lox grade
+            flags.expected = Some(
+                args.next()
+                    .ok_or_else(|| anyhow!("--expected expects a directory"))?,
+            );
+        } else if arg == "--exercise" {
+            // the directory of an exercise made by lox new-exercise, whose config lox grade uses
+            flags.exercise = Some(
+                args.next()
+                    .ok_or_else(|| anyhow!("--exercise expects a directory"))?,
+            );
+        } else if arg == "--timeout" {
+            let seconds = args
+                .next()
+                .ok_or_else(|| anyhow!("--timeout expects a number of seconds"))?;
+            flags.timeout = Some(Duration::from_secs_f64(seconds.parse().map_err(|_| {
+                anyhow!("--timeout expects a number of seconds, got {}", seconds)
+            })?));
+        } else if let Some(mode) = arg.strip_prefix("--numbers=") {
+            // --numbers=binary|decimal picks whether the number operators do decimal arithmetic
+            interpreter = interpreter.with_number_mode(mode.parse()?);
+        } else if let Some(format) = arg.strip_prefix("--report=") {
+            // --report=json|csv
+            flags.report_format = format.parse()?;
+        } else if arg == "--dry-run" {
+            // natives needing a capability are stubbed out, and their calls printed once the script
+            // has run
+            flags.dry_run.get_or_insert_with(DryRun::new);
+        } else if arg == "--stub" {
+            // --stub NAME=VALUE sets the value the stub of a native returns, which implies --dry-run
+            let definition = args
+                .next()
+                .ok_or_else(|| anyhow!("--stub expects a NAME=VALUE argument"))?;
+            let (name, value) = parse_define(&definition)?;
+            if !interpreter.native_capabilities().contains_key(&name) {
+                return Err(anyhow!(
+                    "--stub expects a native needing a capability, got {}",
+                    name
+                ));
+            }
+            flags.dry_run = Some(flags.dry_run.unwrap_or_default().with_value(name, value));
+        } else if arg == "--allow" {
+            // --allow file-io|system|environment|network grants a capability to the natives
+            let capability = args
+                .next()
+                .ok_or_else(|| anyhow!("--allow expects a capability"))?;
+            interpreter = interpreter.with_capability(capability.parse()?);
+        } else if arg == "--seed" {
+            // --seed N makes the random natives give the same numbers on every run
+            let value = args
+                .next()
+                .ok_or_else(|| anyhow!("--seed expects a whole number"))?;
+            let value: u64 = value
+                .parse()
+                .map_err(|_| anyhow!("--seed expects a whole number, got {}", value))?;
+            interpreter = interpreter.with_seed(value);
+            flags.seed = Some(value);
+        } else if arg == "--max-heap" {
+            // --max-heap BYTES throws OutOfMemory once the values a script holds take more
+            let value = args
+                .next()
+                .ok_or_else(|| anyhow!("--max-heap expects a number of bytes"))?;
+            let bytes: usize = value
+                .parse()
+                .map_err(|_| anyhow!("--max-heap expects a number of bytes, got {}", value))?;
+            interpreter = interpreter.with_heap_limit(bytes);
+        } else if arg == "--stack-traces" {
+            // runtime errors raised inside of function calls are reported with the calls
+            let options = TraceOptions {
+                on_errors: true,
+                ..*interpreter.trace_options()
+            };
+            interpreter = interpreter.with_trace_options(options);
+        } else if arg == "--trace-limit" {
+            // --trace-limit N lists at most N calls of a stack trace and how many were left out
+            let value = args
+                .next()
+                .ok_or_else(|| anyhow!("--trace-limit expects a whole number"))?;
+            let max_frames = value
+                .parse()
+                .map_err(|_| anyhow!("--trace-limit expects a whole number, got {}", value))?;
+            let options = TraceOptions {
+                max_frames: Some(max_frames),
+                ..*interpreter.trace_options()
+            };
+            interpreter = interpreter.with_trace_options(options);
+        } else if arg == "--trace-natives" {
+            // the calls of natives in stack traces are marked with [native]
+            let options = TraceOptions {
+                mark_natives: true,
+                ..*interpreter.trace_options()
+            };
+            interpreter = interpreter.with_trace_options(options);
+        } else if arg == "--no-trace-collapse" {
+            // stack traces list every call of a recursion instead of collapsing the repeated ones
+            let options = TraceOptions {
+                collapse_recursion: false,
+                ..*interpreter.trace_options()
+            };
+            interpreter = interpreter.with_trace_options(options);
+        } else if arg == "--no-cache" {
+            // scripts are parsed every time instead of reusing the syntax tree cached by the last run
+            flags.parse_cache = false;
+        } else if arg == "--jobs" {
+            // --jobs N runs the scripts of lox test on N threads instead of one per core
+            let value = args
+                .next()
+                .ok_or_else(|| anyhow!("--jobs expects a whole number"))?;
+            flags.jobs = Some(
+                value
+                    .parse()
+                    .map_err(|_| anyhow!("--jobs expects a whole number, got {}", value))?,
+            );
+        } else if arg == "--report" {
+            // --report results.xml|results.json writes the results of lox test for CI systems
+            flags.test_report =
+                Some(PathBuf::from(args.next().ok_or_else(|| {
+                    anyhow!("--report expects the path of a .xml or .json file")
+                })?));
+        } else if arg == "--fail-fast" {
+            // lox test stops starting scripts once one of them has failed
+            flags.fail_fast = true;
+        } else if arg == "--stats" {
+            // print how long each stage of the pipeline took once the script has run
+            flags.stats = true;
+        } else if arg == "--no-std" {
+            // only the natives are defined, for runs which stick to the core of the language
+            stdlib = false;
+        } else {
+            flags.matches.push(arg);
+        }
+    }
+    if stdlib {
+        interpreter = interpreter.with_plugin(&StdlibPlugin)?;
+    }
+    Ok((interpreter, flags))
+}
+
+/// parses a `<line>:<column>` position as editors show them, where both start at 1
+fn parse_position(position: &str) -> Result<Position> {
+    let error = || anyhow!("expected a position like 3:12, got {}", position);
+    let (line, column) = position.split_once(':').ok_or_else(error)?;
+    let line: u32 = line.parse().map_err(|_| error())?;
+    let column: usize = column.parse().map_err(|_| error())?;
+    if line == 0 || column == 0 {
+        return Err(error());
+    }
+    Ok(Position {
+        line,
+        column: column - 1,
+    })
+}
+
 #[cfg(test)]
 mod test {}
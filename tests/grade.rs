@@ -0,0 +1,52 @@
+use lox_lib::grade::{CaseResult, Grader, Outcome};
+use std::path::PathBuf;
+
+#[test]
+/// tests that lox grade feeds the input of a case to the programs, which read it with readLine
+fn grade_input_test() {
+    let root = std::env::temp_dir().join(format!("lox-grade-input-{}", std::process::id()));
+    let submissions = root.join("submissions");
+    let expected = root.join("expected");
+    std::fs::create_dir_all(&submissions).unwrap();
+    std::fs::create_dir_all(&expected).unwrap();
+    std::fs::write(expected.join("names.in"), "ada\nlin\n").unwrap();
+    std::fs::write(expected.join("names.out"), "hello ada\nhello lin\n").unwrap();
+    std::fs::write(expected.join("none.out"), "").unwrap();
+    // greets every line of its input
+    std::fs::write(
+        submissions.join("a.lox"),
+        "var name = readLine();\nwhile (name != nil) {\n  print \"hello \" + name;\n  name = readLine();\n}\n",
+    )
+    .unwrap();
+    // only greets the first line
+    std::fs::write(
+        submissions.join("b.lox"),
+        "var name = readLine();\nif (name != nil) print \"hello \" + name;\n",
+    )
+    .unwrap();
+
+    let report = Grader::new(PathBuf::from(env!("CARGO_BIN_EXE_lox_ast")))
+        .grade(&submissions, &expected)
+        .unwrap();
+    std::fs::remove_dir_all(&root).unwrap();
+
+    let cases: Vec<_> = report
+        .submissions
+        .iter()
+        .map(|submission| (submission.name.as_str(), submission.cases.as_slice()))
+        .collect();
+    let passed = |case: &str| CaseResult {
+        case: case.to_string(),
+        outcome: Outcome::Passed,
+    };
+    assert_eq!(
+        cases[0],
+        ("a", [passed("names"), passed("none")].as_slice())
+    );
+    assert_eq!(cases[1].0, "b");
+    assert!(matches!(
+        &cases[1].1[0].outcome,
+        Outcome::Failed { diff } if diff.contains("-hello lin")
+    ));
+    assert_eq!(cases[1].1[1], passed("none"));
+}